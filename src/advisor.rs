@@ -0,0 +1,247 @@
+//! Resign / draw advisor (`--advisor`)
+//!
+//! Anti-tilt help for rated sessions: playing on in a hopeless position, or grinding a
+//! dead-level one on a losing clock, is how one bad game turns into five. Each analyzed
+//! position's eval is turned into the player's win probability (the curve lichess uses)
+//! and kept for the last few moves. When the win chance has stayed below `--advisor-lost`
+//! for `--advisor-moves` positions in a row, the advisor suggests moving on; when the eval
+//! has stayed level in a simplified position, it suggests offering a draw.
+//!
+//! Before advising, both clocks are read once (LLM OCR, screen input only) so the advice
+//! fits the time situation: a lost position against an opponent in time trouble is worth
+//! playing on quickly, and a level position is worth a draw offer when the player is far
+//! behind on the clock. Each advice is given once per streak.
+
+use crate::PlayerSide;
+use crate::clock::Clocks;
+use crate::sparring::win_percent;
+use std::collections::VecDeque;
+
+/// Win chance (percent) within this distance of 50 counts as level (about ±0.55 pawns)
+const LEVEL_BAND: f64 = 5.0;
+
+/// Non-pawn material per side (N/B 3, R 5, Q 9) at or below which a position is simplified
+const SIMPLIFIED_MATERIAL: u32 = 8;
+
+/// Clock time below which a player counts as short of time
+const LOW_TIME_SECS: u32 = 120;
+
+/// Stand-in centipawns for a forced mate (the win curve is flat beyond this)
+const MATE_CP: i32 = 1000;
+
+/// Thresholds for advice
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    /// Win chance (percent) below which the position counts as lost
+    pub lost_percent: f64,
+    /// Consecutive analyzed positions the outlook must hold for
+    pub moves: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { lost_percent: 10.0, moves: 4 }
+    }
+}
+
+/// How the last few positions have gone for the player
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outlook {
+    Lost,
+    Level,
+    /// Level with little material left on either side
+    SimplifiedLevel,
+}
+
+/// Tracks the player's win chance over the session
+#[derive(Debug)]
+pub struct Advisor {
+    config: Config,
+    /// Win chance and outlook of the most recent positions, oldest first
+    recent: VecDeque<(f64, Option<Outlook>)>,
+    /// Outlook of the current streak once it has been acted on
+    advised: Option<Outlook>,
+}
+
+impl Advisor {
+    pub fn new(config: Config) -> Self {
+        Advisor { config, recent: VecDeque::new(), advised: None }
+    }
+
+    /// Records an analyzed position. Returns the outlook once it has held for the configured
+    /// number of positions, the first time only.
+    pub fn observe(&mut self, fen: &str, eval: &str, side: PlayerSide) -> Option<Outlook> {
+        let cp = parse_eval(eval)?;
+        // Engine evals are from the side to move's perspective
+        let to_move = if fen.split_whitespace().nth(1) == Some("b") { PlayerSide::Black } else { PlayerSide::White };
+        let win = win_percent(if to_move == side { cp } else { -cp });
+
+        let outlook = if win < self.config.lost_percent {
+            Some(Outlook::Lost)
+        } else if (win - 50.0).abs() <= LEVEL_BAND {
+            Some(if is_simplified(fen) { Outlook::SimplifiedLevel } else { Outlook::Level })
+        } else {
+            None
+        };
+        self.recent.push_back((win, outlook));
+        if self.recent.len() > self.config.moves {
+            self.recent.pop_front();
+        }
+
+        let held = self.recent.len() == self.config.moves
+            && outlook.is_some()
+            && self.recent.iter().all(|&(_, o)| same_kind(o, outlook));
+        if !held {
+            self.advised = None;
+            return None;
+        }
+        if self.advised == outlook {
+            return None;
+        }
+        self.advised = outlook;
+        outlook
+    }
+
+    /// Advice for an outlook, adjusted to the clocks when they could be read. None when the
+    /// situation doesn't call for any (a level middlegame with no clock trouble).
+    pub fn advice(&self, outlook: Outlook, clocks: Option<Clocks>) -> Option<String> {
+        let short_of_time = |secs: u32, other: u32| secs <= LOW_TIME_SECS && secs * 2 <= other;
+        match outlook {
+            Outlook::Lost => {
+                let win = self.recent.iter().map(|&(w, _)| w).sum::<f64>() / self.recent.len().max(1) as f64;
+                if let Some(c) = clocks
+                    && short_of_time(c.theirs, c.mine)
+                {
+                    return Some(format!(
+                        "Position looks lost (win chance {:.0}%), but your opponent is down to {} - play fast, safe moves",
+                        win,
+                        format_clock(c.theirs)
+                    ));
+                }
+                Some(format!(
+                    "Position has looked lost for {} moves (win chance {:.0}%) - consider resigning and taking a short break",
+                    self.config.moves, win
+                ))
+            }
+            Outlook::Level | Outlook::SimplifiedLevel => match clocks {
+                Some(c) if short_of_time(c.mine, c.theirs) => Some(format!(
+                    "Level position but you're behind on the clock ({} vs {}) - consider offering a draw",
+                    format_clock(c.mine),
+                    format_clock(c.theirs)
+                )),
+                Some(c) if short_of_time(c.theirs, c.mine) => None, // their clock is the bigger problem
+                _ if outlook == Outlook::SimplifiedLevel => {
+                    Some("Level, simplified position - consider offering a draw".to_string())
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Level and simplified-level positions belong to the same streak
+fn same_kind(a: Option<Outlook>, b: Option<Outlook>) -> bool {
+    matches!(
+        (a, b),
+        (Some(Outlook::Lost), Some(Outlook::Lost))
+            | (Some(Outlook::Level | Outlook::SimplifiedLevel), Some(Outlook::Level | Outlook::SimplifiedLevel))
+    )
+}
+
+/// Centipawns for an engine eval ("+1.45", "#-3"); None for "Stalemate" and the like
+pub fn parse_eval(eval: &str) -> Option<i32> {
+    let eval = eval.trim();
+    if let Some(moves) = eval.strip_prefix('#') {
+        let moves: i32 = moves.parse().ok()?;
+        return Some(if moves > 0 { MATE_CP } else { -MATE_CP });
+    }
+    let pawns: f64 = eval.trim_start_matches('+').parse().ok()?;
+    Some((pawns * 100.0).round() as i32)
+}
+
+/// True when both sides have at most a rook and a minor piece's worth of pieces
+fn is_simplified(fen: &str) -> bool {
+    let (mut white, mut black) = (0, 0);
+    for c in fen.split_whitespace().next().unwrap_or_default().chars() {
+        let value = match c.to_ascii_lowercase() {
+            'n' | 'b' => 3,
+            'r' => 5,
+            'q' => 9,
+            _ => continue,
+        };
+        if c.is_ascii_uppercase() { white += value } else { black += value }
+    }
+    white <= SIMPLIFIED_MATERIAL && black <= SIMPLIFIED_MATERIAL
+}
+
+/// Clock time as shown on sites ("1:05")
+fn format_clock(secs: u32) -> String {
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIDDLEGAME: &str = "r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R w KQkq - 4 4";
+    const ROOK_ENDGAME: &str = "8/5pk1/6p1/8/8/6P1/r4PK1/R7 w - - 0 40";
+
+    fn advisor() -> Advisor {
+        Advisor::new(Config { lost_percent: 10.0, moves: 3 })
+    }
+
+    #[test]
+    fn test_lost_advice_after_sustained_bad_eval() {
+        let mut a = advisor();
+        assert_eq!(a.observe(MIDDLEGAME, "-7.00", PlayerSide::White), None);
+        assert_eq!(a.observe(MIDDLEGAME, "-8.50", PlayerSide::White), None);
+        assert_eq!(a.observe(MIDDLEGAME, "#-4", PlayerSide::White), Some(Outlook::Lost));
+        // Given once per streak
+        assert_eq!(a.observe(MIDDLEGAME, "-7.00", PlayerSide::White), None);
+        assert!(a.advice(Outlook::Lost, None).unwrap().contains("consider resigning"));
+    }
+
+    #[test]
+    fn test_streak_broken_by_recovery() {
+        let mut a = advisor();
+        a.observe(MIDDLEGAME, "-7.00", PlayerSide::White);
+        a.observe(MIDDLEGAME, "-1.00", PlayerSide::White);
+        assert_eq!(a.observe(MIDDLEGAME, "-7.00", PlayerSide::White), None);
+    }
+
+    #[test]
+    fn test_eval_read_from_players_side() {
+        // Black to move and winning: good for a Black player, lost for White
+        let fen = MIDDLEGAME.replace(" w ", " b ");
+        let mut a = advisor();
+        let mut b = advisor();
+        for _ in 0..3 {
+            assert_eq!(a.observe(&fen, "+7.00", PlayerSide::Black), None);
+        }
+        assert_eq!(b.observe(&fen, "+7.00", PlayerSide::White), None);
+        b.observe(&fen, "+7.00", PlayerSide::White);
+        assert_eq!(b.observe(&fen, "+7.00", PlayerSide::White), Some(Outlook::Lost));
+    }
+
+    #[test]
+    fn test_draw_advice_needs_simplification_or_clock_trouble() {
+        let mut a = advisor();
+        a.observe(ROOK_ENDGAME, "+0.10", PlayerSide::White);
+        a.observe(ROOK_ENDGAME, "-0.20", PlayerSide::White);
+        assert_eq!(a.observe(ROOK_ENDGAME, "+0.00", PlayerSide::White), Some(Outlook::SimplifiedLevel));
+        assert!(a.advice(Outlook::SimplifiedLevel, None).unwrap().contains("offering a draw"));
+
+        let behind = Clocks { mine: 40, theirs: 200 };
+        let ahead = Clocks { mine: 200, theirs: 40 };
+        assert!(a.advice(Outlook::Level, None).is_none());
+        assert!(a.advice(Outlook::Level, Some(behind)).unwrap().contains("0:40 vs 3:20"));
+        assert!(a.advice(Outlook::SimplifiedLevel, Some(ahead)).is_none());
+        assert!(a.advice(Outlook::Lost, Some(ahead)).unwrap().contains("play fast"));
+    }
+
+    #[test]
+    fn test_simplified_material() {
+        assert!(is_simplified(ROOK_ENDGAME));
+        assert!(!is_simplified(MIDDLEGAME));
+    }
+}
@@ -0,0 +1,280 @@
+//! Analysis board tracking
+//!
+//! When the user opens the site's own analysis board, consecutive captures no longer form
+//! a linear game: moves are taken back, alternatives are tried, and earlier positions are
+//! revisited. The analysis layout is recognized by its evaluation bar (a narrow vertical
+//! strip next to the board split into a white and a black part), and while it is showing,
+//! recognized positions are fed into a variation tree instead of the session's game line:
+//! - a position already in the tree moves the cursor there (stepping back/forward)
+//! - a position one or two plies from the cursor extends the tree, branching into a new
+//!   variation when the move differs from the existing continuation
+//! - a position one ply from any other node is attached there (a jump, then a move)
+//!
+//! The tree is written as PGN with variations to `analysis/<start time>.pgn` after every
+//! change. `--analysis-board on|off` forces the mode instead of detecting it.
+
+use anyhow::{Context, Result};
+use crate::pgn;
+use image::DynamicImage;
+use crate::game::GameTree;
+use shakmaty::{Chess, Position};
+
+/// Directory for analysis board PGNs
+const ANALYSIS_DIR: &str = "analysis";
+
+/// Luminance above/below which an eval bar pixel counts as its white/black part
+const BAR_LIGHT: u8 = 200;
+const BAR_DARK: u8 = 70;
+
+/// How analysis board tracking is switched on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnalysisBoardMode {
+    /// Detect the analysis layout by its eval bar
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+impl AnalysisBoardMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(AnalysisBoardMode::Auto),
+            "on" => Some(AnalysisBoardMode::On),
+            "off" => Some(AnalysisBoardMode::Off),
+            _ => None,
+        }
+    }
+}
+
+/// What a recognized position did to the tree
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Observation {
+    /// Same position as the cursor
+    Unchanged,
+    /// Moved the cursor to a position already in the tree
+    Navigated,
+    /// Added moves along the existing line
+    Extended,
+    /// Added moves as a new variation
+    Branched,
+    /// Not reachable from the tree
+    Unconnected,
+}
+
+/// Feeds a recognized position into the tree
+pub fn observe(tree: &mut GameTree, position: &Chess) -> Observation {
+    let target = position.board();
+    if tree.position().board() == target {
+        return Observation::Unchanged;
+    }
+    if let Some(found) = tree.find(target) {
+        tree.goto(found);
+        return Observation::Navigated;
+    }
+    if let Some(moves) = pgn::connect(tree.position(), target) {
+        return extend(tree, moves);
+    }
+    // Jumped back to an earlier position and played a move there
+    let origin = tree.node_ids().find_map(|id| {
+        pgn::connect(tree.position_at(id), target)
+            .filter(|moves| moves.len() == 1)
+            .map(|moves| (id, moves))
+    });
+    match origin {
+        Some((id, moves)) => {
+            tree.goto(id);
+            extend(tree, moves)
+        }
+        None => Observation::Unconnected,
+    }
+}
+
+fn extend(tree: &mut GameTree, moves: Vec<shakmaty::Move>) -> Observation {
+    let mut branched = false;
+    for m in moves {
+        branched |= tree.play(m);
+    }
+    if branched { Observation::Branched } else { Observation::Extended }
+}
+
+/// Follows the analysis board across captures and saves its tree
+pub struct AnalysisTracker {
+    mode: AnalysisBoardMode,
+    tree: Option<GameTree>,
+    path: String,
+}
+
+impl AnalysisTracker {
+    pub fn new(mode: AnalysisBoardMode) -> Self {
+        AnalysisTracker {
+            mode,
+            tree: None,
+            path: String::new(),
+        }
+    }
+
+    /// True while the analysis board is being tracked
+    pub fn active(&self) -> bool {
+        self.tree.is_some()
+    }
+
+    /// Updates tracking with the latest capture and its recognized FEN.
+    /// Returns a status line when something worth telling the user happened.
+    pub fn update(&mut self, screenshot: &DynamicImage, fen: &str) -> Result<Option<String>> {
+        let active = match self.mode {
+            AnalysisBoardMode::Off => return Ok(None),
+            AnalysisBoardMode::On => true,
+            AnalysisBoardMode::Auto => detect(screenshot),
+        };
+
+        if !active {
+            return Ok(self.tree.take().map(|tree| {
+                format!("Left analysis board ({} moves saved to {})", tree.move_count(), self.path)
+            }));
+        }
+
+        let position = pgn::parse_position(fen).context("Recognized FEN is not a legal position")?;
+        let Some(tree) = &mut self.tree else {
+            self.tree = Some(GameTree::new(position));
+            self.path = format!("{}/{}.pgn", ANALYSIS_DIR, now_secs());
+            return Ok(Some("Analysis board detected: tracking variations".to_string()));
+        };
+
+        let status = match observe(tree, &position) {
+            Observation::Unchanged | Observation::Navigated => return Ok(None),
+            Observation::Unconnected => return Ok(Some("Analysis board: position not connected to the tree".to_string())),
+            Observation::Extended => None,
+            Observation::Branched => Some(format!("Analysis board: new variation ({} moves)", tree.move_count())),
+        };
+        self.save()?;
+        Ok(status)
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(tree) = &self.tree else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(ANALYSIS_DIR).context("Failed to create analysis directory")?;
+        let headers = pgn::Headers::new("Analysis board");
+        std::fs::write(&self.path, tree.to_pgn(&headers)).with_context(|| format!("Failed to write {}", self.path))
+    }
+}
+
+/// True when the screenshot shows an analysis layout (an eval bar next to the board)
+fn detect(img: &DynamicImage) -> bool {
+    // No detectable board means no analysis layout either
+    crate::ocr_native::locate_board(img).is_ok_and(|board| has_eval_bar(img, board))
+}
+
+/// Looks for at least three eval-bar-like columns within 1/12 board width of either side
+fn has_eval_bar(img: &DynamicImage, board: (u32, u32, u32, u32)) -> bool {
+    let gray = img.to_luma8();
+    let (x, y, w, h) = board;
+    let reach = (w / 12).max(1);
+    let left = x.saturating_sub(reach)..x;
+    let right = (x + w).min(gray.width())..(x + w + reach).min(gray.width());
+
+    let bar_columns = left
+        .chain(right)
+        .filter(|&col| {
+            let column: Vec<u8> = (y..(y + h).min(gray.height())).map(|row| gray.get_pixel(col, row)[0]).collect();
+            is_eval_bar_column(&column)
+        })
+        .count();
+    bar_columns >= 3
+}
+
+/// A bar column is almost entirely light or dark, has both parts, and switches at most twice
+fn is_eval_bar_column(column: &[u8]) -> bool {
+    if column.is_empty() {
+        return false;
+    }
+    let classes: Vec<Option<bool>> = column
+        .iter()
+        .map(|&l| match l {
+            l if l >= BAR_LIGHT => Some(true),
+            l if l <= BAR_DARK => Some(false),
+            _ => None,
+        })
+        .collect();
+    let light = classes.iter().filter(|c| **c == Some(true)).count();
+    let dark = classes.iter().filter(|c| **c == Some(false)).count();
+    let switches = classes.iter().flatten().collect::<Vec<_>>().windows(2).filter(|w| w[0] != w[1]).count();
+
+    let n = column.len();
+    (light + dark) * 100 >= n * 95 && light * 100 >= n * 3 && dark * 100 >= n * 3 && switches <= 2
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    fn after(moves: &[&str]) -> Chess {
+        let mut position = pgn::parse_position(START).unwrap();
+        for text in moves {
+            let san: shakmaty::san::San = text.parse().unwrap();
+            let m = san.to_move(&position).unwrap();
+            position.play_unchecked(m);
+        }
+        position
+    }
+
+    #[test]
+    fn test_tree_tracks_variations_and_navigation() {
+        let mut tree = GameTree::new(after(&[]));
+        assert_eq!(observe(&mut tree, &after(&["e4"])), Observation::Extended);
+        assert_eq!(observe(&mut tree, &after(&["e4", "e5"])), Observation::Extended);
+        assert_eq!(observe(&mut tree, &after(&["e4", "e5", "Nf3"])), Observation::Extended);
+        // Step back and try another reply
+        assert_eq!(observe(&mut tree, &after(&["e4"])), Observation::Navigated);
+        assert_eq!(observe(&mut tree, &after(&["e4", "c5"])), Observation::Branched);
+        // Jump to the start and play a different first move
+        assert_eq!(observe(&mut tree, &after(&["d4"])), Observation::Branched);
+        assert_eq!(tree.move_count(), 5);
+        assert_eq!(tree.movetext(), "1. e4 (1. d4) 1... e5 (1... c5) 2. Nf3");
+    }
+
+    #[test]
+    fn test_unconnected_position() {
+        let mut tree = GameTree::new(after(&[]));
+        assert_eq!(observe(&mut tree, &after(&["e4", "e5", "Nf3", "Nc6"])), Observation::Unconnected);
+        assert_eq!(tree.move_count(), 0);
+    }
+
+    fn screenshot(with_bar: bool) -> RgbImage {
+        let mut img = RgbImage::from_pixel(400, 300, Rgb([128, 128, 128]));
+        // Checkered "board" at (100, 20), 240px
+        for yy in 0..240 {
+            for xx in 0..240 {
+                let light = (xx / 30 + yy / 30) % 2 == 0;
+                img.put_pixel(100 + xx, 20 + yy, if light { Rgb([240, 217, 181]) } else { Rgb([181, 136, 99]) });
+            }
+        }
+        if with_bar {
+            for yy in 0..240 {
+                for xx in 90..98 {
+                    img.put_pixel(xx, 20 + yy, if yy < 100 { Rgb([40, 40, 40]) } else { Rgb([250, 250, 250]) });
+                }
+            }
+        }
+        img
+    }
+
+    #[test]
+    fn test_eval_bar_detection() {
+        let board = (100, 20, 240, 240);
+        assert!(has_eval_bar(&DynamicImage::ImageRgb8(screenshot(true)), board));
+        assert!(!has_eval_bar(&DynamicImage::ImageRgb8(screenshot(false)), board));
+    }
+}
@@ -0,0 +1,112 @@
+//! OCR benchmark over recorded hard cases
+//!
+//! `zugzwang-rs bench-ocr` re-runs OCR on every image in the hard case set
+//! (`hard_cases/cases.jsonl`) and reports how often the piece placement now matches the
+//! corrected FEN. Requests are dispatched concurrently via `ocr::board_to_fen_batch`,
+//! bounded by `--parallel` and `--rate` so large sets finish quickly without tripping
+//! provider rate limits.
+
+use anyhow::Result;
+use crate::PlayerSide;
+use crate::hard_cases::HardCase;
+use crate::ocr::{self, BatchLimits, OcrMode};
+use std::time::Instant;
+
+/// Outcome of one benchmarked case
+#[derive(Debug, PartialEq, Eq)]
+enum CaseOutcome {
+    Correct,
+    Wrong { got: String },
+    Failed { error: String },
+}
+
+/// Runs OCR over all hard cases in `dir` and prints per-case results and overall accuracy
+pub async fn run(dir: &str, site: &str, mode: OcrMode, player_side: PlayerSide, limits: BatchLimits) -> Result<()> {
+    let cases = crate::hard_cases::load(dir)?;
+    if cases.is_empty() {
+        println!("No hard cases recorded in {}/", dir);
+        return Ok(());
+    }
+
+    println!(
+        "Benchmarking {} OCR on {} cases (parallel: {}, min interval: {}ms)",
+        mode,
+        cases.len(),
+        limits.parallelism,
+        limits.min_interval.as_millis()
+    );
+
+    let paths: Vec<String> = cases.iter().map(|c| format!("{}/{}", dir, c.image)).collect();
+    let start = Instant::now();
+    let results = ocr::board_to_fen_batch(paths, site, mode, player_side, limits).await;
+    let elapsed = start.elapsed();
+
+    let mut correct = 0;
+    for (case, result) in cases.iter().zip(results) {
+        match score_case(case, result) {
+            CaseOutcome::Correct => {
+                correct += 1;
+                println!("  ✓ {}", case.image);
+            }
+            CaseOutcome::Wrong { got } => {
+                println!("  ✗ {}", case.image);
+                println!("      expected: {}", placement(&case.corrected_fen));
+                println!("      got:      {}", placement(&got));
+            }
+            CaseOutcome::Failed { error } => println!("  ⚠ {}: {}", case.image, error),
+        }
+    }
+
+    println!();
+    println!(
+        "Accuracy: {}/{} ({:.1}%) in {:.1}s",
+        correct,
+        cases.len(),
+        100.0 * correct as f64 / cases.len() as f64,
+        elapsed.as_secs_f64()
+    );
+    Ok(())
+}
+
+/// Compares an OCR result against the case's corrected FEN (piece placement only)
+fn score_case(case: &HardCase, result: Result<String>) -> CaseOutcome {
+    match result {
+        Ok(fen) if placement(&fen) == placement(&case.corrected_fen) => CaseOutcome::Correct,
+        Ok(fen) => CaseOutcome::Wrong { got: fen },
+        Err(e) => CaseOutcome::Failed { error: format!("{:#}", e) },
+    }
+}
+
+/// Piece placement field of a FEN
+fn placement(fen: &str) -> &str {
+    fen.split_whitespace().next().unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(corrected: &str) -> HardCase {
+        HardCase {
+            timestamp_ms: 0,
+            source: "test".to_string(),
+            image: "0.jpg".to_string(),
+            wrong_fen: String::new(),
+            corrected_fen: corrected.to_string(),
+            cycle: None,
+        }
+    }
+
+    #[test]
+    fn test_score_ignores_side_to_move() {
+        let c = case("8/8/8/8/8/8/8/K6k w - - 0 1");
+        assert_eq!(score_case(&c, Ok("8/8/8/8/8/8/8/K6k b - - 0 1".to_string())), CaseOutcome::Correct);
+    }
+
+    #[test]
+    fn test_score_wrong_and_failed() {
+        let c = case("8/8/8/8/8/8/8/K6k w - - 0 1");
+        assert!(matches!(score_case(&c, Ok("8/8/8/8/8/8/8/K5k1 w - - 0 1".to_string())), CaseOutcome::Wrong { .. }));
+        assert!(matches!(score_case(&c, Err(anyhow::anyhow!("boom"))), CaseOutcome::Failed { .. }));
+    }
+}
@@ -0,0 +1,637 @@
+//! Board detection (`ocr_native::locate_board`)
+//!
+//! The board is first looked for by edge density: a board with pieces is the densest large
+//! square of edges on screen. That misses low-contrast themes, boards smaller than the 300px
+//! the search starts at, and boards partly covered by a popup or animation, so further
+//! detectors are tried in turn before giving up:
+//!
+//! 1. `edges`: edge density × size over a grid of candidate squares
+//! 2. `color grid`: the 8×8 checker of two alternating square colors, searched on a
+//!    downscaled frame and then aligned on the full one by the contrast along square borders
+//! 3. `coordinate labels`: the rank and file labels in the board's top-left and bottom-right
+//!    corners, matched against the patches `--calibrate` saves to `templates/{site}/labels/`
+//!    (found only at the board size and side used for calibration)
+//! 4. `cached region`: the last board found in a frame of the same size, while it still
+//!    shows the checker pattern
+//! 5. `configured region`: `--fallback-region X,Y,W,H` (`fallback-region` in the config file)
+//!
+//! With `--verbose` every cycle reports which detector found the board.
+
+use anyhow::Result;
+use crate::PlayerSide;
+use image::{DynamicImage, GenericImageView, GrayImage, Rgba, imageops};
+use imageproc::edges::canny;
+use imageproc::template_matching::{MatchTemplateMethod, find_extremes, match_template};
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+/// Board rectangle in frame pixels: (x, y, width, height)
+pub type Region = (u32, u32, u32, u32);
+
+/// One configured board: its screen rectangle and the user's side on it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoardRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Side played on this board (None: the global side)
+    pub side: Option<PlayerSide>,
+}
+
+impl BoardRegion {
+    /// Parses `X,Y,W,H` with an optional `:white` / `:black` suffix
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (rect, side) = match text.split_once(':') {
+            Some((rect, "white")) => (rect, Some(PlayerSide::White)),
+            Some((rect, "black")) => (rect, Some(PlayerSide::Black)),
+            Some((_, other)) => return Err(format!("unknown side '{}' (expected white or black)", other)),
+            None => (text, None),
+        };
+        let numbers: Vec<u32> = rect
+            .split(',')
+            .map(|n| n.trim().parse::<u32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("'{}' is not X,Y,WIDTH,HEIGHT", rect))?;
+        let [x, y, width, height] = numbers[..] else {
+            return Err(format!("'{}' is not X,Y,WIDTH,HEIGHT", rect));
+        };
+        if width == 0 || height == 0 {
+            return Err("board region must not be empty".to_string());
+        }
+        Ok(BoardRegion { x, y, width, height, side })
+    }
+}
+
+/// Detector that found the board
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Detector {
+    Edges,
+    ColorGrid,
+    CoordinateLabels,
+    CachedRegion,
+    ConfiguredRegion,
+}
+
+impl fmt::Display for Detector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Detector::Edges => "edges",
+            Detector::ColorGrid => "color grid",
+            Detector::CoordinateLabels => "coordinate labels",
+            Detector::CachedRegion => "cached region",
+            Detector::ConfiguredRegion => "configured region",
+        })
+    }
+}
+
+/// Corner patches with the coordinate labels, cut by `--calibrate`
+pub struct Labels {
+    pub top_left: GrayImage,
+    pub bottom_right: GrayImage,
+}
+
+/// Site whose coordinate label patches are used
+static SITE: OnceLock<String> = OnceLock::new();
+
+/// `--fallback-region`
+static CONFIGURED: OnceLock<Region> = OnceLock::new();
+
+/// The site's label patches, loaded on first use (None when not calibrated)
+static LABELS: OnceLock<Option<Labels>> = OnceLock::new();
+
+/// Last board found, with the size of the frame it was found in
+static CACHED: Mutex<Option<((u32, u32), Region)>> = Mutex::new(None);
+
+/// Detector behind the latest board found, until reported
+static LAST_DETECTOR: Mutex<Option<Detector>> = Mutex::new(None);
+
+/// Width the color grid search downscales frames to
+const WORK_WIDTH: u32 = 320;
+
+/// Smallest board the color grid search looks for, in frame pixels
+const MIN_BOARD: u32 = 128;
+
+/// Share of square corners that must follow the checker pattern
+const GRID_SCORE: f32 = 0.8;
+
+/// Lower share accepted for the cached region, where the board is already known to have been
+const CACHED_GRID_SCORE: f32 = 0.6;
+
+/// Smallest difference between the two square colors (sum over RGB)
+const MIN_SQUARE_CONTRAST: u32 = 40;
+
+/// Largest difference of a square corner from its square color (sum over RGB)
+const SQUARE_TOLERANCE: u32 = 48;
+
+/// Size the label patches are matched at on the downscaled frame
+const LABEL_MATCH_SIZE: f32 = 12.0;
+
+/// Normalized squared-error score a label patch must beat
+const LABEL_SCORE: f32 = 0.1;
+
+/// Sets the site whose label patches are matched (first call wins)
+pub fn set_site(site: &str) {
+    let _ = SITE.set(site.to_string());
+}
+
+/// Uses `region` when every detector fails (first call wins)
+pub fn set_fallback_region(region: Region) {
+    let _ = CONFIGURED.set(region);
+}
+
+/// Parses `--fallback-region X,Y,W,H`
+pub fn parse_region(text: &str) -> Result<Region, String> {
+    let region = BoardRegion::parse(text)?;
+    if region.side.is_some() {
+        return Err("a fallback region has no side (drop the :white / :black suffix)".to_string());
+    }
+    Ok((region.x, region.y, region.width, region.height))
+}
+
+/// Detector that found the latest board, once (for the verbose cycle report)
+pub fn take_last_detector() -> Option<Detector> {
+    LAST_DETECTOR.lock().ok()?.take()
+}
+
+/// Directory of the coordinate label patches for `site`
+pub fn label_dir(site: &str) -> String {
+    format!("templates/{}/labels", site)
+}
+
+/// Cuts the label patches (half a square from the top-left and bottom-right corners) of a board
+pub fn cut_labels(img: &DynamicImage, (x, y, width, height): Region) -> Labels {
+    let patch = (width.min(height) / 16).max(4);
+    Labels {
+        top_left: img.crop_imm(x, y, patch, patch).to_luma8(),
+        bottom_right: img.crop_imm(x + width - patch, y + height - patch, patch, patch).to_luma8(),
+    }
+}
+
+/// Finds the board with the first detector that succeeds
+pub fn locate(img: &DynamicImage) -> Result<(Region, Detector)> {
+    let frame = img.dimensions();
+    let mut failures = Vec::new();
+    let mut found = match edges(img) {
+        Ok(region) => Some((region, Detector::Edges)),
+        Err(e) => {
+            failures.push(format!("{:#}", e));
+            None
+        }
+    };
+    if found.is_none() {
+        found = color_grid(img).map(|region| (region, Detector::ColorGrid));
+        if found.is_none() {
+            failures.push("no 8×8 grid of two square colors".to_string());
+        }
+    }
+    if found.is_none() {
+        let labels = LABELS.get_or_init(|| SITE.get().and_then(|site| load_labels(&label_dir(site))));
+        match labels {
+            Some(labels) => {
+                found = find_labels(img, labels).map(|region| (region, Detector::CoordinateLabels));
+                if found.is_none() {
+                    failures.push("coordinate labels not found".to_string());
+                }
+            }
+            None => failures.push("no coordinate labels calibrated".to_string()),
+        }
+    }
+    if found.is_none() {
+        let cached = CACHED.lock().ok().and_then(|cached| *cached);
+        found = cached
+            .filter(|&(size, region)| size == frame && cached_region_valid(img, region))
+            .map(|(_, region)| (region, Detector::CachedRegion));
+        if found.is_none() {
+            failures.push("no cached region".to_string());
+        }
+    }
+    if found.is_none() {
+        match CONFIGURED.get() {
+            Some(&region) if fits(frame, region) => found = Some((region, Detector::ConfiguredRegion)),
+            Some(_) => failures.push(format!("--fallback-region lies outside the {}×{} frame", frame.0, frame.1)),
+            None => failures.push("no --fallback-region".to_string()),
+        }
+    }
+
+    let Some((region, detector)) = found else {
+        anyhow::bail!("{}", failures.join("; "));
+    };
+    if let Ok(mut cached) = CACHED.lock() {
+        *cached = Some((frame, region));
+    }
+    if let Ok(mut last) = LAST_DETECTOR.lock() {
+        *last = Some(detector);
+    }
+    Ok((region, detector))
+}
+
+fn fits((width, height): (u32, u32), (x, y, w, h): Region) -> bool {
+    w > 0 && h > 0 && x + w <= width && y + h <= height
+}
+
+/// Edge detection: the candidate square with the best edge density × size
+fn edges(img: &DynamicImage) -> Result<Region> {
+    // Step 1: Edge detection (full screenshot)
+    let gray: GrayImage = img.to_luma8();
+    let edges: GrayImage = canny(&gray, 50.0, 150.0);
+
+    // Step 2: Generate candidate regions
+    let (width, height) = img.dimensions();
+    let candidates = generate_candidate_regions(width, height);
+
+    // Step 3: Score each candidate by edge density × size factor
+    // We multiply by size to prefer larger boards (avoids selecting partial boards)
+    let mut best_candidate: Option<Region> = None;
+    let mut best_score = 0.0f32;
+    let mut best_density = 0.0f32;
+
+    for (x, y, size) in candidates {
+        let density = calculate_edge_density(&edges, x, y, size);
+        // Score = density × size, so larger boards with decent density win
+        let score = density * size as f32;
+
+        if score > best_score {
+            best_score = score;
+            best_density = density;
+            best_candidate = Some((x, y, size, size));
+        }
+    }
+
+    // Step 4: Validate best candidate
+    const MIN_EDGE_DENSITY: f32 = 0.01; // 1% of pixels should be edges
+
+    if best_density < MIN_EDGE_DENSITY {
+        anyhow::bail!(
+            "No board detected: best edge density {:.3}% < {:.1}% threshold",
+            best_density * 100.0,
+            MIN_EDGE_DENSITY * 100.0
+        );
+    }
+
+    best_candidate.ok_or_else(|| anyhow::anyhow!("No candidate regions found"))
+}
+
+// Helper: generate search regions
+// Creates a grid of candidate regions to search across the screenshot.
+// Returns Vec of (x, y, size) tuples representing potential board locations.
+fn generate_candidate_regions(width: u32, height: u32) -> Vec<(u32, u32, u32)> {
+    let mut candidates = Vec::new();
+
+    // Calculate reasonable board sizes to search for
+    // Minimum 300px to avoid selecting partial boards on downsampled images
+    let min_size = 300u32;
+    let max_size = if width < height { width } else { height };
+    let size_step = 50u32; // Finer granularity for better detection
+
+    // Grid search: Try different positions and sizes
+    for size in (min_size..=max_size).step_by(size_step as usize) {
+        let step = size / 4; // Overlap regions by 75% for better coverage
+
+        let mut y = 0;
+        while y + size <= height {
+            let mut x = 0;
+            while x + size <= width {
+                candidates.push((x, y, size));
+                x += step;
+            }
+            y += step;
+        }
+    }
+
+    candidates
+}
+
+// Helper: calculate edge density in region
+// Counts what percentage of pixels in a region are edges (bright pixels in edge map).
+// Chessboards should have high edge density due to grid lines and piece shapes.
+fn calculate_edge_density(edges: &GrayImage, x: u32, y: u32, size: u32) -> f32 {
+    let mut edge_count = 0usize;
+    let edge_threshold = 128u8; // Pixel brightness > 128 = edge detected
+
+    for dy in 0..size {
+        for dx in 0..size {
+            if let Some(pixel) = edges.get_pixel_checked(x + dx, y + dy)
+                && pixel[0] > edge_threshold
+            {
+                edge_count += 1;
+            }
+        }
+    }
+
+    edge_count as f32 / (size * size) as f32
+}
+
+/// Color grid: the best-scoring checker on a downscaled frame, aligned on the full frame
+fn color_grid(img: &DynamicImage) -> Option<Region> {
+    let (width, height) = img.dimensions();
+    let scale = (WORK_WIDTH as f32 / width as f32).min(1.0);
+    let small = imageops::thumbnail(img, ((width as f32 * scale) as u32).max(1), ((height as f32 * scale) as u32).max(1));
+    let (small_w, small_h) = small.dimensions();
+
+    // Steps of a sixth of a square keep some candidate within the corner inset of the grid
+    let mut best: Option<(f32, u32, u32, u32)> = None;
+    let mut size = ((MIN_BOARD as f32 * scale) as u32).max(32);
+    while size <= small_w.min(small_h) {
+        let step = (size / 48).max(1) as usize;
+        for y in (0..=small_h - size).step_by(step) {
+            for x in (0..=small_w - size).step_by(step) {
+                let Some(colors) = quick_checker(&small, x as f32, y as f32, size as f32) else {
+                    continue;
+                };
+                if !checker_likely(&small, x as f32, y as f32, size as f32, colors) {
+                    continue;
+                }
+                let score = grid_score(&small, x as f32, y as f32, size as f32);
+                if score >= GRID_SCORE && best.is_none_or(|(best_score, ..)| score > best_score) {
+                    best = Some((score, x, y, size));
+                }
+            }
+        }
+        size += (size / 32).max(1);
+    }
+
+    let (_, x, y, size) = best?;
+    let region = (
+        (x as f32 / scale) as u32,
+        (y as f32 / scale) as u32,
+        (size as f32 / scale) as u32,
+        (size as f32 / scale) as u32,
+    );
+    Some(align_grid(&img.to_luma8(), region))
+}
+
+/// Cheap pre-check: the four middle squares alternate between two distinct colors, returned
+/// as (even, odd) square colors
+fn quick_checker<I: GenericImageView<Pixel = Rgba<u8>>>(img: &I, x: f32, y: f32, size: f32) -> Option<[[u8; 3]; 2]> {
+    let corner = |row: u32, col: u32| sample(img, x, y, size, row, col, (0.15, 0.15));
+    let (a, b, c, d) = (corner(3, 3)?, corner(3, 4)?, corner(4, 3)?, corner(4, 4)?);
+    let checker = color_distance(a, d) <= SQUARE_TOLERANCE
+        && color_distance(b, c) <= SQUARE_TOLERANCE
+        && color_distance(a, b) >= MIN_SQUARE_CONTRAST;
+    checker.then_some([a, b])
+}
+
+/// Second pre-check: one corner of every square is close to the middle squares' color of
+/// its parity, stopping as soon as too many aren't
+fn checker_likely<I: GenericImageView<Pixel = Rgba<u8>>>(img: &I, x: f32, y: f32, size: f32, colors: [[u8; 3]; 2]) -> bool {
+    let allowed = ((1.0 - GRID_SCORE) * 64.0) as u32;
+    let mut misses = 0;
+    for row in 0..8 {
+        for col in 0..8 {
+            let own = colors[((row + col) % 2) as usize];
+            let near = sample(img, x, y, size, row, col, (0.85, 0.85)).is_some_and(|c| color_distance(c, own) <= SQUARE_TOLERANCE);
+            if !near {
+                misses += 1;
+                if misses > allowed {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Share of the 256 square corners (inset 15%) that match their square color and not the other
+fn grid_score<I: GenericImageView<Pixel = Rgba<u8>>>(img: &I, x: f32, y: f32, size: f32) -> f32 {
+    const INSETS: [(f32, f32); 4] = [(0.15, 0.15), (0.85, 0.15), (0.15, 0.85), (0.85, 0.85)];
+    let mut samples: [Vec<[u8; 3]>; 2] = [Vec::with_capacity(128), Vec::with_capacity(128)];
+    for row in 0..8 {
+        for col in 0..8 {
+            for inset in INSETS {
+                let Some(color) = sample(img, x, y, size, row, col, inset) else {
+                    return 0.0;
+                };
+                samples[((row + col) % 2) as usize].push(color);
+            }
+        }
+    }
+
+    // Medians, so pieces and labels reaching into corners don't shift the square colors
+    let colors = [median(&samples[0]), median(&samples[1])];
+    if color_distance(colors[0], colors[1]) < MIN_SQUARE_CONTRAST {
+        return 0.0;
+    }
+    let matching: usize = (0..2)
+        .map(|parity| {
+            let (own, other) = (colors[parity], colors[1 - parity]);
+            samples[parity]
+                .iter()
+                .filter(|&&c| color_distance(c, own) <= SQUARE_TOLERANCE && color_distance(c, own) < color_distance(c, other))
+                .count()
+        })
+        .sum();
+    matching as f32 / 256.0
+}
+
+/// Color at a relative point `inset` of square (row, col) of the board at (x, y, size)
+fn sample<I: GenericImageView<Pixel = Rgba<u8>>>(
+    img: &I,
+    x: f32,
+    y: f32,
+    size: f32,
+    row: u32,
+    col: u32,
+    (dx, dy): (f32, f32),
+) -> Option<[u8; 3]> {
+    let square = size / 8.0;
+    let px = (x + (col as f32 + dx) * square) as u32;
+    let py = (y + (row as f32 + dy) * square) as u32;
+    if px >= img.width() || py >= img.height() {
+        return None;
+    }
+    let Rgba([r, g, b, _]) = img.get_pixel(px, py);
+    Some([r, g, b])
+}
+
+fn median(colors: &[[u8; 3]]) -> [u8; 3] {
+    let channel = |i: usize| {
+        let mut values: Vec<u8> = colors.iter().map(|c| c[i]).collect();
+        values.sort_unstable();
+        values.get(values.len() / 2).copied().unwrap_or(0)
+    };
+    [channel(0), channel(1), channel(2)]
+}
+
+fn color_distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter().zip(b).map(|(&a, b)| a.abs_diff(b) as u32).sum()
+}
+
+/// Moves and resizes a roughly placed board so its square borders fall on the grid lines
+fn align_grid(gray: &GrayImage, (x, y, size, _): Region) -> Region {
+    let reach = (size / 40).max(3) as i64;
+    let (x, y, size) = (x as i64, y as i64, size as i64);
+    let mut best = (0u64, x, y, size);
+    for s in (size - reach).max(64)..=size + reach {
+        let (bx, x_contrast) = (x - reach..=x + reach)
+            .map(|cx| (cx, border_contrast(gray, cx, y, s, true)))
+            .max_by_key(|&(_, contrast)| contrast)
+            .unwrap_or((x, 0));
+        let (by, y_contrast) = (y - reach..=y + reach)
+            .map(|cy| (cy, border_contrast(gray, x, cy, s, false)))
+            .max_by_key(|&(_, contrast)| contrast)
+            .unwrap_or((y, 0));
+        if x_contrast + y_contrast > best.0 {
+            best = (x_contrast + y_contrast, bx, by, s);
+        }
+    }
+    let (_, x, y, size) = best;
+    (x.max(0) as u32, y.max(0) as u32, size as u32, size as u32)
+}
+
+/// Brightness steps across the seven inner borders between files (`vertical`) or ranks
+fn border_contrast(gray: &GrayImage, x: i64, y: i64, size: i64, vertical: bool) -> u64 {
+    let luma = |px: i64, py: i64| {
+        (px >= 0 && py >= 0).then(|| gray.get_pixel_checked(px as u32, py as u32)).flatten().map(|p| p[0] as i64)
+    };
+    let mut total = 0;
+    for line in 1..8 {
+        let across = line * size / 8;
+        for step in 0..16 {
+            let along = (2 * step + 1) * size / 32;
+            let (before, after) = if vertical {
+                (luma(x + across - 2, y + along), luma(x + across + 1, y + along))
+            } else {
+                (luma(x + along, y + across - 2), luma(x + along, y + across + 1))
+            };
+            if let (Some(before), Some(after)) = (before, after) {
+                total += before.abs_diff(after);
+            }
+        }
+    }
+    total
+}
+
+/// Loads the label patches from `dir` (None when they aren't there)
+fn load_labels(dir: &str) -> Option<Labels> {
+    let load = |name: &str| image::open(format!("{}/{}.png", dir, name)).ok().map(|img| img.to_luma8());
+    Some(Labels { top_left: load("top-left")?, bottom_right: load("bottom-right")? })
+}
+
+/// Coordinate labels: the board between the matched top-left and bottom-right label patches
+fn find_labels(img: &DynamicImage, labels: &Labels) -> Option<Region> {
+    let gray = img.to_luma8();
+    let (left, top) = find_patch(&gray, &labels.top_left)?;
+    let (right, bottom) = find_patch(&gray, &labels.bottom_right)?;
+    let width = (right + labels.bottom_right.width()).checked_sub(left)?;
+    let height = (bottom + labels.bottom_right.height()).checked_sub(top)?;
+    let square = width >= 64 && (width as f32 / height as f32 - 1.0).abs() <= 0.1;
+    square.then_some((left, top, width, height))
+}
+
+/// Top-left corner of the best match of `patch` in `gray`, matched coarsely on downscaled
+/// images and then exactly around the coarse match
+fn find_patch(gray: &GrayImage, patch: &GrayImage) -> Option<(u32, u32)> {
+    let (width, height) = gray.dimensions();
+    let (patch_w, patch_h) = patch.dimensions();
+    if patch_w == 0 || patch_h == 0 || patch_w > width || patch_h > height {
+        return None;
+    }
+    let scale = (LABEL_MATCH_SIZE / patch_w.max(patch_h) as f32).min(1.0);
+    let resize = |img: &GrayImage| {
+        let (w, h) = img.dimensions();
+        imageops::resize(img, ((w as f32 * scale) as u32).max(1), ((h as f32 * scale) as u32).max(1), imageops::FilterType::Triangle)
+    };
+    let coarse = find_extremes(&match_template(&resize(gray), &resize(patch), MatchTemplateMethod::SumOfSquaredErrorsNormalized));
+    let (coarse_x, coarse_y) = coarse.min_value_location;
+
+    let reach = (2.0 / scale).ceil() as u32 + 1;
+    let left = ((coarse_x as f32 / scale) as u32).saturating_sub(reach).min(width - patch_w);
+    let top = ((coarse_y as f32 / scale) as u32).saturating_sub(reach).min(height - patch_h);
+    let window_w = (patch_w + 2 * reach).min(width - left);
+    let window_h = (patch_h + 2 * reach).min(height - top);
+    let window = imageops::crop_imm(gray, left, top, window_w, window_h).to_image();
+    let fine = find_extremes(&match_template(&window, patch, MatchTemplateMethod::SumOfSquaredErrorsNormalized));
+    (fine.min_value <= LABEL_SCORE).then_some((left + fine.min_value_location.0, top + fine.min_value_location.1))
+}
+
+/// The cached region is kept while it fits the frame and still shows the checker
+fn cached_region_valid(img: &DynamicImage, region: Region) -> bool {
+    let (x, y, size, _) = region;
+    fits(img.dimensions(), region) && grid_score(img, x as f32, y as f32, size as f32) >= CACHED_GRID_SCORE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    const LIGHT: Rgba<u8> = Rgba([238, 238, 210, 255]);
+    const DARK: Rgba<u8> = Rgba([118, 150, 86, 255]);
+
+    /// A flat-colored board with a few pieces and corner labels on a dark background
+    fn frame(width: u32, height: u32, (bx, by, size): (u32, u32, u32)) -> DynamicImage {
+        let square = size / 8;
+        let img = RgbaImage::from_fn(width, height, |x, y| {
+            if x < bx || y < by || x >= bx + size || y >= by + size {
+                return Rgba([40, 40, 40, 255]);
+            }
+            let (col, row) = ((x - bx) / square, (y - by) / square);
+            let (dx, dy) = ((x - bx) % square, (y - by) % square);
+            let piece = row % 3 == 0 && (square / 4..square * 3 / 4).contains(&dx) && (square / 5..square * 4 / 5).contains(&dy);
+            let label = (col == 0 && row == 0 && dx < square / 5 && (2..square / 4).contains(&dy))
+                || (col == 7 && row == 7 && dx > square * 4 / 5 && dy > square * 3 / 4);
+            match (piece, label, (row + col) % 2) {
+                (true, _, _) => Rgba([20, 20, 20, 255]),
+                (_, true, 0) => DARK,
+                (_, true, _) => LIGHT,
+                (_, _, 0) => LIGHT,
+                _ => DARK,
+            }
+        });
+        DynamicImage::ImageRgba8(img)
+    }
+
+    fn near(found: Region, expected: Region, tolerance: u32) -> bool {
+        found.0.abs_diff(expected.0) <= tolerance
+            && found.1.abs_diff(expected.1) <= tolerance
+            && found.2.abs_diff(expected.2) <= tolerance
+            && found.3.abs_diff(expected.3) <= tolerance
+    }
+
+    #[test]
+    fn test_color_grid_finds_and_aligns_the_board() {
+        let img = frame(1000, 700, (173, 91, 336));
+        let found = color_grid(&img).expect("board found");
+        assert!(near(found, (173, 91, 336, 336), 3), "{:?}", found);
+
+        let blank = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1000, 700, Rgba([40, 40, 40, 255])));
+        assert_eq!(color_grid(&blank), None);
+    }
+
+    #[test]
+    fn test_labels_found_where_the_board_moved() {
+        let calibrated = frame(900, 600, (100, 50, 400));
+        let labels = cut_labels(&calibrated, (100, 50, 400, 400));
+        let moved = frame(900, 600, (321, 143, 400));
+        let found = find_labels(&moved, &labels).expect("labels found");
+        assert_eq!(found, (321, 143, 400, 400));
+    }
+
+    #[test]
+    fn test_cached_region_needs_the_checker() {
+        let img = frame(800, 600, (200, 100, 320));
+        assert!(cached_region_valid(&img, (200, 100, 320, 320)));
+        assert!(!cached_region_valid(&img, (0, 0, 320, 320)));
+        assert!(!cached_region_valid(&img, (600, 400, 320, 320)));
+    }
+
+    #[test]
+    fn test_parse_region() {
+        assert_eq!(parse_region("10,20,400,400"), Ok((10, 20, 400, 400)));
+        assert!(parse_region("10,20,400,400:black").is_err());
+        assert!(parse_region("10,20,400").is_err());
+    }
+
+    #[test]
+    fn test_parse_board_region() {
+        assert_eq!(
+            BoardRegion::parse("0,100,800,800:black"),
+            Ok(BoardRegion { x: 0, y: 100, width: 800, height: 800, side: Some(PlayerSide::Black) })
+        );
+        assert_eq!(BoardRegion::parse("960, 100, 800, 800").unwrap().side, None);
+        assert!(BoardRegion::parse("1,2,3").is_err());
+        assert!(BoardRegion::parse("1,2,3,4:red").is_err());
+        assert!(BoardRegion::parse("1,2,0,4").is_err());
+    }
+}
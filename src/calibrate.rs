@@ -0,0 +1,308 @@
+//! Interactive calibration (`--calibrate --site <name>`)
+//!
+//! Native OCR compares every square with piece images cut from the site's own board, so a
+//! custom piece set or board theme needs its own templates. Calibration takes them from one
+//! screenshot of the starting position, seen from `--side`:
+//!
+//! 1. The board comes from `--board-region`, or is detected and confirmed against a saved
+//!    preview, or is given by typing its top-left and bottom-right corners as read from the
+//!    saved screenshot.
+//! 2. Each piece is cut from its first home square from the a-file into `templates/{site}/`
+//!    (or the `--theme` pack, `templates/{site}/{theme}/`)
+//!    (`RW.png` from a1, ...). Templates include the square's background, so pieces that also
+//!    stand on the other square color get a second `-alt` template from there (h1, ...).
+//! 3. The empty-square variance and match-score thresholds are placed between what the
+//!    empty middle of the board and the 32 pieces measure, checked by reading the position
+//!    back, and saved under `[calibration.{site}]` (or `"{site}/{theme}"`) in the config file.
+//! 4. The board's top-left and bottom-right corners, with their coordinate labels, are saved
+//!    to `templates/{site}/labels/` for `board_detect` to find the board by when other
+//!    detectors fail.
+
+use anyhow::{Context, Result};
+use crate::PlayerSide;
+use crate::board_detect;
+use crate::capture;
+use crate::config;
+use crate::board_detect::BoardRegion;
+use crate::ocr_native::{self, PIECE_FILES, Thresholds};
+use crate::prompt;
+use image::{DynamicImage, GenericImageView, GrayImage, RgbaImage, imageops};
+use std::collections::HashMap;
+
+/// Starting position as displayed with White at the bottom, rank 8 first ('.' = empty)
+const START_RANKS: [&str; 8] = [
+    "rnbqkbnr", "pppppppp", "........", "........", "........", "........", "PPPPPPPP", "RNBQKBNR",
+];
+
+/// Headroom of the match threshold over the worst score a correctly placed piece produced
+const MATCH_MARGIN: f32 = 1.5;
+
+/// Lowest match threshold written (a pixel-perfect calibration would otherwise give 0)
+const MIN_MATCH_SCORE: f32 = 0.05;
+
+/// Where the board preview and full screenshot are saved for checking coordinates
+const PREVIEW_DIR: &str = "screenshots";
+
+/// Templates and thresholds measured on a starting-position board
+#[derive(Debug)]
+struct Calibration {
+    /// File name (without `.png`) and template image
+    templates: Vec<(String, GrayImage)>,
+    thresholds: Thresholds,
+    /// Squares the new templates read wrongly ("f1: N instead of B")
+    misread: Vec<String>,
+}
+
+/// Runs the calibration for `site` and saves templates and thresholds
+pub fn run(site: &str, side: PlayerSide, region: Option<&BoardRegion>) -> Result<()> {
+    let pack = ocr_native::calibration_key(site);
+    println!("Calibrating native OCR for {}", pack);
+    println!("  Open a board in the starting position with {} at the bottom,", side);
+    println!("  with no highlighted squares, arrows, or hovered pieces.");
+    if prompt::select("Capture the board now?", &["Capture", "Cancel"], 0)? != 0 {
+        return Ok(());
+    }
+
+    let frame = capture::capture_screenshot()?;
+    let screen = frame.image.as_ref();
+    let (x, y, width, height) = board_region(screen, region)?;
+    let board = imageops::resize(&screen.crop_imm(x, y, width, height), 512, 512, imageops::FilterType::Lanczos3);
+    // Same orientation as OCR: rank 8 at the top
+    let board = if side.needs_board_flip() { imageops::rotate180(&board) } else { board };
+
+    let calibration = calibrate(&board)?;
+    if !calibration.misread.is_empty() {
+        println!("⚠ The new templates misread {}", calibration.misread.join(", "));
+        if prompt::select("Save them anyway?", &["No", "Yes"], 0)? == 0 {
+            return Ok(());
+        }
+    }
+
+    let dir = ocr_native::template_dir(site);
+    if std::path::Path::new(&dir).exists() {
+        let question = format!("Replace the templates in {}/?", dir);
+        if prompt::select(&question, &["Yes", "No, cancel"], 0)? != 0 {
+            return Ok(());
+        }
+        // Stale alternates would keep matching the old theme
+        for (_, name) in PIECE_FILES {
+            let _ = std::fs::remove_file(format!("{}/{}-alt.png", dir, name));
+        }
+    }
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir))?;
+    for (name, template) in &calibration.templates {
+        let path = format!("{}/{}.png", dir, name);
+        template.save(&path).with_context(|| format!("Failed to write {}", path))?;
+    }
+    let labels = board_detect::cut_labels(screen, (x, y, width, height));
+    let label_dir = board_detect::label_dir(site);
+    std::fs::create_dir_all(&label_dir).with_context(|| format!("Failed to create {}", label_dir))?;
+    for (name, patch) in [("top-left", &labels.top_left), ("bottom-right", &labels.bottom_right)] {
+        let path = format!("{}/{}.png", label_dir, name);
+        patch.save(&path).with_context(|| format!("Failed to write {}", path))?;
+    }
+
+    let mut saved = config::load()?;
+    saved.calibration.insert(pack, calibration.thresholds);
+    let path = config::save(&saved)?;
+    println!("✓ Saved {} templates to {}/", calibration.templates.len(), dir);
+    println!(
+        "✓ Saved thresholds (empty variance {:.0}, match score {:.2}) to {}",
+        calibration.thresholds.empty_variance,
+        calibration.thresholds.match_score,
+        path.display()
+    );
+    Ok(())
+}
+
+/// Board rectangle in the screenshot: `--board-region`, the confirmed detection, or typed corners
+fn board_region(screen: &DynamicImage, region: Option<&BoardRegion>) -> Result<(u32, u32, u32, u32)> {
+    let (screen_w, screen_h) = screen.dimensions();
+    if let Some(r) = region {
+        anyhow::ensure!(
+            r.x + r.width <= screen_w && r.y + r.height <= screen_h,
+            "--board-region lies outside the {}×{} screenshot",
+            screen_w,
+            screen_h
+        );
+        return Ok((r.x, r.y, r.width, r.height));
+    }
+
+    std::fs::create_dir_all(PREVIEW_DIR).with_context(|| format!("Failed to create {}", PREVIEW_DIR))?;
+    match ocr_native::locate_board(screen) {
+        Ok((x, y, w, h)) => {
+            let preview = format!("{}/calibration_board.png", PREVIEW_DIR);
+            screen.crop_imm(x, y, w, h).save(&preview).with_context(|| format!("Failed to write {}", preview))?;
+            println!("  Detected a {}×{} board at ({}, {}) - preview saved to {}", w, h, x, y, preview);
+            if prompt::select("Does the preview show exactly the board?", &["Yes", "No, enter its corners"], 0)? == 0 {
+                return Ok((x, y, w, h));
+            }
+        }
+        Err(e) => println!("  Board not detected: {:#}", e),
+    }
+
+    let full = format!("{}/calibration_screen.png", PREVIEW_DIR);
+    screen.save(&full).with_context(|| format!("Failed to write {}", full))?;
+    println!("  Screenshot saved to {} ({}×{}); read the board's corners from it", full, screen_w, screen_h);
+    let validate = |text: &str| parse_point(text).map(|_| ());
+    let top_left = parse_point(&prompt::input("Top-left corner of the board (x,y)", validate)?).unwrap_or_default();
+    let bottom_right = parse_point(&prompt::input("Bottom-right corner of the board (x,y)", validate)?).unwrap_or_default();
+    let rect = corners_to_rect(top_left, bottom_right).map_err(anyhow::Error::msg)?;
+    anyhow::ensure!(
+        rect.0 + rect.2 <= screen_w && rect.1 + rect.3 <= screen_h,
+        "Corners lie outside the {}×{} screenshot",
+        screen_w,
+        screen_h
+    );
+    Ok(rect)
+}
+
+/// Parses "x,y" screen coordinates
+fn parse_point(text: &str) -> Result<(u32, u32), &'static str> {
+    let (x, y) = text.split_once(',').ok_or("Expected x,y (e.g. 120,85)")?;
+    match (x.trim().parse(), y.trim().parse()) {
+        (Ok(x), Ok(y)) => Ok((x, y)),
+        _ => Err("Expected x,y (e.g. 120,85)"),
+    }
+}
+
+/// (x, y, width, height) between two corners; the board must be roughly square
+fn corners_to_rect(top_left: (u32, u32), bottom_right: (u32, u32)) -> Result<(u32, u32, u32, u32), String> {
+    let (width, height) = (bottom_right.0.saturating_sub(top_left.0), bottom_right.1.saturating_sub(top_left.1));
+    if width < 64 || height < 64 {
+        return Err(format!("Board of {}×{} is too small (bottom-right must be below and right of top-left)", width, height));
+    }
+    if (width as f32 / height as f32 - 1.0).abs() > 0.1 {
+        return Err(format!("Board of {}×{} isn't square", width, height));
+    }
+    Ok((top_left.0, top_left.1, width, height))
+}
+
+/// Cuts templates and measures thresholds on a 512×512 starting-position board (rank 8 at the top)
+fn calibrate(board: &RgbaImage) -> Result<Calibration> {
+    let squares = ocr_native::split_into_squares(board);
+    let expected = |row: usize, col: usize| START_RANKS[row].as_bytes()[col] as char;
+
+    // First square of each piece from the a-file, then one on the other square color
+    let mut cut: Vec<(String, GrayImage)> = Vec::new();
+    let mut templates: HashMap<char, Vec<GrayImage>> = HashMap::new();
+    let mut first_light: HashMap<char, bool> = HashMap::new();
+    for row in [0, 1, 6, 7] {
+        for (col, square) in squares[row].iter().enumerate() {
+            let piece = expected(row, col);
+            let light = (row + col).is_multiple_of(2);
+            let name = PIECE_FILES.iter().find(|(c, _)| *c == piece).map(|(_, n)| *n).context("Unknown piece")?;
+            let name = match first_light.get(&piece) {
+                None => name.to_string(),
+                Some(&first) if first != light && templates[&piece].len() == 1 => format!("{}-alt", name),
+                Some(_) => continue,
+            };
+            first_light.entry(piece).or_insert(light);
+            templates.entry(piece).or_default().push(square.clone());
+            cut.push((name, square.clone()));
+        }
+    }
+
+    // Empty squares are flat; every piece square must vary more than any empty one
+    let (mut empty_max, mut piece_min) = (0.0f32, f32::MAX);
+    let mut worst_match = 0.0f32;
+    for (row, rank) in squares.iter().enumerate() {
+        for (col, square) in rank.iter().enumerate() {
+            let variance = ocr_native::square_variance(square);
+            match expected(row, col) {
+                '.' => empty_max = empty_max.max(variance),
+                piece => {
+                    piece_min = piece_min.min(variance);
+                    let own = templates[&piece].iter().map(|t| ocr_native::match_score(square, t)).fold(f32::MAX, f32::min);
+                    worst_match = worst_match.max(own);
+                }
+            }
+        }
+    }
+    anyhow::ensure!(
+        empty_max < piece_min,
+        "Empty squares and pieces can't be told apart (empty squares vary up to {:.0}, pieces from {:.0}) - \
+         is the starting position shown, without highlights?",
+        empty_max,
+        piece_min
+    );
+    let thresholds = Thresholds {
+        empty_variance: (empty_max + piece_min) / 2.0,
+        match_score: (worst_match * MATCH_MARGIN).max(MIN_MATCH_SCORE),
+    };
+
+    // Read the position back with the new templates
+    let mut misread = Vec::new();
+    for (row, rank) in squares.iter().enumerate() {
+        for (col, square) in rank.iter().enumerate() {
+            let want = expected(row, col);
+            let got = match ocr_native::match_square(square, &templates, thresholds) {
+                '1' => '.',
+                piece => piece,
+            };
+            if got != want {
+                let name = format!("{}{}", (b'a' + col as u8) as char, 8 - row);
+                misread.push(format!("{}: {} instead of {}", name, got, want));
+            }
+        }
+    }
+    Ok(Calibration { templates: cut, thresholds, misread })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// Synthetic 512×512 starting position: flat squares, each piece a bar of its own width
+    fn board(pieces: bool) -> RgbaImage {
+        RgbaImage::from_fn(512, 512, |x, y| {
+            let (row, col) = ((y / 64) as usize, (x / 64) as usize);
+            let background = if (row + col).is_multiple_of(2) { 220 } else { 120 };
+            let piece = START_RANKS[row].as_bytes()[col] as char;
+            let width = match piece.to_ascii_lowercase() {
+                'p' => 8,
+                'n' => 14,
+                'b' => 20,
+                'r' => 26,
+                'q' => 32,
+                'k' => 38,
+                _ => 0,
+            };
+            let (px, py) = (x % 64, y % 64);
+            let value = if pieces && px >= 10 && px < 10 + width && (16..48).contains(&py) {
+                if piece.is_ascii_uppercase() { 255 } else { 0 }
+            } else {
+                background
+            };
+            Rgba([value, value, value, 255])
+        })
+    }
+
+    #[test]
+    fn test_calibrate_starting_position() {
+        let calibration = calibrate(&board(true)).unwrap();
+        // Kings and queens stand on one square color only
+        assert_eq!(calibration.templates.len(), 20);
+        assert!(calibration.templates.iter().any(|(name, _)| name == "PW-alt"));
+        assert!(!calibration.templates.iter().any(|(name, _)| name == "KW-alt"));
+        assert!(calibration.thresholds.empty_variance > 0.0);
+        assert_eq!(calibration.thresholds.match_score, MIN_MATCH_SCORE);
+        assert!(calibration.misread.is_empty(), "{:?}", calibration.misread);
+    }
+
+    #[test]
+    fn test_calibrate_rejects_board_without_pieces() {
+        let err = calibrate(&board(false)).unwrap_err();
+        assert!(err.to_string().contains("can't be told apart"));
+    }
+
+    #[test]
+    fn test_corners() {
+        assert_eq!(parse_point(" 120, 85"), Ok((120, 85)));
+        assert!(parse_point("120").is_err());
+        assert_eq!(corners_to_rect((100, 50), (900, 850)), Ok((100, 50, 800, 800)));
+        assert!(corners_to_rect((900, 850), (100, 50)).is_err());
+        assert!(corners_to_rect((0, 0), (800, 400)).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+//! Webcam capture (`--input camera:<index>`, `camera` feature)
+//!
+//! For over-the-board post-mortems: a webcam pointed at a physical board replaces the
+//! screen as the frame source. The camera is opened once and owned by a worker thread
+//! (nokhwa handles aren't `Send`), which hands out the latest frame on request, so the
+//! stream stays warm between captures instead of renegotiating the device every cycle.
+
+use anyhow::{Context, Result, anyhow};
+use image::RgbaImage;
+use nokhwa::Camera;
+use nokhwa::pixel_format::RgbFormat;
+use nokhwa::utils::{CameraIndex, RequestedFormat, RequestedFormatType};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Mutex, OnceLock};
+
+/// Frame request: the worker answers on the enclosed channel
+type Request = Sender<Result<RgbaImage>>;
+
+/// Worker thread of the open camera, started on first capture
+static WORKER: OnceLock<Mutex<Sender<Request>>> = OnceLock::new();
+
+/// Grabs one frame from camera `index`
+pub fn grab(index: u32) -> Result<RgbaImage> {
+    let worker = match WORKER.get() {
+        Some(worker) => worker,
+        None => {
+            let sender = start(index)?;
+            WORKER.get_or_init(|| Mutex::new(sender))
+        }
+    };
+    let (reply, response) = mpsc::channel();
+    worker
+        .lock()
+        .map_err(|_| anyhow!("Camera worker poisoned"))?
+        .send(reply)
+        .map_err(|_| anyhow!("Camera worker stopped"))?;
+    response.recv().context("Camera worker stopped")?
+}
+
+/// Opens the camera on a worker thread, failing if the device can't be opened
+fn start(index: u32) -> Result<Sender<Request>> {
+    let (requests, incoming) = mpsc::channel::<Request>();
+    let (ready, opened) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut camera = match open(index) {
+            Ok(camera) => {
+                let _ = ready.send(Ok(()));
+                camera
+            }
+            Err(e) => {
+                let _ = ready.send(Err(e));
+                return;
+            }
+        };
+        for reply in incoming {
+            let _ = reply.send(read_frame(&mut camera));
+        }
+    });
+    opened.recv().context("Camera worker stopped")??;
+    Ok(requests)
+}
+
+fn open(index: u32) -> Result<Camera> {
+    // Asks for camera permission on macOS (no-op elsewhere)
+    let (granted, permission) = mpsc::channel();
+    nokhwa::nokhwa_initialize(move |ok| {
+        let _ = granted.send(ok);
+    });
+    anyhow::ensure!(
+        permission.recv().unwrap_or(false),
+        "Camera access denied — grant Terminal camera permission in System Settings > Privacy & Security"
+    );
+
+    let format = RequestedFormat::new::<RgbFormat>(RequestedFormatType::AbsoluteHighestResolution);
+    let mut camera = Camera::new(CameraIndex::Index(index), format)
+        .with_context(|| format!("Failed to open camera {} — check camera permission", index))?;
+    camera.open_stream().context("Failed to start camera stream")?;
+    Ok(camera)
+}
+
+fn read_frame(camera: &mut Camera) -> Result<RgbaImage> {
+    let frame = camera.frame().context("Failed to read camera frame")?;
+    let rgb = frame.decode_image::<RgbFormat>().context("Failed to decode camera frame")?;
+    Ok(image::DynamicImage::ImageRgb8(rgb).to_rgba8())
+}
@@ -1,29 +1,252 @@
 //! Screen capture module
-//! Pure screenshot service: captures primary display full-screen via `xcap` (cross-platform), saves PNG for OCR.
+//! Pure screenshot service: captures primary display full-screen via `xcap` (cross-platform) and hands the
+//! decoded image to the pipeline in memory (no temp file round-trip, no clash between running instances).
 //! No cropping here—shifts flexibility to OCR for board detection across varying windows/apps/sites (e.g., macOS Chess.app, browsers).
-//! Latency goal: <200ms (capture + downsample + save).
+//! Latency goal: <200ms (capture + downsample).
 //! Note: High-DPI displays (4K/5K/6K) are aggressively downsampled for performance.
-//! Future: Add window-specific capture, dynamic crop if perf bottleneck, or multi-monitor support.
+//! With `--window <title>`, only the window whose title contains that text is captured
+//! (e.g. the browser tab showing the game), skipping other monitors' content entirely.
+//! With `--input camera:<index>` (built with the `camera` feature), frames come from a
+//! webcam pointed at a physical board instead (see `camera`).
+//! With `--input replay:<dir>`, frames are the images in that directory in name order
+//! (recorded captures, or canned frames in tests); once they run out, capturing fails with
+//! `ReplayFinished` and the live loop ends.
+//! With `--when-focused`, the live loops capture only while the focused window's title
+//! matches (`capture_allowed`), so alt-tabbing to anything else pauses them.
+//! Future: dynamic crop if perf bottleneck.
+//!
+//! Images are encoded only where they leave the process, and how depends on who reads
+//! them (`Consumer`): template matching tools get a lossless PNG (JPEG artifacts blur
+//! piece edges), LLM uploads a low-quality JPEG (the API downsamples anyway, so smaller
+//! uploads are free savings), and archived frames a mid-quality JPEG.
 
 use anyhow::{Context, Result};
 use image::{DynamicImage, GenericImageView, ImageEncoder, imageops};
 use image::codecs::jpeg::JpegEncoder;
-use std::fs::{self, File};
-use std::io::BufWriter;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Instant;
-use xcap::Monitor;
+use xcap::{Monitor, Window};
 
 /// Maximum width for captured screenshots. Images larger than this are downsampled.
 /// 1920px gives LLM OCR enough detail to read pieces accurately.
 /// Higher than 1280 for better accuracy, still much faster than full 6K.
 const MAX_CAPTURE_WIDTH: u32 = 1920;
 
-/// Captures the full screenshot of the primary monitor and saves as PNG to screenshots/current_board.png.
-/// OCR module will load and handle board detection/cropping for flexibility across apps/sites.
-/// Debug: Set env var `DEBUG_CAPTURE=1` to also save full screen variant to screenshots/debug_full_screen.png.
-/// Later phases: Optional window-specific capture or dynamic cropping here if perf needed.
+/// Title substring of the window to capture (--window); the primary monitor when unset
+static WINDOW: OnceLock<String> = OnceLock::new();
+
+/// Title substrings (lowercase) of the windows that must be focused for a capture (--when-focused)
+static FOCUS_GATE: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Frame source (--input); the screen when unset
+static INPUT: OnceLock<Input> = OnceLock::new();
+
+/// Index of the next frame of `--input replay:<dir>`
+static REPLAY_NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// Most recent capture, its cycle ID, and where it was on screen, for actions outside the
+/// pipeline (e.g. recording a manual correction, tagging an LLM response with the cycle it
+/// belongs to, or drawing the overlay)
+static LATEST: Mutex<Option<LatestCapture>> = Mutex::new(None);
+
+/// Image, cycle ID, and screen area of a capture
+type LatestCapture = (Arc<DynamicImage>, String, Option<ScreenArea>);
+
+/// Restricts captures to the window whose title contains `title` (first call wins)
+pub fn set_window(title: &str) {
+    let _ = WINDOW.set(title.to_string());
+}
+
+/// The `--window` title, if set
+pub fn window_title() -> Option<&'static str> {
+    WINDOW.get().map(String::as_str)
+}
+
+/// Text in the title of a site's game window
+pub fn site_window_title(site: &str) -> &'static str {
+    match site {
+        "lichess" => "lichess",
+        "macOS" => "Chess",
+        _ => "chess.com",
+    }
+}
+
+/// Title of the focused window (None when no window reports focus)
+pub fn focused_title() -> Result<Option<String>> {
+    Ok(Window::all()
+        .context("Failed to enumerate windows")?
+        .into_iter()
+        .find(|w| w.is_focused().unwrap_or(false))
+        .and_then(|w| w.title().ok()))
+}
+
+/// Whether the focused window's title contains `title` (case-insensitive)
+pub fn window_focused(title: &str) -> Result<bool> {
+    let needle = title.to_lowercase();
+    Ok(focused_title()?.is_some_and(|t| t.to_lowercase().contains(&needle)))
+}
+
+/// Captures only while a window whose title contains one of `titles` is focused
+/// (first call wins). Fails when this system doesn't report the focused window.
+pub fn set_focus_gate(titles: &[String]) -> Result<()> {
+    focused_title().context("--when-focused needs to know the focused window")?;
+    let _ = FOCUS_GATE.set(titles.iter().map(|t| t.to_lowercase()).collect());
+    Ok(())
+}
+
+/// Whether the live loop may capture now: always without `--when-focused`, otherwise only
+/// while a matching window is focused (an unreadable focus counts as not matching)
+pub fn capture_allowed() -> bool {
+    let Some(titles) = FOCUS_GATE.get() else {
+        return true;
+    };
+    matches!(focused_title(), Ok(Some(title)) if title_matches(&title, titles))
+}
+
+fn title_matches(title: &str, patterns: &[String]) -> bool {
+    let title = title.to_lowercase();
+    patterns.iter().any(|p| title.contains(p.as_str()))
+}
+
+/// Where frames come from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Input {
+    /// Primary monitor, or the `--window` window
+    Screen,
+    /// Webcam with this index, pointed at a physical board
+    Camera(u32),
+    /// Images in this directory, in name order
+    Replay(PathBuf),
+}
+
+impl Input {
+    /// Parses `screen`, `camera:<index>` (`camera` alone is camera 0), or `replay:<dir>`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.split_once(':') {
+            None if text == "screen" => Ok(Input::Screen),
+            None if text == "camera" => Ok(Input::Camera(0)),
+            Some(("camera", index)) => index
+                .parse()
+                .map(Input::Camera)
+                .map_err(|_| format!("'{}' is not a camera index", index)),
+            Some(("replay", dir)) if !dir.is_empty() => Ok(Input::Replay(PathBuf::from(dir))),
+            _ => Err(format!("unknown input '{}' (expected screen, camera:<index>, or replay:<dir>)", text)),
+        }
+    }
+}
+
+/// Capture error once every frame of `--input replay:<dir>` has been analyzed
+#[derive(Debug)]
+pub struct ReplayFinished;
+
+impl std::fmt::Display for ReplayFinished {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No frames left to replay")
+    }
+}
+
+impl std::error::Error for ReplayFinished {}
+
+/// Sets the frame source (first call wins)
+pub fn set_input(input: Input) {
+    let _ = INPUT.set(input);
+}
+
+/// True when frames are photos of a physical board rather than screenshots
+pub fn is_camera() -> bool {
+    matches!(INPUT.get(), Some(Input::Camera(_)))
+}
+
+/// Who reads an encoded image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Consumer {
+    /// Pixel comparison (external OCR programs, stored OCR test images)
+    TemplateMatching,
+    /// LLM vision upload
+    Llm,
+    /// Frames kept on disk
+    Archive,
+}
+
+impl Consumer {
+    /// JPEG quality, or None for lossless PNG
+    pub fn jpeg_quality(self) -> Option<u8> {
+        match self {
+            Consumer::TemplateMatching => None,
+            Consumer::Llm => Some(60),
+            Consumer::Archive => Some(80),
+        }
+    }
+}
+
+/// Encodes an image for a consumer (JPEG or PNG bytes, see `Consumer::jpeg_quality`)
+pub fn encode(img: &DynamicImage, consumer: Consumer) -> Result<Vec<u8>> {
+    let rgb = img.to_rgb8();
+    let mut bytes = Vec::new();
+    match consumer.jpeg_quality() {
+        Some(quality) => JpegEncoder::new_with_quality(&mut bytes, quality)
+            .write_image(rgb.as_raw(), rgb.width(), rgb.height(), image::ExtendedColorType::Rgb8)
+            .context("Failed to encode JPEG")?,
+        // Fast compression: PNG's default level is what made full-screen PNGs slow
+        None => PngEncoder::new_with_quality(&mut bytes, CompressionType::Fast, FilterType::Sub)
+            .write_image(rgb.as_raw(), rgb.width(), rgb.height(), image::ExtendedColorType::Rgb8)
+            .context("Failed to encode PNG")?,
+    }
+    Ok(bytes)
+}
+
+/// Part of the screen a capture shows, in the units the platform reports window positions
+/// in (points on macOS, pixels elsewhere)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScreenArea {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A captured screenshot
+pub struct Frame {
+    /// The (downsampled) screenshot, shared by every consumer of the cycle
+    pub image: Arc<DynamicImage>,
+    /// When the screen was grabbed, in milliseconds since the Unix epoch
+    pub captured_ms: u128,
+    /// Unique ID of the cycle analyzing this frame, carried into every output and artifact
+    pub cycle: String,
+    grabbed: Instant,
+}
+
+impl Frame {
+    /// Time since the screen was grabbed
+    pub fn age(&self) -> std::time::Duration {
+        self.grabbed.elapsed()
+    }
+}
+
+/// The most recent capture, if any
+pub fn latest() -> Option<Arc<DynamicImage>> {
+    LATEST.lock().ok()?.as_ref().map(|(image, _, _)| Arc::clone(image))
+}
+
+/// The most recent capture with where it was on screen (None for camera and replayed frames)
+pub fn latest_on_screen() -> Option<(Arc<DynamicImage>, ScreenArea)> {
+    LATEST.lock().ok()?.as_ref().and_then(|(image, _, area)| Some((Arc::clone(image), (*area)?)))
+}
+
+/// ID of the cycle analyzing the most recent capture
+pub fn current_cycle() -> Option<String> {
+    LATEST.lock().ok()?.as_ref().map(|(_, cycle, _)| cycle.clone())
+}
+
+/// Captures the full screenshot of the primary monitor (or the `--window` window, or a camera
+/// frame with `--input camera`) and returns it with the time it was grabbed.
+/// OCR module will handle board detection/cropping for flexibility across apps/sites.
+/// Debug: Set env var `DEBUG_CAPTURE=1` to also save the image to screenshots/debug_full_screen.jpg.
 /// On macOS, grant Screen Recording permission to Terminal in System Settings > Privacy & Security.
-pub fn capture_screenshot() -> Result<()> {
+pub fn capture_screenshot() -> Result<Frame> {
     use std::io::Write;
 
     eprint!("Capturing screen... ");
@@ -31,50 +254,35 @@ pub fn capture_screenshot() -> Result<()> {
 
     let start = Instant::now();
 
-    let screenshot = Monitor::all()
-        .context("Failed to enumerate monitors")?
-        .into_iter()
-        .next()
-        .context("No monitors found")?
-        .capture_image()
-        .context("Failed to capture image — check Screen Recording permission")?;
+    let (screenshot, area) = match (INPUT.get(), WINDOW.get()) {
+        (Some(Input::Camera(index)), _) => (grab_camera(*index)?, None),
+        (Some(Input::Replay(dir)), _) => (grab_replay(dir)?, None),
+        (_, Some(title)) => {
+            let window = find_window(title)?;
+            let area = screen_area(window.x(), window.y(), window.width(), window.height());
+            (window.capture_image().context("Failed to capture window — check Screen Recording permission")?, area)
+        }
+        (_, None) => {
+            let monitor = Monitor::all()
+                .context("Failed to enumerate monitors")?
+                .into_iter()
+                .next()
+                .context("No monitors found")?;
+            let area = screen_area(monitor.x(), monitor.y(), monitor.width(), monitor.height());
+            (monitor.capture_image().context("Failed to capture image — check Screen Recording permission")?, area)
+        }
+    };
+    let grabbed = Instant::now();
+    let captured_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
 
     // Convert to image crate format for processing
     let (orig_width, orig_height) = (screenshot.width(), screenshot.height());
     let img = DynamicImage::ImageRgba8(screenshot);
 
-    // Downsample if larger than MAX_CAPTURE_WIDTH (critical for 4K/5K/6K displays)
-    // This prevents O(n²) blowup in edge detection and candidate region search
-    let final_img = if orig_width > MAX_CAPTURE_WIDTH {
-        let scale = MAX_CAPTURE_WIDTH as f32 / orig_width as f32;
-        let new_height = (orig_height as f32 * scale) as u32;
-        let resized = imageops::resize(
-            img.as_rgba8().unwrap(),
-            MAX_CAPTURE_WIDTH,
-            new_height,
-            imageops::FilterType::Triangle, // Fast bilinear filtering
-        );
-        DynamicImage::ImageRgba8(resized)
-    } else {
-        img
-    };
-
-    fs::create_dir_all("screenshots").context("Failed to create screenshots dir")?;
-
-    // Save as JPEG for speed (PNG encoding is very slow)
-    // Quality 85 is visually lossless and ~10x faster than PNG
-    let output_path = "screenshots/current_board.jpg";
-    let file = File::create(output_path).context("Failed to create output file")?;
-    let mut writer = BufWriter::new(file);
-    let rgb_img = final_img.to_rgb8();
-    JpegEncoder::new_with_quality(&mut writer, 85)
-        .write_image(
-            rgb_img.as_raw(),
-            rgb_img.width(),
-            rgb_img.height(),
-            image::ExtendedColorType::Rgb8,
-        )
-        .context("Failed to encode JPEG")?;
+    let final_img = downsample(img);
 
     let latency = start.elapsed();
     let (final_w, final_h) = final_img.dimensions();
@@ -90,10 +298,93 @@ pub fn capture_screenshot() -> Result<()> {
 
     // Debug: DEBUG_CAPTURE=1 → save extra copy (the downsampled version)
     if std::env::var("DEBUG_CAPTURE").is_ok_and(|v| v == "1") {
+        let _ = std::fs::create_dir_all("screenshots");
         let _ = final_img.save("screenshots/debug_full_screen.jpg"); // fire-and-forget
     }
 
-    Ok(())
+    let image = Arc::new(final_img);
+    let cycle = uuid::Uuid::new_v4().to_string();
+    eprintln!("Cycle {}", cycle);
+    if let Ok(mut latest) = LATEST.lock() {
+        *latest = Some((Arc::clone(&image), cycle.clone(), area));
+    }
+    Ok(Frame { image, captured_ms, cycle, grabbed })
+}
+
+/// Shrinks an image wider than `MAX_CAPTURE_WIDTH` to that width (critical for 4K/5K/6K
+/// displays and video frames): this prevents O(n²) blowup in edge detection and candidate
+/// region search
+pub fn downsample(img: DynamicImage) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    if width <= MAX_CAPTURE_WIDTH {
+        return img;
+    }
+    let scale = MAX_CAPTURE_WIDTH as f32 / width as f32;
+    let new_height = (height as f32 * scale) as u32;
+    let resized = imageops::resize(
+        &img,
+        MAX_CAPTURE_WIDTH,
+        new_height,
+        imageops::FilterType::Triangle, // Fast bilinear filtering
+    );
+    DynamicImage::ImageRgba8(resized)
+}
+
+/// Where a window or monitor is, when the platform says
+fn screen_area(
+    x: xcap::XCapResult<i32>,
+    y: xcap::XCapResult<i32>,
+    width: xcap::XCapResult<u32>,
+    height: xcap::XCapResult<u32>,
+) -> Option<ScreenArea> {
+    Some(ScreenArea { x: x.ok()?, y: y.ok()?, width: width.ok()?, height: height.ok()? })
+}
+
+/// Images in a directory (PNG or JPEG), in name order
+pub fn replay_frames(dir: &std::path::Path) -> Result<Vec<PathBuf>> {
+    let mut frames: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read replay frames from {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_image_file(path))
+        .collect();
+    frames.sort();
+    Ok(frames)
+}
+
+/// True for a path with a PNG or JPEG extension
+pub fn is_image_file(path: &std::path::Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "png" | "jpg" | "jpeg"))
+}
+
+/// Next frame of a replayed recording
+fn grab_replay(dir: &std::path::Path) -> Result<image::RgbaImage> {
+    let frames = replay_frames(dir)?;
+    let frame = frames.get(REPLAY_NEXT.fetch_add(1, Ordering::Relaxed)).ok_or(ReplayFinished)?;
+    Ok(image::open(frame).with_context(|| format!("Failed to load replay frame {}", frame.display()))?.to_rgba8())
+}
+
+#[cfg(feature = "camera")]
+fn grab_camera(index: u32) -> Result<image::RgbaImage> {
+    crate::camera::grab(index)
+}
+
+#[cfg(not(feature = "camera"))]
+fn grab_camera(_index: u32) -> Result<image::RgbaImage> {
+    anyhow::bail!("Camera input needs a build with the camera feature (cargo build --features camera)")
+}
+
+/// First visible window whose title contains `title` (case-insensitive)
+fn find_window(title: &str) -> Result<Window> {
+    let needle = title.to_lowercase();
+    Window::all()
+        .context("Failed to enumerate windows")?
+        .into_iter()
+        .find(|w| {
+            !w.is_minimized().unwrap_or(false) && w.title().is_ok_and(|t| t.to_lowercase().contains(&needle))
+        })
+        .with_context(|| format!("No open window with \"{}\" in its title", title))
 }
 
 #[cfg(test)]
@@ -104,11 +395,40 @@ mod tests {
     #[test]
     #[ignore = "requires graphical display and screen recording permissions"]
     fn test_capture_dimensions() {
-        capture_screenshot().expect("capture_screenshot failed");
-        let saved_img = image::open("screenshots/current_board.png")
-            .expect("Failed to load saved screenshot for validation");
-        let (w, h) = saved_img.dimensions();
-        assert!(w > 0 && h > 0, "saved screenshot has invalid dimensions {}x{}", w, h);
+        let frame = capture_screenshot().expect("capture_screenshot failed");
+        let (w, h) = frame.image.dimensions();
+        assert!(w > 0 && h > 0, "captured screenshot has invalid dimensions {}x{}", w, h);
         assert!(w >= 800 && h >= 600, "Screenshot too small; expected full screen-like size"); // Rough check
     }
+
+    #[test]
+    fn test_parse_input() {
+        assert_eq!(Input::parse("screen"), Ok(Input::Screen));
+        assert_eq!(Input::parse("camera:1"), Ok(Input::Camera(1)));
+        assert_eq!(Input::parse("camera"), Ok(Input::Camera(0)));
+        assert!(Input::parse("camera:front").is_err());
+        assert!(Input::parse("webcam").is_err());
+        assert_eq!(Input::parse("replay:frames"), Ok(Input::Replay(PathBuf::from("frames"))));
+        assert!(Input::parse("replay:").is_err());
+    }
+
+    #[test]
+    fn test_focus_title_matching() {
+        let patterns = vec![site_window_title("chesscom").to_string(), "lichess".to_string()];
+        assert!(title_matches("Play Chess Online - Chess.com - Firefox", &patterns));
+        assert!(title_matches("Lichess.org • Free Online Chess", &patterns));
+        assert!(!title_matches("Inbox (3) - Mail", &patterns));
+    }
+
+    #[test]
+    fn test_encoding_follows_consumer() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 64, |x, y| image::Rgb([(x * 4) as u8, (y * 4) as u8, 90])));
+        let png = encode(&img, Consumer::TemplateMatching).unwrap();
+        assert_eq!(image::load_from_memory(&png).unwrap().to_rgb8(), img.to_rgb8(), "lossless for template matching");
+
+        let llm = encode(&img, Consumer::Llm).unwrap();
+        let archive = encode(&img, Consumer::Archive).unwrap();
+        assert_eq!(image::guess_format(&llm).unwrap(), image::ImageFormat::Jpeg);
+        assert!(llm.len() < archive.len());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,190 @@
+//! Time control detection and speed presets
+//!
+//! At game start the area around the board (name plates, clocks, and the side panel) is
+//! read once with a low-detail LLM request. Sites usually print the time control itself
+//! ("3 | 2" on chess.com, "3+2" on lichess); when only a clock is visible, its starting
+//! value is rounded up to the nearest common base time.
+//!
+//! The detected time control selects a preset (search depth and capture interval) so a
+//! bullet game gets fast, shallow answers and a rapid game deeper ones, and it is recorded
+//! with the session for the PGN `TimeControl` tag. `--time-control` skips detection.
+
+use anyhow::Result;
+use crate::profiles::TimeClass;
+use image::{DynamicImage, GenericImageView};
+
+const CLOCK_PROMPT: &str = "This is part of a chess website around the board. \
+If the game's time control is shown (e.g. \"3 | 2\", \"3+2\", \"10 min\"), reply exactly:\n\
+TIME CONTROL: <minutes>+<increment seconds>\n\
+Otherwise, if a player's clock is visible, reply exactly:\nCLOCK: <time as shown>\n\
+If neither is visible, reply NONE.";
+
+const CLOCKS_PROMPT: &str = "This is part of a chess website around the board. \
+Each player's clock is shown next to their name, one above and one below the board. Reply exactly:\n\
+TOP: <time as shown>\nBOTTOM: <time as shown>\n\
+If the clocks are not visible, reply NONE.";
+
+/// Common base times in seconds, used to round a starting clock up
+const BASE_TIMES: [u32; 10] = [60, 120, 180, 300, 600, 900, 1200, 1800, 2700, 3600];
+
+/// Engine depth and capture interval suited to a time control
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Preset {
+    pub depth: u16,
+    pub interval_ms: u64,
+    /// Board age beyond which a suggestion is reported as lagging behind the game
+    pub max_lag_ms: u64,
+}
+
+impl Preset {
+    pub fn for_class(class: TimeClass) -> Self {
+        let (depth, interval_ms, max_lag_ms) = match class {
+            TimeClass::Bullet => (4, 300, 1000),
+            TimeClass::Blitz => (6, 1000, 2500),
+            TimeClass::Rapid => (8, 2000, 6000),
+            TimeClass::Classical => (10, 3000, 15_000),
+            TimeClass::Daily => (12, 5000, 60_000),
+        };
+        Preset { depth, interval_ms, max_lag_ms }
+    }
+
+    /// Preset for a PGN time control (blitz when unrecognized)
+    pub fn for_time_control(time_control: &str) -> Self {
+        Self::for_class(TimeClass::from_time_control(time_control).unwrap_or_default())
+    }
+}
+
+/// Time left on both clocks, in seconds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Clocks {
+    pub mine: u32,
+    pub theirs: u32,
+}
+
+/// Reads both clocks from a full screenshot. The player's clock is the one below the board
+/// (sites show the board from the player's side).
+pub async fn read_clocks(img: &DynamicImage) -> Result<Option<Clocks>> {
+    let board = crate::ocr_native::locate_board(img)?;
+    let (x, y, w, h) = clock_region(board, img.dimensions());
+    let text = crate::ocr_llm::read_text(&img.crop_imm(x, y, w, h), CLOCKS_PROMPT).await?;
+    Ok(parse_clocks(&text))
+}
+
+/// Reads the time control from a full screenshot (PGN form, e.g. "180+2")
+pub async fn read_time_control(img: &DynamicImage) -> Result<Option<String>> {
+    let board = crate::ocr_native::locate_board(img)?;
+    let (x, y, w, h) = clock_region(board, img.dimensions());
+    let text = crate::ocr_llm::read_text(&img.crop_imm(x, y, w, h), CLOCK_PROMPT).await?;
+    Ok(parse_reply(&text))
+}
+
+/// The board plus one square above and below, extended half a board to the right
+/// (clocks sit at the end of the name plates or in the side panel)
+fn clock_region(board: (u32, u32, u32, u32), image: (u32, u32)) -> (u32, u32, u32, u32) {
+    let (x, y, w, h) = board;
+    let (img_w, img_h) = image;
+    let strip = h / 8;
+    let top = y.saturating_sub(strip);
+    let bottom = (y + h + strip).min(img_h);
+    let width = (w + w / 2).min(img_w.saturating_sub(x));
+    (x, top, width, bottom - top)
+}
+
+fn parse_reply(text: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("TIME CONTROL:") {
+            parse_time_control(value)
+        } else if let Some(value) = line.strip_prefix("CLOCK:") {
+            clock_to_time_control(value)
+        } else {
+            None
+        }
+    })
+}
+
+fn parse_clocks(text: &str) -> Option<Clocks> {
+    let find = |label: &str| {
+        text.lines()
+            .find_map(|line| line.trim().strip_prefix(label))
+            .and_then(clock_seconds)
+    };
+    Some(Clocks { mine: find("BOTTOM:")?, theirs: find("TOP:")? })
+}
+
+/// Parses a displayed time control ("3+2", "3 | 2", "10 min", "½+0") into PGN form
+pub fn parse_time_control(text: &str) -> Option<String> {
+    let text = text.trim().trim_end_matches("min").trim();
+    let (base, increment) = match text.split_once(['+', '|']) {
+        Some((base, increment)) => (base.trim(), increment.trim()),
+        None => (text, "0"),
+    };
+    let base_secs = match base {
+        "½" | "1/2" | "0.5" => 30,
+        "¼" | "1/4" | "0.25" => 15,
+        minutes => minutes.parse::<u32>().ok()? * 60,
+    };
+    let increment: u32 = increment.trim_end_matches('s').trim().parse().ok()?;
+    (base_secs > 0).then(|| format!("{}+{}", base_secs, increment))
+}
+
+/// Rounds a starting clock ("2:58", "0:59.3", "1:00:00") up to a common base time
+fn clock_to_time_control(clock: &str) -> Option<String> {
+    let secs = clock_seconds(clock)?;
+    let base = BASE_TIMES.iter().copied().find(|&b| b >= secs).unwrap_or(secs);
+    (base > 0).then(|| format!("{}+0", base))
+}
+
+/// Seconds on a displayed clock ("2:58", "0:59.3", "1:00:00"), tenths dropped
+fn clock_seconds(clock: &str) -> Option<u32> {
+    let clock = clock.trim().split('.').next()?;
+    let mut secs = 0u32;
+    for part in clock.split(':') {
+        secs = secs * 60 + part.trim().parse::<u32>().ok()?;
+    }
+    Some(secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_time_control() {
+        assert_eq!(parse_time_control("3 | 2").as_deref(), Some("180+2"));
+        assert_eq!(parse_time_control("15+10").as_deref(), Some("900+10"));
+        assert_eq!(parse_time_control("10 min").as_deref(), Some("600+0"));
+        assert_eq!(parse_time_control("½+0").as_deref(), Some("30+0"));
+        assert_eq!(parse_time_control("fast"), None);
+    }
+
+    #[test]
+    fn test_clock_rounded_up_to_base_time() {
+        assert_eq!(clock_to_time_control("2:58").as_deref(), Some("180+0"));
+        assert_eq!(clock_to_time_control("0:59.3").as_deref(), Some("60+0"));
+        assert_eq!(clock_to_time_control("1:30:00").as_deref(), Some("5400+0"));
+    }
+
+    #[test]
+    fn test_parse_reply_prefers_time_control_line() {
+        assert_eq!(parse_reply("TIME CONTROL: 5+3").as_deref(), Some("300+3"));
+        assert_eq!(parse_reply("CLOCK: 9:59").as_deref(), Some("600+0"));
+        assert_eq!(parse_reply("NONE"), None);
+    }
+
+    #[test]
+    fn test_parse_clocks_bottom_is_mine() {
+        assert_eq!(parse_clocks("TOP: 2:10\nBOTTOM: 0:42.7"), Some(Clocks { mine: 42, theirs: 130 }));
+        assert_eq!(parse_clocks("TOP: 2:10\nBOTTOM: NONE"), None);
+        assert_eq!(parse_clocks("NONE"), None);
+    }
+
+    #[test]
+    fn test_presets_scale_with_time_class() {
+        let bullet = Preset::for_class(TimeClass::Bullet);
+        let rapid = Preset::for_class(TimeClass::Rapid);
+        assert!(bullet.depth < rapid.depth);
+        assert!(bullet.interval_ms < rapid.interval_ms);
+        assert!(bullet.max_lag_ms < rapid.max_lag_ms);
+    }
+}
@@ -0,0 +1,236 @@
+//! Persistent defaults (`~/.config/zugzwang/config.toml`)
+//!
+//! The file holds the answers to the startup questions and the usual flags, so a run with
+//! no arguments starts straight away:
+//!
+//! ```toml
+//! ocr = "llm"
+//! analysis = "engine"
+//! trigger = "auto"
+//! hotkey = "F9"
+//! side = "white"
+//! site = "lichess"
+//! theme = "merida"
+//! interval = 1500
+//! depth = 8
+//! window = "lichess"
+//! provider = "openai,gemini"
+//!
+//! [calibration.chesscom]
+//! empty-variance = 85.0
+//! match-score = 0.24
+//! ```
+//!
+//! File values become the defaults of the matching flags, so anything given on the command
+//! line still wins. `capture-region` is one or more `--board-region` rectangles,
+//! `fallback-region` the `--fallback-region` rectangle,
+//! `provider` the `--llm-fallback` chain, and `output` a list of `--output` sinks (e.g.
+//! `["console", "tts"]`, spoken as set by `tts-language` and `tts-voice`). After the interactive questions the answers can be
+//! saved to the file, and `$XDG_CONFIG_HOME` is honoured when set. `calibration` tables are
+//! written by `--calibrate`: native OCR thresholds per site, or per `site/theme` pack.
+//! `secrets` holds API keys and tokens by environment variable name, encrypted (see
+//! `secrets`); plaintext entries are encrypted in the file when it is read.
+
+use anyhow::{Context, Result};
+use clap::Command;
+use crate::ocr_native::Thresholds;
+use crate::secrets;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Saved defaults; unset values fall back to the built-in defaults
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ocr: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analysis: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hotkey: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub side: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site: Option<String>,
+    /// Native OCR template pack (`--theme`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    /// Loop interval in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+    /// Starting engine depth
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<u16>,
+    /// Title of the window to capture
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window: Option<String>,
+    /// Board rectangles ("X,Y,W,H[:SIDE]")
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub capture_region: Vec<String>,
+    /// Board rectangle used when detection fails ("X,Y,W,H", `--fallback-region`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_region: Option<String>,
+    /// LLM provider chain ("openai,gemini")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Output sinks ("console", "tts", "webhook:URL", ...)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub output: Vec<String>,
+    /// Spoken move language (`--tts-language`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts_language: Option<String>,
+    /// Platform voice for spoken moves (`--tts-voice`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts_voice: Option<String>,
+    /// Native OCR thresholds per site or `site/theme` (`--calibrate`)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub calibration: BTreeMap<String, Thresholds>,
+    /// Encrypted API keys and tokens by environment variable name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub secrets: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// Makes file values the defaults of the matching flags
+    pub fn apply(&self, mut cli: Command) -> Command {
+        let values = [
+            ("ocr", self.ocr.clone()),
+            ("analysis", self.analysis.clone()),
+            ("trigger", self.trigger.clone()),
+            ("hotkey", self.hotkey.clone()),
+            ("side", self.side.clone()),
+            ("site", self.site.clone()),
+            ("theme", self.theme.clone()),
+            ("interval", self.interval.map(|ms| ms.to_string())),
+            ("depth", self.depth.map(|depth| depth.to_string())),
+            ("window", self.window.clone()),
+            ("fallback-region", self.fallback_region.clone()),
+            ("llm-fallback", self.provider.clone()),
+            ("tts-language", self.tts_language.clone()),
+            ("tts-voice", self.tts_voice.clone()),
+        ];
+        for (id, value) in values {
+            if let Some(value) = value {
+                cli = cli.mut_arg(id, |arg| arg.default_value(value));
+            }
+        }
+        if !self.capture_region.is_empty() {
+            let regions = self.capture_region.clone();
+            cli = cli.mut_arg("board-region", |arg| arg.default_values(regions));
+        }
+        if !self.output.is_empty() {
+            let sinks = self.output.clone();
+            cli = cli.mut_arg("output", |arg| arg.default_values(sinks));
+        }
+        cli
+    }
+}
+
+/// Location of the config file (None when no home directory is known)
+pub fn path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?).join(".config"),
+    };
+    Some(base.join("zugzwang").join("config.toml"))
+}
+
+/// Reads the config file; a missing file gives the defaults. Plaintext secrets are
+/// encrypted and the file rewritten.
+pub fn load() -> Result<Config> {
+    let Some(path) = path().filter(|p| p.exists()) else {
+        return Ok(Config::default());
+    };
+    let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut config = parse(&text).with_context(|| format!("Invalid config file {}", path.display()))?;
+    match secrets::encrypt_plaintext(&mut config.secrets) {
+        Ok(0) => {}
+        Ok(count) => {
+            save(&config)?;
+            eprintln!("🔒 Encrypted {} plaintext secret(s) in {}", count, path.display());
+        }
+        Err(e) => eprintln!("⚠ Secrets in {} left in plaintext: {:#}", path.display(), e),
+    }
+    Ok(config)
+}
+
+/// Writes the config file, creating its directory
+pub fn save(config: &Config) -> Result<PathBuf> {
+    let path = path().context("No home directory to save the config in")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let text = toml::to_string(config).context("Failed to serialize config")?;
+    std::fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+fn parse(text: &str) -> Result<Config> {
+    Ok(toml::from_str(text)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+
+    fn cli() -> Command {
+        Command::new("test")
+            .arg(Arg::new("side").long("side").value_parser(["white", "black"]))
+            .arg(Arg::new("interval").long("interval").default_value("1000").value_parser(clap::value_parser!(u64)))
+            .arg(Arg::new("ocr").long("ocr"))
+            .arg(Arg::new("analysis").long("analysis"))
+            .arg(Arg::new("trigger").long("trigger"))
+            .arg(Arg::new("site").long("site"))
+            .arg(Arg::new("depth").long("depth"))
+            .arg(Arg::new("window").long("window"))
+            .arg(Arg::new("llm-fallback").long("llm-fallback"))
+            .arg(Arg::new("board-region").long("board-region").action(clap::ArgAction::Append))
+    }
+
+    #[test]
+    fn test_parse_kebab_case_and_missing_keys() {
+        let config = parse("side = \"black\"\ninterval = 1500\ncapture-region = [\"0,80,900,900\"]\n").unwrap();
+        assert_eq!(config.side.as_deref(), Some("black"));
+        assert_eq!(config.interval, Some(1500));
+        assert_eq!(config.capture_region, vec!["0,80,900,900"]);
+        assert_eq!(config.ocr, None);
+        assert!(parse("interval = \"soon\"").is_err());
+        assert!(parse("sid = \"black\"").is_err()); // typos are reported, not ignored
+    }
+
+    #[test]
+    fn test_round_trip_skips_unset_values() {
+        let config = Config { ocr: Some("llm".to_string()), depth: Some(8), ..Default::default() };
+        let text = toml::to_string(&config).unwrap();
+        assert_eq!(text, "ocr = \"llm\"\ndepth = 8\n");
+        assert_eq!(parse(&text).unwrap(), config);
+
+        let mut calibrated = config.clone();
+        calibrated.calibration.insert("lichess".to_string(), Thresholds { empty_variance: 80.0, match_score: 0.25 });
+        let text = toml::to_string(&calibrated).unwrap();
+        assert!(text.ends_with("[calibration.lichess]\nempty-variance = 80.0\nmatch-score = 0.25\n"), "{}", text);
+        assert_eq!(parse(&text).unwrap(), calibrated);
+
+        // Secrets keep their variable names (no kebab-case)
+        let mut with_secret = calibrated.clone();
+        with_secret.secrets.insert("OPENAI_API_KEY".to_string(), "enc:v1:machine:AAAA".to_string());
+        let text = toml::to_string(&with_secret).unwrap();
+        assert!(text.ends_with("[secrets]\nOPENAI_API_KEY = \"enc:v1:machine:AAAA\"\n"), "{}", text);
+        assert_eq!(parse(&text).unwrap(), with_secret);
+    }
+
+    #[test]
+    fn test_command_line_overrides_file() {
+        let config = Config { side: Some("black".to_string()), interval: Some(1500), ..Default::default() };
+        let matches = config.apply(cli()).get_matches_from(["test"]);
+        assert_eq!(matches.get_one::<String>("side").map(String::as_str), Some("black"));
+        assert_eq!(matches.get_one::<u64>("interval"), Some(&1500));
+
+        let matches = config.apply(cli()).get_matches_from(["test", "--side", "white"]);
+        assert_eq!(matches.get_one::<String>("side").map(String::as_str), Some("white"));
+    }
+}
@@ -0,0 +1,328 @@
+//! Runtime keyboard controls
+//!
+//! Lets the user tweak settings while the main loop is running instead of restarting
+//! (and losing game context) just to change one knob.
+//!
+//! Stdin is line-buffered, so each command is a single key followed by Enter:
+//! - `d` / `D`: decrease / increase engine search depth
+//! - `m`: toggle MultiPV (show alternative candidate moves)
+//! - `o`: switch OCR mode (native ↔ llm)
+//! - `s`: swap sides (white ↔ black)
+//! - `c <square> [piece]`: correct one square of the last recognized board
+//!   (e.g. `c e4 N`, `c e4 .` to clear, or `c e4` to cycle through pieces)
+//! - `move <move>`: play a move on the last board, e.g. the opponent's reply (`move e7e5`)
+//! - `fen <FEN>`: replace the last board with a typed position
+//! - `back` / `fwd`: step through the moves explored with `move` (what-if lines)
+//! - `line`: print the explored moves as PGN, variations included
+//! - `snapshot <name>` / `b`: save the last analysis for later (see `snapshots`)
+//! - `?` / `h`: show the key help
+//! - empty line (just Enter): trigger a capture in manual mode
+//!
+//! A dedicated thread owns stdin and forwards parsed commands over a channel, so the
+//! async main loop never blocks on keyboard input. The TUI reads keys itself (raw mode)
+//! and sends the same commands, so it takes the channel without the stdin thread.
+
+use crate::ocr::OcrMode;
+use crate::prompt;
+use crate::PlayerSide;
+use std::io::BufRead;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// Shallowest depth the user can dial down to
+pub const MIN_DEPTH: u16 = 1;
+/// Deepest depth the user can dial up to (tanton gets very slow beyond this; UCI engines
+/// get `engine::backend_depth` of it)
+pub const MAX_DEPTH: u16 = 12;
+
+/// Candidate moves listed with MultiPV on (`--multipv` picks another count in this range)
+pub const DEFAULT_MULTIPV: usize = 3;
+pub const MIN_MULTIPV: usize = 2;
+pub const MAX_MULTIPV: usize = 5;
+
+/// One-line key help shown in the banner and on `?`
+pub const HELP_LINE: &str = "Keys (+Enter): d/D depth -/+, m MultiPV, o OCR mode, s swap side, \
+    c <sq> [piece] fix square, move <move>, fen <FEN>, back/fwd/line explore, b/snapshot <name> bookmark, heat square control, ? help";
+
+/// A command typed by the user while the loop is running
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ControlCommand {
+    /// Empty line: capture now (manual trigger)
+    Capture,
+    DepthDown,
+    DepthUp,
+    ToggleMultiPv,
+    ToggleOcrMode,
+    SwapSide,
+    /// Fix one square of the last recognized board (0-based file/rank).
+    /// `piece` is None to cycle to the next piece, Some('1') to clear the square.
+    Correct { file: u8, rank: u8, piece: Option<char> },
+    /// Play a move (any notation the engine accepts) on the last recognized board
+    PlayMove(String),
+    /// Replace the last recognized board with a typed FEN
+    SetFen(String),
+    /// Step back / forward through the explored moves
+    Back,
+    Forward,
+    /// Show the explored moves as PGN
+    ShowLine,
+    /// Save the last analysis under a name (empty: unnamed)
+    Snapshot(String),
+    /// Show who controls each square of the last recognized board
+    Heatmap,
+    Help,
+}
+
+impl ControlCommand {
+    /// Parses a single line of keyboard input. Returns None for unknown keys.
+    pub fn parse(line: &str) -> Option<Self> {
+        match line.trim() {
+            "" => Some(ControlCommand::Capture),
+            "d" => Some(ControlCommand::DepthDown),
+            "D" => Some(ControlCommand::DepthUp),
+            "m" | "M" => Some(ControlCommand::ToggleMultiPv),
+            "o" | "O" => Some(ControlCommand::ToggleOcrMode),
+            "s" | "S" => Some(ControlCommand::SwapSide),
+            "?" | "h" | "H" => Some(ControlCommand::Help),
+            "back" => Some(ControlCommand::Back),
+            "fwd" => Some(ControlCommand::Forward),
+            "line" => Some(ControlCommand::ShowLine),
+            "heat" | "heatmap" => Some(ControlCommand::Heatmap),
+            "b" | "snapshot" => Some(ControlCommand::Snapshot(String::new())),
+            other => {
+                if let Some(text) = other.strip_prefix("move ") {
+                    return Some(ControlCommand::PlayMove(text.trim().to_string()));
+                }
+                if let Some(name) = other.strip_prefix("snapshot ") {
+                    return Some(ControlCommand::Snapshot(name.trim().to_string()));
+                }
+                if let Some(fen) = other.strip_prefix("fen ") {
+                    return Some(ControlCommand::SetFen(fen.trim().to_string()));
+                }
+                Self::parse_correction(other)
+            }
+        }
+    }
+
+    /// Parses `c <square> [piece]`, where piece is one of KQRBNPkqrbnp or '.' for empty
+    fn parse_correction(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        if parts.next()? != "c" {
+            return None;
+        }
+        let (file, rank) = crate::correction::parse_square(parts.next()?)?;
+        let piece = match parts.next() {
+            None => None,
+            Some(".") => Some('1'),
+            Some(p) if p.len() == 1 && "KQRBNPkqrbnp".contains(p) => p.chars().next(),
+            Some(_) => return None,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(ControlCommand::Correct { file, rank, piece })
+    }
+}
+
+/// Settings that can change between cycles without restarting
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RuntimeSettings {
+    pub depth: u16,
+    pub multipv: bool,
+    /// Candidate moves listed while `multipv` is on
+    pub multipv_lines: usize,
+    pub ocr_mode: OcrMode,
+    pub player_side: PlayerSide,
+}
+
+impl RuntimeSettings {
+    /// Applies a settings command and returns a short status message for the user.
+    /// Returns None for commands that don't change settings (capture/help).
+    pub fn apply(&mut self, command: ControlCommand) -> Option<String> {
+        match command {
+            ControlCommand::Capture
+            | ControlCommand::Help
+            | ControlCommand::Correct { .. }
+            | ControlCommand::PlayMove(_)
+            | ControlCommand::SetFen(_)
+            | ControlCommand::Back
+            | ControlCommand::Forward
+            | ControlCommand::ShowLine
+            | ControlCommand::Snapshot(_)
+            | ControlCommand::Heatmap => None,
+            ControlCommand::DepthDown => {
+                self.depth = self.depth.saturating_sub(1).max(MIN_DEPTH);
+                Some(format!("Depth: {}", self.depth))
+            }
+            ControlCommand::DepthUp => {
+                self.depth = (self.depth + 1).min(crate::engine::backend_depth(MAX_DEPTH));
+                Some(format!("Depth: {}", self.depth))
+            }
+            ControlCommand::ToggleMultiPv => {
+                self.multipv = !self.multipv;
+                Some(if self.multipv { format!("MultiPV: on ({} lines)", self.multipv_lines) } else { "MultiPV: off".to_string() })
+            }
+            ControlCommand::ToggleOcrMode => {
+                let next = match self.ocr_mode {
+                    OcrMode::Native => OcrMode::Llm,
+                    OcrMode::Llm | OcrMode::Custom(_) => OcrMode::Native,
+                };
+                // The key prompt can't run here (stdin belongs to the control thread)
+                if next == OcrMode::Llm && !crate::ocr::llm_available() {
+                    return Some("OCR mode unchanged: OPENAI_API_KEY not set".to_string());
+                }
+                self.ocr_mode = next;
+                Some(format!("OCR mode: {}", self.ocr_mode))
+            }
+            ControlCommand::SwapSide => {
+                self.player_side = match self.player_side {
+                    PlayerSide::White => PlayerSide::Black,
+                    PlayerSide::Black => PlayerSide::White,
+                };
+                Some(format!("Playing: {}", self.player_side))
+            }
+        }
+    }
+
+    /// Number of candidate moves to rank this cycle (0 with MultiPV off)
+    pub fn candidate_count(&self) -> usize {
+        if self.multipv { self.multipv_lines } else { 0 }
+    }
+}
+
+/// The command channel without a stdin reader, for inputs that read the keyboard
+/// themselves (the TUI)
+pub fn channel() -> (UnboundedSender<ControlCommand>, UnboundedReceiver<ControlCommand>) {
+    mpsc::unbounded_channel()
+}
+
+/// Spawns the stdin reader thread and returns both ends of its command channel; the
+/// sender lets other inputs (the capture hotkey) feed the same loop.
+/// Unknown keys are reported immediately and not forwarded. Lines typed while a prompt
+/// is waiting go to the prompt instead.
+pub fn spawn_listener() -> (UnboundedSender<ControlCommand>, UnboundedReceiver<ControlCommand>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let sender = tx.clone();
+
+    prompt::set_listening();
+    std::thread::spawn(move || {
+        let stdin = std::io::stdin();
+        for line in stdin.lock().lines() {
+            let Ok(line) = line else { break };
+            let Some(line) = prompt::offer_line(line) else { continue };
+            match ControlCommand::parse(&line) {
+                Some(command) => {
+                    if tx.send(command).is_err() {
+                        break; // Main loop has exited
+                    }
+                }
+                None => eprintln!("Unknown key '{}'. {}", line.trim(), HELP_LINE),
+            }
+        }
+    });
+
+    (sender, rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> RuntimeSettings {
+        RuntimeSettings {
+            depth: 6,
+            multipv: false,
+            multipv_lines: DEFAULT_MULTIPV,
+            ocr_mode: OcrMode::Native,
+            player_side: PlayerSide::White,
+        }
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(ControlCommand::parse(""), Some(ControlCommand::Capture));
+        assert_eq!(ControlCommand::parse("d\n"), Some(ControlCommand::DepthDown));
+        assert_eq!(ControlCommand::parse("D"), Some(ControlCommand::DepthUp));
+        assert_eq!(ControlCommand::parse(" m "), Some(ControlCommand::ToggleMultiPv));
+        assert_eq!(ControlCommand::parse("o"), Some(ControlCommand::ToggleOcrMode));
+        assert_eq!(ControlCommand::parse("s"), Some(ControlCommand::SwapSide));
+        assert_eq!(ControlCommand::parse("?"), Some(ControlCommand::Help));
+        assert_eq!(ControlCommand::parse("x"), None);
+    }
+
+    #[test]
+    fn test_parse_correction() {
+        assert_eq!(
+            ControlCommand::parse("c e4 N"),
+            Some(ControlCommand::Correct { file: 4, rank: 3, piece: Some('N') })
+        );
+        assert_eq!(
+            ControlCommand::parse("c a1 ."),
+            Some(ControlCommand::Correct { file: 0, rank: 0, piece: Some('1') })
+        );
+        assert_eq!(
+            ControlCommand::parse("c h8"),
+            Some(ControlCommand::Correct { file: 7, rank: 7, piece: None })
+        );
+        assert_eq!(ControlCommand::parse("c z9 N"), None);
+        assert_eq!(ControlCommand::parse("c e4 X"), None);
+        assert_eq!(ControlCommand::parse("c e4 N extra"), None);
+    }
+
+    #[test]
+    fn test_parse_typed_move_and_fen() {
+        assert_eq!(ControlCommand::parse("move e7e5"), Some(ControlCommand::PlayMove("e7e5".to_string())));
+        assert_eq!(
+            ControlCommand::parse("fen 8/8/8/8/8/8/8/K6k w - - 0 1"),
+            Some(ControlCommand::SetFen("8/8/8/8/8/8/8/K6k w - - 0 1".to_string()))
+        );
+        assert_eq!(ControlCommand::parse("move"), None);
+        assert_eq!(ControlCommand::parse("back"), Some(ControlCommand::Back));
+        assert_eq!(ControlCommand::parse("line"), Some(ControlCommand::ShowLine));
+        assert_eq!(ControlCommand::parse("heat"), Some(ControlCommand::Heatmap));
+        assert_eq!(
+            ControlCommand::parse("snapshot critical moment"),
+            Some(ControlCommand::Snapshot("critical moment".to_string()))
+        );
+        assert_eq!(ControlCommand::parse("b"), Some(ControlCommand::Snapshot(String::new())));
+    }
+
+    #[test]
+    fn test_depth_is_clamped() {
+        let mut s = settings();
+        s.depth = MIN_DEPTH;
+        s.apply(ControlCommand::DepthDown);
+        assert_eq!(s.depth, MIN_DEPTH);
+
+        s.depth = MAX_DEPTH;
+        s.apply(ControlCommand::DepthUp);
+        assert_eq!(s.depth, MAX_DEPTH);
+    }
+
+    #[test]
+    fn test_toggle_multipv_and_swap_side() {
+        let mut s = settings();
+        assert_eq!(s.candidate_count(), 0);
+        s.apply(ControlCommand::ToggleMultiPv);
+        assert!(s.multipv);
+        assert_eq!(s.candidate_count(), DEFAULT_MULTIPV);
+        s.apply(ControlCommand::SwapSide);
+        assert_eq!(s.player_side, PlayerSide::Black);
+        s.apply(ControlCommand::SwapSide);
+        assert_eq!(s.player_side, PlayerSide::White);
+    }
+
+    #[test]
+    fn test_llm_to_native_always_allowed() {
+        let mut s = settings();
+        s.ocr_mode = OcrMode::Llm;
+        s.apply(ControlCommand::ToggleOcrMode);
+        assert_eq!(s.ocr_mode, OcrMode::Native);
+    }
+
+    #[test]
+    fn test_capture_does_not_change_settings() {
+        let mut s = settings();
+        assert!(s.apply(ControlCommand::Capture).is_none());
+        assert_eq!(s, settings());
+    }
+}
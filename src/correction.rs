@@ -0,0 +1,199 @@
+//! Manual square correction
+//!
+//! Lets the user fix a single OCR mistake on the recognized board (e.g. a knight read
+//! as a bishop) without re-capturing: the square is edited in the last FEN, the
+//! corrected position is re-analyzed, and the square image is kept as a labeled
+//! training sample under `corrections/` in the site's templates. Native OCR matches squares
+//! against the newest samples too (see `ocr_native::load_templates`), so the same mistake
+//! isn't made on the next frames.
+//!
+//! Pieces use the same characters as the native OCR grid: 'K'..'p' for pieces and
+//! '1' for an empty square.
+
+use anyhow::{Context, Result};
+use crate::fen::serialize_placement;
+use image::{imageops, GenericImageView};
+use crate::PlayerSide;
+
+/// Order in which `cycle_piece` steps through square contents
+const PIECE_CYCLE: [char; 13] = ['1', 'P', 'N', 'B', 'R', 'Q', 'K', 'p', 'n', 'b', 'r', 'q', 'k'];
+
+/// Returns the next piece in the correction cycle (empty → P → N → ... → k → empty)
+pub fn cycle_piece(current: char) -> char {
+    let idx = PIECE_CYCLE.iter().position(|&c| c == current).unwrap_or(0);
+    PIECE_CYCLE[(idx + 1) % PIECE_CYCLE.len()]
+}
+
+/// Parses an algebraic square like "e4" into (file, rank), both 0-based (a1 = (0, 0))
+pub fn parse_square(square: &str) -> Option<(u8, u8)> {
+    let bytes = square.trim().as_bytes();
+    if bytes.len() != 2 {
+        return None;
+    }
+    let file = bytes[0].to_ascii_lowercase().checked_sub(b'a')?;
+    let rank = bytes[1].checked_sub(b'1')?;
+    (file < 8 && rank < 8).then_some((file, rank))
+}
+
+/// Formats a 0-based (file, rank) back to algebraic notation
+pub fn square_name(file: u8, rank: u8) -> String {
+    format!("{}{}", (b'a' + file) as char, rank + 1)
+}
+
+/// Returns the piece currently on a square of the FEN ('1' if empty)
+pub fn piece_at(fen: &str, file: u8, rank: u8) -> Result<char> {
+    let grid = parse_placement(fen)?;
+    Ok(grid[7 - rank as usize][file as usize])
+}
+
+/// Replaces the contents of one square in the FEN, keeping the remaining fields
+/// (turn, castling, ...) unchanged. `piece` is '1' to clear the square.
+/// The result is validated with shakmaty before being returned.
+pub fn set_square(fen: &str, file: u8, rank: u8, piece: char) -> Result<String> {
+    if !PIECE_CYCLE.contains(&piece) {
+        anyhow::bail!("Invalid piece '{}': expected one of KQRBNPkqrbnp or '.' for empty", piece);
+    }
+
+    let mut grid = parse_placement(fen)?;
+    grid[7 - rank as usize][file as usize] = piece;
+
+    let rest: Vec<&str> = fen.split_whitespace().skip(1).collect();
+    let corrected = if rest.is_empty() {
+        serialize_placement(&grid)
+    } else {
+        format!("{} {}", serialize_placement(&grid), rest.join(" "))
+    };
+
+    shakmaty::fen::Fen::from_ascii(corrected.as_bytes())
+        .map_err(|e| anyhow::anyhow!("Corrected FEN is invalid: {} (FEN: {})", e, corrected))?;
+
+    Ok(corrected)
+}
+
+/// Saves the image of a corrected square from the last cropped board as a training sample.
+/// Only available after native OCR (the LLM path never crops the board).
+/// Files are named with the template convention ({Piece}{Color}, or "empty") plus a timestamp,
+/// e.g. `templates/chesscom/corrections/NW_1732790000123.png` (inside the theme pack, if any).
+pub fn save_training_sample(site: &str, file: u8, rank: u8, piece: char, player_side: PlayerSide) -> Result<String> {
+    let board = crate::ocr_native::last_board().context("No cropped board available (training samples need native OCR)")?;
+
+    let (w, h) = board.dimensions();
+    let square_w = w / 8;
+    let square_h = h / 8;
+
+    // The cropped board is kept as shown on screen: flipped when playing Black
+    let (col, row) = if player_side.needs_board_flip() {
+        (7 - file as u32, rank as u32)
+    } else {
+        (file as u32, 7 - rank as u32)
+    };
+
+    let square = imageops::crop_imm(&board, col * square_w, row * square_h, square_w, square_h).to_image();
+
+    let dir = format!("{}/corrections", crate::ocr_native::template_dir(site));
+    std::fs::create_dir_all(&dir).context("Failed to create corrections directory")?;
+
+    let label = sample_label(piece);
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let path = format!("{}/{}_{}.png", dir, label, timestamp);
+    square.save(&path).with_context(|| format!("Failed to save training sample: {}", path))?;
+
+    Ok(path)
+}
+
+/// Maps a piece char to the template file label (KW, kb → KB, '1' → empty)
+fn sample_label(piece: char) -> String {
+    if piece == '1' {
+        return "empty".to_string();
+    }
+    let color = if piece.is_ascii_uppercase() { 'W' } else { 'B' };
+    format!("{}{}", piece.to_ascii_uppercase(), color)
+}
+
+/// Expands the piece placement field into an 8×8 grid (row 0 = rank 8)
+pub fn parse_placement(fen: &str) -> Result<[[char; 8]; 8]> {
+    let placement = fen.split_whitespace().next().unwrap_or("");
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        anyhow::bail!("FEN placement must have 8 ranks: '{}'", placement);
+    }
+
+    let mut grid = [['1'; 8]; 8];
+    for (row, rank_str) in ranks.iter().enumerate() {
+        let mut col = 0usize;
+        for c in rank_str.chars() {
+            if let Some(n) = c.to_digit(10) {
+                col += n as usize;
+            } else {
+                if col >= 8 {
+                    anyhow::bail!("FEN rank too long: '{}'", rank_str);
+                }
+                grid[row][col] = c;
+                col += 1;
+            }
+        }
+        if col != 8 {
+            anyhow::bail!("FEN rank must cover 8 squares: '{}'", rank_str);
+        }
+    }
+
+    Ok(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn test_parse_square() {
+        assert_eq!(parse_square("a1"), Some((0, 0)));
+        assert_eq!(parse_square("E4"), Some((4, 3)));
+        assert_eq!(parse_square("h8"), Some((7, 7)));
+        assert_eq!(parse_square("i1"), None);
+        assert_eq!(parse_square("a9"), None);
+        assert_eq!(parse_square("e"), None);
+    }
+
+    #[test]
+    fn test_square_name_roundtrip() {
+        assert_eq!(square_name(4, 3), "e4");
+        assert_eq!(parse_square(&square_name(6, 0)), Some((6, 0)));
+    }
+
+    #[test]
+    fn test_cycle_piece_wraps() {
+        assert_eq!(cycle_piece('1'), 'P');
+        assert_eq!(cycle_piece('K'), 'p');
+        assert_eq!(cycle_piece('k'), '1');
+    }
+
+    #[test]
+    fn test_set_square_places_piece() {
+        let fen = set_square(START_FEN, 4, 3, 'P').unwrap();
+        assert_eq!(fen, "rnbqkbnr/pppppppp/8/8/4P3/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    }
+
+    #[test]
+    fn test_set_square_clears_piece() {
+        let fen = set_square(START_FEN, 6, 0, '1').unwrap();
+        assert!(fen.starts_with("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKB1R "));
+        assert_eq!(piece_at(&fen, 6, 0).unwrap(), '1');
+    }
+
+    #[test]
+    fn test_set_square_rejects_unknown_piece() {
+        assert!(set_square(START_FEN, 0, 0, 'x').is_err());
+    }
+
+    #[test]
+    fn test_sample_label() {
+        assert_eq!(sample_label('N'), "NW");
+        assert_eq!(sample_label('q'), "QB");
+        assert_eq!(sample_label('1'), "empty");
+    }
+}
@@ -0,0 +1,158 @@
+//! Cost and latency estimate for the startup banner
+//!
+//! Before the first capture the banner shows roughly what a cycle will take and cost with
+//! the chosen model, detail, interval, and depth, so "gpt-4o at high detail every second"
+//! is seen to cost real money before it does. It is an upper bound: in auto mode every
+//! capture is assumed to be analyzed (unchanged boards are skipped in practice) and every
+//! answer to use its full token allowance. Prices come from `session_stats`.
+
+use crate::ocr_llm::RequestShape;
+
+/// Characters per prompt token (English text)
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Screenshot size assumed for image tokens: the capture width at 16:9
+const SCREEN: (u32, u32) = (1920, 1080);
+
+/// Image tokens of a low-detail image, and the base of a high-detail one
+const IMAGE_BASE_TOKENS: u64 = 85;
+
+/// Tokens per 512px tile of a high-detail image
+const IMAGE_TILE_TOKENS: u64 = 170;
+
+/// Typical vision request time at low and high detail
+const LLM_LOW_DETAIL_MS: u64 = 2000;
+const LLM_HIGH_DETAIL_MS: u64 = 4000;
+
+/// Typical screenshot time, and local (template, CNN, command) OCR time
+const CAPTURE_MS: u64 = 50;
+const LOCAL_OCR_MS: u64 = 100;
+
+/// What a cycle does with the chosen settings
+pub struct Setup {
+    /// LLM model, when the cycle calls one
+    pub model: String,
+    /// LLM requests per cycle (sent concurrently)
+    pub requests: Vec<RequestShape>,
+    /// OCR runs locally (no LLM request for the board)
+    pub local_ocr: bool,
+    /// Engine searches per cycle and the time of one (`engine::estimated_search_ms`)
+    pub searches: u64,
+    pub search_ms: u64,
+    /// Auto mode's interval; None when captures are triggered by hand
+    pub interval_ms: Option<u64>,
+}
+
+/// Estimated time and cost of a cycle
+#[derive(Debug, PartialEq)]
+pub struct Estimate {
+    pub cycle_ms: u64,
+    /// USD per cycle; None for a model without a known price
+    pub cycle_cost: Option<f64>,
+    /// Cycles per hour in auto mode
+    pub cycles_per_hour: Option<f64>,
+}
+
+impl Estimate {
+    /// Banner line, e.g. "~4.3s/cycle, ~$0.0049/cycle, up to ~$3.69/hour"
+    pub fn line(&self, setup: &Setup) -> String {
+        let mut parts = vec![format!("~{:.1}s/cycle", self.cycle_ms as f64 / 1000.0)];
+        match self.cycle_cost {
+            _ if setup.requests.is_empty() => parts.push("no API cost".to_string()),
+            Some(cost) => {
+                parts.push(format!("~${:.4}/cycle", cost));
+                if let Some(cycles) = self.cycles_per_hour {
+                    parts.push(format!("up to ~${:.2}/hour", cost * cycles));
+                }
+            }
+            None => parts.push(format!("cost unknown for {}", setup.model)),
+        }
+        parts.join(", ")
+    }
+}
+
+/// Estimates a cycle of `setup`
+pub fn estimate(setup: &Setup) -> Estimate {
+    let price = crate::session_stats::price(&setup.model);
+    let mut cycle_cost = Some(0.0);
+    let mut llm_ms = 0;
+    for request in &setup.requests {
+        let input = (request.prompt_chars / CHARS_PER_TOKEN) as u64 + image_tokens(request.detail, SCREEN);
+        let output = u64::from(request.max_output_tokens);
+        cycle_cost = cycle_cost.zip(price).map(|(cost, (per_input, per_output))| {
+            cost + (input as f64 * per_input + output as f64 * per_output) / 1_000_000.0
+        });
+        let request_ms = if request.detail == "low" { LLM_LOW_DETAIL_MS } else { LLM_HIGH_DETAIL_MS };
+        llm_ms = llm_ms.max(request_ms);
+    }
+    let ocr_ms = if setup.local_ocr { LOCAL_OCR_MS } else { 0 };
+    let cycle_ms = CAPTURE_MS + llm_ms.max(ocr_ms) + setup.searches * setup.search_ms;
+    let cycles_per_hour = setup.interval_ms.map(|interval| 3_600_000.0 / (cycle_ms + interval) as f64);
+    Estimate { cycle_ms, cycle_cost, cycles_per_hour }
+}
+
+/// OpenAI's image token count: low detail is flat; high (and auto, for a full screenshot)
+/// fits the image in 2048px, scales its short side to 768px, and counts 512px tiles
+fn image_tokens(detail: &str, (width, height): (u32, u32)) -> u64 {
+    if detail == "low" {
+        return IMAGE_BASE_TOKENS;
+    }
+    let fit = (2048.0 / f64::from(width.max(height))).min(1.0);
+    let (width, height) = (f64::from(width) * fit, f64::from(height) * fit);
+    let shrink = (768.0 / width.min(height)).min(1.0);
+    let tiles = ((width * shrink) / 512.0).ceil() * ((height * shrink) / 512.0).ceil();
+    IMAGE_BASE_TOKENS + IMAGE_TILE_TOKENS * tiles as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fen_request(detail: &'static str) -> RequestShape {
+        RequestShape { prompt_chars: 2000, detail, max_output_tokens: 100 }
+    }
+
+    #[test]
+    fn test_image_tokens() {
+        assert_eq!(image_tokens("low", (1920, 1080)), 85);
+        // 1365x768: 3x2 tiles
+        assert_eq!(image_tokens("high", (1920, 1080)), 85 + 170 * 6);
+        assert_eq!(image_tokens("high", (512, 512)), 85 + 170);
+    }
+
+    #[test]
+    fn test_gpt_4o_every_second() {
+        let setup = Setup {
+            model: "gpt-4o".to_string(),
+            requests: vec![fen_request("high")],
+            local_ocr: false,
+            searches: 1,
+            search_ms: 150,
+            interval_ms: Some(1000),
+        };
+        let estimate = estimate(&setup);
+        assert_eq!(estimate.cycle_ms, 4200);
+        // 500 + 1105 input tokens at $2.50/M, 100 output at $10/M
+        let cost = estimate.cycle_cost.unwrap();
+        assert!((cost - 0.0050125).abs() < 1e-9, "{}", cost);
+        assert_eq!(estimate.line(&setup), "~4.2s/cycle, ~$0.0050/cycle, up to ~$3.47/hour");
+    }
+
+    #[test]
+    fn test_local_ocr_and_unknown_models() {
+        let native = Setup {
+            model: String::new(),
+            requests: Vec::new(),
+            local_ocr: true,
+            searches: 1,
+            search_ms: 150,
+            interval_ms: None,
+        };
+        assert_eq!(estimate(&native).line(&native), "~0.3s/cycle, no API cost");
+
+        let local_llm = Setup { model: "llava".to_string(), requests: vec![fen_request("low")], ..native };
+        let estimate = estimate(&local_llm);
+        assert_eq!(estimate.cycle_cost, None);
+        assert_eq!(estimate.line(&local_llm), "~2.2s/cycle, cost unknown for llava");
+    }
+}
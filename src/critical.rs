@@ -0,0 +1,114 @@
+//! Critical moment detection
+//!
+//! Most moves of a game are routine; a few decide it. After each engine analysis the
+//! candidate moves are compared, and two kinds of position are flagged so the user spends
+//! their clock there:
+//! - **only move**: the best move is at least `ONLY_MOVE_GAP_CP` better than the next best
+//! - **sharp**: the position is balanced, but at most two moves keep it that way (the
+//!   third best is at least `SHARP_GAP_CP` worse)
+//!
+//! Detection needs the top three candidates, so they are computed every cycle (and shown
+//! only as far as MultiPV asks). `--no-critical` turns it off.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether positions are checked (cleared by --no-critical)
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Candidates needed to tell the kinds apart
+pub const CANDIDATES: usize = 3;
+
+/// Gap between best and second best that makes the best an only move
+const ONLY_MOVE_GAP_CP: i32 = 150;
+
+/// Evals within this of zero count as balanced
+const BALANCED_CP: i32 = 50;
+
+/// A move this much worse than the best no longer holds a balanced position
+const SHARP_GAP_CP: i32 = 100;
+
+/// Why a position deserves thinking time
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Critical {
+    /// Every other move is much worse; `next_best` is the second candidate's eval
+    OnlyMove { next_best: String },
+    /// Balanced, but only `holding` moves keep it so
+    Sharp { holding: usize },
+}
+
+impl Critical {
+    /// Machine-readable kind for JSON output
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Critical::OnlyMove { .. } => "only_move",
+            Critical::Sharp { .. } => "sharp",
+        }
+    }
+
+    /// What makes the position critical, e.g. "only move (next best -1.20)"
+    pub fn describe(&self) -> String {
+        match self {
+            Critical::OnlyMove { next_best } => format!("only move (next best {})", next_best),
+            Critical::Sharp { holding: 1 } => "sharp: one move holds the balance".to_string(),
+            Critical::Sharp { holding } => format!("sharp: only {} moves hold the balance", holding),
+        }
+    }
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Checks the ranked (move, eval) candidates, best first, of one analysis
+pub fn detect(candidates: &[(String, String)]) -> Option<Critical> {
+    let evals: Vec<i32> = candidates.iter().map_while(|(_, eval)| crate::advisor::parse_eval(eval)).collect();
+    let (&best, &second) = (evals.first()?, evals.get(1)?);
+    if best - second >= ONLY_MOVE_GAP_CP {
+        return Some(Critical::OnlyMove { next_best: candidates[1].1.clone() });
+    }
+    let third = *evals.get(2)?;
+    if best.abs() <= BALANCED_CP && best - third >= SHARP_GAP_CP {
+        let holding = evals.iter().filter(|&&eval| best - eval < SHARP_GAP_CP).count();
+        return Some(Critical::Sharp { holding });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(evals: &[&str]) -> Vec<(String, String)> {
+        evals.iter().enumerate().map(|(i, eval)| (format!("move{}", i), eval.to_string())).collect()
+    }
+
+    #[test]
+    fn test_only_move() {
+        assert_eq!(
+            detect(&candidates(&["+0.30", "-1.20", "-1.50"])),
+            Some(Critical::OnlyMove { next_best: "-1.20".to_string() })
+        );
+        // Mate or nothing
+        assert!(matches!(detect(&candidates(&["#3", "+2.00"])), Some(Critical::OnlyMove { .. })));
+        assert_eq!(detect(&candidates(&["+1.30", "0.00", "-0.20"])), None);
+    }
+
+    #[test]
+    fn test_sharp_balanced_positions() {
+        assert_eq!(detect(&candidates(&["+0.10", "-0.20", "-1.40"])), Some(Critical::Sharp { holding: 2 }));
+        // Not balanced: a winning side has many ways to stay winning
+        assert_eq!(detect(&candidates(&["+3.00", "+2.80", "+1.60"])), None);
+        // Quiet: plenty of moves hold
+        assert_eq!(detect(&candidates(&["+0.10", "0.00", "-0.20"])), None);
+    }
+
+    #[test]
+    fn test_needs_two_candidates() {
+        assert_eq!(detect(&candidates(&["+0.30"])), None);
+        assert_eq!(detect(&candidates(&["Stalemate", "+0.30"])), None);
+    }
+}
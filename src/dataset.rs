@@ -0,0 +1,206 @@
+//! Labeled screenshot dataset
+//!
+//! With `--collect-dataset`, the screenshot behind every analyzed position is archived
+//! next to its session (`sessions/<session>/<timestamp>-<cycle ID>.jpg`, the cycle ID
+//! matching the session entry and JSON output). A session whose history is fully
+//! consistent - every recognized position follows from the previous one by legal moves -
+//! can be trusted as ground truth: its frames are copied to `dataset/images/` and each is
+//! paired with the verified FEN and the move played from it in
+//! `dataset/labels.jsonl`, turning everyday use into training data for the OCR backends.
+//! Sessions with a misread (a position that doesn't connect) are left out entirely.
+//!
+//! Labeling runs automatically for the previous session when a live run starts.
+
+use anyhow::{Context, Result};
+use crate::capture;
+use crate::pgn;
+use crate::session::{self, SessionEntry};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::{EnPassantMode, Position};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Directory holding the labeled dataset
+pub const DATASET_DIR: &str = "dataset";
+
+/// Whether frames are archived and labeled (set by --collect-dataset)
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// One labeled frame, serialized as a JSON line in `dataset/labels.jsonl`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Label {
+    /// Session file name the frame came from
+    pub session: String,
+    /// Image file (relative to `dataset/`)
+    pub image: String,
+    /// Verified position, with castling rights and move counters from the game
+    pub fen: String,
+    /// Side shown at the bottom of the board
+    pub orientation: String,
+    /// Move played from this position in SAN (None for the last frame or an unchanged board)
+    #[serde(rename = "move", default, skip_serializing_if = "Option::is_none")]
+    pub next_move: Option<String>,
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Stores a screenshot in the session's frame directory (encoded at archive quality).
+/// Returns the path of the archived frame.
+pub fn archive_frame(session: &Path, timestamp_ms: u128, cycle: &str, image: &DynamicImage) -> Result<String> {
+    let dir = session.with_extension("");
+    std::fs::create_dir_all(&dir).context("Failed to create frame directory")?;
+    let path = dir.join(format!("{}-{}.jpg", timestamp_ms, cycle));
+    std::fs::write(&path, capture::encode(image, capture::Consumer::Archive)?).context("Failed to archive frame")?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Verified FEN and next move (SAN) for every entry, or None when the history isn't
+/// consistent (an illegal position or one not reachable from the previous)
+pub fn verify(entries: &[SessionEntry]) -> Option<Vec<(String, Option<String>)>> {
+    let mut position = pgn::parse_position(&entries.first()?.fen)?;
+    let mut labels = Vec::with_capacity(entries.len());
+    for pair in entries.windows(2) {
+        let fen = Fen::from_position(&position, EnPassantMode::Legal).to_string();
+        let target = pgn::parse_position(&pair[1].fen)?;
+        if position.board() == target.board() {
+            labels.push((fen, None));
+            continue;
+        }
+        let moves = pgn::connect(&position, target.board())?;
+        let mut next_move = None;
+        for m in moves {
+            let san = SanPlus::from_move_and_play_unchecked(&mut position, m).to_string();
+            next_move.get_or_insert(san);
+        }
+        labels.push((fen, next_move));
+    }
+    labels.push((Fen::from_position(&position, EnPassantMode::Legal).to_string(), None));
+    Some(labels)
+}
+
+/// Labels the most recent session if it is consistent and not labeled yet, logging
+/// instead of failing (a dataset problem must not stop the live run)
+pub fn label_last_or_warn() {
+    let Ok(path) = session::resolve("last") else {
+        return;
+    };
+    match label_session(&path) {
+        Ok(Some(count)) if count > 0 => eprintln!("Dataset: labeled {} frames from {}", count, path.display()),
+        Ok(Some(_)) => {}
+        Ok(None) => eprintln!("Dataset: {} has an inconsistent history, frames not labeled", path.display()),
+        Err(e) => eprintln!("⚠ Dataset not updated: {:#}", e),
+    }
+}
+
+/// Adds a session's archived frames to the dataset. Returns the number of labeled
+/// frames, or None when the session's history is inconsistent.
+pub fn label_session(path: &Path) -> Result<Option<usize>> {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    if already_labeled(&name)? {
+        return Ok(Some(0));
+    }
+    let entries = session::load(path)?;
+    if entries.iter().all(|e| e.frame.is_none()) {
+        return Ok(Some(0));
+    }
+    let Some(verified) = verify(&entries) else {
+        return Ok(None);
+    };
+
+    let images_dir = format!("{}/images", DATASET_DIR);
+    std::fs::create_dir_all(&images_dir).context("Failed to create dataset directory")?;
+    let mut manifest = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(format!("{}/labels.jsonl", DATASET_DIR))
+        .context("Failed to open dataset labels")?;
+
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut count = 0;
+    for (entry, (fen, next_move)) in entries.iter().zip(verified) {
+        let Some(frame) = &entry.frame else {
+            continue;
+        };
+        let image = format!("images/{}_{}.jpg", stem, entry.timestamp_ms);
+        if let Err(e) = std::fs::copy(frame, format!("{}/{}", DATASET_DIR, image)) {
+            eprintln!("⚠ Skipping frame {}: {}", frame, e);
+            continue;
+        }
+        let orientation = if entry.fen.split(' ').nth(1) == Some("b") { "black" } else { "white" };
+        let label = Label {
+            session: name.clone(),
+            image,
+            fen,
+            orientation: orientation.to_string(),
+            next_move,
+        };
+        let line = serde_json::to_string(&label).context("Failed to serialize label")?;
+        writeln!(manifest, "{}", line).context("Failed to write dataset labels")?;
+        count += 1;
+    }
+    Ok(Some(count))
+}
+
+/// True when the manifest already holds frames from this session
+fn already_labeled(session: &str) -> Result<bool> {
+    let content = match std::fs::read_to_string(format!("{}/labels.jsonl", DATASET_DIR)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e).context("Failed to read dataset labels"),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Label>(line).ok())
+        .any(|label| label.session == session))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(fen: &str) -> SessionEntry {
+        SessionEntry {
+            cycle: None,
+            timestamp_ms: 0,
+            captured_ms: None,
+            fen: fen.to_string(),
+            best_move: String::new(),
+            evaluation: "+0.00".to_string(),
+            comment: None,
+            frame: None,
+        }
+    }
+
+    #[test]
+    fn test_consistent_history_is_labeled_with_moves() {
+        let entries = [
+            entry("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1"),
+            entry("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w - - 0 1"),
+            entry("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w - - 0 1"),
+        ];
+        let labels = verify(&entries).unwrap();
+        assert_eq!(labels[0], ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string(), Some("e4".to_string())));
+        assert_eq!(labels[1], ("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2".to_string(), None));
+        assert_eq!(labels.len(), 3);
+    }
+
+    #[test]
+    fn test_disconnected_history_is_rejected() {
+        let entries = [
+            entry("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1"),
+            entry("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKB1R w - - 0 1"),
+        ];
+        assert!(verify(&entries).is_none());
+    }
+}
@@ -0,0 +1,217 @@
+//! Long-running deep analysis of a single position
+//!
+//! `zugzwang-rs deep --fen <FEN> --hours 2 --engine-path <stockfish>` hands the position to
+//! an external UCI engine with MultiPV enabled and lets it think for hours. The current
+//! best lines are checkpointed to `deep/<timestamp>/checkpoint.json` every few minutes (so
+//! an interrupted run still leaves results), and a readable `report.txt` is written when
+//! the time is up.
+
+use anyhow::{Context, Result};
+use crate::uci::{self, InfoLine, UciEngine};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Settings for one deep analysis run
+#[derive(Clone, Debug)]
+pub struct DeepJob {
+    pub fen: String,
+    pub duration: Duration,
+    pub engine_path: String,
+    pub multipv: usize,
+    pub threads: usize,
+    pub hash_mb: usize,
+    pub checkpoint_every: Duration,
+}
+
+/// One analyzed line as written to checkpoints
+#[derive(Clone, Debug, Serialize)]
+struct LineSnapshot {
+    rank: usize,
+    depth: u32,
+    score: String,
+    /// First move in readable form ("E2 to E4")
+    best_move: String,
+    pv: Vec<String>,
+}
+
+/// Checkpoint file contents
+#[derive(Debug, Serialize)]
+struct Checkpoint<'a> {
+    fen: &'a str,
+    engine: &'a str,
+    elapsed_secs: u64,
+    finished: bool,
+    lines: Vec<LineSnapshot>,
+}
+
+/// Runs the job until its duration elapses, checkpointing along the way.
+/// Returns the directory holding the checkpoint and report.
+pub async fn run(job: &DeepJob) -> Result<String> {
+    shakmaty::fen::Fen::from_ascii(job.fen.as_bytes()).map_err(|e| anyhow::anyhow!("Invalid FEN: {}", e))?;
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let dir = format!("deep/{}", timestamp);
+    std::fs::create_dir_all(&dir).context("Failed to create deep analysis directory")?;
+
+    let mut engine = UciEngine::spawn(&job.engine_path).await?;
+    engine.set_option("MultiPV", &job.multipv.to_string()).await?;
+    engine.set_option("Threads", &job.threads.to_string()).await?;
+    engine.set_option("Hash", &job.hash_mb.to_string()).await?;
+    engine.ready().await?;
+    engine.position(&job.fen).await?;
+    engine.send("go infinite").await?;
+
+    println!(
+        "Deep analysis with {} for {:.1}h (MultiPV {}), writing to {}/",
+        engine.name,
+        job.duration.as_secs_f64() / 3600.0,
+        job.multipv,
+        dir
+    );
+
+    let start = Instant::now();
+    let deadline = tokio::time::Instant::now() + job.duration;
+    let mut next_checkpoint = tokio::time::Instant::now() + job.checkpoint_every;
+    // Latest line per MultiPV slot
+    let mut lines: BTreeMap<usize, InfoLine> = BTreeMap::new();
+
+    loop {
+        let wake = next_checkpoint.min(deadline);
+        tokio::select! {
+            line = engine.read_line() => {
+                let line = line?.context("Engine exited during analysis")?;
+                if let Some(info) = uci::parse_info(&line) {
+                    lines.insert(info.multipv, info);
+                }
+            }
+            _ = tokio::time::sleep_until(wake) => {
+                if tokio::time::Instant::now() >= deadline {
+                    break;
+                }
+                write_checkpoint(&dir, job, &engine.name, &lines, start.elapsed(), false)?;
+                if let Some(best) = lines.get(&1) {
+                    println!(
+                        "[{}m] depth {}: {} ({})",
+                        start.elapsed().as_secs() / 60,
+                        best.depth,
+                        readable_first_move(best),
+                        best.score
+                    );
+                }
+                next_checkpoint += job.checkpoint_every;
+            }
+        }
+    }
+
+    // Stop and collect any final info lines before bestmove
+    engine.send("stop").await?;
+    while let Some(line) = engine.read_line().await? {
+        if line.starts_with("bestmove") {
+            break;
+        }
+        if let Some(info) = uci::parse_info(&line) {
+            lines.insert(info.multipv, info);
+        }
+    }
+    let engine_name = engine.name.clone();
+    engine.quit().await;
+
+    write_checkpoint(&dir, job, &engine_name, &lines, start.elapsed(), true)?;
+    let report = format_report(job, &engine_name, &lines, start.elapsed());
+    std::fs::write(format!("{}/report.txt", dir), &report).context("Failed to write report")?;
+    println!();
+    println!("{}", report);
+    Ok(dir)
+}
+
+/// Saves the current lines to `checkpoint.json` (overwritten each time)
+fn write_checkpoint(
+    dir: &str,
+    job: &DeepJob,
+    engine_name: &str,
+    lines: &BTreeMap<usize, InfoLine>,
+    elapsed: Duration,
+    finished: bool,
+) -> Result<()> {
+    let checkpoint = Checkpoint {
+        fen: &job.fen,
+        engine: engine_name,
+        elapsed_secs: elapsed.as_secs(),
+        finished,
+        lines: snapshots(lines),
+    };
+    let content = serde_json::to_string_pretty(&checkpoint).context("Failed to serialize checkpoint")?;
+    std::fs::write(format!("{}/checkpoint.json", dir), content).context("Failed to write checkpoint")
+}
+
+fn snapshots(lines: &BTreeMap<usize, InfoLine>) -> Vec<LineSnapshot> {
+    lines
+        .values()
+        .map(|info| LineSnapshot {
+            rank: info.multipv,
+            depth: info.depth,
+            score: info.score.to_string(),
+            best_move: readable_first_move(info),
+            pv: info.pv.clone(),
+        })
+        .collect()
+}
+
+fn readable_first_move(info: &InfoLine) -> String {
+    info.pv.first().map(|mv| crate::engine::format_move_readable(mv)).unwrap_or_default()
+}
+
+/// Human-readable final report
+fn format_report(job: &DeepJob, engine_name: &str, lines: &BTreeMap<usize, InfoLine>, elapsed: Duration) -> String {
+    let mut out = vec![
+        "Deep analysis report".to_string(),
+        format!("FEN:     {}", job.fen),
+        format!("Engine:  {} (Threads {}, Hash {} MB)", engine_name, job.threads, job.hash_mb),
+        format!("Time:    {}h {:02}m", elapsed.as_secs() / 3600, elapsed.as_secs() / 60 % 60),
+        String::new(),
+    ];
+    if lines.is_empty() {
+        out.push("No lines reported by the engine.".to_string());
+    }
+    for snapshot in snapshots(lines) {
+        out.push(format!(
+            "{}. {} ({}) depth {}",
+            snapshot.rank, snapshot.best_move, snapshot.score, snapshot.depth
+        ));
+        out.push(format!("   {}", snapshot.pv.join(" ")));
+    }
+    out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uci::Score;
+
+    #[test]
+    fn test_report_lists_lines_in_rank_order() {
+        let job = DeepJob {
+            fen: "8/8/8/8/8/8/8/K6k w - - 0 1".to_string(),
+            duration: Duration::from_secs(7200),
+            engine_path: "stockfish".to_string(),
+            multipv: 2,
+            threads: 4,
+            hash_mb: 256,
+            checkpoint_every: Duration::from_secs(300),
+        };
+        let mut lines = BTreeMap::new();
+        lines.insert(2, InfoLine { depth: 30, multipv: 2, score: Score::Cp(0), pv: vec!["a1b1".to_string()] });
+        lines.insert(1, InfoLine { depth: 31, multipv: 1, score: Score::Cp(5), pv: vec!["a1a2".to_string(), "h1g2".to_string()] });
+
+        let report = format_report(&job, "Stockfish 17", &lines, Duration::from_secs(3725));
+        assert!(report.contains("Time:    1h 02m"));
+        let first = report.find("1. A1 to A2 (+0.05) depth 31").unwrap();
+        let second = report.find("2. A1 to B1 (+0.00) depth 30").unwrap();
+        assert!(first < second);
+        assert!(report.contains("   a1a2 h1g2"));
+    }
+}
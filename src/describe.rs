@@ -0,0 +1,88 @@
+//! Plain-language position descriptions for screen readers
+//!
+//! With `--describe`, each recognized position is also printed as a sentence listing every
+//! piece by square, e.g. "White: king g1, rooks a1 f1, pawns f2 g2 h2. Black: king g8,
+//! pawns f7 g7 h7. Black to move." - readable by screen readers and text-to-speech,
+//! unlike a FEN string.
+
+use anyhow::Result;
+
+/// Piece kinds in the order they are announced, with singular and plural names
+const PIECE_NAMES: [(char, &str, &str); 6] = [
+    ('k', "king", "kings"),
+    ('q', "queen", "queens"),
+    ('r', "rook", "rooks"),
+    ('b', "bishop", "bishops"),
+    ('n', "knight", "knights"),
+    ('p', "pawn", "pawns"),
+];
+
+/// Describes the position of a FEN in plain language, side by side and piece by piece.
+/// Squares are listed from White's first rank upward, a-file to h-file.
+pub fn describe_position(fen: &str) -> Result<String> {
+    let grid = crate::correction::parse_placement(fen)?;
+
+    let mut sides = Vec::new();
+    for (side, is_white) in [("White", true), ("Black", false)] {
+        let groups: Vec<String> = PIECE_NAMES
+            .iter()
+            .filter_map(|&(kind, singular, plural)| {
+                let piece = if is_white { kind.to_ascii_uppercase() } else { kind };
+                let squares = squares_of(&grid, piece);
+                match squares.len() {
+                    0 => None,
+                    1 => Some(format!("{} {}", singular, squares[0])),
+                    _ => Some(format!("{} {}", plural, squares.join(" "))),
+                }
+            })
+            .collect();
+
+        let listing = if groups.is_empty() { "no pieces".to_string() } else { groups.join(", ") };
+        sides.push(format!("{}: {}.", side, listing));
+    }
+
+    let to_move = match fen.split_whitespace().nth(1) {
+        Some("b") => " Black to move.",
+        Some("w") => " White to move.",
+        _ => "",
+    };
+
+    Ok(format!("{}{}", sides.join(" "), to_move))
+}
+
+/// Squares holding `piece`, from rank 1 upward and a-file to h-file
+fn squares_of(grid: &[[char; 8]; 8], piece: char) -> Vec<String> {
+    let mut squares = Vec::new();
+    for rank in 0..8u8 {
+        for file in 0..8u8 {
+            if grid[7 - rank as usize][file as usize] == piece {
+                squares.push(crate::correction::square_name(file, rank));
+            }
+        }
+    }
+    squares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_start_position() {
+        let text = describe_position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert!(text.starts_with("White: king e1, queen d1, rooks a1 h1, bishops c1 f1, knights b1 g1, pawns a2 b2"));
+        assert!(text.contains("Black: king e8, queen d8, rooks a8 h8"));
+        assert!(text.ends_with("White to move."));
+    }
+
+    #[test]
+    fn test_describe_sparse_position() {
+        let text = describe_position("6k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert_eq!(text, "White: king g1. Black: king g8, pawns f7 g7 h7. Black to move.");
+    }
+
+    #[test]
+    fn test_describe_rejects_bad_fen() {
+        assert!(describe_position("not a fen").is_err());
+    }
+}
@@ -1,14 +1,221 @@
 //! Engine module
 //! Uses `tanton` pure-Rust chess engine for move calculation (actively maintained fork of Pleco (~2900 ELO))
 //! Pipeline: FEN string → Board → Search → (best_move, evaluation)
+//!
+//! With `--engine stockfish --engine-path <binary>`, searches go to an external UCI engine
+//! instead (`EngineBackend::Uci`). The engine process is started once and owned by a worker
+//! thread, so callers keep the same synchronous API; tanton still handles move parsing,
+//! legality, and terminal positions.
+//!
+//! With a progress callback set (`set_progress`), the main search reports each depth it
+//! completes - eval and current line - before the final answer, so a GUI can show a live
+//! eval. Side searches (single-move checks, scoring) don't report.
+//!
+//! UCI engines are external processes, so they need the desktop feature; without it (e.g.
+//! the wasm32 build) tanton is the only backend.
+//!
+//! `analyze_cancellable` runs a search on tokio's blocking pool so it doesn't stall the
+//! runtime, and can be stopped through a `CancelToken` once the position is stale: it then
+//! answers with the best move of the deepest finished depth (tanton) or sends `stop` and
+//! takes the engine's lines so far (UCI).
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use crate::uci::{InfoLine, Score};
+#[cfg(feature = "desktop")]
+use crate::uci::UciEngine;
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::{CastlingMode, Chess};
+#[cfg(feature = "desktop")]
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
 use tanton::Board;
 use tanton::bots::IterativeSearcher;
+use tanton::bots::alphabeta::alpha_beta_search;
 use tanton::tools::Searcher;
 
-/// Analyzes a chess position from FEN notation
-pub fn analyze_position(fen: &str) -> Result<(String, String)> {
+/// Default search depth (depth 12 was causing hangs; 6 keeps cycles responsive)
+pub const DEFAULT_DEPTH: u16 = 6;
+
+/// Typical tanton search time at `DEFAULT_DEPTH` (see `estimated_search_ms`)
+const SEARCH_MS_AT_DEFAULT: u64 = 150;
+
+/// How often a waiting search checks whether it was cancelled
+#[cfg(feature = "desktop")]
+const CANCEL_POLL: std::time::Duration = std::time::Duration::from_millis(20);
+
+/// Extra plies for UCI engines: depths are tuned for tanton, and a real engine reaches
+/// far deeper in the same time
+const UCI_DEPTH_OFFSET: u16 = 10;
+
+/// Which engine searches positions
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum EngineBackend {
+    /// Built-in pure-Rust search
+    #[default]
+    Tanton,
+    /// External engine speaking UCI over stdin/stdout
+    Uci(UciConfig),
+}
+
+/// External engine binary and its options
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UciConfig {
+    pub path: String,
+    pub threads: usize,
+    pub hash_mb: usize,
+    /// Strength limit ("Skill Level" 0-20), None for full strength
+    pub skill: Option<u8>,
+}
+
+/// Selected backend (tanton when unset)
+static BACKEND: OnceLock<EngineBackend> = OnceLock::new();
+
+/// Worker thread owning the UCI engine process
+static UCI_WORKER: OnceLock<Mutex<Sender<UciRequest>>> = OnceLock::new();
+
+/// Name reported by the UCI engine
+static UCI_NAME: OnceLock<String> = OnceLock::new();
+
+/// Thread cap for the engine while the game window is focused (0: none)
+static THREAD_CAP: AtomicUsize = AtomicUsize::new(0);
+
+/// Pool for tanton searches under the thread cap, with its size; tanton otherwise uses
+/// rayon's global pool (one thread per core)
+static CAPPED_POOL: Mutex<Option<(usize, Arc<rayon::ThreadPool>)>> = Mutex::new(None);
+
+/// Receives search progress (unset: no progress is reported)
+static PROGRESS: OnceLock<fn(&Progress)> = OnceLock::new();
+
+/// A depth reached by the main search of a position
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub fen: String,
+    pub depth: u32,
+    /// From the side to move's perspective ("+0.35", "#4")
+    pub eval: String,
+    /// Current best line, readable ("E2 to E4"); just the best move with tanton
+    pub line: Vec<String>,
+}
+
+/// One search for the UCI worker
+#[cfg_attr(not(feature = "desktop"), allow(dead_code))]
+struct UciRequest {
+    fen: String,
+    depth: u16,
+    multipv: usize,
+    /// Restricts the search to this move (UCI notation)
+    only_move: Option<String>,
+    /// Report each new depth of MultiPV slot 1 to the progress callback
+    progress: bool,
+    /// Sends `stop` once cancelled
+    cancel: Option<CancelToken>,
+    reply: Sender<Result<Vec<InfoLine>>>,
+}
+
+/// Stops a search once its position is stale. Clones share the flag, so one can go with
+/// the search while the caller keeps another.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<CancelState>);
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    /// Move and eval of the deepest depth tanton finished so far
+    best: Mutex<Option<(String, String)>>,
+}
+
+impl CancelToken {
+    /// Asks the search to stop
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Move and eval of the deepest finished depth (tanton only)
+    pub fn best_so_far(&self) -> Option<(String, String)> {
+        self.0.best.lock().ok()?.clone()
+    }
+
+    fn record(&self, best: (String, String)) {
+        if let Ok(mut slot) = self.0.best.lock() {
+            *slot = Some(best);
+        }
+    }
+
+    /// Returns once `cancel` was called
+    #[cfg(feature = "desktop")]
+    async fn cancelled(&self) {
+        while !self.is_cancelled() {
+            tokio::time::sleep(CANCEL_POLL).await;
+        }
+    }
+}
+
+/// Selects the engine (first call wins). A UCI engine is started right away so a wrong
+/// path or non-UCI binary fails at startup rather than on the first position.
+pub fn set_backend(backend: EngineBackend) -> Result<()> {
+    if let EngineBackend::Uci(config) = &backend {
+        let (sender, name) = start_uci(config.clone())?;
+        let _ = UCI_NAME.set(name);
+        let _ = UCI_WORKER.set(Mutex::new(sender));
+    }
+    let _ = BACKEND.set(backend);
+    Ok(())
+}
+
+/// Sets the callback for search progress (first call wins). It runs on the searching
+/// thread, so it should only hand the event on.
+pub fn set_progress(callback: fn(&Progress)) {
+    let _ = PROGRESS.set(callback);
+}
+
+/// Caps the engine's threads from the next search on; None restores `--engine-threads`
+/// (UCI) or all cores (tanton)
+pub fn set_thread_cap(cap: Option<usize>) {
+    THREAD_CAP.store(cap.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Rough time of one search at `depth` in milliseconds, for the startup estimate: tanton
+/// takes about `SEARCH_MS_AT_DEFAULT` at the default depth, about three times as long per
+/// extra ply (UCI depths are offset to take about as long)
+pub fn estimated_search_ms(depth: u16) -> u64 {
+    let depth = if uses_uci() { depth.saturating_sub(UCI_DEPTH_OFFSET) } else { depth };
+    let plies = i32::from(depth) - i32::from(DEFAULT_DEPTH);
+    (SEARCH_MS_AT_DEFAULT as f64 * 3f64.powi(plies)).round() as u64
+}
+
+/// True when searches go to an external UCI engine
+pub fn uses_uci() -> bool {
+    matches!(BACKEND.get(), Some(EngineBackend::Uci(_)))
+}
+
+/// Display name of the selected engine
+pub fn name() -> String {
+    match UCI_NAME.get() {
+        Some(name) if uses_uci() => name.clone(),
+        _ => "Tanton".to_string(),
+    }
+}
+
+/// Converts a depth on tanton's scale (defaults, presets, key limits) to the selected engine's
+pub fn backend_depth(depth: u16) -> u16 {
+    if uses_uci() { depth + UCI_DEPTH_OFFSET } else { depth }
+}
+
+/// Analyzes a chess position from FEN notation, searching to `depth` plies
+pub fn analyze_position(fen: &str, depth: u16) -> Result<(String, String)> {
+    search_position(fen, depth, None)
+}
+
+/// `analyze_position`, stopping early when `cancel` fires: tanton after the depth it is on,
+/// with that depth's answer; a UCI engine at once, with its line so far
+fn search_position(fen: &str, depth: u16, cancel: Option<&CancelToken>) -> Result<(String, String)> {
     use std::io::Write;
 
     eprint!("Engine analysis... ");
@@ -16,8 +223,7 @@ pub fn analyze_position(fen: &str) -> Result<(String, String)> {
     let start = std::time::Instant::now();
 
     // Step 1: Parse FEN string into a Board
-    let board = Board::from_fen(fen)
-        .map_err(|_| anyhow!("Invalid FEN: {}", fen))?;
+    let board = load_board(fen)?;
 
     // Step 2: Check for terminal states (checkmate/stalemate) before expensive search
     if board.checkmate() {
@@ -29,32 +235,408 @@ pub fn analyze_position(fen: &str) -> Result<(String, String)> {
     }
     
     // Step 3: Run engine search (iterative deepening to fixed depth)
-    // Using depth 6 for faster response (depth 12 was causing hangs)
-    const SEARCH_DEPTH: u16 = 6;
-    eprintln!("(depth {})", SEARCH_DEPTH);
+    eprintln!("(depth {})", depth);
     let _ = std::io::stderr().flush();
-    let best_move = IterativeSearcher::best_move(board.shallow_clone(), SEARCH_DEPTH);
-    
-    // Step 4: Extract evaluation score (PSQT after best move; white-positive)
-    let mut eval_board = board.shallow_clone();
-    eval_board.apply_move(best_move);
-    let raw_eval = eval_board.psq().mg();
+    if uses_uci() {
+        let best = uci_search(fen, depth, 1, None, true, cancel.cloned())?
+            .into_iter()
+            .next()
+            .context("Engine returned no line")?;
+        eprintln!("{:.0}ms", start.elapsed().as_secs_f64() * 1000.0);
+        return Ok(readable_line(&best));
+    }
+    // With progress on or a cancellable search, deepen one ply at a time so each depth can
+    // be reported or kept as the answer (the shallow searches add a fraction of the last
+    // one's cost). tanton's iterative search starts at depth 2 (depth 1 has no move).
+    if PROGRESS.get().is_some() || cancel.is_some() {
+        for shallow in 2..depth {
+            let best_move = iterative_search(&board, shallow);
+            let (move_str, eval_str) = tanton_result(&board, best_move, shallow);
+            if let Some(report) = PROGRESS.get() {
+                report(&Progress { fen: fen.to_string(), depth: shallow as u32, eval: eval_str.clone(), line: vec![move_str.clone()] });
+            }
+            if let Some(cancel) = cancel {
+                cancel.record((move_str.clone(), eval_str.clone()));
+                if cancel.is_cancelled() {
+                    eprintln!("stopped at depth {}", shallow);
+                    return Ok((move_str, eval_str));
+                }
+            }
+        }
+    }
+    let best_move = iterative_search(&board, depth);
 
-    // Negate for side-to-move perspective (if Black to move, flip sign)
-    let eval_score = if board.turn() == tanton::Player::Black { -raw_eval } else { raw_eval };
-    
-    // Step 5: Format move + eval string and return
-    let uci_str = best_move.stringify(); // e.g., "c2c3"
-    let move_str = format_move_readable(&uci_str);
-    let eval_str = format_eval(eval_score);
+    // Step 4 + 5: Evaluate and format move + eval string
+    let (move_str, eval_str) = tanton_result(&board, best_move, depth);
 
     eprintln!("{:.0}ms", start.elapsed().as_secs_f64() * 1000.0);
 
     Ok((move_str, eval_str))
 }
 
+/// Runs tanton's parallel search, inside a pool of `THREAD_CAP` threads while capped
+fn iterative_search(board: &Board, depth: u16) -> tanton::BitMove {
+    let board = board.shallow_clone();
+    match capped_pool() {
+        Some(pool) => pool.install(move || IterativeSearcher::best_move(board, depth)),
+        None => IterativeSearcher::best_move(board, depth),
+    }
+}
+
+/// The pool for the current thread cap, built when the cap changes; None without a cap
+fn capped_pool() -> Option<Arc<rayon::ThreadPool>> {
+    let cap = THREAD_CAP.load(Ordering::Relaxed);
+    if cap == 0 {
+        return None;
+    }
+    let mut pool = CAPPED_POOL.lock().unwrap_or_else(|e| e.into_inner());
+    match pool.as_ref() {
+        Some((threads, pool)) if *threads == cap => Some(pool.clone()),
+        _ => {
+            let built = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(cap).build().ok()?);
+            *pool = Some((cap, built.clone()));
+            Some(built)
+        }
+    }
+}
+
+/// Checks that a FEN describes a position that can arise in a game before tanton sees it.
+/// Misread boards (a missing king, the side not to move in check, pawns on the back rank)
+/// make tanton panic or suggest nonsense; this names the problem instead. Checkmate and
+/// stalemate pass: they are reported as the result.
+pub fn check_position(fen: &str) -> Result<()> {
+    use shakmaty::{CastlingMode, Chess, FromSetup, PositionError, PositionErrorKinds, fen::Fen};
+
+    let setup = Fen::from_ascii(fen.as_bytes()).map_err(|e| anyhow!("Invalid FEN ({}): {}", e, fen))?.into_setup();
+    let side_to_move = if setup.turn == shakmaty::Color::White { "White" } else { "Black" };
+    let error = match Chess::from_setup(setup, CastlingMode::Standard)
+        .or_else(PositionError::ignore_invalid_castling_rights)
+        .or_else(PositionError::ignore_invalid_ep_square)
+    {
+        Ok(_) => return Ok(()),
+        Err(error) => error,
+    };
+    let kinds = error.kinds();
+    let problem = if kinds.contains(PositionErrorKinds::EMPTY_BOARD) {
+        "the board is empty".to_string()
+    } else if kinds.contains(PositionErrorKinds::MISSING_KING) {
+        "a king is missing".to_string()
+    } else if kinds.contains(PositionErrorKinds::TOO_MANY_KINGS) {
+        "a side has more than one king".to_string()
+    } else if kinds.contains(PositionErrorKinds::OPPOSITE_CHECK) {
+        let other = if side_to_move == "White" { "Black" } else { "White" };
+        format!("{} is in check but it is {}'s turn", other, side_to_move)
+    } else if kinds.contains(PositionErrorKinds::PAWNS_ON_BACKRANK) {
+        "pawns on the first or last rank".to_string()
+    } else if kinds.contains(PositionErrorKinds::IMPOSSIBLE_CHECK) {
+        format!("{} is in a check no move could give", side_to_move)
+    } else if kinds.contains(PositionErrorKinds::TOO_MUCH_MATERIAL) {
+        "more pieces than a game can have".to_string()
+    } else {
+        error.to_string()
+    };
+    Err(anyhow!("Impossible position: {} ({})", problem, fen))
+}
+
+/// Parses a FEN for tanton after `check_position`
+fn load_board(fen: &str) -> Result<Board> {
+    check_position(fen)?;
+    Board::from_fen(fen).map_err(|_| anyhow!("Invalid FEN: {}", fen))
+}
+
+/// Readable move and eval for a tanton search result, from the side to move's perspective.
+/// The eval is the move's search score on the same scale as the candidates (the PSQT
+/// material count after the move misjudged tactical positions), with mates as "#N".
+fn tanton_result(board: &Board, best_move: tanton::BitMove, depth: u16) -> (String, String) {
+    let reply_depth = depth.saturating_sub(2).max(1);
+    let score = score_root_move(board, best_move, reply_depth);
+    (format_move_readable(&best_move.stringify()), readable_score(root_score(board, best_move, reply_depth, score)))
+}
+
+/// A tanton score for a root move as a UCI-style score. tanton's search marks a mate
+/// without its distance, so the shallowest search that still finds it gives N.
+fn root_score(board: &Board, mov: tanton::BitMove, reply_depth: u16, score: i32) -> Score {
+    if score.abs() < TANTON_MATE {
+        return Score::Cp(score);
+    }
+    let plies = (1..=reply_depth)
+        .find(|&depth| score_root_move(board, mov, depth).abs() >= TANTON_MATE)
+        .unwrap_or(reply_depth) as i32;
+    // Mating takes an odd number of plies from the root (our move last), being mated an even one
+    if score > 0 { Score::Mate((plies + 1) / 2) } else { Score::Mate(-(plies / 2).max(1)) }
+}
+
+/// Best move with its eval, plus the ranked alternatives when MultiPV is on
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    pub best_move: String,
+    pub eval: String,
+    /// (move, eval) pairs, best first; empty with MultiPV off
+    pub candidates: Vec<(String, String)>,
+}
+
+/// A chess engine that can search positions: the configured backend (`SelectedEngine`), or
+/// one an embedding app brings along
+pub trait Engine: Send + Sync {
+    /// Best move and eval for `fen` at `depth`, with the top `count` candidates
+    fn analyze(&self, fen: &str, depth: u16, count: usize) -> Result<Analysis>;
+}
+
+/// The engine chosen with `set_backend` (tanton unless a UCI engine was configured)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SelectedEngine;
+
+impl Engine for SelectedEngine {
+    fn analyze(&self, fen: &str, depth: u16, count: usize) -> Result<Analysis> {
+        analyze_multipv(fen, depth, count)
+    }
+}
+
+/// `analyze_position` plus the top `count` candidate moves (none when `count` is 0).
+/// A UCI engine answers both from a single MultiPV search, so its best move and first
+/// candidate always agree; tanton ranks the candidates with `candidate_moves`.
+pub fn analyze_multipv(fen: &str, depth: u16, count: usize) -> Result<Analysis> {
+    search_multipv(fen, depth, count, None)
+}
+
+/// `analyze_multipv` that stops early when `cancel` fires (see `search_position`); tanton
+/// skips the candidates then
+fn search_multipv(fen: &str, depth: u16, count: usize, cancel: Option<&CancelToken>) -> Result<Analysis> {
+    if count == 0 || !uses_uci() {
+        let (best_move, eval) = search_position(fen, depth, cancel)?;
+        let candidates = if count == 0 || cancel.is_some_and(CancelToken::is_cancelled) {
+            Vec::new()
+        } else {
+            candidate_moves(fen, depth, count)?
+        };
+        return Ok(Analysis { best_move, eval, candidates });
+    }
+
+    let board = load_board(fen)?;
+    let candidates = if board.checkmate() || board.stalemate() {
+        Vec::new()
+    } else {
+        uci_search(fen, depth, count, None, true, cancel.cloned())?.iter().map(readable_line).collect()
+    };
+    let (best_move, eval) = match candidates.first() {
+        Some(best) => best.clone(),
+        None => analyze_position(fen, depth)?, // checkmate/stalemate message
+    };
+    Ok(Analysis { best_move, eval, candidates })
+}
+
+/// `analyze_multipv` on tokio's blocking pool. Once `cancel` fires it returns without
+/// waiting for the depth tanton is on: the best move of the deepest finished depth, no
+/// candidates. The search itself stops after that depth (a UCI engine gets `stop`).
+#[cfg(feature = "desktop")]
+pub async fn analyze_cancellable(fen: &str, depth: u16, count: usize, cancel: &CancelToken) -> Result<Analysis> {
+    let (position, token) = (fen.to_string(), cancel.clone());
+    let mut search = tokio::task::spawn_blocking(move || search_multipv(&position, depth, count, Some(&token)));
+    tokio::select! {
+        result = &mut search => return result.context("Engine search panicked")?,
+        _ = cancel.cancelled() => {}
+    }
+    match cancel.best_so_far() {
+        Some((best_move, eval)) if !uses_uci() => Ok(Analysis { best_move, eval, candidates: Vec::new() }),
+        // Nothing finished yet (or a UCI engine, which answers right after `stop`)
+        _ => search.await.context("Engine search panicked")?,
+    }
+}
+
+/// Ranks the top `count` candidate moves for the side to move (MultiPV-style preview).
+/// Each root move is scored with a plain alpha-beta search two plies shallower than `depth`,
+/// so the list is cheap enough to compute every cycle but coarser than the main search.
+/// The best move's eval comes from the same scoring (`tanton_result`), so a move shows the
+/// same eval on the "Best" line and in the list; only the order can differ from the main
+/// search's choice.
+/// A UCI engine ranks them with its own MultiPV search instead.
+/// Returns (move, eval) pairs, best first. Empty for checkmate/stalemate.
+pub fn candidate_moves(fen: &str, depth: u16, count: usize) -> Result<Vec<(String, String)>> {
+    if uses_uci() {
+        let board = load_board(fen)?;
+        if board.checkmate() || board.stalemate() {
+            return Ok(Vec::new());
+        }
+        return Ok(uci_search(fen, depth, count, None, true, None)?.iter().map(readable_line).collect());
+    }
+    let board = load_board(fen)?;
+    let reply_depth = depth.saturating_sub(2).max(1);
+    Ok(scored_root_moves(&board, reply_depth)
+        .into_iter()
+        .take(count)
+        .map(|(mov, score)| {
+            (format_move_readable(&mov.stringify()), readable_score(root_score(&board, mov, reply_depth, score)))
+        })
+        .collect())
+}
+
+/// Scores every legal move like `candidate_moves`, returning readable moves with raw
+/// centipawn scores (side to move's perspective), best first
+pub fn score_moves(fen: &str, depth: u16) -> Result<Vec<(String, i32)>> {
+    let board = load_board(fen)?;
+    Ok(scored_root_moves(&board, depth.saturating_sub(2).max(1))
+        .into_iter()
+        .map(|(mov, score)| (format_move_readable(&mov.stringify()), score))
+        .collect())
+}
+
+/// Every legal move with its `score_root_move` score, best first
+fn scored_root_moves(board: &Board, reply_depth: u16) -> Vec<(tanton::BitMove, i32)> {
+    let mut scored: Vec<(tanton::BitMove, i32)> =
+        board.generate_moves().iter().map(|&mov| (mov, score_root_move(board, mov, reply_depth))).collect();
+    scored.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    scored
+}
+
+/// Scores a move suggested from outside the engine (e.g. the LLM's pick in hybrid mode)
+/// on the same scale as `candidate_moves`, so it can be compared with the engine's choice.
+/// Accepts SAN ("Nf3", "exd5"), readable ("E2 to E4"), UCI ("e2e4"), or castling ("O-O") notation.
+/// Returns the normalized readable move and its eval, or None if it is not legal here.
+pub fn evaluate_move(fen: &str, suggestion: &str, depth: u16) -> Result<Option<(String, String)>> {
+    let board = load_board(fen)?;
+
+    if uses_uci() {
+        let Some(mov) = find_move(&board, suggestion) else {
+            return Ok(None);
+        };
+        let line = uci_search(fen, depth, 1, Some(mov.stringify()), false, None)?
+            .into_iter()
+            .next()
+            .context("Engine returned no line")?;
+        return Ok(Some(readable_line(&line)));
+    }
+
+    Ok(find_move(&board, suggestion).map(|mov| {
+        let reply_depth = depth.saturating_sub(2).max(1);
+        let score = score_root_move(&board, mov, reply_depth);
+        (format_move_readable(&mov.stringify()), readable_score(root_score(&board, mov, reply_depth, score)))
+    }))
+}
+
+/// Centipawn stand-in for a forced mate in UCI scores (minus the moves to mate)
+const MATE_CP: i32 = 100_000;
+
+/// Scores from tanton's alpha-beta at or beyond this are mates (it scores them ±31000)
+const TANTON_MATE: i32 = 30_000;
+
+/// Best move and its centipawn score (side to move's perspective), on the same scale as
+/// `score_move`. None for checkmate/stalemate.
+pub fn best_scored_move(fen: &str, depth: u16) -> Result<Option<(String, i32)>> {
+    Ok(top_scored_moves(fen, depth, 1)?.into_iter().next())
+}
+
+/// The `count` best moves with centipawn scores like `best_scored_move`, best first
+/// (a UCI engine's MultiPV lines, or tanton's `score_moves`). Empty for checkmate/stalemate.
+pub fn top_scored_moves(fen: &str, depth: u16, count: usize) -> Result<Vec<(String, i32)>> {
+    if uses_uci() {
+        let board = load_board(fen)?;
+        if board.checkmate() || board.stalemate() {
+            return Ok(Vec::new());
+        }
+        return Ok(uci_search(fen, depth, count, None, false, None)?
+            .iter()
+            .map(|line| (format_move_readable(&line.pv[0]), score_cp(line.score)))
+            .collect());
+    }
+    Ok(score_moves(fen, depth)?.into_iter().take(count).collect())
+}
+
+/// Scores one move (any notation accepted by `evaluate_move`) in centipawns from the side
+/// to move's perspective. Returns the readable move and score, or None if it is not legal.
+pub fn score_move(fen: &str, text: &str, depth: u16) -> Result<Option<(String, i32)>> {
+    let board = load_board(fen)?;
+    let Some(mov) = find_move(&board, text) else {
+        return Ok(None);
+    };
+    let readable = format_move_readable(&mov.stringify());
+    if uses_uci() {
+        let line = uci_search(fen, depth, 1, Some(mov.stringify()), false, None)?
+            .into_iter()
+            .next()
+            .context("Engine returned no line")?;
+        return Ok(Some((readable, score_cp(line.score))));
+    }
+    Ok(Some((readable, score_root_move(&board, mov, depth.saturating_sub(2).max(1)))))
+}
+
+/// Returns the readable form of a move in any notation accepted by `evaluate_move`,
+/// or None if it is not legal in the position
+pub fn normalize_move(fen: &str, text: &str) -> Result<Option<String>> {
+    let board = load_board(fen)?;
+    Ok(find_move(&board, text).map(|mov| format_move_readable(&mov.stringify())))
+}
+
+/// Plays a move (any notation accepted by `evaluate_move`) and returns the resulting FEN.
+/// Used to update a known position by hand, e.g. after the opponent moves.
+pub fn apply_move(fen: &str, text: &str) -> Result<String> {
+    let mut board = load_board(fen)?;
+    let mov = find_move(&board, text).ok_or_else(|| anyhow!("'{}' is not a legal move here", text.trim()))?;
+    board.apply_move(mov);
+    Ok(board.fen())
+}
+
+/// Finds the legal move matching move text: SAN ("Nf3", "exd5") or free-form squares.
+/// A promotion without an explicit piece defaults to a queen.
+fn find_move(board: &Board, text: &str) -> Option<tanton::BitMove> {
+    let wanted = san_to_uci(&board.fen(), text).or_else(|| suggestion_to_uci(text, board.turn()))?;
+    board
+        .generate_moves()
+        .iter()
+        .filter(|m| m.stringify().starts_with(&wanted[..4]))
+        .find(|m| {
+            let uci = m.stringify();
+            uci.len() == 4 || uci[4..] == *wanted.get(4..).filter(|p| !p.is_empty()).unwrap_or("q")
+        })
+        .copied()
+}
+
+/// Scores one root move with alpha-beta, from the perspective of the side to move
+fn score_root_move(board: &Board, mov: tanton::BitMove, reply_depth: u16) -> i32 {
+    let mut child = board.shallow_clone();
+    child.apply_move(mov);
+    // Negamax: the child's score is from the opponent's perspective
+    -(alpha_beta_search(&mut child, i16::MIN + 1, i16::MAX, reply_depth).score as i32)
+}
+
+/// Resolves standard algebraic notation against the position, as UCI ("Nf3" → "g1f3")
+fn san_to_uci(fen: &str, text: &str) -> Option<String> {
+    let pos: Chess = Fen::from_ascii(fen.as_bytes()).ok()?.into_position(CastlingMode::Standard).ok()?;
+    let mov = SanPlus::from_ascii(text.trim().as_bytes()).ok()?.san.to_move(&pos).ok()?;
+    Some(mov.to_uci(CastlingMode::Standard).to_string())
+}
+
+/// Extracts a UCI move ("e2e4", "e7e8q") from free-form move text
+fn suggestion_to_uci(text: &str, turn: tanton::Player) -> Option<String> {
+    let text = text.trim().to_lowercase().replace('0', "o");
+    let back_rank = if turn == tanton::Player::White { '1' } else { '8' };
+    if text.starts_with("o-o-o") {
+        return Some(format!("e{}c{}", back_rank, back_rank));
+    }
+    if text.starts_with("o-o") {
+        return Some(format!("e{}g{}", back_rank, back_rank));
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let squares: Vec<String> = chars
+        .windows(2)
+        .filter(|w| ('a'..='h').contains(&w[0]) && ('1'..='8').contains(&w[1]))
+        .map(|w| w.iter().collect())
+        .collect();
+    if squares.len() < 2 {
+        return None;
+    }
+
+    // Promotion piece: "(=Q)", "=q", or a trailing UCI letter
+    let promo = match text.split_once('=') {
+        Some((_, tail)) => tail.chars().find(|c| "qrbn".contains(*c)),
+        None => chars.get(4).copied().filter(|c| chars.len() == 5 && "qrbn".contains(*c)),
+    };
+
+    Some(format!("{}{}{}", squares[0], squares[1], promo.map(String::from).unwrap_or_default()))
+}
+
 /// Converts UCI notation to readable format: "c2c3" → "C2 to C3"
-fn format_move_readable(uci: &str) -> String {
+pub fn format_move_readable(uci: &str) -> String {
     if uci.len() >= 4 {
         let from = &uci[0..2].to_uppercase();
         let to = &uci[2..4].to_uppercase();
@@ -71,7 +653,184 @@ fn format_move_readable(uci: &str) -> String {
     }
 }
 
-fn format_eval(centipawns: i32) -> String {
+/// Readable first move and eval of an engine line
+fn readable_line(line: &InfoLine) -> (String, String) {
+    (format_move_readable(&line.pv[0]), readable_score(line.score))
+}
+
+fn readable_score(score: Score) -> String {
+    match score {
+        Score::Cp(cp) => format_eval(cp),
+        mate => mate.to_string(),
+    }
+}
+
+/// Progress event for a UCI info line
+#[cfg_attr(not(feature = "desktop"), allow(dead_code))]
+fn line_progress(fen: &str, line: &InfoLine) -> Progress {
+    Progress {
+        fen: fen.to_string(),
+        depth: line.depth,
+        eval: readable_score(line.score),
+        line: line.pv.iter().map(|mv| format_move_readable(mv)).collect(),
+    }
+}
+
+/// Centipawns for a UCI score, mates counting as ±`MATE_CP` (sooner mates score higher)
+fn score_cp(score: Score) -> i32 {
+    match score {
+        Score::Cp(cp) => cp,
+        Score::Mate(n) if n > 0 => MATE_CP - n,
+        Score::Mate(n) => -MATE_CP - n,
+    }
+}
+
+/// Runs one search on the UCI worker, blocking until the engine answers.
+/// Returns the final line of each MultiPV slot, best first.
+fn uci_search(
+    fen: &str,
+    depth: u16,
+    multipv: usize,
+    only_move: Option<String>,
+    progress: bool,
+    cancel: Option<CancelToken>,
+) -> Result<Vec<InfoLine>> {
+    let worker = UCI_WORKER.get().context("UCI engine not started")?;
+    let (reply, response) = mpsc::channel();
+    let request = UciRequest { fen: fen.to_string(), depth, multipv, only_move, progress, cancel, reply };
+    worker
+        .lock()
+        .map_err(|_| anyhow!("UCI worker poisoned"))?
+        .send(request)
+        .map_err(|_| anyhow!("UCI engine stopped"))?;
+    response.recv().context("UCI engine stopped")?
+}
+
+/// Starts the engine on a worker thread with its own runtime; returns the request channel
+/// and the engine's name once the handshake and options went through
+#[cfg(feature = "desktop")]
+fn start_uci(config: UciConfig) -> Result<(Sender<UciRequest>, String)> {
+    let (requests, incoming) = mpsc::channel::<UciRequest>();
+    let (ready, started) = mpsc::channel();
+    std::thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                let _ = ready.send(Err(anyhow!(e).context("Failed to start engine runtime")));
+                return;
+            }
+        };
+        runtime.block_on(async move {
+            let mut engine = match open_uci(&config).await {
+                Ok(engine) => {
+                    let _ = ready.send(Ok(engine.name.clone()));
+                    engine
+                }
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    return;
+                }
+            };
+            let mut multipv = 1;
+            let mut threads = config.threads;
+            for request in incoming {
+                let result = match apply_thread_cap(&mut engine, &mut threads, config.threads).await {
+                    Ok(()) => search_uci(&mut engine, &mut multipv, &request).await,
+                    Err(e) => Err(e),
+                };
+                let _ = request.reply.send(result);
+            }
+            engine.quit().await;
+        });
+    });
+    let name = started.recv().context("UCI engine stopped")??;
+    Ok((requests, name))
+}
+
+#[cfg(not(feature = "desktop"))]
+fn start_uci(_config: UciConfig) -> Result<(Sender<UciRequest>, String)> {
+    anyhow::bail!("External UCI engines need a build with the desktop feature")
+}
+
+#[cfg(feature = "desktop")]
+async fn open_uci(config: &UciConfig) -> Result<UciEngine> {
+    let mut engine = UciEngine::spawn(&config.path).await?;
+    engine.set_option("Threads", &config.threads.to_string()).await?;
+    engine.set_option("Hash", &config.hash_mb.to_string()).await?;
+    if let Some(skill) = config.skill {
+        engine.set_option("Skill Level", &skill.to_string()).await?;
+    }
+    engine.ready().await?;
+    Ok(engine)
+}
+
+/// Sets the engine's Threads option to the configured count, capped while limited
+#[cfg(feature = "desktop")]
+async fn apply_thread_cap(engine: &mut UciEngine, threads: &mut usize, configured: usize) -> Result<()> {
+    let wanted = match THREAD_CAP.load(Ordering::Relaxed) {
+        0 => configured,
+        cap => configured.min(cap),
+    };
+    if *threads != wanted {
+        engine.set_option("Threads", &wanted.to_string()).await?;
+        *threads = wanted;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "desktop")]
+async fn search_uci(engine: &mut UciEngine, multipv: &mut usize, request: &UciRequest) -> Result<Vec<InfoLine>> {
+    if *multipv != request.multipv {
+        engine.set_option("MultiPV", &request.multipv.to_string()).await?;
+        *multipv = request.multipv;
+    }
+    engine.position(&request.fen).await?;
+    let mut go = format!("go depth {}", request.depth);
+    if let Some(mov) = &request.only_move {
+        go.push_str(" searchmoves ");
+        go.push_str(mov);
+    }
+    engine.send(&go).await?;
+
+    // Latest line per MultiPV slot until the search ends
+    let mut lines: BTreeMap<usize, InfoLine> = BTreeMap::new();
+    let report = PROGRESS.get().filter(|_| request.progress);
+    let mut reported_depth = 0;
+    let mut stopping = false;
+    loop {
+        let line = match request.cancel.as_ref().filter(|_| !stopping) {
+            Some(cancel) => tokio::select! {
+                line = engine.read_line() => line?,
+                _ = cancel.cancelled() => {
+                    // The engine answers `stop` with its bestmove, ending the loop below
+                    engine.send("stop").await?;
+                    stopping = true;
+                    continue;
+                }
+            },
+            None => engine.read_line().await?,
+        };
+        let Some(line) = line else { break };
+        if line.starts_with("bestmove") {
+            return Ok(lines.into_values().collect());
+        }
+        if let Some(info) = crate::uci::parse_info(&line) {
+            // One event per depth: the first line of the top slot to reach it
+            if let Some(report) = report
+                && info.multipv == 1
+                && info.depth > reported_depth
+            {
+                reported_depth = info.depth;
+                report(&line_progress(&request.fen, &info));
+            }
+            lines.insert(info.multipv, info);
+        }
+    }
+    anyhow::bail!("Engine exited during search")
+}
+
+/// Formats centipawns as a signed pawn value, e.g. 145 → "+1.45"
+pub fn format_eval(centipawns: i32) -> String {
     let pawns = centipawns as f64 / 100.0;
     if pawns >= 0.0 {
         format!("+{:.2}", pawns)
@@ -79,3 +838,190 @@ fn format_eval(centipawns: i32) -> String {
         format!("{:.2}", pawns)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn test_format_move_readable() {
+        assert_eq!(format_move_readable("e2e4"), "E2 to E4");
+        assert_eq!(format_move_readable("e7e8q"), "E7 to E8 (=Q)");
+    }
+
+    #[test]
+    fn test_readable_line_formats_score() {
+        let line = |score| InfoLine { depth: 20, multipv: 1, score, pv: vec!["g1f3".to_string()] };
+        assert_eq!(readable_line(&line(Score::Cp(-35))), ("G1 to F3".to_string(), "-0.35".to_string()));
+        assert_eq!(readable_line(&line(Score::Mate(3))), ("G1 to F3".to_string(), "#3".to_string()));
+    }
+
+    #[test]
+    fn test_line_progress_reads_whole_pv() {
+        let info = InfoLine { depth: 14, multipv: 1, score: Score::Cp(28), pv: vec!["e2e4".to_string(), "e7e5".to_string()] };
+        let progress = line_progress(START_FEN, &info);
+        assert_eq!(progress.depth, 14);
+        assert_eq!(progress.eval, "+0.28");
+        assert_eq!(progress.line, vec!["E2 to E4", "E7 to E5"]);
+    }
+
+    #[test]
+    fn test_mate_scores_rank_above_centipawns() {
+        assert!(score_cp(Score::Mate(2)) > score_cp(Score::Mate(5)));
+        assert!(score_cp(Score::Mate(5)) > score_cp(Score::Cp(2000)));
+        assert!(score_cp(Score::Mate(-5)) > score_cp(Score::Mate(-2)));
+        assert!(score_cp(Score::Mate(-5)) < score_cp(Score::Cp(-2000)));
+    }
+
+    #[test]
+    fn test_thread_cap_sizes_tanton_pool() {
+        set_thread_cap(Some(2));
+        let threads = capped_pool().map(|pool| pool.current_num_threads());
+        set_thread_cap(None);
+        assert_eq!(threads, Some(2));
+        assert!(capped_pool().is_none());
+    }
+
+    #[test]
+    fn test_score_move_matches_best_scored_move() {
+        let fen = "4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1";
+        let (best, best_cp) = best_scored_move(fen, 2).unwrap().unwrap();
+        assert_eq!(best, "D1 to D5");
+        assert_eq!(score_move(fen, "Qxd5", 2).unwrap(), Some((best.clone(), best_cp)));
+        assert_eq!(score_move(fen, "d1d5", 2).unwrap(), Some((best, best_cp)));
+        assert_eq!(score_move(fen, "Qxd6", 2).unwrap(), None);
+    }
+
+    #[test]
+    fn test_tanton_reports_mate_distance() {
+        // Back-rank mate: Ra8#
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        assert_eq!(candidate_moves(fen, 4, 1).unwrap(), vec![("A1 to A8".to_string(), "#1".to_string())]);
+        // Walking into the corner lets Black mate on the back rank
+        let exposed = "r5k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1";
+        let (_, eval) = evaluate_move(exposed, "g1h1", 4).unwrap().unwrap();
+        assert_eq!(eval, "#-1");
+    }
+
+    #[test]
+    fn test_format_eval_sign() {
+        assert_eq!(format_eval(145), "+1.45");
+        assert_eq!(format_eval(-30), "-0.30");
+    }
+
+    #[test]
+    fn test_evaluate_move_accepts_readable_and_uci() {
+        let (readable, _) = evaluate_move(START_FEN, "E2 to E4", 2).unwrap().unwrap();
+        assert_eq!(readable, "E2 to E4");
+        let (readable, _) = evaluate_move(START_FEN, "g1f3", 2).unwrap().unwrap();
+        assert_eq!(readable, "G1 to F3");
+        let (readable, _) = evaluate_move(START_FEN, "Nf3", 2).unwrap().unwrap();
+        assert_eq!(readable, "G1 to F3");
+
+        let scandinavian = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2";
+        let (readable, _) = evaluate_move(scandinavian, "exd5", 2).unwrap().unwrap();
+        assert_eq!(readable, "E4 to D5");
+
+        let promotion = "1k6/4P3/8/8/8/8/8/4K3 w - - 0 1";
+        let (readable, _) = evaluate_move(promotion, "e8=Q+", 2).unwrap().unwrap();
+        assert_eq!(readable, "E7 to E8 (=Q)");
+        let (readable, _) = evaluate_move(promotion, "e8=N", 2).unwrap().unwrap();
+        assert_eq!(readable, "E7 to E8 (=N)");
+    }
+
+    #[test]
+    fn test_check_position_names_the_problem() {
+        assert!(check_position(START_FEN).is_ok());
+        // Fool's mate: over, but a real position
+        assert!(check_position("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").is_ok());
+        let error = check_position("4k3/8/8/8/8/8/4R3/4K3 w - - 0 1").unwrap_err().to_string();
+        assert!(error.contains("Black is in check but it is White's turn"), "{}", error);
+        let error = check_position("8/8/8/8/8/8/8/4K3 w - - 0 1").unwrap_err().to_string();
+        assert!(error.contains("a king is missing"), "{}", error);
+        assert!(analyze_position("P3k3/8/8/8/8/8/8/4K3 w - - 0 1", 2).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_move_rejects_illegal() {
+        assert!(evaluate_move(START_FEN, "E2 to E5", 2).unwrap().is_none());
+        assert!(evaluate_move(START_FEN, "pawn forward", 2).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_evaluate_move_castling_and_promotion() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        let (readable, _) = evaluate_move(fen, "O-O", 2).unwrap().unwrap();
+        assert_eq!(readable, "E1 to G1");
+
+        let fen = "7k/P7/8/8/8/8/8/K7 w - - 0 1";
+        let (readable, _) = evaluate_move(fen, "A7 to A8", 2).unwrap().unwrap();
+        assert_eq!(readable, "A7 to A8 (=Q)");
+        let (readable, _) = evaluate_move(fen, "A7 to A8 (=N)", 2).unwrap().unwrap();
+        assert_eq!(readable, "A7 to A8 (=N)");
+    }
+
+    #[test]
+    fn test_apply_move_updates_position() {
+        let fen = apply_move(START_FEN, "e2 to e4").unwrap();
+        assert!(fen.starts_with("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b"));
+        assert!(apply_move(&fen, "e2e4").is_err());
+    }
+
+    #[test]
+    fn test_score_moves_covers_all_legal_moves() {
+        let scored = score_moves(START_FEN, 2).unwrap();
+        assert_eq!(scored.len(), 20);
+        assert!(scored.windows(2).all(|w| w[0].1 >= w[1].1));
+    }
+
+    #[test]
+    fn test_candidate_moves_limited_to_count() {
+        let candidates = candidate_moves(START_FEN, 2, 3).unwrap();
+        assert_eq!(candidates.len(), 3);
+    }
+
+    #[test]
+    fn test_candidate_moves_prefers_free_queen() {
+        // White to move can capture an undefended queen on d5
+        let fen = "4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1";
+        let candidates = candidate_moves(fen, 2, 1).unwrap();
+        assert_eq!(candidates[0].0, "D1 to D5");
+    }
+
+    #[test]
+    fn test_best_move_eval_matches_its_candidate() {
+        for fen in [START_FEN, "4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1"] {
+            let analysis = analyze_multipv(fen, 4, 40).unwrap();
+            let listed = analysis.candidates.iter().find(|(mv, _)| *mv == analysis.best_move).unwrap();
+            assert_eq!(listed.1, analysis.eval, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_analyze_multipv_lists_candidates_only_when_asked() {
+        let analysis = analyze_multipv(START_FEN, 2, 0).unwrap();
+        assert!(analysis.candidates.is_empty());
+        let analysis = analyze_multipv(START_FEN, 2, 4).unwrap();
+        assert_eq!(analysis.candidates.len(), 4);
+        assert_ne!(analysis.best_move, "--");
+    }
+
+    #[test]
+    fn test_cancelled_search_answers_with_shallowest_depth() {
+        let cancel = CancelToken::default();
+        cancel.cancel();
+        let analysis = search_multipv(START_FEN, 8, 3, Some(&cancel)).unwrap();
+        assert_ne!(analysis.best_move, "--");
+        assert!(analysis.candidates.is_empty());
+        assert_eq!(cancel.best_so_far(), Some((analysis.best_move, analysis.eval)));
+    }
+
+    #[test]
+    fn test_candidate_moves_empty_when_mated() {
+        // Fool's mate: White is checkmated
+        let fen = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
+        assert!(candidate_moves(fen, 2, 3).unwrap().is_empty());
+    }
+}
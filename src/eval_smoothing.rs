@@ -0,0 +1,128 @@
+//! Eval smoothing for the eval bar
+//!
+//! Reading the same position again can give a different eval: a shallower search after an
+//! adaptive depth change, or a square misread for one frame. Shown as is, the dashboard's eval
+//! bar jumps back and forth between frames of one position. The smoothed eval is a moving
+//! average from White's point of view in which each cycle counts by its confidence:
+//!
+//! - the same position again counts by its search depth, against older evals halved each cycle
+//! - a board a couple of squares off the last one that no legal move leads to is most likely
+//!   a misread, and counts for a quarter of that
+//! - a move played, a different position, or a mate starts the average over
+//!
+//! It is added to engine and hybrid results as `smoothed_evaluation` (side to move's point of
+//! view, like `evaluation`), which the eval bar shows. Move lines keep the engine's own evals.
+
+use crate::pgn;
+use crate::tui::white_eval;
+use shakmaty::{Chess, Position, Square};
+
+/// Share of the accumulated weight kept from one cycle to the next
+const DECAY: f64 = 0.5;
+
+/// Weight of a likely misread relative to a clean read at the same depth
+const MISREAD_WEIGHT: f64 = 0.25;
+
+/// Most squares a misread board differs from the last position by
+const MISREAD_SQUARES: usize = 2;
+
+/// Running average of the evals of one position
+#[derive(Default)]
+pub struct EvalSmoother {
+    /// Position the average belongs to
+    position: Option<Chess>,
+    /// Average eval in pawns from White's point of view
+    white: f64,
+    /// Accumulated confidence behind the average
+    weight: f64,
+}
+
+impl EvalSmoother {
+    /// Adds the eval of `fen` searched to `depth`; returns the smoothed eval from the side to
+    /// move's point of view ("+0.42", or the mate as given). None if `eval` isn't an engine eval.
+    pub fn update(&mut self, fen: &str, eval: &str, depth: u16) -> Option<String> {
+        let white = white_eval(fen, eval)?;
+        let position = pgn::parse_position(fen)?;
+        let confidence = f64::from(depth.max(1));
+
+        // (weight, likely misread)
+        let sample = match &self.position {
+            _ if white.is_infinite() || self.weight == 0.0 => None,
+            Some(last) if last.board() == position.board() => Some((confidence, false)),
+            Some(last) if pgn::connect(last, position.board()).is_none() && differing_squares(last, &position) <= MISREAD_SQUARES => {
+                Some((confidence * MISREAD_WEIGHT, true))
+            }
+            _ => None,
+        };
+        match sample {
+            Some((weight, misread)) => {
+                let kept = self.weight * DECAY;
+                self.white = (self.white * kept + white * weight) / (kept + weight);
+                self.weight = kept + weight;
+                // A misread doesn't replace the position the average belongs to
+                if !misread {
+                    self.position = Some(position);
+                }
+            }
+            None => {
+                self.position = Some(position);
+                if white.is_infinite() {
+                    // Mates aren't averaged: the next eval starts over
+                    self.weight = 0.0;
+                    return Some(eval.to_string());
+                }
+                self.white = white;
+                self.weight = confidence;
+            }
+        }
+
+        let black_to_move = fen.split_whitespace().nth(1) == Some("b");
+        let pawns = if black_to_move { -self.white } else { self.white };
+        Some(format!("{:+.2}", pawns))
+    }
+}
+
+/// Squares with a different piece (or none) on the two boards
+fn differing_squares(a: &Chess, b: &Chess) -> usize {
+    Square::ALL.into_iter().filter(|&square| a.board().piece_at(square) != b.board().piece_at(square)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    const AFTER_E4: &str = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+
+    #[test]
+    fn test_same_position_is_averaged_by_depth() {
+        let mut smoother = EvalSmoother::default();
+        assert_eq!(smoother.update(START, "+0.40", 8).as_deref(), Some("+0.40"));
+        // Older weight 8 halves to 4, the new eval counts 4: halfway
+        assert_eq!(smoother.update(START, "-0.40", 4).as_deref(), Some("+0.00"));
+        // A deeper search moves it further
+        assert_eq!(smoother.update(START, "+0.40", 12).as_deref(), Some("+0.30"));
+    }
+
+    #[test]
+    fn test_moves_and_mates_start_over() {
+        let mut smoother = EvalSmoother::default();
+        smoother.update(START, "+0.40", 8);
+        // Black to move: -0.30 for Black is +0.30 for White, not averaged with the start
+        assert_eq!(smoother.update(AFTER_E4, "-0.30", 8).as_deref(), Some("-0.30"));
+        assert_eq!(smoother.update(AFTER_E4, "#-3", 8).as_deref(), Some("#-3"));
+        assert_eq!(smoother.update(AFTER_E4, "-0.50", 8).as_deref(), Some("-0.50"));
+        assert_eq!(smoother.update(START, "slight advantage", 8), None);
+    }
+
+    #[test]
+    fn test_misread_counts_little() {
+        let mut smoother = EvalSmoother::default();
+        smoother.update(START, "+0.40", 8);
+        // The b8 knight missed: not a move
+        let misread = "r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(smoother.update(misread, "+3.40", 8).as_deref(), Some("+1.40"));
+        // The average still belongs to the real position
+        assert_eq!(smoother.update(START, "+0.40", 8).as_deref(), Some("+0.67"));
+    }
+}
@@ -0,0 +1,204 @@
+//! Evaluation units
+//!
+//! Engine evals are pawns from the side to move's point of view ("+0.35"; mates "#3", or
+//! "#-3" when being mated). Each output sink can show them in other units, chosen with an
+//! `@UNITS` suffix on the sink (`--output console@win`):
+//!
+//! - `pawns`: as the engine reports them (default)
+//! - `cp`: centipawns, "+35 cp"
+//! - `win`: the side to move's winning chances in lichess' model, "win 53%"
+//! - `accuracy`: lichess-style accuracy of a move compared with the best line, "accuracy 94%"
+//!   (the best move itself is 100%)
+//!
+//! Mates keep their "#n" form in centipawns and count as certain wins or losses otherwise.
+//! Anything that isn't an eval (LLM wording like "slight advantage") is left as it is.
+//!
+//! Console lines show the best move's eval with its winning chances, "+1.45, 67% win"
+//! (`with_win_chance`); other units drop that note, since they already say as much.
+
+/// How evals are shown
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EvalUnits {
+    #[default]
+    Pawns,
+    Centipawns,
+    WinPercent,
+    Accuracy,
+}
+
+/// Centipawn evals beyond this are treated alike (lichess' cap for win chances)
+const MAX_CP: f64 = 1000.0;
+
+impl EvalUnits {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pawns" => Some(EvalUnits::Pawns),
+            "cp" => Some(EvalUnits::Centipawns),
+            "win" => Some(EvalUnits::WinPercent),
+            "accuracy" => Some(EvalUnits::Accuracy),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EvalUnits::Pawns => "pawns",
+            EvalUnits::Centipawns => "cp",
+            EvalUnits::WinPercent => "win",
+            EvalUnits::Accuracy => "accuracy",
+        }
+    }
+
+    /// An eval in these units; `best` is the best line's eval, which accuracy is measured
+    /// against. None if `eval` isn't an engine eval.
+    pub fn format(self, eval: &str, best: Option<&str>) -> Option<String> {
+        let score = Score::parse(eval)?;
+        Some(match self {
+            EvalUnits::Pawns => eval.to_string(),
+            EvalUnits::Centipawns => match score {
+                Score::Pawns(pawns) => format!("{:+.0} cp", pawns * 100.0),
+                Score::Mate(_) => eval.to_string(),
+            },
+            EvalUnits::WinPercent => format!("win {:.0}%", score.win_percent()),
+            EvalUnits::Accuracy => {
+                let best = best.and_then(Score::parse).unwrap_or(score);
+                format!("accuracy {:.0}%", accuracy(best.win_percent(), score.win_percent()))
+            }
+        })
+    }
+
+    /// `text` with every eval in it converted (e.g. "Best: E2 to E4 (+0.35)")
+    pub fn convert_text(self, text: &str, best: Option<&str>) -> String {
+        if self == EvalUnits::Pawns {
+            return text.to_string();
+        }
+        let text = &without_win_chance(text);
+        let mut converted = String::with_capacity(text.len());
+        let mut token = String::new();
+        for c in text.chars().chain(std::iter::once('\0')) {
+            if c.is_whitespace() || matches!(c, '(' | ')' | ',' | '\0') {
+                match self.format(&token, best) {
+                    Some(eval) => converted.push_str(&eval),
+                    None => converted.push_str(&token),
+                }
+                token.clear();
+                if c != '\0' {
+                    converted.push(c);
+                }
+            } else {
+                token.push(c);
+            }
+        }
+        converted
+    }
+}
+
+/// A pawn eval with the side to move's winning chances, "+1.45, 67% win" (mates and
+/// anything else unchanged)
+pub fn with_win_chance(eval: &str) -> String {
+    match Score::parse(eval) {
+        Some(score @ Score::Pawns(_)) => format!("{}, {:.0}{}", eval, score.win_percent(), WIN_NOTE),
+        _ => eval.to_string(),
+    }
+}
+
+/// The side to move's winning chances in percent for an engine eval (None for anything else)
+pub fn win_chance(eval: &str) -> Option<f64> {
+    Score::parse(eval).map(Score::win_percent)
+}
+
+/// Ending of the note `with_win_chance` adds
+const WIN_NOTE: &str = "% win";
+
+/// `text` with the notes of `with_win_chance` removed
+fn without_win_chance(text: &str) -> String {
+    let mut stripped = text.to_string();
+    while let Some(end) = stripped.find(WIN_NOTE) {
+        let digits = stripped[..end].trim_end_matches(|c: char| c.is_ascii_digit());
+        match digits.strip_suffix(", ") {
+            Some(before) if digits.len() < end => {
+                stripped = format!("{}{}", before, &stripped[end + WIN_NOTE.len()..]);
+            }
+            _ => break,
+        }
+    }
+    stripped
+}
+
+/// An eval as the engine writes it
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Score {
+    Pawns(f64),
+    /// Moves to mate; negative when the side to move gets mated
+    Mate(i32),
+}
+
+impl Score {
+    /// Parses "+0.35"/"-1.20" (sign required, as the engine always writes it) or "#3"/"#-3"
+    fn parse(eval: &str) -> Option<Score> {
+        if let Some(mate) = eval.strip_prefix('#') {
+            return mate.parse().ok().map(Score::Mate);
+        }
+        let digits = eval.strip_prefix(['+', '-'])?;
+        let (whole, fraction) = digits.split_once('.')?;
+        let numeric = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+        if !numeric(whole) || !numeric(fraction) {
+            return None;
+        }
+        eval.parse().ok().map(Score::Pawns)
+    }
+
+    /// Winning chances of the side to move (lichess' logistic model)
+    fn win_percent(self) -> f64 {
+        let cp = match self {
+            Score::Pawns(pawns) => (pawns * 100.0).clamp(-MAX_CP, MAX_CP),
+            Score::Mate(n) if n > 0 => return 100.0,
+            Score::Mate(_) => return 0.0,
+        };
+        50.0 + 50.0 * (2.0 / (1.0 + (-0.00368208 * cp).exp()) - 1.0)
+    }
+}
+
+/// Lichess' move accuracy for a drop in winning chances from `best` to `played`
+fn accuracy(best: f64, played: f64) -> f64 {
+    let loss = (best - played).max(0.0);
+    (103.1668100711649 * (-0.04354415386753951 * loss).exp() - 3.166924740191411).clamp(0.0, 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_units() {
+        assert_eq!(EvalUnits::Centipawns.format("-1.20", None).as_deref(), Some("-120 cp"));
+        assert_eq!(EvalUnits::Centipawns.format("#3", None).as_deref(), Some("#3"));
+        assert_eq!(EvalUnits::WinPercent.format("+0.00", None).as_deref(), Some("win 50%"));
+        assert_eq!(EvalUnits::WinPercent.format("+3.00", None).as_deref(), Some("win 75%"));
+        assert_eq!(EvalUnits::WinPercent.format("#-2", None).as_deref(), Some("win 0%"));
+        assert_eq!(EvalUnits::Accuracy.format("+0.50", Some("+0.50")).as_deref(), Some("accuracy 100%"));
+        assert_eq!(EvalUnits::Accuracy.format("-2.00", Some("+1.00")).as_deref(), Some("accuracy 29%"));
+        assert_eq!(EvalUnits::WinPercent.format("slight advantage", None), None);
+        assert_eq!(EvalUnits::WinPercent.format("1", None), None);
+    }
+
+    #[test]
+    fn test_convert_text() {
+        let units = EvalUnits::Centipawns;
+        assert_eq!(units.convert_text("Best: E2 to E4 (+0.35)", None), "Best: E2 to E4 (+35 cp)");
+        assert_eq!(units.convert_text("Eval: -0.10", None), "Eval: -10 cp");
+        // FEN fields are not evals
+        let fen = "FEN:  8/8/8/8/8/8/8/K6k w - - 0 1";
+        assert_eq!(units.convert_text(fen, None), fen);
+        assert_eq!(EvalUnits::Pawns.convert_text("(+0.35)", None), "(+0.35)");
+    }
+
+    #[test]
+    fn test_win_chance_note() {
+        assert_eq!(with_win_chance("+1.45"), "+1.45, 63% win");
+        assert_eq!(with_win_chance("#3"), "#3");
+        let line = format!("Best: E2 to E4 ({})", with_win_chance("+1.45"));
+        assert_eq!(EvalUnits::Centipawns.convert_text(&line, None), "Best: E2 to E4 (+145 cp)");
+        assert_eq!(EvalUnits::Pawns.convert_text(&line, None), line);
+    }
+}
@@ -0,0 +1,313 @@
+//! FEN validation and repair
+//!
+//! Recognizers (LLM, native templates, CNN) report only what is on the board, so their FENs
+//! can be impossible (a missing king, nine pawns) or claim castling rights the pieces no
+//! longer have. `validate_fen` rejects the former and fixes the latter before a FEN reaches
+//! the engine, which panics on illegal positions.
+//!
+//! Both functions take arbitrary text and never panic; the property tests feed them random
+//! boards and strings to keep it that way.
+
+use anyhow::Result;
+
+/// Checks a recognized FEN for impossible piece counts and corrects its castling rights.
+/// Returns the corrected FEN, or an error naming the problem.
+pub fn validate_fen(fen: &str) -> Result<String> {
+    let board_part = fen.split_whitespace().next().unwrap_or("");
+
+    // Step 1: Validate king count (exactly 1 white king 'K' and 1 black king 'k')
+    // This prevents Tanton engine panics on illegal positions
+    let white_kings = board_part.chars().filter(|&c| c == 'K').count();
+    let black_kings = board_part.chars().filter(|&c| c == 'k').count();
+
+    if white_kings != 1 || black_kings != 1 {
+        anyhow::bail!(
+            "Invalid FEN: expected exactly 1 king per side, got {} white kings and {} black kings (received: '{}')",
+            white_kings, black_kings, fen
+        );
+    }
+
+    // Step 2: Validate pawn count (max 8 per side)
+    // LLM sometimes forgets to remove a pawn from starting square when it moves
+    let white_pawns = board_part.chars().filter(|&c| c == 'P').count();
+    let black_pawns = board_part.chars().filter(|&c| c == 'p').count();
+
+    if white_pawns > 8 {
+        anyhow::bail!(
+            "Invalid FEN: White has {} pawns (max 8), likely one left on its starting square after it moved. (received: '{}')",
+            white_pawns, fen
+        );
+    }
+    if black_pawns > 8 {
+        anyhow::bail!(
+            "Invalid FEN: Black has {} pawns (max 8), likely one left on its starting square after it moved. (received: '{}')",
+            black_pawns, fen
+        );
+    }
+
+    // Step 3: Fix castling rights based on king/rook positions
+    // Recognizers always output "KQkq" but this can be invalid if pieces have moved
+    let corrected_fen = fix_castling_rights(fen);
+
+    // Step 4: Final syntax validation with shakmaty
+    shakmaty::fen::Fen::from_ascii(corrected_fen.as_bytes())
+        .map_err(|e| anyhow::anyhow!("Invalid FEN syntax: {} (received: '{}')", e, corrected_fen))?;
+
+    Ok(corrected_fen)
+}
+
+/// Fixes castling rights in FEN based on actual king and rook positions.
+/// Castling is only legal if:
+/// - King is on its starting square (e1 for White, e8 for Black)
+/// - Rook is on its starting square (a1/h1 for White, a8/h8 for Black)
+///
+/// Rights are only ever removed. The en passant square and move counters are kept (tracked
+/// by `history`); missing ones default to "- 0 1".
+pub fn fix_castling_rights(fen: &str) -> String {
+    let parts: Vec<&str> = fen.split_whitespace().collect();
+    if parts.len() < 3 {
+        return fen.to_string();
+    }
+
+    let board = parts[0];
+    let turn = parts[1];
+
+    // Parse the board into ranks (rank 8 is first, rank 1 is last)
+    let ranks: Vec<&str> = board.split('/').collect();
+    if ranks.len() != 8 {
+        return fen.to_string();
+    }
+
+    // Helper to expand a rank string (e.g., "r3k2r" stays, "8" → "........")
+    fn expand_rank(rank: &str) -> String {
+        let mut result = String::new();
+        for c in rank.chars() {
+            if let Some(n) = c.to_digit(10) {
+                result.push_str(&".".repeat(n as usize));
+            } else {
+                result.push(c);
+            }
+        }
+        result
+    }
+
+    // Get rank 1 (White's back rank, index 7) and rank 8 (Black's back rank, index 0)
+    let rank1 = expand_rank(ranks[7]); // White's back rank
+    let rank8 = expand_rank(ranks[0]); // Black's back rank
+
+    // Check piece positions (0-indexed: a=0, b=1, ..., h=7)
+    let white_king_e1 = rank1.chars().nth(4) == Some('K');
+    let white_rook_a1 = rank1.starts_with('R');
+    let white_rook_h1 = rank1.chars().nth(7) == Some('R');
+    let black_king_e8 = rank8.chars().nth(4) == Some('k');
+    let black_rook_a8 = rank8.starts_with('r');
+    let black_rook_h8 = rank8.chars().nth(7) == Some('r');
+
+    // Build castling rights string
+    let mut castling = String::new();
+    if white_king_e1 && white_rook_h1 { castling.push('K'); }
+    if white_king_e1 && white_rook_a1 { castling.push('Q'); }
+    if black_king_e8 && black_rook_h8 { castling.push('k'); }
+    if black_king_e8 && black_rook_a8 { castling.push('q'); }
+
+    // A right the FEN already gave up stays gone (the king or rook left and came back)
+    if let Some(claimed) = parts.get(2) {
+        castling.retain(|right| claimed.contains(right));
+    }
+    if castling.is_empty() {
+        castling = "-".to_string();
+    }
+
+    // Rebuild FEN with corrected castling rights, keeping the tracked fields
+    let en_passant = parts.get(3).unwrap_or(&"-");
+    let halfmove = parts.get(4).unwrap_or(&"0");
+    let fullmove = parts.get(5).unwrap_or(&"1");
+    format!("{} {} {} {} {} {}", board, turn, castling, en_passant, halfmove, fullmove)
+}
+
+/// Collapses an 8×8 grid ('1' = empty, row 0 = rank 8) into a FEN piece placement field
+pub fn serialize_placement(grid: &[[char; 8]; 8]) -> String {
+    grid.iter()
+        .map(|row| {
+            let mut rank_str = String::new();
+            let mut empty_count = 0;
+            for &piece in row {
+                if piece == '1' {
+                    empty_count += 1;
+                } else {
+                    if empty_count > 0 {
+                        rank_str.push_str(&empty_count.to_string());
+                        empty_count = 0;
+                    }
+                    rank_str.push(piece);
+                }
+            }
+            if empty_count > 0 {
+                rank_str.push_str(&empty_count.to_string());
+            }
+            rank_str
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_validate_fen_accepts_valid_white() {
+        let valid = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let result = validate_fen(valid);
+        assert!(result.is_ok());
+        // Starting position should keep all castling rights
+        assert_eq!(result.unwrap(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    }
+
+    #[test]
+    fn test_validate_fen_accepts_valid_black() {
+        let valid = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        let result = validate_fen(valid);
+        assert!(result.is_ok());
+        // Kings and rooks still on starting squares, keep all castling rights
+        assert_eq!(result.unwrap(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+    }
+
+    #[test]
+    fn test_validate_fen_rejects_invalid() {
+        let invalid = "not a fen string";
+        assert!(validate_fen(invalid).is_err());
+    }
+
+    #[test]
+    fn test_validate_fen_rejects_too_many_white_pawns() {
+        // 9 white pawns (pawn on e4 + all 8 on rank 2) - common LLM error
+        let invalid = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1";
+        let result = validate_fen(invalid);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("9 pawns"));
+    }
+
+    #[test]
+    fn test_validate_fen_rejects_too_many_black_pawns() {
+        // 9 black pawns
+        let invalid = "rnbqkbnr/pppppppp/4p3/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let result = validate_fen(invalid);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("9 pawns"));
+    }
+
+    #[test]
+    fn test_fix_castling_removes_rights_when_king_moved() {
+        // Black king castled (on g8), but FEN claims KQkq - should fix to KQ only
+        let fen_with_bad_castling = "r4rk1/pp1p1ppp/1n6/2p5/3P2N1/3P1N2/PPPBP1PP/R2QKB1R b KQkq - 0 1";
+        let result = validate_fen(fen_with_bad_castling);
+        assert!(result.is_ok());
+        let corrected = result.unwrap();
+        // Black king not on e8, so no black castling rights
+        // White king on e1 with rooks on a1 and h1, so KQ
+        assert!(corrected.contains(" KQ ") || corrected.contains(" - "));
+        assert!(!corrected.contains("kq"));
+    }
+
+    #[test]
+    fn test_fix_castling_no_rights_when_both_kings_moved() {
+        // Both kings have moved - should have no castling rights
+        let fen = "r4rk1/pppppppp/8/8/8/8/PPPPPPPP/R3K2R b KQkq - 0 1";
+        let result = validate_fen(fen);
+        assert!(result.is_ok());
+        let corrected = result.unwrap();
+        // Black king on g8 (not e8), White king on e1 with rooks
+        // White should have KQ, Black should have none
+        assert!(corrected.contains(" KQ "));
+    }
+
+    #[test]
+    fn test_fix_castling_keeps_partial_rights() {
+        // White has only kingside rook, Black has both
+        let fen = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/4K2R w KQkq - 0 1";
+        let result = validate_fen(fen);
+        assert!(result.is_ok());
+        let corrected = result.unwrap();
+        // White: King on e1, only h1 rook → K only
+        // Black: King on e8, both rooks → kq
+        assert!(corrected.contains(" Kkq "));
+    }
+
+    #[test]
+    fn test_fix_castling_keeps_tracked_fields() {
+        let fen = "r3k2r/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/R3K2R b KQkq d3 4 12";
+        assert_eq!(fix_castling_rights(fen), fen);
+        // Rights can only be lost: a rook back on h1 doesn't bring K back
+        assert_eq!(fix_castling_rights("r3k2r/8/8/8/8/8/8/R3K2R w Qk - 7 30"), "r3k2r/8/8/8/8/8/8/R3K2R w Qk - 7 30");
+        // Missing fields are filled in
+        assert_eq!(fix_castling_rights("4k3/8/8/8/8/8/8/4K3 w KQkq"), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+    }
+
+    /// A placement with one king per side and random other pieces, and its pawn counts
+    fn board() -> impl Strategy<Value = (String, usize, usize)> {
+        let square = prop::sample::select(vec!['1', '1', '1', '1', '1', 'P', 'p', 'N', 'n', 'B', 'b', 'R', 'r', 'Q', 'q']);
+        (prop::collection::vec(square, 64), 0..64usize, 0..63usize).prop_map(|(mut squares, white_king, black_king)| {
+            squares[white_king] = 'K';
+            // Skip the white king's square
+            squares[if black_king >= white_king { black_king + 1 } else { black_king }] = 'k';
+            let count = |piece| squares.iter().filter(|&&c| c == piece).count();
+            let (white_pawns, black_pawns) = (count('P'), count('p'));
+            let grid: [[char; 8]; 8] = std::array::from_fn(|rank| std::array::from_fn(|file| squares[rank * 8 + file]));
+            (serialize_placement(&grid), white_pawns, black_pawns)
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_validate_never_panics(text in "\\PC{0,80}") {
+            let _ = validate_fen(&text);
+            let _ = fix_castling_rights(&text);
+        }
+
+        #[test]
+        fn prop_validate_accepts_exactly_the_possible_counts(
+            (placement, white_pawns, black_pawns) in board(),
+            turn in prop::sample::select(vec!["w", "b"]),
+        ) {
+            let fen = format!("{} {} KQkq - 0 1", placement, turn);
+            let result = validate_fen(&fen);
+            prop_assert_eq!(result.is_ok(), white_pawns <= 8 && black_pawns <= 8);
+            if let Ok(fixed) = result {
+                prop_assert!(shakmaty::fen::Fen::from_ascii(fixed.as_bytes()).is_ok());
+            }
+        }
+
+        #[test]
+        fn prop_fix_castling_is_idempotent_and_keeps_other_fields(
+            (placement, _, _) in board(),
+            castling in prop::sample::select(vec!["KQkq", "Kq", "k", "-"]),
+            en_passant in prop::sample::select(vec!["-", "e3", "d6"]),
+            halfmove in 0..100u32,
+            fullmove in 1..300u32,
+        ) {
+            let fen = format!("{} w {} {} {} {}", placement, castling, en_passant, halfmove, fullmove);
+            let fixed = fix_castling_rights(&fen);
+            prop_assert_eq!(fix_castling_rights(&fixed), fixed.clone());
+            let (before, after): (Vec<&str>, Vec<&str>) = (fen.split(' ').collect(), fixed.split(' ').collect());
+            prop_assert_eq!(&before[..2], &after[..2]);
+            prop_assert_eq!(&before[3..], &after[3..]);
+            // Every right left is one the FEN claimed, with its king and rook at home
+            let ranks: Vec<&str> = placement.split('/').collect();
+            let home = |rank: &str, file: usize, piece: char| {
+                let mut col = 0;
+                rank.chars().any(|c| match c.to_digit(10) {
+                    Some(n) => { col += n as usize; false }
+                    None => { col += 1; col - 1 == file && c == piece }
+                })
+            };
+            for right in after[2].chars().filter(|&c| c != '-') {
+                prop_assert!(castling.contains(right));
+                let (rank, king, rook) = if right.is_uppercase() { (ranks[7], 'K', 'R') } else { (ranks[0], 'k', 'r') };
+                let rook_file = if right.eq_ignore_ascii_case(&'k') { 7 } else { 0 };
+                prop_assert!(home(rank, 4, king) && home(rank, rook_file, rook));
+            }
+        }
+    }
+}
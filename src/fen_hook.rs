@@ -0,0 +1,121 @@
+//! FEN post-processor hook (`--fen-hook`)
+//!
+//! `--fen-hook "./fix_fen.py {side}"` runs a user-provided program on every recognized FEN
+//! before it reaches the engine, so users can apply their own corrections (e.g. a piece
+//! set their recognizer always confuses, or a known castling state) without touching the
+//! Rust code. Placeholders in the command template:
+//! - `{fen}`: the recognized FEN (shell-quoted)
+//! - `{side}`: "white" or "black" (the side at the bottom of the board)
+//!
+//! The FEN is also written to the program's stdin. The program prints the FEN to use on
+//! stdout (first non-empty line), or nothing to keep the recognized one. Its FEN goes
+//! through the same checks as a recognizer's (`fen::validate_fen`). A hook that fails, runs
+//! past the timeout, or prints an invalid FEN is reported and the recognized FEN is used.
+
+use anyhow::{Context, Result};
+use crate::PlayerSide;
+use crate::fen::validate_fen;
+use crate::ocr_command::{shell_command, shell_quote};
+use std::process::Stdio;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// How long the hook may run before it is killed; it holds up every cycle
+const HOOK_TIMEOUT_MS: u64 = 2000;
+
+/// Command template from `--fen-hook` (set once at startup)
+static COMMAND: OnceLock<String> = OnceLock::new();
+
+/// Sets the command template (first call wins)
+pub fn set_command(template: &str) {
+    let _ = COMMAND.set(template.to_string());
+}
+
+/// `fen` as the hook corrects it; the recognized FEN when no hook is set or it fails
+pub async fn apply(fen: String, player_side: PlayerSide) -> String {
+    let Some(template) = COMMAND.get() else {
+        return fen;
+    };
+    match run(template, &fen, player_side).await {
+        Ok(Some(corrected)) => corrected,
+        Ok(None) => fen,
+        Err(e) => {
+            eprintln!("⚠ FEN hook: {:#} - using the recognized FEN", e);
+            fen
+        }
+    }
+}
+
+/// Runs the hook on `fen`; None when it printed nothing
+async fn run(template: &str, fen: &str, player_side: PlayerSide) -> Result<Option<String>> {
+    let command_line = expand_template(template, fen, player_side);
+    let mut child = shell_command(&command_line)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run {}", command_line))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that only reads {fen} may exit without reading stdin
+        let _ = stdin.write_all(format!("{}\n", fen).as_bytes()).await;
+    }
+    let output = tokio::time::timeout(Duration::from_millis(HOOK_TIMEOUT_MS), child.wait_with_output())
+        .await
+        .with_context(|| format!("timed out after {}ms", HOOK_TIMEOUT_MS))?
+        .context("Failed to read the hook's output")?;
+    if !output.status.success() {
+        anyhow::bail!("exited with {}: {}", output.status, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    parse_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Substitutes the placeholders in the command template
+fn expand_template(template: &str, fen: &str, player_side: PlayerSide) -> String {
+    let side = match player_side {
+        PlayerSide::White => "white",
+        PlayerSide::Black => "black",
+    };
+    template.replace("{fen}", &shell_quote(fen)).replace("{side}", side)
+}
+
+/// The first non-empty stdout line, validated; None when there is none
+fn parse_output(stdout: &str) -> Result<Option<String>> {
+    match stdout.lines().map(str::trim).find(|line| !line.is_empty()) {
+        Some(line) => validate_fen(line).map(Some).context("printed an unusable FEN"),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn test_parse_output() {
+        assert_eq!(parse_output(&format!("\n{}\n", START)).unwrap().as_deref(), Some(START));
+        assert_eq!(parse_output("  \n").unwrap(), None);
+        // Same checks as recognized FENs: impossible boards are refused, and castling rights
+        // the pieces don't allow are dropped
+        let no_white_king = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1BNR w KQkq - 0 1";
+        assert!(parse_output(no_white_king).is_err());
+        let no_rook = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1";
+        assert_eq!(parse_output(no_rook).unwrap().as_deref(), Some("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w Qkq - 0 1"));
+    }
+
+    #[tokio::test]
+    #[cfg(not(windows))]
+    async fn test_hook_corrects_and_falls_back() {
+        let corrected = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        // Reads the FEN from stdin and prints another
+        let template = format!("read fen && test \"$fen\" = {{fen}} && echo '{}'", corrected);
+        assert_eq!(run(&template, START, PlayerSide::White).await.unwrap().as_deref(), Some(corrected));
+        assert_eq!(run("true", START, PlayerSide::White).await.unwrap(), None);
+        assert!(run("echo garbage", START, PlayerSide::White).await.is_err());
+        assert!(run("exit 3", START, PlayerSide::White).await.is_err());
+        assert!(run("sleep 5", START, PlayerSide::White).await.is_err());
+    }
+}
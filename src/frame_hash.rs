@@ -0,0 +1,159 @@
+//! Frame deduplication by perceptual hash
+//!
+//! In auto mode most captures show the same board as the previous one. Before any OCR
+//! work, the board region is reduced to a difference hash (the brightness gradient signs
+//! of a 33x32 grayscale thumbnail, 1024 bits - 16 per square, so a moved piece always
+//! flips some) and compared square by square with the last analyzed frame. A square only
+//! counts as changed when several of its bits flip, so a mouse cursor over the board or
+//! compression noise doesn't trigger a new cycle; a frame with no changed square is dropped.
+//!
+//! The board is located once and its region reused while frames keep matching, so a
+//! dropped frame costs one crop and one resize. Frames that do change are read, and when
+//! the recognized position is still the previous one (e.g. only a highlight changed) the
+//! engine step is skipped as well.
+
+use anyhow::Result;
+use image::{DynamicImage, GenericImageView, imageops};
+
+/// Thumbnail height in pixels (width is one more for the horizontal gradients)
+const HASH_SIZE: u32 = 32;
+
+/// Difference hash of a board region, one bit per thumbnail gradient
+pub type FrameHash = [u64; (HASH_SIZE * HASH_SIZE / 64) as usize];
+
+/// Thumbnail pixels per square side (4x4 = 16 bits per square)
+const SQUARE_SIZE: u32 = HASH_SIZE / 8;
+
+/// Flipped bits below which a square counts as unchanged (cursor, anti-aliasing, noise)
+const SQUARE_NOISE_BITS: u32 = 3;
+
+/// Remembers the last analyzed frame's board region, hash and position
+#[derive(Default)]
+pub struct FrameDeduper {
+    region: Option<(u32, u32, u32, u32)>,
+    last: Option<FrameHash>,
+    last_fen: Option<String>,
+}
+
+impl FrameDeduper {
+    /// True when no square of the screenshot's board changed since the last analyzed frame
+    pub fn is_duplicate(&mut self, img: &DynamicImage) -> Result<bool> {
+        let (img_w, img_h) = img.dimensions();
+        if let (Some((x, y, w, h)), Some(last)) = (self.region, self.last)
+            && x + w <= img_w
+            && y + h <= img_h
+            && changed_squares(&dhash(&img.crop_imm(x, y, w, h)), &last) == 0
+        {
+            return Ok(true);
+        }
+
+        // Changed, or the board moved: locate it again
+        let region = crate::ocr_native::locate_board(img)?;
+        let (x, y, w, h) = region;
+        let hash = dhash(&img.crop_imm(x, y, w, h));
+        let duplicate = self.last.is_some_and(|last| changed_squares(&hash, &last) == 0);
+        self.region = Some(region);
+        // Keep comparing against the analyzed frame so small changes can't add up unnoticed
+        if !duplicate {
+            self.last = Some(hash);
+        }
+        Ok(duplicate)
+    }
+
+    /// True when `fen` is the position analyzed last (the picture changed, the position didn't)
+    pub fn is_same_position(&mut self, fen: &str) -> bool {
+        if self.last_fen.as_deref() == Some(fen) {
+            return true;
+        }
+        self.last_fen = Some(fen.to_string());
+        false
+    }
+
+    /// Forgets the previous frame so the next one is analyzed (e.g. after a setting change)
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.last_fen = None;
+    }
+}
+
+/// Number of squares with at least `SQUARE_NOISE_BITS` differing bits
+pub fn changed_squares(a: &FrameHash, b: &FrameHash) -> usize {
+    let mut flipped = [0u32; 64];
+    for bit in 0..(HASH_SIZE * HASH_SIZE) as usize {
+        if (a[bit / 64] ^ b[bit / 64]) >> (bit % 64) & 1 == 1 {
+            let (x, y) = (bit as u32 % HASH_SIZE, bit as u32 / HASH_SIZE);
+            flipped[((y / SQUARE_SIZE) * 8 + x / SQUARE_SIZE) as usize] += 1;
+        }
+    }
+    flipped.iter().filter(|&&count| count >= SQUARE_NOISE_BITS).count()
+}
+
+/// Difference hash of a board region
+pub fn dhash(img: &DynamicImage) -> FrameHash {
+    let thumb = imageops::resize(&img.to_luma8(), HASH_SIZE + 1, HASH_SIZE, imageops::FilterType::Triangle);
+    let mut hash = FrameHash::default();
+    for y in 0..HASH_SIZE {
+        for x in 0..HASH_SIZE {
+            if thumb.get_pixel(x, y)[0] > thumb.get_pixel(x + 1, y)[0] {
+                let bit = (y * HASH_SIZE + x) as usize;
+                hash[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    /// Checkered 240px board with a dark disc on the given square
+    fn board(piece: (u32, u32)) -> DynamicImage {
+        let mut img = RgbImage::from_fn(240, 240, |x, y| {
+            if (x / 30 + y / 30) % 2 == 0 { Rgb([240, 217, 181]) } else { Rgb([181, 136, 99]) }
+        });
+        let (cx, cy) = (piece.0 * 30 + 15, piece.1 * 30 + 15);
+        for y in cy - 10..cy + 10 {
+            for x in cx - 10..cx + 10 {
+                if (x as i32 - cx as i32).pow(2) + (y as i32 - cy as i32).pow(2) <= 100 {
+                    img.put_pixel(x, y, Rgb([20, 20, 20]));
+                }
+            }
+        }
+        DynamicImage::ImageRgb8(img)
+    }
+
+    #[test]
+    fn test_same_board_same_hash() {
+        assert_eq!(dhash(&board((4, 6))), dhash(&board((4, 6))));
+    }
+
+    #[test]
+    fn test_moved_piece_changes_squares() {
+        assert_eq!(changed_squares(&dhash(&board((4, 6))), &dhash(&board((4, 4)))), 2);
+        assert_eq!(changed_squares(&dhash(&board((0, 0))), &dhash(&board((1, 0)))), 2);
+    }
+
+    #[test]
+    fn test_cursor_is_not_a_change() {
+        let plain = board((4, 6));
+        let mut hovered = plain.to_rgb8();
+        // Small arrow-sized mark on an empty square
+        for y in 95..103 {
+            for x in 100..104 {
+                hovered.put_pixel(x, y, Rgb([255, 255, 255]));
+            }
+        }
+        assert_eq!(changed_squares(&dhash(&plain), &dhash(&DynamicImage::ImageRgb8(hovered))), 0);
+    }
+
+    #[test]
+    fn test_same_position_is_reported_once_changed() {
+        let mut deduper = FrameDeduper::default();
+        assert!(!deduper.is_same_position("8/8/8/8/8/8/8/8 w - - 0 1"));
+        assert!(deduper.is_same_position("8/8/8/8/8/8/8/8 w - - 0 1"));
+        deduper.reset();
+        assert!(!deduper.is_same_position("8/8/8/8/8/8/8/8 w - - 0 1"));
+    }
+}
@@ -0,0 +1,309 @@
+//! Game tree: mainline plus variations
+//!
+//! Every position reached is a node; a node's first child is the main continuation and
+//! any further children are variations. Nodes carry the move that led to them (SAN), an
+//! optional comment, and NAGs (`$2` = mistake, ...). A cursor marks the current position
+//! for navigation (back/forward/jump).
+//!
+//! Used for session PGN export, analysis board tracking, and the what-if exploration of
+//! the typed `move` command.
+
+use anyhow::{Context, Result};
+use crate::pgn;
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::{Board, Chess, Color, EnPassantMode, Move, Position};
+
+/// Index of a node in its tree
+pub type NodeId = usize;
+
+/// The starting position of every tree
+pub const ROOT: NodeId = 0;
+
+/// Standard NAGs
+pub const NAG_MISTAKE: u8 = 2;
+pub const NAG_BLUNDER: u8 = 4;
+pub const NAG_DUBIOUS: u8 = 6;
+
+struct Node {
+    position: Chess,
+    /// Move leading here in SAN (None for the root)
+    san: Option<String>,
+    /// Move number and mover of that move
+    number: u32,
+    white_moved: bool,
+    parent: Option<NodeId>,
+    /// First child is the main continuation, the rest are variations
+    children: Vec<NodeId>,
+    nags: Vec<u8>,
+    comment: Option<String>,
+}
+
+impl Node {
+    fn root(position: Chess) -> Self {
+        Node {
+            position,
+            san: None,
+            number: 0,
+            white_moved: false,
+            parent: None,
+            children: Vec::new(),
+            nags: Vec::new(),
+            comment: None,
+        }
+    }
+}
+
+/// A game with variations and a cursor
+pub struct GameTree {
+    nodes: Vec<Node>,
+    cursor: NodeId,
+}
+
+impl GameTree {
+    pub fn new(position: Chess) -> Self {
+        GameTree {
+            nodes: vec![Node::root(position)],
+            cursor: ROOT,
+        }
+    }
+
+    /// Starts a tree from a FEN (castling rights are inferred when missing, see `pgn::parse_position`)
+    pub fn from_fen(fen: &str) -> Result<Self> {
+        let position = pgn::parse_position(fen).with_context(|| format!("Not a legal position: {}", fen))?;
+        Ok(Self::new(position))
+    }
+
+    pub fn cursor(&self) -> NodeId {
+        self.cursor
+    }
+
+    /// Position at the cursor
+    pub fn position(&self) -> &Chess {
+        &self.nodes[self.cursor].position
+    }
+
+    /// FEN at the cursor
+    pub fn fen(&self) -> String {
+        Fen::from_position(self.position(), EnPassantMode::Legal).to_string()
+    }
+
+    /// FEN of the starting position
+    pub fn start_fen(&self) -> String {
+        Fen::from_position(&self.nodes[ROOT].position, EnPassantMode::Legal).to_string()
+    }
+
+    /// Number of moves in the tree (all variations)
+    pub fn move_count(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    /// First node whose board matches
+    pub fn find(&self, board: &Board) -> Option<NodeId> {
+        self.nodes.iter().position(|n| n.position.board() == board)
+    }
+
+    /// Every node id, in creation order
+    pub fn node_ids(&self) -> std::ops::Range<NodeId> {
+        0..self.nodes.len()
+    }
+
+    /// Position of any node
+    pub fn position_at(&self, id: NodeId) -> &Chess {
+        &self.nodes[id].position
+    }
+
+    pub fn goto(&mut self, id: NodeId) {
+        if id < self.nodes.len() {
+            self.cursor = id;
+        }
+    }
+
+    /// Steps to the previous position; false at the start
+    pub fn back(&mut self) -> bool {
+        match self.nodes[self.cursor].parent {
+            Some(parent) => {
+                self.cursor = parent;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Steps along the main continuation; false at the end of the line
+    pub fn forward(&mut self) -> bool {
+        match self.nodes[self.cursor].children.first() {
+            Some(&child) => {
+                self.cursor = child;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Plays a legal move at the cursor, following an existing continuation when it is the
+    /// same move. Returns true when the move started a new variation.
+    pub fn play(&mut self, m: Move) -> bool {
+        let at = self.cursor;
+        let mut position = self.nodes[at].position.clone();
+        let number = position.fullmoves().get();
+        let white_moved = position.turn() == Color::White;
+        let san = SanPlus::from_move_and_play_unchecked(&mut position, m).to_string();
+
+        let existing = self.nodes[at].children.iter().copied().find(|&c| self.nodes[c].san.as_deref() == Some(&san));
+        if let Some(child) = existing {
+            self.cursor = child;
+            return false;
+        }
+
+        let branched = !self.nodes[at].children.is_empty();
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            san: Some(san),
+            number,
+            white_moved,
+            parent: Some(at),
+            ..Node::root(position)
+        });
+        self.nodes[at].children.push(id);
+        self.cursor = id;
+        branched
+    }
+
+    /// Plays a move given as text (SAN, or anything `engine::apply_move` accepts such as
+    /// UCI or "E2 to E4") at the cursor
+    pub fn play_text(&mut self, text: &str) -> Result<bool> {
+        let san = text.trim().parse::<SanPlus>().ok().and_then(|san| san.san.to_move(self.position()).ok());
+        let m = match san {
+            Some(m) => m,
+            None => {
+                let next = crate::engine::apply_move(&self.fen(), text)?;
+                let target = pgn::parse_position(&next).context("Move produced an invalid position")?;
+                pgn::connect(self.position(), target.board())
+                    .and_then(|moves| moves.first().copied())
+                    .with_context(|| format!("'{}' is not a legal move here", text.trim()))?
+            }
+        };
+        Ok(self.play(m))
+    }
+
+    /// Appends to the comment after the move at the cursor (before the first move at the root)
+    pub fn add_comment(&mut self, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        let comment = &mut self.nodes[self.cursor].comment;
+        *comment = Some(match comment.take() {
+            Some(existing) => format!("{} {}", existing, text),
+            None => text.to_string(),
+        });
+    }
+
+    /// Attaches a NAG to the move leading to `id`
+    pub fn add_nag(&mut self, id: NodeId, nag: u8) {
+        if id != ROOT && !self.nodes[id].nags.contains(&nag) {
+            self.nodes[id].nags.push(nag);
+        }
+    }
+
+    /// PGN movetext with comments, NAGs, and variations in parentheses (no result)
+    pub fn movetext(&self) -> String {
+        let mut tokens = Vec::new();
+        let mut needs_number = true;
+        if let Some(comment) = &self.nodes[ROOT].comment {
+            tokens.push(format_comment(comment));
+        }
+        self.write_line(ROOT, &mut needs_number, &mut tokens);
+        tokens.join(" ").replace("( ", "(").replace(" )", ")")
+    }
+
+    /// Complete PGN for this tree
+    pub fn to_pgn(&self, headers: &pgn::Headers) -> String {
+        pgn::render_game(headers, &self.start_fen(), &self.movetext())
+    }
+
+    /// Writes the continuation after `parent`, each alternative as a variation
+    fn write_line(&self, parent: NodeId, needs_number: &mut bool, tokens: &mut Vec<String>) {
+        let mut at = parent;
+        while let Some((&main, alternatives)) = self.nodes[at].children.split_first() {
+            self.write_move(main, needs_number, tokens);
+            for &alt in alternatives {
+                tokens.push("(".to_string());
+                let mut alt_number = true;
+                self.write_move(alt, &mut alt_number, tokens);
+                self.write_line(alt, &mut alt_number, tokens);
+                tokens.push(")".to_string());
+                *needs_number = true;
+            }
+            at = main;
+        }
+    }
+
+    fn write_move(&self, id: NodeId, needs_number: &mut bool, tokens: &mut Vec<String>) {
+        let node = &self.nodes[id];
+        if node.white_moved {
+            tokens.push(format!("{}.", node.number));
+        } else if *needs_number {
+            tokens.push(format!("{}...", node.number));
+        }
+        tokens.push(node.san.clone().unwrap_or_default());
+        tokens.extend(node.nags.iter().map(|nag| format!("${}", nag)));
+        *needs_number = false;
+        if let Some(comment) = &node.comment {
+            tokens.push(format_comment(comment));
+            *needs_number = true;
+        }
+    }
+}
+
+/// `{ text }`, with braces in the text replaced so they can't end the comment early
+fn format_comment(text: &str) -> String {
+    format!("{{ {} }}", text.replace('{', "(").replace('}', ")"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn test_variations_comments_and_nags() {
+        let mut tree = GameTree::from_fen(START).unwrap();
+        tree.play_text("e4").unwrap();
+        tree.play_text("e7e5").unwrap();
+        tree.add_comment("Open game");
+        tree.play_text("G1 to F3").unwrap();
+        assert!(tree.back());
+        assert!(tree.play_text("f4").unwrap(), "second move at a node starts a variation");
+        tree.add_nag(tree.cursor(), NAG_DUBIOUS);
+        assert_eq!(tree.move_count(), 4);
+        assert_eq!(tree.movetext(), "1. e4 e5 { Open game } 2. Nf3 (2. f4 $6)");
+    }
+
+    #[test]
+    fn test_black_variation_numbering_and_navigation() {
+        let mut tree = GameTree::from_fen(START).unwrap();
+        tree.play_text("e4").unwrap();
+        tree.play_text("e5").unwrap();
+        tree.back();
+        tree.play_text("c5").unwrap();
+        tree.goto(ROOT);
+        assert!(tree.forward());
+        assert!(tree.forward());
+        assert_eq!(tree.fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2");
+        assert!(!tree.forward());
+        assert_eq!(tree.movetext(), "1. e4 e5 (1... c5)");
+    }
+
+    #[test]
+    fn test_replaying_same_move_follows_existing_node() {
+        let mut tree = GameTree::from_fen(START).unwrap();
+        tree.play_text("d4").unwrap();
+        tree.back();
+        assert!(!tree.play_text("d2d4").unwrap());
+        assert_eq!(tree.move_count(), 1);
+        assert!(tree.play_text("Ke2").is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//! Guess-the-move training on master games
+//!
+//! `zugzwang-rs guess game.pgn` steps through a game's mainline. At each of the chosen
+//! side's moves (`--side`, both sides when omitted) the user guesses the next move before
+//! it is revealed. The game move earns full points; any other legal guess is scored by the
+//! engine against the game move, so a sound alternative still earns something. The session
+//! ends with the points total, how often the game and engine moves were found, and the
+//! same accuracy review as `play` (guesses measured against the engine's best move).
+
+use anyhow::{Context, Result};
+use crate::PlayerSide;
+use crate::engine;
+use crate::pgn;
+use crate::play;
+use crate::sparring::Review;
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::{CastlingMode, Chess, Color, EnPassantMode, Position};
+use std::io::{self, BufRead, Write};
+
+/// Points for finding the game move
+const MAX_POINTS: u32 = 5;
+
+/// Running score of a guessing session
+#[derive(Debug, Default)]
+struct Tally {
+    guesses: u32,
+    points: u32,
+    game_moves: u32,
+    engine_moves: u32,
+}
+
+impl Tally {
+    fn report(&self) -> String {
+        if self.guesses == 0 {
+            return "No moves guessed.".to_string();
+        }
+        let max = self.guesses * MAX_POINTS;
+        [
+            "── Guess the move ───────────────────────────────────────────".to_string(),
+            format!("Score:         {}/{} ({:.0}%)", self.points, max, 100.0 * self.points as f64 / max as f64),
+            format!("Game moves:    {}/{} found", self.game_moves, self.guesses),
+            format!("Engine moves:  {}/{} found", self.engine_moves, self.guesses),
+        ]
+        .join("\n")
+    }
+}
+
+/// Runs a guessing session over the first game in the PGN file at `path`
+pub fn run(path: &str, side: Option<PlayerSide>, depth: u16) -> Result<()> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+    let game = pgn::read_game(&text)?;
+
+    let tag = |name| game.headers.get(name).filter(|v| *v != "?").unwrap_or("?");
+    println!("{} - {} ({}, {})", tag("White"), tag("Black"), tag("Event"), tag("Date"));
+    match side {
+        Some(side) => println!("Guess {}'s moves (engine depth {}).", side, depth),
+        None => println!("Guess every move (engine depth {}).", depth),
+    }
+    println!("Type a move (\"Nf3\", \"g1f3\", \"G1 to F3\"), 'skip', 'board', or 'quit'.");
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut tally = Tally::default();
+    let mut review = Review::default();
+    let mut position = game.start.clone();
+
+    for &game_move in &game.moves {
+        let mover = if position.turn() == Color::White { PlayerSide::White } else { PlayerSide::Black };
+        let label = move_label(&position);
+        let san = SanPlus::from_move(position.clone(), game_move).to_string();
+        if side.is_some_and(|s| s != mover) {
+            println!("{} {}", label, san);
+            position.play_unchecked(game_move);
+            continue;
+        }
+
+        let fen = Fen::from_position(&position, EnPassantMode::Legal).to_string();
+        println!();
+        println!("{}", play::render_board(&fen, side.unwrap_or(PlayerSide::White))?);
+
+        // Read until a legal guess (or skip) is entered
+        let guess = loop {
+            print!("{} {} to move - your guess: ", label, mover);
+            io::stdout().flush()?;
+            let Some(line) = lines.next() else {
+                println!();
+                return finish(&tally, &review); // stdin closed
+            };
+            match line.context("Failed to read move")?.trim() {
+                "quit" | "q" => return finish(&tally, &review),
+                "skip" | "s" => break None,
+                "board" => println!("{}", play::render_board(&fen, side.unwrap_or(PlayerSide::White))?),
+                "fen" => println!("FEN:  {}", fen),
+                text => match parse_guess(&position, &fen, text)? {
+                    Some(guess) => break Some(guess),
+                    None => println!("'{}' is not a legal move here", text),
+                },
+            }
+        };
+
+        let game_readable = engine::format_move_readable(&game_move.to_uci(CastlingMode::Standard).to_string());
+        match guess {
+            None => println!("{} {} was played", label, san),
+            Some(guess) => {
+                let (guess, guess_cp) = engine::score_move(&fen, &guess, depth)?.context("Guess is not legal")?;
+                let (_, game_cp) = engine::score_move(&fen, &game_readable, depth)?.context("Game move is not legal")?;
+                let (best, best_cp) = engine::best_scored_move(&fen, depth)?.context("No legal moves")?;
+                // A restricted search can edge out the full one; the best move is never worse
+                let best_cp = best_cp.max(guess_cp).max(game_cp);
+
+                let is_game_move = guess == game_readable;
+                let earned = points(is_game_move, guess_cp, game_cp);
+                tally.guesses += 1;
+                tally.points += earned;
+                tally.game_moves += u32::from(is_game_move);
+                tally.engine_moves += u32::from(guess == best);
+                review.record(&guess, guess_cp, &best, best_cp, false);
+
+                if is_game_move {
+                    println!("✓ {} {} - the game move ({})  +{}", label, san, engine::format_eval(game_cp), earned);
+                } else {
+                    println!(
+                        "✗ You: {} ({})  +{}   Game: {} {} ({})",
+                        guess,
+                        engine::format_eval(guess_cp),
+                        earned,
+                        label,
+                        san,
+                        engine::format_eval(game_cp)
+                    );
+                }
+                if best != guess && best != game_readable {
+                    println!("  Engine: {} ({})", best, engine::format_eval(best_cp));
+                }
+            }
+        }
+        position.play_unchecked(game_move);
+    }
+
+    println!();
+    println!("End of game ({}).", tag("Result"));
+    finish(&tally, &review)
+}
+
+/// Prints the session summary
+fn finish(tally: &Tally, review: &Review) -> Result<()> {
+    println!("{}", tally.report());
+    if tally.guesses > 0 {
+        println!("{}", review.report());
+    }
+    Ok(())
+}
+
+/// Points for a guess: full marks for the game move, otherwise by how the engine rates the
+/// guess against the game move
+fn points(is_game_move: bool, guess_cp: i32, game_cp: i32) -> u32 {
+    if is_game_move {
+        return MAX_POINTS;
+    }
+    match game_cp - guess_cp {
+        loss if loss <= 0 => MAX_POINTS - 1, // at least as good as the game move
+        loss if loss <= 50 => 3,
+        loss if loss <= 150 => 1,
+        _ => 0,
+    }
+}
+
+/// Move number prefix for the side to move ("12." or "12...")
+fn move_label(position: &Chess) -> String {
+    let dots = if position.turn() == Color::White { "." } else { "..." };
+    format!("{}{}", position.fullmoves(), dots)
+}
+
+/// Readable form of a guess in SAN or any notation the engine accepts; None if illegal
+fn parse_guess(position: &Chess, fen: &str, text: &str) -> Result<Option<String>> {
+    let san = text.parse::<SanPlus>().ok().and_then(|san| san.san.to_move(position).ok());
+    match san {
+        Some(m) => Ok(Some(engine::format_move_readable(&m.to_uci(CastlingMode::Standard).to_string()))),
+        None => engine::normalize_move(fen, text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_points_grade_alternatives_against_game_move() {
+        assert_eq!(points(true, -300, 50), MAX_POINTS);
+        assert_eq!(points(false, 80, 50), MAX_POINTS - 1);
+        assert_eq!(points(false, 20, 50), 3);
+        assert_eq!(points(false, -50, 50), 1);
+        assert_eq!(points(false, -300, 50), 0);
+    }
+
+    #[test]
+    fn test_parse_guess_accepts_san_and_engine_notation() {
+        let position = Chess::default();
+        let fen = Fen::from_position(&position, EnPassantMode::Legal).to_string();
+        assert_eq!(parse_guess(&position, &fen, "Nf3").unwrap().as_deref(), Some("G1 to F3"));
+        assert_eq!(parse_guess(&position, &fen, "e2e4").unwrap().as_deref(), Some("E2 to E4"));
+        assert_eq!(parse_guess(&position, &fen, "Nf6").unwrap(), None);
+    }
+
+    #[test]
+    fn test_move_label() {
+        let mut position = Chess::default();
+        assert_eq!(move_label(&position), "1.");
+        let m = "e4".parse::<SanPlus>().unwrap().san.to_move(&position).unwrap();
+        position.play_unchecked(m);
+        assert_eq!(move_label(&position), "1...");
+    }
+}
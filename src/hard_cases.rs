@@ -0,0 +1,119 @@
+//! Hard-case recorder for OCR misreads
+//!
+//! Whenever a misread is caught - an LLM FEN that failed validation before a retry
+//! succeeded, or a square the user corrected by hand - the source image and both FENs
+//! are saved under `hard_cases/`. Each case gets its own image file and a line in
+//! `hard_cases/cases.jsonl`, so prompt/template tuning (and a future OCR benchmark)
+//! can replay exactly the positions that went wrong.
+
+use anyhow::{Context, Result};
+use crate::capture;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+
+/// Directory holding recorded cases
+pub const HARD_CASES_DIR: &str = "hard_cases";
+
+/// One recorded misread, serialized as a JSON line in the manifest
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HardCase {
+    /// Milliseconds since the Unix epoch when the case was recorded
+    pub timestamp_ms: u128,
+    /// What caught the misread ("llm-validation-retry", "manual-correction")
+    pub source: String,
+    /// Image file (relative to `hard_cases/`)
+    pub image: String,
+    /// FEN the OCR produced
+    pub wrong_fen: String,
+    /// FEN after the retry/correction
+    pub corrected_fen: String,
+    /// ID of the cycle whose capture was misread
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycle: Option<String>,
+}
+
+/// Stores the image (lossless) and appends the case to `hard_cases/cases.jsonl`.
+/// Returns the path of the stored image.
+pub fn record(image: &DynamicImage, wrong_fen: &str, corrected_fen: &str, source: &str) -> Result<String> {
+    std::fs::create_dir_all(HARD_CASES_DIR).context("Failed to create hard_cases directory")?;
+
+    let timestamp_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+
+    let image_name = format!("{}_{}.png", timestamp_ms, source);
+    let stored_path = format!("{}/{}", HARD_CASES_DIR, image_name);
+    std::fs::write(&stored_path, capture::encode(image, capture::Consumer::TemplateMatching)?)
+        .with_context(|| format!("Failed to write {}", stored_path))?;
+
+    let case = HardCase {
+        timestamp_ms,
+        source: source.to_string(),
+        image: image_name,
+        wrong_fen: wrong_fen.to_string(),
+        corrected_fen: corrected_fen.to_string(),
+        cycle: capture::current_cycle(),
+    };
+
+    let mut manifest = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(format!("{}/cases.jsonl", HARD_CASES_DIR))
+        .context("Failed to open hard case manifest")?;
+    let line = serde_json::to_string(&case).context("Failed to serialize hard case")?;
+    writeln!(manifest, "{}", line).context("Failed to write hard case manifest")?;
+
+    Ok(stored_path)
+}
+
+/// Loads all cases from `{dir}/cases.jsonl` (malformed lines are skipped with a warning)
+pub fn load(dir: &str) -> Result<Vec<HardCase>> {
+    let manifest = format!("{}/cases.jsonl", dir);
+    let content = std::fs::read_to_string(&manifest)
+        .with_context(|| format!("Failed to read hard case manifest: {}", manifest))?;
+
+    let mut cases = Vec::new();
+    for (line_no, line) in content.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        match serde_json::from_str::<HardCase>(line) {
+            Ok(case) => cases.push(case),
+            Err(e) => eprintln!("⚠ Skipping {} line {}: {}", manifest, line_no + 1, e),
+        }
+    }
+    Ok(cases)
+}
+
+/// Records a case, logging instead of failing: telemetry must never break a cycle
+pub fn record_or_warn(image: &DynamicImage, wrong_fen: &str, corrected_fen: &str, source: &str) {
+    match record(image, wrong_fen, corrected_fen, source) {
+        Ok(path) => eprintln!("Saved hard case: {}", path),
+        Err(e) => eprintln!("⚠ Could not save hard case: {:#}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hard_case_serializes_all_fields() {
+        let case = HardCase {
+            timestamp_ms: 42,
+            source: "manual-correction".to_string(),
+            image: "42_manual-correction.jpg".to_string(),
+            wrong_fen: "8/8/8/8/8/8/8/K6k w - - 0 1".to_string(),
+            corrected_fen: "8/8/8/8/8/8/8/K5k1 w - - 0 1".to_string(),
+            cycle: None,
+        };
+        let json = serde_json::to_string(&case).unwrap();
+        assert!(json.contains("\"source\":\"manual-correction\""));
+        assert!(json.contains("\"wrong_fen\":\"8/8/8/8/8/8/8/K6k w - - 0 1\""));
+        assert!(json.contains("\"corrected_fen\""));
+        assert!(json.contains("\"timestamp_ms\":42"));
+
+        let parsed: HardCase = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.corrected_fen, case.corrected_fen);
+    }
+}
@@ -0,0 +1,164 @@
+//! End-to-end test harness
+//!
+//! Runs the whole live pipeline (`run`) headlessly: frames come from canned images through
+//! `--input replay:<dir>`, the LLM is a mock chat completions server reached through
+//! `--llm-base-url` (keyless, as the ollama provider), and results are read back from a
+//! `json-file` sink. The loop ends by itself once the replayed frames run out.
+//!
+//! tanton's search has no randomness (its parallel splits are joined in a fixed order), so
+//! at a fixed `--depth` the engine's answers are the same on every run and can be compared
+//! with a direct `engine` call.
+
+use anyhow::{Context, Result};
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+/// Chat completions server answering each request with the next canned reply (the last
+/// one repeats)
+pub struct MockLlm {
+    pub url: String,
+    requests: Arc<AtomicUsize>,
+}
+
+impl MockLlm {
+    pub async fn start(replies: &[&str]) -> Result<Self> {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await?;
+        let url = format!("http://127.0.0.1:{}/v1/chat/completions", listener.local_addr()?.port());
+        let replies: Vec<String> = replies.iter().map(|reply| reply.to_string()).collect();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let index = counter.fetch_add(1, Ordering::Relaxed);
+                let reply = replies[index.min(replies.len() - 1)].clone();
+                tokio::spawn(async move {
+                    let _ = answer(stream, &reply).await;
+                });
+            }
+        });
+        Ok(MockLlm { url, requests })
+    }
+
+    /// Requests received so far
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+}
+
+/// Reads one request (head and body) and answers it with `reply` as the model's message
+async fn answer(stream: tokio::net::TcpStream, reply: &str) -> Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse()?;
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+
+    let body = serde_json::json!({
+        "choices": [{ "message": { "content": reply } }],
+        "usage": { "total_tokens": 100 },
+    })
+    .to_string();
+    let mut stream = reader.into_inner();
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// A fresh scratch directory for one test
+pub fn scratch_dir(name: &str) -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("zugzwang-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Writes `count` distinct board-like frames (`frame-00.png`, ...): a checkerboard with a
+/// marker on a different square each time, so none is dropped as a duplicate
+pub fn write_frames(dir: &Path, count: usize) -> Result<()> {
+    for index in 0..count {
+        let marker = (index as u32 * 9) % 64;
+        let frame = RgbaImage::from_fn(512, 512, |x, y| {
+            let (col, row) = (x / 64, y / 64);
+            let (dx, dy) = (x % 64, y % 64);
+            if row * 8 + col == marker && (16..48).contains(&dx) && (16..48).contains(&dy) {
+                Rgba([20, 20, 20, 255])
+            } else if (col + row) % 2 == 0 {
+                Rgba([240, 217, 181, 255])
+            } else {
+                Rgba([181, 136, 99, 255])
+            }
+        });
+        frame.save(dir.join(format!("frame-{:02}.png", index)))?;
+    }
+    Ok(())
+}
+
+/// Runs the pipeline with `args` (after the program name) and the saved defaults left out,
+/// returning the JSON results it wrote to `results`
+pub async fn run_pipeline(args: &[&str], results: &Path) -> Result<Vec<serde_json::Value>> {
+    let sink = format!("json-file:{}", results.display());
+    let args = std::iter::once("zugzwang").chain(args.iter().copied()).chain(["--output", &sink]);
+    crate::run(args.map(String::from).collect::<Vec<_>>(), crate::config::Config::default()).await?;
+    std::fs::read_to_string(results)
+        .context("Pipeline wrote no results")?
+        .lines()
+        .map(|line| serde_json::from_str(line).context("Invalid JSON result"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_engine_pipeline_end_to_end() {
+        let dir = scratch_dir("e2e").unwrap();
+        let frames = dir.join("frames");
+        std::fs::create_dir_all(&frames).unwrap();
+        write_frames(&frames, 2).unwrap();
+        // The LLM reads 1. e4 and then 1... e5; we play Black
+        let after_e4 = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        let after_e5 = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        let replies = [after_e4, after_e5].map(|fen| serde_json::json!({ "fen": fen }).to_string());
+        let llm = MockLlm::start(&[&replies[0], &replies[1]]).await.unwrap();
+
+        let input = format!("replay:{}", frames.display());
+        let args = [
+            "--input", &input, "--ocr", "llm", "--llm-provider", "ollama", "--llm-base-url", &llm.url,
+            "--analysis", "engine", "--trigger", "auto", "--interval", "0", "--side", "black", "--depth", "2",
+            "--time-control", "10+0", "--no-player-ocr", "--json",
+        ];
+        let results = run_pipeline(&args, &dir.join("results.jsonl")).await.unwrap();
+
+        assert_eq!(llm.requests(), 2);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["mode"], "engine");
+        assert_eq!(results[0]["fen"], after_e4);
+        let (best, eval) = crate::engine::analyze_position(after_e4, 2).unwrap();
+        assert_eq!((results[0]["best_move"].as_str(), results[0]["evaluation"].as_str()), (Some(&*best), Some(&*eval)));
+        // The second board follows from the first: White is to move (the history overrides the
+        // recognized side), so the analysis is the opponent's
+        assert_eq!(results[1]["fen"], "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2");
+        assert_eq!(results[1]["opponent_to_move"], true);
+        assert!(results.iter().all(|result| result["cycle"].is_string()));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,175 @@
+//! Square-control heatmap
+//!
+//! A single suggested move says little about why; a map of who controls which squares
+//! shows weak squares and pieces under fire at a glance. For each square this counts the
+//! pieces of each side attacking it (directly - x-rays through other pieces don't count)
+//! and draws the board with the difference from the player's point of view: "+2" is a
+//! square the player covers twice more than the opponent. On a color terminal the squares
+//! are shaded too, green for the player's, red for the opponent's, yellow where control is
+//! even but contested.
+//!
+//! Below the board, pieces attacked more often than they are defended are listed for both
+//! sides. The map is shown with `heat` while the loop runs and `analyze --heatmap`.
+
+use anyhow::Result;
+use crate::PlayerSide;
+use shakmaty::fen::Fen;
+use shakmaty::{Board, Color, File, Rank, Role, Square};
+use std::io::IsTerminal;
+
+/// Attackers of every square, by side (indexed by `Square`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Control {
+    board: Board,
+    white: [u8; 64],
+    black: [u8; 64],
+}
+
+impl Control {
+    /// Counts the attackers of every square in the FEN's position
+    pub fn of(fen: &str) -> Result<Self> {
+        let board = Fen::from_ascii(fen.trim().as_bytes())
+            .map_err(|e| anyhow::anyhow!("Invalid FEN ({}): {}", e, fen))?
+            .into_setup()
+            .board;
+        let (mut white, mut black) = ([0; 64], [0; 64]);
+        for square in Square::ALL {
+            white[square as usize] = board.attacks_to(square, Color::White, board.occupied()).count() as u8;
+            black[square as usize] = board.attacks_to(square, Color::Black, board.occupied()).count() as u8;
+        }
+        Ok(Control { board, white, black })
+    }
+
+    /// Attackers of `square` by `color`
+    pub fn attackers(&self, square: Square, color: Color) -> u8 {
+        match color {
+            Color::White => self.white[square as usize],
+            Color::Black => self.black[square as usize],
+        }
+    }
+
+    /// `color`'s attackers of `square` minus the other side's
+    pub fn net(&self, square: Square, color: Color) -> i8 {
+        self.attackers(square, color) as i8 - self.attackers(square, color.other()) as i8
+    }
+
+    /// Pieces attacked more often than defended (kings aside): square, piece letter,
+    /// attackers, defenders; the most outnumbered first
+    pub fn hanging(&self) -> Vec<(Square, char, u8, u8)> {
+        let mut hanging: Vec<_> = self
+            .board
+            .occupied()
+            .into_iter()
+            .filter_map(|square| {
+                let piece = self.board.piece_at(square)?;
+                let attackers = self.attackers(square, piece.color.other());
+                let defenders = self.attackers(square, piece.color);
+                (piece.role != Role::King && attackers > defenders).then_some((square, piece.char(), attackers, defenders))
+            })
+            .collect();
+        hanging.sort_by_key(|&(square, _, attackers, defenders)| (std::cmp::Reverse(attackers - defenders), square));
+        hanging
+    }
+
+    /// Net control per square from White's point of view, rank 8 first (for JSON)
+    pub fn rows(&self) -> Vec<Vec<i8>> {
+        (0..8u32)
+            .rev()
+            .map(|rank| (0..8u32).map(|file| self.net(Square::from_coords(File::new(file), Rank::new(rank)), Color::White)).collect())
+            .collect()
+    }
+
+    /// The board seen from `player_side`, every square with its piece ('.' when empty) and
+    /// the player's net control, shaded with ANSI colors when `color` is set
+    pub fn render(&self, player_side: PlayerSide, color: bool) -> String {
+        let mover = if player_side == PlayerSide::Black { Color::Black } else { Color::White };
+        let flip = player_side.needs_board_flip();
+        let order = |flipped: bool| -> Vec<u32> { if flipped { (0..8).collect() } else { (0..8).rev().collect() } };
+        let files: Vec<u32> = order(!flip);
+
+        let mut out = String::new();
+        for rank in order(flip) {
+            let mut row = format!("  {} ", rank + 1);
+            for &file in &files {
+                let square = Square::from_coords(File::new(file), Rank::new(rank));
+                let piece = self.board.piece_at(square).map_or('.', |piece| piece.char());
+                let net = self.net(square, mover);
+                let cell = match net {
+                    0 => format!(" {}   ", piece),
+                    _ => format!(" {}{:<+3}", piece, net),
+                };
+                match shade(self, square, net).filter(|_| color) {
+                    Some(background) => row.push_str(&format!("\x1b[{};30m{}\x1b[0m", background, cell)),
+                    None => row.push_str(&cell),
+                }
+            }
+            out.push_str(row.trim_end());
+            out.push('\n');
+        }
+        out.push_str("    ");
+        for &file in &files {
+            out.push_str(&format!(" {}   ", (b'a' + file as u8) as char));
+        }
+        out.truncate(out.trim_end().len());
+
+        let hanging = self.hanging();
+        if !hanging.is_empty() {
+            let listed: Vec<String> = hanging
+                .iter()
+                .map(|(square, piece, attackers, defenders)| format!("{}{} ({} vs {})", piece, square, attackers, defenders))
+                .collect();
+            out.push_str(&format!("\nOutnumbered: {} (attackers vs defenders)", listed.join(", ")));
+        }
+        out
+    }
+}
+
+/// ANSI background for a square: the player's (green, brighter when stronger), the
+/// opponent's (red), or contested and even (yellow); None when nobody attacks it
+fn shade(control: &Control, square: Square, net: i8) -> Option<u8> {
+    match net {
+        1 => Some(42),
+        2.. => Some(102),
+        -1 => Some(41),
+        ..=-2 => Some(101),
+        0 if control.attackers(square, Color::White) > 0 => Some(43),
+        0 => None,
+    }
+}
+
+/// Whether to color the heatmap: stdout is a terminal and `NO_COLOR` isn't set
+pub fn use_color() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_control_counts_attackers() {
+        let control = Control::of("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        // f3: pawns e2 and g2, knight g1
+        assert_eq!(control.attackers(Square::F3, Color::White), 3);
+        assert_eq!(control.net(Square::F3, Color::White), 3);
+        assert_eq!(control.net(Square::F6, Color::White), -3);
+        assert_eq!(control.net(Square::E4, Color::Black), 0);
+        assert!(control.hanging().is_empty());
+        assert_eq!(control.rows()[5][5], 3);
+    }
+
+    #[test]
+    fn test_hanging_and_plain_render() {
+        // White knight on e5 attacked by the d6 pawn, defended by nothing
+        let control = Control::of("4k3/8/3p4/4N3/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(control.hanging(), vec![(Square::E5, 'N', 1, 0)]);
+        let board = control.render(PlayerSide::White, false);
+        let lines: Vec<&str> = board.lines().collect();
+        assert_eq!(lines.len(), 10);
+        assert_eq!(lines[3], "  5  .    .    .-1  .    N-1  .    .    .");
+        assert_eq!(lines[8], "     a    b    c    d    e    f    g    h");
+        assert_eq!(lines[9], "Outnumbered: Ne5 (1 vs 0) (attackers vs defenders)");
+        // From Black's side the same square counts for Black
+        assert!(control.render(PlayerSide::Black, false).contains("N+1"));
+    }
+}
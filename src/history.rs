@@ -0,0 +1,90 @@
+//! Position history across capture cycles
+//!
+//! A screenshot shows only piece placement, so OCR used to emit every FEN with `- 0 1`:
+//! no en passant square (the engine never considered capturing a pawn that had just
+//! advanced two squares) and a fresh half-move clock (no fifty-move rule, no sense of how
+//! long the game has been shuffling). The previous cycle's position fills that in: the one
+//! or two plies leading from it to the new board are replayed, which yields the en passant
+//! target (when a capture is possible, as lichess writes it), castling rights, and the
+//! half-move/full-move counters.
+//!
+//! When the boards don't connect (a new game, a missed frame, an OCR error) the recognized
+//! FEN is used as it is and becomes the new starting point.
+
+use crate::pgn::{connect, parse_position};
+use shakmaty::fen::Fen;
+use shakmaty::{Chess, EnPassantMode, Position};
+
+/// Full position of the last recognized board
+#[derive(Default)]
+pub struct PositionHistory {
+    last: Option<Chess>,
+}
+
+impl PositionHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The recognized FEN completed from the previous cycle (en passant, castling, move
+    /// counters). FENs that aren't a legal position are returned unchanged and not kept.
+    pub fn infer(&mut self, fen: &str) -> String {
+        let Some(recognized) = parse_position(fen) else {
+            return fen.to_string();
+        };
+        let continued = self.last.as_ref().and_then(|last| {
+            let plies = connect(last, recognized.board())?;
+            let mut position = last.clone();
+            for ply in plies {
+                position.play_unchecked(ply);
+            }
+            Some(position)
+        });
+        match continued {
+            Some(position) => {
+                let inferred = Fen::from_position(&position, EnPassantMode::Legal).to_string();
+                self.last = Some(position);
+                inferred
+            }
+            None => {
+                self.last = Some(recognized);
+                fen.to_string()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_double_push_sets_en_passant_target() {
+        let mut history = PositionHistory::new();
+        history.infer("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1");
+        assert_eq!(history.infer("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1"), "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2");
+    }
+
+    #[test]
+    fn test_counters_carry_over() {
+        let mut history = PositionHistory::new();
+        history.infer("4k3/8/8/8/8/8/8/R3K3 w Q - 5 10");
+        // King and rook moves: the clock keeps running and castling rights are lost
+        assert_eq!(history.infer("4k3/8/8/8/8/8/8/R2K4 b - - 0 1"), "4k3/8/8/8/8/8/8/R2K4 b - - 6 10");
+        assert_eq!(history.infer("3k4/8/8/8/8/8/8/R2K4 w - - 0 1"), "3k4/8/8/8/8/8/8/R2K4 w - - 7 11");
+    }
+
+    #[test]
+    fn test_unconnected_board_starts_over() {
+        let mut history = PositionHistory::new();
+        history.infer("4k3/8/8/8/8/8/8/R3K3 w Q - 5 10");
+        let new_game = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(history.infer(new_game), new_game);
+        // Impossible boards are passed through without replacing the history
+        assert_eq!(history.infer("8/8/8/8/8/8/8/8 w - - 0 1"), "8/8/8/8/8/8/8/8 w - - 0 1");
+        assert_eq!(
+            history.infer("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! Global capture hotkey (`--trigger hotkey`)
+//!
+//! Manual mode waits for Enter in the terminal, which means alt-tabbing away from the chess
+//! window for every capture. With `--trigger hotkey` a key pressed anywhere (`--hotkey`,
+//! F9 by default) triggers the cycle instead; Enter in the terminal still works too.
+//!
+//! The key is read system-wide with `rdev` on its own thread and forwarded as a
+//! `ControlCommand::Capture` over the controls channel. Needs a build with
+//! `--features hotkey`; on Linux an X11 session (Wayland doesn't allow global listening),
+//! on macOS the Accessibility permission.
+
+use crate::controls::ControlCommand;
+use anyhow::Result;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Default `--hotkey`
+pub const DEFAULT_KEY: &str = "F9";
+
+/// Keys that can be the hotkey: ones chess sites and browsers leave alone
+pub const KEY_NAMES: [&str; 20] = [
+    "F1", "F2", "F3", "F4", "F5", "F6", "F7", "F8", "F9", "F10", "F11", "F12", "Pause", "ScrollLock", "Insert", "Home",
+    "End", "PageUp", "PageDown", "PrintScreen",
+];
+
+/// The canonical name of a supported key, matched case-insensitively ("f9" → "F9")
+pub fn key_name(name: &str) -> Option<&'static str> {
+    KEY_NAMES.iter().find(|key| key.eq_ignore_ascii_case(name.trim())).copied()
+}
+
+/// Starts listening for `key` (one of `KEY_NAMES`), sending a capture command on each press
+#[cfg(feature = "hotkey")]
+pub fn spawn(key: &'static str, commands: UnboundedSender<ControlCommand>) -> Result<()> {
+    use rdev::{EventType, Key};
+
+    let target = match key {
+        "F1" => Key::F1,
+        "F2" => Key::F2,
+        "F3" => Key::F3,
+        "F4" => Key::F4,
+        "F5" => Key::F5,
+        "F6" => Key::F6,
+        "F7" => Key::F7,
+        "F8" => Key::F8,
+        "F9" => Key::F9,
+        "F10" => Key::F10,
+        "F11" => Key::F11,
+        "F12" => Key::F12,
+        "Pause" => Key::Pause,
+        "ScrollLock" => Key::ScrollLock,
+        "Insert" => Key::Insert,
+        "Home" => Key::Home,
+        "End" => Key::End,
+        "PageUp" => Key::PageUp,
+        "PageDown" => Key::PageDown,
+        "PrintScreen" => Key::PrintScreen,
+        other => anyhow::bail!("Unsupported hotkey '{}' (choose one of {})", other, KEY_NAMES.join(", ")),
+    };
+
+    std::thread::spawn(move || {
+        // Holding the key auto-repeats presses: one capture until it is released
+        let mut held = false;
+        let result = rdev::listen(move |event| match event.event_type {
+            EventType::KeyPress(pressed) if pressed == target && !held => {
+                held = true;
+                let _ = commands.send(ControlCommand::Capture);
+            }
+            EventType::KeyRelease(released) if released == target => held = false,
+            _ => {}
+        });
+        if let Err(e) = result {
+            eprintln!("⚠ Hotkey {} unavailable ({:?}) - press Enter in this terminal to capture instead", key, e);
+        }
+    });
+    Ok(())
+}
+
+/// Builds without the `hotkey` feature can't listen for keys
+#[cfg(not(feature = "hotkey"))]
+pub fn spawn(_key: &'static str, _commands: UnboundedSender<ControlCommand>) -> Result<()> {
+    anyhow::bail!("--trigger hotkey needs a build with global key support: cargo build --release --features hotkey")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_name() {
+        assert_eq!(key_name("f9"), Some("F9"));
+        assert_eq!(key_name("pageup"), Some("PageUp"));
+        assert_eq!(key_name(DEFAULT_KEY), Some("F9"));
+        assert_eq!(key_name("a"), None);
+    }
+}
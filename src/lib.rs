@@ -0,0 +1,101 @@
+//! ZugzwangRS as a library: screen capture, board OCR, FEN validation and engine analysis
+//!
+//! The entry point for embedding the capture → FEN → analysis pipeline in another app is
+//! `pipeline::Pipeline`, built from an `ocr::OcrBackend` (a registered one, or the app's own)
+//! and an `engine::Engine`. The modules below it can also be used on their own: `capture`
+//! for screenshots, `ocr` and `ocr_native` / `ocr_llm` for reading a board, `engine` for
+//! searching a position.
+//!
+//! FEN handling and the engine are platform-independent. Without the default `desktop`
+//! feature (screen capture, OCR, processes, networking) the library builds with nothing but
+//! tanton, so the same logic can run in a browser companion:
+//!
+//! ```text
+//! cargo build --lib --release --target wasm32-unknown-unknown --no-default-features --features wasm
+//! ```
+//!
+//! The desktop binary (`src/main.rs`) is the command line around this library: it parses
+//! the options, runs the live loop, and sends results to its outputs.
+
+#[cfg(feature = "desktop")]
+pub mod board_detect;
+#[cfg(feature = "camera")]
+pub mod camera;
+#[cfg(feature = "desktop")]
+pub mod capture;
+#[cfg(feature = "desktop")]
+pub mod config;
+#[cfg(feature = "desktop")]
+pub mod crash;
+pub mod engine;
+pub mod fen;
+#[cfg(feature = "desktop")]
+pub mod fen_hook;
+#[cfg(feature = "desktop")]
+pub mod frame_hash;
+#[cfg(feature = "desktop")]
+pub mod hard_cases;
+#[cfg(feature = "desktop")]
+pub mod llm_cache;
+#[cfg(feature = "desktop")]
+pub mod llm_provider;
+#[cfg(feature = "desktop")]
+pub mod ocr;
+#[cfg(feature = "desktop")]
+pub mod ocr_cnn;
+#[cfg(feature = "desktop")]
+pub mod ocr_command;
+#[cfg(feature = "desktop")]
+pub mod ocr_hybrid;
+#[cfg(feature = "desktop")]
+pub mod ocr_llm;
+#[cfg(feature = "desktop")]
+pub mod ocr_native;
+#[cfg(feature = "desktop")]
+pub mod pipeline;
+#[cfg(feature = "desktop")]
+pub mod rate_limit;
+#[cfg(feature = "desktop")]
+pub mod secrets;
+#[cfg(feature = "desktop")]
+pub mod session_stats;
+#[cfg(feature = "desktop")]
+pub mod token_budget;
+#[cfg(feature = "desktop")]
+pub mod turn;
+pub mod uci;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+/// Which side the user is playing - affects board orientation and turn in FEN
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlayerSide {
+    #[default]
+    White,
+    Black,
+}
+
+impl PlayerSide {
+    /// Returns the FEN turn character for when it's this player's turn
+    pub fn fen_turn(&self) -> char {
+        match self {
+            PlayerSide::White => 'w',
+            PlayerSide::Black => 'b',
+        }
+    }
+
+    /// Returns true if board image needs vertical flip for correct FEN
+    /// (When playing as Black, the board is shown with Black at bottom)
+    pub fn needs_board_flip(&self) -> bool {
+        matches!(self, PlayerSide::Black)
+    }
+}
+
+impl std::fmt::Display for PlayerSide {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlayerSide::White => write!(f, "White"),
+            PlayerSide::Black => write!(f, "Black"),
+        }
+    }
+}
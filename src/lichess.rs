@@ -0,0 +1,58 @@
+//! Lichess study export
+//!
+//! Uploads PGN into an existing Lichess study through the study import endpoint, which
+//! creates one chapter per game in the PGN. Needs a personal API token with the
+//! `study:write` scope, given with `--token` or the `LICHESS_TOKEN` environment variable.
+
+use anyhow::{Context, Result};
+
+/// Environment variable holding the Lichess API token
+pub const TOKEN_VAR: &str = "LICHESS_TOKEN";
+
+/// Imports `pgn` into the study as new chapter(s); returns the study URL
+pub async fn import_to_study(study_id: &str, token: &str, chapter_name: &str, orientation: &str, pgn: &str) -> Result<String> {
+    let study_id = parse_study_id(study_id)?;
+    let client = crate::ocr_llm::http_client(30)?;
+    let response = client
+        .post(format!("https://lichess.org/api/study/{}/import-pgn", study_id))
+        .bearer_auth(token)
+        .form(&[("pgn", pgn), ("name", chapter_name), ("orientation", orientation)])
+        .send()
+        .await
+        .context("Lichess request failed")?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!("Lichess rejected the import ({}): {}", status, body.trim());
+    }
+    Ok(format!("https://lichess.org/study/{}", study_id))
+}
+
+/// Accepts a bare study id or a study URL (optionally with a chapter id)
+fn parse_study_id(text: &str) -> Result<&str> {
+    let text = text.trim().trim_end_matches('/');
+    let id = match text.split_once("/study/") {
+        Some((_, rest)) => rest.split('/').next().unwrap_or(rest),
+        None => text,
+    };
+    anyhow::ensure!(
+        id.len() == 8 && id.chars().all(|c| c.is_ascii_alphanumeric()),
+        "Invalid Lichess study id: {}",
+        text
+    );
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_study_id() {
+        assert_eq!(parse_study_id("AbCd1234").unwrap(), "AbCd1234");
+        assert_eq!(parse_study_id("https://lichess.org/study/AbCd1234/xyzW9876").unwrap(), "AbCd1234");
+        assert!(parse_study_id("https://lichess.org/study/").is_err());
+        assert!(parse_study_id("toolongid").is_err());
+    }
+}
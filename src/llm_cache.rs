@@ -0,0 +1,167 @@
+//! LLM OCR result cache
+//!
+//! In auto mode the same position stays on screen for as long as the opponent thinks, and
+//! a board the deduplication let through (a highlight or arrow that came and went, a
+//! position reached again) would be read by the LLM again, billed each time. Every FEN the
+//! LLM reads is remembered under the board region's perceptual hash (`frame_hash`); a later
+//! board matching a remembered one square by square gets its FEN without an API call.
+//!
+//! The cache keeps the most recently used `CAPACITY` boards. With `--llm-cache FILE` it is
+//! loaded from and saved to a JSON lines file, so it carries over between sessions.
+
+use anyhow::{Context, Result};
+use crate::PlayerSide;
+use crate::frame_hash::{FrameHash, changed_squares, dhash};
+use image::DynamicImage;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Boards remembered
+const CAPACITY: usize = 256;
+
+/// The session's cache
+static CACHE: Mutex<Cache> = Mutex::new(Cache { entries: VecDeque::new(), file: None });
+
+/// A board read by the LLM
+struct Entry {
+    hash: FrameHash,
+    /// Side at the bottom of the board (the FEN's orientation and default turn)
+    side: PlayerSide,
+    fen: String,
+}
+
+/// Entries, most recently used first
+struct Cache {
+    entries: VecDeque<Entry>,
+    file: Option<PathBuf>,
+}
+
+/// Loads the cache from `path` (if it exists) and saves it there from now on
+pub fn set_file(path: &Path) -> Result<()> {
+    let mut cache = CACHE.lock().map_err(|_| anyhow::anyhow!("LLM cache poisoned"))?;
+    if path.exists() {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        cache.entries = text.lines().filter_map(parse_entry).take(CAPACITY).collect();
+    }
+    cache.file = Some(path.to_path_buf());
+    Ok(())
+}
+
+/// Hash of the screenshot's board region; None when no board is found
+pub fn key(img: &DynamicImage) -> Option<FrameHash> {
+    let (x, y, w, h) = crate::ocr_native::locate_board(img).ok()?;
+    Some(dhash(&img.crop_imm(x, y, w, h)))
+}
+
+/// The FEN read from a matching board, if there is one
+pub fn get(hash: &FrameHash, side: PlayerSide) -> Option<String> {
+    CACHE.lock().ok()?.get(hash, side)
+}
+
+/// Remembers the FEN read from a board (saved to the cache file, if any)
+pub fn insert(hash: FrameHash, side: PlayerSide, fen: &str) {
+    let Ok(mut cache) = CACHE.lock() else {
+        return;
+    };
+    cache.insert(hash, side, fen);
+    if let Some(path) = &cache.file
+        && let Err(e) = std::fs::write(path, cache.to_lines())
+    {
+        eprintln!("⚠ Failed to save the LLM cache to {}: {}", path.display(), e);
+    }
+}
+
+impl Cache {
+    fn get(&mut self, hash: &FrameHash, side: PlayerSide) -> Option<String> {
+        let index = self.entries.iter().position(|entry| entry.side == side && changed_squares(&entry.hash, hash) == 0)?;
+        let entry = self.entries.remove(index)?;
+        let fen = entry.fen.clone();
+        self.entries.push_front(entry);
+        Some(fen)
+    }
+
+    fn insert(&mut self, hash: FrameHash, side: PlayerSide, fen: &str) {
+        self.entries.retain(|entry| !(entry.side == side && changed_squares(&entry.hash, &hash) == 0));
+        self.entries.push_front(Entry { hash, side, fen: fen.to_string() });
+        self.entries.truncate(CAPACITY);
+    }
+
+    fn to_lines(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let hash: String = entry.hash.iter().map(|word| format!("{:016x}", word)).collect();
+                let side = entry.side.fen_turn().to_string();
+                format!("{}\n", serde_json::json!({ "hash": hash, "side": side, "fen": entry.fen }))
+            })
+            .collect()
+    }
+}
+
+/// One line of the cache file; None for lines that aren't entries
+fn parse_entry(line: &str) -> Option<Entry> {
+    let value: serde_json::Value = serde_json::from_str(line).ok()?;
+    let hex = value["hash"].as_str()?;
+    let mut hash = FrameHash::default();
+    if hex.len() != hash.len() * 16 {
+        return None;
+    }
+    for (word, chunk) in hash.iter_mut().zip(hex.as_bytes().chunks(16)) {
+        *word = u64::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+    }
+    let side = match value["side"].as_str()? {
+        "w" => PlayerSide::White,
+        "b" => PlayerSide::Black,
+        _ => return None,
+    };
+    Some(Entry { hash, side, fen: value["fen"].as_str()?.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    fn hash(seed: u64) -> FrameHash {
+        std::array::from_fn(|i| seed.wrapping_mul(0x9e37_79b9_7f4a_7c15).rotate_left(i as u32))
+    }
+
+    #[test]
+    fn test_hits_tolerate_noise_and_need_the_side() {
+        let mut cache = Cache { entries: VecDeque::new(), file: None };
+        cache.insert(hash(1), PlayerSide::White, START);
+        let mut noisy = hash(1);
+        noisy[0] ^= 1; // one flipped bit: noise, not a move
+        assert_eq!(cache.get(&noisy, PlayerSide::White).as_deref(), Some(START));
+        assert_eq!(cache.get(&noisy, PlayerSide::Black), None);
+        assert_eq!(cache.get(&hash(2), PlayerSide::White), None);
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let mut cache = Cache { entries: VecDeque::new(), file: None };
+        for seed in 0..CAPACITY as u64 {
+            cache.insert(hash(seed), PlayerSide::White, &format!("fen {}", seed));
+        }
+        // Using the oldest keeps it; the next oldest goes instead
+        assert!(cache.get(&hash(0), PlayerSide::White).is_some());
+        cache.insert(hash(9999), PlayerSide::White, "new");
+        assert_eq!(cache.entries.len(), CAPACITY);
+        assert!(cache.get(&hash(0), PlayerSide::White).is_some());
+        assert!(cache.get(&hash(1), PlayerSide::White).is_none());
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let mut cache = Cache { entries: VecDeque::new(), file: None };
+        cache.insert(hash(1), PlayerSide::White, START);
+        cache.insert(hash(2), PlayerSide::Black, "8/8/8/8/8/8/8/K6k b - - 0 1");
+        let text = cache.to_lines();
+        let entries: Vec<Entry> = text.lines().filter_map(parse_entry).collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[1].hash, entries[1].side, entries[1].fen.as_str()), (hash(1), PlayerSide::White, START));
+        assert!(parse_entry("{\"hash\": \"00\"}").is_none());
+    }
+}
@@ -0,0 +1,227 @@
+//! LLM provider selection and fallback chain
+//!
+//! `ocr_llm` builds one request in the OpenAI chat completions format (with image input);
+//! most providers accept it as is, so only the URL, model, and credentials change:
+//! - **openai**: api.openai.com (OPENAI_API_KEY / OPENAI_API_KEYS)
+//! - **anthropic**: Claude's Messages API (ANTHROPIC_API_KEY, ANTHROPIC_MODEL); the request
+//!   is translated to its format on the way out
+//! - **gemini**: Google's OpenAI-compatible endpoint (GEMINI_API_KEY, GEMINI_MODEL)
+//! - **ollama**: local server, no key (OLLAMA_HOST, OLLAMA_MODEL, e.g. llava)
+//!
+//! `--llm-provider` picks the provider to use. With `--llm-fallback openai,gemini,ollama` the
+//! chain is tried in order: when the active provider has an outage (network failure or
+//! persistent 5xx), requests move to the next one for the rest of the session. Providers
+//! without credentials (e.g. openai with OPENAI_API_KEY unset) are skipped from the start.
+//!
+//! `--llm-base-url` sends chat requests to another OpenAI-compatible endpoint instead (a
+//! gateway, a self-hosted server, or a mock in tests).
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const OPENAI_URL: &str = "https://api.openai.com/v1/chat/completions";
+const OPENAI_MODEL: &str = "gpt-4o"; // Full GPT-4o for better vision accuracy (was gpt-4o-mini)
+const OPENAI_BUDGET_MODEL: &str = "gpt-4o-mini"; // Once the session nears `--token-budget`
+const ANTHROPIC_URL: &str = "https://api.anthropic.com/v1/messages";
+const ANTHROPIC_DEFAULT_MODEL: &str = "claude-sonnet-4-5";
+const GEMINI_URL: &str = "https://generativelanguage.googleapis.com/v1beta/openai/chat/completions";
+const GEMINI_DEFAULT_MODEL: &str = "gemini-2.0-flash";
+const OLLAMA_DEFAULT_HOST: &str = "http://localhost:11434";
+const OLLAMA_DEFAULT_MODEL: &str = "llava";
+
+/// Ordered provider chain from `--llm-fallback` (defaults to OpenAI only)
+static CHAIN: OnceLock<Vec<Provider>> = OnceLock::new();
+
+/// Chat endpoint replacing every provider's own (`--llm-base-url`)
+static BASE_URL: OnceLock<String> = OnceLock::new();
+
+/// Index into the chain of the provider currently in use
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// A vision LLM provider
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Provider {
+    OpenAi,
+    Anthropic,
+    Gemini,
+    Ollama,
+}
+
+impl Provider {
+    /// Parses a provider name as used on the command line
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "openai" => Some(Provider::OpenAi),
+            "anthropic" => Some(Provider::Anthropic),
+            "gemini" => Some(Provider::Gemini),
+            "ollama" => Some(Provider::Ollama),
+            _ => None,
+        }
+    }
+
+    /// Chat completions endpoint
+    pub fn chat_url(self) -> String {
+        if let Some(url) = BASE_URL.get() {
+            return url.clone();
+        }
+        match self {
+            Provider::OpenAi => OPENAI_URL.to_string(),
+            Provider::Anthropic => ANTHROPIC_URL.to_string(),
+            Provider::Gemini => GEMINI_URL.to_string(),
+            Provider::Ollama => {
+                let host = std::env::var("OLLAMA_HOST").unwrap_or_else(|_| OLLAMA_DEFAULT_HOST.to_string());
+                format!("{}/v1/chat/completions", host.trim_end_matches('/'))
+            }
+        }
+    }
+
+    /// Vision model to request (OpenAI's cheaper model once the token budget runs low)
+    pub fn model(self) -> String {
+        match self {
+            Provider::OpenAi if crate::token_budget::downgraded() => OPENAI_BUDGET_MODEL.to_string(),
+            Provider::OpenAi => OPENAI_MODEL.to_string(),
+            Provider::Anthropic => std::env::var("ANTHROPIC_MODEL").unwrap_or_else(|_| ANTHROPIC_DEFAULT_MODEL.to_string()),
+            Provider::Gemini => std::env::var("GEMINI_MODEL").unwrap_or_else(|_| GEMINI_DEFAULT_MODEL.to_string()),
+            Provider::Ollama => std::env::var("OLLAMA_MODEL").unwrap_or_else(|_| OLLAMA_DEFAULT_MODEL.to_string()),
+        }
+    }
+
+    /// Environment variable holding this provider's key (None if no key is needed)
+    pub fn key_var(self) -> Option<&'static str> {
+        match self {
+            Provider::OpenAi => Some("OPENAI_API_KEY"),
+            Provider::Anthropic => Some("ANTHROPIC_API_KEY"),
+            Provider::Gemini => Some("GEMINI_API_KEY"),
+            Provider::Ollama => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Provider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Provider::OpenAi => write!(f, "openai"),
+            Provider::Anthropic => write!(f, "anthropic"),
+            Provider::Gemini => write!(f, "gemini"),
+            Provider::Ollama => write!(f, "ollama"),
+        }
+    }
+}
+
+/// Parses a comma-separated provider list, e.g. "openai,gemini,ollama"
+pub fn parse_chain(list: &str) -> Result<Vec<Provider>, String> {
+    let mut chain = Vec::new();
+    for name in list.split(',').filter(|n| !n.trim().is_empty()) {
+        let provider = Provider::from_name(name).ok_or_else(|| {
+            format!("Unknown LLM provider '{}' (expected openai, anthropic, gemini, or ollama)", name.trim())
+        })?;
+        if !chain.contains(&provider) {
+            chain.push(provider);
+        }
+    }
+    if chain.is_empty() {
+        return Err("LLM provider list is empty".to_string());
+    }
+    Ok(chain)
+}
+
+/// Puts the `--llm-provider` choice at the front of the fallback chain
+pub fn with_primary(mut chain: Vec<Provider>, primary: Provider) -> Vec<Provider> {
+    chain.retain(|&p| p != primary);
+    chain.insert(0, primary);
+    chain
+}
+
+/// Sends chat requests to `url` instead of the providers' endpoints (first call wins)
+pub fn set_base_url(url: &str) {
+    let _ = BASE_URL.set(url.to_string());
+}
+
+/// Sets the provider chain (first call wins; call before the first request)
+pub fn set_chain(chain: Vec<Provider>) {
+    let _ = CHAIN.set(chain);
+}
+
+/// Returns the configured provider chain
+pub fn chain() -> &'static [Provider] {
+    CHAIN.get_or_init(|| vec![Provider::OpenAi])
+}
+
+/// Returns the provider currently in use
+pub fn active() -> Provider {
+    let chain = chain();
+    chain[ACTIVE.load(Ordering::Relaxed).min(chain.len() - 1)]
+}
+
+/// Moves past providers `ready` rejects (no credentials) to the first ready one, from the
+/// active provider on. Returns the provider now in use, or None if none from there is ready.
+pub fn skip_unready(ready: impl Fn(Provider) -> bool) -> Option<Provider> {
+    let chain = chain();
+    let index = first_ready(chain, ACTIVE.load(Ordering::Relaxed), ready)?;
+    ACTIVE.store(index, Ordering::Relaxed);
+    Some(chain[index])
+}
+
+/// Index of the first provider at or after `from` that `ready` accepts
+fn first_ready(chain: &[Provider], from: usize, ready: impl Fn(Provider) -> bool) -> Option<usize> {
+    (from..chain.len()).find(|&index| ready(chain[index]))
+}
+
+/// Moves to the next provider after an outage. Returns it, or None if the chain is exhausted.
+pub fn fail_over() -> Option<Provider> {
+    let chain = chain();
+    let next = ACTIVE.load(Ordering::Relaxed) + 1;
+    if next >= chain.len() {
+        return None;
+    }
+    ACTIVE.store(next, Ordering::Relaxed);
+    Some(chain[next])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chain_in_order() {
+        let chain = parse_chain("openai, gemini,ollama").unwrap();
+        assert_eq!(chain, vec![Provider::OpenAi, Provider::Gemini, Provider::Ollama]);
+    }
+
+    #[test]
+    fn test_parse_chain_dedups_and_rejects_unknown() {
+        assert_eq!(parse_chain("ollama,ollama").unwrap(), vec![Provider::Ollama]);
+        assert!(parse_chain("openai,bard").is_err());
+        assert!(parse_chain(" , ").is_err());
+    }
+
+    #[test]
+    fn test_provider_display_roundtrip() {
+        for provider in [Provider::OpenAi, Provider::Anthropic, Provider::Gemini, Provider::Ollama] {
+            assert_eq!(Provider::from_name(&provider.to_string()), Some(provider));
+        }
+    }
+
+    #[test]
+    fn test_primary_leads_the_chain() {
+        let chain = parse_chain("openai,anthropic,ollama").unwrap();
+        assert_eq!(with_primary(chain, Provider::Anthropic), vec![Provider::Anthropic, Provider::OpenAi, Provider::Ollama]);
+        assert_eq!(with_primary(Vec::new(), Provider::Ollama), vec![Provider::Ollama]);
+    }
+
+    #[test]
+    fn test_first_ready_skips_providers_without_credentials() {
+        let chain = parse_chain("openai,ollama").unwrap();
+        // No OpenAI key: ollama is used from the first request
+        let has_key = |provider: Provider| provider != Provider::OpenAi;
+        assert_eq!(first_ready(&chain, 0, has_key), Some(1));
+        assert_eq!(first_ready(&chain, 0, |_| true), Some(0));
+        assert_eq!(first_ready(&chain[..1], 0, has_key), None);
+    }
+
+    #[test]
+    fn test_ollama_needs_no_key() {
+        assert_eq!(Provider::Ollama.key_var(), None);
+        assert_eq!(Provider::OpenAi.key_var(), Some("OPENAI_API_KEY"));
+    }
+}
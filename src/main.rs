@@ -1,17 +1,67 @@
-mod capture;
-mod ocr_native;
-mod ocr_llm;
-mod ocr;
-mod engine;
-// mod config;
-// mod calibrate; // Enable for calibration mode
+mod advisor;
+mod analysis_board;
+mod bench;
+mod clock;
+mod controls;
+mod correction;
+mod cost_estimate;
+mod critical;
+mod dataset;
+mod deep;
+mod describe;
+mod lichess;
+mod material;
+mod mini_window;
+mod multi_board;
+mod notify;
+mod notation;
+mod oneshot;
+mod openings;
+mod output;
+mod overlay;
+mod tui;
+mod orientation;
+mod eval_smoothing;
+mod eval_units;
+mod game;
+mod guess;
+mod heatmap;
+mod hotkey;
+#[cfg(test)]
+mod harness;
+mod history;
+mod pgn;
+mod play;
+mod post_game;
+mod players;
+mod profiles;
+mod prompt;
+mod queue;
+mod remote;
+mod repertoire;
+mod replay;
+mod resources;
+mod scouting;
+mod server;
+mod session;
+mod snapshots;
+mod shutdown;
+mod speech;
+mod sparring;
+mod tactics;
+mod calibrate;
 
 use anyhow::{Context, Result};
 use clap::{Arg, Command};
-use dialoguer::{theme::ColorfulTheme, Input, Select};
-use ocr::OcrMode;
-use std::io::{self, BufRead};
+use controls::{ControlCommand, RuntimeSettings};
+use ocr::{MoveRecommendation, OcrMode};
+use std::io;
 use std::time::Duration;
+use zugzwang_core::{
+    PlayerSide, board_detect, capture, config, crash, engine, fen, fen_hook, frame_hash, hard_cases, llm_cache,
+    llm_provider, ocr, ocr_cnn, ocr_command, ocr_hybrid, ocr_llm, ocr_native, secrets, session_stats, token_budget, turn,
+    uci,
+};
 
 /// How move analysis is performed
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
@@ -22,306 +72,2643 @@ pub enum AnalysisMode {
     /// Direct: LLM sees board and decides move in one shot
     /// (Requires LLM OCR mode, provides reasoning)
     Direct,
+    /// Hybrid: LLM recommendation cross-checked against the engine's best move
+    /// (Requires an LLM; the FEN still comes from the selected OCR mode)
+    Hybrid,
+}
+
+impl AnalysisMode {
+    /// Parses an `--analysis` value, including the `llm-direct` alias
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "engine" => Some(AnalysisMode::Engine),
+            "direct" | "llm-direct" => Some(AnalysisMode::Direct),
+            "hybrid" => Some(AnalysisMode::Hybrid),
+            _ => None,
+        }
+    }
+
+    /// Name as accepted by `--analysis`
+    pub fn name(self) -> &'static str {
+        match self {
+            AnalysisMode::Engine => "engine",
+            AnalysisMode::Direct => "direct",
+            AnalysisMode::Hybrid => "hybrid",
+        }
+    }
+}
+
+impl std::fmt::Display for AnalysisMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnalysisMode::Engine if engine::uses_uci() => write!(f, "Engine ({})", engine::name()),
+            AnalysisMode::Engine => write!(f, "Engine (Tanton ~2900 ELO)"),
+            AnalysisMode::Direct => write!(f, "Direct (GPT-4o with reasoning)"),
+            AnalysisMode::Hybrid => write!(f, "Hybrid (GPT-4o cross-checked by engine)"),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    crash::install();
+    let config = config::load()?;
+    secrets::export(&config.secrets);
+    run(std::env::args_os(), config).await
+}
+
+/// `--analysis` values, parsed into the mode so aliases resolve like their names
+fn analysis_parser() -> impl clap::builder::TypedValueParser<Value = AnalysisMode> {
+    use clap::builder::TypedValueParser;
+    clap::builder::PossibleValuesParser::new([
+        clap::builder::PossibleValue::new("engine"),
+        clap::builder::PossibleValue::new("direct").alias("llm-direct"),
+        clap::builder::PossibleValue::new("hybrid"),
+    ])
+    .map(|name| AnalysisMode::from_name(&name).unwrap_or_default())
+}
+
+/// Runs the command line `args` (program name first) with `config`'s saved defaults.
+/// Split from `main` so tests can drive the whole pipeline (see `harness`).
+async fn run<I, T>(args: I, config: config::Config) -> Result<()>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<std::ffi::OsString> + Clone,
+{
+    // Parse CLI arguments (saved defaults fill in flags that aren't given)
+    let cli = Command::new("Zugzwang-RS")
+        .version("0.1.1")
+        .author("Crimson Sun")
+        .about("Pure-Rust chess assistant for browser windows")
+        .arg(
+            Arg::new("ocr")
+                .long("ocr")
+                .global(true)
+                .value_name("MODE")
+                .help("OCR mode: native (default), llm, hybrid (native, LLM when unsure), or any registered backend")
+                .value_parser(clap::builder::PossibleValuesParser::new(ocr::backend_names())),
+        )
+        .arg(
+            Arg::new("ocr-cmd")
+                .long("ocr-cmd")
+                .value_name("COMMAND")
+                .global(true)
+                .help("External recognizer for --ocr command, e.g. \"./my_detector {image}\" (prints a FEN)"),
+        )
+        .arg(
+            Arg::new("fen-hook")
+                .long("fen-hook")
+                .value_name("COMMAND")
+                .global(true)
+                .help("Program that may correct each recognized FEN before analysis, e.g. \"./fix_fen.py {fen} {side}\" (gets the FEN on stdin too; prints a FEN, or nothing to keep it)"),
+        )
+        .arg(
+            Arg::new("llm-cache")
+                .long("llm-cache")
+                .value_name("FILE")
+                .global(true)
+                .help("Keep the LLM's board readings in this file, so boards seen before (also in earlier sessions) aren't sent again")
+                .value_parser(clap::value_parser!(std::path::PathBuf)),
+        )
+        .arg(
+            Arg::new("hybrid-threshold")
+                .long("hybrid-threshold")
+                .value_name("CONFIDENCE")
+                .global(true)
+                .help("Board confidence (0-1) below which --ocr hybrid asks the LLM (default: 0.5)")
+                .value_parser(clap::value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("cnn-model")
+                .long("cnn-model")
+                .value_name("FILE")
+                .global(true)
+                .help("ONNX piece classifier for --ocr cnn (default: models/pieces.onnx)"),
+        )
+        .arg(
+            Arg::new("interval")
+                .long("interval")
+                .value_name("MS")
+                .help("Loop interval in milliseconds")
+                .default_value("1000")
+                .value_parser(clap::value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("depth")
+                .long("depth")
+                .value_name("PLIES")
+                .help("Starting engine search depth (default: 6, or the time control's preset; d/D adjust it live)")
+                .value_parser(clap::value_parser!(u16).range(1..)),
+        )
+        .arg(
+            Arg::new("site")
+                .long("site")
+                .global(true)
+                .value_name("SITE")
+                .help("Chess site for native OCR templates")
+                .default_value("chesscom")
+                .value_parser(["chesscom", "lichess", "macOS"]),
+        )
+        .arg(
+            Arg::new("theme")
+                .long("theme")
+                .global(true)
+                .value_name("NAME")
+                .help("Piece set for native OCR: a template pack in templates/{site}/{NAME}/ (e.g. cburnett, merida, alpha), or auto to detect it on the first frame"),
+        )
+        .arg(
+            Arg::new("calibrate")
+                .long("calibrate")
+                .help("Cut native OCR templates and thresholds for --site from a board in the starting position, then exit")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .help("Enable verbose logging for debugging")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("trigger")
+                .long("trigger")
+                .value_name("MODE")
+                .help("Capture trigger: auto (interval-based), manual (press Enter), or hotkey (press --hotkey anywhere, or Enter)")
+                .value_parser(["auto", "manual", "hotkey"]),
+        )
+        .arg(
+            Arg::new("hotkey")
+                .long("hotkey")
+                .value_name("KEY")
+                .help(format!("Global key for --trigger hotkey: {} (default: {})", hotkey::KEY_NAMES.join(", "), hotkey::DEFAULT_KEY))
+                .value_parser(|key: &str| hotkey::key_name(key).ok_or_else(|| format!("unsupported hotkey '{}'", key))),
+        )
+        .arg(
+            Arg::new("side")
+                .long("side")
+                .global(true)
+                .value_name("SIDE")
+                .help("Which side you are playing: white (default), black, or auto (detected from the board's orientation)")
+                .value_parser(["white", "black", "auto"]),
+        )
+        .arg(
+            Arg::new("assume-turn")
+                .long("assume-turn")
+                .global(true)
+                .help("Always treat it as your turn instead of reading the side to move from the screen (last-move highlight, running clock)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("notation")
+                .long("notation")
+                .global(true)
+                .value_name("STYLE")
+                .help("How moves are shown: san (Nf3, O-O, e8=Q+; default), uci (g1f3), or verbose (G1 to F3)")
+                .value_parser(["san", "uci", "verbose"]),
+        )
+        .arg(
+            Arg::new("analysis")
+                .long("analysis")
+                .value_name("MODE")
+                .help("Analysis mode: engine (Tanton), direct (GPT-4o decides the move alone; alias llm-direct), or hybrid (GPT-4o's move compared with the engine's)")
+                .value_parser(analysis_parser()),
+        )
+        .arg(
+            Arg::new("board-region")
+                .long("board-region")
+                .value_name("X,Y,W,H[:SIDE]")
+                .help("Analyze a board in this screen region; repeat for two boards at once (e.g. 0,80,900,900:black)")
+                .action(clap::ArgAction::Append)
+                .value_parser(board_detect::BoardRegion::parse),
+        )
+        .arg(
+            Arg::new("fallback-region")
+                .long("fallback-region")
+                .value_name("X,Y,W,H")
+                .help("Board region to use when no board can be detected (e.g. 0,80,900,900)")
+                .value_parser(board_detect::parse_region),
+        )
+        .arg(
+            Arg::new("window")
+                .long("window")
+                .value_name("TITLE")
+                .help("Capture only the window whose title contains this text (e.g. \"lichess\") instead of the whole screen"),
+        )
+        .arg(
+            Arg::new("when-focused")
+                .long("when-focused")
+                .value_name("TITLES")
+                .num_args(0..=1)
+                .default_missing_value("")
+                .help("Capture only while the focused window's title contains one of these comma-separated texts (default: the --window title or the site); auto trigger only"),
+        )
+        .arg(
+            Arg::new("input")
+                .long("input")
+                .value_name("SOURCE")
+                .help("Frame source: screen (default), camera:<index> for a webcam over a physical board (needs --ocr llm), or replay:<dir> for recorded frames")
+                .conflicts_with("window")
+                .value_parser(capture::Input::parse),
+        )
+        .arg(
+            Arg::new("engine")
+                .long("engine")
+                .value_name("ENGINE")
+                .help("Search engine: tanton (built-in, default) or stockfish / uci (external binary, see --engine-path)")
+                .default_value("tanton")
+                .value_parser(["tanton", "stockfish", "uci"]),
+        )
+        .arg(
+            Arg::new("engine-path")
+                .long("engine-path")
+                .value_name("PATH")
+                .help("UCI engine binary for --engine stockfish")
+                .default_value("stockfish"),
+        )
+        .arg(
+            Arg::new("engine-threads")
+                .long("engine-threads")
+                .value_name("N")
+                .help("UCI engine threads")
+                .default_value("1")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("nice")
+                .long("nice")
+                .help("Run at low CPU priority (engine included) so analysis never competes with the game")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("cpu-limit")
+                .long("cpu-limit")
+                .value_name("THREADS")
+                .help("Cap the engine at this many threads while the chess site's window is focused")
+                .value_parser(clap::value_parser!(u16).range(1..)),
+        )
+        .arg(
+            Arg::new("engine-hash")
+                .long("engine-hash")
+                .value_name("MB")
+                .help("UCI engine hash size in MB")
+                .default_value("64")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("engine-skill")
+                .long("engine-skill")
+                .value_name("LEVEL")
+                .help("UCI engine skill level 0-20 (default: full strength)")
+                .value_parser(clap::value_parser!(u8).range(0..=20)),
+        )
+        .arg(
+            Arg::new("multipv")
+                .long("multipv")
+                .value_name("N")
+                .help("Start with MultiPV on, listing the top N candidate moves (2-5; toggle with the m key)")
+                .value_parser(
+                    clap::value_parser!(u64).range(controls::MIN_MULTIPV as u64..=controls::MAX_MULTIPV as u64),
+                ),
+        )
+        .arg(
+            Arg::new("advisor")
+                .long("advisor")
+                .help("Suggest resigning or offering a draw when the eval stays lost or level (anti-tilt)")
+                .conflicts_with("json")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("advisor-lost")
+                .long("advisor-lost")
+                .value_name("PCT")
+                .help("Win chance below which --advisor counts the position as lost (default: 10)")
+                .requires("advisor")
+                .value_parser(clap::value_parser!(u8).range(1..50)),
+        )
+        .arg(
+            Arg::new("advisor-moves")
+                .long("advisor-moves")
+                .value_name("N")
+                .help("Positions in a row the eval must stay lost or level before --advisor speaks (default: 4)")
+                .requires("advisor")
+                .value_parser(clap::value_parser!(u8).range(2..)),
+        )
+        .arg(
+            Arg::new("collect-dataset")
+                .long("collect-dataset")
+                .help("Archive analyzed screenshots and label consistent sessions as OCR training data (dataset/)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print one JSON object per analysis on stdout (banner and prompts suppressed)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("only")
+                .long("only")
+                .value_name("FIELDS")
+                .help("Print just these fields of each analysis on one line: best, eval, fen, comma-separated (banner and prompts suppressed)")
+                .value_parser(output::Field::parse)
+                .value_delimiter(',')
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("suppress-fen")
+                .long("suppress-fen")
+                .help("Leave the FEN out of console and JSON output")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("SINK")
+                .help("Where results go, repeatable: console (default), json, json-file:PATH, websocket:PORT, webhook:URL, http:PORT, remote:PORT, clipboard, tts, tui, overlay, mini, notify; add @pawns, @cp, @win, or @accuracy for eval units (e.g. console@win)")
+                .value_parser(output::Sink::parse)
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("tts-language")
+                .long("tts-language")
+                .value_name("LANG")
+                .help("Language of spoken moves with --output tts: en (default), de, fr, or es")
+                .value_parser(speech::Language::CODES),
+        )
+        .arg(
+            Arg::new("tts-voice")
+                .long("tts-voice")
+                .value_name("VOICE")
+                .help("Voice for --output tts, as the platform names it (say -v '?', espeak --voices, or a Windows voice name)"),
+        )
+        .arg(
+            Arg::new("tui")
+                .long("tui")
+                .conflicts_with("json")
+                .help("Show a full-screen dashboard (board, best move, eval bar, moves, latency) instead of console lines")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("notify")
+                .long("notify")
+                .help("Also show each best move and eval as a desktop notification (same as --output notify)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("remote")
+                .long("remote")
+                .value_name("PORT")
+                .help("Let a phone trigger captures (POST /trigger) and read the latest result (GET /last) over the local network (same as --output remote:PORT)")
+                .value_parser(clap::value_parser!(u16)),
+        )
+        .arg(
+            Arg::new("remote-token")
+                .long("remote-token")
+                .value_name("TOKEN")
+                .help(format!("Token the phone remote requires (default: ${}, or a random one per run)", remote::TOKEN_VAR)),
+        )
+        .arg(
+            Arg::new("progress")
+                .long("progress")
+                .requires("json")
+                .help("With --json, also print a progress event for each depth the engine reaches (live eval for GUIs)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("describe")
+                .long("describe")
+                .help("Also describe each position in plain language (for screen readers / text-to-speech)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("analysis-board")
+                .long("analysis-board")
+                .value_name("MODE")
+                .help("Track variations on the site's analysis board: auto (detect eval bar), on, or off")
+                .default_value("auto")
+                .value_parser(["auto", "on", "off"]),
+        )
+        .arg(
+            Arg::new("post-game")
+                .long("post-game")
+                .value_name("MODE")
+                .help("While the site's post-game review decorates the board: pause analysis (default) or analyze anyway")
+                .default_value("pause")
+                .value_parser(["pause", "analyze"]),
+        )
+        .arg(
+            Arg::new("accessible")
+                .long("accessible")
+                .help("Screen-reader friendly: typed prompts instead of menus, plain-language board descriptions")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("time-control")
+                .long("time-control")
+                .global(true)
+                .value_name("TC")
+                .help("Time control in PGN form, e.g. 180+2 (skips detection; picks the speed preset and rating category)")
+                .value_parser(|tc: &str| {
+                    profiles::TimeClass::from_time_control(tc)
+                        .map(|_| tc.trim().to_string())
+                        .ok_or_else(|| format!("invalid time control '{}' (expected e.g. 180+2)", tc))
+                }),
+        )
+        .arg(
+            Arg::new("opponent")
+                .long("opponent")
+                .value_name("NAME")
+                .help("Opponent's username on --site, scouted before the first cycle (default: read from the name plate)"),
+        )
+        .arg(
+            Arg::new("openings")
+                .long("openings")
+                .value_name("PATH")
+                .help("Opening book: a TSV of eco, name, pgn (lichess chess-openings format) or a directory of them (default: bundled main lines)")
+                .value_parser(clap::value_parser!(std::path::PathBuf)),
+        )
+        .arg(
+            Arg::new("no-material")
+                .long("no-material")
+                .help("Don't show the material summary (who is up what, bishop pair, opposite bishops)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-openings")
+                .long("no-openings")
+                .help("Don't show opening names and book moves")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-critical")
+                .long("no-critical")
+                .help("Don't flag critical moments (only moves, sharp balanced positions)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-scout")
+                .long("no-scout")
+                .help("Don't fetch the opponent's recent games for a scouting report")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-player-ocr")
+                .long("no-player-ocr")
+                .global(true)
+                .help("Don't read player names and ratings from the screen (privacy)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("proxy")
+                .long("proxy")
+                .global(true)
+                .value_name("URL")
+                .help("Proxy for LLM requests, e.g. http://host:3128 or socks5://host:1080 (default: HTTPS_PROXY)"),
+        )
+        .arg(
+            Arg::new("detail")
+                .long("detail")
+                .global(true)
+                .value_name("LEVEL")
+                .help("LLM image detail: high (default), low, auto, or adaptive (low, high on failure)")
+                .value_parser(["high", "low", "auto", "adaptive"]),
+        )
+        .arg(
+            Arg::new("llm-provider")
+                .long("llm-provider")
+                .global(true)
+                .value_name("PROVIDER")
+                .help("Vision LLM: openai (default), anthropic, gemini, or ollama (local, no API key)")
+                .value_parser(["openai", "anthropic", "gemini", "ollama"]),
+        )
+        .arg(
+            Arg::new("llm-base-url")
+                .long("llm-base-url")
+                .global(true)
+                .value_name("URL")
+                .help("Send LLM requests to this OpenAI-compatible chat completions URL (gateway or self-hosted server)"),
+        )
+        .arg(
+            Arg::new("token-budget")
+                .long("token-budget")
+                .global(true)
+                .value_name("TOKENS")
+                .help("LLM tokens for the session; near it, switch to gpt-4o-mini and low detail instead of stopping")
+                .value_parser(clap::value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("llm-fallback")
+                .long("llm-fallback")
+                .global(true)
+                .value_name("PROVIDERS")
+                .help("Ordered LLM provider chain, e.g. openai,gemini,ollama (switches on outage)"),
+        )
+        .subcommand(
+            Command::new("bench-ocr")
+                .about("Re-run OCR on recorded hard cases and report accuracy")
+                .arg(
+                    Arg::new("dir")
+                        .long("dir")
+                        .value_name("DIR")
+                        .help("Hard case directory containing cases.jsonl")
+                        .default_value(hard_cases::HARD_CASES_DIR),
+                )
+                .arg(
+                    Arg::new("parallel")
+                        .long("parallel")
+                        .value_name("N")
+                        .help("Maximum concurrent OCR requests (LLM mode only)")
+                        .default_value("4")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("rate")
+                        .long("rate")
+                        .value_name("PER_MIN")
+                        .help("Maximum OCR requests started per minute (0 = unlimited)")
+                        .default_value("0")
+                        .value_parser(clap::value_parser!(u32)),
+                ),
+        )
+        .subcommand(
+            Command::new("replay")
+                .about("Run saved screenshots or a video through OCR and the engine instead of live capture, and report on every frame")
+                .arg(
+                    Arg::new("path")
+                        .value_name("PATH")
+                        .required(true)
+                        .help("Directory of PNG/JPEG frames (name order), a single image, or a video file (needs ffmpeg)")
+                        .value_parser(clap::value_parser!(std::path::PathBuf)),
+                )
+                .arg(
+                    Arg::new("every")
+                        .long("every")
+                        .value_name("SECS")
+                        .help("Seconds between the frames taken from a video (default: 1)")
+                        .value_parser(|text: &str| match text.parse::<f64>() {
+                            Ok(secs) if secs > 0.0 => Ok(secs),
+                            _ => Err(format!("'{}' is not a positive number of seconds", text)),
+                        }),
+                )
+                .arg(
+                    Arg::new("depth")
+                        .long("depth")
+                        .value_name("PLIES")
+                        .help("Engine search depth (default: 6)")
+                        .value_parser(clap::value_parser!(u16).range(1..)),
+                )
+                .arg(
+                    Arg::new("pgn")
+                        .long("pgn")
+                        .value_name("FILE")
+                        .help("Also write the game the recognized positions connect into")
+                        .value_parser(clap::value_parser!(std::path::PathBuf)),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print the report as one JSON object")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("fen")
+                .about("Print the FEN of the board in an image file and exit (OCR only, no engine)")
+                .arg(Arg::new("image").value_name("IMAGE").required(true).help("PNG or JPEG screenshot of the board"))
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print {\"fen\": ...} instead of the bare FEN")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("analyze")
+                .about("Print the best move in a position and exit (engine only, no capture)")
+                .arg(Arg::new("fen").long("fen").value_name("FEN").required(true).help("Position to analyze"))
+                .arg(
+                    Arg::new("depth")
+                        .long("depth")
+                        .value_name("PLIES")
+                        .help("Engine search depth (default: 6)")
+                        .value_parser(clap::value_parser!(u16).range(1..)),
+                )
+                .arg(
+                    Arg::new("multipv")
+                        .long("multipv")
+                        .value_name("N")
+                        .help("Also list the top N candidate moves")
+                        .value_parser(clap::value_parser!(u64).range(1..=controls::MAX_MULTIPV as u64)),
+                )
+                .arg(
+                    Arg::new("heatmap")
+                        .long("heatmap")
+                        .help("Also show who controls each square, and outnumbered pieces")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print the result as a JSON object (as --json does in the live loop)")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("play")
+                .about("Play against the built-in engine on a terminal board (no capture/OCR)")
+                .arg(
+                    Arg::new("depth")
+                        .long("depth")
+                        .value_name("PLIES")
+                        .help("Engine search depth (default: 6)")
+                        .value_parser(clap::value_parser!(u16).range(controls::MIN_DEPTH as i64..=controls::MAX_DEPTH as i64)),
+                )
+                .arg(
+                    Arg::new("fen")
+                        .long("fen")
+                        .value_name("FEN")
+                        .help("Starting position (default: standard start)")
+                        .default_value(play::START_FEN),
+                )
+                .arg(
+                    Arg::new("rating")
+                        .long("rating")
+                        .value_name("ELO")
+                        .help("Sparring mode: engine plays at roughly this rating and sometimes offers tactics")
+                        .value_parser(
+                            clap::value_parser!(u16).range(sparring::MIN_RATING as i64..=sparring::MAX_RATING as i64),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("guess")
+                .about("Guess-the-move training: step through a PGN game and guess each move (--side for one side only)")
+                .arg(Arg::new("pgn").value_name("PGN").required(true).help("PGN file (the first game is used)"))
+                .arg(
+                    Arg::new("depth")
+                        .long("depth")
+                        .value_name("PLIES")
+                        .help("Engine depth for scoring guesses (default: 6)")
+                        .value_parser(clap::value_parser!(u16).range(1..)),
+                ),
+        )
+        .subcommand(
+            Command::new("queue")
+                .about("Deep analysis queue for correspondence games")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("Register a game: fen:<FEN>, lichess:<id>, or chesscom:<username>/<id>")
+                        .arg(Arg::new("name").required(true).help("Name for the game"))
+                        .arg(Arg::new("source").required(true).help("Where to read the position from")),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Remove a game from the queue")
+                        .arg(Arg::new("name").required(true)),
+                )
+                .subcommand(Command::new("list").about("Show queued games and their latest analysis"))
+                .subcommand(
+                    Command::new("run")
+                        .about("Analyze games whose position changed")
+                        .arg(
+                            Arg::new("depth")
+                                .long("depth")
+                                .value_name("PLIES")
+                                .help("Search depth (default: 10)")
+                                .value_parser(clap::value_parser!(u16).range(controls::MIN_DEPTH as i64..=controls::MAX_DEPTH as i64)),
+                        )
+                        .arg(
+                            Arg::new("watch")
+                                .long("watch")
+                                .value_name("MINUTES")
+                                .help("Keep polling for new moves at this interval")
+                                .value_parser(clap::value_parser!(u64).range(1..)),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("deep")
+                .about("Long MultiPV analysis of one position with an external UCI engine")
+                .arg(Arg::new("fen").long("fen").value_name("FEN").required(true).help("Position to analyze"))
+                .arg(
+                    Arg::new("hours")
+                        .long("hours")
+                        .value_name("HOURS")
+                        .help("How long to think")
+                        .default_value("2")
+                        .value_parser(clap::value_parser!(f64)),
+                )
+                .arg(
+                    Arg::new("engine-path")
+                        .long("engine-path")
+                        .value_name("PATH")
+                        .help("UCI engine binary (e.g. stockfish)")
+                        .default_value("stockfish"),
+                )
+                .arg(
+                    Arg::new("multipv")
+                        .long("multipv")
+                        .value_name("N")
+                        .help("Number of lines to keep")
+                        .default_value("3")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("threads")
+                        .long("threads")
+                        .value_name("N")
+                        .help("Engine threads")
+                        .default_value("1")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("hash")
+                        .long("hash")
+                        .value_name("MB")
+                        .help("Engine hash size in MB")
+                        .default_value("256")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("checkpoint")
+                        .long("checkpoint")
+                        .value_name("MINUTES")
+                        .help("Minutes between checkpoints")
+                        .default_value("5")
+                        .value_parser(clap::value_parser!(u64).range(1..)),
+                ),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("Dump data recorded in a session")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("fens")
+                        .about("Every observed FEN in order, with timestamp and eval (tab-separated)")
+                        .arg(
+                            Arg::new("session")
+                                .long("session")
+                                .value_name("FILE")
+                                .help("Session log to read (\"last\" for the most recent)")
+                                .default_value("last"),
+                        )
+                        .arg(
+                            Arg::new("json")
+                                .long("json")
+                                .help("Print one JSON object per position instead")
+                                .action(clap::ArgAction::SetTrue),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("snapshots")
+                .about("List positions saved with `snapshot <name>`, or analyze them again deeper")
+                .subcommand(
+                    Command::new("analyze")
+                        .about("Search snapshots again at a higher depth")
+                        .arg(Arg::new("snapshot").help("Number or name from the list (default: all)"))
+                        .arg(
+                            Arg::new("depth")
+                                .long("depth")
+                                .value_name("PLIES")
+                                .help("Search depth (default: 10)")
+                                .value_parser(clap::value_parser!(u16).range(controls::MIN_DEPTH as i64..=controls::MAX_DEPTH as i64)),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("export-study")
+                .about("Upload a recorded session as an annotated Lichess study chapter")
+                .arg(
+                    Arg::new("session")
+                        .long("session")
+                        .value_name("FILE")
+                        .help("Session log to export (\"last\" for the most recent)")
+                        .default_value("last"),
+                )
+                .arg(
+                    Arg::new("study")
+                        .long("study")
+                        .value_name("ID")
+                        .help("Lichess study id or URL (omit to only write PGN)"),
+                )
+                .arg(Arg::new("name").long("name").value_name("NAME").help("Chapter name"))
+                .arg(
+                    Arg::new("token")
+                        .long("token")
+                        .value_name("TOKEN")
+                        .help("Lichess API token with study:write scope (default: $LICHESS_TOKEN)"),
+                )
+                .arg(
+                    Arg::new("username")
+                        .long("username")
+                        .value_name("NAME")
+                        .help("Your username on --site (looked up for the PGN headers)"),
+                )
+                .arg(
+                    Arg::new("opponent")
+                        .long("opponent")
+                        .value_name("NAME")
+                        .help("Opponent's username on --site"),
+                )
+                .arg(
+                    Arg::new("pgn-out")
+                        .long("pgn-out")
+                        .value_name("FILE")
+                        .help("Also write the PGN to this file"),
+                ),
+        )
+        .subcommand(
+            Command::new("prep")
+                .about("Live analysis that follows your opening repertoire and alerts when the game leaves it")
+                .arg(
+                    Arg::new("repertoire")
+                        .long("repertoire")
+                        .value_name("PGN")
+                        .required(true)
+                        .help("Repertoire PGN (games, chapters, and variations are all read)"),
+                ),
+        )
+        .subcommand(Command::new("overlay-window").hide(true).about("Move arrow window driven by --output overlay"))
+        .subcommand(Command::new("mini-window").hide(true).about("Mini window driven by --output mini"))
+        .subcommand(
+            Command::new("serve")
+                .about("Live analysis served over HTTP (/analysis, /fen) and a WebSocket (/ws) for overlays and frontends")
+                .arg(
+                    Arg::new("port")
+                        .long("port")
+                        .value_name("PORT")
+                        .help("Port to listen on (localhost only)")
+                        .default_value(server::DEFAULT_PORT.to_string())
+                        .value_parser(clap::value_parser!(u16)),
+                ),
+        )
+        .subcommand(
+            Command::new("tactics")
+                .about("Find missed tactics in recorded sessions and export them as puzzles")
+                .arg(
+                    Arg::new("session")
+                        .long("session")
+                        .value_name("FILE")
+                        .help("Session log to mine (\"last\" for the most recent, \"all\" for every session)")
+                        .default_value("all"),
+                )
+                .arg(
+                    Arg::new("min-gain")
+                        .long("min-gain")
+                        .value_name("CP")
+                        .help("How far the tactic must beat the second-best move, in centipawns (default: 200)")
+                        .value_parser(clap::value_parser!(i32).range(1..)),
+                )
+                .arg(
+                    Arg::new("depth")
+                        .long("depth")
+                        .value_name("PLIES")
+                        .help("Engine search depth (default: 6)")
+                        .value_parser(clap::value_parser!(u16).range(1..)),
+                )
+                .arg(
+                    Arg::new("study")
+                        .long("study")
+                        .value_name("ID")
+                        .help("Lichess study id or URL (omit to only write PGN)"),
+                )
+                .arg(Arg::new("name").long("name").value_name("NAME").help("Chapter name (default: Zugzwang puzzles)"))
+                .arg(
+                    Arg::new("token")
+                        .long("token")
+                        .value_name("TOKEN")
+                        .help("Lichess API token with study:write scope (default: $LICHESS_TOKEN)"),
+                )
+                .arg(
+                    Arg::new("pgn-out")
+                        .long("pgn-out")
+                        .value_name("FILE")
+                        .help("Also write the PGN to this file"),
+                ),
+        );
+    let matches = config.apply(cli).get_matches_from(args);
+    // The overlay and mini sinks' window processes: nothing else to set up
+    if let Some(("overlay-window", _)) = matches.subcommand() {
+        return overlay::run_window();
+    }
+    if let Some(("mini-window", _)) = matches.subcommand() {
+        return mini_window::run_window();
+    }
+
+    prompt::set_accessible(matches.get_flag("accessible"));
+    players::set_enabled(!matches.get_flag("no-player-ocr"));
+    critical::set_enabled(!matches.get_flag("no-critical"));
+    openings::set_enabled(!matches.get_flag("no-openings"));
+    material::set_enabled(!matches.get_flag("no-material"));
+    if let Some(path) = matches.get_one::<std::path::PathBuf>("openings") {
+        openings::set_path(path)?;
+    }
+    scouting::set_enabled(!matches.get_flag("no-scout") && !matches.get_flag("json"));
+    dataset::set_enabled(matches.get_flag("collect-dataset"));
+    if let Some(title) = matches.get_one::<String>("window") {
+        capture::set_window(title);
+    }
+    let input = matches.get_one::<capture::Input>("input").cloned().unwrap_or(capture::Input::Screen);
+    capture::set_input(input.clone());
+    if let Some(titles) = matches.get_one::<String>("when-focused") {
+        let mut titles: Vec<String> =
+            titles.split(',').map(str::trim).filter(|t| !t.is_empty()).map(String::from).collect();
+        if titles.is_empty() {
+            let site = matches.get_one::<String>("site").unwrap();
+            titles.push(capture::window_title().unwrap_or_else(|| capture::site_window_title(site)).to_string());
+        }
+        capture::set_focus_gate(&titles)?;
+    }
+    let site = matches.get_one::<String>("site").unwrap();
+    if let Some(theme) = matches.get_one::<String>("theme") {
+        anyhow::ensure!(
+            theme == "auto" || std::path::Path::new(&format!("templates/{}/{}", site, theme)).is_dir() || matches.get_flag("calibrate"),
+            "No template pack templates/{}/{}/ (packs: {})",
+            site,
+            theme,
+            ocr_native::themes(site).join(", ")
+        );
+        ocr_native::set_theme(theme);
+    }
+    if let Some(thresholds) = config.calibration.get(&ocr_native::calibration_key(site)) {
+        ocr_native::set_thresholds(*thresholds);
+    }
+    board_detect::set_site(site);
+    if let Some(region) = matches.get_one::<board_detect::Region>("fallback-region") {
+        board_detect::set_fallback_region(*region);
+    }
+
+    if matches.get_flag("calibrate") {
+        let side = match matches.get_one::<String>("side").map(String::as_str) {
+            Some("black") => PlayerSide::Black,
+            _ => PlayerSide::White,
+        };
+        let region = matches.get_one::<board_detect::BoardRegion>("board-region");
+        anyhow::ensure!(
+            matches.get_one::<String>("theme").is_none_or(|theme| theme != "auto"),
+            "--calibrate needs a theme name to save the pack under, not auto"
+        );
+        return calibrate::run(site, side, region);
+    }
+
+    let mut chain = match matches.get_one::<String>("llm-fallback") {
+        Some(list) => llm_provider::parse_chain(list).map_err(anyhow::Error::msg)?,
+        None => Vec::new(),
+    };
+    if let Some(provider) = matches.get_one::<String>("llm-provider").and_then(|name| llm_provider::Provider::from_name(name)) {
+        chain = llm_provider::with_primary(chain, provider);
+    }
+    if !chain.is_empty() {
+        llm_provider::set_chain(chain);
+    }
+    // Start with the first provider that has credentials
+    llm_provider::skip_unready(ocr_llm::provider_ready);
+
+    if let Some(url) = matches.get_one::<String>("llm-base-url") {
+        llm_provider::set_base_url(url);
+    }
+
+    if let Some(detail) = matches.get_one::<String>("detail") {
+        ocr_llm::set_detail(ocr_llm::ImageDetail::from_name(detail).unwrap_or_default());
+    }
+
+    if let Some(&budget) = matches.get_one::<u64>("token-budget") {
+        token_budget::set_budget(budget);
+    }
+
+    if let Some(command) = matches.get_one::<String>("ocr-cmd") {
+        ocr_command::set_command(command);
+    }
+    if let Some(command) = matches.get_one::<String>("fen-hook") {
+        fen_hook::set_command(command);
+    }
+    if let Some(path) = matches.get_one::<std::path::PathBuf>("llm-cache") {
+        llm_cache::set_file(path)?;
+    }
+    if let Some(model) = matches.get_one::<String>("cnn-model") {
+        ocr_cnn::set_model_path(model);
+    }
+    if let Some(&threshold) = matches.get_one::<f32>("hybrid-threshold") {
+        ocr_hybrid::set_threshold(threshold);
+    }
+    if matches.get_one::<String>("ocr").map(String::as_str) == Some("command") && !ocr_command::has_command() {
+        anyhow::bail!("--ocr command requires --ocr-cmd \"<program> {{image}}\"");
+    }
+
+    // Configure the LLM proxy before any request (including API key checks)
+    if let Some(proxy) = matches.get_one::<String>("proxy") {
+        ocr_llm::set_proxy(proxy)?;
+    }
+
+    // Before the engine starts, so its process inherits the priority
+    if matches.get_flag("nice")
+        && let Err(e) = resources::lower_priority()
+    {
+        eprintln!("⚠ {:#}", e);
+    }
+    if let Some(&threads) = matches.get_one::<u16>("cpu-limit") {
+        resources::set_cpu_limit(usize::from(threads));
+    }
+
+    if matches.get_one::<String>("engine").unwrap() != "tanton" {
+        engine::set_backend(engine::EngineBackend::Uci(engine::UciConfig {
+            path: matches.get_one::<String>("engine-path").unwrap().clone(),
+            threads: *matches.get_one::<usize>("engine-threads").unwrap(),
+            hash_mb: *matches.get_one::<usize>("engine-hash").unwrap(),
+            skill: matches.get_one::<u8>("engine-skill").copied(),
+        }))?;
+    }
+
+    if let Some(("play", play_matches)) = matches.subcommand() {
+        let player_side = match play_matches.get_one::<String>("side").map(String::as_str) {
+            Some("black") => PlayerSide::Black,
+            _ => PlayerSide::White,
+        };
+        return play::run(
+            play_matches.get_one::<String>("fen").unwrap(),
+            player_side,
+            play_matches.get_one::<u16>("depth").copied().unwrap_or(engine::DEFAULT_DEPTH),
+            play_matches.get_one::<u16>("rating").copied(),
+        );
+    }
+
+    if let Some(("guess", guess_matches)) = matches.subcommand() {
+        // No side (or auto): guess both sides' moves
+        let side = guess_matches.get_one::<String>("side").filter(|side| *side != "auto").map(|side| match side.as_str() {
+            "black" => PlayerSide::Black,
+            _ => PlayerSide::White,
+        });
+        return guess::run(
+            guess_matches.get_one::<String>("pgn").unwrap(),
+            side,
+            guess_matches.get_one::<u16>("depth").copied().unwrap_or(engine::backend_depth(engine::DEFAULT_DEPTH)),
+        );
+    }
+
+    if let Some(("deep", deep_matches)) = matches.subcommand() {
+        let hours = *deep_matches.get_one::<f64>("hours").unwrap();
+        anyhow::ensure!(hours > 0.0, "--hours must be positive");
+        let job = deep::DeepJob {
+            fen: deep_matches.get_one::<String>("fen").unwrap().clone(),
+            duration: Duration::from_secs_f64(hours * 3600.0),
+            engine_path: deep_matches.get_one::<String>("engine-path").unwrap().clone(),
+            multipv: *deep_matches.get_one::<usize>("multipv").unwrap(),
+            threads: *deep_matches.get_one::<usize>("threads").unwrap(),
+            hash_mb: *deep_matches.get_one::<usize>("hash").unwrap(),
+            checkpoint_every: Duration::from_secs(deep_matches.get_one::<u64>("checkpoint").unwrap() * 60),
+        };
+        deep::run(&job).await?;
+        return Ok(());
+    }
+
+    if let Some(("history", history_matches)) = matches.subcommand() {
+        if let Some(("fens", m)) = history_matches.subcommand() {
+            let path = session::resolve(m.get_one::<String>("session").unwrap())?;
+            for entry in session::load(&path)? {
+                println!("{}", session::fen_history_line(&entry, m.get_flag("json"))?);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(("snapshots", snapshot_matches)) = matches.subcommand() {
+        return match snapshot_matches.subcommand() {
+            Some(("analyze", m)) => snapshots::analyze(
+                m.get_one::<String>("snapshot").map(String::as_str),
+                m.get_one::<u16>("depth").copied().unwrap_or(engine::backend_depth(snapshots::SNAPSHOT_DEPTH)),
+            ),
+            _ => snapshots::list(),
+        };
+    }
+
+    if let Some(("export-study", export_matches)) = matches.subcommand() {
+        let player_side = match export_matches.get_one::<String>("side").map(String::as_str) {
+            Some("black") => PlayerSide::Black,
+            _ => PlayerSide::White,
+        };
+        return export_study(export_matches, player_side).await;
+    }
+
+    if let Some(("tactics", tactics_matches)) = matches.subcommand() {
+        let player_side = match tactics_matches.get_one::<String>("side").map(String::as_str) {
+            Some("black") => PlayerSide::Black,
+            _ => PlayerSide::White,
+        };
+        return export_tactics(tactics_matches, player_side).await;
+    }
+
+    // `prep` runs the live pipeline below with the repertoire loaded
+    let mut prep = match matches.subcommand() {
+        Some(("prep", prep_matches)) => Some(repertoire::PrepTracker::new(repertoire::Repertoire::load(
+            prep_matches.get_one::<String>("repertoire").unwrap(),
+        )?)),
+        _ => None,
+    };
+
+    if let Some(("queue", queue_matches)) = matches.subcommand() {
+        return match queue_matches.subcommand() {
+            Some(("add", m)) => queue::add(
+                m.get_one::<String>("name").unwrap(),
+                queue::GameSource::parse(m.get_one::<String>("source").unwrap())?,
+            ),
+            Some(("remove", m)) => queue::remove(m.get_one::<String>("name").unwrap()),
+            Some(("run", m)) => {
+                queue::run(
+                    m.get_one::<u16>("depth").copied().unwrap_or(engine::backend_depth(queue::QUEUE_DEPTH)),
+                    m.get_one::<u64>("watch").map(|min| Duration::from_secs(min * 60)),
+                )
+                .await
+            }
+            _ => queue::list(),
+        };
+    }
+
+    if let Some(("bench-ocr", bench_matches)) = matches.subcommand() {
+        let ocr_mode = bench_matches
+            .get_one::<String>("ocr")
+            .and_then(|name| OcrMode::from_name(name))
+            .unwrap_or_default();
+        if ocr_mode.uses_llm() && !ocr::llm_available() {
+            prompt_for_api_key().await?;
+        }
+        let player_side = match bench_matches.get_one::<String>("side").map(String::as_str) {
+            Some("black") => PlayerSide::Black,
+            _ => PlayerSide::White,
+        };
+        let limits = ocr::BatchLimits::new(
+            *bench_matches.get_one::<usize>("parallel").unwrap(),
+            *bench_matches.get_one::<u32>("rate").unwrap(),
+        );
+        return bench::run(
+            bench_matches.get_one::<String>("dir").unwrap(),
+            bench_matches.get_one::<String>("site").unwrap(),
+            ocr_mode,
+            player_side,
+            limits,
+        )
+        .await;
+    }
+
+    if let Some(("replay", replay_matches)) = matches.subcommand() {
+        let mode = replay_matches
+            .get_one::<String>("ocr")
+            .and_then(|name| OcrMode::from_name(name))
+            .unwrap_or_default();
+        if mode.uses_llm() && !ocr::llm_available() {
+            prompt_for_api_key().await?;
+        }
+        let options = replay::Options {
+            mode,
+            site: replay_matches.get_one::<String>("site").unwrap().clone(),
+            player_side: match replay_matches.get_one::<String>("side").map(String::as_str) {
+                Some("black") => PlayerSide::Black,
+                _ => PlayerSide::White,
+            },
+            depth: engine::backend_depth(replay_matches.get_one::<u16>("depth").copied().unwrap_or(engine::DEFAULT_DEPTH)),
+            every_secs: replay_matches.get_one::<f64>("every").copied().unwrap_or(replay::DEFAULT_EVERY_SECS),
+            json: replay_matches.get_flag("json"),
+            pgn: replay_matches.get_one::<std::path::PathBuf>("pgn").cloned(),
+        };
+        return replay::run(replay_matches.get_one::<std::path::PathBuf>("path").unwrap(), &options).await;
+    }
+
+    if let Some(("fen", fen_matches)) = matches.subcommand() {
+        let mode = fen_matches
+            .get_one::<String>("ocr")
+            .and_then(|name| OcrMode::from_name(name))
+            .unwrap_or_default();
+        if mode.uses_llm() && !ocr::llm_available() {
+            prompt_for_api_key().await?;
+        }
+        let options = oneshot::FenOptions {
+            mode,
+            site: fen_matches.get_one::<String>("site").unwrap().clone(),
+            player_side: match fen_matches.get_one::<String>("side").map(String::as_str) {
+                Some("black") => PlayerSide::Black,
+                _ => PlayerSide::White,
+            },
+            json: fen_matches.get_flag("json"),
+        };
+        return oneshot::fen(fen_matches.get_one::<String>("image").unwrap(), &options).await;
+    }
+
+    if let Some(("analyze", analyze_matches)) = matches.subcommand() {
+        return oneshot::analyze(
+            analyze_matches.get_one::<String>("fen").unwrap(),
+            engine::backend_depth(analyze_matches.get_one::<u16>("depth").copied().unwrap_or(engine::DEFAULT_DEPTH)),
+            analyze_matches.get_one::<u64>("multipv").map_or(0, |&n| n as usize),
+            analyze_matches.get_flag("heatmap"),
+            analyze_matches.get_flag("json"),
+        );
+    }
+
+    // Answers to the startup questions, offered for saving once all are asked
+    let mut answers = config.clone();
+
+    // Determine OCR mode
+    let ocr_mode = if let Some(mode_str) = matches.get_one::<String>("ocr") {
+        // Explicit mode from CLI
+        let mode = OcrMode::from_name(mode_str).expect("clap only accepts registered backends");
+        if mode.uses_llm() && !ocr::llm_available() {
+            // Prompt for API key
+            prompt_for_api_key().await?;
+        }
+        mode
+    } else {
+        // No CLI flag - show interactive selector
+        let mode = select_ocr_mode_interactive().await?;
+        answers.ocr = Some(mode.name().to_string());
+        mode
+    };
+
+    let mut interval = *matches.get_one::<u64>("interval").unwrap();
+    // A preset only overrides the interval and depth when they weren't given explicitly
+    let interval_from_preset =
+        matches.value_source("interval") != Some(clap::parser::ValueSource::CommandLine) && config.interval.is_none();
+    let depth_from_preset = !matches.contains_id("depth");
+    let verbose = matches.get_flag("verbose");
+    let json = matches.get_flag("json");
+    let filter = output::Filter {
+        only: matches.get_many::<output::Field>("only").unwrap_or_default().copied().collect(),
+        suppress_fen: matches.get_flag("suppress-fen"),
+    };
+    // Stdout is reserved for results with --json and --only: no banner, prompts, or notes
+    let quiet = json || !filter.only.is_empty();
+    if matches.get_flag("progress") {
+        engine::set_progress(print_progress);
+    }
+    let describe = matches.get_flag("describe") || prompt::accessible();
+    let multipv_lines = matches.get_one::<u64>("multipv").map(|&n| n as usize);
+
+    // Determine trigger mode (the hotkey is manual mode with another way to trigger)
+    let hotkey = matches
+        .get_one::<String>("trigger")
+        .is_some_and(|trigger| trigger == "hotkey")
+        .then(|| matches.get_one::<&'static str>("hotkey").copied().unwrap_or(hotkey::DEFAULT_KEY));
+    let manual_mode = if let Some(trigger) = matches.get_one::<String>("trigger") {
+        // Explicit mode from CLI
+        trigger != "auto"
+    } else {
+        // No CLI flag - show interactive selector
+        let manual = select_trigger_mode_interactive()?;
+        answers.trigger = Some(if manual { "manual" } else { "auto" }.to_string());
+        manual
+    };
+
+    // Determine player side (None: auto-detect from the first captures)
+    let chosen_side = if let Some(side_str) = matches.get_one::<String>("side") {
+        // Explicit side from CLI
+        match side_str.as_str() {
+            "black" => Some(PlayerSide::Black),
+            "auto" => None,
+            _ => Some(PlayerSide::White),
+        }
+    } else {
+        // No CLI flag - show interactive selector
+        let side = select_player_side_interactive()?;
+        answers.side = Some(side.map_or("auto".to_string(), |side| side.to_string().to_lowercase()));
+        side
+    };
+    let player_side = chosen_side.unwrap_or_default();
+
+    // Determine analysis mode
+    let analysis_mode = if let Some(&mode) = matches.get_one::<AnalysisMode>("analysis") {
+        // Explicit mode from CLI
+        match mode {
+            AnalysisMode::Direct => {
+                // Direct mode requires LLM - ensure API key is available
+                if !ocr::llm_available() {
+                    prompt_for_api_key().await?;
+                }
+                AnalysisMode::Direct
+            }
+            AnalysisMode::Hybrid => {
+                if !ocr::llm_available() {
+                    prompt_for_api_key().await?;
+                }
+                AnalysisMode::Hybrid
+            }
+            AnalysisMode::Engine => AnalysisMode::Engine,
+        }
+    } else {
+        // No CLI flag - show interactive selector (only if LLM mode was selected)
+        if ocr_mode == OcrMode::Llm {
+            let mode = select_analysis_mode_interactive()?;
+            answers.analysis = Some(mode.name().to_string());
+            mode
+        } else {
+            // Native OCR can only use engine analysis
+            AnalysisMode::Engine
+        }
+    };
+
+    if answers != config {
+        offer_to_save(&answers)?;
+    }
+
+    // Template matching needs a flat, screen-rendered board; photos are read by the LLM
+    anyhow::ensure!(
+        !capture::is_camera() || analysis_mode == AnalysisMode::Direct || ocr_mode == OcrMode::Llm,
+        "Camera input needs --ocr llm (native templates can't read a physical board)"
+    );
+
+    turn::set_enabled(!matches.get_flag("assume-turn"));
+    if let Some(style) = matches.get_one::<String>("notation").and_then(|name| notation::Notation::from_name(name)) {
+        notation::set_notation(style);
+    }
+    if let Some(language) = matches.get_one::<String>("tts-language").and_then(|code| speech::Language::from_code(code)) {
+        speech::set_language(language);
+    }
+    if let Some(voice) = matches.get_one::<String>("tts-voice") {
+        speech::set_voice(voice);
+    }
+    let mut sinks: Vec<output::Sink> = matches.get_many("output").unwrap_or_default().cloned().collect();
+    if matches.get_flag("tui") {
+        // The dashboard takes the console's place, and its units
+        let units = sinks.iter().find(|s| s.spec == output::SinkSpec::Console).map(|s| s.units).unwrap_or_default();
+        sinks.retain(|s| s.spec != output::SinkSpec::Console);
+        sinks.insert(0, output::Sink { spec: output::SinkSpec::Tui, units });
+    }
+    if matches.get_flag("notify") && !sinks.iter().any(|s| s.spec == output::SinkSpec::Notify) {
+        // In addition to the usual output, not instead of it
+        if sinks.is_empty() {
+            sinks.push(output::Sink::new(output::SinkSpec::Console));
+        }
+        sinks.push(output::Sink::new(output::SinkSpec::Notify));
+    }
+    if let Some(token) = matches.get_one::<String>("remote-token") {
+        remote::set_token(token);
+    }
+    if let Some(&port) = matches.get_one::<u16>("remote")
+        && !sinks.iter().any(|s| s.spec == output::SinkSpec::Remote(port))
+    {
+        if sinks.is_empty() {
+            sinks.push(output::Sink::new(output::SinkSpec::Console));
+        }
+        sinks.push(output::Sink::new(output::SinkSpec::Remote(port)));
+    }
+    // `serve` runs the live pipeline with the results also served over HTTP
+    if let Some(("serve", serve_matches)) = matches.subcommand() {
+        if sinks.is_empty() {
+            sinks.push(output::Sink::new(output::SinkSpec::Console));
+        }
+        sinks.push(output::Sink::new(output::SinkSpec::Http(*serve_matches.get_one::<u16>("port").unwrap())));
+    }
+    let mut outputs = output::Outputs::start(&sinks, json, verbose, &filter).await?;
+
+    // Startup banner (stdout is reserved for results with --json and --only)
+    if !quiet {
+        println!();
+        println!("╔═══════════════════════════════════════════════════════════╗");
+        println!("║         Zugzwang-RS Chess Assistant v0.1.5                ║");
+        println!("╚═══════════════════════════════════════════════════════════╝");
+        println!();
+        match chosen_side {
+            Some(side) => println!("  Playing:   {}", side),
+            None => println!("  Playing:   auto (detected from the board)"),
+        }
+        println!("  Analysis:  {}", analysis_mode);
+        if analysis_mode != AnalysisMode::Direct {
+            println!("  OCR Mode:  {}", ocr_mode);
+        }
+        let trigger_display = if let Some(key) = hotkey {
+            format!("hotkey (press {} anywhere, or Enter)", key)
+        } else if manual_mode {
+            "manual (press Enter)".to_string()
+        } else {
+            format!("auto ({}ms)", interval)
+        };
+        println!("  Trigger:   {}", trigger_display);
+        if let Some(lines) = multipv_lines {
+            println!("  MultiPV:   top {} moves", lines);
+        }
+        if matches.get_flag("advisor") {
+            println!("  Advisor:   resign/draw advice on");
+        }
+        if let Some(threads) = resources::cpu_limit() {
+            println!("  CPU limit: engine ≤{} threads while the game window is focused", threads);
+        }
+        if let Some(prep) = &prep {
+            let (positions, games) = prep.repertoire().size();
+            println!("  Repertoire: {} positions from {} games", positions, games);
+        }
+        match &input {
+            capture::Input::Camera(index) => println!("  Input:     camera {}", index),
+            capture::Input::Replay(dir) => println!("  Input:     replay of {}", dir.display()),
+            capture::Input::Screen => {}
+        }
+        if ocr_mode == OcrMode::Native {
+            println!("  Site:      {}", site);
+        } else if ocr_mode == OcrMode::Custom("hybrid") {
+            println!("  Site:      {} (LLM below confidence {})", site, ocr_hybrid::threshold());
+        } else if ocr_mode == OcrMode::Custom("cnn") {
+            println!("  Model:     {}", ocr_cnn::model_path());
+        } else {
+            println!("  Detail:    {}", ocr_llm::detail());
+        }
+        if llm_provider::chain().len() > 1 {
+            let names: Vec<String> = llm_provider::chain().iter().map(|p| p.to_string()).collect();
+            println!("  LLM chain: {}", names.join(" → "));
+        } else if llm_provider::active() != llm_provider::Provider::OpenAi {
+            let provider = llm_provider::active();
+            println!("  LLM:       {} ({})", provider, provider.model());
+        }
+        let depth = matches.get_one::<u16>("depth").copied().unwrap_or(engine::backend_depth(engine::DEFAULT_DEPTH));
+        let setup = estimate_setup(analysis_mode, ocr_mode, player_side, depth, (!manual_mode).then_some(interval));
+        println!("  Estimate:  {}", cost_estimate::estimate(&setup).line(&setup));
+        if outputs.names() != ["console"] {
+            println!("  Outputs:   {}", outputs.names().join(", "));
+        }
+        if verbose {
+            println!("  Verbose:   enabled");
+        }
+        println!();
+        if let Some(key) = hotkey {
+            println!("  Press {} (or Enter here) to capture & analyze, Ctrl+C to stop.", key);
+        } else if manual_mode {
+            println!("  Press Enter to capture & analyze, Ctrl+C to stop.");
+        } else {
+            println!("  Press Ctrl+C to stop.");
+        }
+        println!("  {}", controls::HELP_LINE);
+        println!();
+        println!("─────────────────────────────────────────────────────────────");
+        println!();
+    }
+
+    // Settings the user can change mid-run via single-key commands
+    let mut settings = RuntimeSettings {
+        depth: matches.get_one::<u16>("depth").copied().unwrap_or(engine::backend_depth(engine::DEFAULT_DEPTH)),
+        multipv: multipv_lines.is_some(),
+        multipv_lines: multipv_lines.unwrap_or(controls::DEFAULT_MULTIPV),
+        ocr_mode,
+        player_side,
+    };
+    // The dashboard reads keys itself (raw mode), so stdin gets no line reader then
+    let (command_sender, mut commands) = if sinks.iter().any(|s| s.spec == output::SinkSpec::Tui) {
+        let channel = controls::channel();
+        tui::set_commands(channel.0.clone());
+        channel
+    } else {
+        controls::spawn_listener()
+    };
+    if manual_mode {
+        // The phone remote triggers like Enter does
+        remote::set_commands(command_sender.clone());
+    }
+    if let Some(key) = hotkey {
+        hotkey::spawn(key, command_sender)?;
+    }
+
+    // Several boards run their own pipelines instead of the single-board loop below
+    if let Some(regions) = matches.get_many::<board_detect::BoardRegion>("board-region") {
+        anyhow::ensure!(analysis_mode == AnalysisMode::Engine, "--board-region supports engine analysis only");
+        anyhow::ensure!(chosen_side.is_some(), "--side auto isn't supported with --board-region (add :black to a region instead)");
+        let options = multi_board::Options { site, manual: manual_mode, interval_ms: interval, json };
+        return multi_board::run(regions.copied().collect(), settings, options, commands).await;
+    }
+
+    // Main pipeline loop
+    let mut cycle_count = 0u64;
+    // Last recognized position, kept so single squares can be corrected by hand
+    let mut last_fen: Option<String> = None;
+    // Previous positions fill in what a screenshot can't show (en passant, move counters)
+    let mut history = history::PositionHistory::new();
+    let mut eval_smoother = eval_smoothing::EvalSmoother::default();
+    // Moves typed with `move`, explored as a tree from the last board
+    let mut what_if: Option<game::GameTree> = None;
+    // Last analyzed capture, saved by `snapshot`
+    let mut last_analysis: Option<snapshots::Analyzed> = None;
+    // Analyzed positions are logged for later review and export; the previous session's
+    // frames are labeled before this one starts
+    if dataset::enabled() {
+        dataset::label_last_or_warn();
+    }
+    let session_log = session::SessionLog::start();
+    let mut analysis_tracker = analysis_board::AnalysisTracker::new(
+        analysis_board::AnalysisBoardMode::from_name(matches.get_one::<String>("analysis-board").unwrap())
+            .unwrap_or_default(),
+    );
+    let pause_post_game = matches.get_one::<String>("post-game").is_some_and(|mode| mode == "pause");
+    let mut in_review = false;
+    let mut time_control = matches.get_one::<String>("time-control").cloned();
+    if let Some(tc) = &time_control {
+        apply_preset(tc, depth_from_preset.then_some(&mut settings.depth), interval_from_preset.then_some(&mut interval), quiet);
+    }
+    // Players and time control are detected once, after the first capture
+    let mut game_start_pending = true;
+    // `--side auto`: remaining captures to try before settling on the default side
+    let mut side_attempts = if chosen_side.is_none() { orientation::DETECT_ATTEMPTS } else { 0 };
+    // A named opponent is scouted up front; otherwise once the name plate has been read
+    let opponent = matches.get_one::<String>("opponent");
+    if let Some(name) = opponent {
+        scout(site, name, time_control.as_deref()).await;
+    }
+    // Auto mode skips cycles while another window is focused (`--when-focused`)
+    let mut paused = false;
+    // Auto mode drops captures whose board looks exactly like the previous one
+    let mut deduper = frame_hash::FrameDeduper::default();
+    // Auto mode keeps watching the screen during a search and stops it once the board changes
+    let watch_board = !manual_mode && input == capture::Input::Screen;
+    let mut advisor = matches.get_flag("advisor").then(|| {
+        let defaults = advisor::Config::default();
+        advisor::Advisor::new(advisor::Config {
+            lost_percent: matches.get_one::<u8>("advisor-lost").map_or(defaults.lost_percent, |&p| f64::from(p)),
+            moves: matches.get_one::<u8>("advisor-moves").map_or(defaults.moves, |&n| usize::from(n)),
+        })
+    });
+
+    shutdown::install();
+    'cycles: loop {
+        if shutdown::requested() {
+            break;
+        }
+        if manual_mode {
+            // Wait for Enter, applying any setting changes typed in the meantime
+            if !quiet {
+                match hotkey {
+                    Some(key) => print!("▶ Press {} or Enter to capture & analyze... ", key),
+                    None => print!("▶ Press Enter to capture & analyze... "),
+                }
+                io::Write::flush(&mut io::stdout())?;
+            }
+            loop {
+                let command = tokio::select! {
+                    command = commands.recv() => command,
+                    _ = shutdown::wait() => break 'cycles,
+                };
+                match command {
+                    Some(ControlCommand::Capture) => break,
+                    Some(command) => {
+                        if let Some(result) =
+                            handle_command(&mut settings, command, &mut last_fen, &mut what_if, &last_analysis, site).await
+                        {
+                            outputs.emit(&result).await;
+                        }
+                    }
+                    None => break 'cycles, // stdin closed
+                }
+            }
+        } else {
+            // Apply any commands typed since the last cycle (Enter alone is a no-op here)
+            while let Ok(command) = commands.try_recv() {
+                if let Some(result) = handle_command(&mut settings, command, &mut last_fen, &mut what_if, &last_analysis, site).await {
+                    outputs.emit(&result).await;
+                }
+                // Settings may have changed: analyze the next frame even if the board didn't
+                deduper.reset();
+            }
+            if !focus_gate(&mut paused, quiet) {
+                shutdown::sleep(Duration::from_millis(interval)).await;
+                continue;
+            }
+        }
+
+        cycle_count += 1;
+        let cycle_start = std::time::Instant::now();
+        let mut timings = Vec::new();
+
+        if verbose {
+            println!("┌─ Cycle {} ─────────────────────────────────────────────────", cycle_count);
+        }
+
+        // Step 1: Capture full screenshot
+        let step_start = std::time::Instant::now();
+        let frame = match capture::capture_screenshot() {
+            Ok(frame) => frame,
+            // A replayed recording has no frames left
+            Err(e) if e.is::<capture::ReplayFinished>() => break,
+            Err(e) => return Err(e.context("Failed to capture screenshot")),
+        };
+        timings.push(("capture", step_start.elapsed()));
+        if verbose {
+            println!("│ [1] Capture:  {:>6.1}ms", step_start.elapsed().as_secs_f64() * 1000.0);
+        }
+
+        if !manual_mode && deduper.is_duplicate(&frame.image).unwrap_or(false) {
+            if verbose {
+                println!("│ Board unchanged, skipped");
+                println!("└─────────────────────────────────────────────────────────────");
+            }
+            shutdown::sleep(Duration::from_millis(interval)).await;
+            continue;
+        }
+
+        if pause_post_game && !post_game_gate(&mut in_review, &frame.image, quiet) {
+            if verbose {
+                println!("│ Post-game review on screen, skipped");
+                println!("└─────────────────────────────────────────────────────────────");
+            }
+            shutdown::sleep(Duration::from_millis(interval)).await;
+            continue;
+        }
+
+        report_cpu_limit(site, quiet);
+        if side_attempts > 0 {
+            side_attempts -= 1;
+            detect_side(&mut settings, &frame, site, side_attempts == 0, quiet).await;
+        }
+
+        // Branch based on analysis mode
+        match analysis_mode {
+            AnalysisMode::Direct => {
+                // Direct LLM analysis: LLM sees board and decides move
+                let step_start = std::time::Instant::now();
+                let recommendation = ocr::recommend_move(&frame.image, settings.player_side)
+                    .await
+                    .context("Failed to analyze board with LLM")?;
+                timings.push(("llm", step_start.elapsed()));
+                if verbose && !quiet {
+                    println!("│ [2] LLM:      {:>6.1}ms", step_start.elapsed().as_secs_f64() * 1000.0);
+                    println!("│ [3] Total:    {:>6.1}ms", cycle_start.elapsed().as_secs_f64() * 1000.0);
+                }
+                let result = output::CycleOutput {
+                    value: with_capture(recommendation_json(&recommendation), &frame),
+                    lines: recommendation_lines(&recommendation),
+                    headline: format!("Move: {} ({})", recommendation.best_move, recommendation.evaluation),
+                    timings,
+                };
+                outputs.emit(&result).await;
+                session_stats::record_cycle(&result.timings);
+            }
+            AnalysisMode::Engine => {
+                // Traditional pipeline: OCR → FEN → Engine
+                // Step 2: OCR to FEN (async)
+                let step_start = std::time::Instant::now();
+                let fen = ocr::board_to_fen(&frame.image, site, settings.ocr_mode, settings.player_side)
+                    .await
+                    .context("Failed to recognize board from screenshot")?;
+                let fen = history.infer(&fen);
+                timings.push(("ocr", step_start.elapsed()));
+                if verbose {
+                    println!("│ [2] OCR:      {:>6.1}ms", step_start.elapsed().as_secs_f64() * 1000.0);
+                    if let Some(detector) = board_detect::take_last_detector() {
+                        println!("│     Board:    {}", detector);
+                    }
+                }
+                if !position_playable(&fen, &mut deduper) {
+                    shutdown::sleep(Duration::from_millis(interval)).await;
+                    continue;
+                }
+                if !manual_mode && deduper.is_same_position(&fen) {
+                    if verbose {
+                        println!("│ Position unchanged, skipped");
+                        println!("└─────────────────────────────────────────────────────────────");
+                    }
+                    shutdown::sleep(Duration::from_millis(interval)).await;
+                    continue;
+                }
+
+                // Step 3: Engine analysis
+                let step_start = std::time::Instant::now();
+                // Critical moment detection compares the top candidates; only MultiPV's are listed
+                let listed = settings.candidate_count();
+                let count = if critical::enabled() { listed.max(critical::CANDIDATES) } else { listed };
+                let (engine::Analysis { best_move, eval, mut candidates }, stopped_early) =
+                    search_position(&fen, settings.depth, count, &frame, watch_board, interval)
+                        .await
+                        .context("Failed to analyze position")?;
+                let critical_moment = critical::detect(&candidates).filter(|_| !opponent_to_move(&fen, settings.player_side));
+                candidates.truncate(listed);
+                timings.push(("engine", step_start.elapsed()));
+                let description = describe.then(|| describe::describe_position(&fen)).transpose()?;
+                if verbose && !quiet {
+                    println!("│ [3] Engine:   {:>6.1}ms", step_start.elapsed().as_secs_f64() * 1000.0);
+                    if stopped_early {
+                        println!("│     Board changed, search stopped early");
+                    }
+                    println!("│ [4] Total:    {:>6.1}ms", cycle_start.elapsed().as_secs_f64() * 1000.0);
+                }
+                let mut value = with_description(engine_json(&fen, &best_move, &eval, &candidates), &description);
+                if stopped_early {
+                    value["stopped_early"] = serde_json::Value::Bool(true);
+                }
+                let mut lines = engine_lines(&fen, &description, &best_move, &eval, &candidates);
+                add_opening(&fen, &mut value, &mut lines);
+                add_material(&fen, &mut value, &mut lines);
+                let shown = notation::display(&fen, &best_move);
+                let mut headline = format!("Best: {} ({})", shown, eval);
+                if let Some(smoothed) = eval_smoother.update(&fen, &eval, settings.depth) {
+                    value["smoothed_evaluation"] = smoothed.into();
+                }
+                // Detected from the screen: the analysis is the opponent's reply, not our move
+                value["player_side"] = settings.player_side.to_string().to_lowercase().into();
+                if opponent_to_move(&fen, settings.player_side) {
+                    value["opponent_to_move"] = serde_json::Value::Bool(true);
+                    lines.insert(1, "Opponent to move (their best reply below)".to_string());
+                    headline = format!("Opponent's best: {} ({})", shown, eval);
+                }
+                if let Some(critical) = &critical_moment {
+                    value["critical"] = serde_json::json!({ "kind": critical.kind(), "reason": critical.describe() });
+                    lines.insert(1, format!("‼ Critical moment: {}", critical.describe()));
+                    headline = format!("‼ {}", headline);
+                }
+                let result = output::CycleOutput {
+                    value: with_capture(value, &frame),
+                    lines,
+                    headline,
+                    timings,
+                };
+                outputs.emit(&result).await;
+                session_stats::record_cycle(&result.timings);
+                // Analysis board positions go to the variation tree, not the game line
+                if !track_analysis_board(&mut analysis_tracker, &frame.image, &fen, quiet) {
+                    let note = critical_moment.as_ref().map(|critical| format!("Critical moment: {}.", critical.describe()));
+                    let move_time = session_log.record_or_warn(&frame, &fen, &best_move, &eval, note.as_deref());
+                    record_move_time(move_time, settings.player_side);
+                    follow_prep(&mut prep, &fen, settings.player_side, quiet);
+                    advise(&mut advisor, &frame, &fen, &eval, settings.player_side).await;
+                }
+                last_analysis = Some(snapshots::Analyzed {
+                    fen: fen.clone(),
+                    best_move,
+                    evaluation: eval,
+                    candidates,
+                    depth: settings.depth,
+                    image: std::sync::Arc::clone(&frame.image),
+                    cycle: frame.cycle.clone(),
+                });
+                last_fen = Some(fen);
+            }
+            AnalysisMode::Hybrid => {
+                // Second opinion: LLM recommendation and OCR run concurrently, then the engine
+                // scores both the LLM's move and its own pick at the same depth
+                let step_start = std::time::Instant::now();
+                let (fen, recommendation) = tokio::join!(
+                    ocr::board_to_fen(&frame.image, site, settings.ocr_mode, settings.player_side),
+                    ocr::recommend_move(&frame.image, settings.player_side),
+                );
+                let fen = history.infer(&fen.context("Failed to recognize board from screenshot")?);
+                let recommendation = recommendation.context("Failed to analyze board with LLM")?;
+                timings.push(("ocr+llm", step_start.elapsed()));
+                if verbose {
+                    println!("│ [2] OCR+LLM:  {:>6.1}ms", step_start.elapsed().as_secs_f64() * 1000.0);
+                    if let Some(detector) = board_detect::take_last_detector() {
+                        println!("│     Board:    {}", detector);
+                    }
+                }
+                if !position_playable(&fen, &mut deduper) {
+                    shutdown::sleep(Duration::from_millis(interval)).await;
+                    continue;
+                }
+
+                let step_start = std::time::Instant::now();
+                let (engine::Analysis { best_move, .. }, _) =
+                    search_position(&fen, settings.depth, 0, &frame, watch_board, interval)
+                        .await
+                        .context("Failed to analyze position")?;
+                let (engine_scored, llm_scored) = {
+                    let (fen, engine_move, llm_move) = (fen.clone(), best_move.clone(), recommendation.best_move.clone());
+                    let depth = settings.depth;
+                    tokio::task::spawn_blocking(move || -> Result<_> {
+                        Ok((engine::evaluate_move(&fen, &engine_move, depth)?, engine::evaluate_move(&fen, &llm_move, depth)?))
+                    })
+                    .await
+                    .context("Engine search panicked")??
+                };
+                timings.push(("engine", step_start.elapsed()));
+                if verbose {
+                    println!("│ [3] Engine:   {:>6.1}ms", step_start.elapsed().as_secs_f64() * 1000.0);
+                    println!("│ [4] Total:    {:>6.1}ms", cycle_start.elapsed().as_secs_f64() * 1000.0);
+                }
+
+                let check = CrossCheck {
+                    engine: engine_scored.unwrap_or((best_move, "--".to_string())),
+                    llm: llm_scored,
+                };
+                let description = describe.then(|| describe::describe_position(&fen)).transpose()?;
+                let mut value = with_description(hybrid_json(&fen, &recommendation, &check), &description);
+                if let Some(smoothed) = eval_smoother.update(&fen, &check.engine.1, settings.depth) {
+                    value["smoothed_evaluation"] = smoothed.into();
+                }
+                value["player_side"] = settings.player_side.to_string().to_lowercase().into();
+                let mut lines = vec![format!("FEN:  {}", fen)];
+                lines.extend(description.iter().map(|text| format!("Board: {}", text)));
+                lines.extend(cross_check_lines(&fen, &recommendation, &check));
+                add_material(&fen, &mut value, &mut lines);
+                let result = output::CycleOutput {
+                    value: with_capture(value, &frame),
+                    lines,
+                    headline: cross_check_headline(&fen, &check),
+                    timings,
+                };
+                outputs.emit(&result).await;
+                session_stats::record_cycle(&result.timings);
+                if !track_analysis_board(&mut analysis_tracker, &frame.image, &fen, quiet) {
+                    let move_time = session_log.record_or_warn(&frame, &fen, &check.engine.0, &check.engine.1, Some(&recommendation.reasoning));
+                    record_move_time(move_time, settings.player_side);
+                    follow_prep(&mut prep, &fen, settings.player_side, quiet);
+                    advise(&mut advisor, &frame, &fen, &check.engine.1, settings.player_side).await;
+                }
+                let (best_move, evaluation) = check.engine;
+                last_analysis = Some(snapshots::Analyzed {
+                    fen: fen.clone(),
+                    best_move,
+                    evaluation,
+                    candidates: Vec::new(),
+                    depth: settings.depth,
+                    image: std::sync::Arc::clone(&frame.image),
+                    cycle: frame.cycle.clone(),
+                });
+                last_fen = Some(fen);
+            }
+        }
+
+        if !quiet {
+            report_age(&frame, time_control.as_deref());
+        }
+
+        if game_start_pending {
+            game_start_pending = false;
+            let mut meta = session::SessionMeta::default();
+
+            // Direct mode records no session, so there is nothing to attach names to; a
+            // photo of a physical board has no name plates or clock to read
+            if analysis_mode != AnalysisMode::Direct && players::enabled() && !capture::is_camera() {
+                match players::read_players(&frame.image).await {
+                    Ok(found) => {
+                        if !quiet && !found.is_empty() {
+                            println!("Players: {}", found);
+                        }
+                        meta.players = found;
+                    }
+                    Err(e) => eprintln!("⚠ Player names not read: {:#}", e),
+                }
+            }
+            if time_control.is_none() && ocr_llm::llm_ready() && !capture::is_camera() {
+                match clock::read_time_control(&frame.image).await {
+                    Ok(Some(tc)) => {
+                        apply_preset(
+                            &tc,
+                            depth_from_preset.then_some(&mut settings.depth),
+                            interval_from_preset.then_some(&mut interval),
+                            quiet,
+                        );
+                        time_control = Some(tc);
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("⚠ Time control not detected: {:#}", e),
+                }
+            }
+            meta.time_control = time_control.clone();
+            if opponent.is_none()
+                && let Some(tag) = &meta.players.opponent
+            {
+                scout(site, &tag.name, time_control.as_deref()).await;
+            }
+
+            if analysis_mode != AnalysisMode::Direct
+                && !meta.is_empty()
+                && let Err(e) = session_log.record_meta(&meta)
+            {
+                eprintln!("⚠ Session details not recorded: {:#}", e);
+            }
+        }
+        if !quiet {
+            println!();
+        }
+
+        // Wait before next cycle (only in auto mode)
+        if !manual_mode {
+            shutdown::sleep(Duration::from_millis(interval)).await;
+        }
+    }
+
+    // Stopped between cycles: every log is complete. The outputs go first (the TUI gives
+    // the terminal back), then what interrupted requests left in the temp directory.
+    drop(outputs);
+    ocr_command::remove_scratch_images();
+    let summary = session_stats::summary(cycle_count);
+    if quiet {
+        eprintln!("{}", summary.join("\n"));
+    } else {
+        println!("Session summary");
+        for line in summary {
+            println!("  {}", line);
+        }
+    }
+    Ok(())
+}
+
+/// What a cycle does with the startup settings, for the banner's estimate
+fn estimate_setup(
+    analysis_mode: AnalysisMode,
+    ocr_mode: OcrMode,
+    side: PlayerSide,
+    depth: u16,
+    interval_ms: Option<u64>,
+) -> cost_estimate::Setup {
+    let llm_ocr = analysis_mode != AnalysisMode::Direct && ocr_mode == OcrMode::Llm;
+    let mut requests = Vec::new();
+    if llm_ocr {
+        requests.push(ocr_llm::request_shape(false, side));
+    }
+    if analysis_mode != AnalysisMode::Engine {
+        requests.push(ocr_llm::request_shape(true, side));
+    }
+    cost_estimate::Setup {
+        model: llm_provider::active().model(),
+        requests,
+        local_ocr: analysis_mode != AnalysisMode::Direct && !llm_ocr,
+        // Hybrid mode searches for its own move, then scores both candidates
+        searches: match analysis_mode {
+            AnalysisMode::Direct => 0,
+            AnalysisMode::Engine => 1,
+            AnalysisMode::Hybrid => 3,
+        },
+        search_ms: engine::estimated_search_ms(depth),
+        interval_ms,
+    }
+}
+
+/// Prints a scouting report on the opponent's recent games (failures are only warned about)
+async fn scout(site: &str, opponent: &str, time_control: Option<&str>) {
+    if !scouting::enabled() || !matches!(site, "chesscom" | "lichess") {
+        return;
+    }
+    let class = time_control.and_then(profiles::TimeClass::from_time_control);
+    match scouting::fetch_games(site, opponent, class).await {
+        Ok(games) => println!("{}", scouting::report(opponent, site, &games)),
+        Err(e) => eprintln!("⚠ Opponent not scouted: {:#}", e),
+    }
+}
+
+/// `--when-focused`: whether this cycle may capture; says when the loop pauses and resumes
+fn focus_gate(paused: &mut bool, json: bool) -> bool {
+    let allowed = capture::capture_allowed();
+    if allowed == *paused {
+        *paused = !allowed;
+        if !json {
+            if allowed {
+                println!("▶ Game window focused again, resuming");
+            } else {
+                println!("⏸ Paused: the game window isn't focused");
+            }
+        }
+    }
+    allowed
+}
+
+/// `--post-game pause`: whether the board can be read, or is decorated by the site's
+/// post-game review; says when analysis pauses and resumes
+fn post_game_gate(in_review: &mut bool, screenshot: &image::DynamicImage, json: bool) -> bool {
+    let review = post_game::detect(screenshot);
+    if review != *in_review {
+        *in_review = review;
+        if !json {
+            if review {
+                println!("⏸ Post-game review on screen: analysis paused until a plain board is back");
+            } else {
+                println!("▶ Review closed, resuming");
+            }
+        }
+    }
+    !review
+}
+
+/// Whether a recognized position can go to the engine. A misread board (e.g. the side not
+/// to move in check) is reported and the next frame is read again, even if it looks the same.
+fn position_playable(fen: &str, deduper: &mut frame_hash::FrameDeduper) -> bool {
+    match engine::check_position(fen) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("⚠ {:#} - skipped, the board will be read again", e);
+            deduper.reset();
+            false
+        }
+    }
+}
+
+/// Searches `fen` off the runtime. With `watch` on, the screen is captured again every
+/// `interval` ms meanwhile and a changed board cancels the search, which then answers with
+/// the best move found so far. Returns the analysis and whether it was cut short.
+async fn search_position(
+    fen: &str,
+    depth: u16,
+    count: usize,
+    frame: &capture::Frame,
+    watch: bool,
+    interval: u64,
+) -> Result<(engine::Analysis, bool)> {
+    let cancel = engine::CancelToken::default();
+    let search = engine::analyze_cancellable(fen, depth, count, &cancel);
+    if !watch {
+        return Ok((search.await?, false));
+    }
+    tokio::pin!(search);
+    tokio::select! {
+        analysis = &mut search => return Ok((analysis?, false)),
+        _ = board_changed(frame, interval) => cancel.cancel(),
+    }
+    Ok((search.await?, true))
+}
+
+/// Searches a position for a command off the runtime, so outputs keep running meanwhile.
+/// Ctrl+C stops the search with the best move found so far.
+async fn analyze_off_runtime(fen: &str, depth: u16) -> Result<(String, String)> {
+    let cancel = engine::CancelToken::default();
+    let search = engine::analyze_cancellable(fen, depth, 0, &cancel);
+    tokio::pin!(search);
+    tokio::select! {
+        analysis = &mut search => return analysis.map(|a| (a.best_move, a.eval)),
+        _ = shutdown::wait() => cancel.cancel(),
+    }
+    search.await.map(|a| (a.best_move, a.eval))
+}
+
+/// Returns once a new capture shows a different board than `frame`, or on Ctrl+C
+async fn board_changed(frame: &capture::Frame, interval: u64) {
+    let mut watcher = frame_hash::FrameDeduper::default();
+    let _ = watcher.is_duplicate(&frame.image);
+    loop {
+        shutdown::sleep(Duration::from_millis(interval)).await;
+        if shutdown::requested() {
+            return;
+        }
+        let Ok(now) = capture::capture_screenshot() else { continue };
+        if watcher.is_duplicate(&now.image).is_ok_and(|same| !same) {
+            return;
+        }
+    }
+}
+
+/// Adds a recorded move's time to the session stats, as the user's or the opponent's
+fn record_move_time(time: Option<pgn::MoveTime>, side: PlayerSide) {
+    if let Some(time) = time {
+        let user = (time.mover == shakmaty::Color::White) == (side == PlayerSide::White);
+        session_stats::record_move_time(user, Duration::from_millis(time.spent_ms));
+    }
+}
+
+/// Applies `--cpu-limit` for the focused window and says when it switches
+fn report_cpu_limit(site: &str, json: bool) {
+    match resources::update(site) {
+        Some(true) if !json => println!("Game window focused: engine limited to {} threads", resources::cpu_limit().unwrap_or(1)),
+        Some(false) if !json => println!("Game window in the background: engine at full strength"),
+        _ => {}
+    }
+}
+
+/// `--side auto`: sets the player's side from the board's orientation. `last` reports the
+/// fallback when this was the final attempt.
+async fn detect_side(settings: &mut RuntimeSettings, frame: &capture::Frame, site: &str, last: bool, json: bool) {
+    match orientation::detect(&frame.image, site, settings.ocr_mode).await {
+        Ok(Some(side)) => {
+            settings.player_side = side;
+            if !json {
+                println!("Side: {} ({} pieces at the bottom)", side, side);
+            }
+            return;
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("⚠ Side not detected: {:#}", e),
+    }
+    if last {
+        eprintln!("⚠ Couldn't tell the board's orientation; playing as {} (use --side to set it)", settings.player_side);
+    }
+}
+
+/// Looks a game position up in the repertoire (`prep`) and prints the book move or alert
+fn follow_prep(prep: &mut Option<repertoire::PrepTracker>, fen: &str, side: PlayerSide, json: bool) {
+    if let Some(prep) = prep
+        && let Some(text) = prep.update(fen, side)
+        && !json
+    {
+        println!("{}", text);
+    }
+}
+
+/// Feeds an analyzed game position to the resign/draw advisor and prints its advice, reading
+/// the clocks first when an LLM is available
+async fn advise(advisor: &mut Option<advisor::Advisor>, frame: &capture::Frame, fen: &str, eval: &str, side: PlayerSide) {
+    let Some(advisor) = advisor else {
+        return;
+    };
+    let Some(outlook) = advisor.observe(fen, eval, side) else {
+        return;
+    };
+    let clocks = if ocr_llm::llm_ready() && !capture::is_camera() {
+        clock::read_clocks(&frame.image).await.unwrap_or_else(|e| {
+            eprintln!("⚠ Clocks not read: {:#}", e);
+            None
+        })
+    } else {
+        None
+    };
+    if let Some(text) = advisor.advice(outlook, clocks) {
+        println!("Advice: {}", text);
+    }
+}
+
+/// Feeds a recognized position to the analysis board tracker.
+/// Returns true while the analysis board is showing.
+fn track_analysis_board(tracker: &mut analysis_board::AnalysisTracker, screenshot: &image::DynamicImage, fen: &str, json: bool) -> bool {
+    match tracker.update(screenshot, fen) {
+        Ok(Some(status)) if !json => println!("{}", status),
+        Ok(_) => {}
+        Err(e) => eprintln!("⚠ Analysis board: {:#}", e),
+    }
+    tracker.active()
+}
+
+/// Switches depth (and the capture interval, if not set explicitly) to the time control's preset
+fn apply_preset(time_control: &str, depth: Option<&mut u16>, interval: Option<&mut u64>, quiet: bool) {
+    let class = profiles::TimeClass::from_time_control(time_control).unwrap_or_default();
+    let preset = clock::Preset::for_class(class);
+    let depth = depth.map(|depth| {
+        *depth = engine::backend_depth(preset.depth);
+        *depth
+    });
+    if let Some(interval) = interval {
+        *interval = preset.interval_ms;
+    }
+    if quiet {
+        return;
+    }
+    match depth {
+        Some(depth) => println!("Time control {} ({}): depth {}", time_control, class.name(), depth),
+        None => println!("Time control {} ({})", time_control, class.name()),
+    }
+}
+
+/// Console lines for a direct-mode recommendation (move, evaluation, reasoning)
+fn recommendation_lines(recommendation: &MoveRecommendation) -> Vec<String> {
+    vec![
+        format!("Move: {}", recommendation.best_move),
+        format!("Eval: {}", recommendation.evaluation),
+        format!("Why:  {}", recommendation.reasoning),
+    ]
+}
+
+/// Console lines for an engine analysis: position, best move, and candidates
+fn engine_lines(
+    fen: &str,
+    description: &Option<String>,
+    best_move: &str,
+    eval: &str,
+    candidates: &[(String, String)],
+) -> Vec<String> {
+    let mut lines = vec![format!("FEN:  {}", fen)];
+    lines.extend(description.iter().map(|text| format!("Board: {}", text)));
+    lines.push(format!("Best: {} ({})", notation::display(fen, best_move), eval_units::with_win_chance(eval)));
+    for (rank, (mv, ev)) in candidates.iter().enumerate() {
+        lines.push(format!("  {}. {} ({})", rank + 1, notation::display(fen, mv), ev));
+    }
+    lines
+}
+
+/// Adds the opening's name and book moves to an analysis while the game is in the book
+fn add_opening(fen: &str, value: &mut serde_json::Value, lines: &mut Vec<String>) {
+    if let Some(opening) = openings::lookup(fen) {
+        lines.insert(1, opening.line());
+        value["opening"] = serde_json::json!(opening);
+    }
+}
+
+/// Adds the material summary to an analysis (unless turned off)
+fn add_material(fen: &str, value: &mut serde_json::Value, lines: &mut Vec<String>) {
+    if material::enabled()
+        && let Ok(summary) = material::summary(fen)
+    {
+        lines.insert(1, format!("Material: {}", summary));
+        value["material"] = summary.into();
+    }
 }
 
-impl std::fmt::Display for AnalysisMode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            AnalysisMode::Engine => write!(f, "Engine (Tanton ~2900 ELO)"),
-            AnalysisMode::Direct => write!(f, "Direct (GPT-4o with reasoning)"),
-        }
-    }
+/// True when the FEN has the other side to move than the player (detected from the screen)
+fn opponent_to_move(fen: &str, player_side: PlayerSide) -> bool {
+    fen.split_whitespace().nth(1).and_then(|turn| turn.chars().next()).is_some_and(|turn| turn != player_side.fen_turn())
 }
 
-/// Which side the user is playing - affects board orientation and turn in FEN
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
-pub enum PlayerSide {
-    #[default]
-    White,
-    Black,
+/// JSON line for a direct-mode recommendation
+fn recommendation_json(recommendation: &MoveRecommendation) -> serde_json::Value {
+    serde_json::json!({ "mode": "direct", "recommendation": recommendation })
 }
 
-impl PlayerSide {
-    /// Returns the FEN turn character for when it's this player's turn
-    pub fn fen_turn(&self) -> char {
-        match self {
-            PlayerSide::White => 'w',
-            PlayerSide::Black => 'b',
-        }
+/// Engine verdict on the LLM's recommendation in hybrid mode
+struct CrossCheck {
+    /// Engine's best move and its eval
+    engine: (String, String),
+    /// LLM's move normalized and scored by the engine (None if illegal in the recognized position)
+    llm: Option<(String, String)>,
+}
+
+impl CrossCheck {
+    /// True when the LLM and the engine picked the same move
+    fn agrees(&self) -> bool {
+        self.llm.as_ref().is_some_and(|(mv, _)| *mv == self.engine.0)
     }
+}
 
-    /// Returns true if board image needs vertical flip for correct FEN
-    /// (When playing as Black, the board is shown with Black at bottom)
-    pub fn needs_board_flip(&self) -> bool {
-        matches!(self, PlayerSide::Black)
+/// Hybrid result lines: one verdict when both agree, both moves with evals otherwise
+fn cross_check_lines(fen: &str, recommendation: &MoveRecommendation, check: &CrossCheck) -> Vec<String> {
+    let (engine_move, engine_eval) = &check.engine;
+    let engine_move = notation::display(fen, engine_move);
+    if check.agrees() {
+        return vec![
+            format!("Best: {} ({}) ✓ engine and LLM agree - high confidence", engine_move, engine_eval),
+            format!("Why:  {}", recommendation.reasoning),
+        ];
     }
+
+    let llm = match &check.llm {
+        Some((llm_move, llm_eval)) => format!("  LLM:    {} ({})", notation::display(fen, llm_move), llm_eval),
+        None => format!("  LLM:    {} (not legal in recognized position)", recommendation.best_move),
+    };
+    vec![
+        "⚠ Engine and LLM disagree:".to_string(),
+        format!("  Engine: {} ({})", engine_move, engine_eval),
+        llm,
+        format!("  Why:    {}", recommendation.reasoning),
+    ]
 }
 
-impl std::fmt::Display for PlayerSide {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            PlayerSide::White => write!(f, "White"),
-            PlayerSide::Black => write!(f, "Black"),
-        }
+/// One-line hybrid verdict (clipboard, speech)
+fn cross_check_headline(fen: &str, check: &CrossCheck) -> String {
+    let (engine_move, engine_eval) = &check.engine;
+    let engine_move = notation::display(fen, engine_move);
+    match &check.llm {
+        _ if check.agrees() => format!("Best: {} ({})", engine_move, engine_eval),
+        Some((llm_move, _)) => format!("Engine: {} ({}), LLM: {}", engine_move, engine_eval, notation::display(fen, llm_move)),
+        None => format!("Best: {} ({})", engine_move, engine_eval),
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Parse CLI arguments
-    let matches = Command::new("Zugzwang-RS")
-        .version("0.1.1")
-        .author("Crimson Sun")
-        .about("Pure-Rust chess assistant for browser windows")
-        .arg(
-            Arg::new("ocr")
-                .long("ocr")
-                .value_name("MODE")
-                .help("OCR mode: native (default) or llm")
-                .value_parser(["native", "llm"]),
-        )
-        .arg(
-            Arg::new("interval")
-                .long("interval")
-                .value_name("MS")
-                .help("Loop interval in milliseconds")
-                .default_value("1000")
-                .value_parser(clap::value_parser!(u64)),
-        )
-        .arg(
-            Arg::new("site")
-                .long("site")
-                .value_name("SITE")
-                .help("Chess site for native OCR templates")
-                .default_value("chesscom")
-                .value_parser(["chesscom", "lichess", "macOS"]),
-        )
-        .arg(
-            Arg::new("verbose")
-                .short('v')
-                .long("verbose")
-                .help("Enable verbose logging for debugging")
-                .action(clap::ArgAction::SetTrue),
-        )
-        .arg(
-            Arg::new("trigger")
-                .long("trigger")
-                .value_name("MODE")
-                .help("Capture trigger: auto (interval-based) or manual (press Enter)")
-                .value_parser(["auto", "manual"]),
-        )
-        .arg(
-            Arg::new("side")
-                .long("side")
-                .value_name("SIDE")
-                .help("Which side you are playing: white (default) or black")
-                .value_parser(["white", "black"]),
-        )
-        .arg(
-            Arg::new("analysis")
-                .long("analysis")
-                .value_name("MODE")
-                .help("Analysis mode: engine (Tanton) or direct (GPT-4o decides move)")
-                .value_parser(["engine", "direct"]),
-        )
-        .get_matches();
+/// JSON line for a hybrid cross-check
+fn hybrid_json(fen: &str, recommendation: &MoveRecommendation, check: &CrossCheck) -> serde_json::Value {
+    serde_json::json!({
+        "mode": "hybrid",
+        "fen": fen,
+        "agree": check.agrees(),
+        "engine": { "move": check.engine.0, "evaluation": check.engine.1 },
+        "llm": {
+            "recommendation": recommendation,
+            "legal": check.llm.is_some(),
+            "engine_evaluation": check.llm.as_ref().map(|(_, eval)| eval),
+        },
+    })
+}
 
-    // Determine OCR mode
-    let ocr_mode = if let Some(mode_str) = matches.get_one::<String>("ocr") {
-        // Explicit mode from CLI
-        match mode_str.as_str() {
-            "llm" => {
-                if !ocr::llm_available() {
-                    // Prompt for API key
-                    prompt_for_api_key()?;
-                }
-                OcrMode::Llm
-            }
-            "native" => OcrMode::Native,
-            _ => unreachable!(),
-        }
-    } else {
-        // No CLI flag - show interactive selector
-        select_ocr_mode_interactive()?
-    };
+/// JSON line for an engine analysis
+fn engine_json(fen: &str, best_move: &str, eval: &str, candidates: &[(String, String)]) -> serde_json::Value {
+    let candidates: Vec<_> = candidates
+        .iter()
+        .map(|(mv, ev)| serde_json::json!({ "move": mv, "evaluation": ev }))
+        .collect();
+    serde_json::json!({
+        "mode": "engine",
+        "fen": fen,
+        "best_move": best_move,
+        "evaluation": eval,
+        "candidates": candidates,
+    })
+}
 
-    let interval = *matches.get_one::<u64>("interval").unwrap();
-    let site = matches.get_one::<String>("site").unwrap();
-    let verbose = matches.get_flag("verbose");
+/// JSON line for a depth reached while the engine searches (`--progress`)
+fn progress_json(progress: &engine::Progress) -> serde_json::Value {
+    serde_json::json!({
+        "mode": "progress",
+        "fen": progress.fen,
+        "depth": progress.depth,
+        "evaluation": progress.eval,
+        "line": progress.line,
+    })
+}
 
-    // Determine trigger mode
-    let manual_mode = if let Some(trigger) = matches.get_one::<String>("trigger") {
-        // Explicit mode from CLI
-        trigger == "manual"
-    } else {
-        // No CLI flag - show interactive selector
-        select_trigger_mode_interactive()?
-    };
+fn print_progress(progress: &engine::Progress) {
+    println!("{}", progress_json(progress));
+}
 
-    // Determine player side
-    let player_side = if let Some(side_str) = matches.get_one::<String>("side") {
-        // Explicit side from CLI
-        match side_str.as_str() {
-            "black" => PlayerSide::Black,
-            _ => PlayerSide::White,
-        }
+/// Adds the plain-language description (if requested) to a JSON result
+fn with_description(mut value: serde_json::Value, description: &Option<String>) -> serde_json::Value {
+    if let Some(text) = description {
+        value["description"] = serde_json::Value::from(text.as_str());
+    }
+    value
+}
+
+/// Adds the cycle ID, when the analyzed board was captured, and how old it was when the
+/// result was ready
+fn with_capture(mut value: serde_json::Value, frame: &capture::Frame) -> serde_json::Value {
+    value["cycle"] = serde_json::Value::from(frame.cycle.as_str());
+    value["captured_at_ms"] = serde_json::Value::from(frame.captured_ms as u64);
+    value["age_ms"] = serde_json::Value::from(frame.age().as_millis() as u64);
+    value
+}
+
+/// Reports how old the board behind the suggestion is, warning when analysis falls behind
+/// the pace of the game's time control
+fn report_age(frame: &capture::Frame, time_control: Option<&str>) {
+    let age = frame.age();
+    let max_lag_ms = time_control.map(|tc| clock::Preset::for_time_control(tc).max_lag_ms);
+    if max_lag_ms.is_some_and(|max| age.as_millis() > u128::from(max)) {
+        println!("⚠ Suggestion based on board {:.1}s ago - analysis is lagging (lower depth with d)", age.as_secs_f64());
     } else {
-        // No CLI flag - show interactive selector
-        select_player_side_interactive()?
-    };
+        println!("Suggestion based on board {:.1}s ago", age.as_secs_f64());
+    }
+}
 
-    // Determine analysis mode
-    let analysis_mode = if let Some(mode_str) = matches.get_one::<String>("analysis") {
-        // Explicit mode from CLI
-        match mode_str.as_str() {
-            "direct" => {
-                // Direct mode requires LLM - ensure API key is available
-                if !ocr::llm_available() {
-                    prompt_for_api_key()?;
-                }
-                AnalysisMode::Direct
+/// Applies a runtime keyboard command and reports the result. A correction returns the
+/// re-analyzed board, for the outputs.
+async fn handle_command(
+    settings: &mut RuntimeSettings,
+    command: ControlCommand,
+    last_fen: &mut Option<String>,
+    what_if: &mut Option<game::GameTree>,
+    last_analysis: &Option<snapshots::Analyzed>,
+    site: &str,
+) -> Option<output::CycleOutput> {
+    match command {
+        ControlCommand::Help => println!("  {}", controls::HELP_LINE),
+        ControlCommand::Snapshot(name) => {
+            let result = last_analysis
+                .as_ref()
+                .context("No analyzed position yet")
+                .and_then(|analyzed| snapshots::save(&name, analyzed));
+            match result {
+                Ok(snapshot) => println!("📌 Snapshot saved: {} ({} {})", snapshot.label(), snapshot.best_move, snapshot.evaluation),
+                Err(e) => println!("⚠ Snapshot not saved: {:#}", e),
             }
-            _ => AnalysisMode::Engine,
         }
-    } else {
-        // No CLI flag - show interactive selector (only if LLM mode was selected)
-        if ocr_mode == OcrMode::Llm {
-            select_analysis_mode_interactive()?
-        } else {
-            // Native OCR can only use engine analysis
-            AnalysisMode::Engine
+        ControlCommand::Correct { file, rank, piece } => match correct_square(settings, last_fen, site, file, rank, piece).await {
+            Ok(result) => return Some(result),
+            Err(e) => println!("⚠ Correction failed: {:#}", e),
+        },
+        ControlCommand::PlayMove(text) => {
+            let explored = last_fen
+                .as_deref()
+                .context("No recognized board yet")
+                .and_then(|fen| explore_move(what_if, fen, &text));
+            let result = match explored {
+                Ok(fen) => show_typed_position(settings, last_fen, fen).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                println!("⚠ Move not applied: {:#}", e);
+            }
+        }
+        ControlCommand::SetFen(fen) => {
+            let result = match shakmaty::fen::Fen::from_ascii(fen.as_bytes()) {
+                Ok(_) => show_typed_position(settings, last_fen, fen).await,
+                Err(e) => Err(anyhow::anyhow!("Invalid FEN: {}", e)),
+            };
+            if let Err(e) = result {
+                println!("⚠ Position not set: {:#}", e);
+            }
         }
+        ControlCommand::Back | ControlCommand::Forward => {
+            let back = command == ControlCommand::Back;
+            let Some(tree) = what_if.as_mut() else {
+                println!("⚠ No explored moves yet (type: move <move>)");
+                return None;
+            };
+            let moved = if back { tree.back() } else { tree.forward() };
+            if !moved {
+                println!("⚠ {}", if back { "Already at the start" } else { "No further moves" });
+                return None;
+            }
+            let fen = tree.fen();
+            if let Err(e) = show_typed_position(settings, last_fen, fen).await {
+                println!("⚠ {:#}", e);
+            }
+        }
+        ControlCommand::ShowLine => match what_if {
+            Some(tree) if tree.move_count() > 0 => println!("  {}\n", tree.movetext()),
+            _ => println!("⚠ No explored moves yet (type: move <move>)"),
+        },
+        ControlCommand::Heatmap => match last_fen.as_deref().map(heatmap::Control::of) {
+            Some(Ok(control)) => println!("{}\n", control.render(settings.player_side, heatmap::use_color())),
+            Some(Err(e)) => println!("⚠ {:#}", e),
+            None => println!("⚠ No recognized board yet"),
+        },
+        _ => {
+            if let Some(status) = settings.apply(command) {
+                println!("⚙ {}", status);
+            }
+        }
+    }
+    None
+}
+
+/// Fixes one square of the last recognized board, re-analyzes the corrected position,
+/// and keeps the square image as a training sample when available (native OCR matches
+/// against it from the next frame on)
+async fn correct_square(
+    settings: &RuntimeSettings,
+    last_fen: &mut Option<String>,
+    site: &str,
+    file: u8,
+    rank: u8,
+    piece: Option<char>,
+) -> Result<output::CycleOutput> {
+    let fen = last_fen
+        .as_deref()
+        .context("No recognized board yet (corrections apply to engine-mode results)")?;
+
+    let piece = match piece {
+        Some(p) => p,
+        None => correction::cycle_piece(correction::piece_at(fen, file, rank)?),
     };
+    let corrected = correction::set_square(fen, file, rank, piece)?;
 
-    // Startup banner
-    println!();
-    println!("╔═══════════════════════════════════════════════════════════╗");
-    println!("║         Zugzwang-RS Chess Assistant v0.1.5                ║");
-    println!("╚═══════════════════════════════════════════════════════════╝");
-    println!();
-    println!("  Playing:   {}", player_side);
-    println!("  Analysis:  {}", analysis_mode);
-    if analysis_mode == AnalysisMode::Engine {
-        println!("  OCR Mode:  {}", ocr_mode);
+    let square = correction::square_name(file, rank);
+    if let Some(screenshot) = capture::latest() {
+        hard_cases::record_or_warn(&screenshot, fen, &corrected, "manual-correction");
     }
-    let trigger_display = if manual_mode {
-        "manual (press Enter)".to_string()
-    } else {
-        format!("auto ({}ms)", interval)
-    };
-    println!("  Trigger:   {}", trigger_display);
-    if ocr_mode == OcrMode::Native {
-        println!("  Site:      {}", site);
+    let start = std::time::Instant::now();
+    let (best_move, eval) = analyze_off_runtime(&corrected, settings.depth)
+        .await
+        .context("Failed to analyze corrected position")?;
+
+    let mut value = engine_json(&corrected, &best_move, &eval, &[]);
+    value["corrected"] = serde_json::json!({ "square": square, "piece": piece.to_string() });
+    let mut lines = engine_lines(&corrected, &None, &best_move, &eval, &[]);
+    lines.insert(0, format!("✎ {} → {}", square, if piece == '1' { '.' } else { piece }));
+    if settings.ocr_mode == OcrMode::Native {
+        match correction::save_training_sample(site, file, rank, piece, settings.player_side) {
+            Ok(path) => {
+                lines.push(format!("  Saved training sample: {}", path));
+                // Match the next board in full, with the new sample
+                ocr_native::forget_previous();
+            }
+            Err(e) => eprintln!("  (training sample not saved: {:#})", e),
+        }
     }
-    if verbose {
-        println!("  Verbose:   enabled");
+
+    let headline = format!("Best: {} ({})", notation::display(&corrected, &best_move), eval);
+    *last_fen = Some(corrected);
+    Ok(output::CycleOutput { value, lines, headline, timings: vec![("engine", start.elapsed())] })
+}
+
+/// Plays a typed move in the what-if tree and returns the new FEN. The tree continues
+/// while the last board is one of its positions; otherwise it restarts from that board.
+fn explore_move(what_if: &mut Option<game::GameTree>, fen: &str, text: &str) -> Result<String> {
+    // Compare placement and side to move only: recognized FENs carry no castling rights
+    let key = |fen: &str| fen.split(' ').take(2).collect::<Vec<_>>().join(" ");
+    let tree = match what_if.take() {
+        Some(tree) if key(&tree.fen()) == key(fen) => tree,
+        _ => game::GameTree::from_fen(fen)?,
+    };
+    let tree = what_if.insert(tree);
+    tree.play_text(text)?;
+    Ok(tree.fen())
+}
+
+/// Analyzes a position the user typed (`move` / `fen` commands) and makes it the last board
+async fn show_typed_position(settings: &RuntimeSettings, last_fen: &mut Option<String>, fen: String) -> Result<()> {
+    println!("FEN:  {}", fen);
+    if prompt::accessible() {
+        println!("Board: {}", describe::describe_position(&fen)?);
     }
+    let (best_move, eval) = analyze_off_runtime(&fen, settings.depth)
+        .await
+        .context("Failed to analyze position")?;
+    println!("Best: {} ({})", notation::display(&fen, &best_move), eval);
     println!();
-    if manual_mode {
-        println!("  Press Enter to capture & analyze, Ctrl+C to stop.");
-    } else {
-        println!("  Press Ctrl+C to stop.");
+
+    *last_fen = Some(fen);
+    Ok(())
+}
+
+/// PGN headers for an export: players' public profiles and the time control.
+/// Names and time control default to those detected during the session.
+async fn export_headers(
+    matches: &clap::ArgMatches,
+    site: &str,
+    player_side: PlayerSide,
+    meta: &session::SessionMeta,
+) -> Result<pgn::Headers> {
+    let players = &meta.players;
+    let time_control = matches.get_one::<String>("time-control").or(meta.time_control.as_ref());
+    let class = time_control
+        .and_then(|tc| profiles::TimeClass::from_time_control(tc))
+        .unwrap_or_default();
+
+    let site_name = profiles::site_name(site);
+    let event = match time_control {
+        Some(_) => format!("{} {} game", site_name, class.name()),
+        None => format!("{} game", site_name),
+    };
+    let mut headers = pgn::Headers::new(&event);
+    headers.set("Site", site_name);
+    if let Some(tc) = time_control {
+        headers.set("TimeControl", tc);
     }
-    println!();
-    println!("─────────────────────────────────────────────────────────────");
-    println!();
 
-    // Main pipeline loop
-    let mut cycle_count = 0u64;
-    let stdin = io::stdin();
+    let (user_color, opponent_color) = match player_side {
+        PlayerSide::White => (shakmaty::Color::White, shakmaty::Color::Black),
+        PlayerSide::Black => (shakmaty::Color::Black, shakmaty::Color::White),
+    };
+    for (arg, color, seen) in [
+        ("username", user_color, &players.user),
+        ("opponent", opponent_color, &players.opponent),
+    ] {
+        let name = match (matches.get_one::<String>(arg), seen) {
+            (Some(name), _) => name.clone(),
+            (None, Some(tag)) => tag.name.clone(),
+            (None, None) => continue,
+        };
+        // A failed lookup still leaves the name (and any on-screen rating) in the headers
+        let profile = profiles::fetch(site, &name, class).await.unwrap_or_else(|e| {
+            eprintln!("⚠ No profile for {}: {:#}", name, e);
+            profiles::PlayerProfile {
+                rating: seen.as_ref().filter(|tag| tag.name == name).and_then(|tag| tag.rating),
+                ..profiles::PlayerProfile::named(&name)
+            }
+        });
+        headers.set_player(color, &profile);
+    }
+    Ok(headers)
+}
 
-    loop {
-        // In manual mode, wait for user to press Enter before capturing
-        if manual_mode {
-            print!("▶ Press Enter to capture & analyze... ");
-            io::Write::flush(&mut io::stdout())?;
-            let mut line = String::new();
-            stdin.lock().read_line(&mut line)?;
-        }
+/// Converts a recorded session to PGN and uploads it to a Lichess study (or only writes the PGN)
+async fn export_study(matches: &clap::ArgMatches, player_side: PlayerSide) -> Result<()> {
+    let path = session::resolve(matches.get_one::<String>("session").unwrap())?;
+    let entries = session::load(&path)?;
+    anyhow::ensure!(!entries.is_empty(), "Session {} has no analyzed positions", path.display());
 
-        cycle_count += 1;
-        let cycle_start = std::time::Instant::now();
+    let site = matches.get_one::<String>("site").unwrap();
+    let meta = session::load_meta(&path).unwrap_or_default();
+    let headers = export_headers(matches, site, player_side, &meta).await?;
+    let pgn = pgn::session_to_pgn(&entries, &headers);
+    if let Some(out) = matches.get_one::<String>("pgn-out") {
+        std::fs::write(out, &pgn).with_context(|| format!("Failed to write {}", out))?;
+        println!("PGN written to {}", out);
+    }
 
-        if verbose {
-            println!("┌─ Cycle {} ─────────────────────────────────────────────────", cycle_count);
+    let Some(study) = matches.get_one::<String>("study") else {
+        if !matches.contains_id("pgn-out") {
+            print!("{}", pgn);
         }
+        return Ok(());
+    };
+    let token = match matches.get_one::<String>("token") {
+        Some(token) => token.clone(),
+        None => std::env::var(lichess::TOKEN_VAR)
+            .with_context(|| format!("Lichess token required: pass --token or set {}", lichess::TOKEN_VAR))?,
+    };
+    let default_name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = matches.get_one::<String>("name").unwrap_or(&default_name);
+    let orientation = player_side.to_string().to_lowercase();
 
-        // Step 1: Capture full screenshot
-        let step_start = std::time::Instant::now();
-        capture::capture_screenshot().context("Failed to capture screenshot")?;
-        if verbose {
-            println!("│ [1] Capture:  {:>6.1}ms", step_start.elapsed().as_secs_f64() * 1000.0);
-        }
+    let url = lichess::import_to_study(study, &token, name, &orientation, &pgn).await?;
+    println!("Exported {} positions to {}", entries.len(), url);
+    Ok(())
+}
 
-        // Branch based on analysis mode
-        match analysis_mode {
-            AnalysisMode::Direct => {
-                // Direct LLM analysis: LLM sees board and decides move
-                let step_start = std::time::Instant::now();
-                let recommendation = ocr_llm::analyze_board("screenshots/current_board.jpg", player_side)
-                    .await
-                    .context("Failed to analyze board with LLM")?;
-                if verbose {
-                    println!("│ [2] LLM:      {:>6.1}ms", step_start.elapsed().as_secs_f64() * 1000.0);
-                    println!("│ [3] Total:    {:>6.1}ms", cycle_start.elapsed().as_secs_f64() * 1000.0);
-                    println!("├─────────────────────────────────────────────────────────────");
-                    println!("│ Move: {}", recommendation.best_move);
-                    println!("│ Eval: {}", recommendation.evaluation);
-                    println!("│ Why:  {}", recommendation.reasoning);
-                    println!("└─────────────────────────────────────────────────────────────");
-                } else {
-                    println!("Move: {}", recommendation.best_move);
-                    println!("Eval: {}", recommendation.evaluation);
-                    println!("Why:  {}", recommendation.reasoning);
-                }
-            }
-            AnalysisMode::Engine => {
-                // Traditional pipeline: OCR → FEN → Engine
-                // Step 2: OCR to FEN (async)
-                let step_start = std::time::Instant::now();
-                let fen = ocr::board_to_fen("screenshots/current_board.jpg", site, ocr_mode, player_side)
-                    .await
-                    .context("Failed to recognize board from screenshot")?;
-                if verbose {
-                    println!("│ [2] OCR:      {:>6.1}ms", step_start.elapsed().as_secs_f64() * 1000.0);
-                }
+/// Mines sessions for tactics and writes them as a puzzle PGN or Lichess study chapters
+async fn export_tactics(matches: &clap::ArgMatches, player_side: PlayerSide) -> Result<()> {
+    let paths = tactics::sessions(matches.get_one::<String>("session").unwrap())?;
+    anyhow::ensure!(!paths.is_empty(), "No sessions recorded yet");
+    let depth = matches.get_one::<u16>("depth").copied().unwrap_or(engine::backend_depth(engine::DEFAULT_DEPTH));
+    let min_gain = matches.get_one::<i32>("min-gain").copied().unwrap_or(tactics::DEFAULT_MIN_GAIN);
 
-                // Step 3: Engine analysis
-                let step_start = std::time::Instant::now();
-                let (best_move, eval) =
-                    engine::analyze_position(&fen).context("Failed to analyze position")?;
-                if verbose {
-                    println!("│ [3] Engine:   {:>6.1}ms", step_start.elapsed().as_secs_f64() * 1000.0);
-                    println!("│ [4] Total:    {:>6.1}ms", cycle_start.elapsed().as_secs_f64() * 1000.0);
-                    println!("├─────────────────────────────────────────────────────────────");
-                    println!("│ FEN:  {}", fen);
-                    println!("│ Best: {} ({})", best_move, eval);
-                    println!("└─────────────────────────────────────────────────────────────");
-                } else {
-                    println!("FEN:  {}", fen);
-                    println!("Best: {} ({})", best_move, eval);
-                }
-            }
-        }
-        println!();
+    println!("Searching {} session(s) at depth {}...", paths.len(), depth);
+    let puzzles = tactics::extract(&paths, depth, min_gain)?;
+    anyhow::ensure!(!puzzles.is_empty(), "No tactics worth {} cp or more found", min_gain);
+    println!("Found {} puzzle(s)", puzzles.len());
 
-        // Wait before next cycle (only in auto mode)
-        if !manual_mode {
-            tokio::time::sleep(Duration::from_millis(interval)).await;
-        }
+    let pgn = tactics::to_pgn(&puzzles)?;
+    if let Some(out) = matches.get_one::<String>("pgn-out") {
+        std::fs::write(out, &pgn).with_context(|| format!("Failed to write {}", out))?;
+        println!("PGN written to {}", out);
     }
+
+    let Some(study) = matches.get_one::<String>("study") else {
+        if !matches.contains_id("pgn-out") {
+            print!("{}", pgn);
+        }
+        return Ok(());
+    };
+    let token = match matches.get_one::<String>("token") {
+        Some(token) => token.clone(),
+        None => std::env::var(lichess::TOKEN_VAR)
+            .with_context(|| format!("Lichess token required: pass --token or set {}", lichess::TOKEN_VAR))?,
+    };
+    let name = matches.get_one::<String>("name").map(String::as_str).unwrap_or("Zugzwang puzzles");
+    let orientation = player_side.to_string().to_lowercase();
+
+    let url = lichess::import_to_study(study, &token, name, &orientation, &pgn).await?;
+    println!("Exported {} puzzles to {}", puzzles.len(), url);
+    Ok(())
 }
 
-/// Prompts the user to enter their OpenAI API key
-fn prompt_for_api_key() -> Result<()> {
+/// Prompts the user to enter their OpenAI API key.
+/// The key is checked against the API right away so an invalid or expired key is
+/// reported here (and re-prompted) instead of failing on the first OCR call mid-game.
+async fn prompt_for_api_key() -> Result<()> {
+    if let Some(var) = llm_provider::active().key_var().filter(|&var| var != "OPENAI_API_KEY") {
+        anyhow::bail!("{} environment variable not set", var);
+    }
     println!();
     println!("  OPENAI_API_KEY not set. Enter your API key to continue:");
     println!("  (Get one at https://platform.openai.com/api-keys)");
     println!();
 
-    let api_key: String = Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("API Key")
-        .validate_with(|input: &String| {
+    let api_key = loop {
+        let input = prompt::input("API Key", |input| {
             if input.trim().is_empty() {
                 Err("API key cannot be empty")
             } else if !input.starts_with("sk-") {
@@ -330,9 +2717,29 @@ fn prompt_for_api_key() -> Result<()> {
                 Ok(())
             }
         })
-        .interact_text()
         .context("Failed to read API key")?;
 
+        print!("  Checking key... ");
+        io::Write::flush(&mut io::stdout())?;
+        match ocr_llm::check_api_key(input.trim()).await {
+            ocr_llm::KeyStatus::Valid => {
+                println!("valid");
+                break input;
+            }
+            ocr_llm::KeyStatus::Invalid(reason) => {
+                println!("rejected");
+                println!("  ✗ {}. Please enter a different key.", reason);
+                println!();
+            }
+            ocr_llm::KeyStatus::Unverified(reason) => {
+                // Don't block offline starts: the real calls will retry/report anyway
+                println!("could not verify");
+                println!("  ⚠ {} - continuing with this key", reason);
+                break input;
+            }
+        }
+    };
+
     // Set the environment variable for this session
     // SAFETY: We're single-threaded at this point (before the main loop starts)
     // and no other threads are reading environment variables concurrently.
@@ -341,13 +2748,42 @@ fn prompt_for_api_key() -> Result<()> {
     }
     println!();
     println!("  ✓ API key set for this session");
+    if let Some(path) = config::path() {
+        let options = [format!("Yes (encrypted in {})", path.display()), "No".to_string()];
+        let options: Vec<&str> = options.iter().map(String::as_str).collect();
+        if prompt::select("Remember this key?", &options, 1)? == 0 {
+            let mut saved = config::load()?;
+            saved.secrets.insert("OPENAI_API_KEY".to_string(), secrets::encrypt(api_key.trim())?);
+            config::save(&saved)?;
+            println!("  ✓ Key saved");
+        }
+    }
     println!();
 
     Ok(())
 }
 
+/// Asks whether to keep the startup answers as defaults and saves them if so
+fn offer_to_save(answers: &config::Config) -> Result<()> {
+    let Some(path) = config::path() else {
+        return Ok(());
+    };
+    let options = [format!("Yes (saved to {})", path.display()), "No".to_string()];
+    let options: Vec<&str> = options.iter().map(String::as_str).collect();
+    if prompt::select("Use these answers next time?", &options, 0)? == 0 {
+        // Keep secrets saved since the answers were loaded (e.g. a key entered just now)
+        let mut answers = answers.clone();
+        if let Ok(saved) = config::load() {
+            answers.secrets = saved.secrets;
+        }
+        let path = config::save(&answers)?;
+        println!("  Saved defaults to {} (command-line flags still override them)", path.display());
+    }
+    Ok(())
+}
+
 /// Interactive CLI selector for OCR mode
-fn select_ocr_mode_interactive() -> Result<OcrMode> {
+async fn select_ocr_mode_interactive() -> Result<OcrMode> {
     let llm_available = ocr::llm_available();
 
     let options = if llm_available {
@@ -368,18 +2804,13 @@ fn select_ocr_mode_interactive() -> Result<OcrMode> {
     println!("╚═══════════════════════════════════════════════════════════╝");
     println!();
 
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Select OCR mode")
-        .items(&options)
-        .default(0) // Native is default
-        .interact()
-        .context("Failed to get user selection")?;
+    let selection = prompt::select("Select OCR mode", &options, 0)?; // Native is default
 
     let mode = match selection {
         0 => OcrMode::Native,
         1 => {
             if !llm_available {
-                prompt_for_api_key()?;
+                prompt_for_api_key().await?;
             }
             OcrMode::Llm
         }
@@ -397,33 +2828,26 @@ fn select_trigger_mode_interactive() -> Result<bool> {
         "Manual (on-demand) - press Enter to capture",
     ];
 
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Select capture trigger")
-        .items(&options)
-        .default(0) // Auto is default
-        .interact()
-        .context("Failed to get user selection")?;
+    let selection = prompt::select("Select capture trigger", &options, 0)?; // Auto is default
 
     Ok(selection == 1) // 1 = Manual
 }
 
 /// Interactive CLI selector for player side
-fn select_player_side_interactive() -> Result<PlayerSide> {
+/// Returns None for auto-detection
+fn select_player_side_interactive() -> Result<Option<PlayerSide>> {
     let options = vec![
         "White (your pieces at bottom of screen)",
         "Black (your pieces at bottom of screen)",
+        "Auto-detect (from the board's orientation)",
     ];
 
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Which side are you playing?")
-        .items(&options)
-        .default(0) // White is default
-        .interact()
-        .context("Failed to get user selection")?;
+    let selection = prompt::select("Which side are you playing?", &options, 0)?; // White is default
 
     Ok(match selection {
-        1 => PlayerSide::Black,
-        _ => PlayerSide::White,
+        1 => Some(PlayerSide::Black),
+        2 => None,
+        _ => Some(PlayerSide::White),
     })
 }
 
@@ -433,17 +2857,14 @@ fn select_analysis_mode_interactive() -> Result<AnalysisMode> {
     let options = vec![
         "Engine (Tanton ~2900 ELO) - strongest play, no explanation",
         "Direct (GPT-4o) - explains reasoning, slightly weaker",
+        "Hybrid - GPT-4o move cross-checked by the engine",
     ];
 
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("How should moves be analyzed?")
-        .items(&options)
-        .default(0) // Engine is default
-        .interact()
-        .context("Failed to get user selection")?;
+    let selection = prompt::select("How should moves be analyzed?", &options, 0)?; // Engine is default
 
     Ok(match selection {
         1 => AnalysisMode::Direct,
+        2 => AnalysisMode::Hybrid,
         _ => AnalysisMode::Engine,
     })
 }
@@ -492,9 +2913,82 @@ mod tests {
         assert_eq!(PlayerSide::default(), PlayerSide::White);
     }
 
+    #[test]
+    fn test_analysis_alias_resolves_to_direct() {
+        let cli = Command::new("test").arg(Arg::new("analysis").long("analysis").value_parser(analysis_parser()));
+        for (value, mode) in [("llm-direct", AnalysisMode::Direct), ("direct", AnalysisMode::Direct), ("hybrid", AnalysisMode::Hybrid)] {
+            let matches = cli.clone().try_get_matches_from(["test", "--analysis", value]).unwrap();
+            assert_eq!(matches.get_one::<AnalysisMode>("analysis"), Some(&mode));
+        }
+        assert!(cli.try_get_matches_from(["test", "--analysis", "llm"]).is_err());
+    }
+
     #[test]
     fn test_player_side_display() {
         assert_eq!(format!("{}", PlayerSide::White), "White");
         assert_eq!(format!("{}", PlayerSide::Black), "Black");
     }
+
+    // ===== Hybrid Cross-Check Tests =====
+
+    #[test]
+    fn test_opponent_to_move() {
+        assert!(opponent_to_move("8/8/8/8/8/8/8/K6k b - - 0 1", PlayerSide::White));
+        assert!(!opponent_to_move("8/8/8/8/8/8/8/K6k b - - 0 1", PlayerSide::Black));
+        assert!(!opponent_to_move("8/8/8/8/8/8/8/K6k", PlayerSide::White));
+    }
+
+    #[test]
+    fn test_cross_check_agreement() {
+        let engine = ("E2 to E4".to_string(), "+0.30".to_string());
+        let agree = CrossCheck { engine: engine.clone(), llm: Some(engine.clone()) };
+        assert!(agree.agrees());
+
+        let disagree = CrossCheck { engine: engine.clone(), llm: Some(("D2 to D4".to_string(), "+0.25".to_string())) };
+        assert!(!disagree.agrees());
+
+        let illegal = CrossCheck { engine, llm: None };
+        assert!(!illegal.agrees());
+    }
+
+    // ===== JSON Output Tests =====
+
+    #[test]
+    fn test_recommendation_json_includes_reasoning() {
+        let recommendation = MoveRecommendation {
+            best_move: "Knight to F3".to_string(),
+            reasoning: "Develops toward the center.".to_string(),
+            evaluation: "equal".to_string(),
+        };
+        let value = recommendation_json(&recommendation);
+        assert_eq!(value["mode"], "direct");
+        assert_eq!(value["recommendation"]["reasoning"], "Develops toward the center.");
+        assert_eq!(value["recommendation"]["best_move"], "Knight to F3");
+    }
+
+    #[test]
+    fn test_engine_json_candidates() {
+        let candidates = vec![("E2 to E4".to_string(), "+0.30".to_string())];
+        let value = engine_json("8/8/8/8/8/8/8/K6k w - - 0 1", "E2 to E4", "+0.30", &candidates);
+        assert_eq!(value["mode"], "engine");
+        assert_eq!(value["candidates"][0]["move"], "E2 to E4");
+        assert!(value.get("description").is_none());
+
+        let value = with_description(value, &Some("White: king a1.".to_string()));
+        assert_eq!(value["description"], "White: king a1.");
+    }
+
+    #[test]
+    fn test_progress_json() {
+        let progress = engine::Progress {
+            fen: "8/8/8/8/8/8/8/K6k w - - 0 1".to_string(),
+            depth: 12,
+            eval: "+0.30".to_string(),
+            line: vec!["E2 to E4".to_string(), "E7 to E5".to_string()],
+        };
+        let value = progress_json(&progress);
+        assert_eq!(value["mode"], "progress");
+        assert_eq!(value["depth"], 12);
+        assert_eq!(value["line"][1], "E7 to E5");
+    }
 }
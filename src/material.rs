@@ -0,0 +1,154 @@
+//! Material and imbalance summary
+//!
+//! A centipawn number doesn't say where an advantage comes from. Each analyzed position
+//! also gets a one-line summary of the material, "White up the exchange, Black has the
+//! bishop pair, opposite-colored bishops": who is up what (pawns, a piece, the exchange,
+//! or the pieces each side has that the other doesn't), the bishop pair, and
+//! opposite-colored bishops. `--no-material` leaves it out.
+
+use anyhow::Result;
+use shakmaty::fen::Fen;
+use shakmaty::{Bitboard, Board, ByRole, Color, Role};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Piece letters and values, in the order imbalances list them
+const PIECES: [(Role, &str, i32); 5] =
+    [(Role::Queen, "Q", 9), (Role::Rook, "R", 5), (Role::Bishop, "B", 3), (Role::Knight, "N", 3), (Role::Pawn, "P", 1)];
+
+/// The summary line for a FEN's position
+pub fn summary(fen: &str) -> Result<String> {
+    let board = Fen::from_ascii(fen.trim().as_bytes())
+        .map_err(|e| anyhow::anyhow!("Invalid FEN ({}): {}", e, fen))?
+        .into_setup()
+        .board;
+    let mut parts = vec![balance(&board)];
+    for color in Color::ALL {
+        if bishop_pair(&board, color) && !bishop_pair(&board, color.other()) {
+            parts.push(format!("{} has the bishop pair", side_name(color)));
+        }
+    }
+    if opposite_bishops(&board) {
+        parts.push("opposite-colored bishops".to_string());
+    }
+    Ok(parts.join(", "))
+}
+
+/// Who is up what
+fn balance(board: &Board) -> String {
+    let (white, black) = (board.material_side(Color::White), board.material_side(Color::Black));
+    let diff = |role: Role| i32::from(*white.get(role)) - i32::from(*black.get(role));
+    let (queens, rooks, minors, pawns) =
+        (diff(Role::Queen), diff(Role::Rook), diff(Role::Bishop) + diff(Role::Knight), diff(Role::Pawn));
+    let bishops_for_knights = diff(Role::Bishop) != 0 && minors == 0;
+
+    // The piece difference in words, for the side it favors
+    let pieces = match (queens, rooks, minors) {
+        (0, 0, 0) if !bishops_for_knights => None,
+        (0, 0, 0) => return imbalance(&white, &black),
+        (0, 1, -1) | (0, -1, 1) => Some((rooks > 0, "the exchange")),
+        (0, 0, 1) | (0, 0, -1) => Some((minors > 0, "a piece")),
+        (0, 1, 0) | (0, -1, 0) => Some((rooks > 0, "a rook")),
+        (1, 0, 0) | (-1, 0, 0) => Some((queens > 0, "a queen")),
+        _ => return imbalance(&white, &black),
+    };
+    let side = |white_ahead: bool| if white_ahead { "White" } else { "Black" };
+    match pieces {
+        None if pawns == 0 => "even".to_string(),
+        None => format!("{} up {}", side(pawns > 0), pawn_count(pawns.abs())),
+        Some((white_ahead, what)) if pawns == 0 => format!("{} up {}", side(white_ahead), what),
+        Some((white_ahead, what)) if (pawns > 0) == white_ahead => {
+            format!("{} up {} and {}", side(white_ahead), what, pawn_count(pawns.abs()))
+        }
+        Some((white_ahead, what)) => format!("{} up {} for {}", side(white_ahead), what, pawn_count(pawns.abs())),
+    }
+}
+
+/// Pieces each side has over the other, "White Q vs Black R+B (White +1)"
+fn imbalance(white: &ByRole<u8>, black: &ByRole<u8>) -> String {
+    let extra = |mine: &ByRole<u8>, theirs: &ByRole<u8>| -> String {
+        let letters: Vec<String> = PIECES
+            .iter()
+            .flat_map(|&(role, letter, _)| {
+                let count = mine.get(role).saturating_sub(*theirs.get(role));
+                std::iter::repeat_n(letter.to_string(), count as usize)
+            })
+            .collect();
+        if letters.is_empty() { "nothing".to_string() } else { letters.join("+") }
+    };
+    let points = |side: &ByRole<u8>| PIECES.iter().map(|&(role, _, value)| i32::from(*side.get(role)) * value).sum::<i32>();
+    let total = points(white) - points(black);
+    let verdict = match total {
+        0 => "even".to_string(),
+        _ => format!("{} +{}", if total > 0 { "White" } else { "Black" }, total.abs()),
+    };
+    format!("White {} vs Black {} ({})", extra(white, black), extra(black, white), verdict)
+}
+
+fn pawn_count(count: i32) -> String {
+    match count {
+        1 => "a pawn".to_string(),
+        2 => "two pawns".to_string(),
+        3 => "three pawns".to_string(),
+        n => format!("{} pawns", n),
+    }
+}
+
+fn side_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    }
+}
+
+/// Bishops on both square colors
+fn bishop_pair(board: &Board, color: Color) -> bool {
+    let bishops = board.bishops() & board.by_color(color);
+    (bishops & Bitboard::LIGHT_SQUARES).any() && (bishops & Bitboard::DARK_SQUARES).any()
+}
+
+/// One bishop each, on different square colors
+fn opposite_bishops(board: &Board) -> bool {
+    let (white, black) = (board.bishops() & board.white(), board.bishops() & board.black());
+    white.count() == 1
+        && black.count() == 1
+        && (white & Bitboard::LIGHT_SQUARES).any() != (black & Bitboard::LIGHT_SQUARES).any()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balance() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(summary(start).unwrap(), "even");
+        // White rook for Black's knight, and a pawn less
+        assert_eq!(summary("n3k3/ppp5/8/8/8/8/PP6/R3K3 w - - 0 1").unwrap(), "White up the exchange for a pawn");
+        assert_eq!(summary("n3k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1").unwrap(), "Black up a piece");
+        assert_eq!(summary("4k3/8/8/8/8/8/PPP5/4K3 w - - 0 1").unwrap(), "White up three pawns");
+        // Queen against rook and bishop
+        assert_eq!(summary("r3kb2/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap(), "White Q vs Black R+B (White +1)");
+    }
+
+    #[test]
+    fn test_bishops() {
+        // Black has both bishops, White bishop and knight
+        assert_eq!(
+            summary("2b1kb2/8/8/8/8/8/8/2B1KN2 w - - 0 1").unwrap(),
+            "White N vs Black B (even), Black has the bishop pair"
+        );
+        // c1 is dark, c8 light
+        assert_eq!(summary("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap(), "even, opposite-colored bishops");
+        assert_eq!(summary("3bk3/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap(), "even");
+    }
+}
@@ -0,0 +1,357 @@
+//! Small always-on-top text window (`--output mini`)
+//!
+//! For users who want the answer next to the board without an arrow over it or a
+//! full-screen dashboard: a borderless window a few lines tall showing the best move, the
+//! eval, and how sure the suggestion is (the side to move's winning chances, and whether
+//! the engine and the LLM agree in hybrid mode). Drag it anywhere with the mouse.
+//!
+//! Like the overlay it runs in a child process (`zugzwang mini-window`, built with
+//! `--features overlay`) fed one JSON line per result, the lines to show. Text is drawn with
+//! a system font (DejaVu Sans, Arial, Segoe UI, ... - or the file in `$ZUGZWANG_MINI_FONT`).
+
+use anyhow::{Context, Result};
+use crate::eval_units::EvalUnits;
+use crate::ocr::BoxFuture;
+use crate::output::{CycleOutput, Field, OutputSink};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::{Child, ChildStdin};
+
+/// Environment variable naming the font file to draw with
+#[cfg(feature = "overlay")]
+pub const FONT_VAR: &str = "ZUGZWANG_MINI_FONT";
+
+/// Sends each result's lines to the mini window process
+pub struct MiniSink {
+    // Kept so the window closes with the sink (kill on drop)
+    _window: Child,
+    stdin: ChildStdin,
+}
+
+impl MiniSink {
+    /// Opens the mini window (showing a placeholder until the first result)
+    pub fn start() -> Result<Self> {
+        anyhow::ensure!(
+            cfg!(feature = "overlay"),
+            "--output mini needs a build with the overlay feature (cargo build --release --features overlay)"
+        );
+        let exe = std::env::current_exe().context("Failed to find this program to open the mini window")?;
+        let mut window = tokio::process::Command::new(exe)
+            .arg("mini-window")
+            .stdin(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Failed to open the mini window")?;
+        let stdin = window.stdin.take().context("Mini window has no input")?;
+        Ok(MiniSink { _window: window, stdin })
+    }
+}
+
+impl OutputSink for MiniSink {
+    fn name(&self) -> String {
+        "mini".to_string()
+    }
+
+    fn emit<'a>(&'a mut self, output: &'a CycleOutput) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let line = format!("{}\n", serde_json::to_string(&mini_lines(output))?);
+            self.stdin.write_all(line.as_bytes()).await.context("Mini window was closed")
+        })
+    }
+}
+
+/// Move, eval, and confidence of a result
+fn mini_lines(output: &CycleOutput) -> Vec<String> {
+    let value = &output.value;
+    let best = Field::Best.value(output);
+    let eval = Field::Eval.value(output);
+    let mover = match value["opponent_to_move"].as_bool() {
+        Some(true) => "Opponent: ",
+        _ => "",
+    };
+    let mut confidence = Vec::new();
+    if let Some(win) = EvalUnits::WinPercent.format(&eval, None) {
+        confidence.push(win);
+    }
+    match value["agree"].as_bool() {
+        Some(true) => confidence.push("engine and LLM agree".to_string()),
+        Some(false) => confidence.push("engine and LLM differ".to_string()),
+        None if value["mode"] == "direct" => confidence.push("LLM only".to_string()),
+        None => {}
+    }
+    if value["stopped_early"].as_bool() == Some(true) {
+        confidence.push("stopped early".to_string());
+    }
+    vec![format!("{}{}", mover, best), eval, confidence.join(", ")]
+}
+
+/// Runs the mini window until the pipeline closes its input (`mini-window`)
+#[cfg(feature = "overlay")]
+pub fn run_window() -> Result<()> {
+    use std::io::BufRead;
+    use winit::event_loop::EventLoop;
+
+    let font = window::load_font()?;
+    let event_loop = EventLoop::<Option<Vec<String>>>::with_user_event()
+        .build()
+        .context("Failed to start the mini window")?;
+    let proxy = event_loop.create_proxy();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if let Ok(lines) = serde_json::from_str::<Vec<String>>(&line)
+                && proxy.send_event(Some(lines)).is_err()
+            {
+                return;
+            }
+        }
+        // The pipeline has exited
+        let _ = proxy.send_event(None);
+    });
+    event_loop.run_app(&mut window::Mini::new(font)).context("Mini window failed")
+}
+
+/// Builds without the `overlay` feature have no window to run
+#[cfg(not(feature = "overlay"))]
+pub fn run_window() -> Result<()> {
+    anyhow::bail!("This build has no mini window (cargo build --release --features overlay)")
+}
+
+#[cfg(feature = "overlay")]
+mod window {
+    use anyhow::{Context, Result};
+    use ab_glyph::{Font, FontVec, PxScale, ScaleFont, point};
+    use std::num::NonZeroU32;
+    use std::rc::Rc;
+    use winit::application::ApplicationHandler;
+    use winit::dpi::LogicalSize;
+    use winit::event::{ElementState, MouseButton, WindowEvent};
+    use winit::event_loop::ActiveEventLoop;
+    use winit::window::{Window, WindowId, WindowLevel};
+
+    type Surface = softbuffer::Surface<Rc<Window>, Rc<Window>>;
+
+    /// Window size in logical pixels
+    const WIDTH: u32 = 260;
+    const HEIGHT: u32 = 84;
+
+    /// Text sizes (first line, the rest) and margin, in logical pixels
+    const MOVE_PX: f32 = 26.0;
+    const LINE_PX: f32 = 17.0;
+    const MARGIN_PX: f32 = 8.0;
+
+    /// Colors, RGB
+    const BACKGROUND: u32 = 0x20_2124;
+    const MOVE_COLOR: u32 = 0xFF_FFFF;
+    const LINE_COLOR: u32 = 0xB0_B3B8;
+
+    /// Fonts tried in order when `$ZUGZWANG_MINI_FONT` isn't set
+    const FONT_PATHS: &[&str] = &[
+        "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+        "/usr/share/fonts/TTF/DejaVuSans.ttf",
+        "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+        "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+        "/usr/share/fonts/noto/NotoSans-Regular.ttf",
+        "/System/Library/Fonts/Supplemental/Arial.ttf",
+        "/Library/Fonts/Arial.ttf",
+        "C:\\Windows\\Fonts\\segoeui.ttf",
+        "C:\\Windows\\Fonts\\arial.ttf",
+    ];
+
+    /// The font from `$ZUGZWANG_MINI_FONT`, or the first system font found
+    pub fn load_font() -> Result<FontVec> {
+        let path = match std::env::var(super::FONT_VAR) {
+            Ok(path) => path,
+            Err(_) => FONT_PATHS
+                .iter()
+                .find(|path| std::path::Path::new(path).is_file())
+                .map(|path| path.to_string())
+                .with_context(|| format!("No font found for the mini window; set ${} to a .ttf file", super::FONT_VAR))?,
+        };
+        let data = std::fs::read(&path).with_context(|| format!("Failed to read font {}", path))?;
+        FontVec::try_from_vec(data).map_err(|_| anyhow::anyhow!("{} is not a usable font", path))
+    }
+
+    /// Pixels (RGB, row-major) of a `width`×`height` window showing `lines`, `scale`
+    /// physical pixels per logical one
+    pub fn text_pixels(font: &FontVec, width: u32, height: u32, scale: f32, lines: &[String]) -> Vec<u32> {
+        let mut pixels = vec![BACKGROUND; (width * height) as usize];
+        let mut top = MARGIN_PX * scale;
+        for (index, line) in lines.iter().enumerate() {
+            let (size, color) = if index == 0 { (MOVE_PX, MOVE_COLOR) } else { (LINE_PX, LINE_COLOR) };
+            let scaled = font.as_scaled(PxScale::from(size * scale));
+            let baseline = top + scaled.ascent();
+            let mut caret = MARGIN_PX * scale;
+            for c in line.chars() {
+                let glyph = scaled.scaled_glyph(c);
+                let advance = scaled.h_advance(glyph.id);
+                let glyph = glyph.id.with_scale_and_position(size * scale, point(caret, baseline));
+                caret += advance;
+                let Some(outline) = font.outline_glyph(glyph) else { continue };
+                let bounds = outline.px_bounds();
+                outline.draw(|x, y, coverage| {
+                    let (px, py) = (bounds.min.x as i32 + x as i32, bounds.min.y as i32 + y as i32);
+                    if (0..width as i32).contains(&px) && (0..height as i32).contains(&py) {
+                        let pixel = &mut pixels[(py as u32 * width + px as u32) as usize];
+                        *pixel = blend(*pixel, color, coverage);
+                    }
+                });
+            }
+            top = baseline - scaled.descent() + scaled.line_gap();
+        }
+        pixels
+    }
+
+    /// `over` on top of `under` with the given opacity
+    fn blend(under: u32, over: u32, opacity: f32) -> u32 {
+        let channel = |shift: u32| {
+            let (a, b) = ((under >> shift) & 0xFF, (over >> shift) & 0xFF);
+            ((a as f32 + (b as f32 - a as f32) * opacity.clamp(0.0, 1.0)).round() as u32) << shift
+        };
+        channel(16) | channel(8) | channel(0)
+    }
+
+    /// softbuffer's errors hold window handles, which can't cross threads as anyhow requires
+    fn display_error(e: softbuffer::SoftBufferError) -> anyhow::Error {
+        anyhow::anyhow!("{}", e)
+    }
+
+    pub struct Mini {
+        font: FontVec,
+        window: Option<(Rc<Window>, Surface)>,
+        lines: Vec<String>,
+    }
+
+    impl Mini {
+        pub fn new(font: FontVec) -> Self {
+            Mini { font, window: None, lines: vec!["Waiting for a position...".to_string()] }
+        }
+
+        fn open(event_loop: &ActiveEventLoop) -> Result<(Rc<Window>, Surface)> {
+            let attributes = Window::default_attributes()
+                .with_title("Zugzwang")
+                .with_decorations(false)
+                .with_resizable(false)
+                .with_active(false)
+                .with_window_level(WindowLevel::AlwaysOnTop)
+                .with_inner_size(LogicalSize::new(WIDTH, HEIGHT));
+            let window = Rc::new(event_loop.create_window(attributes)?);
+            let context = softbuffer::Context::new(Rc::clone(&window)).map_err(display_error)?;
+            let surface = softbuffer::Surface::new(&context, Rc::clone(&window)).map_err(display_error)?;
+            Ok((window, surface))
+        }
+
+        fn redraw(&mut self) -> Result<()> {
+            let Some((window, surface)) = &mut self.window else {
+                return Ok(());
+            };
+            let size = window.inner_size();
+            let (Some(width), Some(height)) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) else {
+                return Ok(());
+            };
+            surface.resize(width, height).map_err(display_error)?;
+            let mut buffer = surface.buffer_mut().map_err(display_error)?;
+            let scale = window.scale_factor() as f32;
+            buffer.copy_from_slice(&text_pixels(&self.font, size.width, size.height, scale, &self.lines));
+            buffer.present().map_err(display_error)?;
+            Ok(())
+        }
+    }
+
+    impl ApplicationHandler<Option<Vec<String>>> for Mini {
+        fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+            if self.window.is_some() {
+                return;
+            }
+            match Mini::open(event_loop) {
+                Ok(window) => self.window = Some(window),
+                Err(e) => {
+                    eprintln!("⚠ Mini window failed: {:#}", e);
+                    event_loop.exit();
+                }
+            }
+        }
+
+        fn user_event(&mut self, event_loop: &ActiveEventLoop, lines: Option<Vec<String>>) {
+            let Some(lines) = lines else {
+                return event_loop.exit();
+            };
+            self.lines = lines;
+            if let Some((window, _)) = &self.window {
+                window.request_redraw();
+            }
+        }
+
+        fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+            match event {
+                WindowEvent::RedrawRequested => {
+                    if let Err(e) = self.redraw() {
+                        eprintln!("⚠ Mini window drawing failed: {:#}", e);
+                    }
+                }
+                // Borderless: the whole window is the handle to move it by
+                WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } => {
+                    if let Some((window, _)) = &self.window {
+                        let _ = window.drag_window();
+                    }
+                }
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    if let Some((window, _)) = &self.window {
+                        window.request_redraw();
+                    }
+                }
+                WindowEvent::CloseRequested => event_loop.exit(),
+                _ => {}
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_blend() {
+            assert_eq!(blend(0x000000, 0xFFFFFF, 0.0), 0x000000);
+            assert_eq!(blend(0x000000, 0xFFFFFF, 1.0), 0xFFFFFF);
+            assert_eq!(blend(0x204060, 0x204060, 0.5), 0x204060);
+            assert_eq!(blend(0x000000, 0xC8_6420, 0.5), 0x64_3210);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(value: serde_json::Value) -> CycleOutput {
+        CycleOutput { value, lines: Vec::new(), headline: String::new(), timings: Vec::new() }
+    }
+
+    #[test]
+    fn test_mini_lines_engine_mode() {
+        let lines = mini_lines(&output(serde_json::json!({
+            "mode": "engine",
+            "fen": "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "best_move": "E2 to E4",
+            "evaluation": "+0.00",
+        })));
+        assert_eq!(lines, vec!["e4", "+0.00", "win 50%"]);
+    }
+
+    #[test]
+    fn test_mini_lines_hybrid_and_direct() {
+        let hybrid = mini_lines(&output(serde_json::json!({
+            "mode": "hybrid",
+            "agree": false,
+            "opponent_to_move": true,
+            "engine": { "move": "E7 to E5", "evaluation": "#2" },
+        })));
+        assert_eq!(hybrid, vec!["Opponent: E7 to E5", "#2", "win 100%, engine and LLM differ"]);
+        let direct = mini_lines(&output(serde_json::json!({
+            "mode": "direct",
+            "recommendation": { "best_move": "Knight to F3", "evaluation": "slight advantage" },
+        })));
+        assert_eq!(direct, vec!["Knight to F3", "slight advantage", "LLM only"]);
+    }
+}
@@ -0,0 +1,182 @@
+//! Multi-board analysis
+//!
+//! For simuls or two blitz games played side by side, `--board-region X,Y,W,H[:black]`
+//! (given once per board) runs an independent pipeline per screen region. Each cycle the
+//! screen is captured once; every region is cropped and compared with its previous frame,
+//! and the boards that changed are recognized concurrently and analyzed by the engine.
+//!
+//! Output is labeled per board ("[Board 2] Best: ..."), and each board keeps its own game
+//! tracking: its own session log (`sessions/<start time>-board<n>.jsonl`) and last
+//! position, so one board failing to read never stops the other.
+//!
+//! Regions are in captured-image pixels (the screenshot after downsampling, or the window
+//! with `--window`). Only engine analysis is supported.
+
+use anyhow::{Context, Result};
+use crate::PlayerSide;
+use crate::board_detect::BoardRegion;
+use crate::capture;
+use crate::controls::{ControlCommand, RuntimeSettings};
+use crate::frame_hash::FrameDeduper;
+use crate::ocr::{self, BatchLimits, ImageSource};
+use crate::session::SessionLog;
+use image::GenericImageView;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// Loop options shared by every board
+pub struct Options<'a> {
+    pub site: &'a str,
+    pub manual: bool,
+    pub interval_ms: u64,
+    pub json: bool,
+}
+
+/// One board's pipeline state
+struct Board {
+    label: String,
+    region: BoardRegion,
+    session: SessionLog,
+    deduper: FrameDeduper,
+    last_fen: Option<String>,
+}
+
+impl Board {
+    fn side(&self, settings: &RuntimeSettings) -> PlayerSide {
+        self.region.side.unwrap_or(settings.player_side)
+    }
+}
+
+/// Runs one pipeline per region until stdin closes (manual mode) or the process is stopped
+pub async fn run(
+    regions: Vec<BoardRegion>,
+    mut settings: RuntimeSettings,
+    options: Options<'_>,
+    mut commands: UnboundedReceiver<ControlCommand>,
+) -> Result<()> {
+    let mut boards: Vec<Board> = regions
+        .into_iter()
+        .enumerate()
+        .map(|(idx, region)| Board {
+            label: format!("Board {}", idx + 1),
+            region,
+            session: SessionLog::start_for(&format!("board{}", idx + 1)),
+            deduper: FrameDeduper::default(),
+            last_fen: None,
+        })
+        .collect();
+    let mut paused = false;
+
+    loop {
+        if options.manual {
+            if !options.json {
+                print!("▶ Press Enter to capture & analyze all boards... ");
+                std::io::Write::flush(&mut std::io::stdout())?;
+            }
+            loop {
+                match commands.recv().await {
+                    Some(ControlCommand::Capture) => break,
+                    Some(command) => apply(&mut settings, command),
+                    None => return Ok(()), // stdin closed
+                }
+            }
+        } else {
+            while let Ok(command) = commands.try_recv() {
+                apply(&mut settings, command);
+                boards.iter_mut().for_each(|b| b.deduper.reset());
+            }
+            if !crate::focus_gate(&mut paused, options.json) {
+                tokio::time::sleep(Duration::from_millis(options.interval_ms)).await;
+                continue;
+            }
+        }
+
+        let frame = match capture::capture_screenshot() {
+            Ok(frame) => frame,
+            // A replayed recording has no frames left
+            Err(e) if e.is::<capture::ReplayFinished>() => return Ok(()),
+            Err(e) => return Err(e.context("Failed to capture screenshot")),
+        };
+        let (img_w, img_h) = frame.image.dimensions();
+        crate::report_cpu_limit(options.site, options.json);
+
+        // Crop every board; in auto mode only boards whose picture changed are read
+        let mut changed = Vec::new();
+        for (idx, board) in boards.iter_mut().enumerate() {
+            let BoardRegion { x, y, width, height, .. } = board.region;
+            anyhow::ensure!(
+                x + width <= img_w && y + height <= img_h,
+                "{} region {},{},{},{} is outside the captured image ({}×{})",
+                board.label, x, y, width, height, img_w, img_h
+            );
+            let crop = Arc::new(frame.image.crop_imm(x, y, width, height));
+            if !options.manual && board.deduper.is_duplicate(&crop).unwrap_or(false) {
+                continue;
+            }
+            changed.push((idx, crop));
+        }
+
+        let requests = changed
+            .iter()
+            .map(|(idx, crop)| (ImageSource::Image(Arc::clone(crop)), boards[*idx].side(&settings)))
+            .collect();
+        let fens = ocr::board_to_fen_many(requests, options.site, settings.ocr_mode, BatchLimits::new(boards.len(), 0)).await;
+
+        for ((idx, _), fen) in changed.into_iter().zip(fens) {
+            let board = &mut boards[idx];
+            let fen = match fen {
+                Ok(fen) => fen,
+                Err(e) => {
+                    eprintln!("⚠ [{}] Board not recognized: {:#}", board.label, e);
+                    board.deduper.reset();
+                    continue;
+                }
+            };
+            if board.last_fen.as_deref() == Some(fen.as_str()) {
+                continue;
+            }
+            if let Err(e) = analyze(board, &fen, &settings, &frame, options.json) {
+                eprintln!("⚠ [{}] {:#}", board.label, e);
+            }
+            board.last_fen = Some(fen);
+        }
+        if !options.json {
+            println!();
+        }
+
+        if !options.manual {
+            tokio::time::sleep(Duration::from_millis(options.interval_ms)).await;
+        }
+    }
+}
+
+/// Analyzes one board's position, prints the labeled result, and records it
+fn analyze(board: &Board, fen: &str, settings: &RuntimeSettings, frame: &capture::Frame, json: bool) -> Result<()> {
+    let crate::engine::Analysis { best_move, eval, candidates } = crate::engine::analyze_multipv(fen, settings.depth, settings.candidate_count())
+        .context("Failed to analyze position")?;
+    if json {
+        let mut value = crate::with_capture(crate::engine_json(fen, &best_move, &eval, &candidates), frame);
+        value["board"] = serde_json::Value::from(board.label.as_str());
+        println!("{}", value);
+    } else {
+        println!("[{}] FEN:  {}", board.label, fen);
+        println!("[{}] Best: {} ({})", board.label, best_move, eval);
+        for (rank, (mv, ev)) in candidates.iter().enumerate() {
+            println!("[{}]  {}. {} ({})", board.label, rank + 1, mv, ev);
+        }
+    }
+    board.session.record_or_warn(frame, fen, &best_move, &eval, None);
+    Ok(())
+}
+
+/// Applies a settings command (board-specific commands like corrections need a single board)
+fn apply(settings: &mut RuntimeSettings, command: ControlCommand) {
+    match command {
+        ControlCommand::Help => println!("  {}", crate::controls::HELP_LINE),
+        command => match settings.apply(command) {
+            Some(status) => println!("⚙ {}", status),
+            None => println!("⚠ Not available with several boards"),
+        },
+    }
+}
@@ -0,0 +1,113 @@
+//! Move notation for display (`--notation`)
+//!
+//! The engine hands moves around in its readable form, "G1 to F3" / "E7 to E8 (=Q)", which
+//! the rest of the pipeline (JSON results, speech, move matching) reads back. For people it
+//! is ambiguous ("C2 to C3" could be any piece) and awkward for castling ("E1 to G1"), so
+//! console and dashboard lines show moves in the chosen notation:
+//!
+//! - `san`: standard algebraic notation with check marks, "Nf3", "exd5", "O-O", "e8=Q+" (default)
+//! - `uci`: "g1f3", "e7e8q"
+//! - `verbose`: the readable form as it is
+//!
+//! Text that isn't a legal move in the position (checkmate messages, LLM wording) is left as it is.
+
+use crate::pgn::parse_position;
+use shakmaty::san::SanPlus;
+use shakmaty::uci::UciMove;
+use shakmaty::{CastlingMode, Role, Square};
+use std::sync::OnceLock;
+
+/// How moves are shown
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Notation {
+    #[default]
+    San,
+    Uci,
+    Verbose,
+}
+
+impl Notation {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "san" => Some(Notation::San),
+            "uci" => Some(Notation::Uci),
+            "verbose" => Some(Notation::Verbose),
+            _ => None,
+        }
+    }
+}
+
+/// Notation from `--notation` (SAN when unset)
+static NOTATION: OnceLock<Notation> = OnceLock::new();
+
+/// Sets the display notation (first call wins)
+pub fn set_notation(notation: Notation) {
+    let _ = NOTATION.set(notation);
+}
+
+/// A readable engine move shown in the `--notation` notation for `fen`
+pub fn display(fen: &str, readable: &str) -> String {
+    format(fen, readable, NOTATION.get().copied().unwrap_or_default())
+}
+
+/// A readable engine move ("G1 to F3", "E7 to E8 (=Q)") in `notation`
+pub fn format(fen: &str, readable: &str, notation: Notation) -> String {
+    if notation == Notation::Verbose {
+        return readable.to_string();
+    }
+    let converted = parse_position(fen).and_then(|position| {
+        let m = readable_uci(readable)?.to_move(&position).ok()?;
+        Some(match notation {
+            Notation::Uci => m.to_uci(CastlingMode::Standard).to_string(),
+            _ => SanPlus::from_move(position, m).to_string(),
+        })
+    });
+    converted.unwrap_or_else(|| readable.to_string())
+}
+
+/// The UCI move in a readable move, "E7 to E8 (=Q)" → e7e8q
+fn readable_uci(readable: &str) -> Option<UciMove> {
+    let mut words = readable.split_whitespace();
+    let from: Square = words.next()?.to_lowercase().parse().ok()?;
+    if words.next()? != "to" {
+        return None;
+    }
+    let to: Square = words.next()?.to_lowercase().parse().ok()?;
+    let promotion = match words.next() {
+        Some(piece) => Some(Role::from_char(piece.strip_prefix("(=")?.chars().next()?.to_ascii_lowercase())?),
+        None => None,
+    };
+    Some(UciMove::Normal { from, to, promotion })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn test_san() {
+        assert_eq!(format(START, "G1 to F3", Notation::San), "Nf3");
+        assert_eq!(format(START, "E2 to E4", Notation::San), "e4");
+        let castle = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(format(castle, "E1 to G1", Notation::San), "O-O");
+        assert_eq!(format(castle, "E1 to C1", Notation::San), "O-O-O");
+        let capture = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+        assert_eq!(format(capture, "E4 to D5", Notation::San), "exd5");
+        let promotion = "7k/4P3/8/8/8/8/8/K7 w - - 0 1";
+        assert_eq!(format(promotion, "E7 to E8 (=Q)", Notation::San), "e8=Q+");
+        // Both rooks can reach d1: the file tells them apart
+        let rooks = "4k3/8/8/8/8/8/4K3/R6R w - - 0 1";
+        assert_eq!(format(rooks, "H1 to D1", Notation::San), "Rhd1");
+    }
+
+    #[test]
+    fn test_other_notations_and_fallback() {
+        assert_eq!(format(START, "G1 to F3", Notation::Uci), "g1f3");
+        assert_eq!(format(START, "G1 to F3", Notation::Verbose), "G1 to F3");
+        // Not a move here: shown as it is
+        assert_eq!(format(START, "E2 to E5", Notation::San), "E2 to E5");
+        assert_eq!(format(START, "Checkmate - no moves", Notation::San), "Checkmate - no moves");
+    }
+}
@@ -0,0 +1,77 @@
+//! Desktop notifications (`--output notify`, or `--notify`)
+//!
+//! With the browser full-screen the terminal is out of sight, so this sink raises a native
+//! notification with each result's headline, "Best: Nf3 (+0.35)". On Linux each notification
+//! replaces the previous one instead of stacking up; elsewhere they expire on their own.
+//!
+//! Notifications go through `notify-rust` (the desktop's notification service on Linux,
+//! Notification Center on macOS, toasts on Windows) and need a build with
+//! `--features notify`.
+
+use anyhow::Result;
+use crate::ocr::BoxFuture;
+use crate::output::{CycleOutput, OutputSink};
+
+/// How long a notification stays up
+#[cfg(feature = "notify")]
+const TIMEOUT_MS: u32 = 5000;
+
+/// Shows each result's headline as a desktop notification
+pub struct NotifySink {
+    /// Id of the notification on screen, replaced by the next one (Linux)
+    shown: Option<u32>,
+}
+
+impl NotifySink {
+    pub fn start() -> Result<Self> {
+        anyhow::ensure!(
+            cfg!(feature = "notify"),
+            "--output notify needs a build with the notify feature (cargo build --release --features notify)"
+        );
+        Ok(NotifySink { shown: None })
+    }
+}
+
+impl OutputSink for NotifySink {
+    fn name(&self) -> String {
+        "notify".to_string()
+    }
+
+    fn emit<'a>(&'a mut self, output: &'a CycleOutput) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            // Talking to the notification service blocks
+            let (headline, shown) = (output.headline.clone(), self.shown);
+            self.shown = tokio::task::spawn_blocking(move || show(&headline, shown)).await??;
+            Ok(())
+        })
+    }
+}
+
+/// Raises a notification, replacing `replaces` where the platform allows; returns its id
+#[cfg(feature = "notify")]
+fn show(headline: &str, replaces: Option<u32>) -> Result<Option<u32>> {
+    use anyhow::Context;
+    use notify_rust::{Notification, Timeout};
+
+    let mut notification = Notification::new();
+    notification.appname("ZugzwangRS").summary(headline).timeout(Timeout::Milliseconds(TIMEOUT_MS));
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        if let Some(id) = replaces {
+            notification.id(id);
+        }
+        let handle = notification.show().context("Failed to show a notification")?;
+        Ok(Some(handle.id()))
+    }
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    {
+        let _ = replaces;
+        notification.show().context("Failed to show a notification")?;
+        Ok(None)
+    }
+}
+
+#[cfg(not(feature = "notify"))]
+fn show(_headline: &str, _replaces: Option<u32>) -> Result<Option<u32>> {
+    anyhow::bail!("Notifications need a build with the notify feature")
+}
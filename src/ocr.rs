@@ -3,16 +3,107 @@
 //! This module provides a unified interface for board-to-FEN conversion:
 //! - **LLM mode**: Sends full screenshot to GPT-4o (it finds the board itself)
 //! - **Native mode**: Detects/crops board first, then uses template matching
+//! - **CNN mode**: Same board detection, then an ONNX piece classifier per square
+//! - **Hybrid mode**: Native, handing frames it isn't sure of to the LLM (see `ocr_hybrid`)
 //!
 //! The modes differ in board detection:
 //! - LLM skips CPU-intensive edge detection (GPT handles it)
 //! - Native requires board detection for accurate template matching
+//!
+//! Each mode is an `OcrBackend` looked up by name in a registry, so new backends
+//! (e.g. a model behind an HTTP service) can be added with `register_backend`
+//! instead of another arm in a match statement here. The built-in backends are public
+//! (`NativeBackend`, `LlmBackend`, `ocr_command::CommandBackend`, `ocr_cnn::CnnBackend`),
+//! so an added backend can wrap one, e.g. to fall back to templates when a service is down.
+//! Whether a mode needs an API key and how the banner names it come from its backend too.
 
 use anyhow::{Context, Result};
 use crate::PlayerSide;
+use image::DynamicImage;
+pub use crate::ocr_llm::MoveRecommendation;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::time::Instant;
+
+/// FEN string produced by an OCR backend
+pub type Fen = String;
+
+/// Boxed future returned by `OcrBackend::recognize` (keeps the trait object-safe)
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Inputs shared by every OCR backend
+#[derive(Clone, Debug)]
+pub struct OcrRequest {
+    /// Full screenshot to read
+    pub image: Arc<DynamicImage>,
+    /// File the screenshot was loaded from, when it came from disk (batch workloads)
+    pub image_path: Option<String>,
+    /// Chess site (selects native templates)
+    pub site: String,
+    /// Board orientation and side to move
+    pub player_side: PlayerSide,
+}
+
+/// A board recognizer: screenshot in, FEN out
+pub trait OcrBackend: Send + Sync {
+    /// Reads the position from the screenshot in `request`
+    fn recognize<'a>(&'a self, request: &'a OcrRequest) -> BoxFuture<'a, Result<Fen>>;
+
+    /// Whether several `recognize` calls may run at once (used by batch OCR)
+    fn supports_concurrency(&self) -> bool {
+        true
+    }
+
+    /// Whether the backend sends frames to the LLM (and so needs an API key)
+    fn uses_llm(&self) -> bool {
+        false
+    }
+
+    /// Name shown at startup; None shows the registry name as a custom backend
+    fn display_name(&self) -> Option<&str> {
+        None
+    }
+}
 
-/// Path where the cropped board image is saved for OCR processing
-const CROPPED_BOARD_PATH: &str = "screenshots/cropped_board.png";
+/// Registered backends by name; built-ins are added on first access
+static REGISTRY: OnceLock<RwLock<BTreeMap<&'static str, Arc<dyn OcrBackend>>>> = OnceLock::new();
+
+fn registry() -> &'static RwLock<BTreeMap<&'static str, Arc<dyn OcrBackend>>> {
+    REGISTRY.get_or_init(|| {
+        let mut backends: BTreeMap<&'static str, Arc<dyn OcrBackend>> = BTreeMap::new();
+        backends.insert("native", Arc::new(NativeBackend));
+        backends.insert("llm", Arc::new(LlmBackend));
+        backends.insert("command", Arc::new(crate::ocr_command::CommandBackend));
+        backends.insert("cnn", Arc::new(crate::ocr_cnn::CnnBackend));
+        backends.insert("hybrid", Arc::new(crate::ocr_hybrid::HybridBackend));
+        RwLock::new(backends)
+    })
+}
+
+/// Registers (or replaces) a backend under `name`, making it selectable with `--ocr <name>`.
+/// Call before parsing the command line.
+pub fn register_backend(name: &'static str, backend: Arc<dyn OcrBackend>) {
+    registry().write().expect("OCR registry poisoned").insert(name, backend);
+}
+
+/// Names of all registered backends, sorted
+pub fn backend_names() -> Vec<&'static str> {
+    registry().read().expect("OCR registry poisoned").keys().copied().collect()
+}
+
+/// Looks up the backend for a mode
+pub fn backend(mode: OcrMode) -> Result<Arc<dyn OcrBackend>> {
+    registry()
+        .read()
+        .expect("OCR registry poisoned")
+        .get(mode.name())
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("No OCR backend registered as '{}'", mode.name()))
+}
 
 /// OCR implementation mode
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
@@ -22,23 +113,51 @@ pub enum OcrMode {
     /// Template-based matching (default for backward compatibility)
     #[default]
     Native,
+    /// Backend added through `register_backend`
+    Custom(&'static str),
+}
+
+impl OcrMode {
+    /// Parses a registered backend name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "llm" => Some(OcrMode::Llm),
+            "native" => Some(OcrMode::Native),
+            _ => backend_names().into_iter().find(|n| *n == name).map(OcrMode::Custom),
+        }
+    }
+
+    /// Registry name of this mode
+    pub fn name(self) -> &'static str {
+        match self {
+            OcrMode::Llm => "llm",
+            OcrMode::Native => "native",
+            OcrMode::Custom(name) => name,
+        }
+    }
+
+    /// Whether the mode's backend sends frames to the LLM (and so needs an API key)
+    pub fn uses_llm(self) -> bool {
+        backend(self).is_ok_and(|backend| backend.uses_llm())
+    }
 }
 
 impl std::fmt::Display for OcrMode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            OcrMode::Llm => write!(f, "LLM (GPT-4o)"),
-            OcrMode::Native => write!(f, "Native (template matching)"),
+        let backend = backend(*self).ok();
+        match backend.as_deref().and_then(OcrBackend::display_name) {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "{} (custom backend)", self.name()),
         }
     }
 }
 
-/// Checks if the LLM OCR mode is available (API key is set)
+/// Checks if the LLM OCR mode is available (a provider in the chain has credentials)
 pub fn llm_available() -> bool {
-    crate::ocr_llm::has_api_key()
+    crate::ocr_llm::llm_ready()
 }
 
-/// Main entry point for board-to-FEN conversion.
+/// Main entry point for board-to-FEN conversion: runs the backend registered for `mode`.
 ///
 /// For Native mode: Detects and crops the chessboard, then uses template matching.
 /// For LLM mode: Sends the full screenshot directly to GPT-4o Mini (it can find the board itself).
@@ -46,59 +165,244 @@ pub fn llm_available() -> bool {
 /// The `player_side` parameter determines:
 /// - Board orientation interpretation (Black = board flipped 180°)
 /// - FEN turn indicator ('w' for White, 'b' for Black)
-pub async fn board_to_fen(image_path: &str, site: &str, mode: OcrMode, player_side: PlayerSide) -> Result<String> {
-    use std::io::Write;
-    use std::time::Instant;
+pub async fn board_to_fen(image: &Arc<DynamicImage>, site: &str, mode: OcrMode, player_side: PlayerSide) -> Result<String> {
+    let request = OcrRequest {
+        image: Arc::clone(image),
+        image_path: None,
+        site: site.to_string(),
+        player_side,
+    };
+    let fen = backend(mode)?.recognize(&request).await?;
+    let fen = crate::fen_hook::apply(fen, player_side).await;
+    crate::crash::note_fen(&fen);
+    Ok(fen)
+}
+
+/// Where a batch image comes from: read from disk when its turn comes (so large batches
+/// don't hold every image in memory), or already in memory
+pub enum ImageSource {
+    File(String),
+    Image(Arc<DynamicImage>),
+}
+
+/// Loads an image file for OCR
+pub fn load_image(path: &str) -> Result<Arc<DynamicImage>> {
+    let image = image::ImageReader::open(path)
+        .with_context(|| format!("Failed to open image: {}", path))?
+        .decode()
+        .with_context(|| format!("Failed to decode image: {}", path))?;
+    Ok(Arc::new(image))
+}
+
+/// Built-in GPT-4o backend (see `ocr_llm`); public so other backends can fall back to it
+pub struct LlmBackend;
+
+impl OcrBackend for LlmBackend {
+    fn recognize<'a>(&'a self, request: &'a OcrRequest) -> BoxFuture<'a, Result<Fen>> {
+        Box::pin(async move {
+            use std::io::Write;
 
-    match mode {
-        OcrMode::Llm => {
             // LLM mode: Skip board detection - GPT-4o can find the board in the full image
             // This saves 5-10 seconds of CPU-intensive edge detection
             eprint!("LLM OCR... ");
             let _ = std::io::stderr().flush();
-            let ocr_start = Instant::now();
-            let result = crate::ocr_llm::board_to_fen(image_path, player_side).await;
+            let ocr_start = std::time::Instant::now();
+            let result = crate::ocr_llm::board_to_fen(&request.image, request.player_side).await;
             eprintln!("{:.0}ms", ocr_start.elapsed().as_secs_f64() * 1000.0);
             result
+        })
+    }
+
+    fn display_name(&self) -> Option<&str> {
+        Some("LLM (GPT-4o)")
+    }
+
+    fn uses_llm(&self) -> bool {
+        true
+    }
+}
+
+/// Built-in template matching backend (see `ocr_native`); public so other backends can
+/// fall back to it
+pub struct NativeBackend;
+
+impl OcrBackend for NativeBackend {
+    fn recognize<'a>(&'a self, request: &'a OcrRequest) -> BoxFuture<'a, Result<Fen>> {
+        Box::pin(async move { read_native(request).await.map(|recognition| recognition.fen) })
+    }
+
+    /// CPU-bound: boards are matched one at a time
+    fn supports_concurrency(&self) -> bool {
+        false
+    }
+
+    fn display_name(&self) -> Option<&str> {
+        Some("Native (template matching)")
+    }
+}
+
+/// Native OCR with the confidence of every square: board detection, template matching,
+/// then the side to move from the last-move highlight or the running clock
+pub async fn read_native(request: &OcrRequest) -> Result<crate::ocr_native::Recognition> {
+    use std::io::Write;
+
+    // Native mode: Need board detection for template matching
+    eprint!("Board detection... ");
+    let _ = std::io::stderr().flush();
+    let detect_start = std::time::Instant::now();
+
+    let image = Arc::clone(&request.image);
+    let (board, bounds) = tokio::task::spawn_blocking(move || {
+        crate::ocr_native::screenshot_to_board_with_bounds(&image)
+            .context("Failed to detect/crop board from screenshot")
+    })
+    .await
+    .map_err(|e| anyhow::anyhow!("Board detection task failed: {}", e))??;
+
+    eprintln!("{:.0}ms", detect_start.elapsed().as_secs_f64() * 1000.0);
+
+    // Template matching on cropped board
+    eprint!("Template matching... ");
+    let _ = std::io::stderr().flush();
+    let ocr_start = std::time::Instant::now();
+    let site = request.site.clone();
+    let player_side = request.player_side;
+    let screen = Arc::clone(&request.image);
+    let result = tokio::task::spawn_blocking(move || {
+        let mut recognition = crate::ocr_native::read_cropped_board(&board, &site, player_side)?;
+        // Side to move from the last-move highlight or the running clock
+        let placement = recognition.fen.split_whitespace().next().unwrap_or_default();
+        let turn = shakmaty::Board::from_ascii_board_fen(placement.as_bytes())
+            .ok()
+            .and_then(|placement| crate::turn::detect(&board, &placement, &screen, bounds, &site, player_side));
+        if let Some(turn) = turn {
+            recognition.fen = crate::turn::with_turn(&recognition.fen, turn);
         }
-        OcrMode::Native => {
-            // Native mode: Need board detection for template matching
-            eprint!("Board detection... ");
-            let _ = std::io::stderr().flush();
-            let detect_start = Instant::now();
+        anyhow::Ok(recognition)
+    })
+    .await
+    .map_err(|e| anyhow::anyhow!("Native OCR task failed: {}", e))?;
 
-            let path = image_path.to_string();
-            let cropped_path = tokio::task::spawn_blocking(move || -> Result<String> {
-                let board_img = crate::ocr_native::screenshot_to_board(&path)
-                    .context("Failed to detect/crop board from screenshot")?;
+    eprintln!("{:.0}ms", ocr_start.elapsed().as_secs_f64() * 1000.0);
+    result
+}
 
-                // Save cropped board for OCR processing
-                board_img
-                    .save(CROPPED_BOARD_PATH)
-                    .context("Failed to save cropped board image")?;
+/// Direct analysis entry point: the LLM reads the screenshot and recommends a move,
+/// returning the move together with its evaluation and reasoning.
+pub async fn recommend_move(image: &DynamicImage, player_side: PlayerSide) -> Result<MoveRecommendation> {
+    use std::io::Write;
 
-                Ok(CROPPED_BOARD_PATH.to_string())
-            })
-            .await
-            .map_err(|e| anyhow::anyhow!("Board detection task failed: {}", e))??;
+    eprint!("LLM analysis... ");
+    let _ = std::io::stderr().flush();
+    let start = std::time::Instant::now();
+    let result = crate::ocr_llm::analyze_board(image, player_side).await;
+    eprintln!("{:.0}ms", start.elapsed().as_secs_f64() * 1000.0);
+    result
+}
 
-            eprintln!("{:.0}ms", detect_start.elapsed().as_secs_f64() * 1000.0);
+/// Concurrency and rate limits for batch OCR (replay/bench workloads)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchLimits {
+    /// Maximum number of OCR requests in flight at once
+    pub parallelism: usize,
+    /// Minimum spacing between request starts (zero = no rate limit)
+    pub min_interval: Duration,
+}
 
-            // Template matching on cropped board
-            eprint!("Template matching... ");
-            let _ = std::io::stderr().flush();
-            let ocr_start = Instant::now();
-            let site = site.to_string();
-            let result = tokio::task::spawn_blocking(move || {
-                crate::ocr_native::cropped_board_to_fen(&cropped_path, &site, player_side)
-            })
-            .await
-            .map_err(|e| anyhow::anyhow!("Native OCR task failed: {}", e))?;
+impl BatchLimits {
+    /// Builds limits from a parallelism level and a requests-per-minute cap (0 = uncapped)
+    pub fn new(parallelism: usize, requests_per_minute: u32) -> Self {
+        let min_interval = if requests_per_minute == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs(60) / requests_per_minute
+        };
+        BatchLimits {
+            parallelism: parallelism.max(1),
+            min_interval,
+        }
+    }
+}
 
-            eprintln!("{:.0}ms", ocr_start.elapsed().as_secs_f64() * 1000.0);
-            result
+/// Runs OCR over many images concurrently, honoring the batch limits.
+/// Results are returned in input order.
+///
+/// Backends that don't support concurrency (native) run one image at a time.
+pub async fn board_to_fen_batch(
+    image_paths: Vec<String>,
+    site: &str,
+    mode: OcrMode,
+    player_side: PlayerSide,
+    limits: BatchLimits,
+) -> Vec<Result<String>> {
+    let requests = image_paths.into_iter().map(|path| (ImageSource::File(path), player_side)).collect();
+    board_to_fen_many(requests, site, mode, limits).await
+}
+
+/// Like `board_to_fen_batch`, with the player side given per image (e.g. one per board)
+pub async fn board_to_fen_many(
+    requests: Vec<(ImageSource, PlayerSide)>,
+    site: &str,
+    mode: OcrMode,
+    limits: BatchLimits,
+) -> Vec<Result<String>> {
+    let parallelism = match backend(mode) {
+        Ok(b) if b.supports_concurrency() => limits.parallelism,
+        _ => 1,
+    };
+    let permits = Arc::new(Semaphore::new(parallelism));
+    let next_start = Arc::new(Mutex::new(Instant::now()));
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for (idx, (source, player_side)) in requests.into_iter().enumerate() {
+        let permits = Arc::clone(&permits);
+        let next_start = Arc::clone(&next_start);
+        let site = site.to_string();
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await.expect("semaphore is never closed");
+
+            // Rate limit: reserve the next start slot, then wait for it
+            let start_at = {
+                let mut next = next_start.lock().await;
+                let slot = (*next).max(Instant::now());
+                *next = slot + limits.min_interval;
+                slot
+            };
+            tokio::time::sleep_until(start_at).await;
+
+            let request = match source {
+                ImageSource::File(path) => load_image(&path).map(|image| OcrRequest {
+                    image,
+                    image_path: Some(path),
+                    site,
+                    player_side,
+                }),
+                ImageSource::Image(image) => Ok(OcrRequest { image, image_path: None, site, player_side }),
+            };
+            let result = match (request, backend(mode)) {
+                (Ok(request), Ok(backend)) => match backend.recognize(&request).await {
+                    Ok(fen) => Ok(crate::fen_hook::apply(fen, player_side).await),
+                    Err(e) => Err(e),
+                },
+                (Err(e), _) | (_, Err(e)) => Err(e),
+            };
+            (idx, result)
+        });
+    }
+
+    let mut results: Vec<Option<Result<String>>> = Vec::new();
+    results.resize_with(tasks.len(), || None);
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((idx, result)) => results[idx] = Some(result),
+            Err(e) => eprintln!("⚠ Batch OCR task failed: {}", e),
         }
     }
+
+    results
+        .into_iter()
+        .map(|r| r.unwrap_or_else(|| Err(anyhow::anyhow!("OCR task did not complete"))))
+        .collect()
 }
 
 #[cfg(test)]
@@ -115,4 +419,74 @@ mod tests {
     fn test_ocr_mode_default() {
         assert_eq!(OcrMode::default(), OcrMode::Native);
     }
+
+    /// Test backend that always returns the same FEN
+    struct FixedBackend;
+
+    impl OcrBackend for FixedBackend {
+        fn recognize<'a>(&'a self, request: &'a OcrRequest) -> BoxFuture<'a, Result<Fen>> {
+            Box::pin(async move { Ok(format!("8/8/8/8/8/8/8/K6k {} - - 0 1", request.player_side.fen_turn())) })
+        }
+    }
+
+    #[test]
+    fn test_builtin_backends_registered() {
+        let names = backend_names();
+        assert!(names.contains(&"native"));
+        assert!(names.contains(&"llm"));
+        assert!(names.contains(&"command"));
+        assert!(names.contains(&"hybrid"));
+        assert_eq!(OcrMode::from_name("llm"), Some(OcrMode::Llm));
+        assert!(OcrMode::from_name("hybrid").unwrap().uses_llm());
+        assert!(!OcrMode::Native.uses_llm());
+        assert_eq!(OcrMode::Native.to_string(), "Native (template matching)");
+        assert_eq!(OcrMode::from_name("cnn").unwrap().to_string(), "CNN (ONNX piece classifier)");
+        assert_eq!(OcrMode::from_name("unknown"), None);
+    }
+
+    #[test]
+    fn test_registered_backend_resolves_by_name() {
+        let stub: Arc<dyn OcrBackend> = Arc::new(FixedBackend);
+        register_backend("stub-test", Arc::clone(&stub));
+        assert!(backend_names().contains(&"stub-test"));
+        let mode = OcrMode::from_name("stub-test").unwrap();
+        assert_eq!(mode.name(), "stub-test");
+        assert!(Arc::ptr_eq(&backend(mode).unwrap(), &stub));
+        assert!(backend(OcrMode::Custom("never-registered")).is_err());
+        assert_eq!(mode.to_string(), "stub-test (custom backend)");
+        assert!(!mode.uses_llm());
+    }
+
+    #[tokio::test]
+    async fn test_registered_backend_is_dispatched() {
+        register_backend("fixed-test", Arc::new(FixedBackend));
+        let mode = OcrMode::from_name("fixed-test").unwrap();
+        assert_eq!(mode, OcrMode::Custom("fixed-test"));
+
+        let image = Arc::new(DynamicImage::new_rgb8(8, 8));
+        let fen = board_to_fen(&image, "chesscom", mode, PlayerSide::Black).await.unwrap();
+        assert_eq!(fen, "8/8/8/8/8/8/8/K6k b - - 0 1");
+    }
+
+    #[test]
+    fn test_batch_limits_rate() {
+        let limits = BatchLimits::new(4, 120);
+        assert_eq!(limits.parallelism, 4);
+        assert_eq!(limits.min_interval, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_batch_limits_uncapped_and_min_parallelism() {
+        let limits = BatchLimits::new(0, 0);
+        assert_eq!(limits.parallelism, 1);
+        assert_eq!(limits.min_interval, Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_batch_preserves_order_of_failures() {
+        let paths = vec!["missing/a.jpg".to_string(), "missing/b.jpg".to_string()];
+        let results = board_to_fen_batch(paths, "chesscom", OcrMode::Native, PlayerSide::White, BatchLimits::new(2, 0)).await;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.is_err()));
+    }
 }
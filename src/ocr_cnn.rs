@@ -0,0 +1,205 @@
+//! CNN piece classifier OCR backend (`--ocr cnn`, `cnn` feature)
+//!
+//! Template matching only recognizes the exact piece set it was cut from, so a site theme
+//! change breaks it until `--calibrate` is run again. This backend labels each of the 64
+//! squares with a small convolutional classifier instead, which can be trained across many
+//! themes and sites. It runs fully offline with tract; the model is read from
+//! `models/pieces.onnx` (or `--cnn-model`) on first use.
+//!
+//! Model contract:
+//! - input `[64, 3, 32, 32]` float32: the squares in reading order (rank 8 to 1, files a to
+//!   h, as displayed for White), RGB scaled to 0..1
+//! - output `[64, 13]` scores, one per class in `CLASSES` order (empty square first)
+//!
+//! The board is located and cropped the same way as for native OCR. Labeled frames from
+//! `--collect-dataset` (`dataset/labels.jsonl`) are a ready-made training set.
+
+use anyhow::{Context, Result};
+use crate::ocr::{BoxFuture, Fen, OcrBackend, OcrRequest};
+use image::{RgbaImage, imageops};
+use std::sync::{Arc, OnceLock};
+
+/// Model used when `--cnn-model` isn't given
+pub const DEFAULT_MODEL: &str = "models/pieces.onnx";
+
+/// Square classes in the order of the model's output ('1' = empty)
+pub const CLASSES: [char; 13] = ['1', 'P', 'N', 'B', 'R', 'Q', 'K', 'p', 'n', 'b', 'r', 'q', 'k'];
+
+/// Side of one square as fed to the model, in pixels
+pub const SQUARE_SIZE: u32 = 32;
+
+/// Model file from `--cnn-model` (set once at startup)
+static MODEL_PATH: OnceLock<String> = OnceLock::new();
+
+/// Sets the model file (first call wins)
+pub fn set_model_path(path: &str) {
+    let _ = MODEL_PATH.set(path.to_string());
+}
+
+/// The configured model file
+pub fn model_path() -> &'static str {
+    MODEL_PATH.get().map(String::as_str).unwrap_or(DEFAULT_MODEL)
+}
+
+/// OCR backend that classifies each square with the ONNX model
+pub struct CnnBackend;
+
+impl OcrBackend for CnnBackend {
+    fn recognize<'a>(&'a self, request: &'a OcrRequest) -> BoxFuture<'a, Result<Fen>> {
+        Box::pin(async move {
+            use std::io::Write;
+
+            eprint!("CNN OCR... ");
+            let _ = std::io::stderr().flush();
+            let start = std::time::Instant::now();
+            let image = Arc::clone(&request.image);
+            let player_side = request.player_side;
+            let result = tokio::task::spawn_blocking(move || {
+                let board = crate::ocr_native::screenshot_to_board(&image)
+                    .context("Failed to detect/crop board from screenshot")?;
+                let mut board = board.to_rgba8();
+                // Seen from Black's side: turn the board so rank 8 is at the top
+                if player_side.needs_board_flip() {
+                    board = imageops::rotate180(&board);
+                }
+                let scores = runtime::classify(square_tensor(&board))?;
+                crate::ocr_native::build_fen_string(board_from_scores(&scores)?, player_side)
+            })
+            .await
+            .map_err(|e| anyhow::anyhow!("CNN OCR task failed: {}", e))?;
+            eprintln!("{:.0}ms", start.elapsed().as_secs_f64() * 1000.0);
+            result
+        })
+    }
+
+    /// CPU-bound: boards are classified one at a time
+    fn supports_concurrency(&self) -> bool {
+        false
+    }
+
+    fn display_name(&self) -> Option<&str> {
+        Some("CNN (ONNX piece classifier)")
+    }
+}
+
+/// The model's input for a board image: 64 squares × RGB planes, scaled to 0..1
+pub fn square_tensor(board: &RgbaImage) -> Vec<f32> {
+    let side = SQUARE_SIZE * 8;
+    let board = imageops::resize(board, side, side, imageops::FilterType::Triangle);
+    let plane = (SQUARE_SIZE * SQUARE_SIZE) as usize;
+    let mut input = vec![0.0; 64 * 3 * plane];
+    for (x, y, pixel) in board.enumerate_pixels() {
+        let square = (y / SQUARE_SIZE * 8 + x / SQUARE_SIZE) as usize;
+        let offset = ((y % SQUARE_SIZE) * SQUARE_SIZE + x % SQUARE_SIZE) as usize;
+        for channel in 0..3 {
+            input[(square * 3 + channel) * plane + offset] = f32::from(pixel[channel]) / 255.0;
+        }
+    }
+    input
+}
+
+/// Highest-scoring class of each square, as board rows (rank 8 first)
+pub fn board_from_scores(scores: &[f32]) -> Result<[[char; 8]; 8]> {
+    anyhow::ensure!(
+        scores.len() == 64 * CLASSES.len(),
+        "CNN model returned {} scores, expected 64 squares × {} classes",
+        scores.len(),
+        CLASSES.len()
+    );
+    let mut board = [['1'; 8]; 8];
+    for (square, row) in scores.chunks(CLASSES.len()).enumerate() {
+        let best = row
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(class, _)| class)
+            .unwrap_or_default();
+        board[square / 8][square % 8] = CLASSES[best];
+    }
+    Ok(board)
+}
+
+#[cfg(feature = "cnn")]
+mod runtime {
+    use super::{SQUARE_SIZE, model_path};
+    use anyhow::{Context, Result};
+    use std::sync::OnceLock;
+    use tract_onnx::prelude::*;
+
+    /// The loaded model, optimized for the fixed input shape
+    static MODEL: OnceLock<TypedRunnableModel<TypedModel>> = OnceLock::new();
+
+    fn model() -> Result<&'static TypedRunnableModel<TypedModel>> {
+        if let Some(model) = MODEL.get() {
+            return Ok(model);
+        }
+        let path = model_path();
+        anyhow::ensure!(
+            std::path::Path::new(path).exists(),
+            "CNN model not found at {} (pass --cnn-model <file.onnx>)",
+            path
+        );
+        let size = SQUARE_SIZE as usize;
+        let model = tract_onnx::onnx()
+            .model_for_path(path)
+            .and_then(|model| model.with_input_fact(0, f32::fact([64, 3, size, size]).into()))
+            .and_then(|model| model.into_optimized())
+            .and_then(|model| model.into_runnable())
+            .with_context(|| format!("Failed to load CNN model {}", path))?;
+        Ok(MODEL.get_or_init(|| model))
+    }
+
+    /// Runs the model on a `square_tensor` input; returns the flat scores
+    pub fn classify(input: Vec<f32>) -> Result<Vec<f32>> {
+        let size = SQUARE_SIZE as usize;
+        let tensor = tract_ndarray::Array4::from_shape_vec((64, 3, size, size), input)?.into_tensor();
+        let outputs = model()?.run(tvec!(tensor.into())).context("CNN inference failed")?;
+        let scores = outputs[0].to_array_view::<f32>().context("Unexpected CNN output type")?;
+        Ok(scores.iter().copied().collect())
+    }
+}
+
+#[cfg(not(feature = "cnn"))]
+mod runtime {
+    use anyhow::Result;
+
+    pub fn classify(_input: Vec<f32>) -> Result<Vec<f32>> {
+        anyhow::bail!("CNN OCR needs a build with the cnn feature (cargo build --features cnn)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_square_tensor_layout() {
+        // Top-left square red, everything else black
+        let board = RgbaImage::from_fn(512, 512, |x, y| {
+            if x < 64 && y < 64 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 0, 0, 255]) }
+        });
+        let input = square_tensor(&board);
+        let plane = (SQUARE_SIZE * SQUARE_SIZE) as usize;
+        let center = plane / 2 + SQUARE_SIZE as usize / 2;
+        assert_eq!(input.len(), 64 * 3 * plane);
+        assert_eq!(input[center], 1.0); // a8, red
+        assert_eq!(input[plane + center], 0.0); // a8, green
+        assert_eq!(input[3 * plane + center], 0.0); // b8, red
+    }
+
+    #[test]
+    fn test_board_from_scores_picks_best_class() {
+        let mut scores = vec![0.0; 64 * CLASSES.len()];
+        for square in 0..64 {
+            scores[square * CLASSES.len()] = 1.0; // empty
+        }
+        scores[4 * CLASSES.len() + 12] = 5.0; // e8: black king
+        scores[60 * CLASSES.len() + 6] = 5.0; // e1: white king
+        let board = board_from_scores(&scores).unwrap();
+        assert_eq!(board[0][4], 'k');
+        assert_eq!(board[7][4], 'K');
+        assert_eq!(board[3][3], '1');
+        assert!(board_from_scores(&scores[..13]).is_err());
+    }
+}
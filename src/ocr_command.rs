@@ -0,0 +1,197 @@
+//! External command OCR backend
+//!
+//! `--ocr command --ocr-cmd "./my_detector {image}"` shells out to a user-provided program
+//! for each screenshot, so researchers can plug in their own recognizer (e.g. a PyTorch
+//! classifier) without touching the Rust code.
+//!
+//! Placeholders in the command template:
+//! - `{image}`: path of the screenshot (shell-quoted; a temporary PNG for live captures)
+//! - `{side}`: "white" or "black" (the side at the bottom of the board)
+//!
+//! The program must print a FEN on stdout (first non-empty line). A bare piece placement
+//! is accepted too; the side to move is then filled in from `--side`.
+
+use anyhow::{Context, Result};
+use crate::PlayerSide;
+use crate::ocr::{BoxFuture, Fen, OcrBackend, OcrRequest};
+use image::DynamicImage;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// How long the external program may run before it is killed
+const COMMAND_TIMEOUT_SECS: u64 = 30;
+
+/// Command template from `--ocr-cmd` (set once at startup)
+static COMMAND: OnceLock<String> = OnceLock::new();
+
+/// Sets the command template (first call wins)
+pub fn set_command(template: &str) {
+    let _ = COMMAND.set(template.to_string());
+}
+
+/// Returns true if a command template was configured
+pub fn has_command() -> bool {
+    COMMAND.get().is_some()
+}
+
+/// OCR backend that runs the configured external command
+pub struct CommandBackend;
+
+impl OcrBackend for CommandBackend {
+    fn recognize<'a>(&'a self, request: &'a OcrRequest) -> BoxFuture<'a, Result<Fen>> {
+        Box::pin(async move {
+            let template = COMMAND
+                .get()
+                .context("--ocr command requires --ocr-cmd \"<program> {image}\"")?;
+            // The program reads a file: use the one the image came from, or write it out
+            let scratch = match &request.image_path {
+                Some(_) => None,
+                None => Some(ScratchImage::write(&request.image)?),
+            };
+            let image_path = request.image_path.as_deref().or(scratch.as_ref().map(|s| s.path.as_str())).unwrap_or_default();
+            let command_line = expand_template(template, image_path, request.player_side);
+
+            let output = tokio::time::timeout(
+                Duration::from_secs(COMMAND_TIMEOUT_SECS),
+                shell_command(&command_line).kill_on_drop(true).output(),
+            )
+            .await
+            .with_context(|| format!("OCR command timed out after {}s: {}", COMMAND_TIMEOUT_SECS, command_line))?
+            .with_context(|| format!("Failed to run OCR command: {}", command_line))?;
+
+            if !output.status.success() {
+                anyhow::bail!(
+                    "OCR command exited with {}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+
+            parse_output(&String::from_utf8_lossy(&output.stdout), request.player_side)
+        })
+    }
+
+    fn display_name(&self) -> Option<&str> {
+        Some("Command (external recognizer)")
+    }
+}
+
+/// Lossless copy of an in-memory screenshot for the external program, removed when dropped.
+/// Named per process and request so concurrent requests and instances never share a file.
+struct ScratchImage {
+    path: String,
+}
+
+impl ScratchImage {
+    fn write(image: &DynamicImage) -> Result<Self> {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let name = format!("zugzwang-{}-{}.png", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(name).to_string_lossy().into_owned();
+        let bytes = crate::capture::encode(image, crate::capture::Consumer::TemplateMatching)?;
+        std::fs::write(&path, bytes).with_context(|| format!("Failed to write {}", path))?;
+        Ok(ScratchImage { path })
+    }
+}
+
+impl Drop for ScratchImage {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Removes this process's scratch images left behind by requests that never finished
+pub fn remove_scratch_images() {
+    let prefix = format!("zugzwang-{}-", std::process::id());
+    let Ok(entries) = std::fs::read_dir(std::env::temp_dir()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with(&prefix) && name.ends_with(".png") {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Substitutes the placeholders in the command template
+fn expand_template(template: &str, image_path: &str, player_side: PlayerSide) -> String {
+    let side = match player_side {
+        PlayerSide::White => "white",
+        PlayerSide::Black => "black",
+    };
+    template
+        .replace("{image}", &shell_quote(image_path))
+        .replace("{side}", side)
+}
+
+/// Quotes a path so the shell passes it through as a single argument
+#[cfg(not(windows))]
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(windows)]
+pub fn shell_quote(value: &str) -> String {
+    format!("\"{}\"", value)
+}
+
+/// Builds a command running `command_line` through the platform shell
+pub fn shell_command(command_line: &str) -> tokio::process::Command {
+    #[cfg(windows)]
+    let (shell, flag) = ("cmd", "/C");
+    #[cfg(not(windows))]
+    let (shell, flag) = ("sh", "-c");
+
+    let mut command = tokio::process::Command::new(shell);
+    command.arg(flag).arg(command_line);
+    command
+}
+
+/// Takes the first non-empty stdout line as the FEN, completing a bare placement
+/// and validating the result with shakmaty
+fn parse_output(stdout: &str, player_side: PlayerSide) -> Result<Fen> {
+    let line = stdout
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .context("OCR command printed no FEN")?;
+
+    let fen = if line.split_whitespace().count() == 1 {
+        format!("{} {} - - 0 1", line, player_side.fen_turn())
+    } else {
+        line.to_string()
+    };
+
+    shakmaty::fen::Fen::from_ascii(fen.as_bytes())
+        .map_err(|e| anyhow::anyhow!("OCR command printed an invalid FEN: {} (FEN: {})", e, fen))?;
+
+    Ok(fen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_expand_template_quotes_image() {
+        let cmd = expand_template("./detect {image} --side {side}", "shots/it's.jpg", PlayerSide::Black);
+        assert_eq!(cmd, r"./detect 'shots/it'\''s.jpg' --side black");
+    }
+
+    #[test]
+    fn test_parse_output_completes_placement() {
+        let fen = parse_output("\n8/8/8/8/8/8/8/K6k\n", PlayerSide::Black).unwrap();
+        assert_eq!(fen, "8/8/8/8/8/8/8/K6k b - - 0 1");
+    }
+
+    #[test]
+    fn test_parse_output_keeps_full_fen_and_rejects_garbage() {
+        let full = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(parse_output(full, PlayerSide::White).unwrap(), full);
+        assert!(parse_output("no board found", PlayerSide::White).is_err());
+        assert!(parse_output("", PlayerSide::White).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//! Hybrid OCR backend (`--ocr hybrid`): native speed, LLM accuracy on doubtful frames
+//!
+//! Every frame is read by template matching first. When the board's confidence (that of
+//! its most doubtful square, see `ocr_native::Recognition`) is below the threshold, or
+//! template matching fails outright, the frame goes to the LLM backend instead. Most frames
+//! of a game are clean, so this costs an LLM request only for the odd one with an arrow,
+//! a dragged piece, or an animation in progress.
+//!
+//! The threshold is `--hybrid-threshold` (0 to 1, default `DEFAULT_THRESHOLD`). Without
+//! LLM credentials the native reading is used as is, with a warning.
+
+use anyhow::Result;
+use crate::ocr::{BoxFuture, Fen, LlmBackend, OcrBackend, OcrRequest};
+use crate::ocr_native::Recognition;
+use std::sync::OnceLock;
+
+/// Board confidence below which a frame is read by the LLM
+pub const DEFAULT_THRESHOLD: f32 = 0.5;
+
+/// Threshold from `--hybrid-threshold` (set once at startup)
+static THRESHOLD: OnceLock<f32> = OnceLock::new();
+
+/// Sets the confidence threshold (first call wins)
+pub fn set_threshold(threshold: f32) {
+    let _ = THRESHOLD.set(threshold.clamp(0.0, 1.0));
+}
+
+/// The configured confidence threshold
+pub fn threshold() -> f32 {
+    THRESHOLD.get().copied().unwrap_or(DEFAULT_THRESHOLD)
+}
+
+/// Why a native reading should go to the LLM; None when it can be trusted
+pub fn escalation(native: &Result<Recognition>, threshold: f32) -> Option<String> {
+    match native {
+        Err(e) => Some(format!("native OCR failed: {:#}", e)),
+        Ok(recognition) if recognition.overall() < threshold => {
+            let (square, confidence) = recognition.weakest();
+            Some(format!("confidence {:.2} at {} < {:.2}", confidence, square, threshold))
+        }
+        Ok(_) => None,
+    }
+}
+
+/// Template matching with the LLM as fallback
+pub struct HybridBackend;
+
+impl OcrBackend for HybridBackend {
+    fn recognize<'a>(&'a self, request: &'a OcrRequest) -> BoxFuture<'a, Result<Fen>> {
+        Box::pin(async move {
+            let native = crate::ocr::read_native(request).await;
+            let Some(reason) = escalation(&native, threshold()) else {
+                return native.map(|recognition| recognition.fen);
+            };
+            if !crate::ocr::llm_available() {
+                eprintln!("⚠ Hybrid OCR: {}, but no LLM credentials; keeping the native reading", reason);
+                return native.map(|recognition| recognition.fen);
+            }
+            eprintln!("Hybrid OCR: {}, asking the LLM", reason);
+            match (LlmBackend.recognize(request).await, native) {
+                (Ok(fen), _) => Ok(fen),
+                // A doubtful native reading beats none
+                (Err(e), Ok(recognition)) => {
+                    eprintln!("⚠ LLM OCR failed ({:#}); keeping the native reading", e);
+                    Ok(recognition.fen)
+                }
+                (Err(e), Err(_)) => Err(e),
+            }
+        })
+    }
+
+    /// Template matching is CPU-bound, as for the native backend
+    fn supports_concurrency(&self) -> bool {
+        false
+    }
+
+    fn display_name(&self) -> Option<&str> {
+        Some("Hybrid (templates, LLM when unsure)")
+    }
+
+    /// Unsure frames go to the LLM
+    fn uses_llm(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escalation() {
+        let mut confidence = [[0.9; 8]; 8];
+        let sure = Recognition { fen: String::new(), confidence };
+        assert_eq!(escalation(&Ok(sure), 0.5), None);
+
+        confidence[0][7] = 0.3;
+        let doubtful = Recognition { fen: String::new(), confidence };
+        assert_eq!(escalation(&Ok(doubtful.clone()), 0.5).unwrap(), "confidence 0.30 at h8 < 0.50");
+        assert_eq!(escalation(&Ok(doubtful), 0.2), None);
+
+        let failed = escalation(&Err(anyhow::anyhow!("no board")), 0.5).unwrap();
+        assert_eq!(failed, "native OCR failed: no board");
+    }
+}
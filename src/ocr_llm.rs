@@ -9,36 +9,168 @@
 //!
 //! Latency: 500-2000ms (network dependent)
 //! Requires OPENAI_API_KEY environment variable.
+//! Several keys can be listed comma-separated in OPENAI_API_KEYS; on a 429 rate-limit
+//! response the next key is used, so continuous auto mode isn't capped by one key's limits.
+//! Calls are otherwise held back until the rate limit headers say the budget has reset
+//! (see `rate_limit`).
+//!
+//! Proxies: HTTPS_PROXY/ALL_PROXY are honored automatically; `--proxy` overrides them.
+//! Both http(s):// and socks5:// proxy URLs are supported.
+//!
+//! Image detail (`--detail`): `high` (default), `low` (~85 tokens, several times cheaper),
+//! `auto` (OpenAI decides), or `adaptive` (low first, high only after a validation failure).
+//! Responses are requested gzip-compressed. Near the session's `--token-budget`, requests
+//! drop to low detail (see `token_budget`).
+//!
+//! Requests go to the active provider of the fallback chain (see `llm_provider`); on an
+//! outage the chain moves on to the next provider with credentials. Anthropic doesn't take
+//! the chat completions format, so its requests are translated to the Messages API.
+//!
+//! FEN and move answers are JSON objects (`FenAnswer`, `MoveRecommendation`), requested with
+//! a `json_schema` response format where the API supports it. Anthropic has none, so there
+//! the prompt's own JSON instructions carry it; answers wrapped in a code fence still parse.
 
 use anyhow::{Context, Result};
 use base64::{engine::general_purpose, Engine as _};
 use reqwest::Client;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 use crate::PlayerSide;
+use crate::fen::validate_fen;
+use crate::llm_provider::{self, Provider};
 
-const API_URL: &str = "https://api.openai.com/v1/chat/completions";
-const MODEL: &str = "gpt-4o";  // Full GPT-4o for better vision accuracy (was gpt-4o-mini)
+const MODELS_URL: &str = "https://api.openai.com/v1/models"; // Cheap authenticated endpoint for key checks
 const MAX_API_RETRIES: u32 = 2;      // Retries for network/API errors
 const MAX_VALIDATION_RETRIES: u32 = 2; // Retries when FEN validation fails (e.g., 9 pawns)
 const TIMEOUT_SECS: u64 = 30;  // Increased timeout for larger model
+const KEY_CHECK_TIMEOUT_SECS: u64 = 10;
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Index of the key currently in use (rotates on rate limits)
+static CURRENT_KEY: AtomicUsize = AtomicUsize::new(0);
+
+/// Explicit proxy from `--proxy` (takes precedence over HTTPS_PROXY/ALL_PROXY)
+static PROXY: OnceLock<reqwest::Proxy> = OnceLock::new();
+
+/// Vision detail setting from `--detail`
+static DETAIL: OnceLock<ImageDetail> = OnceLock::new();
+
+/// Vision `detail` level sent with the image, or the adaptive policy
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageDetail {
+    /// Full resolution tiles (most accurate, most expensive)
+    #[default]
+    High,
+    /// Single 512px view (cheap; usually enough for a clearly visible board)
+    Low,
+    /// Let the API choose based on image size
+    Auto,
+    /// Start with low detail and escalate to high only when the FEN fails validation
+    Adaptive,
+}
+
+impl ImageDetail {
+    /// Parses the `--detail` CLI value
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "high" => Some(ImageDetail::High),
+            "low" => Some(ImageDetail::Low),
+            "auto" => Some(ImageDetail::Auto),
+            "adaptive" => Some(ImageDetail::Adaptive),
+            _ => None,
+        }
+    }
+
+    /// API `detail` value for a given attempt (1-based) under this setting
+    fn api_value(self, attempt: u32) -> &'static str {
+        match self {
+            ImageDetail::High => "high",
+            ImageDetail::Low => "low",
+            ImageDetail::Auto => "auto",
+            ImageDetail::Adaptive if attempt <= 1 => "low",
+            ImageDetail::Adaptive => "high",
+        }
+    }
+}
+
+impl std::fmt::Display for ImageDetail {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageDetail::High => write!(f, "high"),
+            ImageDetail::Low => write!(f, "low"),
+            ImageDetail::Auto => write!(f, "auto"),
+            ImageDetail::Adaptive => write!(f, "adaptive (low → high on failure)"),
+        }
+    }
+}
 
 // *************** Request/Response Types ***************
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 struct ChatRequest {
     model: String,
     messages: Vec<ChatMessage>,
     max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
 }
 
-#[derive(Serialize)]
+impl ChatRequest {
+    /// Asks for an answer matching `schema` (OpenAI structured outputs)
+    fn with_schema(mut self, name: &str, schema: serde_json::Value) -> Self {
+        self.response_format = Some(ResponseFormat {
+            kind: "json_schema".to_string(),
+            json_schema: JsonSchemaFormat { name: name.to_string(), strict: true, schema },
+        });
+        self
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    kind: String,
+    json_schema: JsonSchemaFormat,
+}
+
+#[derive(Serialize, Clone)]
+struct JsonSchemaFormat {
+    name: String,
+    strict: bool,
+    schema: serde_json::Value,
+}
+
+/// FEN OCR answer
+#[derive(Deserialize)]
+struct FenAnswer {
+    fen: String,
+}
+
+impl FenAnswer {
+    fn schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": { "fen": { "type": "string" } },
+            "required": ["fen"],
+            "additionalProperties": false,
+        })
+    }
+}
+
+/// Evaluations the move analysis may answer with
+const EVALUATIONS: [&str; 7] =
+    ["winning", "clear advantage", "slight advantage", "equal", "slight disadvantage", "clear disadvantage", "losing"];
+
+#[derive(Serialize, Clone)]
 struct ChatMessage {
     role: String,
     content: Vec<ContentPart>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 #[serde(tag = "type")]
 enum ContentPart {
     #[serde(rename = "text")]
@@ -47,7 +179,7 @@ enum ContentPart {
     ImageUrl { image_url: ImageUrlDetail },
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 struct ImageUrlDetail {
     url: String,
     detail: String,
@@ -56,6 +188,17 @@ struct ImageUrlDetail {
 #[derive(Deserialize)]
 struct ChatResponse {
     choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChatUsage {
+    total_tokens: u64,
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
 }
 
 #[derive(Deserialize)]
@@ -68,23 +211,225 @@ struct ResponseMessage {
     content: String,
 }
 
+/// Anthropic Messages API request
+#[derive(Serialize)]
+struct AnthropicRequest {
+    model: String,
+    max_tokens: u32,
+    messages: Vec<AnthropicMessage>,
+}
+
+#[derive(Serialize)]
+struct AnthropicMessage {
+    role: String,
+    content: Vec<AnthropicPart>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum AnthropicPart {
+    Text { text: String },
+    Image { source: ImageSource },
+}
+
+/// Inline image (`{"type": "base64", "media_type": ..., "data": ...}`)
+#[derive(Serialize)]
+struct ImageSource {
+    #[serde(rename = "type")]
+    kind: String,
+    media_type: String,
+    data: String,
+}
+
+#[derive(Deserialize)]
+struct AnthropicResponse {
+    content: Vec<AnthropicBlock>,
+    #[serde(default)]
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicUsage {
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct AnthropicBlock {
+    #[serde(default)]
+    text: Option<String>,
+}
 
-/// Result of direct LLM chess analysis
-#[derive(Debug, Clone)]
+impl AnthropicRequest {
+    /// Translates a chat completions request (images as data URLs; detail has no equivalent)
+    fn from_chat(request: &ChatRequest) -> Result<Self> {
+        let messages = request
+            .messages
+            .iter()
+            .map(|message| {
+                let content = message
+                    .content
+                    .iter()
+                    .map(|part| match part {
+                        ContentPart::Text { text } => Ok(AnthropicPart::Text { text: text.clone() }),
+                        ContentPart::ImageUrl { image_url } => {
+                            let (media_type, data) = image_url
+                                .url
+                                .strip_prefix("data:")
+                                .and_then(|rest| rest.split_once(";base64,"))
+                                .context("Anthropic requests need inline base64 images")?;
+                            Ok(AnthropicPart::Image {
+                                source: ImageSource {
+                                    kind: "base64".to_string(),
+                                    media_type: media_type.to_string(),
+                                    data: data.to_string(),
+                                },
+                            })
+                        }
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(AnthropicMessage { role: message.role.clone(), content })
+            })
+            .collect::<Result<_>>()?;
+        Ok(AnthropicRequest { model: request.model.clone(), max_tokens: request.max_tokens, messages })
+    }
+}
+
+
+/// Result of direct LLM chess analysis (also the answer's JSON schema)
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MoveRecommendation {
     /// The recommended move in readable format (e.g., "E2 to E4", "Knight to F3")
     pub best_move: String,
     /// Brief explanation of why this move is good
+    #[serde(default = "no_reasoning")]
     pub reasoning: String,
     /// Position evaluation (e.g., "slight advantage", "winning", "equal")
+    #[serde(default = "unknown_evaluation")]
     pub evaluation: String,
 }
 
+impl MoveRecommendation {
+    fn schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "best_move": { "type": "string" },
+                "evaluation": { "type": "string", "enum": EVALUATIONS },
+                "reasoning": { "type": "string" },
+            },
+            "required": ["best_move", "evaluation", "reasoning"],
+            "additionalProperties": false,
+        })
+    }
+}
+
+// Fallbacks for providers that don't enforce the schema
+fn no_reasoning() -> String {
+    "No explanation provided".to_string()
+}
+
+fn unknown_evaluation() -> String {
+    "unknown".to_string()
+}
+
+/// Non-success HTTP response from the API, kept typed so retry logic can react to the status
+#[derive(Debug)]
+struct ApiStatusError {
+    status: u16,
+    body: String,
+}
+
+impl std::fmt::Display for ApiStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "LLM API error {}: {}", self.status, self.body)
+    }
+}
+
+impl std::error::Error for ApiStatusError {}
+
+/// Outcome of checking an API key against the provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyStatus {
+    /// The provider accepted the key
+    Valid,
+    /// The provider rejected the key (invalid, revoked, or expired)
+    Invalid(String),
+    /// The check itself failed (network error, outage) - key may still be fine
+    Unverified(String),
+}
+
 // *************** Public API ***************
 
-/// Checks if the OpenAI API key is available
+/// Checks if at least one OpenAI API key is available
 pub fn has_api_key() -> bool {
-    std::env::var("OPENAI_API_KEY").is_ok()
+    !api_keys().is_empty()
+}
+
+/// Checks if any provider in the fallback chain has the credentials it needs
+pub fn llm_ready() -> bool {
+    llm_provider::chain().iter().any(|&p| provider_ready(p))
+}
+
+/// Fails, naming the keys the chain's providers read, unless one of them is ready
+fn ensure_ready() -> Result<()> {
+    anyhow::ensure!(llm_ready(), "{}", missing_credentials(llm_provider::chain()));
+    Ok(())
+}
+
+/// Error message for a chain without credentials: "No LLM credentials: set OPENAI_API_KEY
+/// or GEMINI_API_KEY"
+fn missing_credentials(chain: &[Provider]) -> String {
+    let vars: Vec<&str> = chain.iter().filter_map(|provider| provider.key_var()).collect();
+    format!("No LLM credentials: set {}", vars.join(" or "))
+}
+
+/// Returns all configured API keys: OPENAI_API_KEYS (comma-separated) followed by
+/// OPENAI_API_KEY, with blanks and duplicates removed
+pub fn api_keys() -> Vec<String> {
+    parse_api_keys(
+        std::env::var("OPENAI_API_KEYS").ok().as_deref(),
+        std::env::var("OPENAI_API_KEY").ok().as_deref(),
+    )
+}
+
+/// Routes all LLM requests through the given proxy (http://, https://, or socks5:// URL).
+/// Must be called before the first request; later calls are ignored.
+pub fn set_proxy(url: &str) -> Result<()> {
+    let proxy = reqwest::Proxy::all(url).with_context(|| format!("Invalid proxy URL: {}", url))?;
+    let _ = PROXY.set(proxy);
+    Ok(())
+}
+
+/// Sets the vision detail level for all subsequent requests (first call wins)
+pub fn set_detail(detail: ImageDetail) {
+    let _ = DETAIL.set(detail);
+}
+
+/// Returns the vision detail level in effect (low once the token budget runs low)
+pub fn detail() -> ImageDetail {
+    if crate::token_budget::downgraded() {
+        return ImageDetail::Low;
+    }
+    DETAIL.get().copied().unwrap_or_default()
+}
+
+/// Verifies an API key with a minimal authenticated request (model list, no tokens billed).
+pub async fn check_api_key(api_key: &str) -> KeyStatus {
+    let client = match http_client(KEY_CHECK_TIMEOUT_SECS) {
+        Ok(client) => client,
+        Err(e) => return KeyStatus::Unverified(format!("Failed to create HTTP client: {}", e)),
+    };
+
+    match client
+        .get(MODELS_URL)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send()
+        .await
+    {
+        Ok(response) => key_status_from_http(response.status().as_u16()),
+        Err(e) => KeyStatus::Unverified(format!("Could not reach OpenAI: {}", e)),
+    }
 }
 
 /// Analyzes a chess board image and returns a move recommendation directly.
@@ -94,21 +439,20 @@ pub fn has_api_key() -> bool {
 ///
 /// The `player_side` parameter tells the LLM which color you're playing as,
 /// so it knows which pieces to move and how the board is oriented.
-pub async fn analyze_board(image_path: &str, player_side: PlayerSide) -> Result<MoveRecommendation> {
-    let api_key = std::env::var("OPENAI_API_KEY")
-        .context("OPENAI_API_KEY environment variable not set")?;
+pub async fn analyze_board(image: &image::DynamicImage, player_side: PlayerSide) -> Result<MoveRecommendation> {
+    ensure_ready()?;
 
-    // Read and encode image
-    let image_data =
-        std::fs::read(image_path).with_context(|| format!("Failed to read image: {}", image_path))?;
-    let base64_image = general_purpose::STANDARD.encode(&image_data);
+    // Encode image for upload
+    let base64_image = general_purpose::STANDARD.encode(encode_jpeg(image)?);
 
     // Build request with move analysis prompt
     let prompt = build_move_prompt(player_side);
-    let request = build_move_request(&base64_image, &prompt);
+    // Direct analysis reads the whole screenshot: adaptive goes straight to high detail
+    let request = build_move_request(&base64_image, &prompt, detail().api_value(2))
+        .with_schema("move_recommendation", MoveRecommendation::schema());
 
     // Call API with retry
-    let response = call_api_with_retry(&api_key, &request).await?;
+    let response = call_api_with_retry(&request).await?;
 
     // Parse the structured response
     parse_move_response(&response)
@@ -123,33 +467,60 @@ pub async fn analyze_board(image_path: &str, player_side: PlayerSide) -> Result<
 /// Includes automatic retry logic:
 /// - Retries on network/API errors (up to MAX_API_RETRIES)
 /// - Retries on validation failures like "9 pawns" (up to MAX_VALIDATION_RETRIES)
-pub async fn board_to_fen(image_path: &str, player_side: PlayerSide) -> Result<String> {
-    let api_key = std::env::var("OPENAI_API_KEY")
-        .context("OPENAI_API_KEY environment variable not set")?;
+///
+/// Boards read before (see `llm_cache`) are answered without calling the API.
+pub async fn board_to_fen(image: &Arc<image::DynamicImage>, player_side: PlayerSide) -> Result<String> {
+    ensure_ready()?;
+
+    let cache_key = crate::llm_cache::key(image);
+    if let Some(fen) = cache_key.as_ref().and_then(|key| crate::llm_cache::get(key, player_side)) {
+        eprintln!("LLM cache hit{}: {}", cycle_tag(), fen);
+        return Ok(fen);
+    }
+    let remember = |fen: &str| {
+        if let Some(key) = cache_key {
+            crate::llm_cache::insert(key, player_side, fen);
+        }
+    };
 
-    // Read and encode image
-    let image_data =
-        std::fs::read(image_path).with_context(|| format!("Failed to read image: {}", image_path))?;
-    let base64_image = general_purpose::STANDARD.encode(&image_data);
+    // Encode image for upload
+    let base64_image = general_purpose::STANDARD.encode(encode_jpeg(image)?);
 
-    // Build request with side-aware prompt
-    let prompt = build_fen_prompt(player_side);
-    let request = build_fen_request(&base64_image, &prompt);
+    // Build side-aware prompt (request is rebuilt per attempt: detail may escalate)
+    let photo = crate::capture::is_camera();
+    let prompt = build_fen_prompt(player_side, photo);
+    let detail = detail();
 
     // Retry loop for validation failures (LLM sometimes returns invalid positions)
     let mut last_validation_error = None;
+    // First rejected FEN, recorded as a hard case if a retry then succeeds
+    let mut first_rejected: Option<String> = None;
 
     for validation_attempt in 1..=MAX_VALIDATION_RETRIES + 1 {
+        let image_detail = detail.api_value(validation_attempt);
+        if validation_attempt > 1 && image_detail != detail.api_value(validation_attempt - 1) {
+            eprintln!("Escalating image detail to {}", image_detail);
+        }
+        let request = build_fen_request(&base64_image, &prompt, image_detail).with_schema("fen", FenAnswer::schema());
+
         // Call API with retry (handles network errors)
-        let fen = call_api_with_retry(&api_key, &request).await?;
+        let response = call_api_with_retry(&request).await?;
 
-        // Always show raw LLM response for debugging
-        eprintln!("LLM returned: {}", fen);
+        // Always show raw LLM response for debugging, tagged with the cycle it belongs to
+        eprintln!("LLM returned{}: {}", cycle_tag(), response);
+        let fen = parse_fen_response(&response);
 
         // Validate and fix FEN (corrects castling rights based on piece positions)
         match validate_fen(&fen) {
-            Ok(corrected_fen) => return Ok(corrected_fen),
+            Ok(corrected_fen) => {
+                if let Some(wrong_fen) = &first_rejected {
+                    crate::hard_cases::record_or_warn(image, wrong_fen, &corrected_fen, "llm-validation-retry");
+                }
+                remember(&corrected_fen);
+                return Ok(corrected_fen);
+            }
             Err(e) => {
+                first_rejected.get_or_insert(fen);
                 if validation_attempt <= MAX_VALIDATION_RETRIES {
                     eprintln!(
                         "⚠ Validation failed (attempt {}/{}): {} - retrying...",
@@ -167,8 +538,86 @@ pub async fn board_to_fen(image_path: &str, player_side: PlayerSide) -> Result<S
         }
     }
 
-    // All retries exhausted
-    Err(last_validation_error.unwrap())
+    // All whole-board retries exhausted: fall back to reading the board in quadrants,
+    // which is far more reliable for cluttered or low-resolution boards
+    let whole_board_error = last_validation_error.unwrap();
+    if photo {
+        // Quadrants are cut from a located screen board, which a photo doesn't have
+        return Err(whole_board_error);
+    }
+    eprintln!("⚠ Whole-board OCR failed - retrying as four quadrants...");
+    match board_to_fen_by_quadrants(image, player_side).await {
+        Ok(fen) => {
+            if let Some(wrong_fen) = &first_rejected {
+                crate::hard_cases::record_or_warn(image, wrong_fen, &fen, "llm-quadrant-fallback");
+            }
+            remember(&fen);
+            Ok(fen)
+        }
+        Err(e) => Err(whole_board_error.context(format!("Quadrant fallback also failed: {:#}", e))),
+    }
+}
+
+/// Fallback OCR: detects and crops the board, splits it into four 4×4 quadrants, and asks
+/// the LLM to read each one separately (concurrently). The answers are stitched back
+/// into a full FEN and validated like a whole-board result.
+async fn board_to_fen_by_quadrants(image: &Arc<image::DynamicImage>, player_side: PlayerSide) -> Result<String> {
+    let image = Arc::clone(image);
+    let board = tokio::task::spawn_blocking(move || crate::ocr_native::screenshot_to_board(&image))
+        .await
+        .map_err(|e| anyhow::anyhow!("Board detection task failed: {}", e))?
+        .context("Quadrant fallback needs a detectable board")?;
+
+    // Rotate so rank 8 is at the top regardless of which side the user plays
+    let board = if player_side.needs_board_flip() { board.rotate180() } else { board };
+    let half = board.width() / 2;
+
+    // Quadrant order: (row, col) → top-left, top-right, bottom-left, bottom-right
+    let mut requests = Vec::with_capacity(4);
+    for (row, col) in [(0u32, 0u32), (0, 1), (1, 0), (1, 1)] {
+        let quadrant = board.crop_imm(col * half, row * half, half, half);
+        let base64_quadrant = general_purpose::STANDARD.encode(encode_jpeg(&quadrant)?);
+        let prompt = build_quadrant_prompt(row, col);
+        requests.push(build_fen_request(&base64_quadrant, &prompt, "high"));
+    }
+
+    let (a, b, c, d) = tokio::join!(
+        call_api_with_retry(&requests[0]),
+        call_api_with_retry(&requests[1]),
+        call_api_with_retry(&requests[2]),
+        call_api_with_retry(&requests[3]),
+    );
+
+    let mut grid = [['1'; 8]; 8];
+    for (idx, response) in [a, b, c, d].into_iter().enumerate() {
+        let quadrant = parse_quadrant_response(&response?)
+            .with_context(|| format!("Unreadable answer for quadrant {}", idx + 1))?;
+        let (row, col) = (idx / 2, idx % 2);
+        for (r, squares) in quadrant.iter().enumerate() {
+            for (c, &piece) in squares.iter().enumerate() {
+                grid[row * 4 + r][col * 4 + c] = piece;
+            }
+        }
+    }
+
+    let placement = crate::fen::serialize_placement(&grid);
+    let fen = format!("{} {} KQkq - 0 1", placement, player_side.fen_turn());
+    eprintln!("LLM quadrants returned{}: {}", cycle_tag(), fen);
+    validate_fen(&fen)
+}
+
+/// Reads short text (e.g. a name plate) from an image crop with a low-detail request
+pub async fn read_text(img: &image::DynamicImage, prompt: &str) -> Result<String> {
+    ensure_ready()?;
+    let base64_image = general_purpose::STANDARD.encode(encode_jpeg(img)?);
+    // Same request shape as FEN recognition: one prompt plus one image, short answer
+    let request = build_fen_request(&base64_image, prompt, "low");
+    call_api_with_retry(&request).await
+}
+
+/// Encodes an image as JPEG for upload
+fn encode_jpeg(img: &image::DynamicImage) -> Result<Vec<u8>> {
+    crate::capture::encode(img, crate::capture::Consumer::Llm).context("Failed to encode image crop")
 }
 
 
@@ -199,10 +648,10 @@ Consider:
 - Positional factors (piece activity, pawn structure, king safety)
 - Immediate threats from {opponent_color}
 
-Respond in EXACTLY this format (3 lines only, no other text):
-MOVE: [source square] to [destination square]
-EVAL: [one of: winning, clear advantage, slight advantage, equal, slight disadvantage, clear disadvantage, losing]
-WHY: [one brief sentence explaining why this is the best move]
+Respond with ONLY a JSON object with these fields (no other text):
+- "best_move": [source square] to [destination square]
+- "evaluation": one of: {evaluations}
+- "reasoning": one brief sentence explaining why this is the best move
 
 IMPORTANT:
 - Use algebraic notation for squares (e.g., E2 to E4, not "pawn forward")
@@ -211,9 +660,8 @@ IMPORTANT:
 - For castling: "O-O" (kingside) or "O-O-O" (queenside)
 
 Example response:
-MOVE: E2 to E4
-EVAL: equal
-WHY: Controls the center and opens lines for the bishop and queen."#,
+{{"best_move": "E2 to E4", "evaluation": "equal", "reasoning": "Controls the center and opens lines for the bishop and queen."}}"#,
+        evaluations = EVALUATIONS.join(", "),
         color = color,
         opponent_color = opponent_color,
         my_pieces_location = my_pieces_location,
@@ -222,9 +670,9 @@ WHY: Controls the center and opens lines for the bishop and queen."#,
 }
 
 /// Builds the API request for move analysis (needs more tokens for reasoning)
-fn build_move_request(base64_image: &str, prompt: &str) -> ChatRequest {
+fn build_move_request(base64_image: &str, prompt: &str, detail: &str) -> ChatRequest {
     ChatRequest {
-        model: MODEL.to_string(),
+        model: llm_provider::active().model(),
         messages: vec![ChatMessage {
             role: "user".to_string(),
             content: vec![
@@ -234,72 +682,164 @@ fn build_move_request(base64_image: &str, prompt: &str) -> ChatRequest {
                 ContentPart::ImageUrl {
                     image_url: ImageUrlDetail {
                         url: format!("data:image/jpeg;base64,{}", base64_image),
-                        detail: "high".to_string(),
+                        detail: detail.to_string(),
                     },
                 },
             ],
         }],
         max_tokens: 200, // More tokens needed for move + reasoning
+        response_format: None,
     }
 }
 
-/// Parses the structured move response from GPT-4o
+/// What one request sends and gets back, for the startup estimate (`cost_estimate`)
+pub struct RequestShape {
+    pub prompt_chars: usize,
+    /// API `detail` of the screenshot on the first attempt
+    pub detail: &'static str,
+    pub max_output_tokens: u32,
+}
+
+/// Shape of the FEN request, or of the direct move request with `recommend`
+pub fn request_shape(recommend: bool, player_side: PlayerSide) -> RequestShape {
+    // Direct analysis reads the whole screenshot: adaptive goes straight to high detail
+    let (prompt, detail) = if recommend {
+        (build_move_prompt(player_side), detail().api_value(2))
+    } else {
+        (build_fen_prompt(player_side, crate::capture::is_camera()), detail().api_value(1))
+    };
+    let request = if recommend { build_move_request("", &prompt, detail) } else { build_fen_request("", &prompt, detail) };
+    RequestShape { prompt_chars: prompt.len(), detail, max_output_tokens: request.max_tokens }
+}
+
+/// Parses the move analysis answer
 fn parse_move_response(response: &str) -> Result<MoveRecommendation> {
-    let mut best_move = None;
-    let mut evaluation = None;
-    let mut reasoning = None;
-
-    for line in response.lines() {
-        let line = line.trim();
-        if let Some(rest) = line.strip_prefix("MOVE:") {
-            best_move = Some(rest.trim().to_string());
-        } else if let Some(rest) = line.strip_prefix("EVAL:") {
-            evaluation = Some(rest.trim().to_string());
-        } else if let Some(rest) = line.strip_prefix("WHY:") {
-            reasoning = Some(rest.trim().to_string());
-        }
-    }
+    parse_json_answer(response)
+}
 
-    let best_move = best_move.ok_or_else(|| anyhow::anyhow!(
-        "LLM response missing MOVE line. Response was: {}", response
-    ))?;
+/// The FEN from a FEN OCR answer; a bare FEN is taken as is (providers that ignore the
+/// response format), and anything else is left to FEN validation to reject
+fn parse_fen_response(response: &str) -> String {
+    parse_json_answer::<FenAnswer>(response)
+        .map(|answer| answer.fen.trim().to_string())
+        .unwrap_or_else(|_| response.trim().to_string())
+}
 
-    Ok(MoveRecommendation {
-        best_move,
-        evaluation: evaluation.unwrap_or_else(|| "unknown".to_string()),
-        reasoning: reasoning.unwrap_or_else(|| "No explanation provided".to_string()),
-    })
+/// Deserializes a JSON answer, looking past a code fence or text around the object
+fn parse_json_answer<T: DeserializeOwned>(response: &str) -> Result<T> {
+    let object = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => response,
+    };
+    serde_json::from_str(object).with_context(|| format!("LLM answer isn't the expected JSON. Response was: {}", response))
 }
 
 /// Builds the prompt for FEN OCR based on which side the player is playing.
 /// - When playing as White: White pieces are at the bottom, turn indicator is 'w'
 /// - When playing as Black: Black pieces are at the bottom, turn indicator is 'b'
-fn build_fen_prompt(player_side: PlayerSide) -> String {
-    let (piece_position, turn_char) = match player_side {
-        PlayerSide::White => ("White pieces are at the bottom of the image", 'w'),
-        PlayerSide::Black => ("Black pieces are at the bottom of the image", 'b'),
+///
+/// `photo` is for camera frames of a physical board: orientation is described relative
+/// to the camera, and the model is told to look past perspective and surroundings.
+fn build_fen_prompt(player_side: PlayerSide, photo: bool) -> String {
+    let (piece_position, turn_char) = match (player_side, photo) {
+        (PlayerSide::White, false) => ("White pieces are at the bottom of the image", 'w'),
+        (PlayerSide::Black, false) => ("Black pieces are at the bottom of the image", 'b'),
+        (PlayerSide::White, true) => ("White pieces started on the side of the board nearest the camera", 'w'),
+        (PlayerSide::Black, true) => ("Black pieces started on the side of the board nearest the camera", 'b'),
+    };
+    let (subject, photo_rules) = if photo {
+        (
+            "This is a photo of a physical chessboard, possibly taken at an angle.",
+            "\n- Read each square along the board's own grid, not the image's rows\n- Ignore everything off the board (table, hands, captured pieces, clock)",
+        )
+    } else {
+        ("Analyze this chessboard image.", "")
+    };
+    // Screenshots show who is to move; photos of a physical board don't reliably
+    let turn_rule = if !photo && crate::turn::enabled() {
+        format!(
+            "- Append the side to move, then KQkq - 0 1. The side to move is the one that did NOT make the last move \
+(its two squares are tinted), or the one whose clock is highlighted/running: w or b. If neither shows, use {}",
+            turn_char
+        )
+    } else {
+        format!("- Append: {} KQkq - 0 1", turn_char)
     };
 
-    format!(r#"Analyze this chessboard image. Output ONLY the FEN string.
+    format!(r#"{subject} Output ONLY a JSON object with the FEN.
 
 Rules:
-- Output ONLY the FEN, nothing else (no explanation, no markdown, no quotes)
-- {piece_position}
+- Output ONLY {{"fen": "<FEN>"}}, nothing else (no explanation, no markdown)
+- {piece_position}{photo_rules}
 - Use standard FEN: uppercase = White (KQRBNP), lowercase = Black (kqrbnp)
 - Numbers represent consecutive empty squares
 - Rows separated by / (starting from rank 8 at the top of the board)
-- Append: {turn_char} KQkq - 0 1
+{turn_rule}
 
-Example output for starting position:
-rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR {turn_char} KQkq - 0 1"#,
+Example output after 1. e4:
+{{"fen": "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR {turn_char} KQkq - 0 1"}}"#,
+        subject = subject,
         piece_position = piece_position,
+        photo_rules = photo_rules,
+        turn_rule = turn_rule,
         turn_char = turn_char
     )
 }
 
-fn build_fen_request(base64_image: &str, prompt: &str) -> ChatRequest {
+/// Builds the prompt for one quadrant of the board (row/col 0 = top/left half).
+/// Quadrants are always shown with White at the bottom (rank 8 at the top).
+fn build_quadrant_prompt(row: u32, col: u32) -> String {
+    let (files, first_file, last_file) = if col == 0 { ("a-d", 'a', 'd') } else { ("e-h", 'e', 'h') };
+    let (ranks, top_rank, bottom_rank) = if row == 0 { ("8-5", 8, 5) } else { ("4-1", 4, 1) };
+
+    format!(r#"This image shows one quarter of a chessboard: a 4x4 block of squares covering files {files} and ranks {ranks}.
+The top-left square is {first_file}{top_rank} and the bottom-right square is {last_file}{bottom_rank}.
+
+Identify the content of each of the 16 squares.
+
+Output EXACTLY 4 lines of 4 characters, top row first, nothing else:
+- Uppercase = White (K Q R B N P), lowercase = Black (k q r b n p)
+- Use . for an empty square
+
+Example:
+r.b.
+pp..
+..n.
+...."#,
+        files = files,
+        ranks = ranks,
+        first_file = first_file,
+        last_file = last_file,
+        top_rank = top_rank,
+        bottom_rank = bottom_rank,
+    )
+}
+
+/// Parses a quadrant answer into a 4×4 grid ('1' = empty, matching the native OCR grid).
+/// Tolerates code fences, spaces between squares, and surrounding chatter.
+fn parse_quadrant_response(response: &str) -> Result<[[char; 4]; 4]> {
+    let rows: Vec<Vec<char>> = response
+        .lines()
+        .map(|line| line.chars().filter(|c| !c.is_whitespace()).collect::<Vec<char>>())
+        .filter(|row| row.len() == 4 && row.iter().all(|c| *c == '.' || "KQRBNPkqrbnp".contains(*c)))
+        .collect();
+
+    if rows.len() != 4 {
+        anyhow::bail!("expected 4 rows of 4 squares, got {} (response: '{}')", rows.len(), response);
+    }
+
+    let mut grid = [['1'; 4]; 4];
+    for (r, row) in rows.iter().enumerate() {
+        for (c, &square) in row.iter().enumerate() {
+            grid[r][c] = if square == '.' { '1' } else { square };
+        }
+    }
+    Ok(grid)
+}
+
+fn build_fen_request(base64_image: &str, prompt: &str, detail: &str) -> ChatRequest {
     ChatRequest {
-        model: MODEL.to_string(),
+        model: llm_provider::active().model(),
         messages: vec![ChatMessage {
             role: "user".to_string(),
             content: vec![
@@ -309,25 +849,131 @@ fn build_fen_request(base64_image: &str, prompt: &str) -> ChatRequest {
                 ContentPart::ImageUrl {
                     image_url: ImageUrlDetail {
                         url: format!("data:image/jpeg;base64,{}", base64_image),
-                        detail: "high".to_string(), // High detail for accurate piece recognition
+                        detail: detail.to_string(),
                     },
                 },
             ],
         }],
         max_tokens: 100,
+        response_format: None,
     }
 }
 
-async fn call_api_with_retry(api_key: &str, request: &ChatRequest) -> Result<String> {
-    let client = Client::builder()
-        .timeout(Duration::from_secs(TIMEOUT_SECS))
-        .build()
-        .context("Failed to create HTTP client")?;
+/// Parses the key list from the raw env values (pool first, then the single key)
+fn parse_api_keys(pool: Option<&str>, single: Option<&str>) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    let candidates = pool.unwrap_or("").split(',').chain(single);
+    for key in candidates.map(str::trim).filter(|k| !k.is_empty()) {
+        if !keys.iter().any(|existing| existing == key) {
+            keys.push(key.to_string());
+        }
+    }
+    keys
+}
+
+/// Returns the key currently in rotation
+fn current_api_key() -> Result<String> {
+    let keys = api_keys();
+    anyhow::ensure!(!keys.is_empty(), "OPENAI_API_KEY environment variable not set");
+    Ok(keys[CURRENT_KEY.load(Ordering::Relaxed) % keys.len()].clone())
+}
+
+/// Advances to the next key after a rate limit. Returns false if there is nothing to rotate to.
+fn rotate_api_key() -> bool {
+    let count = api_keys().len();
+    if count < 2 {
+        return false;
+    }
+    let next = (CURRENT_KEY.fetch_add(1, Ordering::Relaxed) + 1) % count;
+    eprintln!("⚠ Rate limited - switching to API key {}/{}", next + 1, count);
+    true
+}
+
+/// Builds the HTTP client used for all outgoing requests, applying the `--proxy` override if set.
+/// Without an override, reqwest picks up HTTPS_PROXY/ALL_PROXY from the environment.
+pub fn http_client(timeout_secs: u64) -> Result<Client> {
+    let mut builder = Client::builder().timeout(Duration::from_secs(timeout_secs));
+    if let Some(proxy) = PROXY.get() {
+        builder = builder.proxy(proxy.clone());
+    }
+    builder.build().context("Failed to create HTTP client")
+}
+
+/// Returns true if the provider has the credentials it needs
+pub fn provider_ready(provider: Provider) -> bool {
+    match provider {
+        Provider::OpenAi => has_api_key(),
+        other => other.key_var().is_none_or(|var| std::env::var(var).is_ok()),
+    }
+}
+
+/// Returns the key to send to a provider (None for keyless local providers)
+fn provider_api_key(provider: Provider) -> Result<Option<String>> {
+    match provider {
+        Provider::OpenAi => current_api_key().map(Some),
+        other => match other.key_var() {
+            Some(var) => std::env::var(var)
+                .map(Some)
+                .with_context(|| format!("{} environment variable not set", var)),
+            None => Ok(None),
+        },
+    }
+}
+
+/// Returns true if an error means the provider itself is unavailable
+/// (network failure, timeout, or server error) rather than a problem with the request
+fn is_outage(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.downcast_ref::<ApiStatusError>().is_some_and(|e| e.status >= 500)
+            || cause
+                .downcast_ref::<reqwest::Error>()
+                .is_some_and(|e| e.is_connect() || e.is_timeout())
+    })
+}
+
+/// Sends a request to the active provider, failing over along the provider chain
+/// when the provider is down after exhausting its retries
+async fn call_api_with_retry(request: &ChatRequest) -> Result<String> {
+    let client = http_client(TIMEOUT_SECS)?;
+
+    loop {
+        let provider = llm_provider::active();
+        if !provider_ready(provider) {
+            // No key for it (e.g. OPENAI_API_KEY unset with ollama further down the chain)
+            match llm_provider::skip_unready(provider_ready) {
+                Some(next) => eprintln!("⚠ LLM provider {} has no credentials - switching to {}", provider, next),
+                None => anyhow::bail!("{}", missing_credentials(llm_provider::chain())),
+            }
+            continue;
+        }
+        let error = match call_provider_with_retry(&client, provider, request).await {
+            Ok(text) => return Ok(text),
+            // A key that went missing counts like an outage: the next provider may have one
+            Err(e) if is_outage(&e) || !provider_ready(provider) => e,
+            Err(e) => return Err(e),
+        };
+
+        // Skip over providers that aren't configured
+        let next = std::iter::from_fn(llm_provider::fail_over).find(|&p| provider_ready(p));
+        match next {
+            Some(next) => eprintln!("⚠ LLM provider {} unavailable - switching to {}", provider, next),
+            None => return Err(error),
+        }
+    }
+}
+
+async fn call_provider_with_retry(client: &Client, provider: Provider, request: &ChatRequest) -> Result<String> {
+    let request = ChatRequest {
+        model: provider.model(),
+        ..request.clone()
+    };
 
     let mut last_error = None;
 
     for attempt in 1..=MAX_API_RETRIES + 1 {
-        match call_api(&client, api_key, request).await {
+        let api_key = provider_api_key(provider)?;
+        crate::rate_limit::wait(provider, api_key.as_deref()).await?;
+        match call_api(client, provider, api_key.as_deref(), &request).await {
             Ok(fen) => return Ok(fen),
             Err(e) => {
                 eprintln!(
@@ -336,8 +982,16 @@ async fn call_api_with_retry(api_key: &str, request: &ChatRequest) -> Result<Str
                     MAX_API_RETRIES + 1,
                     e
                 );
+                // On a rate limit, retry right away with the next key if one is configured
+                let rate_limited = e
+                    .downcast_ref::<ApiStatusError>()
+                    .is_some_and(|err| err.status == 429);
+                if rate_limited && provider == Provider::OpenAi {
+                    rotate_api_key();
+                }
                 last_error = Some(e);
-                if attempt <= MAX_API_RETRIES {
+                // A rate-limited retry waits as long as the provider asked (in `wait`)
+                if attempt <= MAX_API_RETRIES && !rate_limited {
                     tokio::time::sleep(Duration::from_millis(500)).await;
                 }
             }
@@ -347,137 +1001,92 @@ async fn call_api_with_retry(api_key: &str, request: &ChatRequest) -> Result<Str
     Err(last_error.unwrap())
 }
 
-async fn call_api(client: &Client, api_key: &str, request: &ChatRequest) -> Result<String> {
-    let response = client
-        .post(API_URL)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
+async fn call_api(client: &Client, provider: Provider, api_key: Option<&str>, request: &ChatRequest) -> Result<String> {
+    if provider == Provider::Anthropic {
+        return call_anthropic(client, api_key.unwrap_or_default(), request).await;
+    }
+    let mut builder = client
+        .post(provider.chat_url())
+        .header("Content-Type", "application/json");
+    if let Some(api_key) = api_key {
+        builder = builder.header("Authorization", format!("Bearer {}", api_key));
+    }
+    let response = builder
         .json(request)
         .send()
         .await
-        .context("Failed to send request to OpenAI")?;
+        .with_context(|| format!("Failed to send request to {}", provider))?;
+    crate::rate_limit::record(provider, api_key, response.status().as_u16(), response.headers());
 
     if !response.status().is_success() {
-        let status = response.status();
+        let status = response.status().as_u16();
         let body = response.text().await.unwrap_or_default();
-        anyhow::bail!("OpenAI API error {}: {}", status, body);
+        return Err(ApiStatusError { status, body }.into());
     }
 
     let api_response: ChatResponse = response
         .json()
         .await
-        .context("Failed to parse OpenAI response")?;
+        .with_context(|| format!("Failed to parse {} response", provider))?;
+    if let Some(usage) = &api_response.usage {
+        crate::token_budget::record(usage.total_tokens);
+        crate::session_stats::record_api_call(&request.model, usage.prompt_tokens, usage.completion_tokens);
+    }
 
     let fen = api_response
         .choices
         .first()
         .map(|c| c.message.content.trim().to_string())
-        .ok_or_else(|| anyhow::anyhow!("No response from OpenAI"))?;
+        .ok_or_else(|| anyhow::anyhow!("No response from {}", provider))?;
 
     Ok(fen)
 }
 
-fn validate_fen(fen: &str) -> Result<String> {
-    let board_part = fen.split_whitespace().next().unwrap_or("");
-
-    // Step 1: Validate king count (exactly 1 white king 'K' and 1 black king 'k')
-    // This prevents Tanton engine panics on illegal positions
-    let white_kings = board_part.chars().filter(|&c| c == 'K').count();
-    let black_kings = board_part.chars().filter(|&c| c == 'k').count();
+async fn call_anthropic(client: &Client, api_key: &str, request: &ChatRequest) -> Result<String> {
+    let response = client
+        .post(Provider::Anthropic.chat_url())
+        .header("x-api-key", api_key)
+        .header("anthropic-version", ANTHROPIC_VERSION)
+        .json(&AnthropicRequest::from_chat(request)?)
+        .send()
+        .await
+        .context("Failed to send request to anthropic")?;
+    crate::rate_limit::record(Provider::Anthropic, Some(api_key), response.status().as_u16(), response.headers());
 
-    if white_kings != 1 || black_kings != 1 {
-        anyhow::bail!(
-            "Invalid FEN from LLM: expected exactly 1 king per side, got {} white kings and {} black kings (received: '{}')",
-            white_kings, black_kings, fen
-        );
+    if !response.status().is_success() {
+        let status = response.status().as_u16();
+        let body = response.text().await.unwrap_or_default();
+        return Err(ApiStatusError { status, body }.into());
     }
 
-    // Step 2: Validate pawn count (max 8 per side)
-    // LLM sometimes forgets to remove a pawn from starting square when it moves
-    let white_pawns = board_part.chars().filter(|&c| c == 'P').count();
-    let black_pawns = board_part.chars().filter(|&c| c == 'p').count();
-
-    if white_pawns > 8 {
-        anyhow::bail!(
-            "Invalid FEN from LLM: White has {} pawns (max 8). LLM likely forgot to remove pawn from starting square. (received: '{}')",
-            white_pawns, fen
-        );
+    let api_response: AnthropicResponse = response.json().await.context("Failed to parse anthropic response")?;
+    if let Some(usage) = &api_response.usage {
+        crate::token_budget::record(usage.input_tokens + usage.output_tokens);
+        crate::session_stats::record_api_call(&request.model, usage.input_tokens, usage.output_tokens);
     }
-    if black_pawns > 8 {
-        anyhow::bail!(
-            "Invalid FEN from LLM: Black has {} pawns (max 8). LLM likely forgot to remove pawn from starting square. (received: '{}')",
-            black_pawns, fen
-        );
-    }
-
-    // Step 3: Fix castling rights based on king/rook positions
-    // The LLM always outputs "KQkq" but this can be invalid if pieces have moved
-    let corrected_fen = fix_castling_rights(fen);
-
-    // Step 4: Final syntax validation with shakmaty
-    shakmaty::fen::Fen::from_ascii(corrected_fen.as_bytes())
-        .map_err(|e| anyhow::anyhow!("Invalid FEN syntax from LLM: {} (received: '{}')", e, corrected_fen))?;
-
-    Ok(corrected_fen)
+    api_response
+        .content
+        .into_iter()
+        .find_map(|block| block.text)
+        .map(|text| text.trim().to_string())
+        .ok_or_else(|| anyhow::anyhow!("No response from anthropic"))
 }
 
-/// Fixes castling rights in FEN based on actual king and rook positions.
-/// Castling is only legal if:
-/// - King is on its starting square (e1 for White, e8 for Black)
-/// - Rook is on its starting square (a1/h1 for White, a8/h8 for Black)
-fn fix_castling_rights(fen: &str) -> String {
-    let parts: Vec<&str> = fen.split_whitespace().collect();
-    if parts.len() < 3 {
-        return fen.to_string();
-    }
-
-    let board = parts[0];
-    let turn = parts[1];
-
-    // Parse the board into ranks (rank 8 is first, rank 1 is last)
-    let ranks: Vec<&str> = board.split('/').collect();
-    if ranks.len() != 8 {
-        return fen.to_string();
-    }
-
-    // Helper to expand a rank string (e.g., "r3k2r" stays, "8" → "........")
-    fn expand_rank(rank: &str) -> String {
-        let mut result = String::new();
-        for c in rank.chars() {
-            if let Some(n) = c.to_digit(10) {
-                result.push_str(&".".repeat(n as usize));
-            } else {
-                result.push(c);
-            }
-        }
-        result
-    }
-
-    // Get rank 1 (White's back rank, index 7) and rank 8 (Black's back rank, index 0)
-    let rank1 = expand_rank(ranks[7]); // White's back rank
-    let rank8 = expand_rank(ranks[0]); // Black's back rank
-
-    // Check piece positions (0-indexed: a=0, b=1, ..., h=7)
-    let white_king_e1 = rank1.chars().nth(4) == Some('K');
-    let white_rook_a1 = rank1.chars().nth(0) == Some('R');
-    let white_rook_h1 = rank1.chars().nth(7) == Some('R');
-    let black_king_e8 = rank8.chars().nth(4) == Some('k');
-    let black_rook_a8 = rank8.chars().nth(0) == Some('r');
-    let black_rook_h8 = rank8.chars().nth(7) == Some('r');
-
-    // Build castling rights string
-    let mut castling = String::new();
-    if white_king_e1 && white_rook_h1 { castling.push('K'); }
-    if white_king_e1 && white_rook_a1 { castling.push('Q'); }
-    if black_king_e8 && black_rook_h8 { castling.push('k'); }
-    if black_king_e8 && black_rook_a8 { castling.push('q'); }
+/// " (cycle <ID>)" for log lines, empty outside the live loop
+fn cycle_tag() -> String {
+    crate::capture::current_cycle().map(|cycle| format!(" (cycle {})", cycle)).unwrap_or_default()
+}
 
-    if castling.is_empty() {
-        castling = "-".to_string();
+/// Interprets the HTTP status of a key check request
+fn key_status_from_http(status: u16) -> KeyStatus {
+    match status {
+        200..=299 => KeyStatus::Valid,
+        // Rate limited means the key authenticated fine
+        429 => KeyStatus::Valid,
+        401 => KeyStatus::Invalid("API key is invalid or has been revoked".to_string()),
+        403 => KeyStatus::Invalid("API key lacks permission (check organization/project access)".to_string()),
+        other => KeyStatus::Unverified(format!("OpenAI returned HTTP {}", other)),
     }
-
-    // Rebuild FEN with corrected castling rights
-    format!("{} {} {} - 0 1", board, turn, castling)
 }
 
 // *************** Tests ***************
@@ -490,7 +1099,7 @@ mod tests {
 
     #[test]
     fn test_build_fen_prompt_for_white() {
-        let prompt = build_fen_prompt(PlayerSide::White);
+        let prompt = build_fen_prompt(PlayerSide::White, false);
         assert!(prompt.contains("FEN"));
         assert!(prompt.contains("White pieces are at the bottom"));
         assert!(prompt.contains("w KQkq"));
@@ -498,10 +1107,21 @@ mod tests {
 
     #[test]
     fn test_build_fen_prompt_for_black() {
-        let prompt = build_fen_prompt(PlayerSide::Black);
+        let prompt = build_fen_prompt(PlayerSide::Black, false);
         assert!(prompt.contains("FEN"));
         assert!(prompt.contains("Black pieces are at the bottom"));
         assert!(prompt.contains("b KQkq"));
+        assert!(prompt.contains("did NOT make the last move"));
+    }
+
+    #[test]
+    fn test_build_fen_prompt_for_photo() {
+        let prompt = build_fen_prompt(PlayerSide::Black, true);
+        assert!(prompt.starts_with("This is a photo of a physical chessboard"));
+        assert!(prompt.contains("Black pieces started on the side of the board nearest the camera"));
+        assert!(prompt.contains("Ignore everything off the board"));
+        assert!(prompt.contains("b KQkq"));
+        assert!(prompt.contains("Append: b KQkq"));
     }
 
     // ===== Direct Move Analysis Prompt Tests =====
@@ -513,9 +1133,9 @@ mod tests {
         assert!(prompt.contains("White pieces are at the bottom"));
         assert!(prompt.contains("Black pieces are at the top"));
         assert!(prompt.contains("It is White's turn"));
-        assert!(prompt.contains("MOVE:"));
-        assert!(prompt.contains("EVAL:"));
-        assert!(prompt.contains("WHY:"));
+        assert!(prompt.contains("\"best_move\""));
+        assert!(prompt.contains("\"evaluation\": one of: winning, clear advantage"));
+        assert!(prompt.contains("\"reasoning\""));
         assert!(prompt.contains("MUST be different")); // Prevents "B4 to B4" errors
     }
 
@@ -532,7 +1152,7 @@ mod tests {
 
     #[test]
     fn test_parse_move_response_valid() {
-        let response = "MOVE: E2 to E4\nEVAL: equal\nWHY: Controls the center.";
+        let response = r#"{"best_move": "E2 to E4", "evaluation": "equal", "reasoning": "Controls the center."}"#;
         let result = parse_move_response(response).unwrap();
         assert_eq!(result.best_move, "E2 to E4");
         assert_eq!(result.evaluation, "equal");
@@ -540,8 +1160,8 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_move_response_with_extra_whitespace() {
-        let response = "  MOVE:   Knight F3 to G5  \n  EVAL:  slight advantage \n WHY:  Forks the queen and rook.  ";
+    fn test_parse_move_response_in_code_fence() {
+        let response = "```json\n{\"best_move\": \"Knight F3 to G5\", \"evaluation\": \"slight advantage\", \"reasoning\": \"Forks the queen and rook.\"}\n```";
         let result = parse_move_response(response).unwrap();
         assert_eq!(result.best_move, "Knight F3 to G5");
         assert_eq!(result.evaluation, "slight advantage");
@@ -550,8 +1170,7 @@ mod tests {
 
     #[test]
     fn test_parse_move_response_missing_optional_fields() {
-        let response = "MOVE: Castle kingside";
-        let result = parse_move_response(response).unwrap();
+        let result = parse_move_response(r#"{"best_move": "Castle kingside"}"#).unwrap();
         assert_eq!(result.best_move, "Castle kingside");
         assert_eq!(result.evaluation, "unknown");
         assert_eq!(result.reasoning, "No explanation provided");
@@ -559,89 +1178,133 @@ mod tests {
 
     #[test]
     fn test_parse_move_response_missing_move_fails() {
-        let response = "EVAL: winning\nWHY: Because reasons.";
-        let result = parse_move_response(response);
-        assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("missing MOVE"));
+        let result = parse_move_response(r#"{"evaluation": "winning", "reasoning": "Because reasons."}"#);
+        assert!(result.unwrap_err().to_string().contains("expected JSON"));
+        assert!(parse_move_response("MOVE: E2 to E4").is_err());
     }
 
     #[test]
-    fn test_validate_fen_accepts_valid_white() {
-        let valid = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
-        let result = validate_fen(valid);
-        assert!(result.is_ok());
-        // Starting position should keep all castling rights
-        assert_eq!(result.unwrap(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    fn test_parse_fen_response() {
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        assert_eq!(parse_fen_response(&format!(r#"{{"fen": " {} "}}"#, fen)), fen);
+        assert_eq!(parse_fen_response(&format!("```json\n{{\"fen\": \"{}\"}}\n```", fen)), fen);
+        // A bare FEN from a provider without structured outputs
+        assert_eq!(parse_fen_response(&format!("{}\n", fen)), fen);
     }
 
     #[test]
-    fn test_validate_fen_accepts_valid_black() {
-        let valid = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
-        let result = validate_fen(valid);
-        assert!(result.is_ok());
-        // Kings and rooks still on starting squares, keep all castling rights
-        assert_eq!(result.unwrap(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+    fn test_structured_requests() {
+        let request = build_fen_request("QUJD", "Read the board", "low").with_schema("fen", FenAnswer::schema());
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["response_format"]["type"], "json_schema");
+        assert_eq!(json["response_format"]["json_schema"]["strict"], true);
+        assert_eq!(json["response_format"]["json_schema"]["schema"]["required"][0], "fen");
+        let schema = MoveRecommendation::schema();
+        assert_eq!(schema["properties"]["evaluation"]["enum"].as_array().unwrap().len(), EVALUATIONS.len());
+        // Plain requests (quadrants, text) carry no response format
+        let plain = serde_json::to_value(build_fen_request("QUJD", "Read the board", "low")).unwrap();
+        assert!(plain.get("response_format").is_none());
     }
 
     #[test]
-    fn test_validate_fen_rejects_invalid() {
-        let invalid = "not a fen string";
-        assert!(validate_fen(invalid).is_err());
+    fn test_key_status_from_http() {
+        assert_eq!(key_status_from_http(200), KeyStatus::Valid);
+        assert_eq!(key_status_from_http(429), KeyStatus::Valid);
+        assert!(matches!(key_status_from_http(401), KeyStatus::Invalid(_)));
+        assert!(matches!(key_status_from_http(403), KeyStatus::Invalid(_)));
+        assert!(matches!(key_status_from_http(503), KeyStatus::Unverified(_)));
     }
 
     #[test]
-    fn test_validate_fen_rejects_too_many_white_pawns() {
-        // 9 white pawns (pawn on e4 + all 8 on rank 2) - common LLM error
-        let invalid = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1";
-        let result = validate_fen(invalid);
-        assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("9 pawns"));
+    fn test_anthropic_request_inlines_image() {
+        let request = AnthropicRequest::from_chat(&build_fen_request("QUJD", "Read the board", "low")).unwrap();
+        let json = serde_json::to_value(&request).unwrap();
+        let content = &json["messages"][0]["content"];
+        let image = content.as_array().unwrap().iter().find(|part| part["type"] == "image").unwrap();
+        assert_eq!(image["source"]["type"], "base64");
+        assert_eq!(image["source"]["media_type"], "image/jpeg");
+        assert_eq!(image["source"]["data"], "QUJD");
+        assert!(content.as_array().unwrap().iter().any(|part| part["type"] == "text" && part["text"] == "Read the board"));
     }
 
     #[test]
-    fn test_validate_fen_rejects_too_many_black_pawns() {
-        // 9 black pawns
-        let invalid = "rnbqkbnr/pppppppp/4p3/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
-        let result = validate_fen(invalid);
-        assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("9 pawns"));
+    fn test_parse_api_keys_combines_and_dedups() {
+        let keys = parse_api_keys(Some("sk-a, sk-b,,sk-a"), Some("sk-c"));
+        assert_eq!(keys, vec!["sk-a", "sk-b", "sk-c"]);
     }
 
     #[test]
-    fn test_fix_castling_removes_rights_when_king_moved() {
-        // Black king castled (on g8), but FEN claims KQkq - should fix to KQ only
-        let fen_with_bad_castling = "r4rk1/pp1p1ppp/1n6/2p5/3P2N1/3P1N2/PPPBP1PP/R2QKB1R b KQkq - 0 1";
-        let result = validate_fen(fen_with_bad_castling);
-        assert!(result.is_ok());
-        let corrected = result.unwrap();
-        // Black king not on e8, so no black castling rights
-        // White king on e1 with rooks on a1 and h1, so KQ
-        assert!(corrected.contains(" KQ ") || corrected.contains(" - "));
-        assert!(!corrected.contains("kq"));
+    fn test_parse_api_keys_single_only() {
+        assert_eq!(parse_api_keys(None, Some(" sk-x ")), vec!["sk-x"]);
+        assert!(parse_api_keys(None, None).is_empty());
+        assert!(parse_api_keys(Some(" , "), None).is_empty());
     }
 
     #[test]
-    fn test_fix_castling_no_rights_when_both_kings_moved() {
-        // Both kings have moved - should have no castling rights
-        let fen = "r4rk1/pppppppp/8/8/8/8/PPPPPPPP/R3K2R b KQkq - 0 1";
-        let result = validate_fen(fen);
-        assert!(result.is_ok());
-        let corrected = result.unwrap();
-        // Black king on g8 (not e8), White king on e1 with rooks
-        // White should have KQ, Black should have none
-        assert!(corrected.contains(" KQ "));
+    fn test_image_detail_adaptive_escalates() {
+        assert_eq!(ImageDetail::Adaptive.api_value(1), "low");
+        assert_eq!(ImageDetail::Adaptive.api_value(2), "high");
+        assert_eq!(ImageDetail::High.api_value(1), "high");
+        assert_eq!(ImageDetail::Low.api_value(3), "low");
     }
 
     #[test]
-    fn test_fix_castling_keeps_partial_rights() {
-        // White has only kingside rook, Black has both
-        let fen = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/4K2R w KQkq - 0 1";
-        let result = validate_fen(fen);
-        assert!(result.is_ok());
-        let corrected = result.unwrap();
-        // White: King on e1, only h1 rook → K only
-        // Black: King on e8, both rooks → kq
-        assert!(corrected.contains(" Kkq "));
+    fn test_image_detail_from_name() {
+        assert_eq!(ImageDetail::from_name("auto"), Some(ImageDetail::Auto));
+        assert_eq!(ImageDetail::from_name("adaptive"), Some(ImageDetail::Adaptive));
+        assert_eq!(ImageDetail::from_name("medium"), None);
+        assert_eq!(ImageDetail::default(), ImageDetail::High);
+    }
+
+    // ===== Quadrant Fallback Tests =====
+
+    #[test]
+    fn test_build_quadrant_prompt_names_squares() {
+        let prompt = build_quadrant_prompt(0, 0);
+        assert!(prompt.contains("files a-d and ranks 8-5"));
+        assert!(prompt.contains("top-left square is a8"));
+        let prompt = build_quadrant_prompt(1, 1);
+        assert!(prompt.contains("files e-h and ranks 4-1"));
+        assert!(prompt.contains("bottom-right square is h1"));
+    }
+
+    #[test]
+    fn test_parse_quadrant_response_valid() {
+        let grid = parse_quadrant_response("```\nr.b.\np p . .\n..n.\n....\n```").unwrap();
+        assert_eq!(grid[0], ['r', '1', 'b', '1']);
+        assert_eq!(grid[1], ['p', 'p', '1', '1']);
+        assert_eq!(grid[3], ['1'; 4]);
+    }
+
+    #[test]
+    fn test_parse_quadrant_response_rejects_short_answer() {
+        assert!(parse_quadrant_response("r.b.\npp..").is_err());
+        assert!(parse_quadrant_response("I cannot see the board").is_err());
+    }
+
+    #[test]
+    fn test_is_outage_classification() {
+        let server_error: anyhow::Error = ApiStatusError { status: 503, body: String::new() }.into();
+        let rate_limit: anyhow::Error = ApiStatusError { status: 429, body: String::new() }.into();
+        let bad_request: anyhow::Error = ApiStatusError { status: 400, body: String::new() }.into();
+        assert!(is_outage(&server_error));
+        assert!(is_outage(&server_error.context("wrapped")));
+        assert!(!is_outage(&rate_limit));
+        assert!(!is_outage(&bad_request));
+    }
+
+    #[test]
+    fn test_set_proxy_rejects_malformed_url() {
+        assert!(set_proxy("::not a proxy::").is_err());
+    }
+
+    #[test]
+    fn test_missing_credentials_names_the_chains_keys() {
+        assert_eq!(missing_credentials(&[Provider::OpenAi]), "No LLM credentials: set OPENAI_API_KEY");
+        assert_eq!(
+            missing_credentials(&[Provider::Anthropic, Provider::Gemini]),
+            "No LLM credentials: set ANTHROPIC_API_KEY or GEMINI_API_KEY"
+        );
     }
 
     #[test]
@@ -656,7 +1319,8 @@ mod tests {
     #[ignore = "requires OPENAI_API_KEY"]
     async fn test_real_fen_ocr_as_white() {
         // Run with: OPENAI_API_KEY=sk-... cargo test test_real_fen_ocr_as_white -- --ignored
-        let result = board_to_fen("screenshots/current_board.jpg", PlayerSide::White).await;
+        let image = crate::ocr::load_image("screenshots/debug_full_screen.jpg").unwrap();
+        let result = board_to_fen(&image, PlayerSide::White).await;
         println!("FEN Result: {:?}", result);
         assert!(result.is_ok());
     }
@@ -665,7 +1329,8 @@ mod tests {
     #[ignore = "requires OPENAI_API_KEY"]
     async fn test_real_fen_ocr_as_black() {
         // Run with: OPENAI_API_KEY=sk-... cargo test test_real_fen_ocr_as_black -- --ignored
-        let result = board_to_fen("screenshots/current_board.jpg", PlayerSide::Black).await;
+        let image = crate::ocr::load_image("screenshots/debug_full_screen.jpg").unwrap();
+        let result = board_to_fen(&image, PlayerSide::Black).await;
         println!("FEN Result: {:?}", result);
         assert!(result.is_ok());
     }
@@ -674,7 +1339,8 @@ mod tests {
     #[ignore = "requires OPENAI_API_KEY"]
     async fn test_real_analyze_board_as_white() {
         // Run with: OPENAI_API_KEY=sk-... cargo test test_real_analyze_board_as_white -- --ignored
-        let result = analyze_board("screenshots/current_board.jpg", PlayerSide::White).await;
+        let image = crate::ocr::load_image("screenshots/debug_full_screen.jpg").unwrap();
+        let result = analyze_board(&image, PlayerSide::White).await;
         println!("Move recommendation: {:?}", result);
         assert!(result.is_ok());
         let rec = result.unwrap();
@@ -685,7 +1351,8 @@ mod tests {
     #[ignore = "requires OPENAI_API_KEY"]
     async fn test_real_analyze_board_as_black() {
         // Run with: OPENAI_API_KEY=sk-... cargo test test_real_analyze_board_as_black -- --ignored
-        let result = analyze_board("screenshots/current_board.jpg", PlayerSide::Black).await;
+        let image = crate::ocr::load_image("screenshots/debug_full_screen.jpg").unwrap();
+        let result = analyze_board(&image, PlayerSide::Black).await;
         println!("Move recommendation: {:?}", result);
         assert!(result.is_ok());
         let rec = result.unwrap();
@@ -1,120 +1,221 @@
 //! Native OCR module - template-based piece recognition
-//! Pure custom OCR: loads full screenshot PNG, detects/crops board region, then recognizes pieces to FEN.
+//! Pure custom OCR: takes the full screenshot, detects/crops board region, then recognizes pieces to FEN.
 //! Uses template matching via imageproc (no external OCR libs for stealth/purity).
 //! Detects board via edges/contours, splits to 64 squares, classifies pieces/empty.
 //! Outputs validated FEN string via shakmaty.
 //! Latency target: 40-80ms (includes detection; parallelize with rayon).
-//! Requires piece templates in templates/{site}/ directory.
+//! Requires piece templates in templates/{site}/ directory (`--calibrate` cuts them from
+//! the site's own board, along with the thresholds in `Thresholds`).
+//!
+//! Sites with several piece sets keep one template pack per set in
+//! `templates/{site}/{theme}/` (e.g. `templates/lichess/merida/`), chosen with `--theme`.
+//! With `--theme auto`, or when the site has packs but no templates of its own, every pack
+//! is scored on the first frame and the best match is used from then on.
+//!
+//! Matching is incremental: the squares of the previous frame are kept, and only squares
+//! whose pixels changed since are matched again; the others keep their previous reading.
+//! Between two moves that is usually just the two squares of the move (plus highlights),
+//! instead of all 64 on every cycle.
+//!
+//! Every square is read with a confidence from 0 to 1 (how clearly its best template beats
+//! the threshold and the runner-up piece), returned alongside the FEN in a `Recognition`;
+//! `--ocr hybrid` (see `ocr_hybrid`) hands frames with a doubtful square to the LLM.
 
 use anyhow::{Context, Result};
 use image::{DynamicImage, GenericImageView, GrayImage, imageops, ImageReader, RgbaImage};
-use imageproc::edges::canny;
 use imageproc::template_matching::{match_template, MatchTemplateMethod};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
 use crate::PlayerSide;
 
-/// Detects the chessboard in the full screenshot and crops/resizes it to a standard board image.
-/// Uses imageproc for auto-detection via edge analysis.
-/// Returns DynamicImage ready for grid splitting/OCR.
-pub fn screenshot_to_board(image_path: &str) -> Result<DynamicImage> {
-    let img = ImageReader::open(image_path)
-        .context("Failed to open screenshot for board detection")?
-        .decode()
-        .context("Failed to decode screenshot")?;
-
-    // Dynamic detection: Find board region using imageproc edges
-    fn find_board_region(img: &DynamicImage) -> Result<(u32, u32, u32, u32)> {
-        // Step 1: Edge detection (full screenshot)
-        let gray: GrayImage = img.to_luma8();
-        let edges: GrayImage = canny(&gray, 50.0, 150.0);
-
-        // Step 2: Generate candidate regions
-        let (width, height) = img.dimensions();
-        let candidates = generate_candidate_regions(width, height);
-
-        // Step 3: Score each candidate by edge density × size factor
-        // We multiply by size to prefer larger boards (avoids selecting partial boards)
-        let mut best_candidate: Option<(u32, u32, u32, u32)> = None;
-        let mut best_score = 0.0f32;
-        let mut best_density = 0.0f32;
-
-        for (x, y, size) in candidates {
-            let density = calculate_edge_density(&edges, x, y, size);
-            // Score = density × size, so larger boards with decent density win
-            let score = density * size as f32;
-
-            if score > best_score {
-                best_score = score;
-                best_density = density;
-                best_candidate = Some((x, y, size, size));
-            }
-        }
+/// Last board read by template matching (512×512, as shown on screen), kept for corrections
+static LAST_BOARD: Mutex<Option<DynamicImage>> = Mutex::new(None);
+
+/// Calibrated thresholds for the selected site (defaults when unset)
+static THRESHOLDS: OnceLock<Thresholds> = OnceLock::new();
+
+/// Template pack in use (`--theme`, or detected); None for the site's own templates
+static THEME: Mutex<Option<String>> = Mutex::new(None);
+
+/// `--theme auto`: pick the pack on the first frame
+static AUTO_THEME: AtomicBool = AtomicBool::new(false);
+
+/// Squares of the last board matched, for incremental matching
+static PREVIOUS: Mutex<Option<PreviousFrame>> = Mutex::new(None);
+
+/// Gray level difference up to which a pixel counts as unchanged (resampling noise)
+const PIXEL_TOLERANCE: u8 = 16;
+
+/// Share of a square's pixels that must change for it to be matched again
+const CHANGED_SHARE: f32 = 0.005;
+
+/// Newest correction samples (see `correction`) used as extra templates per piece
+const MAX_CORRECTION_SAMPLES: usize = 4;
+
+/// Template files per piece: {Piece}{Color}.png where Color is W (white) or B (black).
+/// K.png/k.png won't work on macOS (case-insensitive filesystem), hence the suffix.
+pub const PIECE_FILES: [(char, &str); 12] = [
+    ('K', "KW"), // White King
+    ('Q', "QW"), // White Queen
+    ('R', "RW"), // White Rook
+    ('B', "BW"), // White Bishop
+    ('N', "NW"), // White Knight
+    ('P', "PW"), // White Pawn
+    ('k', "KB"), // Black King
+    ('q', "QB"), // Black Queen
+    ('r', "RB"), // Black Rook
+    ('b', "BB"), // Black Bishop
+    ('n', "NB"), // Black Knight
+    ('p', "PB"), // Black Pawn
+];
+
+/// Square classification thresholds; tuned to the default chess.com theme unless calibrated
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Thresholds {
+    /// Grayscale variance below which a square is empty (uniform color)
+    pub empty_variance: f32,
+    /// Normalized squared-error score a piece template must beat
+    pub match_score: f32,
+}
 
-        // Step 4: Validate best candidate
-        const MIN_EDGE_DENSITY: f32 = 0.01; // 1% of pixels should be edges
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds { empty_variance: 100.0, match_score: 0.3 }
+    }
+}
 
-        if best_density < MIN_EDGE_DENSITY {
-            anyhow::bail!(
-                "No board detected: best edge density {:.3}% < {:.1}% threshold",
-                best_density * 100.0,
-                MIN_EDGE_DENSITY * 100.0
-            );
-        }
+/// Uses calibrated thresholds for this run (first call wins)
+pub fn set_thresholds(thresholds: Thresholds) {
+    let _ = THRESHOLDS.set(thresholds);
+}
 
-        best_candidate.ok_or_else(|| anyhow::anyhow!("No candidate regions found"))
+/// Selects the template pack: a theme name, or "auto" to detect it on the first frame
+pub fn set_theme(theme: &str) {
+    if theme == "auto" {
+        AUTO_THEME.store(true, Ordering::Relaxed);
+    } else if let Ok(mut current) = THEME.lock() {
+        *current = Some(theme.to_string());
     }
+}
 
-    // Helper: generate search regions
-    // Creates a grid of candidate regions to search across the screenshot.
-    // Returns Vec of (x, y, size) tuples representing potential board locations.
-    fn generate_candidate_regions(width: u32, height: u32) -> Vec<(u32, u32, u32)> {
-        let mut candidates = Vec::new();
+/// The pack in use, if one is selected or detected
+pub fn theme() -> Option<String> {
+    THEME.lock().ok()?.clone()
+}
 
-        // Calculate reasonable board sizes to search for
-        // Minimum 300px to avoid selecting partial boards on downsampled images
-        let min_size = 300u32;
-        let max_size = if width < height { width } else { height };
-        let size_step = 50u32; // Finer granularity for better detection
+/// Directory of the templates in use for `site`
+pub fn template_dir(site: &str) -> String {
+    match theme() {
+        Some(theme) => format!("templates/{}/{}", site, theme),
+        None => format!("templates/{}", site),
+    }
+}
 
-        // Grid search: Try different positions and sizes
-        for size in (min_size..=max_size).step_by(size_step as usize) {
-            let step = size / 4; // Overlap regions by 75% for better coverage
+/// Key of the templates in use under `[calibration]`: `site` or `site/theme`
+pub fn calibration_key(site: &str) -> String {
+    match theme() {
+        Some(theme) => format!("{}/{}", site, theme),
+        None => site.to_string(),
+    }
+}
 
-            let mut y = 0;
-            while y + size <= height {
-                let mut x = 0;
-                while x + size <= width {
-                    candidates.push((x, y, size));
-                    x += step;
-                }
-                y += step;
-            }
-        }
+/// Complete template packs under templates/{site}/, sorted by name
+pub fn themes(site: &str) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(format!("templates/{}", site)) else {
+        return Vec::new();
+    };
+    let mut themes: Vec<String> = entries
+        .flatten()
+        .filter(|entry| has_templates(&entry.path()))
+        .filter_map(|entry| entry.file_name().to_str().map(String::from))
+        .collect();
+    themes.sort();
+    themes
+}
+
+fn has_templates(dir: &std::path::Path) -> bool {
+    PIECE_FILES.iter().all(|(_, name)| dir.join(format!("{}.png", name)).exists())
+}
+
+/// Detects the pack on the first frame when asked to (or when the site only has packs)
+fn resolve_theme(site: &str, squares: &[Vec<GrayImage>], thresholds: Thresholds) -> Result<()> {
+    if theme().is_some() {
+        return Ok(());
+    }
+    let own = has_templates(std::path::Path::new(&format!("templates/{}", site)));
+    if own && !AUTO_THEME.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let themes = themes(site);
+    if themes.is_empty() {
+        anyhow::ensure!(own, "No template packs in templates/{}/ (run --calibrate --theme <name>)", site);
+        return Ok(());
+    }
+    let mut packs = Vec::with_capacity(themes.len());
+    for theme in themes {
+        let templates = load_templates_from(&format!("templates/{}/{}", site, theme))?;
+        packs.push((theme, templates.pieces));
+    }
+    let theme = best_pack(squares, &packs, thresholds).context("No pieces on the board to detect the piece set from")?;
+    println!("Piece set detected: {}/{}", site, theme);
+    if let Ok(mut current) = THEME.lock() {
+        *current = Some(theme.clone());
+    }
+    // Thresholds calibrated for this pack, unless the site's were already set
+    if let Ok(config) = crate::config::load()
+        && let Some(thresholds) = config.calibration.get(&format!("{}/{}", site, theme))
+    {
+        set_thresholds(*thresholds);
+    }
+    Ok(())
+}
 
-        candidates
+/// Pack whose templates fit the occupied squares best (lowest mean match score)
+fn best_pack(squares: &[Vec<GrayImage>], packs: &[(String, HashMap<char, Vec<GrayImage>>)], thresholds: Thresholds) -> Option<String> {
+    let occupied: Vec<&GrayImage> =
+        squares.iter().flatten().filter(|square| square_variance(square) >= thresholds.empty_variance).collect();
+    if occupied.is_empty() {
+        return None;
     }
+    packs
+        .iter()
+        .map(|(theme, pieces)| {
+            let total: f32 = occupied
+                .iter()
+                .map(|square| pieces.values().flatten().map(|t| match_score(square, t)).fold(f32::MAX, f32::min))
+                .sum();
+            (theme, total / occupied.len() as f32)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(theme, _)| theme.clone())
+}
 
-    // Helper: calculate edge density in region
-    // Counts what percentage of pixels in a region are edges (bright pixels in edge map).
-    // Chessboards should have high edge density due to grid lines and piece shapes.
-    fn calculate_edge_density(edges: &GrayImage, x: u32, y: u32, size: u32) -> f32 {
-        let mut edge_count = 0usize;
-        let edge_threshold = 128u8; // Pixel brightness > 128 = edge detected
+/// The board image behind the latest native OCR result, if any
+pub fn last_board() -> Option<DynamicImage> {
+    LAST_BOARD.lock().ok()?.clone()
+}
 
-        for dy in 0..size {
-            for dx in 0..size {
-                if let Some(pixel) = edges.get_pixel_checked(x + dx, y + dy) {
-                    if pixel[0] > edge_threshold {
-                        edge_count += 1;
-                    }
-                }
-            }
-        }
+/// Finds the board in a full screenshot; returns (x, y, width, height) in screenshot pixels
+///
+/// Edge detection first, then the fallbacks in `board_detect` (color grid, coordinate labels,
+/// the cached region, `--fallback-region`).
+pub fn locate_board(img: &DynamicImage) -> Result<(u32, u32, u32, u32)> {
+    crate::board_detect::locate(img).map(|(region, _)| region)
+}
 
-        edge_count as f32 / (size * size) as f32
-    }
+/// Detects the chessboard in the full screenshot and crops/resizes it to a standard board image.
+/// Uses imageproc for auto-detection via edge analysis.
+/// Returns DynamicImage ready for grid splitting/OCR.
+pub fn screenshot_to_board(img: &DynamicImage) -> Result<DynamicImage> {
+    screenshot_to_board_with_bounds(img).map(|(board, _)| board)
+}
 
-    let bounds = find_board_region(&img)
+/// `screenshot_to_board`, also returning where the board was found (x, y, width, height)
+pub fn screenshot_to_board_with_bounds(img: &DynamicImage) -> Result<(DynamicImage, (u32, u32, u32, u32))> {
+    let bounds = locate_board(img)
         .context("Failed to detect board region in screenshot")?;
 
     let (crop_x, crop_y, crop_w, crop_h) = bounds;
@@ -147,53 +248,122 @@ pub fn screenshot_to_board(image_path: &str) -> Result<DynamicImage> {
         let _ = board_img.save("screenshots/debug_cropped_board.png");
     }
 
-    Ok(DynamicImage::ImageRgba8(board_img))
+    Ok((DynamicImage::ImageRgba8(board_img), bounds))
+}
+
+/// A matched board: its squares and how they were read
+struct PreviousFrame {
+    /// Template directory the squares were matched with
+    templates: String,
+    squares: Vec<Vec<GrayImage>>,
+    board: [[char; 8]; 8],
+    confidence: SquareConfidence,
+}
+
+/// Confidence of each square from 0 to 1, rank 8 first, files a to h
+pub type SquareConfidence = [[f32; 8]; 8];
+
+/// A board read by template matching: the FEN and how sure each square's reading is
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recognition {
+    pub fen: String,
+    pub confidence: SquareConfidence,
+}
+
+impl Recognition {
+    /// Confidence of the whole board: that of its most doubtful square, since a single
+    /// misread square makes the FEN wrong
+    pub fn overall(&self) -> f32 {
+        self.confidence.iter().flatten().copied().fold(1.0, f32::min)
+    }
+
+    /// The most doubtful square ("e4") and its confidence
+    pub fn weakest(&self) -> (String, f32) {
+        let mut weakest = (0, 0, f32::MAX);
+        for (rank, row) in self.confidence.iter().enumerate() {
+            for (file, &confidence) in row.iter().enumerate() {
+                if confidence < weakest.2 {
+                    weakest = (rank, file, confidence);
+                }
+            }
+        }
+        let (rank, file, confidence) = weakest;
+        (format!("{}{}", (b'a' + file as u8) as char, 8 - rank), confidence)
+    }
 }
 
 /// Piece template storage for template matching
 struct PieceTemplates {
-    pieces: HashMap<char, GrayImage>, // 'K' -> white king template, etc.
+    pieces: HashMap<char, Vec<GrayImage>>, // 'K' -> white king template(s), etc.
 }
 
-/// Loads piece templates from templates/{site}/ directory
-/// Template naming: {Piece}{Color}.png where Color is W (white) or B (black)
-/// e.g., KW.png = White King, kb.png would conflict with KB.png on case-insensitive filesystems
+/// Loads piece templates from the site's template directory (naming as in `PIECE_FILES`).
+/// Templates include the square's background, so an optional `{name}-alt.png` holds the
+/// piece on the other square color (written by `--calibrate`).
+///
+/// Squares the user corrected (`corrections/{name}_{time}.png`, see `correction`) are added
+/// as more variants of their piece, so a misread piece is read right from then on. Samples
+/// of empty squares go under '1': a square matching one of them best is read as empty.
 fn load_templates(site: &str) -> Result<PieceTemplates> {
+    load_templates_from(&template_dir(site))
+}
+
+fn load_templates_from(dir: &str) -> Result<PieceTemplates> {
     let mut pieces = HashMap::new();
 
-    // Map piece chars to filenames: K.png/k.png won't work on macOS (case-insensitive)
-    // Use {Piece}{Color}.png convention: KW.png (King White), KB.png (King Black), etc.
-    let piece_files: [(char, &str); 12] = [
-        ('K', "KW"), // White King
-        ('Q', "QW"), // White Queen
-        ('R', "RW"), // White Rook
-        ('B', "BW"), // White Bishop
-        ('N', "NW"), // White Knight
-        ('P', "PW"), // White Pawn
-        ('k', "KB"), // Black King
-        ('q', "QB"), // Black Queen
-        ('r', "RB"), // Black Rook
-        ('b', "BB"), // Black Bishop
-        ('n', "NB"), // Black Knight
-        ('p', "PB"), // Black Pawn
-    ];
-
-    for (piece_char, filename) in piece_files {
-        let path = format!("templates/{site}/{filename}.png");
-        let template = ImageReader::open(&path)
-            .with_context(|| format!("Failed to open template: {}", path))?
-            .decode()
-            .with_context(|| format!("Failed to decode template: {}", path))?;
-
-        pieces.insert(piece_char, template.to_luma8());
+    for (piece_char, filename) in PIECE_FILES {
+        let path = format!("{dir}/{filename}.png");
+        let mut variants = vec![load_template(&path)?];
+        let alt = format!("{dir}/{filename}-alt.png");
+        if std::path::Path::new(&alt).exists() {
+            variants.push(load_template(&alt)?);
+        }
+        variants.extend(correction_samples(dir, filename)?);
+        pieces.insert(piece_char, variants);
+    }
+    let empty = correction_samples(dir, "empty")?;
+    if !empty.is_empty() {
+        pieces.insert('1', empty);
     }
 
     Ok(PieceTemplates { pieces })
 }
 
+/// The newest correction samples labeled `label` in `{dir}/corrections`
+fn correction_samples(dir: &str, label: &str) -> Result<Vec<GrayImage>> {
+    let Ok(entries) = std::fs::read_dir(format!("{dir}/corrections")) else {
+        return Ok(Vec::new());
+    };
+    let prefix = format!("{label}_");
+    let mut paths: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.starts_with(&prefix) && name.ends_with(".png"))
+        .map(|name| format!("{dir}/corrections/{name}"))
+        .collect();
+    // Named with a millisecond timestamp, so the newest sort last
+    paths.sort();
+    paths.iter().rev().take(MAX_CORRECTION_SAMPLES).map(|path| load_template(path)).collect()
+}
+
+/// Drops the squares kept for incremental matching, so the next board is matched in full
+/// (after a correction, to read the corrected square with its new sample)
+pub fn forget_previous() {
+    if let Ok(mut previous) = PREVIOUS.lock() {
+        *previous = None;
+    }
+}
+
+fn load_template(path: &str) -> Result<GrayImage> {
+    let template = ImageReader::open(path)
+        .with_context(|| format!("Failed to open template: {}", path))?
+        .decode()
+        .with_context(|| format!("Failed to decode template: {}", path))?;
+    Ok(template.to_luma8())
+}
+
 /// Splits board into 8x8 grid of grayscale squares for template matching
 /// Returns 8 rows (ranks 8→1 top to bottom) × 8 columns (files a→h left to right)
-fn split_into_squares(board: &RgbaImage) -> Vec<Vec<GrayImage>> {
+pub fn split_into_squares(board: &RgbaImage) -> Vec<Vec<GrayImage>> {
     let mut squares = Vec::with_capacity(8);
 
     // Convert to grayscale once for efficiency
@@ -212,61 +382,81 @@ fn split_into_squares(board: &RgbaImage) -> Vec<Vec<GrayImage>> {
     squares
 }
 
-/// Matches a single square against all piece templates
-/// Returns: 'K', 'Q', 'R', etc. for pieces, or '1' for empty square
-fn match_square(square: &GrayImage, templates: &PieceTemplates) -> char {
-    // Step 1: Check if square is empty via variance analysis
-    // Low variance = uniform color = no piece present
+/// Grayscale variance of a square; low variance = uniform color = no piece present
+pub fn square_variance(square: &GrayImage) -> f32 {
     let pixels: Vec<f32> = square.pixels().map(|p| p[0] as f32).collect();
     let mean = pixels.iter().sum::<f32>() / pixels.len() as f32;
-    let variance = pixels
+    pixels
         .iter()
         .map(|&p| (p - mean).powi(2))
         .sum::<f32>()
-        / pixels.len() as f32;
+        / pixels.len() as f32
+}
 
-    const EMPTY_VARIANCE_THRESHOLD: f32 = 100.0;
-    if variance < EMPTY_VARIANCE_THRESHOLD {
-        return '1';
-    }
+/// Sum of Squared Differences (Normalized) between a 64×64 square and a piece template.
+/// Lower score = better match (0.0 = perfect match)
+pub fn match_score(square: &GrayImage, template: &GrayImage) -> f32 {
+    // Resize template to match square size (64x64) if needed
+    let template_resized = if template.dimensions() != (64, 64) {
+        imageops::resize(template, 64, 64, imageops::FilterType::Lanczos3)
+    } else {
+        template.clone()
+    };
 
-    // Step 2: Template matching using Sum of Squared Differences (Normalized)
-    // Lower score = better match (0.0 = perfect match)
-    let mut best_match: char = '1';
-    let mut best_score: f32 = f32::MAX;
+    // match_template returns a score image; for same-size images, it's 1x1
+    let result = match_template(
+        square,
+        &template_resized,
+        MatchTemplateMethod::SumOfSquaredErrorsNormalized,
+    );
+    result.get_pixel(0, 0)[0]
+}
 
-    // Threshold: if no template scores below this, consider square empty
-    const MATCH_THRESHOLD: f32 = 0.3;
-
-    for (&piece_char, template) in &templates.pieces {
-        // Resize template to match square size (64x64) if needed
-        let template_resized = if template.dimensions() != (64, 64) {
-            imageops::resize(template, 64, 64, imageops::FilterType::Lanczos3)
-        } else {
-            template.clone()
-        };
-
-        // match_template returns a score image; for same-size images, it's 1x1
-        let result = match_template(
-            square,
-            &template_resized,
-            MatchTemplateMethod::SumOfSquaredErrorsNormalized,
-        );
+/// Matches a single square against all piece templates
+/// Returns: 'K', 'Q', 'R', etc. for pieces, or '1' for empty square
+pub fn match_square(square: &GrayImage, templates: &HashMap<char, Vec<GrayImage>>, thresholds: Thresholds) -> char {
+    match_square_with_confidence(square, templates, thresholds).0
+}
 
-        // Get the match score (single pixel for same-size comparison)
-        let score = result.get_pixel(0, 0)[0];
+/// Like `match_square`, with the confidence of the reading from 0 to 1:
+/// - uniform (empty) squares: 0.5 at the variance threshold up to 1 for a flat color
+/// - pieces: how far the best score is below the match threshold, and below the best
+///   score of any other piece, whichever is closer
+/// - squares no template matches: how far the best score is above the match threshold
+pub fn match_square_with_confidence(
+    square: &GrayImage,
+    templates: &HashMap<char, Vec<GrayImage>>,
+    thresholds: Thresholds,
+) -> (char, f32) {
+    // Step 1: Check if square is empty via variance analysis
+    let variance = square_variance(square);
+    if variance < thresholds.empty_variance {
+        return ('1', 1.0 - 0.5 * variance / thresholds.empty_variance);
+    }
 
+    // Step 2: Best-scoring template, and the best score of any other piece
+    let mut best_match: char = '1';
+    let mut best_score: f32 = f32::MAX;
+    let mut runner_up: f32 = f32::MAX;
+    for (&piece_char, variants) in templates {
+        let score = variants.iter().map(|template| match_score(square, template)).fold(f32::MAX, f32::min);
         if score < best_score {
+            runner_up = best_score;
             best_score = score;
             best_match = piece_char;
+        } else if score < runner_up {
+            runner_up = score;
         }
     }
 
-    // Only return piece if match is confident enough
-    if best_score < MATCH_THRESHOLD {
-        best_match
+    // Only return piece if match is confident enough (otherwise consider square empty)
+    if best_score < thresholds.match_score {
+        let margin = 1.0 - best_score / thresholds.match_score;
+        let separation = if runner_up == f32::MAX { 1.0 } else { 1.0 - best_score / runner_up.max(f32::EPSILON) };
+        (best_match, margin.min(separation).clamp(0.0, 1.0))
     } else {
-        '1' // No confident match = empty square
+        // No confident match = empty square
+        ('1', (1.0 - thresholds.match_score / best_score).clamp(0.0, 1.0))
     }
 }
 
@@ -274,7 +464,7 @@ fn match_square(square: &GrayImage, templates: &PieceTemplates) -> char {
 /// Takes matched pieces where '1' = empty, 'K'/'k' = king, etc.
 /// The `player_side` determines the turn indicator in the FEN.
 /// Returns validated FEN string with game state appended
-fn build_fen_string(board: [[char; 8]; 8], player_side: PlayerSide) -> Result<String> {
+pub fn build_fen_string(board: [[char; 8]; 8], player_side: PlayerSide) -> Result<String> {
     let mut fen_parts: Vec<String> = Vec::with_capacity(8);
 
     for row in &board {
@@ -321,13 +511,13 @@ fn build_fen_string(board: [[char; 8]; 8], player_side: PlayerSide) -> Result<St
 /// Note: The OCR facade typically calls cropped_board_to_fen directly after
 /// shared board detection. This function is kept for direct usage/testing.
 #[allow(dead_code)]
-pub fn board_to_fen(image_path: &str, site: &str, player_side: PlayerSide) -> Result<String> {
+pub fn board_to_fen(img: &DynamicImage, site: &str, player_side: PlayerSide) -> Result<String> {
     // Detect and crop board from screenshot
-    let board_img = screenshot_to_board(image_path)
+    let board_img = screenshot_to_board(img)
         .context("Failed to detect/crop board from screenshot")?;
 
     // Delegate to the cropped board processor
-    process_board_image(board_img, site, player_side)
+    process_board_image(board_img, site, player_side).map(|recognition| recognition.fen)
 }
 
 /// Processes a pre-cropped board image to generate FEN string.
@@ -337,22 +527,23 @@ pub fn board_to_fen(image_path: &str, site: &str, player_side: PlayerSide) -> Re
 /// The `player_side` parameter determines:
 /// - Board orientation: If Black, the board is flipped 180° before processing
 /// - FEN turn indicator: 'w' for White, 'b' for Black
-pub fn cropped_board_to_fen(image_path: &str, site: &str, player_side: PlayerSide) -> Result<String> {
-    use image::ImageReader;
-
-    let img = ImageReader::open(image_path)
-        .context("Failed to open cropped board image")?
-        .decode()
-        .context("Failed to decode cropped board image")?;
+pub fn cropped_board_to_fen(img: &DynamicImage, site: &str, player_side: PlayerSide) -> Result<String> {
+    read_cropped_board(img, site, player_side).map(|recognition| recognition.fen)
+}
 
+/// `cropped_board_to_fen` with the confidence of every square
+pub fn read_cropped_board(img: &DynamicImage, site: &str, player_side: PlayerSide) -> Result<Recognition> {
     // Ensure it's 512x512 for consistent square sizes
     let (w, h) = img.dimensions();
     let board_img = if w != 512 || h != 512 {
-        let resized = imageops::resize(&img, 512, 512, imageops::FilterType::Lanczos3);
+        let resized = imageops::resize(img, 512, 512, imageops::FilterType::Lanczos3);
         DynamicImage::ImageRgba8(resized)
     } else {
-        img
+        img.clone()
     };
+    if let Ok(mut last) = LAST_BOARD.lock() {
+        *last = Some(board_img.clone());
+    }
 
     process_board_image(board_img, site, player_side)
 }
@@ -362,7 +553,7 @@ pub fn cropped_board_to_fen(image_path: &str, site: &str, player_side: PlayerSid
 ///
 /// When playing as Black, the board appears with Black pieces at the bottom.
 /// We flip the image 180° so that the standard FEN interpretation (rank 8 at top) is correct.
-fn process_board_image(board_img: DynamicImage, site: &str, player_side: PlayerSide) -> Result<String> {
+fn process_board_image(board_img: DynamicImage, site: &str, player_side: PlayerSide) -> Result<Recognition> {
     // Convert to RGBA for processing
     let mut img = board_img.to_rgba8();
 
@@ -375,12 +566,12 @@ fn process_board_image(board_img: DynamicImage, site: &str, player_side: PlayerS
         }
     }
 
-    // Load templates
-    let templates = load_templates(site).context("Failed to load piece templates")?;
-
     // Split into 64 squares
     let squares = split_into_squares(&img);
 
+    // Pick the template pack on the first frame if needed
+    resolve_theme(site, &squares, THRESHOLDS.get().copied().unwrap_or_default())?;
+
     // Debug: Save grid squares if DEBUG_OCR is set
     if std::env::var("DEBUG_OCR").is_ok() {
         let _ = std::fs::create_dir_all("screenshots/ocr_debug");
@@ -392,13 +583,153 @@ fn process_board_image(board_img: DynamicImage, site: &str, player_side: PlayerS
         }
     }
 
-    // Match each square against templates to identify pieces
-    let mut board: [[char; 8]; 8] = [['1'; 8]; 8];
+    // Match the squares that changed since the last board against the templates (loaded
+    // only when there is one)
+    let thresholds = THRESHOLDS.get().copied().unwrap_or_default();
+    let dir = template_dir(site);
+    let mut previous = PREVIOUS.lock().map_err(|_| anyhow::anyhow!("OCR cache poisoned"))?;
+    let unchanged = previous.as_ref().filter(|frame| frame.templates == dir);
+    let mut templates: Option<PieceTemplates> = None;
+    let (board, confidence, matched) = match_changed_squares(&squares, unchanged, |square| {
+        if templates.is_none() {
+            templates = Some(load_templates(site).context("Failed to load piece templates")?);
+        }
+        Ok(match_square_with_confidence(square, &templates.as_ref().unwrap().pieces, thresholds))
+    })?;
+    if std::env::var("DEBUG_OCR").is_ok() {
+        eprintln!("Matched {}/64 squares", matched);
+    }
+    *previous = Some(PreviousFrame { templates: dir, squares, board, confidence });
+
+    Ok(Recognition { fen: build_fen_string(board, player_side)?, confidence })
+}
+
+/// Reads each square with `match_one`, except squares that look the same as in `previous`,
+/// which keep their previous reading; returns the board, the confidence of each square, and
+/// how many squares were matched
+fn match_changed_squares(
+    squares: &[Vec<GrayImage>],
+    previous: Option<&PreviousFrame>,
+    mut match_one: impl FnMut(&GrayImage) -> Result<(char, f32)>,
+) -> Result<([[char; 8]; 8], SquareConfidence, usize)> {
+    let mut board = [['1'; 8]; 8];
+    let mut confidence = [[1.0; 8]; 8];
+    let mut matched = 0;
     for (rank, row) in squares.iter().enumerate() {
         for (file, square) in row.iter().enumerate() {
-            board[rank][file] = match_square(square, &templates);
+            (board[rank][file], confidence[rank][file]) = match previous {
+                Some(frame) if !square_changed(&frame.squares[rank][file], square) => {
+                    (frame.board[rank][file], frame.confidence[rank][file])
+                }
+                _ => {
+                    matched += 1;
+                    match_one(square)?
+                }
+            };
+        }
+    }
+    Ok((board, confidence, matched))
+}
+
+/// Whether a square's pixels differ from its previous image beyond resampling noise
+fn square_changed(before: &GrayImage, after: &GrayImage) -> bool {
+    if before.dimensions() != after.dimensions() {
+        return true;
+    }
+    let changed = before.pixels().zip(after.pixels()).filter(|(a, b)| a[0].abs_diff(b[0]) > PIXEL_TOLERANCE).count();
+    changed as f32 > (before.width() * before.height()) as f32 * CHANGED_SHARE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    /// A square with a bar of `shade` on a plain background (a stand-in for a piece glyph)
+    fn piece(shade: u8, bar_width: u32) -> GrayImage {
+        GrayImage::from_fn(64, 64, |x, _| if (32 - bar_width / 2..32 + bar_width / 2).contains(&x) { Luma([shade]) } else { Luma([200]) })
+    }
+
+    #[test]
+    fn test_best_pack_matches_the_board() {
+        let thin: HashMap<char, Vec<GrayImage>> = [('K', vec![piece(20, 8)]), ('k', vec![piece(90, 8)])].into();
+        let wide: HashMap<char, Vec<GrayImage>> = [('K', vec![piece(20, 40)]), ('k', vec![piece(90, 40)])].into();
+        let packs = vec![("thin".to_string(), thin), ("wide".to_string(), wide)];
+
+        let empty = GrayImage::from_pixel(64, 64, Luma([200]));
+        let mut squares = vec![vec![empty.clone(); 8]; 8];
+        squares[0][4] = piece(90, 38);
+        squares[7][4] = piece(20, 42);
+        assert_eq!(best_pack(&squares, &packs, Thresholds::default()).as_deref(), Some("wide"));
+
+        // Nothing to compare on an empty board
+        assert_eq!(best_pack(&vec![vec![empty; 8]; 8], &packs, Thresholds::default()), None);
+    }
+
+    #[test]
+    fn test_correction_samples_extend_templates() {
+        let dir = std::env::temp_dir().join(format!("zugzwang-templates-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("corrections")).unwrap();
+        for (_, name) in PIECE_FILES {
+            piece(20, 8).save(dir.join(format!("{}.png", name))).unwrap();
+        }
+        for time in 1..=6 {
+            piece(20, 30).save(dir.join(format!("corrections/NW_{}.png", time))).unwrap();
         }
+        piece(150, 4).save(dir.join("corrections/empty_1.png")).unwrap();
+
+        let templates = load_templates_from(dir.to_str().unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(templates.pieces[&'N'].len(), 1 + MAX_CORRECTION_SAMPLES);
+        assert_eq!(templates.pieces[&'n'].len(), 1);
+        // A square like the empty sample now reads as empty
+        let (read, _) = match_square_with_confidence(&piece(150, 4), &templates.pieces, Thresholds::default());
+        assert_eq!(read, '1');
     }
 
-    build_fen_string(board, player_side)
+    #[test]
+    fn test_only_changed_squares_are_matched() {
+        let empty = GrayImage::from_pixel(64, 64, Luma([200]));
+        let read = |square: &GrayImage| Ok(if square_variance(square) > 10.0 { ('P', 0.8) } else { ('1', 1.0) });
+
+        let mut squares = vec![vec![empty.clone(); 8]; 8];
+        squares[6][4] = piece(20, 16);
+        let (board, confidence, matched) = match_changed_squares(&squares, None, read).unwrap();
+        assert_eq!((board[6][4], matched), ('P', 64));
+        let previous = PreviousFrame { templates: String::new(), squares: squares.clone(), board, confidence };
+
+        // e2-e4, with a little resampling noise everywhere
+        let mut next: Vec<Vec<GrayImage>> = squares
+            .iter()
+            .map(|row| row.iter().map(|square| imageops::colorops::brighten(square, 3)).collect())
+            .collect();
+        next[6][4] = empty.clone();
+        next[4][4] = piece(20, 16);
+        let (board, confidence, matched) = match_changed_squares(&next, Some(&previous), read).unwrap();
+        assert_eq!(matched, 2);
+        assert_eq!((board[6][4], board[4][4]), ('1', 'P'));
+        assert_eq!((confidence[6][4], confidence[4][4]), (1.0, 0.8));
+    }
+
+    #[test]
+    fn test_confidence_drops_on_ambiguous_squares() {
+        let thresholds = Thresholds::default();
+        let empty = GrayImage::from_pixel(64, 64, Luma([200]));
+        let distinct: HashMap<char, Vec<GrayImage>> = [('K', vec![piece(20, 8)]), ('k', vec![piece(20, 40)])].into();
+        assert_eq!(match_square_with_confidence(&empty, &distinct, thresholds), ('1', 1.0));
+        let (piece_char, sure) = match_square_with_confidence(&piece(20, 8), &distinct, thresholds);
+        assert_eq!(piece_char, 'K');
+        assert!(sure > 0.9, "{}", sure);
+
+        // Two templates a shade apart: the square matches both about as well
+        let alike: HashMap<char, Vec<GrayImage>> = [('K', vec![piece(20, 8)]), ('k', vec![piece(24, 8)])].into();
+        let (_, doubtful) = match_square_with_confidence(&piece(22, 8), &alike, thresholds);
+        assert!(doubtful < 0.2, "{}", doubtful);
+
+        let mut confidence = [[1.0; 8]; 8];
+        confidence[4][4] = doubtful;
+        let recognition = Recognition { fen: String::new(), confidence };
+        assert_eq!(recognition.overall(), doubtful);
+        assert_eq!(recognition.weakest(), ("e4".to_string(), doubtful));
+    }
 }
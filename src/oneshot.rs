@@ -0,0 +1,82 @@
+//! One-shot commands for a single position, without the capture loop
+//!
+//! `zugzwang-rs fen <IMAGE>` reads the board in an image file and prints its FEN (OCR only,
+//! no engine), and `zugzwang-rs analyze --fen "<FEN>"` searches a position without reading
+//! anything from the screen. Both print once and exit, so they fit in scripts; `--json`
+//! prints the same JSON objects as the live loop's `--json` lines.
+
+use anyhow::Result;
+use crate::PlayerSide;
+use crate::ocr::{self, OcrMode};
+
+/// Settings of `fen <IMAGE>`
+#[derive(Clone, Debug)]
+pub struct FenOptions {
+    pub mode: OcrMode,
+    pub site: String,
+    pub player_side: PlayerSide,
+    pub json: bool,
+}
+
+/// Prints the FEN of the board in the image at `path`
+pub async fn fen(path: &str, options: &FenOptions) -> Result<()> {
+    let image = ocr::load_image(path)?;
+    let fen = ocr::board_to_fen(&image, &options.site, options.mode, options.player_side).await?;
+    crate::engine::check_position(&fen)?;
+    if options.json {
+        println!("{}", serde_json::json!({ "fen": fen }));
+    } else {
+        println!("{}", fen);
+    }
+    Ok(())
+}
+
+/// Searches `fen` to `depth` and prints the best move, with the top `count` candidates and
+/// the square-control heatmap if asked
+pub fn analyze(fen: &str, depth: u16, count: usize, heatmap: bool, json: bool) -> Result<()> {
+    let fen = position(fen)?;
+    let analysis = crate::engine::analyze_multipv(&fen, depth, count)?;
+    let mut value = crate::engine_json(&fen, &analysis.best_move, &analysis.eval, &analysis.candidates);
+    let mut lines = crate::engine_lines(&fen, &None, &analysis.best_move, &analysis.eval, &analysis.candidates);
+    crate::add_opening(&fen, &mut value, &mut lines);
+    crate::add_material(&fen, &mut value, &mut lines);
+    if heatmap {
+        let control = crate::heatmap::Control::of(&fen)?;
+        value["control"] = serde_json::json!(control.rows());
+        lines.push(String::new());
+        // From the side to move's point of view
+        lines.push(control.render(crate::play::side_to_move(&fen), crate::heatmap::use_color()));
+    }
+    if json {
+        println!("{}", value);
+    } else {
+        for line in lines {
+            println!("{}", line);
+        }
+    }
+    Ok(())
+}
+
+/// A FEN typed on the command line, checked and completed: castling rights the pieces no
+/// longer have are dropped, and missing fields default as in `fen::fix_castling_rights`
+fn position(fen: &str) -> Result<String> {
+    let fen = crate::fen::validate_fen(fen.trim())?;
+    crate::engine::check_position(&fen)?;
+    Ok(fen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_completes_and_checks_fens() {
+        assert_eq!(
+            position(" rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1 ").unwrap(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+        // Rooks gone: no castling rights left
+        assert_eq!(position("4k3/8/8/8/8/8/8/4K3 w KQkq - 0 1").unwrap(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert!(position("8/8/8/8/8/8/8/4K3 w - - 0 1").is_err());
+    }
+}
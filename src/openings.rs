@@ -0,0 +1,232 @@
+//! Opening names and book moves (ECO)
+//!
+//! At depth 6 the engine's opening moves are worse than just following theory, so while a
+//! game is still in the book every analyzed position also shows the opening's ECO code and
+//! name and the moves theory continues with ("Opening: B90 Sicilian Defense: Najdorf
+//! Variation (book: Be3, Be2, Bg5)").
+//!
+//! The book is a TSV of `eco`, `name`, and `pgn` columns, the format of lichess'
+//! chess-openings files. A small selection of the main lines is bundled;
+//! `--openings PATH` reads a file or a directory of them instead (e.g. the full
+//! chess-openings `a.tsv` to `e.tsv`), and `--no-openings` turns the lookup off.
+//!
+//! Positions are matched by piece placement and side to move, so transpositions count.
+//! A position inside a line but short of any named one (a move order on the way to a
+//! named line) takes the name of the deepest named position before it.
+
+use anyhow::{Context, Result};
+use crate::pgn;
+use serde::Serialize;
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::{Chess, EnPassantMode};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The bundled selection of openings
+const BUNDLED: &str = include_str!("../data/openings.tsv");
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// The book set with `set_path`, or the bundled one once first looked up
+static BOOK: OnceLock<Book> = OnceLock::new();
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Reads the book from a TSV file or a directory of them instead of the bundled one
+pub fn set_path(path: &Path) -> Result<()> {
+    let text = if path.is_dir() {
+        let mut files: Vec<_> = std::fs::read_dir(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file| file.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("tsv")))
+            .collect();
+        files.sort();
+        anyhow::ensure!(!files.is_empty(), "No .tsv opening files in {}", path.display());
+        let texts = files
+            .iter()
+            .map(|file| std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display())))
+            .collect::<Result<Vec<_>>>()?;
+        texts.join("\n")
+    } else {
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?
+    };
+    let book = Book::parse(&text).with_context(|| format!("Invalid opening book {}", path.display()))?;
+    let _ = BOOK.set(book);
+    Ok(())
+}
+
+/// The opening of a position and the book moves from it; None outside the book or with
+/// the lookup turned off
+pub fn lookup(fen: &str) -> Option<BookPosition> {
+    if !enabled() {
+        return None;
+    }
+    BOOK.get_or_init(|| Book::parse(BUNDLED).expect("bundled opening book is valid")).lookup(fen)
+}
+
+/// A named opening
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Opening {
+    eco: String,
+    name: String,
+}
+
+/// What the book knows about a position
+#[derive(Clone, Debug, Default)]
+struct Node {
+    /// Index into `Book::openings`
+    opening: Option<usize>,
+    /// Moves played from here in the book's lines (SAN), in file order
+    moves: Vec<String>,
+}
+
+/// Opening lines by position
+#[derive(Debug, Default)]
+pub struct Book {
+    openings: Vec<Opening>,
+    /// Placement and side to move → node
+    positions: HashMap<String, Node>,
+}
+
+/// A position found in the book
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct BookPosition {
+    pub eco: String,
+    pub name: String,
+    /// Theory's continuations (SAN); empty at the end of a line
+    pub book_moves: Vec<String>,
+}
+
+impl BookPosition {
+    /// Console line, "Opening: C50 Italian Game (book: Bc5, Nf6)"
+    pub fn line(&self) -> String {
+        let mut line = format!("Opening: {} {}", self.eco, self.name);
+        if !self.book_moves.is_empty() {
+            line.push_str(&format!(" (book: {})", self.book_moves.join(", ")));
+        }
+        line
+    }
+}
+
+impl Book {
+    /// Reads `eco`, `name`, `pgn` lines (a header line and blank lines are skipped)
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || line.starts_with("eco\t") {
+                continue;
+            }
+            let mut columns = line.split('\t');
+            let (Some(eco), Some(name), Some(movetext)) = (columns.next(), columns.next(), columns.next()) else {
+                anyhow::bail!("Line {}: expected eco, name, and pgn separated by tabs", number + 1);
+            };
+            let positions = walk(movetext).with_context(|| format!("Line {} ({})", number + 1, name))?;
+            lines.push((Opening { eco: eco.trim().to_string(), name: name.trim().to_string() }, positions));
+        }
+        anyhow::ensure!(!lines.is_empty(), "No openings found");
+
+        // Name each line's final position first, so a line passing through another's
+        // position finds it named whatever the file order
+        let mut book = Book::default();
+        for (opening, positions) in &lines {
+            let (last, _) = positions.last().expect("walk returns the start position");
+            let node = book.positions.entry(last.clone()).or_default();
+            if node.opening.is_none() {
+                node.opening = Some(book.openings.len());
+                book.openings.push(opening.clone());
+            }
+        }
+        for (_, positions) in &lines {
+            let mut named = None;
+            for (key, next) in positions {
+                let node = book.positions.entry(key.clone()).or_default();
+                named = node.opening.or(named);
+                node.opening = node.opening.or(named);
+                if let Some(san) = next
+                    && !node.moves.contains(san)
+                {
+                    node.moves.push(san.clone());
+                }
+            }
+        }
+        Ok(book)
+    }
+
+    /// Looks a position up by its FEN's placement and side to move
+    pub fn lookup(&self, fen: &str) -> Option<BookPosition> {
+        let node = self.positions.get(&fen_key(fen))?;
+        let opening = &self.openings[node.opening?];
+        Some(BookPosition { eco: opening.eco.clone(), name: opening.name.clone(), book_moves: node.moves.clone() })
+    }
+}
+
+/// Positions along a line from the start (as lookup keys), each with the move played from
+/// it (None after the last)
+fn walk(movetext: &str) -> Result<Vec<(String, Option<String>)>> {
+    let mut position = Chess::default();
+    let mut positions = Vec::new();
+    for token in pgn::movetext_tokens(movetext) {
+        let m = pgn::parse_san(&position, &token).with_context(|| format!("Illegal or unreadable move '{}'", token))?;
+        let before = key(&position);
+        positions.push((before, Some(SanPlus::from_move_and_play_unchecked(&mut position, m).to_string())));
+    }
+    positions.push((key(&position), None));
+    Ok(positions)
+}
+
+fn key(position: &Chess) -> String {
+    fen_key(&Fen::from_position(position, EnPassantMode::Legal).to_string())
+}
+
+/// Placement and side to move of a FEN
+fn fen_key(fen: &str) -> String {
+    fen.split_whitespace().take(2).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAJDORF: &str = "rnbqkb1r/1p2pppp/p2p1n2/8/3NP3/2N5/PPP2PPP/R1BQKB1R w KQkq - 0 6";
+
+    #[test]
+    fn test_bundled_book_names_positions() {
+        let book = Book::parse(BUNDLED).unwrap();
+        let najdorf = book.lookup(NAJDORF).unwrap();
+        assert_eq!((najdorf.eco.as_str(), najdorf.name.as_str()), ("B90", "Sicilian Defense: Najdorf Variation"));
+        assert_eq!(najdorf.book_moves, vec!["Be3", "Be2", "Bg5"]);
+        assert_eq!(najdorf.line(), "Opening: B90 Sicilian Defense: Najdorf Variation (book: Be3, Be2, Bg5)");
+        // Counters and castling rights don't matter
+        assert!(book.lookup("rnbqkb1r/1p2pppp/p2p1n2/8/3NP3/2N5/PPP2PPP/R1BQKB1R w - - 3 20").is_some());
+        // Out of book
+        assert_eq!(book.lookup("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), None);
+    }
+
+    #[test]
+    fn test_unnamed_positions_inherit_and_transpose() {
+        let book = Book::parse(
+            "eco\tname\tpgn\n\
+             C50\tItalian Game\t1. e4 e5 2. Nf3 Nc6 3. Bc4\n\
+             C20\tKing's Pawn Game\t1. e4 e5\n\
+             C46\tThree Knights Opening\t1. Nf3 Nc6 2. e4 e5 3. Nc3\n",
+        )
+        .unwrap();
+        // 2. Nf3 is on the way to the Italian, after the named 1... e5
+        let after_nf3 = "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2";
+        assert_eq!(book.lookup(after_nf3).unwrap().name, "King's Pawn Game");
+        // 1. Nf3 Nc6 2. e4 e5 transposes into the Italian's 2... Nc6 position
+        let transposed = book.lookup("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        assert_eq!(transposed.book_moves, vec!["Bc4", "Nc3"]);
+        assert!(Book::parse("C20\tBroken\t1. e4 e4").is_err());
+    }
+}
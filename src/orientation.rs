@@ -0,0 +1,97 @@
+//! Board orientation detection (`--side auto`)
+//!
+//! Which color the player has is the color shown at the bottom of the board. With LLM OCR
+//! the model is asked directly and told to use the coordinate labels (files a-h left to
+//! right and rank 1 at the bottom mean White) before the piece arrangement. Other OCR
+//! backends read the board as displayed and compare where each color's pieces stand: in
+//! most positions each army is still mostly on its own half. Positions that don't tell
+//! (kings and a few pieces in the middle) give no answer, and the caller tries again on a
+//! later capture.
+
+use anyhow::Result;
+use crate::PlayerSide;
+use crate::ocr::{self, OcrMode};
+use image::DynamicImage;
+use std::sync::Arc;
+
+/// Captures tried before falling back to the default side
+pub const DETECT_ATTEMPTS: usize = 3;
+
+/// Rows between the average White and Black piece needed to tell the sides apart
+const MIN_ROW_GAP: f64 = 2.0;
+
+const ORIENTATION_PROMPT: &str = "This is a chess website (or a photo of a board). \
+Which color's pieces are at the bottom of the board, nearest the viewer? \
+Use the coordinate labels if visible: files a-h from left to right and rank 1 at the bottom mean White; \
+h-a and rank 8 at the bottom mean Black. Otherwise judge by where each color's pieces are. Reply exactly:\n\
+BOTTOM: WHITE\nor\nBOTTOM: BLACK\nIf you can't tell, reply UNKNOWN.";
+
+/// Detects the player's side from a capture; None when the board doesn't tell yet
+pub async fn detect(image: &Arc<DynamicImage>, site: &str, mode: OcrMode) -> Result<Option<PlayerSide>> {
+    if mode == OcrMode::Llm {
+        let text = crate::ocr_llm::read_text(image, ORIENTATION_PROMPT).await?;
+        return Ok(parse_reply(&text));
+    }
+    // As White, OCR keeps the board as displayed (rank 8 = top row)
+    let fen = ocr::board_to_fen(image, site, mode, PlayerSide::White).await?;
+    Ok(side_at_bottom(fen.split_whitespace().next().unwrap_or_default()))
+}
+
+/// Which color's pieces sit at the bottom of a placement read as displayed (top row first)
+pub fn side_at_bottom(placement: &str) -> Option<PlayerSide> {
+    let (mut white, mut black) = ((0.0, 0), (0.0, 0));
+    for (row, rank) in placement.split('/').enumerate() {
+        for piece in rank.chars().filter(char::is_ascii_alphabetic) {
+            let side = if piece.is_ascii_uppercase() { &mut white } else { &mut black };
+            side.0 += row as f64;
+            side.1 += 1;
+        }
+    }
+    if white.1 == 0 || black.1 == 0 {
+        return None;
+    }
+    let gap = white.0 / white.1 as f64 - black.0 / black.1 as f64;
+    if gap >= MIN_ROW_GAP {
+        Some(PlayerSide::White)
+    } else if gap <= -MIN_ROW_GAP {
+        Some(PlayerSide::Black)
+    } else {
+        None
+    }
+}
+
+fn parse_reply(text: &str) -> Option<PlayerSide> {
+    text.lines().find_map(|line| match line.trim().strip_prefix("BOTTOM:")?.trim() {
+        side if side.eq_ignore_ascii_case("white") => Some(PlayerSide::White),
+        side if side.eq_ignore_ascii_case("black") => Some(PlayerSide::Black),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_side_at_bottom_from_arrangement() {
+        assert_eq!(side_at_bottom("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR"), Some(PlayerSide::White));
+        // The same position seen from Black's side
+        assert_eq!(side_at_bottom("RNBKQBNR/PPPPPPPP/8/8/8/8/pppppppp/rnbkqbnr"), Some(PlayerSide::Black));
+        // Middlegame, Black at the bottom: armies still mostly on their own halves
+        assert_eq!(side_at_bottom("R1BK3R/PPP2PPP/2N5/3Pp3/4n3/2p2p2/pp3bpp/r1bk3r"), Some(PlayerSide::Black));
+    }
+
+    #[test]
+    fn test_side_at_bottom_undecided() {
+        assert_eq!(side_at_bottom("8/8/8/3kK3/8/8/8/8"), None);
+        assert_eq!(side_at_bottom("8/8/3k4/4K3/8/8/8/8"), None);
+        assert_eq!(side_at_bottom("8/8/8/8/8/8/8/8"), None);
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(parse_reply("BOTTOM: BLACK"), Some(PlayerSide::Black));
+        assert_eq!(parse_reply("Sure.\nBOTTOM: white"), Some(PlayerSide::White));
+        assert_eq!(parse_reply("UNKNOWN"), None);
+    }
+}
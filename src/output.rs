@@ -0,0 +1,672 @@
+//! Output sinks (`--output`)
+//!
+//! Every analyzed position becomes one `CycleOutput` - the JSON result, the console lines,
+//! and a one-line headline - which is handed to each enabled sink. Several sinks can run at
+//! once, chosen with repeated `--output` flags or the `output` list in the config file:
+//!
+//! - `console`: the human-readable lines (default)
+//! - `json`: the JSON result on stdout, one per line (what `--json` selects)
+//! - `json-file:PATH`: the JSON result appended to a file
+//! - `websocket:PORT`: the JSON result broadcast to every client of `ws://127.0.0.1:PORT`
+//! - `webhook:URL`: the JSON result POSTed to a URL
+//! - `clipboard`: the headline copied with the platform's clipboard tool
+//! - `tts`: the best move read aloud with the platform's speech tool (see `speech`)
+//! - `http:PORT`: `/analysis`, `/fen`, and a `/ws` WebSocket on port PORT (what `serve` selects)
+//! - `tui`: a full-screen dashboard in place of the console lines (what `--tui` selects)
+//! - `overlay`: the best move as an arrow over the board on screen (see `overlay`)
+//! - `mini`: move, eval, and confidence in a small always-on-top window (see `mini_window`)
+//! - `notify`: the headline as a desktop notification (what `--notify` selects; see `notify`)
+//!
+//! Any sink can take an `@UNITS` suffix (`console@win`, `websocket:9001@cp`) to show evals
+//! in other units; see `eval_units`.
+//!
+//! `--only best,eval,fen` trims the stdout sinks (`console`, `json`) to the chosen fields,
+//! one short line per cycle for scripts and tiny terminals; `--suppress-fen` just drops the
+//! FEN from them. See `Filter`.
+//!
+//! A failing sink is reported and skipped for that cycle; the others still run. New outputs
+//! implement `OutputSink` instead of adding another print to the main loop.
+
+use anyhow::{Context, Result};
+use crate::eval_units::EvalUnits;
+use crate::ocr::BoxFuture;
+use futures_util::SinkExt;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::sync::broadcast;
+
+/// Seconds a webhook may take to answer
+const WEBHOOK_TIMEOUT_SECS: u64 = 5;
+
+/// Results kept for WebSocket clients that fall behind
+const WEBSOCKET_BACKLOG: usize = 16;
+
+/// Result of one cycle, in the forms the sinks need
+#[derive(Clone, Debug)]
+pub struct CycleOutput {
+    /// Machine-readable result (the `--json` line)
+    pub value: serde_json::Value,
+    /// Human-readable lines for the console
+    pub lines: Vec<String>,
+    /// One-line summary for short outputs (clipboard, speech)
+    pub headline: String,
+    /// Time spent in each stage of the cycle (capture, ocr, engine, ...)
+    pub timings: Vec<(&'static str, std::time::Duration)>,
+}
+
+impl CycleOutput {
+    /// The result with evals in other units: the JSON `evaluation` fields (plus a `units`
+    /// key), the lines, and the headline
+    pub fn with_units(&self, units: EvalUnits) -> CycleOutput {
+        if units == EvalUnits::Pawns {
+            return self.clone();
+        }
+        let best = self.value["evaluation"]
+            .as_str()
+            .or(self.value["engine"]["evaluation"].as_str())
+            .map(str::to_string);
+        let best = best.as_deref();
+        let mut value = self.value.clone();
+        convert_evals(&mut value, units, best);
+        if let Some(object) = value.as_object_mut() {
+            object.insert("units".to_string(), units.name().into());
+        }
+        CycleOutput {
+            value,
+            lines: self.lines.iter().map(|line| units.convert_text(line, best)).collect(),
+            headline: units.convert_text(&self.headline, best),
+            timings: self.timings.clone(),
+        }
+    }
+}
+
+/// A field `--only` keeps
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    Best,
+    Eval,
+    Fen,
+}
+
+impl Field {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "best" => Ok(Field::Best),
+            "eval" => Ok(Field::Eval),
+            "fen" => Ok(Field::Fen),
+            _ => Err(format!("Unknown field '{}' (expected best, eval, or fen)", name)),
+        }
+    }
+
+    /// Key of the field in a trimmed JSON result
+    fn key(self) -> &'static str {
+        match self {
+            Field::Best => "best_move",
+            Field::Eval => "evaluation",
+            Field::Fen => "fen",
+        }
+    }
+
+    /// The field's value in a result of any analysis mode ("--" when the mode has none)
+    pub fn value(self, output: &CycleOutput) -> String {
+        let value = &output.value;
+        let text = match self {
+            Field::Best => value["best_move"]
+                .as_str()
+                .or(value["engine"]["move"].as_str())
+                .or(value["recommendation"]["best_move"].as_str())
+                .map(|best| match value["fen"].as_str() {
+                    Some(fen) => crate::notation::display(fen, best),
+                    None => best.to_string(),
+                }),
+            Field::Eval => value["evaluation"]
+                .as_str()
+                .or(value["engine"]["evaluation"].as_str())
+                .or(value["recommendation"]["evaluation"].as_str())
+                .map(str::to_string),
+            Field::Fen => value["fen"].as_str().map(str::to_string),
+        };
+        text.unwrap_or_else(|| "--".to_string())
+    }
+}
+
+/// What the stdout sinks keep of a result (`--only`, `--suppress-fen`)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Filter {
+    /// Fields to keep, in order; empty keeps everything
+    pub only: Vec<Field>,
+    pub suppress_fen: bool,
+}
+
+impl Filter {
+    /// The result as the filter leaves it: with `only`, one line of the fields separated by
+    /// spaces and JSON with just their keys; with `suppress_fen`, no FEN line or key
+    pub fn apply(&self, output: &CycleOutput) -> CycleOutput {
+        let mut output = output.clone();
+        if !self.only.is_empty() {
+            let fields: Vec<(Field, String)> = self.only.iter().map(|&field| (field, field.value(&output))).collect();
+            output.lines = vec![fields.iter().map(|(_, text)| text.as_str()).collect::<Vec<_>>().join(" ")];
+            output.value = fields
+                .into_iter()
+                .map(|(field, text)| (field.key().to_string(), serde_json::Value::String(text)))
+                .collect();
+        } else if self.suppress_fen {
+            output.lines.retain(|line| !line.starts_with("FEN:"));
+            if let Some(object) = output.value.as_object_mut() {
+                object.remove("fen");
+            }
+        }
+        output
+    }
+}
+
+/// Converts every `evaluation`/`engine_evaluation` string in a JSON result
+fn convert_evals(value: &mut serde_json::Value, units: EvalUnits, best: Option<&str>) {
+    match value {
+        serde_json::Value::Object(object) => {
+            for (key, field) in object.iter_mut() {
+                if let serde_json::Value::String(eval) = field
+                    && matches!(key.as_str(), "evaluation" | "engine_evaluation")
+                    && let Some(converted) = units.format(eval, best)
+                {
+                    *eval = converted;
+                } else {
+                    convert_evals(field, units, best);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|item| convert_evals(item, units, best)),
+        _ => {}
+    }
+}
+
+/// A destination for cycle results
+pub trait OutputSink: Send {
+    /// Name shown when the sink fails
+    fn name(&self) -> String;
+
+    /// Delivers one result
+    fn emit<'a>(&'a mut self, output: &'a CycleOutput) -> BoxFuture<'a, Result<()>>;
+}
+
+/// A sink and the units it shows evals in, as written on the command line or in the config
+/// file (`SPEC` or `SPEC@UNITS`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sink {
+    pub spec: SinkSpec,
+    pub units: EvalUnits,
+}
+
+impl Sink {
+    pub fn new(spec: SinkSpec) -> Self {
+        Sink { spec, units: EvalUnits::default() }
+    }
+
+    /// Parses a sink with an optional `@UNITS` suffix. Only a known unit name counts as one,
+    /// so an `@` inside a URL (`webhook:https://user@host`) stays part of the spec.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        if let Some((spec, units)) = text.rsplit_once('@')
+            && let Some(units) = EvalUnits::from_name(units)
+        {
+            return Ok(Sink { spec: SinkSpec::parse(spec)?, units });
+        }
+        SinkSpec::parse(text).map(Sink::new)
+    }
+}
+
+/// The kind of sink and its argument
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SinkSpec {
+    Console,
+    Json,
+    JsonFile(PathBuf),
+    WebSocket(u16),
+    Webhook(String),
+    Clipboard,
+    Tts,
+    Tui,
+    Http(u16),
+    Overlay,
+    Mini,
+    Notify,
+    Remote(u16),
+}
+
+impl SinkSpec {
+    /// Parses `name` or `name:argument`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (name, arg) = match spec.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (spec, None),
+        };
+        let need = |what: &str| arg.filter(|a| !a.is_empty()).ok_or_else(|| format!("'{}' needs {} ({}:...)", name, what, name));
+        match name {
+            "console" => Ok(SinkSpec::Console),
+            "json" => Ok(SinkSpec::Json),
+            "json-file" => Ok(SinkSpec::JsonFile(PathBuf::from(need("a file")?))),
+            "websocket" => need("a port")?
+                .parse()
+                .map(SinkSpec::WebSocket)
+                .map_err(|_| format!("Invalid WebSocket port in '{}'", spec)),
+            "webhook" => Ok(SinkSpec::Webhook(need("a URL")?.to_string())),
+            "clipboard" => Ok(SinkSpec::Clipboard),
+            "tts" => Ok(SinkSpec::Tts),
+            "tui" => Ok(SinkSpec::Tui),
+            "overlay" => Ok(SinkSpec::Overlay),
+            "mini" => Ok(SinkSpec::Mini),
+            "notify" => Ok(SinkSpec::Notify),
+            "http" => need("a port")?
+                .parse()
+                .map(SinkSpec::Http)
+                .map_err(|_| format!("Invalid HTTP port in '{}'", spec)),
+            "remote" => need("a port")?
+                .parse()
+                .map(SinkSpec::Remote)
+                .map_err(|_| format!("Invalid remote port in '{}'", spec)),
+            _ => Err(format!(
+                "Unknown output '{}' (expected console, json, json-file:PATH, websocket:PORT, webhook:URL, http:PORT, remote:PORT, clipboard, tts, tui, overlay, mini, or notify)",
+                spec
+            )),
+        }
+    }
+}
+
+/// The enabled sinks
+pub struct Outputs {
+    sinks: Vec<(Box<dyn OutputSink>, EvalUnits)>,
+}
+
+impl Outputs {
+    /// Starts the sinks. With `json`, stdout carries JSON: `console` becomes `json`. `filter`
+    /// trims what the stdout sinks print.
+    pub async fn start(specs: &[Sink], json: bool, verbose: bool, filter: &Filter) -> Result<Self> {
+        let mut specs = if specs.is_empty() { vec![Sink::new(SinkSpec::Console)] } else { specs.to_vec() };
+        if json {
+            for sink in specs.iter_mut().filter(|s| s.spec == SinkSpec::Console) {
+                sink.spec = SinkSpec::Json;
+            }
+        }
+        specs.dedup();
+
+        let mut sinks: Vec<(Box<dyn OutputSink>, EvalUnits)> = Vec::new();
+        for Sink { spec, units } in specs {
+            // The dashboard needs raw evals for its eval bar and converts the rest itself
+            let convert = if spec == SinkSpec::Tui { EvalUnits::Pawns } else { units };
+            let sink: Box<dyn OutputSink> = match spec {
+                SinkSpec::Console => Box::new(ConsoleSink { verbose, filter: filter.clone() }),
+                SinkSpec::Json => Box::new(JsonSink { filter: filter.clone() }),
+                SinkSpec::JsonFile(path) => Box::new(JsonFileSink { path }),
+                SinkSpec::WebSocket(port) => Box::new(WebSocketSink::bind(port).await?),
+                SinkSpec::Webhook(url) => Box::new(WebhookSink::new(url)?),
+                SinkSpec::Clipboard => Box::new(ClipboardSink),
+                SinkSpec::Tts => Box::new(TtsSink { speaking: None }),
+                SinkSpec::Tui => Box::new(crate::tui::TuiSink::start(units)?),
+                SinkSpec::Http(port) => Box::new(crate::server::ServerSink::bind(port).await?),
+                SinkSpec::Overlay => Box::new(crate::overlay::OverlaySink::start()?),
+                SinkSpec::Mini => Box::new(crate::mini_window::MiniSink::start()?),
+                SinkSpec::Notify => Box::new(crate::notify::NotifySink::start()?),
+                SinkSpec::Remote(port) => Box::new(crate::remote::RemoteSink::bind(port).await?),
+            };
+            sinks.push((sink, convert));
+        }
+        Ok(Outputs { sinks })
+    }
+
+    /// Names of the enabled sinks, for the banner
+    pub fn names(&self) -> Vec<String> {
+        self.sinks
+            .iter()
+            .map(|(sink, units)| match units {
+                EvalUnits::Pawns => sink.name(),
+                units => format!("{}@{}", sink.name(), units.name()),
+            })
+            .collect()
+    }
+
+    /// Hands a result to every sink, reporting failures without stopping
+    pub async fn emit(&mut self, output: &CycleOutput) {
+        for (sink, units) in &mut self.sinks {
+            let converted;
+            let output = match units {
+                EvalUnits::Pawns => output,
+                units => {
+                    converted = output.with_units(*units);
+                    &converted
+                }
+            };
+            if let Err(e) = sink.emit(output).await {
+                eprintln!("⚠ Output {} failed: {:#}", sink.name(), e);
+            }
+        }
+    }
+}
+
+/// Human-readable lines, boxed like the cycle timings with --verbose (not with `--only`)
+struct ConsoleSink {
+    verbose: bool,
+    filter: Filter,
+}
+
+impl OutputSink for ConsoleSink {
+    fn name(&self) -> String {
+        "console".to_string()
+    }
+
+    fn emit<'a>(&'a mut self, output: &'a CycleOutput) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let output = self.filter.apply(output);
+            if !self.verbose || !self.filter.only.is_empty() {
+                output.lines.iter().for_each(|line| println!("{}", line));
+                return Ok(());
+            }
+            println!("├─────────────────────────────────────────────────────────────");
+            output.lines.iter().for_each(|line| println!("│ {}", line));
+            println!("└─────────────────────────────────────────────────────────────");
+            Ok(())
+        })
+    }
+}
+
+/// JSON lines on stdout
+struct JsonSink {
+    filter: Filter,
+}
+
+impl OutputSink for JsonSink {
+    fn name(&self) -> String {
+        "json".to_string()
+    }
+
+    fn emit<'a>(&'a mut self, output: &'a CycleOutput) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            println!("{}", self.filter.apply(output).value);
+            Ok(())
+        })
+    }
+}
+
+/// JSON lines appended to a file
+struct JsonFileSink {
+    path: PathBuf,
+}
+
+impl OutputSink for JsonFileSink {
+    fn name(&self) -> String {
+        format!("json-file:{}", self.path.display())
+    }
+
+    fn emit<'a>(&'a mut self, output: &'a CycleOutput) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .with_context(|| format!("Failed to open {}", self.path.display()))?;
+            writeln!(file, "{}", output.value).with_context(|| format!("Failed to write {}", self.path.display()))
+        })
+    }
+}
+
+/// JSON results broadcast to WebSocket clients (e.g. an overlay page or a stream widget)
+struct WebSocketSink {
+    port: u16,
+    sender: broadcast::Sender<String>,
+}
+
+impl WebSocketSink {
+    /// Listens on localhost; each client gets the results from when it connected
+    async fn bind(port: u16) -> Result<Self> {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+            .await
+            .with_context(|| format!("Failed to listen for WebSocket clients on port {}", port))?;
+        let (sender, _) = broadcast::channel(WEBSOCKET_BACKLOG);
+        let clients = sender.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let results = clients.subscribe();
+                tokio::spawn(async move {
+                    if let Ok(socket) = tokio_tungstenite::accept_async(stream).await {
+                        push_results(socket, results).await;
+                    }
+                });
+            }
+        });
+        Ok(WebSocketSink { port, sender })
+    }
+}
+
+impl OutputSink for WebSocketSink {
+    fn name(&self) -> String {
+        format!("websocket:{}", self.port)
+    }
+
+    fn emit<'a>(&'a mut self, output: &'a CycleOutput) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            // No clients connected is not an error
+            let _ = self.sender.send(output.value.to_string());
+            Ok(())
+        })
+    }
+}
+
+/// Sends each broadcast result to a WebSocket client until it disconnects
+pub async fn push_results<S>(mut socket: tokio_tungstenite::WebSocketStream<S>, mut results: broadcast::Receiver<String>)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    loop {
+        let text = match results.recv().await {
+            Ok(text) => text,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if socket.send(tokio_tungstenite::tungstenite::Message::text(text)).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// JSON results POSTed to a URL
+struct WebhookSink {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    fn new(url: String) -> Result<Self> {
+        let client = crate::ocr_llm::http_client(WEBHOOK_TIMEOUT_SECS)?;
+        Ok(WebhookSink { url, client })
+    }
+}
+
+impl OutputSink for WebhookSink {
+    fn name(&self) -> String {
+        format!("webhook:{}", self.url)
+    }
+
+    fn emit<'a>(&'a mut self, output: &'a CycleOutput) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let response = self.client.post(&self.url).json(&output.value).send().await?;
+            anyhow::ensure!(response.status().is_success(), "HTTP {}", response.status());
+            Ok(())
+        })
+    }
+}
+
+/// Headline copied to the clipboard
+struct ClipboardSink;
+
+impl OutputSink for ClipboardSink {
+    fn name(&self) -> String {
+        "clipboard".to_string()
+    }
+
+    fn emit<'a>(&'a mut self, output: &'a CycleOutput) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut last_error = None;
+            for (program, args) in clipboard_commands() {
+                match pipe_to(program, args, &output.headline).await {
+                    Ok(()) => return Ok(()),
+                    Err(e) => last_error = Some(e),
+                }
+            }
+            Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No clipboard tool for this platform")))
+        })
+    }
+}
+
+/// Clipboard tools to try, in order
+fn clipboard_commands() -> &'static [(&'static str, &'static [&'static str])] {
+    if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(windows) {
+        &[("clip", &[])]
+    } else {
+        &[("wl-copy", &[]), ("xclip", &["-selection", "clipboard"]), ("xsel", &["--clipboard", "--input"])]
+    }
+}
+
+/// Runs a program with `text` on its stdin and waits for it
+async fn pipe_to(program: &str, args: &[&str], text: &str) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes()).await?;
+    }
+    let status = child.wait().await?;
+    anyhow::ensure!(status.success(), "{} exited with {}", program, status);
+    Ok(())
+}
+
+/// Headline read aloud; a new result cuts off one still being spoken
+struct TtsSink {
+    speaking: Option<tokio::process::Child>,
+}
+
+impl OutputSink for TtsSink {
+    fn name(&self) -> String {
+        "tts".to_string()
+    }
+
+    fn emit<'a>(&'a mut self, output: &'a CycleOutput) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if let Some(mut previous) = self.speaking.take() {
+                let _ = previous.start_kill();
+            }
+            let (program, args) = crate::speech::command(&crate::speech::spoken(output));
+            let child = tokio::process::Command::new(program)
+                .args(&args)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .kill_on_drop(true)
+                .spawn()
+                .with_context(|| format!("Failed to run {}", program))?;
+            self.speaking = Some(child);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_specs() {
+        assert_eq!(SinkSpec::parse("console"), Ok(SinkSpec::Console));
+        assert_eq!(SinkSpec::parse("json-file:out/moves.jsonl"), Ok(SinkSpec::JsonFile(PathBuf::from("out/moves.jsonl"))));
+        assert_eq!(SinkSpec::parse("websocket:9001"), Ok(SinkSpec::WebSocket(9001)));
+        assert_eq!(SinkSpec::parse("http:8080"), Ok(SinkSpec::Http(8080)));
+        assert_eq!(SinkSpec::parse("notify"), Ok(SinkSpec::Notify));
+        assert_eq!(SinkSpec::parse("remote:8765"), Ok(SinkSpec::Remote(8765)));
+        // The URL's own colons stay in the argument
+        assert_eq!(SinkSpec::parse("webhook:http://localhost:8080/hook"), Ok(SinkSpec::Webhook("http://localhost:8080/hook".to_string())));
+        assert!(SinkSpec::parse("websocket:http").is_err());
+        assert!(SinkSpec::parse("json-file").is_err());
+        assert!(SinkSpec::parse("pager").is_err());
+    }
+
+    #[test]
+    fn test_parse_units_suffix() {
+        assert_eq!(Sink::parse("console@win"), Ok(Sink { spec: SinkSpec::Console, units: EvalUnits::WinPercent }));
+        assert_eq!(Sink::parse("websocket:9001@cp").map(|s| s.units), Ok(EvalUnits::Centipawns));
+        // An @ that isn't a unit belongs to the spec
+        assert_eq!(
+            Sink::parse("webhook:https://user@example.com/hook"),
+            Ok(Sink::new(SinkSpec::Webhook("https://user@example.com/hook".to_string())))
+        );
+        assert!(Sink::parse("console@furlongs").is_err());
+    }
+
+    #[test]
+    fn test_with_units_converts_json_and_lines() {
+        let output = CycleOutput {
+            value: serde_json::json!({
+                "fen": "8/8/8/8/8/8/8/K6k w - - 0 1",
+                "evaluation": "+1.00",
+                "candidates": [{ "move": "A1 to A2", "evaluation": "+1.00" }, { "move": "A1 to B1", "evaluation": "-2.00" }],
+            }),
+            lines: vec!["Best: A1 to A2 (+1.00)".to_string(), "  2. A1 to B1 (-2.00)".to_string()],
+            headline: "Best: A1 to A2 (+1.00)".to_string(),
+            timings: Vec::new(),
+        };
+        let converted = output.with_units(EvalUnits::Accuracy);
+        assert_eq!(converted.value["units"], "accuracy");
+        assert_eq!(converted.value["evaluation"], "accuracy 100%");
+        assert_eq!(converted.value["candidates"][1]["evaluation"], "accuracy 29%");
+        assert_eq!(converted.lines[1], "  2. A1 to B1 (accuracy 29%)");
+        assert_eq!(converted.headline, "Best: A1 to A2 (accuracy 100%)");
+        assert_eq!(output.with_units(EvalUnits::Pawns).value, output.value);
+    }
+
+    #[test]
+    fn test_filter_keeps_only_chosen_fields() {
+        let output = CycleOutput {
+            value: serde_json::json!({ "mode": "engine", "fen": "8/8/8/8/8/8/8/K6k w - - 0 1", "best_move": "A1 to A2", "evaluation": "+0.00" }),
+            lines: vec!["FEN:  8/8/8/8/8/8/8/K6k w - - 0 1".to_string(), "Best: A1 to A2 (+0.00)".to_string()],
+            headline: "Best: A1 to A2 (+0.00)".to_string(),
+            timings: Vec::new(),
+        };
+        let only = Filter { only: vec![Field::Eval, Field::Fen], suppress_fen: false }.apply(&output);
+        assert_eq!(only.lines, vec!["+0.00 8/8/8/8/8/8/8/K6k w - - 0 1"]);
+        assert_eq!(only.value, serde_json::json!({ "evaluation": "+0.00", "fen": "8/8/8/8/8/8/8/K6k w - - 0 1" }));
+
+        let no_fen = Filter { only: Vec::new(), suppress_fen: true }.apply(&output);
+        assert_eq!(no_fen.lines, vec!["Best: A1 to A2 (+0.00)"]);
+        assert!(no_fen.value.get("fen").is_none());
+
+        // Direct mode has no FEN
+        let direct = CycleOutput { value: serde_json::json!({ "mode": "direct", "recommendation": { "best_move": "E2 to E4" } }), ..output };
+        assert_eq!(Filter { only: vec![Field::Best, Field::Fen], suppress_fen: false }.apply(&direct).lines, vec!["E2 to E4 --"]);
+        assert!(Field::parse("pv").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_json_console_swap_and_json_file() {
+        let path = std::env::temp_dir().join(format!("zugzwang-output-{}.jsonl", std::process::id()));
+        let specs = [Sink::new(SinkSpec::Console), Sink::new(SinkSpec::JsonFile(path.clone()))];
+        let mut outputs = Outputs::start(&specs, true, false, &Filter::default()).await.unwrap();
+        assert_eq!(outputs.names(), vec!["json".to_string(), format!("json-file:{}", path.display())]);
+
+        let output = CycleOutput {
+            value: serde_json::json!({ "mode": "engine", "best_move": "E2 to E4" }),
+            lines: vec!["Best: E2 to E4 (+0.35)".to_string()],
+            headline: "Best: E2 to E4 (+0.35)".to_string(),
+            timings: Vec::new(),
+        };
+        outputs.emit(&output).await;
+        outputs.emit(&output).await;
+        let written = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(written.lines().count(), 2);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(written.lines().next().unwrap()).unwrap(), output.value);
+    }
+}
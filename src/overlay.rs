@@ -0,0 +1,422 @@
+//! Move arrow drawn over the board on screen (`--output overlay`)
+//!
+//! Reading "E2 to E4" in a terminal and finding the squares on the board costs seconds in
+//! a fast game. The overlay sink draws the suggested move as an arrow in a transparent,
+//! click-through, always-on-top window laid over the board where it was captured.
+//!
+//! With MultiPV on, the other candidates get arrows too, as in analysis GUIs: each rank its
+//! own color (green for the best move, then blue, orange, purple, red), drawn thinner the
+//! more winning chances the move gives up compared with the best one.
+//!
+//! The window runs in a child process (`zugzwang overlay-window`, built with
+//! `--features overlay`): the GUI event loop has to own a main thread, and the pipeline's
+//! is taken by the async runtime. The sink sends it one JSON line per result, the `Arrows`
+//! to draw, or `null` to hide them when there's no move to show. The board is located in
+//! the latest capture (`ocr_native::locate_board`) and mapped back to screen coordinates,
+//! so the overlay needs screen or `--window` input, not a camera or a replay.
+//!
+//! Transparency depends on the platform's compositor; without one the window shows the
+//! arrow on black over the board.
+
+use anyhow::{Context, Result};
+use crate::capture::{self, ScreenArea};
+use crate::eval_units;
+use crate::ocr::BoxFuture;
+use crate::output::{CycleOutput, OutputSink};
+use serde::{Deserialize, Serialize};
+use shakmaty::Square;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::{Child, ChildStdin};
+
+/// What the overlay window draws: the board's area on screen and the arrows on it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Arrows {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// Best move first
+    pub arrows: Vec<Arrow>,
+}
+
+/// One move's arrow, its squares as fractions of the board (0,0 top left) so the window
+/// can draw at any pixel density
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Arrow {
+    pub from: (f32, f32),
+    pub to: (f32, f32),
+    /// ARGB (platforms without transparency ignore the alpha)
+    pub color: u32,
+    /// Thickness relative to the best move's arrow (0-1]
+    pub weight: f32,
+}
+
+/// Arrow colors by rank: the best move, then the other MultiPV candidates
+pub const ARROW_COLORS: [u32; 5] = [0xCC15_781B, 0xCC1E_63C8, 0xCCE0_8A1E, 0xCC8E_3BB0, 0xCCC6_2828];
+
+/// Winning chances (percentage points) given up at which a candidate's arrow is thinnest
+const THINNEST_GAP: f64 = 30.0;
+/// Thickness of the thinnest arrow relative to the best move's
+const MIN_WEIGHT: f32 = 0.35;
+
+/// Sends each result's best move to the overlay window process
+pub struct OverlaySink {
+    // Kept so the window closes with the sink (kill on drop)
+    _window: Child,
+    stdin: ChildStdin,
+}
+
+impl OverlaySink {
+    /// Opens the overlay window (hidden until there's a move to show)
+    pub fn start() -> Result<Self> {
+        anyhow::ensure!(
+            cfg!(feature = "overlay"),
+            "--output overlay needs a build with the overlay feature (cargo build --release --features overlay)"
+        );
+        let exe = std::env::current_exe().context("Failed to find this program to open the overlay window")?;
+        let mut window = tokio::process::Command::new(exe)
+            .arg("overlay-window")
+            .stdin(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Failed to open the overlay window")?;
+        let stdin = window.stdin.take().context("Overlay window has no input")?;
+        Ok(OverlaySink { _window: window, stdin })
+    }
+}
+
+impl OutputSink for OverlaySink {
+    fn name(&self) -> String {
+        "overlay".to_string()
+    }
+
+    fn emit<'a>(&'a mut self, output: &'a CycleOutput) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let arrows = capture::latest_on_screen().and_then(|(image, area)| {
+                let bounds = crate::ocr_native::locate_board(&image).ok()?;
+                arrows_for(&output.value, (image.width(), image.height()), bounds, area)
+            });
+            let line = format!("{}\n", serde_json::to_string(&arrows)?);
+            self.stdin.write_all(line.as_bytes()).await.context("Overlay window was closed")
+        })
+    }
+}
+
+/// The arrows for a result's best move and candidates, with the board at `bounds` in a
+/// capture of `frame_size` pixels that showed `area` of the screen
+fn arrows_for(value: &serde_json::Value, frame_size: (u32, u32), bounds: (u32, u32, u32, u32), area: ScreenArea) -> Option<Arrows> {
+    let best = value["best_move"].as_str().or(value["engine"]["move"].as_str())?;
+    let best_eval = value["evaluation"].as_str().or(value["engine"]["evaluation"].as_str()).unwrap_or_default();
+    let best_squares = crate::tui::move_squares(best)?;
+    let flipped = value["player_side"].as_str() == Some("black");
+
+    // The best move, then the candidates that differ from it (a UCI engine lists it first)
+    let candidates = value["candidates"].as_array().into_iter().flatten().filter_map(|candidate| {
+        let squares = crate::tui::move_squares(candidate["move"].as_str()?)?;
+        Some((squares, candidate["evaluation"].as_str().unwrap_or_default()))
+    });
+    let mut moves = vec![(best_squares, best_eval)];
+    for (squares, eval) in candidates {
+        if !moves.iter().any(|(seen, _)| *seen == squares) {
+            moves.push((squares, eval));
+        }
+    }
+    let arrows = moves
+        .into_iter()
+        .zip(ARROW_COLORS)
+        .map(|(((from, to), eval), color)| Arrow {
+            from: square_center(from, flipped),
+            to: square_center(to, flipped),
+            color,
+            weight: arrow_weight(best_eval, eval),
+        })
+        .collect();
+
+    // Capture pixels (possibly downsampled) to screen units
+    let (frame_w, frame_h) = frame_size;
+    let (x, y, width, height) = bounds;
+    let scale_x = area.width as f32 / frame_w as f32;
+    let scale_y = area.height as f32 / frame_h as f32;
+    Some(Arrows {
+        x: area.x + (x as f32 * scale_x).round() as i32,
+        y: area.y + (y as f32 * scale_y).round() as i32,
+        width: (width as f32 * scale_x).round() as u32,
+        height: (height as f32 * scale_y).round() as u32,
+        arrows,
+    })
+}
+
+/// Thickness of a candidate's arrow relative to the best move's, shrinking with the winning
+/// chances it gives up (full thickness when either eval isn't an engine eval)
+fn arrow_weight(best_eval: &str, eval: &str) -> f32 {
+    let (Some(best), Some(chance)) = (eval_units::win_chance(best_eval), eval_units::win_chance(eval)) else {
+        return 1.0;
+    };
+    let gap = (best - chance).max(0.0);
+    ((1.0 - gap / THINNEST_GAP) as f32).max(MIN_WEIGHT)
+}
+
+/// Center of a square as a fraction of the displayed board (0,0 top left)
+fn square_center(square: Square, flipped: bool) -> (f32, f32) {
+    let (file, rank) = (u32::from(square.file()), u32::from(square.rank()));
+    let (col, row) = if flipped { (7 - file, rank) } else { (file, 7 - rank) };
+    ((col as f32 + 0.5) / 8.0, (row as f32 + 0.5) / 8.0)
+}
+
+/// Runs the overlay window until the pipeline closes its input (`overlay-window`)
+#[cfg(feature = "overlay")]
+pub fn run_window() -> Result<()> {
+    use std::io::BufRead;
+    use winit::event_loop::EventLoop;
+
+    let event_loop = EventLoop::<Message>::with_user_event().build().context("Failed to start the overlay window")?;
+    let proxy = event_loop.create_proxy();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if let Ok(arrows) = serde_json::from_str::<Option<Arrows>>(&line)
+                && proxy.send_event(Message::Show(arrows)).is_err()
+            {
+                return;
+            }
+        }
+        let _ = proxy.send_event(Message::Closed);
+    });
+    event_loop.run_app(&mut window::Overlay::default()).context("Overlay window failed")
+}
+
+/// Builds without the `overlay` feature have no window to run
+#[cfg(not(feature = "overlay"))]
+pub fn run_window() -> Result<()> {
+    anyhow::bail!("This build has no overlay window (cargo build --release --features overlay)")
+}
+
+/// Input to the window's event loop
+#[cfg(feature = "overlay")]
+enum Message {
+    /// Draw these arrows, or hide the window
+    Show(Option<Arrows>),
+    /// The pipeline has exited
+    Closed,
+}
+
+#[cfg(feature = "overlay")]
+mod window {
+    use super::{Arrow, Arrows, Message};
+    use anyhow::Result;
+    use std::num::NonZeroU32;
+    use std::rc::Rc;
+    use winit::application::ApplicationHandler;
+    use winit::dpi::{LogicalPosition, LogicalSize};
+    use winit::event::WindowEvent;
+    use winit::event_loop::ActiveEventLoop;
+    use winit::window::{Window, WindowId, WindowLevel};
+
+    type Surface = softbuffer::Surface<Rc<Window>, Rc<Window>>;
+
+    /// Shaft thickness, head length, and head half-width of the best move's arrow, in squares
+    const SHAFT_WIDTH: f32 = 0.18;
+    const HEAD_LENGTH: f32 = 0.45;
+    const HEAD_HALF_WIDTH: f32 = 0.3;
+
+    /// Pixels (ARGB, row-major) of a `width`×`height` window with `arrows` on a transparent
+    /// background, the first (best) arrow on top where they cross
+    pub fn arrow_pixels(width: u32, height: u32, arrows: &[Arrow]) -> Vec<u32> {
+        let mut pixels = vec![0; (width * height) as usize];
+        for arrow in arrows.iter().rev() {
+            draw_arrow(&mut pixels, width, height, arrow);
+        }
+        pixels
+    }
+
+    /// Paints one arrow over `pixels`
+    fn draw_arrow(pixels: &mut [u32], width: u32, height: u32, arrow: &Arrow) {
+        let square = width.min(height) as f32 / 8.0;
+        let from = (arrow.from.0 * width as f32, arrow.from.1 * height as f32);
+        let tip = (arrow.to.0 * width as f32, arrow.to.1 * height as f32);
+        let length = ((tip.0 - from.0).powi(2) + (tip.1 - from.1).powi(2)).sqrt().max(1.0);
+        let dir = ((tip.0 - from.0) / length, (tip.1 - from.1) / length);
+        let head = (HEAD_LENGTH * square).min(length);
+        let (shaft_half, head_half) = (SHAFT_WIDTH * square * arrow.weight / 2.0, HEAD_HALF_WIDTH * square * arrow.weight);
+
+        for (index, pixel) in pixels.iter_mut().enumerate() {
+            let (px, py) = ((index as u32 % width) as f32 + 0.5, (index as u32 / width) as f32 + 0.5);
+            // Position along the arrow and distance from its axis
+            let (dx, dy) = (px - from.0, py - from.1);
+            let along = dx * dir.0 + dy * dir.1;
+            let across = (dx * dir.1 - dy * dir.0).abs();
+            let in_shaft = (0.0..=length - head).contains(&along) && across <= shaft_half;
+            let in_head = along > length - head && along <= length && across <= head_half * (length - along) / head;
+            if in_shaft || in_head {
+                *pixel = arrow.color;
+            }
+        }
+    }
+
+    /// softbuffer's errors hold window handles, which can't cross threads as anyhow requires
+    fn display_error(e: softbuffer::SoftBufferError) -> anyhow::Error {
+        anyhow::anyhow!("{}", e)
+    }
+
+    #[derive(Default)]
+    pub struct Overlay {
+        window: Option<(Rc<Window>, Surface)>,
+        arrows: Option<Arrows>,
+    }
+
+    impl Overlay {
+        fn open(event_loop: &ActiveEventLoop) -> Result<(Rc<Window>, Surface)> {
+            let attributes = Window::default_attributes()
+                .with_title("Zugzwang overlay")
+                .with_transparent(true)
+                .with_decorations(false)
+                .with_resizable(false)
+                .with_active(false)
+                .with_window_level(WindowLevel::AlwaysOnTop)
+                .with_visible(false);
+            let window = Rc::new(event_loop.create_window(attributes)?);
+            // Clicks go through to the board
+            let _ = window.set_cursor_hittest(false);
+            let context = softbuffer::Context::new(Rc::clone(&window)).map_err(display_error)?;
+            let surface = softbuffer::Surface::new(&context, Rc::clone(&window)).map_err(display_error)?;
+            Ok((window, surface))
+        }
+
+        fn redraw(&mut self) -> Result<()> {
+            let (Some((window, surface)), Some(arrows)) = (&mut self.window, &self.arrows) else {
+                return Ok(());
+            };
+            let size = window.inner_size();
+            let (Some(width), Some(height)) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) else {
+                return Ok(());
+            };
+            surface.resize(width, height).map_err(display_error)?;
+            let mut buffer = surface.buffer_mut().map_err(display_error)?;
+            buffer.copy_from_slice(&arrow_pixels(size.width, size.height, &arrows.arrows));
+            buffer.present().map_err(display_error)?;
+            Ok(())
+        }
+    }
+
+    impl ApplicationHandler<Message> for Overlay {
+        fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+            if self.window.is_some() {
+                return;
+            }
+            match Overlay::open(event_loop) {
+                Ok(window) => self.window = Some(window),
+                Err(e) => {
+                    eprintln!("⚠ Overlay window failed: {:#}", e);
+                    event_loop.exit();
+                }
+            }
+        }
+
+        fn user_event(&mut self, event_loop: &ActiveEventLoop, message: Message) {
+            let arrows = match message {
+                Message::Show(arrows) => arrows,
+                Message::Closed => return event_loop.exit(),
+            };
+            if let Some((window, _)) = &self.window {
+                match &arrows {
+                    Some(arrows) => {
+                        window.set_outer_position(LogicalPosition::new(arrows.x, arrows.y));
+                        let _ = window.request_inner_size(LogicalSize::new(arrows.width, arrows.height));
+                        window.set_visible(true);
+                        window.request_redraw();
+                    }
+                    None => window.set_visible(false),
+                }
+            }
+            self.arrows = arrows;
+        }
+
+        fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+            match event {
+                WindowEvent::RedrawRequested => {
+                    if let Err(e) = self.redraw() {
+                        eprintln!("⚠ Overlay drawing failed: {:#}", e);
+                    }
+                }
+                WindowEvent::Resized(_) => {
+                    if let Some((window, _)) = &self.window {
+                        window.request_redraw();
+                    }
+                }
+                WindowEvent::CloseRequested => event_loop.exit(),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_square_center() {
+        assert_eq!(square_center(Square::A1, false), (0.0625, 0.9375));
+        assert_eq!(square_center(Square::H8, false), (0.9375, 0.0625));
+        // Black at the bottom: a1 is top right
+        assert_eq!(square_center(Square::A1, true), (0.9375, 0.0625));
+    }
+
+    #[test]
+    fn test_arrows_for_maps_board_to_screen() {
+        let value = serde_json::json!({ "fen": "8/8/8/8/8/8/8/K6k w - - 0 1", "best_move": "E2 to E4", "player_side": "white" });
+        // A 3840×2160 screen captured at 1920×1080, board at (100, 200) 800×800 in the capture
+        let area = ScreenArea { x: 0, y: 0, width: 3840, height: 2160 };
+        let arrows = arrows_for(&value, (1920, 1080), (100, 200, 800, 800), area).unwrap();
+        assert_eq!((arrows.x, arrows.y, arrows.width, arrows.height), (200, 400, 1600, 1600));
+        assert_eq!(arrows.arrows.len(), 1);
+        assert_eq!((arrows.arrows[0].from, arrows.arrows[0].to), ((0.5625, 0.8125), (0.5625, 0.5625)));
+        // No move to show (direct mode, game over)
+        assert_eq!(arrows_for(&serde_json::json!({ "best_move": "--" }), (1920, 1080), (0, 0, 800, 800), area), None);
+    }
+
+    #[test]
+    fn test_candidate_arrows_by_rank_and_gap() {
+        let value = serde_json::json!({
+            "best_move": "E2 to E4",
+            "evaluation": "+0.30",
+            "candidates": [
+                { "move": "E2 to E4", "evaluation": "+0.30" },
+                { "move": "D2 to D4", "evaluation": "+0.25" },
+                { "move": "G2 to G4", "evaluation": "-3.00" },
+            ],
+        });
+        let area = ScreenArea { x: 0, y: 0, width: 800, height: 800 };
+        let arrows = arrows_for(&value, (800, 800), (0, 0, 800, 800), area).unwrap().arrows;
+        // The best move isn't repeated from the candidates
+        let colors: Vec<_> = arrows.iter().map(|arrow| arrow.color).collect();
+        assert_eq!(colors, ARROW_COLORS[..3]);
+        assert_eq!(arrows[0].weight, 1.0);
+        assert!(arrows[1].weight < 1.0 && arrows[1].weight > 0.9);
+        assert_eq!(arrows[2].weight, MIN_WEIGHT);
+        // Evals in other units (or LLM wording) leave the arrows alike
+        assert_eq!(arrow_weight("win 53%", "+0.25"), 1.0);
+    }
+
+    #[cfg(feature = "overlay")]
+    #[test]
+    fn test_arrow_pixels() {
+        use window::arrow_pixels;
+        // e2 to e4 on a 256×256 board (32px squares), d2 to d4 half as thick
+        let arrow = |from, to, color, weight| Arrow { from, to, color, weight };
+        let arrows = [
+            arrow((0.5625, 0.8125), (0.5625, 0.5625), ARROW_COLORS[0], 1.0),
+            arrow((0.4375, 0.8125), (0.4375, 0.5625), ARROW_COLORS[1], 0.5),
+        ];
+        let pixels = arrow_pixels(256, 256, &arrows);
+        let at = |x: u32, y: u32| pixels[(y * 256 + x) as usize];
+        assert_eq!(at(144, 190), ARROW_COLORS[0]); // shaft, on e3
+        assert_eq!(at(144, 148), ARROW_COLORS[0]); // head, just short of the tip
+        assert_eq!(at(144, 140), 0); // past the tip
+        assert_eq!(at(10, 10), 0);
+        assert_eq!(at(160, 190), 0); // beside the shaft
+        assert_eq!(at(112, 190), ARROW_COLORS[1]); // the second shaft, on d3
+        assert_eq!(at(110, 190), 0); // beside the thinner shaft
+    }
+}
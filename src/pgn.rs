@@ -0,0 +1,599 @@
+//! PGN export of recorded sessions
+//!
+//! A session only stores the positions that were analyzed, not the moves between them,
+//! so the moves are reconstructed: from each position we search for the one or two plies
+//! (the user's move and the opponent's reply) that produce the next recognized board.
+//! Positions that cannot be connected that way (missed captures, OCR errors, a new game)
+//! start a new game with a `[SetUp "1"]` / `[FEN ...]` header.
+//!
+//! Each move carries the engine eval in lichess' `[%eval]` format (White's perspective)
+//! plus the suggested move and any commentary recorded for that position.
+//!
+//! Moves seen on their own (one ply between two recorded positions) also get the time the
+//! mover spent, from capture times, as `[%emt]`; with a `TimeControl` header and a game
+//! from the initial position, the remaining clock follows as `[%clk]` until a move's time
+//! is unknown.
+//!
+//! `read_game` goes the other way for training: it reads a game's tags and mainline
+//! moves (comments, variations, and NAGs are skipped).
+
+use anyhow::{Context, Result};
+use crate::profiles::PlayerProfile;
+use crate::game::{self, GameTree};
+use crate::session::SessionEntry;
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::{Bitboard, Board, CastlingMode, Chess, Color, FromSetup, Move, Position, PositionError};
+
+/// Standard starting placement, used to decide whether a game needs a FEN header
+const START_PLACEMENT: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
+
+/// PGN tag pairs, kept in insertion order (the Seven Tag Roster first)
+#[derive(Clone, Debug)]
+pub struct Headers {
+    tags: Vec<(String, String)>,
+}
+
+impl Headers {
+    /// Seven Tag Roster with the given event, today's date, and unknown players
+    pub fn new(event: &str) -> Self {
+        let mut headers = Headers { tags: Vec::new() };
+        for (name, value) in [
+            ("Event", event),
+            ("Site", "?"),
+            ("Date", &today()),
+            ("Round", "-"),
+            ("White", "?"),
+            ("Black", "?"),
+            ("Result", "*"),
+        ] {
+            headers.set(name, value);
+        }
+        headers
+    }
+
+    /// Sets a tag, replacing an existing value
+    pub fn set(&mut self, name: &str, value: &str) {
+        let value = value.replace(['"', '\\'], "");
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some(tag) => tag.1 = value,
+            None => self.tags.push((name.to_string(), value)),
+        }
+    }
+
+    /// Fills the name, Elo, and title tags for one side
+    pub fn set_player(&mut self, color: Color, profile: &PlayerProfile) {
+        let side = if color == Color::White { "White" } else { "Black" };
+        self.set(side, &profile.username);
+        if let Some(rating) = profile.rating {
+            self.set(&format!("{}Elo", side), &rating.to_string());
+        }
+        if let Some(title) = &profile.title {
+            self.set(&format!("{}Title", side), title);
+        }
+    }
+
+    /// Value of a tag, if present
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn render(&self, start_fen: Option<&str>) -> String {
+        let mut out: Vec<String> = self.tags.iter().map(|(n, v)| format!("[{} \"{}\"]", n, v)).collect();
+        if let Some(fen) = start_fen {
+            out.push("[SetUp \"1\"]".to_string());
+            out.push(format!("[FEN \"{}\"]", fen));
+        }
+        out.join("\n")
+    }
+}
+
+/// Formats one game: tags (with a FEN header unless it starts from the initial position)
+/// and movetext terminated by the result
+pub fn render_game(headers: &Headers, start_fen: &str, movetext: &str) -> String {
+    let setup = !start_fen.starts_with(&format!("{} w KQkq", START_PLACEMENT));
+    let mut movetext = movetext.to_string();
+    if !movetext.is_empty() {
+        movetext.push(' ');
+    }
+    movetext.push('*');
+    format!("{}\n\n{}\n", headers.render(setup.then_some(start_fen)), movetext)
+}
+
+/// Eval drops (pawns, mover's perspective) for annotating the user's move when it differs
+/// from the suggestion
+const DUBIOUS_DROP: f64 = 0.5;
+const MISTAKE_DROP: f64 = 1.0;
+const BLUNDER_DROP: f64 = 3.0;
+
+/// Time a side spent on one move: from the first capture of the position before it to the
+/// capture of the position after it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveTime {
+    pub mover: Color,
+    pub spent_ms: u64,
+    /// Index of the session entry the move led to
+    pub entry: usize,
+}
+
+/// Converts a session into PGN text, one game per connected run of positions
+pub fn session_to_pgn(entries: &[SessionEntry], headers: &Headers) -> String {
+    let time_control = headers.get("TimeControl").and_then(parse_time_control);
+    let (games, _) = replay(entries, time_control);
+    games.iter().map(|g| g.to_pgn(headers)).collect::<Vec<_>>().join("\n")
+}
+
+/// Times of the moves seen on their own; when two plies happened between captures, how
+/// they split the time is unknown
+pub fn move_times(entries: &[SessionEntry]) -> Vec<MoveTime> {
+    replay(entries, None).1
+}
+
+/// Reconstructs the games of a session with their timed moves. `time_control` (base and
+/// increment in ms) adds clocks.
+fn replay(entries: &[SessionEntry], time_control: Option<(u64, u64)>) -> (Vec<GameTree>, Vec<MoveTime>) {
+    let mut games: Vec<GameTree> = Vec::new();
+    let mut times = Vec::new();
+    // Last entry of the current game, whose suggestion the next move is judged against
+    let mut previous: Option<&SessionEntry> = None;
+    // Remaining time of White and Black while every move of the game so far was timed
+    let mut clocks: Option<[u64; 2]> = None;
+
+    for (index, entry) in entries.iter().enumerate() {
+        let Some(position) = parse_position(&entry.fen) else {
+            continue;
+        };
+        let moves = games.last().and_then(|game| connect(game.position(), position.board()));
+        let mut timing = None;
+        match (games.last_mut(), moves) {
+            // Same board re-analyzed: nothing new to record
+            (Some(_), Some(moves)) if moves.is_empty() => continue,
+            (Some(game), Some(moves)) => {
+                let mover = game.position().turn();
+                game.play(moves[0]);
+                let nag = previous.and_then(|prev| judge_move(prev, entry, moves[0], mover));
+                if let Some(nag) = nag {
+                    game.add_nag(game.cursor(), nag);
+                }
+                moves[1..].iter().for_each(|&m| {
+                    game.play(m);
+                });
+                match previous.filter(|_| moves.len() == 1) {
+                    Some(prev) => {
+                        let spent_ms = captured_ms(entry).saturating_sub(captured_ms(prev)) as u64;
+                        times.push(MoveTime { mover, spent_ms, entry: index });
+                        let clock = clocks.as_mut().zip(time_control).map(|(clocks, (_, increment))| {
+                            let side = &mut clocks[usize::from(mover == Color::Black)];
+                            *side = side.saturating_sub(spent_ms) + increment;
+                            *side
+                        });
+                        timing = Some(timing_tags(spent_ms, clock));
+                    }
+                    None => clocks = None,
+                }
+            }
+            _ => {
+                games.push(GameTree::new(position));
+                let from_start = entry.fen.starts_with(&format!("{} w", START_PLACEMENT));
+                clocks = time_control.filter(|_| from_start).map(|(base, _)| [base, base]);
+            }
+        }
+        let game = games.last_mut().expect("a game was just started or extended");
+        game.add_comment(&comment_for(entry, timing.as_deref()));
+        previous = Some(entry);
+    }
+
+    (games, times)
+}
+
+/// When a session entry's board was captured (when it was recorded, for older sessions)
+fn captured_ms(entry: &SessionEntry) -> u128 {
+    entry.captured_ms.unwrap_or(entry.timestamp_ms)
+}
+
+/// Base time and increment in ms from a PGN `TimeControl` ("180+2", "600"); None for
+/// unknown, untimed, or moves-per-period controls
+fn parse_time_control(tc: &str) -> Option<(u64, u64)> {
+    let (base, increment) = tc.split_once('+').unwrap_or((tc, "0"));
+    Some((base.parse::<u64>().ok()? * 1000, increment.parse::<u64>().ok()? * 1000))
+}
+
+/// `[%clk]` (when known) and `[%emt]` tags for a timed move
+fn timing_tags(spent_ms: u64, clock_ms: Option<u64>) -> String {
+    let emt = format!("[%emt {}]", clock_text((spent_ms + 500) / 1000));
+    match clock_ms {
+        Some(clock) => format!("[%clk {}] {}", clock_text(clock / 1000), emt),
+        None => emt,
+    }
+}
+
+/// Seconds as `H:MM:SS`
+fn clock_text(secs: u64) -> String {
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// NAG for a move that deviated from the suggestion, by how much the eval dropped
+fn judge_move(before: &SessionEntry, after: &SessionEntry, played: Move, mover: Color) -> Option<u8> {
+    let readable = crate::engine::format_move_readable(&played.to_uci(CastlingMode::Standard).to_string());
+    if before.best_move.eq_ignore_ascii_case(&readable) {
+        return None;
+    }
+    let sign = if mover == Color::White { 1.0 } else { -1.0 };
+    let drop = (white_pawns(before)? - white_pawns(after)?) * sign;
+    match drop {
+        d if d >= BLUNDER_DROP => Some(game::NAG_BLUNDER),
+        d if d >= MISTAKE_DROP => Some(game::NAG_MISTAKE),
+        d if d >= DUBIOUS_DROP => Some(game::NAG_DUBIOUS),
+        _ => None,
+    }
+}
+
+/// A game read from PGN
+#[derive(Debug)]
+pub struct PgnGame {
+    pub headers: Headers,
+    /// Starting position (the `FEN` tag, or the standard start)
+    pub start: Chess,
+    /// Mainline moves in order
+    pub moves: Vec<Move>,
+}
+
+/// Reads the first game of a PGN text: its tags and mainline moves
+pub fn read_game(text: &str) -> Result<PgnGame> {
+    let (headers, movetext) = split_games(text).into_iter().next().context("No moves found in PGN")?;
+    let start = start_position(&headers)?;
+
+    let mut position = start.clone();
+    let mut moves = Vec::new();
+    for token in mainline_tokens(&movetext) {
+        let m = parse_san(&position, &token)
+            .with_context(|| format!("Illegal or unreadable move '{}' after {} plies", token, moves.len()))?;
+        position.play_unchecked(m);
+        moves.push(m);
+    }
+    anyhow::ensure!(!moves.is_empty(), "No moves found in PGN");
+    Ok(PgnGame { headers, start, moves })
+}
+
+/// Splits a PGN text into its games' tags and movetext
+pub fn split_games(text: &str) -> Vec<(Headers, String)> {
+    let mut games = Vec::new();
+    let mut headers = Headers { tags: Vec::new() };
+    let mut movetext = String::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(tag) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            if !movetext.trim().is_empty() {
+                // next game's tags
+                games.push((std::mem::replace(&mut headers, Headers { tags: Vec::new() }), std::mem::take(&mut movetext)));
+            }
+            if let Some((name, value)) = tag.split_once(' ') {
+                headers.set(name, value.trim().trim_matches('"'));
+            }
+        } else if !line.starts_with('%') {
+            // `;` comments run to the end of the line
+            movetext.push_str(line.split(';').next().unwrap_or_default());
+            movetext.push('\n');
+        }
+    }
+    if !movetext.trim().is_empty() {
+        games.push((headers, movetext));
+    }
+    games
+}
+
+/// Starting position of a game (the `FEN` tag, or the standard start)
+pub fn start_position(headers: &Headers) -> Result<Chess> {
+    match headers.get("FEN") {
+        Some(fen) => Fen::from_ascii(fen.as_bytes())
+            .ok()
+            .and_then(|fen| fen.into_position(CastlingMode::Standard).ok())
+            .with_context(|| format!("Invalid FEN tag: {}", fen)),
+        None => Ok(Chess::default()),
+    }
+}
+
+/// Legal move for a SAN token as written in PGN files; None if unreadable or illegal
+pub fn parse_san(position: &Chess, token: &str) -> Option<Move> {
+    // Annotation glyphs ("!?", "??") aren't part of SAN; castling may be written with zeros
+    let mut san_text = token.trim_end_matches(['!', '?']).to_string();
+    if san_text.starts_with("0-0") {
+        san_text = san_text.replace('0', "O");
+    }
+    san_text.parse::<SanPlus>().ok().and_then(|san| san.san.to_move(position).ok())
+}
+
+/// SAN tokens of the mainline: drops comments, variations, NAGs, move numbers, and the
+/// result (which ends the game)
+fn mainline_tokens(movetext: &str) -> Vec<String> {
+    let mut depth = 0; // variation nesting
+    movetext_tokens(movetext)
+        .into_iter()
+        .filter(|token| match token.as_str() {
+            "(" => {
+                depth += 1;
+                false
+            }
+            ")" => {
+                depth -= 1;
+                false
+            }
+            _ => depth == 0,
+        })
+        .collect()
+}
+
+/// SAN tokens with variations as "(" and ")" tokens: drops comments, NAGs, move numbers,
+/// and the result (which ends the game)
+pub fn movetext_tokens(movetext: &str) -> Vec<String> {
+    let mut text = String::new();
+    let mut in_comment = false;
+    for c in movetext.chars() {
+        match c {
+            '}' if in_comment => in_comment = false,
+            _ if in_comment => {}
+            '{' => in_comment = true,
+            // Keep variation markers apart from the moves around them
+            '(' | ')' => {
+                text.push(' ');
+                text.push(c);
+                text.push(' ');
+            }
+            _ => text.push(c),
+        }
+        // Keep tokens on either side of a comment apart
+        if matches!(c, '{' | '}') {
+            text.push(' ');
+        }
+    }
+
+    text.split_whitespace()
+        .take_while(|t| !matches!(*t, "1-0" | "0-1" | "1/2-1/2" | "*"))
+        // "12." / "12..." / "12.e4": keep only what follows the move number
+        .map(|t| match t.find(|c: char| !c.is_ascii_digit() && c != '.') {
+            Some(i) if t[..i].contains('.') => &t[i..],
+            Some(_) => t,
+            None => "",
+        })
+        .filter(|t| !t.is_empty() && !t.starts_with('$'))
+        .map(String::from)
+        .collect()
+}
+
+/// Parses a recognized FEN, tolerating castling/en passant fields OCR cannot know.
+/// OCR never reports castling rights, so they are assumed wherever king and rook are home.
+pub fn parse_position(fen: &str) -> Option<Chess> {
+    let mut setup = Fen::from_ascii(fen.as_bytes()).ok()?.into_setup();
+    if setup.castling_rights.is_empty() {
+        setup.castling_rights = Bitboard::CORNERS;
+    }
+    Chess::from_setup(setup, CastlingMode::Standard)
+        .or_else(PositionError::ignore_invalid_castling_rights)
+        .or_else(PositionError::ignore_invalid_ep_square)
+        .ok()
+}
+
+/// Finds up to two plies leading from `from` to `target` (empty if already there)
+pub fn connect(from: &Chess, target: &Board) -> Option<Vec<Move>> {
+    if from.board() == target {
+        return Some(Vec::new());
+    }
+    let mut two_ply = None;
+    for first in from.legal_moves() {
+        let mut after = from.clone();
+        after.play_unchecked(first);
+        if after.board() == target {
+            return Some(vec![first]);
+        }
+        if two_ply.is_none() {
+            two_ply = after
+                .legal_moves()
+                .into_iter()
+                .find(|reply| {
+                    let mut next = after.clone();
+                    next.play_unchecked(*reply);
+                    next.board() == target
+                })
+                .map(|reply| vec![first, reply]);
+        }
+    }
+    two_ply
+}
+
+/// Comment text for a position: `[%eval]` (White's perspective), the timing tags of the
+/// move that led to it, suggestion, commentary
+fn comment_for(entry: &SessionEntry, timing: Option<&str>) -> String {
+    let mut parts = Vec::new();
+    if let Some(eval) = white_eval(entry) {
+        parts.push(format!("[%eval {}]", eval));
+    }
+    if let Some(timing) = timing {
+        parts.push(timing.to_string());
+    }
+    if entry.best_move != "--" && !entry.best_move.is_empty() {
+        parts.push(format!("Best: {}.", entry.best_move));
+    }
+    if let Some(comment) = &entry.comment {
+        parts.push(comment.trim().to_string());
+    }
+    parts.join(" ")
+}
+
+/// Converts a side-to-move eval like "+0.35" or "#-2" into White's perspective ("0.35",
+/// "#2" with Black to move), as `[%eval]` expects
+fn white_eval(entry: &SessionEntry) -> Option<String> {
+    if let Some(moves) = entry.evaluation.trim().strip_prefix('#') {
+        let moves: i32 = moves.parse().ok()?;
+        return Some(format!("#{}", if black_to_move(entry) { -moves } else { moves }));
+    }
+    white_pawns(entry).map(|pawns| format!("{:.2}", pawns))
+}
+
+/// Numeric eval in pawns from White's perspective (None for "Stalemate" and the like)
+fn white_pawns(entry: &SessionEntry) -> Option<f64> {
+    let pawns: f64 = entry.evaluation.trim().trim_start_matches('+').parse().ok()?;
+    Some(if black_to_move(entry) { -pawns } else { pawns })
+}
+
+fn black_to_move(entry: &SessionEntry) -> bool {
+    entry.fen.split_whitespace().nth(1) == Some("b")
+}
+
+/// Today's date in PGN format (YYYY.MM.DD, UTC)
+fn today() -> String {
+    let days = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or_default() as i64;
+    // Civil-from-days (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(fen: &str, best_move: &str, evaluation: &str, comment: Option<&str>) -> SessionEntry {
+        SessionEntry {
+            cycle: None,
+            timestamp_ms: 0,
+            captured_ms: None,
+            fen: fen.to_string(),
+            best_move: best_move.to_string(),
+            evaluation: evaluation.to_string(),
+            comment: comment.map(String::from),
+            frame: None,
+        }
+    }
+
+    #[test]
+    fn test_moves_reconstructed_between_positions() {
+        // OCR always reports the user's side to move, so consecutive positions are two plies apart
+        let entries = vec![
+            entry("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1", "E2 to E4", "+0.30", None),
+            entry("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w - - 0 1", "G1 to F3", "+0.25", Some("Develop {fast}")),
+            entry("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w - - 0 1", "G1 to F3", "+0.25", None),
+        ];
+        let pgn = session_to_pgn(&entries, &Headers::new("Test"));
+        assert!(pgn.contains("[Event \"Test\"]"));
+        assert!(!pgn.contains("[SetUp"));
+        assert!(pgn.contains("{ [%eval 0.30] Best: E2 to E4. } 1. e4 e5 { [%eval 0.25] Best: G1 to F3. Develop (fast) } *"));
+    }
+
+    #[test]
+    fn test_deviation_from_suggestion_gets_nag() {
+        let entries = vec![
+            entry("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1", "D2 to D4", "+0.30", None),
+            entry("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w - - 0 1", "G1 to F3", "-1.00", None),
+        ];
+        let pgn = session_to_pgn(&entries, &Headers::new("Test"));
+        assert!(pgn.contains("1. e4 $2 e5"), "{}", pgn);
+    }
+
+    #[test]
+    fn test_disconnected_position_starts_new_game() {
+        let entries = vec![
+            entry("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1", "E2 to E4", "+0.30", None),
+            entry("8/8/8/8/8/8/8/K6k b - - 0 1", "--", "Stalemate", None),
+        ];
+        let pgn = session_to_pgn(&entries, &Headers::new("Test"));
+        assert_eq!(pgn.matches("[Event").count(), 2);
+        assert!(pgn.contains("[FEN \"8/8/8/8/8/8/8/K6k b - - 0 1\"]"));
+    }
+
+    #[test]
+    fn test_single_plies_get_move_times_and_clocks() {
+        let timed = |fen: &str, captured_ms: u128| SessionEntry { captured_ms: Some(captured_ms), ..entry(fen, "--", "+0.20", None) };
+        let entries = vec![
+            timed("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1", 1_000),
+            timed("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b - - 0 1", 6_000),
+            timed("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w - - 0 1", 13_000),
+        ];
+        let mut headers = Headers::new("Test");
+        headers.set("TimeControl", "180+2");
+        let pgn = session_to_pgn(&entries, &headers);
+        assert!(pgn.contains("1. e4 { [%eval -0.20] [%clk 0:02:57] [%emt 0:00:05] }"));
+        assert!(pgn.contains("1... e5 { [%eval 0.20] [%clk 0:02:55] [%emt 0:00:07] }"));
+        assert!(!session_to_pgn(&entries, &Headers::new("Test")).contains("%clk"));
+        let times = move_times(&entries);
+        assert_eq!(times.iter().map(|t| (t.mover, t.spent_ms)).collect::<Vec<_>>(), vec![(Color::White, 5_000), (Color::Black, 7_000)]);
+        assert_eq!(times[1].entry, 2);
+    }
+
+    #[test]
+    fn test_parse_time_control() {
+        assert_eq!(parse_time_control("180+2"), Some((180_000, 2_000)));
+        assert_eq!(parse_time_control("600"), Some((600_000, 0)));
+        assert_eq!(parse_time_control("-"), None);
+        assert_eq!(parse_time_control("40/9000"), None);
+    }
+
+    #[test]
+    fn test_eval_converted_to_white_perspective() {
+        let black = entry("8/8/8/8/8/8/8/K6k b - - 0 1", "H1 to G2", "+1.50", None);
+        assert_eq!(white_eval(&black).as_deref(), Some("-1.50"));
+        let mate = entry("8/8/8/8/8/8/8/K6k w - - 0 1", "--", "Black wins by checkmate", None);
+        assert_eq!(white_eval(&mate), None);
+    }
+
+    #[test]
+    fn test_mate_eval_converted_to_white_perspective() {
+        let white = entry("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "A1 to A8", "#1", None);
+        assert_eq!(white_eval(&white).as_deref(), Some("#1"));
+        assert!(comment_for(&white, None).starts_with("[%eval #1] "));
+        let black = entry("r5k1/8/8/8/8/8/5PPP/6K1 b - - 0 1", "A8 to A1", "#1", None);
+        assert_eq!(white_eval(&black).as_deref(), Some("#-1"));
+        let mated = entry("8/8/8/8/8/8/8/K6k b - - 0 1", "--", "#-3", None);
+        assert_eq!(white_eval(&mated).as_deref(), Some("#3"));
+    }
+
+    #[test]
+    fn test_headers_replace_and_sanitize() {
+        let mut headers = Headers::new("Test");
+        headers.set("White", "some\"one");
+        headers.set("WhiteElo", "1500");
+        let rendered = headers.render(None);
+        assert!(rendered.contains("[White \"someone\"]"));
+        assert!(rendered.ends_with("[WhiteElo \"1500\"]"));
+        assert_eq!(rendered.matches("[White ").count(), 1);
+    }
+
+    #[test]
+    fn test_player_headers() {
+        let mut headers = Headers::new("Test");
+        headers.set_player(Color::Black, &PlayerProfile { username: "Hikaru".to_string(), rating: Some(3300), title: Some("GM".to_string()) });
+        headers.set_player(Color::White, &PlayerProfile::named("me"));
+        let rendered = headers.render(None);
+        assert!(rendered.contains("[White \"me\"]\n[Black \"Hikaru\"]"));
+        assert!(rendered.contains("[BlackElo \"3300\"]\n[BlackTitle \"GM\"]"));
+        assert!(!rendered.contains("WhiteElo"));
+    }
+
+    #[test]
+    fn test_read_game_skips_comments_variations_and_nags() {
+        let text = "[Event \"Casual\"]\n[White \"Morphy\"]\n\n\
+            1. e4 e5 {Open game} 2. Nf3 (2. f4 exf4) d6 $1 3. d4!? Bg4 ; pin\n\
+            4. dxe5 Bxf3 5. Qxf3 dxe5 6. Bc4 Nf6 7. Qb3 Qe7 8. Nc3 c6 9. Bg5 b5 10. Nxb5 cxb5\n\
+            11. Bxb5+ Nbd7 12. 0-0-0 Rd8 1-0\n";
+        let game = read_game(text).unwrap();
+        assert_eq!(game.headers.get("White"), Some("Morphy"));
+        assert_eq!(game.moves.len(), 24);
+        assert_eq!(game.moves[2].to_string(), "Ng1-f3");
+        assert!(game.moves[22].is_castle());
+    }
+
+    #[test]
+    fn test_read_game_rejects_illegal_move() {
+        let err = read_game("1. e4 e5 2. Ke3 *").unwrap_err();
+        assert!(err.to_string().contains("'Ke3'"));
+    }
+}
@@ -0,0 +1,142 @@
+//! The capture → FEN → analysis pipeline as a library API
+//!
+//! For apps embedding ZugzwangRS instead of running the binary: a `Pipeline` pairs an OCR
+//! backend (`ocr::OcrBackend`: a built-in one from `ocr::backend`, or the app's own) with an
+//! engine (`engine::Engine`) and turns a screenshot into a checked FEN and its analysis.
+//!
+//! ```no_run
+//! use zugzwang_core::ocr::OcrMode;
+//! use zugzwang_core::pipeline::Pipeline;
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let mut pipeline = Pipeline::with_mode(OcrMode::Native)?;
+//! pipeline.site = "lichess".to_string();
+//! pipeline.candidates = 3;
+//! let result = pipeline.capture_and_analyze().await?;
+//! println!("{}: {} ({})", result.fen, result.analysis.best_move, result.analysis.eval);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Global settings (LLM provider and keys, native OCR thresholds, a UCI engine, a FEN
+//! post-processor) are read from the environment and the config file as in the CLI, or set
+//! with their modules' `set_*` functions before the first call. Recognized FENs go through
+//! the `fen_hook` like the CLI's, so both read the same frame as the same position.
+
+use anyhow::{Context, Result};
+use crate::PlayerSide;
+use crate::engine::{self, Analysis, Engine, SelectedEngine};
+use crate::ocr::{OcrBackend, OcrMode, OcrRequest};
+use image::DynamicImage;
+use std::sync::Arc;
+
+/// OCR backend and engine, with the settings of one board
+pub struct Pipeline {
+    ocr: Arc<dyn OcrBackend>,
+    engine: Arc<dyn Engine>,
+    /// Chess site, selecting native templates (default "chesscom")
+    pub site: String,
+    /// Board orientation and side to move
+    pub player_side: PlayerSide,
+    /// Search depth in plies
+    pub depth: u16,
+    /// Candidate moves to rank besides the best one (0: none)
+    pub candidates: usize,
+}
+
+/// A recognized position and its analysis
+#[derive(Clone, Debug, PartialEq)]
+pub struct PipelineResult {
+    pub fen: String,
+    pub analysis: Analysis,
+}
+
+impl Pipeline {
+    /// Pipeline with the given backend and engine, at the CLI's default settings
+    pub fn new(ocr: Arc<dyn OcrBackend>, engine: Arc<dyn Engine>) -> Self {
+        Pipeline {
+            ocr,
+            engine,
+            site: "chesscom".to_string(),
+            player_side: PlayerSide::default(),
+            depth: engine::backend_depth(engine::DEFAULT_DEPTH),
+            candidates: 0,
+        }
+    }
+
+    /// Pipeline with a registered OCR backend and the selected engine
+    pub fn with_mode(mode: OcrMode) -> Result<Self> {
+        Ok(Pipeline::new(crate::ocr::backend(mode)?, Arc::new(SelectedEngine)))
+    }
+
+    /// Recognizes the board in `image` and analyzes it
+    pub async fn analyze_image(&self, image: Arc<DynamicImage>) -> Result<PipelineResult> {
+        let request = OcrRequest {
+            image,
+            image_path: None,
+            site: self.site.clone(),
+            player_side: self.player_side,
+        };
+        let fen = self.ocr.recognize(&request).await.context("Failed to recognize the board")?;
+        let fen = crate::fen_hook::apply(fen, self.player_side).await;
+        engine::check_position(&fen)?;
+
+        let (engine, depth, candidates) = (Arc::clone(&self.engine), self.depth, self.candidates);
+        let position = fen.clone();
+        let analysis = tokio::task::spawn_blocking(move || engine.analyze(&position, depth, candidates))
+            .await
+            .map_err(|e| anyhow::anyhow!("Engine task failed: {}", e))??;
+        Ok(PipelineResult { fen, analysis })
+    }
+
+    /// Captures the screen (as configured in `capture`) and analyzes the board on it
+    pub async fn capture_and_analyze(&self) -> Result<PipelineResult> {
+        let frame = crate::capture::capture_screenshot()?;
+        self.analyze_image(frame.image).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocr::{BoxFuture, Fen};
+
+    /// Reads every image as the same position
+    struct FixedOcr(&'static str);
+
+    impl OcrBackend for FixedOcr {
+        fn recognize<'a>(&'a self, _request: &'a OcrRequest) -> BoxFuture<'a, Result<Fen>> {
+            Box::pin(async move { Ok(self.0.to_string()) })
+        }
+    }
+
+    /// Always answers e2e4, with the depth as its eval
+    struct FixedEngine;
+
+    impl Engine for FixedEngine {
+        fn analyze(&self, _fen: &str, depth: u16, _count: usize) -> Result<Analysis> {
+            Ok(Analysis { best_move: "e2e4".to_string(), eval: format!("+0.{}", depth), candidates: Vec::new() })
+        }
+    }
+
+    fn image() -> Arc<DynamicImage> {
+        Arc::new(DynamicImage::new_rgb8(8, 8))
+    }
+
+    #[tokio::test]
+    async fn test_custom_backend_and_engine() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let mut pipeline = Pipeline::new(Arc::new(FixedOcr(start)), Arc::new(FixedEngine));
+        pipeline.depth = 4;
+        let result = pipeline.analyze_image(image()).await.unwrap();
+        assert_eq!(result.fen, start);
+        assert_eq!((result.analysis.best_move.as_str(), result.analysis.eval.as_str()), ("e2e4", "+0.4"));
+    }
+
+    #[tokio::test]
+    async fn test_misread_boards_are_rejected() {
+        // No white king
+        let pipeline = Pipeline::new(Arc::new(FixedOcr("4k3/8/8/8/8/8/8/8 w - - 0 1")), Arc::new(FixedEngine));
+        assert!(pipeline.analyze_image(image()).await.is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! Practice mode against the built-in engine
+//!
+//! `zugzwang-rs play` runs a game on a text board in the terminal - no capture or OCR.
+//! The user types moves ("e2e4", "E2 to E4", "O-O"); each one is scored against the
+//! engine's choice with the same move/eval output as the live assistant, then the
+//! engine replies. Handy for trying depth settings and for offline practice.
+//!
+//! With `--rating` the engine becomes a sparring partner (see `sparring`), and every
+//! game ends with an accuracy review of the user's moves.
+
+use anyhow::{Context, Result};
+use crate::PlayerSide;
+use crate::engine;
+use crate::sparring::{Review, Sparring};
+use std::io::{self, BufRead, Write};
+
+/// Standard starting position
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Plays a game from `start_fen`, the user taking `player_side` against the engine at `depth`.
+/// With `rating`, the engine plays as a rating-limited sparring partner.
+pub fn run(start_fen: &str, player_side: PlayerSide, depth: u16, rating: Option<u16>) -> Result<()> {
+    let mut fen = start_fen.to_string();
+    let mut sparring = rating.map(Sparring::new);
+    let mut review = Review::default();
+    let mut last_was_gift = false;
+
+    match rating {
+        Some(r) => println!("Sparring as {} against a ~{} rated engine (depth {}).", player_side, r, depth),
+        None => println!("Playing {} against the engine (depth {}).", player_side, depth),
+    }
+    println!("Type a move, 'board', 'fen', or 'quit'.");
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        println!();
+        println!("{}", render_board(&fen, player_side)?);
+
+        let scored = engine::score_moves(&fen, depth)?;
+        if scored.is_empty() {
+            // No legal moves: the engine reports checkmate or stalemate
+            let (_, result) = engine::analyze_position(&fen, depth)?;
+            println!("Game over: {}", result);
+            break;
+        }
+
+        if side_to_move(&fen) != player_side {
+            let (reply, gift) = match sparring.as_mut() {
+                Some(s) => s.choose(&scored).context("No move to play")?,
+                None => (engine::analyze_position(&fen, depth)?.0, false),
+            };
+            let eval = scored.iter().find(|(mv, _)| *mv == reply).map_or(0, |(_, cp)| *cp);
+            println!("Engine plays: {} ({})", reply, engine::format_eval(eval));
+            fen = engine::apply_move(&fen, &reply)?;
+            last_was_gift = gift;
+            continue;
+        }
+
+        // User's turn: read until a legal move is entered, then compare with the best move
+        let (best_move, best_cp) = scored[0].clone();
+        loop {
+            print!("Your move: ");
+            io::stdout().flush()?;
+            let Some(line) = lines.next() else {
+                println!();
+                println!("{}", review.report());
+                return Ok(()); // stdin closed
+            };
+            let line = line.context("Failed to read move")?;
+            match line.trim() {
+                "quit" | "q" => {
+                    println!("{}", review.report());
+                    return Ok(());
+                }
+                "board" => println!("{}", render_board(&fen, player_side)?),
+                "fen" => println!("FEN:  {}", fen),
+                text => match engine::normalize_move(&fen, text)? {
+                    Some(played) => {
+                        let played_cp = scored.iter().find(|(mv, _)| *mv == played).map_or(best_cp, |(_, cp)| *cp);
+                        if played_cp >= best_cp {
+                            println!("You: {} ({}) - as good as the engine's pick", played, engine::format_eval(played_cp));
+                        } else {
+                            println!(
+                                "You: {} ({})   Best: {} ({})",
+                                played,
+                                engine::format_eval(played_cp),
+                                best_move,
+                                engine::format_eval(best_cp)
+                            );
+                        }
+                        review.record(&played, played_cp, &best_move, best_cp, last_was_gift);
+                        fen = engine::apply_move(&fen, &played)?;
+                        break;
+                    }
+                    None => println!("'{}' is not a legal move here", text),
+                },
+            }
+        }
+    }
+
+    println!();
+    println!("{}", review.report());
+    Ok(())
+}
+
+/// Side to move according to the FEN
+pub fn side_to_move(fen: &str) -> PlayerSide {
+    match fen.split_whitespace().nth(1) {
+        Some("b") => PlayerSide::Black,
+        _ => PlayerSide::White,
+    }
+}
+
+/// Draws the board as text from `player_side`'s point of view ('.' = empty square)
+pub fn render_board(fen: &str, player_side: PlayerSide) -> Result<String> {
+    let grid = crate::correction::parse_placement(fen)?;
+
+    let rows: Vec<usize> = if player_side.needs_board_flip() { (0..8).rev().collect() } else { (0..8).collect() };
+    let files: Vec<usize> = if player_side.needs_board_flip() { (0..8).rev().collect() } else { (0..8).collect() };
+
+    let mut out = String::new();
+    for &row in &rows {
+        out.push_str(&format!("  {} ", 8 - row));
+        for &col in &files {
+            let piece = grid[row][col];
+            out.push(' ');
+            out.push(if piece == '1' { '.' } else { piece });
+        }
+        out.push('\n');
+    }
+    out.push_str("    ");
+    for &col in &files {
+        out.push(' ');
+        out.push((b'a' + col as u8) as char);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_board_white_view() {
+        let board = render_board(START_FEN, PlayerSide::White).unwrap();
+        let lines: Vec<&str> = board.lines().collect();
+        assert_eq!(lines[0], "  8  r n b q k b n r");
+        assert_eq!(lines[4], "  4  . . . . . . . .");
+        assert_eq!(lines[8], "     a b c d e f g h");
+    }
+
+    #[test]
+    fn test_render_board_black_view() {
+        let board = render_board(START_FEN, PlayerSide::Black).unwrap();
+        let lines: Vec<&str> = board.lines().collect();
+        assert_eq!(lines[0], "  1  R N B K Q B N R");
+        assert_eq!(lines[8], "     h g f e d c b a");
+    }
+
+    #[test]
+    fn test_side_to_move() {
+        assert_eq!(side_to_move(START_FEN), PlayerSide::White);
+        assert_eq!(side_to_move("8/8/8/8/8/8/8/K6k b - - 0 1"), PlayerSide::Black);
+    }
+}
@@ -0,0 +1,149 @@
+//! Player name plates
+//!
+//! Chess sites show each player's name and rating next to the board: the opponent above
+//! it, the user below it (chess.com), or in the side panel to the right (lichess). Once
+//! per session the strips above and below the detected board (extended to the right) are
+//! cropped and read with a low-detail LLM request, and the result is stored with the
+//! session details for PGN headers.
+//!
+//! Reading names off the screen is on by default and can be turned off with
+//! `--no-player-ocr` for privacy; it is also skipped when no LLM is configured.
+
+use anyhow::Result;
+use image::{DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether name plates may be read (cleared by --no-player-ocr)
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+const PLATE_PROMPT: &str = "This is a strip of a chess website next to the board. \
+If it shows a player's username, reply exactly:\nNAME: <username>\nRATING: <rating or none>\n\
+If no username is visible, reply NONE. Ignore clocks, captured pieces, and flags.";
+
+/// Screen rectangle: x, y, width, height
+type Region = (u32, u32, u32, u32);
+
+/// Name and rating read from one name plate
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerTag {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u32>,
+}
+
+/// Both players of a session, as seen from the user's side
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Players {
+    pub user: Option<PlayerTag>,
+    pub opponent: Option<PlayerTag>,
+}
+
+impl Players {
+    pub fn is_empty(&self) -> bool {
+        self.user.is_none() && self.opponent.is_none()
+    }
+}
+
+impl std::fmt::Display for Players {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let tag = |t: &Option<PlayerTag>| match t {
+            Some(PlayerTag { name, rating: Some(r) }) => format!("{} ({})", name, r),
+            Some(PlayerTag { name, rating: None }) => name.clone(),
+            None => "?".to_string(),
+        };
+        write!(f, "{} vs {}", tag(&self.user), tag(&self.opponent))
+    }
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// True when name plates should be read (not opted out and an LLM is available)
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed) && crate::ocr_llm::llm_ready()
+}
+
+/// Reads both name plates from a full screenshot
+pub async fn read_players(img: &DynamicImage) -> Result<Players> {
+    let board = crate::ocr_native::locate_board(img)?;
+    let (above, below) = plate_regions(board, img.dimensions());
+
+    let read = |region: Option<Region>| {
+        async move {
+            let Some((x, y, w, h)) = region else {
+                return Ok(None);
+            };
+            let text = crate::ocr_llm::read_text(&img.crop_imm(x, y, w, h), PLATE_PROMPT).await?;
+            Ok::<_, anyhow::Error>(parse_plate(&text))
+        }
+    };
+    let (top, bottom) = tokio::join!(read(above), read(below));
+    let (top, bottom) = (top?, bottom?);
+
+    // Sites always show the user's own side at the bottom
+    Ok(Players { user: bottom, opponent: top })
+}
+
+/// Strips directly above and below the board, one square high, extended half a board
+/// to the right to cover side panels (None when the board touches the screen edge)
+fn plate_regions(board: Region, image: (u32, u32)) -> (Option<Region>, Option<Region>) {
+    let (x, y, w, h) = board;
+    let (img_w, img_h) = image;
+    let strip = (h / 8).max(1);
+    let width = (w + w / 2).min(img_w.saturating_sub(x));
+
+    let above = (y > 0).then(|| {
+        let top = y.saturating_sub(strip);
+        (x, top, width, y - top)
+    });
+    let below_top = y + h;
+    let below = (below_top < img_h).then(|| (x, below_top, width, strip.min(img_h - below_top)));
+    (above, below)
+}
+
+/// Parses the `NAME:` / `RATING:` reply (None when no name was visible)
+fn parse_plate(text: &str) -> Option<PlayerTag> {
+    let mut name = None;
+    let mut rating = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("NAME:") {
+            name = Some(value.trim().trim_matches('"').to_string()).filter(|n| !n.is_empty());
+        } else if let Some(value) = line.strip_prefix("RATING:") {
+            let digits: String = value.chars().filter(|c| c.is_ascii_digit()).collect();
+            rating = digits.parse().ok().filter(|r| (100..=4000).contains(r));
+        }
+    }
+    Some(PlayerTag { name: name?, rating })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plate() {
+        assert_eq!(
+            parse_plate("NAME: Hikaru\nRATING: (3280)"),
+            Some(PlayerTag { name: "Hikaru".to_string(), rating: Some(3280) })
+        );
+        assert_eq!(
+            parse_plate("NAME: anon\nRATING: none"),
+            Some(PlayerTag { name: "anon".to_string(), rating: None })
+        );
+        assert_eq!(parse_plate("NONE"), None);
+    }
+
+    #[test]
+    fn test_plate_regions_clamped_to_screen() {
+        let (above, below) = plate_regions((100, 80, 800, 800), (1200, 900));
+        assert_eq!(above, Some((100, 0, 1100, 80)));
+        assert_eq!(below, Some((100, 880, 1100, 20)));
+
+        let (above, below) = plate_regions((0, 0, 800, 800), (800, 800));
+        assert_eq!(above, None);
+        assert_eq!(below, None);
+    }
+}
@@ -0,0 +1,155 @@
+//! Post-game review detection
+//!
+//! Once a game ends, chess.com's game review (and lichess' analysis) decorates the board:
+//! move classification badges ("blunder", "best", "brilliant"...) on the squares, arrows,
+//! and the review panel beside it. Read as a position, that board turns into garbage FENs,
+//! so while the review is showing the loop pauses (`--post-game pause`, the default) and
+//! resumes when a plain board is back, e.g. for the next game.
+//!
+//! The review is recognized by its badges: small, strongly colored disks sitting on the
+//! top-right corner of a square. Around every square corner, pixels are counted that are
+//! saturated and unlike anything on the edge of the area looked at; the square colors,
+//! last-move highlights, and arrows all reach that edge, while a badge lies within it.
+//! Arrows alone don't count, since players draw them during games too.
+
+use image::{DynamicImage, GenericImageView, Rgba};
+
+/// Area around a square corner searched for a badge, in squares
+const CORNER_AREA: f32 = 0.7;
+
+/// Saturation from which a pixel can belong to a badge
+const MIN_SATURATION: f32 = 0.45;
+
+/// Smallest difference from every edge color for a badge pixel (sum over RGB)
+const MIN_DISTANCE: u32 = 90;
+
+/// Share of the corner area a badge covers at least
+const MIN_BADGE_SHARE: f32 = 0.06;
+
+/// Edge pixels sampled per side of the corner area
+const EDGE_SAMPLES: u32 = 12;
+
+/// True when the screenshot shows a board with post-game review badges
+pub fn detect(img: &DynamicImage) -> bool {
+    crate::ocr_native::locate_board(img).is_ok_and(|board| has_badges(img, board))
+}
+
+/// Looks for a badge at any square corner of the board at (x, y, width, height)
+fn has_badges(img: &DynamicImage, (x, y, width, height): (u32, u32, u32, u32)) -> bool {
+    let square = width.min(height) as f32 / 8.0;
+    let side = (square * CORNER_AREA) as u32;
+    if side < 8 {
+        return false;
+    }
+    (0..8).any(|row| {
+        (1..=8).any(|col| {
+            let cx = x as f32 + col as f32 * square;
+            let cy = y as f32 + row as f32 * square;
+            let left = (cx - side as f32 / 2.0).max(0.0) as u32;
+            let top = (cy - side as f32 / 2.0).max(0.0) as u32;
+            badge_in(img, left, top, side)
+        })
+    })
+}
+
+/// Whether the `side`×`side` area at (left, top) holds a saturated blob not touching its edge
+fn badge_in(img: &DynamicImage, left: u32, top: u32, side: u32) -> bool {
+    if left + side > img.width() || top + side > img.height() {
+        return false;
+    }
+    let last = side - 1;
+    let edge: Vec<[u8; 3]> = (0..EDGE_SAMPLES)
+        .flat_map(|i| {
+            let along = i * last / (EDGE_SAMPLES - 1);
+            [(along, 0), (along, last), (0, along), (last, along)]
+        })
+        .map(|(dx, dy)| rgb(img.get_pixel(left + dx, top + dy)))
+        .collect();
+
+    let badge_pixels = (1..last)
+        .flat_map(|dy| (1..last).map(move |dx| (dx, dy)))
+        .filter(|&(dx, dy)| {
+            let color = rgb(img.get_pixel(left + dx, top + dy));
+            saturation(color) >= MIN_SATURATION && edge.iter().all(|&e| distance(color, e) >= MIN_DISTANCE)
+        })
+        .count();
+    badge_pixels as f32 >= (side * side) as f32 * MIN_BADGE_SHARE
+}
+
+fn rgb(Rgba([r, g, b, _]): Rgba<u8>) -> [u8; 3] {
+    [r, g, b]
+}
+
+/// HSV saturation
+fn saturation([r, g, b]: [u8; 3]) -> f32 {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    if max == 0 { 0.0 } else { (max - min) as f32 / max as f32 }
+}
+
+fn distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter().zip(b).map(|(&a, b)| a.abs_diff(b) as u32).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    const BOARD: (u32, u32, u32, u32) = (40, 20, 320, 320);
+
+    /// chess.com green board; `paint` decorates it
+    fn screenshot(paint: impl Fn(&mut RgbImage)) -> DynamicImage {
+        let mut img = RgbImage::from_pixel(400, 360, Rgb([48, 46, 43]));
+        for yy in 0..320 {
+            for xx in 0..320 {
+                let light = (xx / 40 + yy / 40) % 2 == 0;
+                img.put_pixel(40 + xx, 20 + yy, if light { Rgb([235, 236, 208]) } else { Rgb([118, 150, 86]) });
+            }
+        }
+        paint(&mut img);
+        DynamicImage::ImageRgb8(img)
+    }
+
+    fn disk(img: &mut RgbImage, (cx, cy): (i32, i32), radius: i32, color: Rgb<u8>) {
+        for yy in cy - radius..=cy + radius {
+            for xx in cx - radius..=cx + radius {
+                if (xx - cx).pow(2) + (yy - cy).pow(2) <= radius * radius {
+                    img.put_pixel(xx as u32, yy as u32, color);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_badge_detected() {
+        // A red "blunder" badge with a white glyph on e4's top-right corner
+        let img = screenshot(|img| {
+            disk(img, (40 + 200 - 3, 20 + 160 + 3), 8, Rgb([202, 52, 49]));
+            disk(img, (40 + 200 - 3, 20 + 160 + 3), 3, Rgb([255, 255, 255]));
+        });
+        assert!(has_badges(&img, BOARD));
+        assert!(!has_badges(&screenshot(|_| {}), BOARD));
+    }
+
+    #[test]
+    fn test_highlights_and_arrows_are_not_badges() {
+        let img = screenshot(|img| {
+            // Last-move highlight on two squares
+            for (sx, sy) in [(4, 6), (4, 4)] {
+                for yy in 0..40 {
+                    for xx in 0..40 {
+                        img.put_pixel(40 + sx * 40 + xx, 20 + sy * 40 + yy, Rgb([246, 246, 105]));
+                    }
+                }
+            }
+            // An orange arrow along the c/d-file border, through the square corners
+            for yy in 20..340 {
+                for xx in 0..10 {
+                    img.put_pixel(40 + 115 + xx, yy, Rgb([255, 170, 0]));
+                }
+            }
+        });
+        assert!(!has_badges(&img, BOARD));
+    }
+}
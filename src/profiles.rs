@@ -0,0 +1,200 @@
+//! Public player profiles for PGN headers
+//!
+//! Looks up a username's title and rating on chess.com or Lichess so exported games carry
+//! real `White`/`Black`/`WhiteElo`/`BlackElo` tags. The rating is taken from the category
+//! matching the game's time control (blitz if unknown).
+
+use anyhow::{Context, Result};
+
+/// Rating category of a time control
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimeClass {
+    Bullet,
+    #[default]
+    Blitz,
+    Rapid,
+    Classical,
+    /// Correspondence
+    Daily,
+}
+
+impl TimeClass {
+    /// Classifies a PGN time control ("180+2", "600", or "-"/"1/86400" for correspondence)
+    /// by estimated game duration (base + 40 × increment, the Lichess convention)
+    pub fn from_time_control(tc: &str) -> Option<Self> {
+        let tc = tc.trim();
+        if tc == "-" || tc.contains('/') {
+            return Some(TimeClass::Daily);
+        }
+        let (base, increment) = tc.split_once('+').unwrap_or((tc, "0"));
+        let estimate = base.parse::<u32>().ok()? + 40 * increment.parse::<u32>().ok()?;
+        Some(match estimate {
+            0..180 => TimeClass::Bullet,
+            180..480 => TimeClass::Blitz,
+            480..1500 => TimeClass::Rapid,
+            _ => TimeClass::Classical,
+        })
+    }
+
+    /// Lowercase name as used in headers and both sites' APIs
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeClass::Bullet => "bullet",
+            TimeClass::Blitz => "blitz",
+            TimeClass::Rapid => "rapid",
+            TimeClass::Classical => "classical",
+            TimeClass::Daily => "daily",
+        }
+    }
+
+    /// Lichess perf type (`perfType` in the API)
+    pub fn lichess_perf(&self) -> &'static str {
+        match self {
+            TimeClass::Daily => "correspondence",
+            other => other.name(),
+        }
+    }
+
+    /// chess.com `time_class` of games in this category
+    pub fn chesscom_class(&self) -> &'static str {
+        self.chesscom_stat().trim_start_matches("chess_")
+    }
+
+    fn chesscom_stat(&self) -> &'static str {
+        match self {
+            TimeClass::Bullet => "chess_bullet",
+            TimeClass::Blitz => "chess_blitz",
+            // chess.com has no classical category; long games are rated as rapid
+            TimeClass::Rapid | TimeClass::Classical => "chess_rapid",
+            TimeClass::Daily => "chess_daily",
+        }
+    }
+}
+
+/// A player's public identity as shown in PGN headers
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlayerProfile {
+    pub username: String,
+    pub rating: Option<u32>,
+    /// FIDE/site title (GM, IM, ...)
+    pub title: Option<String>,
+}
+
+impl PlayerProfile {
+    /// Profile with only a name (used when the lookup fails)
+    pub fn named(username: &str) -> Self {
+        PlayerProfile {
+            username: username.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Display name of a site for the `Site`/`Event` tags
+pub fn site_name(site: &str) -> &'static str {
+    match site {
+        "lichess" => "lichess.org",
+        "chesscom" => "Chess.com",
+        _ => "?",
+    }
+}
+
+/// Fetches a player's profile from the given site ("chesscom" or "lichess")
+pub async fn fetch(site: &str, username: &str, class: TimeClass) -> Result<PlayerProfile> {
+    let client = crate::ocr_llm::http_client(15)?;
+    let get = |url: String| {
+        let client = client.clone();
+        async move {
+            client
+                .get(&url)
+                .header("User-Agent", "zugzwang-rs")
+                .header("Accept", "application/json")
+                .send()
+                .await
+                .with_context(|| format!("Request failed: {}", url))?
+                .error_for_status()
+                .with_context(|| format!("No public profile for '{}'", username))?
+                .json::<serde_json::Value>()
+                .await
+                .context("Malformed profile response")
+        }
+    };
+
+    match site {
+        "lichess" => {
+            let body = get(format!("https://lichess.org/api/user/{}", username)).await?;
+            Ok(lichess_profile(&body, username, class))
+        }
+        "chesscom" => {
+            let name = username.to_lowercase();
+            let profile = get(format!("https://api.chess.com/pub/player/{}", name)).await?;
+            let stats = get(format!("https://api.chess.com/pub/player/{}/stats", name)).await?;
+            Ok(chesscom_profile(&profile, &stats, username, class))
+        }
+        other => anyhow::bail!("Player lookup is not available for site '{}'", other),
+    }
+}
+
+fn lichess_profile(body: &serde_json::Value, username: &str, class: TimeClass) -> PlayerProfile {
+    let perf = &body["perfs"][class.lichess_perf()];
+    PlayerProfile {
+        username: body["username"].as_str().unwrap_or(username).to_string(),
+        // Provisional ratings are too unreliable for the Elo tag
+        rating: perf["rating"]
+            .as_u64()
+            .filter(|_| !perf["prov"].as_bool().unwrap_or(false))
+            .map(|r| r as u32),
+        title: body["title"].as_str().map(String::from),
+    }
+}
+
+fn chesscom_profile(profile: &serde_json::Value, stats: &serde_json::Value, username: &str, class: TimeClass) -> PlayerProfile {
+    // The profile's `username` is lowercased; the canonical casing is in the profile URL
+    let username = profile["url"]
+        .as_str()
+        .and_then(|url| url.trim_end_matches('/').rsplit('/').next())
+        .unwrap_or(username);
+    PlayerProfile {
+        username: username.to_string(),
+        rating: stats[class.chesscom_stat()]["last"]["rating"].as_u64().map(|r| r as u32),
+        title: profile["title"].as_str().map(String::from),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_class_from_time_control() {
+        assert_eq!(TimeClass::from_time_control("60+0"), Some(TimeClass::Bullet));
+        assert_eq!(TimeClass::from_time_control("180+2"), Some(TimeClass::Blitz));
+        assert_eq!(TimeClass::from_time_control("900+10"), Some(TimeClass::Rapid));
+        assert_eq!(TimeClass::from_time_control("1800+20"), Some(TimeClass::Classical));
+        assert_eq!(TimeClass::from_time_control("1/86400"), Some(TimeClass::Daily));
+        assert_eq!(TimeClass::from_time_control("fast"), None);
+    }
+
+    #[test]
+    fn test_lichess_profile_skips_provisional_rating() {
+        let body = serde_json::json!({
+            "username": "DrNykterstein",
+            "title": "GM",
+            "perfs": { "blitz": { "rating": 3100 }, "rapid": { "rating": 1500, "prov": true } },
+        });
+        let blitz = lichess_profile(&body, "drnykterstein", TimeClass::Blitz);
+        assert_eq!(blitz.username, "DrNykterstein");
+        assert_eq!(blitz.rating, Some(3100));
+        assert_eq!(blitz.title.as_deref(), Some("GM"));
+        assert_eq!(lichess_profile(&body, "x", TimeClass::Rapid).rating, None);
+    }
+
+    #[test]
+    fn test_chesscom_profile() {
+        let profile = serde_json::json!({ "username": "hikaru", "url": "https://www.chess.com/member/Hikaru", "title": "GM" });
+        let stats = serde_json::json!({ "chess_blitz": { "last": { "rating": 3300 } }, "chess_rapid": { "last": { "rating": 2800 } } });
+        let p = chesscom_profile(&profile, &stats, "hikaru", TimeClass::Classical);
+        assert_eq!(p.username, "Hikaru");
+        assert_eq!(p.rating, Some(2800));
+    }
+}
@@ -0,0 +1,163 @@
+//! Startup prompts with an accessible fallback
+//!
+//! By default the startup questions use dialoguer's arrow-key menus. Screen readers handle
+//! those poorly (the highlighted item is redrawn in place), so with `--accessible` every
+//! question becomes a plain numbered list answered by typing a number or the option's
+//! first word, and text input is a simple line read.
+//!
+//! Once the command listener owns stdin, prompts take their answer from it instead of
+//! reading stdin themselves (see [`offer_line`]).
+
+use anyhow::{Context, Result};
+use dialoguer::{theme::ColorfulTheme, Input, Select};
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+
+/// Whether plain-text prompts are used instead of dialoguer widgets
+static ACCESSIBLE: AtomicBool = AtomicBool::new(false);
+
+/// Set once the command listener thread reads stdin
+static LISTENING: AtomicBool = AtomicBool::new(false);
+
+/// Where the listener delivers the next line while a prompt is waiting for one
+static WAITING: Mutex<Option<mpsc::Sender<String>>> = Mutex::new(None);
+
+/// Switches all prompts to plain text
+pub fn set_accessible(enabled: bool) {
+    ACCESSIBLE.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns true if `--accessible` is active
+pub fn accessible() -> bool {
+    ACCESSIBLE.load(Ordering::Relaxed)
+}
+
+/// Marks stdin as owned by the command listener; later prompts are answered through it
+pub fn set_listening() {
+    LISTENING.store(true, Ordering::Relaxed);
+}
+
+/// Hands a line read by the command listener to a waiting prompt. Returns the line
+/// back if no prompt is waiting, so the listener parses it as a command.
+pub fn offer_line(line: String) -> Option<String> {
+    let mut waiting = WAITING.lock().unwrap_or_else(|e| e.into_inner());
+    match waiting.take() {
+        Some(answer) => answer.send(line).err().map(|e| e.0),
+        None => Some(line),
+    }
+}
+
+/// Dialoguer widgets read the terminal themselves, so they are only used before the listener starts
+fn plain() -> bool {
+    accessible() || LISTENING.load(Ordering::Relaxed)
+}
+
+/// Asks the user to pick one of `options`, returning its index
+pub fn select(prompt: &str, options: &[&str], default: usize) -> Result<usize> {
+    if !plain() {
+        return Select::with_theme(&ColorfulTheme::default())
+            .with_prompt(prompt)
+            .items(options)
+            .default(default)
+            .interact()
+            .context("Failed to get user selection");
+    }
+
+    println!("{}:", prompt);
+    for (i, option) in options.iter().enumerate() {
+        println!("  {}. {}", i + 1, option);
+    }
+    loop {
+        let answer = read_line(&format!("Type a number, or press Enter for {}", default + 1))?;
+        match parse_choice(&answer, options, default) {
+            Some(choice) => return Ok(choice),
+            None => println!("Not an option: {}", answer.trim()),
+        }
+    }
+}
+
+/// Asks for a line of text, re-asking until `validate` accepts it
+pub fn input(prompt: &str, validate: impl Fn(&str) -> Result<(), &'static str>) -> Result<String> {
+    if !plain() {
+        return Input::<String>::with_theme(&ColorfulTheme::default())
+            .with_prompt(prompt)
+            .validate_with(|input: &String| validate(input))
+            .interact_text()
+            .context("Failed to read input");
+    }
+
+    loop {
+        let answer = read_line(prompt)?;
+        match validate(&answer) {
+            Ok(()) => return Ok(answer),
+            Err(reason) => println!("{}", reason),
+        }
+    }
+}
+
+/// Maps a typed answer to an option index: "2", a word from the option ("manual"), or empty for the default
+fn parse_choice(answer: &str, options: &[&str], default: usize) -> Option<usize> {
+    let answer = answer.trim().to_lowercase();
+    if answer.is_empty() {
+        return Some(default);
+    }
+    if let Ok(n) = answer.parse::<usize>() {
+        return (1..=options.len()).contains(&n).then(|| n - 1);
+    }
+    options.iter().position(|option| {
+        option
+            .split(|c: char| !c.is_alphanumeric())
+            .next()
+            .is_some_and(|word| word.eq_ignore_ascii_case(&answer))
+    })
+}
+
+/// Prints a prompt and reads one trimmed line from stdin, or from the listener once it owns stdin
+fn read_line(prompt: &str) -> Result<String> {
+    print!("{}: ", prompt);
+    io::stdout().flush()?;
+    if LISTENING.load(Ordering::Relaxed) {
+        let (answer, line) = mpsc::channel();
+        *WAITING.lock().unwrap_or_else(|e| e.into_inner()) = Some(answer);
+        let line = line.recv().context("Input closed")?;
+        return Ok(line.trim().to_string());
+    }
+    let mut line = String::new();
+    let read = io::stdin().lock().read_line(&mut line).context("Failed to read input")?;
+    anyhow::ensure!(read > 0, "Input closed");
+    Ok(line.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTIONS: [&str; 2] = ["Auto (continuous) - interval", "Manual (on-demand) - press Enter"];
+
+    #[test]
+    fn test_parse_choice_by_number_and_word() {
+        assert_eq!(parse_choice("2", &OPTIONS, 0), Some(1));
+        assert_eq!(parse_choice("manual", &OPTIONS, 0), Some(1));
+        assert_eq!(parse_choice("  AUTO ", &OPTIONS, 1), Some(0));
+    }
+
+    #[test]
+    fn test_offer_line_answers_waiting_prompt() {
+        assert_eq!(offer_line("d".to_string()), Some("d".to_string()));
+
+        let (answer, line) = mpsc::channel();
+        *WAITING.lock().unwrap() = Some(answer);
+        assert_eq!(offer_line("2".to_string()), None);
+        assert_eq!(line.recv().unwrap(), "2");
+        assert_eq!(offer_line("d".to_string()), Some("d".to_string()));
+    }
+
+    #[test]
+    fn test_parse_choice_default_and_invalid() {
+        assert_eq!(parse_choice("", &OPTIONS, 1), Some(1));
+        assert_eq!(parse_choice("3", &OPTIONS, 0), None);
+        assert_eq!(parse_choice("0", &OPTIONS, 0), None);
+        assert_eq!(parse_choice("continuous", &OPTIONS, 0), None);
+    }
+}
@@ -0,0 +1,305 @@
+//! Analysis queue for correspondence games
+//!
+//! Correspondence games move slowly, so there is time for much deeper analysis than the
+//! live loop can afford. `zugzwang-rs queue` keeps a list of ongoing games in
+//! `queue/games.json` and, when run, re-analyzes each one at high depth whenever its
+//! position has changed, announcing fresh results as they finish.
+//!
+//! A game's source is one of:
+//! - `fen:<FEN>`: a fixed position typed by the user
+//! - `lichess:<game id>`: the current position is fetched from the lichess game export API
+//! - `chesscom:<username>/<game id>`: an ongoing daily game from the chess.com public API
+//!
+//! `queue run --watch <minutes>` keeps polling so new opponent moves are picked up
+//! automatically; without `--watch` every game is refreshed once.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+/// Queue file with registered games and their last analysis
+const QUEUE_PATH: &str = "queue/games.json";
+
+/// Default search depth for queued analysis (the live loop uses `engine::DEFAULT_DEPTH`)
+pub const QUEUE_DEPTH: u16 = 10;
+
+/// Where a queued game's position comes from
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "lowercase")]
+pub enum GameSource {
+    Fen(String),
+    Lichess(String),
+    Chesscom { username: String, game_id: String },
+}
+
+impl GameSource {
+    /// Parses `fen:<FEN>`, `lichess:<id>`, or `chesscom:<username>/<id>`
+    pub fn parse(text: &str) -> Result<Self> {
+        let (kind, value) = text
+            .split_once(':')
+            .context("Game source must be fen:<FEN>, lichess:<id>, or chesscom:<username>/<id>")?;
+        let value = value.trim();
+        match kind.trim().to_lowercase().as_str() {
+            "fen" => {
+                shakmaty::fen::Fen::from_ascii(value.as_bytes())
+                    .map_err(|e| anyhow::anyhow!("Invalid FEN: {}", e))?;
+                Ok(GameSource::Fen(value.to_string()))
+            }
+            "lichess" => {
+                // Accept a full game URL as well as the bare id
+                let id = value.trim_end_matches('/').rsplit('/').next().unwrap_or(value);
+                anyhow::ensure!(!id.is_empty(), "Missing lichess game id");
+                Ok(GameSource::Lichess(id.chars().take(8).collect()))
+            }
+            "chesscom" => {
+                let (username, game_id) = value
+                    .split_once('/')
+                    .context("chess.com games are given as chesscom:<username>/<game id>")?;
+                Ok(GameSource::Chesscom {
+                    username: username.to_lowercase(),
+                    game_id: game_id.to_string(),
+                })
+            }
+            other => anyhow::bail!("Unknown game source '{}' (expected fen, lichess, or chesscom)", other),
+        }
+    }
+}
+
+impl std::fmt::Display for GameSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameSource::Fen(fen) => write!(f, "fen:{}", fen),
+            GameSource::Lichess(id) => write!(f, "lichess:{}", id),
+            GameSource::Chesscom { username, game_id } => write!(f, "chesscom:{}/{}", username, game_id),
+        }
+    }
+}
+
+/// Result of the last deep analysis of a game
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueuedAnalysis {
+    pub fen: String,
+    pub best_move: String,
+    pub evaluation: String,
+    pub depth: u16,
+    /// Milliseconds since the Unix epoch
+    pub analyzed_at_ms: u128,
+}
+
+/// A registered correspondence game
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueuedGame {
+    pub name: String,
+    pub source: GameSource,
+    pub analysis: Option<QueuedAnalysis>,
+}
+
+/// Loads the queue (empty if no file yet)
+pub fn load() -> Result<Vec<QueuedGame>> {
+    if !Path::new(QUEUE_PATH).exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(QUEUE_PATH).context("Failed to read analysis queue")?;
+    serde_json::from_str(&content).with_context(|| format!("Malformed queue file: {}", QUEUE_PATH))
+}
+
+/// Writes the queue back to disk
+fn save(games: &[QueuedGame]) -> Result<()> {
+    std::fs::create_dir_all("queue").context("Failed to create queue directory")?;
+    let content = serde_json::to_string_pretty(games).context("Failed to serialize queue")?;
+    std::fs::write(QUEUE_PATH, content).context("Failed to write analysis queue")
+}
+
+/// Registers a game (replacing any game with the same name)
+pub fn add(name: &str, source: GameSource) -> Result<()> {
+    let mut games = load()?;
+    games.retain(|g| g.name != name);
+    println!("Queued '{}' ({})", name, source);
+    games.push(QueuedGame {
+        name: name.to_string(),
+        source,
+        analysis: None,
+    });
+    save(&games)
+}
+
+/// Removes a game by name
+pub fn remove(name: &str) -> Result<()> {
+    let mut games = load()?;
+    let before = games.len();
+    games.retain(|g| g.name != name);
+    anyhow::ensure!(games.len() < before, "No queued game named '{}'", name);
+    save(&games)?;
+    println!("Removed '{}'", name);
+    Ok(())
+}
+
+/// Prints the queue with each game's latest analysis
+pub fn list() -> Result<()> {
+    let games = load()?;
+    if games.is_empty() {
+        println!("Analysis queue is empty (add games with `queue add <name> <source>`)");
+    }
+    for game in &games {
+        println!("{}  [{}]", game.name, game.source);
+        match &game.analysis {
+            Some(a) => println!("    Best: {} ({}) at depth {}", a.best_move, a.evaluation, a.depth),
+            None => println!("    not analyzed yet"),
+        }
+    }
+    Ok(())
+}
+
+/// Refreshes every game and deep-analyzes those whose position changed.
+/// With `watch`, repeats forever at that interval.
+pub async fn run(depth: u16, watch: Option<Duration>) -> Result<()> {
+    loop {
+        let mut games = load()?;
+        let client = crate::ocr_llm::http_client(15)?;
+
+        for i in 0..games.len() {
+            let game = &games[i];
+            let fen = match fetch_fen(&client, &game.source).await {
+                Ok(fen) => fen,
+                Err(e) => {
+                    eprintln!("⚠ {}: {:#}", game.name, e);
+                    continue;
+                }
+            };
+            let fresh = game.analysis.as_ref().is_none_or(|a| a.fen != fen || a.depth < depth);
+            if !fresh {
+                continue;
+            }
+
+            let position = fen.clone();
+            let (best_move, evaluation) =
+                tokio::task::spawn_blocking(move || crate::engine::analyze_position(&position, depth))
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Analysis task failed: {}", e))??;
+
+            // Terminal bell so a backgrounded queue still gets noticed
+            println!("\x07🔔 {}: {} ({}) at depth {}", game.name, best_move, evaluation, depth);
+            games[i].analysis = Some(QueuedAnalysis {
+                fen,
+                best_move,
+                evaluation,
+                depth,
+                analyzed_at_ms: std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_millis())
+                    .unwrap_or_default(),
+            });
+            // Save after every game so a long run never loses finished work
+            save(&games)?;
+        }
+
+        match watch {
+            Some(interval) => tokio::time::sleep(interval).await,
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Current position of a game
+async fn fetch_fen(client: &reqwest::Client, source: &GameSource) -> Result<String> {
+    match source {
+        GameSource::Fen(fen) => Ok(fen.clone()),
+        GameSource::Lichess(id) => {
+            let url = format!("https://lichess.org/game/export/{}?lastFen=true&moves=false&clocks=false&evals=false", id);
+            let body: serde_json::Value = client
+                .get(&url)
+                .header("Accept", "application/json")
+                .send()
+                .await
+                .context("lichess request failed")?
+                .error_for_status()
+                .context("lichess returned an error")?
+                .json()
+                .await
+                .context("Malformed lichess response")?;
+            body["lastFen"]
+                .as_str()
+                .map(complete_fen)
+                .context("lichess response has no lastFen")
+        }
+        GameSource::Chesscom { username, game_id } => {
+            let url = format!("https://api.chess.com/pub/player/{}/games", username);
+            let body: serde_json::Value = client
+                .get(&url)
+                .header("User-Agent", "zugzwang-rs")
+                .send()
+                .await
+                .context("chess.com request failed")?
+                .error_for_status()
+                .context("chess.com returned an error")?
+                .json()
+                .await
+                .context("Malformed chess.com response")?;
+            find_chesscom_fen(&body, game_id)
+                .with_context(|| format!("No ongoing daily game {} for {}", game_id, username))
+        }
+    }
+}
+
+/// Finds a game's FEN in the chess.com "current daily games" listing
+fn find_chesscom_fen(body: &serde_json::Value, game_id: &str) -> Option<String> {
+    body["games"]
+        .as_array()?
+        .iter()
+        .find(|g| g["url"].as_str().is_some_and(|url| url.trim_end_matches('/').ends_with(&format!("/{}", game_id))))
+        .and_then(|g| g["fen"].as_str())
+        .map(String::from)
+}
+
+/// lichess' lastFen may carry only placement and side to move; pad it to a full FEN
+fn complete_fen(fen: &str) -> String {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    match fields.len() {
+        1 => format!("{} w - - 0 1", fields[0]),
+        2 => format!("{} {} - - 0 1", fields[0], fields[1]),
+        _ => fen.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sources() {
+        assert_eq!(
+            GameSource::parse("lichess:https://lichess.org/abcdEFGH1234").unwrap(),
+            GameSource::Lichess("abcdEFGH".to_string())
+        );
+        assert_eq!(
+            GameSource::parse("chesscom:Hikaru/12345").unwrap(),
+            GameSource::Chesscom { username: "hikaru".to_string(), game_id: "12345".to_string() }
+        );
+        assert!(GameSource::parse("fen:8/8/8/8/8/8/8/K6k w - - 0 1").is_ok());
+        assert!(GameSource::parse("fen:garbage").is_err());
+        assert!(GameSource::parse("chess24:1").is_err());
+    }
+
+    #[test]
+    fn test_source_display_roundtrip() {
+        let source = GameSource::Chesscom { username: "user".to_string(), game_id: "99".to_string() };
+        assert_eq!(GameSource::parse(&source.to_string()).unwrap(), source);
+    }
+
+    #[test]
+    fn test_find_chesscom_fen() {
+        let body = serde_json::json!({ "games": [
+            { "url": "https://www.chess.com/game/daily/111", "fen": "8/8/8/8/8/8/8/K6k w - - 0 1" },
+            { "url": "https://www.chess.com/game/daily/222", "fen": "8/8/8/8/8/8/8/K5k1 b - - 0 1" },
+        ]});
+        assert_eq!(find_chesscom_fen(&body, "222").as_deref(), Some("8/8/8/8/8/8/8/K5k1 b - - 0 1"));
+        assert_eq!(find_chesscom_fen(&body, "22"), None);
+    }
+
+    #[test]
+    fn test_complete_fen() {
+        assert_eq!(complete_fen("8/8/8/8/8/8/8/K6k b"), "8/8/8/8/8/8/8/K6k b - - 0 1");
+        assert_eq!(complete_fen("8/8/8/8/8/8/8/K6k w - - 3 40"), "8/8/8/8/8/8/8/K6k w - - 3 40");
+    }
+}
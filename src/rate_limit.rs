@@ -0,0 +1,182 @@
+//! Scheduling LLM calls within the provider's rate limits
+//!
+//! Providers report how much of the account's request (RPM) and token (TPM) budget is left
+//! in response headers:
+//!
+//! - `x-ratelimit-remaining-requests` / `x-ratelimit-reset-requests` (e.g. "1s", "6m0s")
+//! - `x-ratelimit-remaining-tokens` / `x-ratelimit-reset-tokens`
+//! - `retry-after` (seconds) or `retry-after-ms` on a 429
+//!
+//! After each response the next call to the same provider and key is held back until the
+//! exhausted budget resets, instead of retrying after a fixed delay and collecting 429s
+//! through a long auto-mode session. A 429 without any of these headers backs off for
+//! `FALLBACK_BACKOFF`. Waits longer than `MAX_WAIT` (daily limits) fail the call instead.
+
+use anyhow::Result;
+use crate::llm_provider::Provider;
+use reqwest::header::HeaderMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Tokens a request may need (a high-detail board image plus prompt and answer); less than
+/// this left counts as an exhausted token budget
+const TOKEN_HEADROOM: u64 = 2_000;
+
+/// Wait after a 429 that didn't say how long to wait
+const FALLBACK_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Longest wait scheduled before giving up on a call
+const MAX_WAIT: Duration = Duration::from_secs(120);
+
+/// Waits too long to represent or schedule (`retry-after: 1e999`) are scheduled as this,
+/// which `wait` refuses like any wait over `MAX_WAIT`
+const BEYOND_MAX_WAIT: Duration = MAX_WAIT.saturating_add(Duration::from_secs(1));
+
+/// Waits shorter than this aren't reported
+const QUIET_WAIT: Duration = Duration::from_secs(1);
+
+/// Earliest time of the next call per provider and API key
+static NOT_BEFORE: Mutex<Vec<(Provider, Option<String>, Instant)>> = Mutex::new(Vec::new());
+
+/// Waits until the provider's budget allows another call with this key
+pub async fn wait(provider: Provider, api_key: Option<&str>) -> Result<()> {
+    let not_before = NOT_BEFORE.lock().ok().and_then(|schedule| {
+        schedule
+            .iter()
+            .find(|(p, key, _)| *p == provider && key.as_deref() == api_key)
+            .map(|(_, _, at)| *at)
+    });
+    let Some(wait) = not_before.and_then(|at| at.checked_duration_since(Instant::now())) else {
+        return Ok(());
+    };
+    anyhow::ensure!(
+        wait <= MAX_WAIT,
+        "{} rate limit resets in {}s - try again later or add another API key",
+        provider,
+        wait.as_secs()
+    );
+    if wait >= QUIET_WAIT {
+        eprintln!("⚠ {} rate limit reached - waiting {:.1}s", provider, wait.as_secs_f64());
+    }
+    tokio::time::sleep(wait).await;
+    Ok(())
+}
+
+/// Schedules the next call from a response's status and rate limit headers
+pub fn record(provider: Provider, api_key: Option<&str>, status: u16, headers: &HeaderMap) {
+    let Some(delay) = delay(status, headers) else {
+        return;
+    };
+    let now = Instant::now();
+    let at = now.checked_add(delay).unwrap_or(now + BEYOND_MAX_WAIT);
+    if let Ok(mut schedule) = NOT_BEFORE.lock() {
+        schedule.retain(|(p, key, _)| !(*p == provider && key.as_deref() == api_key));
+        schedule.push((provider, api_key.map(String::from), at));
+    }
+}
+
+/// How long to hold back the next call, if at all
+fn delay(status: u16, headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).map(str::trim);
+    let count = |name: &str| header(name).and_then(|value| value.parse::<u64>().ok());
+    let reset = |name: &str| header(name).and_then(parse_duration);
+
+    let retry_after = header("retry-after-ms")
+        .and_then(|ms| ms.parse::<f64>().ok())
+        .map(|ms| seconds(ms / 1000.0))
+        .or_else(|| header("retry-after").and_then(|secs| secs.parse::<f64>().ok()).map(seconds));
+    let requests = (count("x-ratelimit-remaining-requests") == Some(0)).then(|| reset("x-ratelimit-reset-requests")).flatten();
+    let tokens = count("x-ratelimit-remaining-tokens")
+        .is_some_and(|left| left < TOKEN_HEADROOM)
+        .then(|| reset("x-ratelimit-reset-tokens"))
+        .flatten();
+
+    let scheduled = [retry_after, requests, tokens].into_iter().flatten().max();
+    match scheduled {
+        Some(delay) => Some(delay),
+        None if status == 429 => Some(FALLBACK_BACKOFF),
+        None => None,
+    }
+}
+
+/// Parses reset durations as OpenAI writes them: "20ms", "1.5s", "6m0s", "1h2m3s"
+fn parse_duration(text: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        let value: f64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        total += value
+            * match &rest[..unit] {
+                "ms" => 0.001,
+                "s" => 1.0,
+                "m" => 60.0,
+                "h" => 3600.0,
+                _ => return None,
+            };
+        rest = &rest[unit..];
+    }
+    (!text.is_empty()).then(|| seconds(total))
+}
+
+/// A wait given in seconds; `BEYOND_MAX_WAIT` if it doesn't fit a `Duration` (negative or NaN: none)
+fn seconds(secs: f64) -> Duration {
+    Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(BEYOND_MAX_WAIT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("6m0s"), Some(Duration::from_secs(360)));
+        assert_eq!(parse_duration("1h2m3s"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_duration("1e999s"), None);
+        assert_eq!(parse_duration("99999999999999999999h"), Some(BEYOND_MAX_WAIT));
+        assert_eq!(parse_duration("soon"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+
+    #[test]
+    fn test_delay_from_headers() {
+        // Budget left: no wait
+        let plenty = headers(&[("x-ratelimit-remaining-requests", "499"), ("x-ratelimit-remaining-tokens", "29000")]);
+        assert_eq!(delay(200, &plenty), None);
+        // Out of requests, or too few tokens for another board: wait for the later reset
+        let exhausted = headers(&[
+            ("x-ratelimit-remaining-requests", "0"),
+            ("x-ratelimit-reset-requests", "1s"),
+            ("x-ratelimit-remaining-tokens", "150"),
+            ("x-ratelimit-reset-tokens", "6m0s"),
+        ]);
+        assert_eq!(delay(200, &exhausted), Some(Duration::from_secs(360)));
+        assert_eq!(delay(429, &headers(&[("retry-after", "3")])), Some(Duration::from_secs(3)));
+        assert_eq!(delay(429, &headers(&[("retry-after-ms", "250")])), Some(Duration::from_millis(250)));
+        assert_eq!(delay(429, &HeaderMap::new()), Some(FALLBACK_BACKOFF));
+    }
+
+    #[tokio::test]
+    async fn test_unrepresentable_retry_after_fails_the_call() {
+        assert_eq!(delay(429, &headers(&[("retry-after", "1e999")])), Some(BEYOND_MAX_WAIT));
+        assert_eq!(delay(429, &headers(&[("retry-after-ms", "1e300")])), Some(BEYOND_MAX_WAIT));
+        assert_eq!(delay(429, &headers(&[("retry-after", "1e18")])), Some(Duration::from_secs(1_000_000_000_000_000_000)));
+        assert_eq!(delay(429, &headers(&[("retry-after", "NaN")])), Some(Duration::ZERO));
+
+        record(Provider::OpenAi, Some("test-huge-retry"), 429, &headers(&[("retry-after", "1e18")]));
+        let error = wait(Provider::OpenAi, Some("test-huge-retry")).await.unwrap_err();
+        assert!(error.to_string().contains("rate limit resets in"), "{}", error);
+    }
+}
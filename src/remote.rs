@@ -0,0 +1,210 @@
+//! Phone remote (`--remote PORT`, or `--output remote:PORT`)
+//!
+//! With the game full-screen on the computer, a phone shortcut (iOS Shortcuts, Android HTTP
+//! Shortcuts, Tasker) or a small companion app can trigger captures and read the suggestion:
+//!
+//! - `POST /trigger`: captures and analyzes now, like Enter or the hotkey (manual and hotkey
+//!   trigger modes; 409 in auto mode, which runs by itself)
+//! - `GET /last`: `{"headline": "Best: Nf3 (+0.35)", "result": {...}}` for the latest result,
+//!   404 until the first
+//!
+//! Unlike `serve`, which only listens on localhost, the phone connects over the local
+//! network, so every request needs the token: `Authorization: Bearer TOKEN` or `?token=TOKEN`.
+//! It is `--remote-token` (or ZUGZWANG_REMOTE_TOKEN), otherwise a random one per run, printed
+//! with the address at startup. Plain HTTP: use it on a network you trust.
+
+use anyhow::{Context, Result};
+use crate::controls::ControlCommand;
+use crate::ocr::BoxFuture;
+use crate::output::{CycleOutput, OutputSink};
+use crate::server::{Request, read_request};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Environment variable holding the token when `--remote-token` isn't given
+pub const TOKEN_VAR: &str = "ZUGZWANG_REMOTE_TOKEN";
+
+/// Token from `--remote-token` or the environment (set once at startup)
+static TOKEN: OnceLock<String> = OnceLock::new();
+
+/// Controls channel of the capture loop; unset outside manual trigger modes
+static COMMANDS: OnceLock<UnboundedSender<ControlCommand>> = OnceLock::new();
+
+/// Latest headline and result, shared with the connection handlers
+type Latest = Arc<Mutex<Option<(String, serde_json::Value)>>>;
+
+/// Sets the token (first call wins)
+pub fn set_token(token: &str) {
+    let _ = TOKEN.set(token.to_string());
+}
+
+/// Lets `/trigger` send captures to the loop (first call wins)
+pub fn set_commands(commands: UnboundedSender<ControlCommand>) {
+    let _ = COMMANDS.set(commands);
+}
+
+/// Answers the phone with the latest result and forwards its triggers
+pub struct RemoteSink {
+    port: u16,
+    latest: Latest,
+}
+
+impl RemoteSink {
+    /// Listens on all interfaces (port 0 picks a free port) and prints where to connect
+    pub async fn bind(port: u16) -> Result<Self> {
+        let listener = tokio::net::TcpListener::bind(("0.0.0.0", port))
+            .await
+            .with_context(|| format!("Failed to listen for the phone remote on port {}", port))?;
+        let port = listener.local_addr()?.port();
+        let token = TOKEN
+            .get_or_init(|| std::env::var(TOKEN_VAR).unwrap_or_else(|_| uuid::Uuid::new_v4().simple().to_string()))
+            .clone();
+        // stderr: stdout may carry JSON
+        eprintln!("📱 Phone remote: http://{}:{}/last?token={}", lan_address(), port, token);
+
+        let latest: Latest = Arc::new(Mutex::new(None));
+        let shared = Arc::clone(&latest);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (latest, token) = (Arc::clone(&shared), token.clone());
+                tokio::spawn(async move {
+                    let _ = handle(stream, &latest, &token).await;
+                });
+            }
+        });
+        Ok(RemoteSink { port, latest })
+    }
+}
+
+impl OutputSink for RemoteSink {
+    fn name(&self) -> String {
+        format!("remote:{}", self.port)
+    }
+
+    fn emit<'a>(&'a mut self, output: &'a CycleOutput) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if let Ok(mut latest) = self.latest.lock() {
+                *latest = Some((output.headline.clone(), output.value.clone()));
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Answers one request
+async fn handle(stream: TcpStream, latest: &Latest, token: &str) -> Result<()> {
+    let mut reader = BufReader::new(stream);
+    let request = read_request(&mut reader).await?;
+    let latest = latest.lock().ok().and_then(|latest| latest.clone());
+    let (status, body) = route(&request, token, latest.as_ref(), COMMANDS.get());
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    let mut stream = reader.into_inner();
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Status line and JSON body for a request
+fn route(
+    request: &Request,
+    token: &str,
+    latest: Option<&(String, serde_json::Value)>,
+    commands: Option<&UnboundedSender<ControlCommand>>,
+) -> (&'static str, String) {
+    let error = |message: &str| serde_json::json!({ "error": message }).to_string();
+    let (path, query) = request.path.split_once('?').unwrap_or((&request.path, ""));
+    let given = request.authorization.as_deref().and_then(|value| value.strip_prefix("Bearer ")).or_else(|| {
+        query.split('&').find_map(|pair| pair.strip_prefix("token="))
+    });
+    if !given.is_some_and(|given| same_token(given.trim(), token)) {
+        return ("401 Unauthorized", error("Missing or wrong token"));
+    }
+
+    match (request.method.as_str(), path) {
+        ("POST", "/trigger") => match commands {
+            Some(commands) if commands.send(ControlCommand::Capture).is_ok() => {
+                ("202 Accepted", serde_json::json!({ "triggered": true }).to_string())
+            }
+            Some(_) => ("503 Service Unavailable", error("The capture loop has stopped")),
+            None => ("409 Conflict", error("Captures run automatically (--trigger auto)")),
+        },
+        ("GET", "/last") => match latest {
+            Some((headline, result)) => ("200 OK", serde_json::json!({ "headline": headline, "result": result }).to_string()),
+            None => ("404 Not Found", error("No position analyzed yet")),
+        },
+        (_, "/trigger") => ("405 Method Not Allowed", error("Use POST /trigger")),
+        (_, "/last") => ("405 Method Not Allowed", error("Use GET /last")),
+        _ => ("404 Not Found", error("Endpoints: POST /trigger, GET /last")),
+    }
+}
+
+/// Compares tokens in time independent of where they differ
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len() && given.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// This computer's address on the local network, for the startup line. Connecting a UDP
+/// socket sends nothing; it only picks the interface a packet would leave through.
+fn lan_address() -> String {
+    std::net::UdpSocket::bind(("0.0.0.0", 0))
+        .and_then(|socket| {
+            socket.connect(("192.0.2.1", 9))?;
+            socket.local_addr()
+        })
+        .map(|address| address.ip().to_string())
+        .unwrap_or_else(|_| "<this computer>".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "s3cret";
+
+    fn request(method: &str, path: &str, authorization: Option<&str>) -> Request {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            websocket_key: None,
+            authorization: authorization.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_requests_need_the_token() {
+        let latest = ("Best: Nf3 (+0.35)".to_string(), serde_json::json!({ "best_move": "Nf3" }));
+        let last = |path: &str, authorization| route(&request("GET", path, authorization), TOKEN, Some(&latest), None).0;
+        assert_eq!(last("/last", None), "401 Unauthorized");
+        assert_eq!(last("/last", Some("Bearer wrong!")), "401 Unauthorized");
+        assert_eq!(last("/last?token=s3cre", None), "401 Unauthorized");
+        assert_eq!(last("/last", Some("Bearer s3cret")), "200 OK");
+        assert_eq!(last("/last?t=1&token=s3cret", None), "200 OK");
+
+        let (_, body) = route(&request("GET", "/last", Some("Bearer s3cret")), TOKEN, Some(&latest), None);
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["headline"], "Best: Nf3 (+0.35)");
+        assert_eq!(body["result"]["best_move"], "Nf3");
+        assert_eq!(route(&request("GET", "/last", Some("Bearer s3cret")), TOKEN, None, None).0, "404 Not Found");
+    }
+
+    #[test]
+    fn test_trigger_sends_a_capture() {
+        let trigger = request("POST", "/trigger?token=s3cret", None);
+        // Auto mode: nothing to trigger
+        assert_eq!(route(&trigger, TOKEN, None, None).0, "409 Conflict");
+
+        let (sender, mut commands) = tokio::sync::mpsc::unbounded_channel();
+        assert_eq!(route(&trigger, TOKEN, None, Some(&sender)).0, "202 Accepted");
+        assert_eq!(commands.try_recv().ok(), Some(ControlCommand::Capture));
+        assert_eq!(route(&request("GET", "/trigger?token=s3cret", None), TOKEN, None, Some(&sender)).0, "405 Method Not Allowed");
+        drop(commands);
+        assert_eq!(route(&trigger, TOKEN, None, Some(&sender)).0, "503 Service Unavailable");
+    }
+}
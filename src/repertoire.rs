@@ -0,0 +1,268 @@
+//! Opening preparation (`prep --repertoire my.pgn`)
+//!
+//! The repertoire PGN - any number of games or study chapters, with variations - is read
+//! into a book of positions, each with the moves prepared from it. During a live game every
+//! recognized position is looked up: while it is in the book the prepared continuation is
+//! shown, and the first position outside it raises a one-time alert saying who left the
+//! preparation, with which move, and what was prepared instead.
+//!
+//! Positions are matched by piece placement and side to move, so transpositions count as
+//! in book. Captures can skip a ply or two between cycles, so the alert reconstructs the
+//! moves played since the last book position (`pgn::connect`) to find the deviating one.
+
+use anyhow::{Context, Result};
+use crate::PlayerSide;
+use crate::engine;
+use crate::pgn;
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::{CastlingMode, Chess, Color, EnPassantMode, Move, Position};
+use std::collections::HashMap;
+
+/// Positions of a repertoire and the moves prepared in each
+#[derive(Debug, Default)]
+pub struct Repertoire {
+    /// Placement and side to move → prepared moves (empty at the end of a line)
+    book: HashMap<String, Vec<Move>>,
+    games: usize,
+}
+
+impl Repertoire {
+    /// Reads every game and variation of a PGN text
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut repertoire = Repertoire::default();
+        for (number, (headers, movetext)) in pgn::split_games(text).into_iter().enumerate() {
+            let start = pgn::start_position(&headers)?;
+            repertoire
+                .add_game(start, &pgn::movetext_tokens(&movetext))
+                .with_context(|| format!("Game {} of the repertoire", number + 1))?;
+            repertoire.games += 1;
+        }
+        anyhow::ensure!(repertoire.games > 0, "No games found in repertoire");
+        Ok(repertoire)
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+        Self::parse(&text)
+    }
+
+    /// Number of book positions and of games they were read from
+    pub fn size(&self) -> (usize, usize) {
+        (self.book.len(), self.games)
+    }
+
+    /// Prepared moves in a position, or None if it isn't in the book
+    pub fn prepared(&self, position: &Chess) -> Option<&[Move]> {
+        self.book.get(&key(position)).map(Vec::as_slice)
+    }
+
+    /// Walks one game's tokens; a variation replaces the move just before it
+    fn add_game(&mut self, start: Chess, tokens: &[String]) -> Result<()> {
+        self.book.entry(key(&start)).or_default();
+        // (position before the last move, position after it) for each open variation level
+        let mut stack = Vec::new();
+        let mut before = start.clone();
+        let mut current = start;
+        for token in tokens {
+            match token.as_str() {
+                "(" => {
+                    stack.push((before.clone(), current.clone()));
+                    current = before.clone();
+                }
+                ")" => {
+                    (before, current) = stack.pop().context("Unbalanced ')' in repertoire")?;
+                }
+                san => {
+                    let m = pgn::parse_san(&current, san)
+                        .with_context(|| format!("Illegal or unreadable move '{}'", san))?;
+                    let moves = self.book.entry(key(&current)).or_default();
+                    if !moves.contains(&m) {
+                        moves.push(m);
+                    }
+                    before = current.clone();
+                    current.play_unchecked(m);
+                    self.book.entry(key(&current)).or_default();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Book key: piece placement and side to move
+fn key(position: &Chess) -> String {
+    let fen = Fen::from_position(position, EnPassantMode::Legal).to_string();
+    fen.split_whitespace().take(2).collect::<Vec<_>>().join(" ")
+}
+
+/// Follows the live game through the repertoire
+#[derive(Debug)]
+pub struct PrepTracker {
+    repertoire: Repertoire,
+    /// Last position seen in the book
+    last_book: Option<Chess>,
+    /// Whether the game has left the book since
+    left: bool,
+}
+
+impl PrepTracker {
+    pub fn new(repertoire: Repertoire) -> Self {
+        PrepTracker { repertoire, last_book: None, left: false }
+    }
+
+    pub fn repertoire(&self) -> &Repertoire {
+        &self.repertoire
+    }
+
+    /// Checks a recognized position (OCR FEN, with the player to move or just moved).
+    /// Returns what to show: the prepared continuation while in book, an alert once when
+    /// the game leaves it, and nothing afterwards.
+    pub fn update(&mut self, fen: &str, side: PlayerSide) -> Option<String> {
+        let player = if side == PlayerSide::White { Color::White } else { Color::Black };
+        // OCR can't see whose turn it is; try the player's turn first
+        let placement = fen.split_whitespace().next()?;
+        let in_book = [player, !player].into_iter().find_map(|turn| {
+            let position = pgn::parse_position(&format!("{} {} - - 0 1", placement, turn.char()))?;
+            self.repertoire.prepared(&position).map(|moves| (position, moves.to_vec()))
+        });
+
+        match in_book {
+            Some((position, moves)) => {
+                let same = self.last_book.as_ref().is_some_and(|last| key(last) == key(&position));
+                if moves.is_empty() {
+                    // End of a prepared line: said once, and what follows isn't a deviation
+                    let first = !(same && self.left);
+                    self.last_book = Some(position);
+                    self.left = true;
+                    return first.then(|| "Book: end of your preparation".to_string());
+                }
+                let returned = std::mem::take(&mut self.left);
+                self.last_book = Some(position.clone());
+                let prefix = if returned { "Back in book" } else { "Book" };
+                if position.turn() == player {
+                    Some(format!("{}: {}", prefix, list_moves(&position, &moves)))
+                } else {
+                    Some(format!("{}: waiting for your opponent (prepared for {})", prefix, list_moves(&position, &moves)))
+                }
+            }
+            None if self.left => None,
+            None => {
+                let last = self.last_book.as_ref()?;
+                self.left = true;
+                let target = pgn::parse_position(fen)?;
+                Some(self.deviation(last, target.board(), player))
+            }
+        }
+    }
+
+    /// Describes who left the book and with which move, replaying the plies between the last
+    /// book position and the current board
+    fn deviation(&self, last: &Chess, board: &shakmaty::Board, player: Color) -> String {
+        let Some(moves) = pgn::connect(last, board) else {
+            return "⚠ Out of book: this position isn't in your repertoire".to_string();
+        };
+        let mut position = last.clone();
+        for m in moves {
+            let prepared = self.repertoire.prepared(&position).unwrap_or_default();
+            if !prepared.contains(&m) {
+                let played = SanPlus::from_move(position.clone(), m);
+                let who = if position.turn() == player { "You left" } else { "Your opponent left" };
+                return if prepared.is_empty() {
+                    format!("⚠ {} your preparation with {} (end of the prepared line)", who, played)
+                } else {
+                    format!("⚠ {} your preparation with {} - prepared: {}", who, played, list_moves(&position, prepared))
+                };
+            }
+            position.play_unchecked(m);
+        }
+        "⚠ Out of book".to_string()
+    }
+}
+
+/// Prepared moves as SAN with the readable squares of the main one ("Nf3 (G1 to F3), c4")
+fn list_moves(position: &Chess, moves: &[Move]) -> String {
+    moves
+        .iter()
+        .enumerate()
+        .map(|(i, &m)| {
+            let san = SanPlus::from_move(position.clone(), m).to_string();
+            if i == 0 {
+                format!("{} ({})", san, engine::format_move_readable(&m.to_uci(CastlingMode::Standard).to_string()))
+            } else {
+                san
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPERTOIRE: &str = "[Event \"Sicilian\"]\n\n1. e4 c5 2. Nf3 (2. c3 d5) 2... d6 3. d4 *\n\n\
+        [Event \"French\"]\n\n1. e4 e6 {Main line} 2. d4 d5 *\n";
+
+    /// OCR-style FEN (no castling, player's turn) after the given SAN moves
+    fn fen_after(moves: &[&str], side: PlayerSide) -> String {
+        let mut position = Chess::default();
+        for san in moves {
+            let m = pgn::parse_san(&position, san).unwrap();
+            position.play_unchecked(m);
+        }
+        let fen = Fen::from_position(&position, EnPassantMode::Legal).to_string();
+        let placement = fen.split_whitespace().next().unwrap();
+        format!("{} {} - - 0 1", placement, side.fen_turn())
+    }
+
+    #[test]
+    fn test_parse_reads_games_and_variations() {
+        let repertoire = Repertoire::parse(REPERTOIRE).unwrap();
+        assert_eq!(repertoire.size().1, 2);
+        let after_c5 = pgn::parse_position(&fen_after(&["e4", "c5"], PlayerSide::White)).unwrap();
+        let prepared: Vec<String> = repertoire
+            .prepared(&after_c5)
+            .unwrap()
+            .iter()
+            .map(|&m| SanPlus::from_move(after_c5.clone(), m).to_string())
+            .collect();
+        assert_eq!(prepared, vec!["Nf3", "c3"]);
+        assert!(Repertoire::parse("1. e4 e5 2. Ke3 *").is_err());
+    }
+
+    #[test]
+    fn test_tracker_shows_book_move_then_flags_opponent_deviation() {
+        let mut tracker = PrepTracker::new(Repertoire::parse(REPERTOIRE).unwrap());
+        let white = PlayerSide::White;
+        assert_eq!(tracker.update(&fen_after(&[], white), white).as_deref(), Some("Book: e4 (E2 to E4)"));
+        let waiting = tracker.update(&fen_after(&["e4"], white), white).unwrap();
+        assert_eq!(waiting, "Book: waiting for your opponent (prepared for c5 (C7 to C5), e6)");
+
+        let alert = tracker.update(&fen_after(&["e4", "e5"], white), white).unwrap();
+        assert_eq!(alert, "⚠ Your opponent left your preparation with e5 - prepared: c5 (C7 to C5), e6");
+        assert_eq!(tracker.update(&fen_after(&["e4", "e5", "Nf3", "Nc6"], white), white), None);
+    }
+
+    #[test]
+    fn test_tracker_flags_own_deviation_across_skipped_plies() {
+        let mut tracker = PrepTracker::new(Repertoire::parse(REPERTOIRE).unwrap());
+        let white = PlayerSide::White;
+        tracker.update(&fen_after(&["e4", "c5"], white), white);
+        // Both the player's and the opponent's move happened between captures
+        let alert = tracker.update(&fen_after(&["e4", "c5", "Nc3", "Nc6"], white), white).unwrap();
+        assert_eq!(alert, "⚠ You left your preparation with Nc3 - prepared: Nf3 (G1 to F3), c3");
+    }
+
+    #[test]
+    fn test_tracker_end_of_line_and_black_side() {
+        let mut tracker = PrepTracker::new(Repertoire::parse(REPERTOIRE).unwrap());
+        let black = PlayerSide::Black;
+        assert_eq!(tracker.update(&fen_after(&["e4"], black), black).as_deref(), Some("Book: c5 (C7 to C5), e6"));
+        let end = fen_after(&["e4", "e6", "d4", "d5"], black);
+        assert_eq!(tracker.update(&end, black).as_deref(), Some("Book: end of your preparation"));
+        assert_eq!(tracker.update(&end, black), None);
+        // Moves after the end of the line aren't reported as leaving the book
+        assert_eq!(tracker.update(&fen_after(&["e4", "e6", "d4", "d5", "Nc3"], black), black), None);
+    }
+}
@@ -0,0 +1,302 @@
+//! Offline replay of recorded frames
+//!
+//! `zugzwang-rs replay <PATH>` runs saved screenshots through the same OCR + engine pipeline
+//! as live capture: a directory of PNG/JPEG frames (in name order, e.g. one recorded for
+//! `--input replay:DIR`), a single image, or a video file whose frames ffmpeg extracts every
+//! `--every` seconds. The report lists each frame's position and best move, then how many
+//! frames were read, how many positions they showed, and which failed - a regression check
+//! for OCR, and a way to review a recorded game afterwards. `--pgn FILE` also writes the
+//! game the positions connect into (see `pgn::session_to_pgn`).
+
+use anyhow::{Context, Result};
+use crate::PlayerSide;
+use crate::ocr::OcrMode;
+use zugzwang_core::pipeline::Pipeline;
+use crate::session::SessionEntry;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Instant, UNIX_EPOCH};
+
+/// Seconds between extracted video frames by default
+pub const DEFAULT_EVERY_SECS: f64 = 1.0;
+
+/// Settings of one replay
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub mode: OcrMode,
+    pub site: String,
+    pub player_side: PlayerSide,
+    pub depth: u16,
+    /// Seconds between extracted video frames
+    pub every_secs: f64,
+    /// Print the report as one JSON object
+    pub json: bool,
+    /// Where to write the reconstructed game
+    pub pgn: Option<PathBuf>,
+}
+
+/// Outcome of one frame
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+struct FrameReport {
+    frame: String,
+    /// When the frame was taken, in ms (file time, or offset into the video)
+    #[serde(skip)]
+    taken_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fen: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    best_move: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    evaluation: Option<String>,
+    /// Same position as the last recognized frame
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    unchanged: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Totals over all frames
+#[derive(Debug, PartialEq, Serialize)]
+struct Summary {
+    frames: usize,
+    recognized: usize,
+    failed: usize,
+    /// Distinct positions in a row (a frame showing the previous position doesn't count)
+    positions: usize,
+    elapsed_secs: f64,
+}
+
+impl Summary {
+    fn of(reports: &[FrameReport], elapsed_secs: f64) -> Self {
+        let recognized = reports.iter().filter(|r| r.fen.is_some()).count();
+        Summary {
+            frames: reports.len(),
+            recognized,
+            failed: reports.len() - recognized,
+            positions: reports.iter().filter(|r| r.fen.is_some() && !r.unchanged).count(),
+            elapsed_secs,
+        }
+    }
+}
+
+/// A frame's file and when it was taken, in ms
+type FrameFile = (PathBuf, Option<u128>);
+
+/// Frames extracted from a video, deleted with it
+struct Extracted(PathBuf);
+
+impl Drop for Extracted {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Replays the frames at `path` and prints the report
+pub async fn run(path: &Path, options: &Options) -> Result<()> {
+    let (frames, _extracted) = frames_at(path, options.every_secs)?;
+    anyhow::ensure!(!frames.is_empty(), "No PNG or JPEG frames in {}", path.display());
+
+    let mut pipeline = Pipeline::with_mode(options.mode)?;
+    pipeline.site = options.site.clone();
+    pipeline.player_side = options.player_side;
+    pipeline.depth = options.depth;
+    if !options.json {
+        println!(
+            "Replaying {} frames from {} ({} OCR, depth {})",
+            frames.len(),
+            path.display(),
+            options.mode,
+            options.depth
+        );
+    }
+
+    let start = Instant::now();
+    let mut reports = Vec::new();
+    let mut last_fen: Option<String> = None;
+    for (frame, taken_ms) in &frames {
+        let mut report = FrameReport {
+            frame: frame.file_name().map_or_else(|| frame.display().to_string(), |name| name.to_string_lossy().into_owned()),
+            taken_ms: *taken_ms,
+            ..FrameReport::default()
+        };
+        let analyzed = match image::open(frame) {
+            Ok(image) => pipeline.analyze_image(Arc::new(crate::capture::downsample(image))).await,
+            Err(e) => Err(anyhow::anyhow!(e).context("Failed to load frame")),
+        };
+        match analyzed {
+            Ok(result) => {
+                report.unchanged = last_fen.as_deref().is_some_and(|last| same_position(last, &result.fen));
+                last_fen = Some(result.fen.clone());
+                report.fen = Some(result.fen);
+                report.best_move = Some(result.analysis.best_move);
+                report.evaluation = Some(result.analysis.eval);
+            }
+            Err(e) => report.error = Some(format!("{:#}", e)),
+        }
+        if !options.json {
+            println!("{}", report_line(&report));
+        }
+        reports.push(report);
+    }
+
+    let summary = Summary::of(&reports, start.elapsed().as_secs_f64());
+    if let Some(pgn) = &options.pgn {
+        std::fs::write(pgn, game_pgn(&reports)).with_context(|| format!("Failed to write {}", pgn.display()))?;
+    }
+    if options.json {
+        println!("{}", serde_json::json!({ "frames": reports, "summary": summary }));
+        return Ok(());
+    }
+    println!();
+    println!(
+        "Frames: {}, recognized {} ({:.1}%), {} positions, {} failed, in {:.1}s",
+        summary.frames,
+        summary.recognized,
+        100.0 * summary.recognized as f64 / summary.frames as f64,
+        summary.positions,
+        summary.failed,
+        summary.elapsed_secs
+    );
+    if let Some(pgn) = &options.pgn {
+        println!("Game written to {}", pgn.display());
+    }
+    Ok(())
+}
+
+/// Frame files with when they were taken: a directory's images, a single image, or a
+/// video's frames extracted to a temporary directory (kept alive by the returned guard)
+fn frames_at(path: &Path, every_secs: f64) -> Result<(Vec<FrameFile>, Option<Extracted>)> {
+    if path.is_dir() {
+        let frames = crate::capture::replay_frames(path)?;
+        let frames = frames
+            .into_iter()
+            .map(|frame| {
+                let taken = modified_ms(&frame);
+                (frame, taken)
+            })
+            .collect();
+        return Ok((frames, None));
+    }
+    anyhow::ensure!(path.is_file(), "No such file or directory: {}", path.display());
+    if crate::capture::is_image_file(path) {
+        return Ok((vec![(path.to_path_buf(), modified_ms(path))], None));
+    }
+
+    let dir = std::env::temp_dir().join(format!("zugzwang-replay-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).context("Failed to create a directory for video frames")?;
+    let extracted = Extracted(dir);
+    extract_video(path, every_secs, &extracted.0)?;
+    let frames = crate::capture::replay_frames(&extracted.0)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, frame)| (frame, Some((index as f64 * every_secs * 1000.0) as u128)))
+        .collect();
+    Ok((frames, Some(extracted)))
+}
+
+/// Writes one PNG per `every_secs` of `video` into `dir` with ffmpeg
+fn extract_video(video: &Path, every_secs: f64, dir: &Path) -> Result<()> {
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-loglevel", "error", "-i"])
+        .arg(video)
+        .arg("-vf")
+        .arg(format!("fps=1/{}", every_secs))
+        .arg(dir.join("frame-%05d.png"))
+        .status()
+        .context("Failed to run ffmpeg (replaying a video needs it on the PATH)")?;
+    anyhow::ensure!(status.success(), "ffmpeg could not extract frames from {}", video.display());
+    Ok(())
+}
+
+/// A file's modification time in ms since the Unix epoch
+fn modified_ms(path: &Path) -> Option<u128> {
+    let modified = std::fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+    modified.duration_since(UNIX_EPOCH).ok().map(|d| d.as_millis())
+}
+
+/// True when two FENs have the same placement and side to move
+fn same_position(a: &str, b: &str) -> bool {
+    a.split_whitespace().take(2).eq(b.split_whitespace().take(2))
+}
+
+/// Console line for a frame
+fn report_line(report: &FrameReport) -> String {
+    match (&report.fen, &report.best_move, &report.evaluation) {
+        _ if report.unchanged => format!("  {}  (unchanged)", report.frame),
+        (Some(fen), Some(best), Some(eval)) => {
+            format!("  {}  {}  Best: {} ({})", report.frame, fen, crate::notation::display(fen, best), eval)
+        }
+        _ => format!("  ⚠ {}: {}", report.frame, report.error.as_deref().unwrap_or("not recognized")),
+    }
+}
+
+/// PGN of the game the recognized positions connect into
+fn game_pgn(reports: &[FrameReport]) -> String {
+    let entries: Vec<SessionEntry> = reports
+        .iter()
+        .filter_map(|report| {
+            Some(SessionEntry {
+                timestamp_ms: report.taken_ms.unwrap_or_default(),
+                captured_ms: report.taken_ms,
+                cycle: None,
+                fen: report.fen.clone()?,
+                best_move: report.best_move.clone()?,
+                evaluation: report.evaluation.clone()?,
+                comment: None,
+                frame: Some(report.frame.clone()),
+            })
+        })
+        .collect();
+    crate::pgn::session_to_pgn(&entries, &crate::pgn::Headers::new("Zugzwang replay"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recognized(frame: &str, fen: &str, unchanged: bool) -> FrameReport {
+        FrameReport {
+            frame: frame.to_string(),
+            fen: Some(fen.to_string()),
+            best_move: Some("E2 to E4".to_string()),
+            evaluation: Some("+0.30".to_string()),
+            unchanged,
+            ..FrameReport::default()
+        }
+    }
+
+    #[test]
+    fn test_summary_counts_positions_once() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let reports = vec![
+            recognized("1.png", start, false),
+            recognized("2.png", start, true),
+            FrameReport { frame: "3.png".to_string(), error: Some("no board".to_string()), ..FrameReport::default() },
+        ];
+        let summary = Summary::of(&reports, 1.5);
+        assert_eq!(summary, Summary { frames: 3, recognized: 2, failed: 1, positions: 1, elapsed_secs: 1.5 });
+        assert_eq!(report_line(&reports[1]), "  2.png  (unchanged)");
+        assert_eq!(report_line(&reports[2]), "  ⚠ 3.png: no board");
+    }
+
+    #[test]
+    fn test_same_position_ignores_counters() {
+        assert!(same_position("8/8/8/8/8/8/8/K6k w - - 0 1", "8/8/8/8/8/8/8/K6k w - - 3 40"));
+        assert!(!same_position("8/8/8/8/8/8/8/K6k w - - 0 1", "8/8/8/8/8/8/8/K6k b - - 0 1"));
+    }
+
+    #[test]
+    fn test_frames_from_directory_in_name_order() {
+        let dir = std::env::temp_dir().join(format!("zugzwang-replay-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["b.PNG", "a.jpg", "notes.txt"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let (frames, extracted) = frames_at(&dir, DEFAULT_EVERY_SECS).unwrap();
+        let names: Vec<_> = frames.iter().map(|(frame, _)| frame.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        assert_eq!(names, vec!["a.jpg", "b.PNG"]);
+        assert!(extracted.is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
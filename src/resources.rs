@@ -0,0 +1,79 @@
+//! Resource limits for background operation (`--nice`, `--cpu-limit`)
+//!
+//! The assistant shares the machine with the game it watches, and a search that takes every
+//! core shows up as input lag and dropped frames in the browser. Two limits keep it in the
+//! background:
+//!
+//! - `--nice` lowers the process priority at startup, before a UCI engine is spawned, so
+//!   the engine inherits it. It stays lowered for the session: an unprivileged process
+//!   can't raise its priority again, and a low-priority process only gives way while the
+//!   CPU is contended - which is exactly while the game is being played.
+//! - `--cpu-limit N` caps the engine at N threads while the chess site's window is
+//!   focused (the `--window` title, or the site's name) and lifts the cap when another
+//!   window is: a UCI engine through its `Threads` option, tanton by searching in a
+//!   thread pool of that size. If focus can't be read (e.g. on Wayland), the cap stays on.
+
+use anyhow::Result;
+use crate::capture;
+use crate::engine;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Nice value for `--nice` (0 is normal, 19 the lowest priority)
+#[cfg(unix)]
+const NICE_LEVEL: i32 = 10;
+
+/// Engine threads while the game window is focused (`--cpu-limit`)
+static CPU_LIMIT: OnceLock<usize> = OnceLock::new();
+
+/// Whether the cap was applied at the last check
+static LIMITED: AtomicBool = AtomicBool::new(false);
+
+/// Lowers the priority of the process and of everything it starts from now on
+#[cfg(unix)]
+pub fn lower_priority() -> Result<()> {
+    // Linux applies priorities per thread, so every running thread is lowered (new threads
+    // and child processes inherit it); elsewhere 0 stands for the whole process
+    #[cfg(target_os = "linux")]
+    let ids: Vec<libc::id_t> = std::fs::read_dir("/proc/self/task")?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .collect();
+    #[cfg(not(target_os = "linux"))]
+    let ids: Vec<libc::id_t> = vec![0];
+
+    for id in ids {
+        // SAFETY: setpriority takes plain integers and touches no memory of ours
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, id, NICE_LEVEL) } != 0 {
+            anyhow::bail!("Failed to lower priority: {}", std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn lower_priority() -> Result<()> {
+    anyhow::bail!("--nice is only supported on Linux and macOS")
+}
+
+/// Enables `--cpu-limit` (first call wins)
+pub fn set_cpu_limit(threads: usize) {
+    let _ = CPU_LIMIT.set(threads);
+}
+
+/// Applies or lifts the engine thread cap for the currently focused window. Returns the
+/// new state (true: capped) when it changed, None otherwise or without `--cpu-limit`.
+pub fn update(site: &str) -> Option<bool> {
+    let &limit = CPU_LIMIT.get()?;
+    let title = capture::window_title().unwrap_or_else(|| capture::site_window_title(site));
+    let focused = capture::window_focused(title).unwrap_or(true);
+    if LIMITED.swap(focused, Ordering::Relaxed) == focused {
+        return None;
+    }
+    engine::set_thread_cap(focused.then_some(limit));
+    Some(focused)
+}
+
+/// The configured thread cap, if any
+pub fn cpu_limit() -> Option<usize> {
+    CPU_LIMIT.get().copied()
+}
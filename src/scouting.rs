@@ -0,0 +1,380 @@
+//! Opponent scouting report
+//!
+//! When the opponent's username is known (`--opponent`, or read from the name plate at game
+//! start) their recent public games are fetched from the site's API - the Lichess game
+//! export or the chess.com monthly archives, limited to the game's time class when known -
+//! and summarized into a short pre-game blurb: results, most played openings with each
+//! color, the opening they score worst in, how they tend to lose, and how error-prone they
+//! are (blunders per game from Lichess computer analysis, or chess.com review accuracy).
+//! `--no-scout` turns it off.
+
+use anyhow::{Context, Result};
+use crate::PlayerSide;
+use crate::profiles::TimeClass;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether opponents are scouted (cleared by --no-scout)
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Number of recent games the report is built from
+const RECENT_GAMES: usize = 30;
+
+/// chess.com archives are monthly; look back at most this many months
+const MAX_ARCHIVES: usize = 3;
+
+/// Openings listed per color
+const TOP_OPENINGS: usize = 3;
+
+/// Share of losses on time at which the report calls it a habit
+const FLAGGING_SHARE: f64 = 0.4;
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Result of a game from the scouted player's side
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// How a game ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ending {
+    Checkmate,
+    Resignation,
+    Time,
+    Other,
+}
+
+/// One of the scouted player's games
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScoutedGame {
+    /// Color the scouted player had
+    pub color: PlayerSide,
+    /// Opening family ("Sicilian Defense")
+    pub opening: String,
+    pub outcome: Outcome,
+    pub ending: Ending,
+    /// Blunders by the scouted player (Lichess, analysed games only)
+    pub blunders: Option<u32>,
+    /// Accuracy of the scouted player (chess.com, reviewed games only)
+    pub accuracy: Option<f64>,
+}
+
+/// Fetches `username`'s recent games from the given site ("chesscom" or "lichess")
+pub async fn fetch_games(site: &str, username: &str, class: Option<TimeClass>) -> Result<Vec<ScoutedGame>> {
+    let client = crate::ocr_llm::http_client(30)?;
+    let get = |url: String, accept: &'static str| {
+        let client = client.clone();
+        async move {
+            client
+                .get(&url)
+                .header("User-Agent", "zugzwang-rs")
+                .header("Accept", accept)
+                .send()
+                .await
+                .with_context(|| format!("Request failed: {}", url))?
+                .error_for_status()
+                .with_context(|| format!("No public games for '{}'", username))?
+                .text()
+                .await
+                .context("Malformed games response")
+        }
+    };
+
+    match site {
+        "lichess" => {
+            let mut url = format!(
+                "https://lichess.org/api/games/user/{}?max={}&opening=true&evals=true&moves=false",
+                username, RECENT_GAMES
+            );
+            if let Some(class) = class {
+                url.push_str(&format!("&perfType={}", class.lichess_perf()));
+            }
+            let body = get(url, "application/x-ndjson").await?;
+            Ok(body
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .filter_map(|game| lichess_game(&game, username))
+                .collect())
+        }
+        "chesscom" => {
+            let name = username.to_lowercase();
+            let archives = get(format!("https://api.chess.com/pub/player/{}/games/archives", name), "application/json").await?;
+            let archives: serde_json::Value = serde_json::from_str(&archives).context("Malformed archive list")?;
+            let urls: Vec<&str> = archives["archives"].as_array().into_iter().flatten().filter_map(|u| u.as_str()).collect();
+
+            let mut games = Vec::new();
+            for url in urls.iter().rev().take(MAX_ARCHIVES) {
+                let month: serde_json::Value =
+                    serde_json::from_str(&get(url.to_string(), "application/json").await?).context("Malformed game archive")?;
+                // Archives list games oldest first
+                for game in month["games"].as_array().into_iter().flatten().rev() {
+                    let class_matches = class.is_none_or(|c| game["time_class"].as_str() == Some(c.chesscom_class()));
+                    if class_matches && let Some(game) = chesscom_game(game, username) {
+                        games.push(game);
+                    }
+                }
+                if games.len() >= RECENT_GAMES {
+                    break;
+                }
+            }
+            games.truncate(RECENT_GAMES);
+            Ok(games)
+        }
+        other => anyhow::bail!("Scouting is not available for site '{}'", other),
+    }
+}
+
+/// A game from the Lichess export (standard chess only)
+fn lichess_game(game: &serde_json::Value, username: &str) -> Option<ScoutedGame> {
+    if game["variant"].as_str().is_some_and(|v| v != "standard") {
+        return None;
+    }
+    let is_player = |color: &str| {
+        game["players"][color]["user"]["name"].as_str().is_some_and(|n| n.eq_ignore_ascii_case(username))
+    };
+    let (color, key) = if is_player("white") {
+        (PlayerSide::White, "white")
+    } else if is_player("black") {
+        (PlayerSide::Black, "black")
+    } else {
+        return None;
+    };
+    let ending = match game["status"].as_str()? {
+        "aborted" | "noStart" | "created" | "started" => return None,
+        "mate" => Ending::Checkmate,
+        "resign" => Ending::Resignation,
+        "outoftime" | "timeout" => Ending::Time,
+        _ => Ending::Other,
+    };
+    let outcome = match game["winner"].as_str() {
+        None => Outcome::Draw,
+        Some(winner) if winner == key => Outcome::Win,
+        Some(_) => Outcome::Loss,
+    };
+    let opening = game["opening"]["name"].as_str().unwrap_or("Unknown opening");
+    Some(ScoutedGame {
+        color,
+        opening: opening.split(':').next().unwrap_or(opening).trim().to_string(),
+        outcome,
+        ending,
+        blunders: game["players"][key]["analysis"]["blunder"].as_u64().map(|n| n as u32),
+        accuracy: None,
+    })
+}
+
+/// A game from a chess.com monthly archive (standard chess only)
+fn chesscom_game(game: &serde_json::Value, username: &str) -> Option<ScoutedGame> {
+    if game["rules"].as_str().is_some_and(|r| r != "chess") {
+        return None;
+    }
+    let is_player = |color: &str| game[color]["username"].as_str().is_some_and(|n| n.eq_ignore_ascii_case(username));
+    let (color, key, other) = if is_player("white") {
+        (PlayerSide::White, "white", "black")
+    } else if is_player("black") {
+        (PlayerSide::Black, "black", "white")
+    } else {
+        return None;
+    };
+    // Each side carries its own result code; the loser's tells how the game ended
+    let own = game[key]["result"].as_str()?;
+    let theirs = game[other]["result"].as_str()?;
+    let outcome = match (own, theirs) {
+        ("win", _) => Outcome::Win,
+        (_, "win") => Outcome::Loss,
+        _ => Outcome::Draw,
+    };
+    let ending = match if outcome == Outcome::Win { theirs } else { own } {
+        "checkmated" => Ending::Checkmate,
+        "resigned" | "abandoned" => Ending::Resignation,
+        "timeout" => Ending::Time,
+        _ => Ending::Other,
+    };
+    Some(ScoutedGame {
+        color,
+        opening: game["eco"].as_str().map(chesscom_opening).unwrap_or_else(|| "Unknown opening".to_string()),
+        outcome,
+        ending,
+        blunders: None,
+        accuracy: game["accuracies"][key].as_f64(),
+    })
+}
+
+/// Opening family from a chess.com opening URL
+/// (".../openings/Sicilian-Defense-Old-Sicilian-3.Nc3" → "Sicilian Defense")
+fn chesscom_opening(url: &str) -> String {
+    const FAMILY_ENDS: [&str; 6] = ["Defense", "Opening", "Gambit", "Game", "Attack", "System"];
+    let slug = url.trim_end_matches('/').rsplit('/').next().unwrap_or(url);
+    let mut words = Vec::new();
+    for word in slug.split('-') {
+        if word.is_empty() || word.starts_with(|c: char| c.is_ascii_digit()) || word == "with" {
+            break;
+        }
+        words.push(word);
+        if FAMILY_ENDS.contains(&word) {
+            break;
+        }
+    }
+    if words.is_empty() { "Unknown opening".to_string() } else { words.join(" ") }
+}
+
+/// Pre-game blurb for `username` built from their recent games
+pub fn report(username: &str, site: &str, games: &[ScoutedGame]) -> String {
+    if games.is_empty() {
+        return format!("Scouting {}: no recent public games on {}", username, crate::profiles::site_name(site));
+    }
+    let count = |outcome| games.iter().filter(|g| g.outcome == outcome).count();
+    let (wins, draws, losses) = (count(Outcome::Win), count(Outcome::Draw), count(Outcome::Loss));
+    let mut lines = vec![
+        format!(
+            "── Scouting {} (last {} games on {}) ──",
+            username,
+            games.len(),
+            crate::profiles::site_name(site)
+        ),
+        format!("Results:   +{} ={} -{}", wins, draws, losses),
+    ];
+
+    for color in [PlayerSide::White, PlayerSide::Black] {
+        let openings = opening_scores(games, color);
+        if openings.is_empty() {
+            continue;
+        }
+        let top: Vec<String> = openings
+            .iter()
+            .take(TOP_OPENINGS)
+            .map(|(name, played, score)| format!("{} ({}, {:.0}%)", name, played, score * 100.0))
+            .collect();
+        lines.push(format!("As {}:  {}", color, top.join(", ")));
+    }
+
+    // Worst-scoring opening played at least twice
+    let weakest = [PlayerSide::White, PlayerSide::Black]
+        .into_iter()
+        .flat_map(|color| opening_scores(games, color).into_iter().map(move |o| (color, o)))
+        .filter(|(_, (_, played, score))| *played >= 2 && *score < 0.5)
+        .min_by(|(_, (_, _, a)), (_, (_, _, b))| a.total_cmp(b));
+    if let Some((color, (name, played, score))) = weakest {
+        lines.push(format!("Weak spot: {} as {} ({:.1}/{})", name, color, score * played as f64, played));
+    }
+
+    if losses > 0 {
+        let lost_by = |ending| games.iter().filter(|g| g.outcome == Outcome::Loss && g.ending == ending).count();
+        let on_time = lost_by(Ending::Time);
+        let mut line = format!(
+            "Losses:    {} on time, {} by checkmate, {} resigned",
+            on_time,
+            lost_by(Ending::Checkmate),
+            lost_by(Ending::Resignation)
+        );
+        if on_time as f64 >= FLAGGING_SHARE * losses as f64 {
+            line.push_str(" - often flags, keep the clock pressure on");
+        }
+        lines.push(line);
+    }
+
+    let blunders: Vec<u32> = games.iter().filter_map(|g| g.blunders).collect();
+    if !blunders.is_empty() {
+        let per_game = blunders.iter().sum::<u32>() as f64 / blunders.len() as f64;
+        lines.push(format!("Errors:    {:.1} blunders per game ({} analysed games)", per_game, blunders.len()));
+    }
+    let accuracies: Vec<f64> = games.iter().filter_map(|g| g.accuracy).collect();
+    if !accuracies.is_empty() {
+        let average = accuracies.iter().sum::<f64>() / accuracies.len() as f64;
+        lines.push(format!("Accuracy:  {:.0}% average ({} reviewed games)", average, accuracies.len()));
+    }
+    lines.join("\n")
+}
+
+/// Openings played with `color`: (name, games, score 0-1), most played first
+fn opening_scores(games: &[ScoutedGame], color: PlayerSide) -> Vec<(String, usize, f64)> {
+    let mut by_opening: HashMap<&str, (usize, f64)> = HashMap::new();
+    for game in games.iter().filter(|g| g.color == color) {
+        let entry = by_opening.entry(&game.opening).or_default();
+        entry.0 += 1;
+        entry.1 += match game.outcome {
+            Outcome::Win => 1.0,
+            Outcome::Draw => 0.5,
+            Outcome::Loss => 0.0,
+        };
+    }
+    let mut openings: Vec<(String, usize, f64)> = by_opening
+        .into_iter()
+        .map(|(name, (played, points))| (name.to_string(), played, points / played as f64))
+        .collect();
+    openings.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    openings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(color: PlayerSide, opening: &str, outcome: Outcome, ending: Ending) -> ScoutedGame {
+        ScoutedGame { color, opening: opening.to_string(), outcome, ending, blunders: None, accuracy: None }
+    }
+
+    #[test]
+    fn test_lichess_game_from_scouted_side() {
+        let line = r#"{"id":"abc","variant":"standard","status":"outoftime","winner":"white",
+            "players":{"white":{"user":{"name":"Alice"}},"black":{"user":{"name":"Bob"},"analysis":{"blunder":2}}},
+            "opening":{"eco":"B01","name":"Scandinavian Defense: Main Line"}}"#;
+        let value: serde_json::Value = serde_json::from_str(line).unwrap();
+        let g = lichess_game(&value, "bob").unwrap();
+        assert_eq!(g.color, PlayerSide::Black);
+        assert_eq!(g.opening, "Scandinavian Defense");
+        assert_eq!(g.outcome, Outcome::Loss);
+        assert_eq!(g.ending, Ending::Time);
+        assert_eq!(g.blunders, Some(2));
+        assert!(lichess_game(&value, "carol").is_none());
+    }
+
+    #[test]
+    fn test_chesscom_game_ending_from_loser() {
+        let value = serde_json::json!({
+            "rules": "chess",
+            "eco": "https://www.chess.com/openings/Sicilian-Defense-Old-Sicilian-3.Nc3",
+            "white": { "username": "Hikaru", "result": "win" },
+            "black": { "username": "someone", "result": "checkmated" },
+            "accuracies": { "white": 91.5, "black": 70.2 },
+        });
+        let g = chesscom_game(&value, "hikaru").unwrap();
+        assert_eq!(g.color, PlayerSide::White);
+        assert_eq!(g.outcome, Outcome::Win);
+        assert_eq!(g.ending, Ending::Checkmate);
+        assert_eq!(g.opening, "Sicilian Defense");
+        assert_eq!(g.accuracy, Some(91.5));
+    }
+
+    #[test]
+    fn test_chesscom_opening_family() {
+        assert_eq!(chesscom_opening("https://www.chess.com/openings/Queens-Gambit-Declined-Ragozin"), "Queens Gambit");
+        assert_eq!(chesscom_opening("https://www.chess.com/openings/Kings-Pawn-Opening-1...e5"), "Kings Pawn Opening");
+        assert_eq!(chesscom_opening("https://www.chess.com/openings/Ruy-Lopez-Opening"), "Ruy Lopez Opening");
+    }
+
+    #[test]
+    fn test_report_lists_openings_and_habits() {
+        let games = vec![
+            game(PlayerSide::White, "Italian Game", Outcome::Win, Ending::Resignation),
+            game(PlayerSide::White, "Italian Game", Outcome::Draw, Ending::Other),
+            game(PlayerSide::Black, "Caro-Kann Defense", Outcome::Loss, Ending::Time),
+            game(PlayerSide::Black, "Caro-Kann Defense", Outcome::Loss, Ending::Checkmate),
+        ];
+        let text = report("Bob", "lichess", &games);
+        assert!(text.contains("Results:   +1 =1 -2"));
+        assert!(text.contains("As White:  Italian Game (2, 75%)"));
+        assert!(text.contains("Weak spot: Caro-Kann Defense as Black (0.0/2)"));
+        assert!(text.contains("1 on time, 1 by checkmate, 0 resigned - often flags"));
+        assert!(report("Bob", "lichess", &[]).contains("no recent public games"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Encrypted secrets in the config file (`[secrets]`)
+//!
+//! API keys and tokens are normally environment variables. Where that is inconvenient (no
+//! keychain, a launcher that doesn't pass the environment) they can be kept in the config
+//! file instead, under the name of the variable they stand for:
+//!
+//! ```toml
+//! [secrets]
+//! OPENAI_API_KEY = "enc:v1:machine:..."
+//! LICHESS_TOKEN = "enc:v1:passphrase:..."
+//! ```
+//!
+//! Values are encrypted with ChaCha20-Poly1305 under a key derived (PBKDF2-SHA256) from the
+//! ZUGZWANG_PASSPHRASE environment variable when it is set, otherwise from this machine's
+//! id and the user name, so a copied config file is useless elsewhere. A plaintext value
+//! (typed into the file by hand, or from an older version) is encrypted the next time the
+//! file is read. At startup each secret becomes its environment variable unless that is
+//! already set: the environment always wins.
+
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose};
+use ring::aead::{Aad, CHACHA20_POLY1305, LessSafeKey, Nonce, NONCE_LEN, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::BTreeMap;
+use std::num::NonZeroU32;
+
+/// Environment variable with the passphrase; unset: the machine-derived key is used
+pub const PASSPHRASE_VAR: &str = "ZUGZWANG_PASSPHRASE";
+
+/// Prefix of encrypted values
+const PREFIX: &str = "enc:v1:";
+
+/// PBKDF2 rounds: slow enough to hamper guessing a passphrase, quick enough at startup
+const KDF_ITERATIONS: u32 = 100_000;
+
+const SALT_LEN: usize = 16;
+
+/// What the encryption key is derived from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeySource {
+    Machine,
+    Passphrase,
+}
+
+impl KeySource {
+    fn name(self) -> &'static str {
+        match self {
+            KeySource::Machine => "machine",
+            KeySource::Passphrase => "passphrase",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "machine" => Some(KeySource::Machine),
+            "passphrase" => Some(KeySource::Passphrase),
+            _ => None,
+        }
+    }
+
+    /// Secret input to the key derivation
+    fn material(self) -> Result<Vec<u8>> {
+        match self {
+            KeySource::Passphrase => std::env::var(PASSPHRASE_VAR)
+                .map(String::into_bytes)
+                .with_context(|| format!("Secret was encrypted with a passphrase: set {}", PASSPHRASE_VAR)),
+            KeySource::Machine => {
+                let user = std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_default();
+                Ok(format!("{}\n{}", machine_id()?, user).into_bytes())
+            }
+        }
+    }
+}
+
+/// True for values written by `encrypt`
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+/// Encrypts `plain` for the config file (passphrase when set, machine key otherwise)
+pub fn encrypt(plain: &str) -> Result<String> {
+    let source = if std::env::var_os(PASSPHRASE_VAR).is_some() { KeySource::Passphrase } else { KeySource::Machine };
+    Ok(format!("{}{}:{}", PREFIX, source.name(), seal(plain, &source.material()?)?))
+}
+
+/// Decrypts a value written by `encrypt`
+pub fn decrypt(value: &str) -> Result<String> {
+    let (source, sealed) = value
+        .strip_prefix(PREFIX)
+        .and_then(|rest| rest.split_once(':'))
+        .and_then(|(source, sealed)| Some((KeySource::from_name(source)?, sealed)))
+        .context("Not an encrypted secret")?;
+    open(sealed, &source.material()?)
+}
+
+/// Encrypts plaintext values in place; returns how many there were
+pub fn encrypt_plaintext(secrets: &mut BTreeMap<String, String>) -> Result<usize> {
+    let mut count = 0;
+    for value in secrets.values_mut().filter(|value| !is_encrypted(value)) {
+        *value = encrypt(value.trim())?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Makes each secret its environment variable, unless that is already set. Secrets that
+/// can't be decrypted (another machine, no passphrase) are reported and skipped.
+pub fn export(secrets: &BTreeMap<String, String>) {
+    for (name, value) in secrets {
+        if std::env::var_os(name).is_some() {
+            continue;
+        }
+        match decrypt(value) {
+            // SAFETY: called at startup, before any thread reads the environment
+            Ok(plain) => unsafe { std::env::set_var(name, plain) },
+            Err(e) => eprintln!("⚠ Config secret {} unavailable: {:#}", name, e),
+        }
+    }
+}
+
+/// salt | nonce | ciphertext and tag, base64
+fn seal(plain: &str, material: &[u8]) -> Result<String> {
+    let random = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    random.fill(&mut salt).map_err(|_| anyhow::anyhow!("No system randomness"))?;
+    random.fill(&mut nonce).map_err(|_| anyhow::anyhow!("No system randomness"))?;
+
+    let mut data = plain.as_bytes().to_vec();
+    key(material, &salt)?
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+        .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+    Ok(general_purpose::STANDARD.encode([&salt[..], &nonce[..], &data].concat()))
+}
+
+fn open(sealed: &str, material: &[u8]) -> Result<String> {
+    let bytes = general_purpose::STANDARD.decode(sealed).context("Damaged secret")?;
+    anyhow::ensure!(bytes.len() > SALT_LEN + NONCE_LEN, "Damaged secret");
+    let (salt, rest) = bytes.split_at(SALT_LEN);
+    let (nonce, data) = rest.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow::anyhow!("Damaged secret"))?;
+    let mut data = data.to_vec();
+    let plain = key(material, salt)?
+        .open_in_place(nonce, Aad::empty(), &mut data)
+        .map_err(|_| anyhow::anyhow!("Wrong key (another machine or passphrase?)"))?;
+    String::from_utf8(plain.to_vec()).context("Damaged secret")
+}
+
+fn key(material: &[u8], salt: &[u8]) -> Result<LessSafeKey> {
+    let mut key = [0u8; 32];
+    let iterations = NonZeroU32::new(KDF_ITERATIONS).unwrap();
+    ring::pbkdf2::derive(ring::pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, material, &mut key);
+    let key = UnboundKey::new(&CHACHA20_POLY1305, &key).map_err(|_| anyhow::anyhow!("Invalid key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// A stable id of this machine from the OS
+fn machine_id() -> Result<String> {
+    #[cfg(target_os = "linux")]
+    let id = ["/etc/machine-id", "/var/lib/dbus/machine-id"].iter().find_map(|path| std::fs::read_to_string(path).ok());
+    #[cfg(target_os = "macos")]
+    let id = command_output("ioreg", &["-rd1", "-c", "IOPlatformExpertDevice"]).and_then(|out| {
+        out.lines().find(|line| line.contains("IOPlatformUUID")).and_then(|line| line.split('"').nth(3).map(str::to_string))
+    });
+    #[cfg(windows)]
+    let id = command_output("reg", &["query", r"HKLM\SOFTWARE\Microsoft\Cryptography", "/v", "MachineGuid"])
+        .and_then(|out| out.split_whitespace().last().map(str::to_string));
+    #[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+    let id: Option<String> = None;
+
+    id.map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .with_context(|| format!("No machine id to derive a key from: set {}", PASSPHRASE_VAR))
+}
+
+#[cfg(any(target_os = "macos", windows))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program).args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let sealed = seal("sk-test-123", b"correct horse").unwrap();
+        assert!(!sealed.contains("sk-test"));
+        assert_eq!(open(&sealed, b"correct horse").unwrap(), "sk-test-123");
+        assert!(open(&sealed, b"wrong horse").is_err());
+        // A fresh salt and nonce every time
+        assert_ne!(seal("sk-test-123", b"correct horse").unwrap(), sealed);
+
+        let mut damaged = general_purpose::STANDARD.decode(&sealed).unwrap();
+        *damaged.last_mut().unwrap() ^= 1;
+        assert!(open(&general_purpose::STANDARD.encode(damaged), b"correct horse").is_err());
+    }
+
+    #[test]
+    fn test_decrypt_needs_the_format() {
+        assert!(!is_encrypted("sk-plain"));
+        assert!(decrypt("sk-plain").is_err());
+        assert!(decrypt("enc:v1:unknown:AAAA").is_err());
+    }
+}
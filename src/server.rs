@@ -0,0 +1,252 @@
+//! HTTP/WebSocket server (`serve`, `--output http:PORT`)
+//!
+//! Exposes the live loop's results on localhost so browser overlays (e.g. an OBS browser
+//! source) and custom frontends can use them without linking against this crate:
+//!
+//! - `GET /analysis`: the latest JSON result (as printed by `--json`)
+//! - `GET /fen`: the latest recognized FEN as plain text
+//! - `/ws`: a WebSocket that receives every new result as a JSON text message
+//!
+//! Both endpoints answer 404 until the first position has been analyzed. Responses allow
+//! any origin so a page served from elsewhere can poll them.
+
+use anyhow::{Context, Result};
+use crate::ocr::BoxFuture;
+use crate::output::{CycleOutput, OutputSink, push_results};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+
+/// Port used when `serve` isn't given `--port`
+pub const DEFAULT_PORT: u16 = 8080;
+
+/// Results kept for WebSocket clients that fall behind
+const WEBSOCKET_BACKLOG: usize = 16;
+
+/// Longest request head accepted, in bytes
+const MAX_REQUEST_HEAD: usize = 8192;
+
+/// Time a client gets to send its request head
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Latest result, shared with the connection handlers
+type Latest = Arc<Mutex<Option<serde_json::Value>>>;
+
+/// Serves the latest result over HTTP and pushes new ones over WebSocket
+pub struct ServerSink {
+    port: u16,
+    latest: Latest,
+    sender: broadcast::Sender<String>,
+}
+
+impl ServerSink {
+    /// Listens on localhost (port 0 picks a free port)
+    pub async fn bind(port: u16) -> Result<Self> {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+            .await
+            .with_context(|| format!("Failed to listen for HTTP clients on port {}", port))?;
+        let port = listener.local_addr()?.port();
+        let latest: Latest = Arc::new(Mutex::new(None));
+        let (sender, _) = broadcast::channel(WEBSOCKET_BACKLOG);
+        let (shared, clients) = (Arc::clone(&latest), sender.clone());
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (latest, results) = (Arc::clone(&shared), clients.subscribe());
+                tokio::spawn(async move {
+                    let _ = handle(stream, latest, results).await;
+                });
+            }
+        });
+        Ok(ServerSink { port, latest, sender })
+    }
+}
+
+impl OutputSink for ServerSink {
+    fn name(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port)
+    }
+
+    fn emit<'a>(&'a mut self, output: &'a CycleOutput) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if let Ok(mut latest) = self.latest.lock() {
+                *latest = Some(output.value.clone());
+            }
+            // No clients connected is not an error
+            let _ = self.sender.send(output.value.to_string());
+            Ok(())
+        })
+    }
+}
+
+/// A parsed request head
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// `Sec-WebSocket-Key` of an upgrade request
+    pub websocket_key: Option<String>,
+    /// `Authorization` header (the phone remote's token)
+    pub authorization: Option<String>,
+}
+
+/// Answers one connection: a WebSocket upgrade or a single HTTP request
+async fn handle(stream: TcpStream, latest: Latest, results: broadcast::Receiver<String>) -> Result<()> {
+    let mut reader = BufReader::new(stream);
+    let request = read_request(&mut reader).await?;
+    let mut stream = reader.into_inner();
+
+    if let Some(key) = &request.websocket_key
+        && matches!(request.path.as_str(), "/ws" | "/")
+    {
+        let accept = derive_accept_key(key.as_bytes());
+        stream
+            .write_all(
+                format!(
+                    "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                    accept
+                )
+                .as_bytes(),
+            )
+            .await?;
+        let socket = tokio_tungstenite::WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+        push_results(socket, results).await;
+        return Ok(());
+    }
+
+    let latest = latest.lock().ok().and_then(|latest| latest.clone());
+    let (status, content_type, body) = route(&request, latest.as_ref());
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    if request.method != "HEAD" {
+        stream.write_all(body.as_bytes()).await?;
+    }
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Reads the request line and headers (the body, if any, is ignored). Clients that send
+/// more than `MAX_REQUEST_HEAD` bytes or take longer than `REQUEST_TIMEOUT` are refused.
+pub async fn read_request(reader: &mut BufReader<TcpStream>) -> Result<Request> {
+    tokio::time::timeout(REQUEST_TIMEOUT, read_head(reader))
+        .await
+        .context("Request head timed out")?
+}
+
+async fn read_head(reader: &mut BufReader<TcpStream>) -> Result<Request> {
+    // Reads stop at the limit, so a line that never ends can't grow without bound
+    let mut head = reader.take(MAX_REQUEST_HEAD as u64);
+    let mut line = String::new();
+    head.read_line(&mut line).await?;
+    anyhow::ensure!(line.ends_with('\n') || head.limit() > 0, "Request head too large");
+    let mut parts = line.split_whitespace();
+    let method = parts.next().context("Empty request")?.to_string();
+    let path = parts.next().context("Request without a path")?.to_string();
+
+    let mut websocket_key = None;
+    let mut authorization = None;
+    let mut upgrade = false;
+    loop {
+        line.clear();
+        let read = head.read_line(&mut line).await?;
+        anyhow::ensure!(line.ends_with('\n') || head.limit() > 0, "Request head too large");
+        if read == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "upgrade" => upgrade = value.eq_ignore_ascii_case("websocket"),
+                "sec-websocket-key" => websocket_key = Some(value.to_string()),
+                "authorization" => authorization = Some(value.to_string()),
+                _ => {}
+            }
+        }
+    }
+    Ok(Request { method, path, websocket_key: websocket_key.filter(|_| upgrade), authorization })
+}
+
+/// Status line, content type, and body for a plain HTTP request
+fn route(request: &Request, latest: Option<&serde_json::Value>) -> (&'static str, &'static str, String) {
+    const NOT_YET: &str = "No position analyzed yet\n";
+    if request.method != "GET" && request.method != "HEAD" {
+        return ("405 Method Not Allowed", "text/plain", "Only GET is supported\n".to_string());
+    }
+    // Query strings (cache busters from overlays) don't change the answer
+    let path = request.path.split('?').next().unwrap_or_default();
+    match path {
+        "/analysis" => match latest {
+            Some(value) => ("200 OK", "application/json", value.to_string()),
+            None => ("404 Not Found", "text/plain", NOT_YET.to_string()),
+        },
+        "/fen" => match latest.and_then(|value| value["fen"].as_str()) {
+            Some(fen) => ("200 OK", "text/plain", format!("{}\n", fen)),
+            None => ("404 Not Found", "text/plain", NOT_YET.to_string()),
+        },
+        _ => ("404 Not Found", "text/plain", "Endpoints: /analysis, /fen, /ws\n".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    fn get(path: &str) -> Request {
+        Request { method: "GET".to_string(), path: path.to_string(), websocket_key: None, authorization: None }
+    }
+
+    #[test]
+    fn test_route() {
+        let value = serde_json::json!({ "fen": "8/8/8/8/8/8/8/K6k w - - 0 1", "best_move": "A1 to A2" });
+        assert_eq!(route(&get("/fen?t=1"), Some(&value)), ("200 OK", "text/plain", "8/8/8/8/8/8/8/K6k w - - 0 1\n".to_string()));
+        assert_eq!(route(&get("/analysis"), Some(&value)).2, value.to_string());
+        assert_eq!(route(&get("/analysis"), None).0, "404 Not Found");
+        // Direct mode results have no FEN
+        assert_eq!(route(&get("/fen"), Some(&serde_json::json!({ "mode": "direct" }))).0, "404 Not Found");
+        let post = Request { method: "POST".to_string(), ..get("/fen") };
+        assert_eq!(route(&post, Some(&value)).0, "405 Method Not Allowed");
+    }
+
+    #[tokio::test]
+    async fn test_refuses_endless_request_line() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(address).await.unwrap();
+            let _ = stream.write_all(&vec![b'a'; MAX_REQUEST_HEAD * 4]).await;
+            stream
+        });
+        let (stream, _) = listener.accept().await.unwrap();
+        let error = read_request(&mut BufReader::new(stream)).await.err().unwrap();
+        assert_eq!(error.to_string(), "Request head too large");
+        drop(client.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_serves_latest_and_pushes_over_websocket() {
+        let mut sink = ServerSink::bind(0).await.unwrap();
+        let base = sink.name();
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://127.0.0.1:{}/ws", sink.port)).await.unwrap();
+
+        let output = CycleOutput {
+            value: serde_json::json!({ "mode": "engine", "fen": "8/8/8/8/8/8/8/K6k w - - 0 1" }),
+            lines: Vec::new(),
+            headline: String::new(),
+            timings: Vec::new(),
+        };
+        sink.emit(&output).await.unwrap();
+
+        let fen = reqwest::get(format!("{}/fen", base)).await.unwrap().text().await.unwrap();
+        assert_eq!(fen, "8/8/8/8/8/8/8/K6k w - - 0 1\n");
+        let pushed = socket.next().await.unwrap().unwrap().into_text().unwrap();
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&pushed).unwrap(), output.value);
+    }
+}
@@ -0,0 +1,292 @@
+//! Session recording
+//!
+//! Every analyzed position of a live run is appended to `sessions/<start time>.jsonl`
+//! (one JSON object per line), so a session can be reviewed, exported as PGN, or
+//! post-processed after the game without scraping console output.
+//!
+//! Next to it, `sessions/<start time>.pgn` grows with the game: after every recorded
+//! position the moves played since the previous one are inferred (see `pgn::connect`) and
+//! the file is rewritten, so an interrupted run still leaves a readable game behind.
+//! `export-study` writes the same moves with full player headers.
+//!
+//! Recording also reports how long the move leading to the new position took (see
+//! `pgn::MoveTime`), for the session's time-management stats.
+
+use anyhow::{Context, Result};
+use crate::capture::Frame;
+use crate::pgn::MoveTime;
+use crate::players::Players;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Directory holding session logs
+pub const SESSIONS_DIR: &str = "sessions";
+
+/// Game details detected at the start of a session, stored next to it
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionMeta {
+    #[serde(flatten)]
+    pub players: Players,
+    /// PGN time control (e.g. "180+2")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_control: Option<String>,
+}
+
+impl SessionMeta {
+    pub fn is_empty(&self) -> bool {
+        self.players.is_empty() && self.time_control.is_none()
+    }
+}
+
+/// One analyzed position
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionEntry {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u128,
+    /// When the analyzed screenshot was grabbed (same clock)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub captured_ms: Option<u128>,
+    /// ID of the cycle that analyzed the position (see `capture::Frame::cycle`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycle: Option<String>,
+    pub fen: String,
+    pub best_move: String,
+    /// Engine eval from the side to move's perspective (e.g. "+0.35")
+    pub evaluation: String,
+    /// Free-text commentary (e.g. the LLM's reasoning in hybrid mode)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Archived screenshot of the position (with --collect-dataset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame: Option<String>,
+}
+
+/// Appends entries to this run's session file
+pub struct SessionLog {
+    path: PathBuf,
+}
+
+impl SessionLog {
+    /// Starts a new session file named after the current time (created on first entry)
+    pub fn start() -> Self {
+        SessionLog {
+            path: PathBuf::from(SESSIONS_DIR).join(format!("{}.jsonl", now_ms())),
+        }
+    }
+
+    /// Starts a session file for one of several boards analyzed at once
+    /// (`<start time>-<label>.jsonl`)
+    pub fn start_for(label: &str) -> Self {
+        SessionLog {
+            path: PathBuf::from(SESSIONS_DIR).join(format!("{}-{}.jsonl", now_ms(), label)),
+        }
+    }
+
+    /// Appends one analyzed position read from `frame`. Returns the time of the move that led
+    /// to it, when that move was seen on its own.
+    pub fn record(
+        &self,
+        frame: &Frame,
+        fen: &str,
+        best_move: &str,
+        evaluation: &str,
+        comment: Option<&str>,
+    ) -> Result<Option<MoveTime>> {
+        std::fs::create_dir_all(SESSIONS_DIR).context("Failed to create sessions directory")?;
+        let timestamp_ms = now_ms();
+        let archived = if crate::dataset::enabled() {
+            crate::dataset::archive_frame(&self.path, timestamp_ms, &frame.cycle, &frame.image)
+                .map_err(|e| eprintln!("⚠ Frame not archived: {:#}", e))
+                .ok()
+        } else {
+            None
+        };
+        let entry = SessionEntry {
+            timestamp_ms,
+            captured_ms: Some(frame.captured_ms),
+            cycle: Some(frame.cycle.clone()),
+            fen: fen.to_string(),
+            best_move: best_move.to_string(),
+            evaluation: evaluation.to_string(),
+            comment: comment.map(String::from),
+            frame: archived,
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context("Failed to open session log")?;
+        let line = serde_json::to_string(&entry).context("Failed to serialize session entry")?;
+        writeln!(file, "{}", line).context("Failed to write session log")?;
+        let entries = load(&self.path)?;
+        self.write_pgn(&entries)?;
+        let newest = entries.len().saturating_sub(1);
+        Ok(crate::pgn::move_times(&entries).pop().filter(|time| time.entry == newest))
+    }
+
+    /// Rewrites the session's PGN (`<session>.pgn`) from every position recorded so far
+    fn write_pgn(&self, entries: &[SessionEntry]) -> Result<()> {
+        let mut headers = crate::pgn::Headers::new("Zugzwang live session");
+        if let Some(tc) = load_meta(&self.path).and_then(|meta| meta.time_control) {
+            headers.set("TimeControl", &tc);
+        }
+        let text = crate::pgn::session_to_pgn(entries, &headers);
+        // Written aside and renamed over the old one, so it is never seen half-written
+        let path = pgn_path(&self.path);
+        let partial = path.with_extension("pgn.partial");
+        std::fs::write(&partial, text).context("Failed to write session PGN")?;
+        std::fs::rename(&partial, &path).context("Failed to write session PGN")
+    }
+
+    /// Stores the game details next to the session (`<session>.meta.json`)
+    pub fn record_meta(&self, meta: &SessionMeta) -> Result<()> {
+        std::fs::create_dir_all(SESSIONS_DIR).context("Failed to create sessions directory")?;
+        let content = serde_json::to_string_pretty(meta).context("Failed to serialize session details")?;
+        std::fs::write(meta_path(&self.path), content).context("Failed to write session details")
+    }
+
+    /// Records an entry, logging instead of failing (the live loop must keep running)
+    pub fn record_or_warn(
+        &self,
+        frame: &Frame,
+        fen: &str,
+        best_move: &str,
+        evaluation: &str,
+        comment: Option<&str>,
+    ) -> Option<MoveTime> {
+        self.record(frame, fen, best_move, evaluation, comment)
+            .unwrap_or_else(|e| {
+                eprintln!("⚠ Session not recorded: {:#}", e);
+                None
+            })
+    }
+}
+
+/// Resolves a session argument: "last" for the most recent session, otherwise a path
+pub fn resolve(session: &str) -> Result<PathBuf> {
+    if session != "last" {
+        return Ok(PathBuf::from(session));
+    }
+    list()?.pop().context("No sessions recorded yet")
+}
+
+/// All recorded session logs, oldest first
+pub fn list() -> Result<Vec<PathBuf>> {
+    let mut sessions: Vec<PathBuf> = std::fs::read_dir(SESSIONS_DIR)
+        .context("No sessions recorded yet")?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    sessions.sort();
+    Ok(sessions)
+}
+
+/// Loads all entries of a session file (malformed lines are skipped with a warning)
+pub fn load(path: &Path) -> Result<Vec<SessionEntry>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read session: {}", path.display()))?;
+    let mut entries = Vec::new();
+    for (line_no, line) in content.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        match serde_json::from_str::<SessionEntry>(line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => eprintln!("⚠ Skipping {} line {}: {}", path.display(), line_no + 1, e),
+        }
+    }
+    Ok(entries)
+}
+
+/// Game details recorded for a session, if any
+pub fn load_meta(path: &Path) -> Option<SessionMeta> {
+    let content = std::fs::read_to_string(meta_path(path)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// One line of `history fens`: `<timestamp ms>\t<eval>\t<FEN>`, or the FEN, eval, and
+/// timestamp as a JSON object. The timestamp is when the board was captured, if known.
+pub fn fen_history_line(entry: &SessionEntry, json: bool) -> Result<String> {
+    let timestamp_ms = entry.captured_ms.unwrap_or(entry.timestamp_ms);
+    if json {
+        let value = serde_json::json!({
+            "timestamp_ms": timestamp_ms as u64,
+            "fen": entry.fen,
+            "evaluation": entry.evaluation,
+        });
+        return serde_json::to_string(&value).context("Failed to serialize history line");
+    }
+    Ok(format!("{}\t{}\t{}", timestamp_ms, entry.evaluation, entry.fen))
+}
+
+fn meta_path(session: &Path) -> PathBuf {
+    session.with_extension("meta.json")
+}
+
+/// Live PGN of a session, next to its log
+fn pgn_path(session: &Path) -> PathBuf {
+    session.with_extension("pgn")
+}
+
+fn now_ms() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_roundtrip_omits_empty_comment() {
+        let entry = SessionEntry {
+            timestamp_ms: 1,
+            captured_ms: None,
+            cycle: None,
+            fen: "8/8/8/8/8/8/8/K6k w - - 0 1".to_string(),
+            best_move: "A1 to A2".to_string(),
+            evaluation: "+0.00".to_string(),
+            comment: None,
+            frame: None,
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(!json.contains("comment"));
+        assert_eq!(serde_json::from_str::<SessionEntry>(&json).unwrap(), entry);
+    }
+
+    #[test]
+    fn test_fen_history_line() {
+        let entry = SessionEntry {
+            timestamp_ms: 1700000000123,
+            captured_ms: None,
+            cycle: None,
+            fen: "8/8/8/8/8/8/8/K6k w - - 0 1".to_string(),
+            best_move: "A1 to A2".to_string(),
+            evaluation: "-0.40".to_string(),
+            comment: None,
+            frame: None,
+        };
+        assert_eq!(fen_history_line(&entry, false).unwrap(), "1700000000123\t-0.40\t8/8/8/8/8/8/8/K6k w - - 0 1");
+        let json: serde_json::Value = serde_json::from_str(&fen_history_line(&entry, true).unwrap()).unwrap();
+        assert_eq!(json["timestamp_ms"], 1700000000123u64);
+        assert_eq!(json["evaluation"], "-0.40");
+    }
+
+    #[test]
+    fn test_meta_file_sits_next_to_session() {
+        assert_eq!(meta_path(Path::new("sessions/123.jsonl")), Path::new("sessions/123.meta.json"));
+        assert_eq!(pgn_path(Path::new("sessions/123-board2.jsonl")), Path::new("sessions/123-board2.pgn"));
+    }
+
+    #[test]
+    fn test_meta_keeps_player_fields_flat() {
+        let meta = SessionMeta {
+            players: Players { user: None, opponent: Some(crate::players::PlayerTag { name: "x".to_string(), rating: None }) },
+            time_control: Some("180+2".to_string()),
+        };
+        let json = serde_json::to_value(&meta).unwrap();
+        assert_eq!(json["opponent"]["name"], "x");
+        assert_eq!(serde_json::from_value::<SessionMeta>(json).unwrap(), meta);
+    }
+}
@@ -0,0 +1,180 @@
+//! Session totals, printed as a summary when the live loop stops
+//!
+//! Analyzed cycles with their step timings (as shown by `--verbose`) and every LLM response
+//! with its token usage are added up. The cost is an estimate from list prices per million
+//! tokens; models not in `PRICES` (local ones, custom names) are counted but not priced.
+//! The time each side spent per move (when a move was seen on its own) is averaged too.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// USD per million input and output tokens, matched by model name prefix (first match wins)
+const PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("claude-sonnet", 3.00, 15.00),
+    ("claude-opus", 15.00, 75.00),
+    ("claude-haiku", 0.80, 4.00),
+    ("gemini-2.0-flash", 0.10, 0.40),
+];
+
+static STATS: Mutex<Stats> = Mutex::new(Stats::new());
+
+#[derive(Default)]
+struct Stats {
+    cycles: u64,
+    /// Step name, total time, and how many cycles had the step, in first-seen order
+    steps: Vec<(&'static str, Duration, u64)>,
+    api_calls: u64,
+    tokens: u64,
+    cost_usd: f64,
+    /// Calls to models without a price
+    unpriced_calls: u64,
+    /// Total, longest, and count of move times: the user's, then the opponent's
+    move_times: [(Duration, Duration, u64); 2],
+}
+
+impl Stats {
+    const fn new() -> Self {
+        Stats {
+            cycles: 0,
+            steps: Vec::new(),
+            api_calls: 0,
+            tokens: 0,
+            cost_usd: 0.0,
+            unpriced_calls: 0,
+            move_times: [(Duration::ZERO, Duration::ZERO, 0); 2],
+        }
+    }
+
+    fn record_cycle(&mut self, timings: &[(&'static str, Duration)]) {
+        self.cycles += 1;
+        for &(name, elapsed) in timings {
+            match self.steps.iter_mut().find(|(step, ..)| *step == name) {
+                Some((_, total, count)) => {
+                    *total += elapsed;
+                    *count += 1;
+                }
+                None => self.steps.push((name, elapsed, 1)),
+            }
+        }
+    }
+
+    fn record_api_call(&mut self, model: &str, input_tokens: u64, output_tokens: u64) {
+        self.api_calls += 1;
+        self.tokens += input_tokens + output_tokens;
+        match price(model) {
+            Some((input, output)) => {
+                self.cost_usd += (input_tokens as f64 * input + output_tokens as f64 * output) / 1_000_000.0;
+            }
+            None => self.unpriced_calls += 1,
+        }
+    }
+
+    fn record_move_time(&mut self, user: bool, spent: Duration) {
+        let (total, longest, count) = &mut self.move_times[usize::from(!user)];
+        *total += spent;
+        *longest = (*longest).max(spent);
+        *count += 1;
+    }
+
+    fn summary(&self, captures: u64) -> Vec<String> {
+        let mut lines = vec![format!("Cycles:     {} analyzed ({} captured)", self.cycles, captures)];
+        if !self.steps.is_empty() {
+            let averages: Vec<String> = self
+                .steps
+                .iter()
+                .map(|(name, total, count)| format!("{} {:.0}ms", name, total.as_secs_f64() * 1000.0 / *count as f64))
+                .collect();
+            lines.push(format!("Average:    {}", averages.join(", ")));
+        }
+        if self.api_calls > 0 {
+            lines.push(format!("API calls:  {} ({} tokens)", self.api_calls, self.tokens));
+            let unpriced = match self.unpriced_calls {
+                0 => String::new(),
+                n => format!(" (+{} calls to unpriced models)", n),
+            };
+            lines.push(format!("Est. cost:  ${:.4}{}", self.cost_usd, unpriced));
+        }
+        let move_times: Vec<String> = ["you", "opponent"]
+            .iter()
+            .zip(&self.move_times)
+            .filter(|(_, (_, _, count))| *count > 0)
+            .map(|(side, (total, longest, count))| {
+                format!("{} {:.1}s avg ({:.0}s max)", side, total.as_secs_f64() / *count as f64, longest.as_secs_f64())
+            })
+            .collect();
+        if !move_times.is_empty() {
+            lines.push(format!("Move time:  {}", move_times.join(", ")));
+        }
+        lines
+    }
+}
+
+/// USD per million input and output tokens for `model`, if known
+pub fn price(model: &str) -> Option<(f64, f64)> {
+    PRICES.iter().find(|(prefix, ..)| model.starts_with(prefix)).map(|&(_, input, output)| (input, output))
+}
+
+/// Adds an analyzed cycle's step timings
+pub fn record_cycle(timings: &[(&'static str, Duration)]) {
+    if let Ok(mut stats) = STATS.lock() {
+        stats.record_cycle(timings);
+    }
+}
+
+/// Adds an LLM response and its token usage
+pub fn record_api_call(model: &str, input_tokens: u64, output_tokens: u64) {
+    if let Ok(mut stats) = STATS.lock() {
+        stats.record_api_call(model, input_tokens, output_tokens);
+    }
+}
+
+/// Adds the time the user (or the opponent) spent on a move
+pub fn record_move_time(user: bool, spent: Duration) {
+    if let Ok(mut stats) = STATS.lock() {
+        stats.record_move_time(user, spent);
+    }
+}
+
+/// Summary lines for the session; `captures` counts every screenshot taken, skipped or not
+pub fn summary(captures: u64) -> Vec<String> {
+    STATS.lock().map(|stats| stats.summary(captures)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_averages_and_costs() {
+        let mut stats = Stats::default();
+        stats.record_cycle(&[("capture", Duration::from_millis(40)), ("ocr", Duration::from_millis(900))]);
+        stats.record_cycle(&[("capture", Duration::from_millis(60)), ("ocr", Duration::from_millis(1100))]);
+        stats.record_api_call("gpt-4o-2024-08-06", 1_000_000, 100_000);
+        stats.record_api_call("gpt-4o-mini", 1_000_000, 0);
+        stats.record_api_call("llava", 500, 20);
+
+        let lines = stats.summary(5);
+        assert_eq!(lines[0], "Cycles:     2 analyzed (5 captured)");
+        assert_eq!(lines[1], "Average:    capture 50ms, ocr 1000ms");
+        assert_eq!(lines[2], "API calls:  3 (2100520 tokens)");
+        // 2.50 + 1.00 for gpt-4o, 0.15 for the mini
+        assert_eq!(lines[3], "Est. cost:  $3.6500 (+1 calls to unpriced models)");
+    }
+
+    #[test]
+    fn test_summary_without_llm() {
+        let lines = Stats::default().summary(0);
+        assert_eq!(lines, vec!["Cycles:     0 analyzed (0 captured)"]);
+    }
+
+    #[test]
+    fn test_summary_move_times_per_side() {
+        let mut stats = Stats::default();
+        stats.record_move_time(true, Duration::from_secs(4));
+        stats.record_move_time(true, Duration::from_secs(11));
+        stats.record_move_time(false, Duration::from_millis(2500));
+        assert_eq!(stats.summary(0)[1], "Move time:  you 7.5s avg (11s max), opponent 2.5s avg (2s max)");
+    }
+}
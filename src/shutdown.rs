@@ -0,0 +1,73 @@
+//! Ctrl+C handling for the live loop
+//!
+//! The first Ctrl+C asks the loop to stop: a wait (the interval sleep, or waiting for Enter)
+//! ends at once, a cycle in progress is finished first, so the session log, its PGN, and the
+//! outputs are never left half-written. The loop then cleans up and prints the session
+//! summary (`session_stats`). A second Ctrl+C quits right away, after the exit hooks (e.g.
+//! restoring the terminal from the TUI).
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Set by the first Ctrl+C
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Wakes the waits in `wait`
+static STOP: Notify = Notify::const_new();
+
+/// Run before quitting on a second Ctrl+C
+static EXIT_HOOKS: Mutex<Vec<fn()>> = Mutex::new(Vec::new());
+
+/// Listens for Ctrl+C (call once, inside the runtime)
+pub fn install() {
+    tokio::spawn(async {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            interrupt();
+        }
+    });
+}
+
+/// Acts on a Ctrl+C: asks the loop to stop the first time, quits the second. Called for
+/// the signal, and by the TUI, whose raw mode gets Ctrl+C as a key instead.
+pub fn interrupt() {
+    if REQUESTED.swap(true, Ordering::Relaxed) {
+        for hook in EXIT_HOOKS.lock().map(|hooks| hooks.clone()).unwrap_or_default() {
+            hook();
+        }
+        crate::ocr_command::remove_scratch_images();
+        std::process::exit(130);
+    }
+    STOP.notify_waiters();
+    eprintln!("\nStopping after the current cycle... (Ctrl+C again to quit now)");
+}
+
+/// Runs `hook` before quitting on a second Ctrl+C
+pub fn on_forced_exit(hook: fn()) {
+    if let Ok(mut hooks) = EXIT_HOOKS.lock() {
+        hooks.push(hook);
+    }
+}
+
+/// True once Ctrl+C was pressed
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::Relaxed)
+}
+
+/// Returns when Ctrl+C is pressed (at once if it already was)
+pub async fn wait() {
+    let stop = STOP.notified();
+    if requested() {
+        return;
+    }
+    stop.await;
+}
+
+/// Sleeps like `tokio::time::sleep`, cut short by Ctrl+C
+pub async fn sleep(duration: Duration) {
+    tokio::select! {
+        _ = tokio::time::sleep(duration) => {}
+        _ = wait() => {}
+    }
+}
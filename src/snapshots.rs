@@ -0,0 +1,211 @@
+//! Named analysis snapshots
+//!
+//! `snapshot <name>` (or `b` to bookmark without a name) saves the last analyzed position
+//! of the live loop under `snapshots/`: the FEN, the engine's move, eval and candidate lines,
+//! and the screenshot it was read from. Each snapshot gets its own image file and a line in
+//! `snapshots/snapshots.jsonl`, like hard cases.
+//!
+//! `zugzwang-rs snapshots` lists them; `snapshots analyze [SNAPSHOT]` searches them again at
+//! a depth the live loop can't afford, next to what was shown during the game.
+
+use anyhow::{Context, Result};
+use crate::capture;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Directory holding snapshots and their manifest
+pub const SNAPSHOTS_DIR: &str = "snapshots";
+
+/// Default depth for re-analysis (the live loop uses `engine::DEFAULT_DEPTH`)
+pub const SNAPSHOT_DEPTH: u16 = 10;
+
+/// Candidate lines shown by re-analysis
+const CANDIDATES: usize = 3;
+
+/// One saved position, serialized as a JSON line in the manifest
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Milliseconds since the Unix epoch when the snapshot was taken
+    pub timestamp_ms: u128,
+    /// Name given with `snapshot <name>` (empty for `b`)
+    #[serde(default)]
+    pub name: String,
+    pub fen: String,
+    pub best_move: String,
+    /// Engine eval from the side to move's perspective (e.g. "+0.35")
+    pub evaluation: String,
+    /// (move, eval) candidate lines shown with MultiPV on
+    #[serde(default)]
+    pub candidates: Vec<(String, String)>,
+    /// Depth of the live analysis
+    pub depth: u16,
+    /// Image file (relative to `snapshots/`)
+    pub image: String,
+    /// ID of the cycle that analyzed the position
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycle: Option<String>,
+}
+
+impl Snapshot {
+    /// Name, or a stand-in for unnamed snapshots
+    pub fn label(&self) -> &str {
+        if self.name.is_empty() { "(unnamed)" } else { &self.name }
+    }
+}
+
+/// The live loop's last analysis, kept for `snapshot`
+pub struct Analyzed {
+    pub fen: String,
+    pub best_move: String,
+    pub evaluation: String,
+    pub candidates: Vec<(String, String)>,
+    pub depth: u16,
+    pub image: Arc<DynamicImage>,
+    pub cycle: String,
+}
+
+/// Stores the image (lossless) and appends the snapshot to the manifest
+pub fn save(name: &str, analyzed: &Analyzed) -> Result<Snapshot> {
+    std::fs::create_dir_all(SNAPSHOTS_DIR).context("Failed to create snapshots directory")?;
+    let timestamp_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+
+    let image = format!("{}.png", timestamp_ms);
+    let image_path = format!("{}/{}", SNAPSHOTS_DIR, image);
+    std::fs::write(&image_path, capture::encode(&analyzed.image, capture::Consumer::TemplateMatching)?)
+        .with_context(|| format!("Failed to write {}", image_path))?;
+
+    let snapshot = Snapshot {
+        timestamp_ms,
+        name: name.trim().to_string(),
+        fen: analyzed.fen.clone(),
+        best_move: analyzed.best_move.clone(),
+        evaluation: analyzed.evaluation.clone(),
+        candidates: analyzed.candidates.clone(),
+        depth: analyzed.depth,
+        image,
+        cycle: Some(analyzed.cycle.clone()),
+    };
+    let mut manifest = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(manifest_path())
+        .context("Failed to open snapshot manifest")?;
+    let line = serde_json::to_string(&snapshot).context("Failed to serialize snapshot")?;
+    writeln!(manifest, "{}", line).context("Failed to write snapshot manifest")?;
+    Ok(snapshot)
+}
+
+/// All snapshots, oldest first (none before the first; malformed lines are skipped)
+pub fn load() -> Result<Vec<Snapshot>> {
+    let path = manifest_path();
+    if !std::path::Path::new(&path).exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path))?;
+    let mut snapshots = Vec::new();
+    for (line_no, line) in content.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        match serde_json::from_str::<Snapshot>(line) {
+            Ok(snapshot) => snapshots.push(snapshot),
+            Err(e) => eprintln!("⚠ Skipping {} line {}: {}", path, line_no + 1, e),
+        }
+    }
+    Ok(snapshots)
+}
+
+/// Prints the snapshots, numbered for `snapshots analyze`
+pub fn list() -> Result<()> {
+    let snapshots = load()?;
+    if snapshots.is_empty() {
+        println!("No snapshots yet (type `snapshot <name>` or `b` while analyzing)");
+    }
+    for (number, snapshot) in snapshots.iter().enumerate() {
+        println!("{:>3}. {}", number + 1, snapshot.label());
+        println!("     Best: {} ({}) at depth {}", snapshot.best_move, snapshot.evaluation, snapshot.depth);
+        println!("     FEN:  {}", snapshot.fen);
+        println!("     {}/{}", SNAPSHOTS_DIR, snapshot.image);
+    }
+    Ok(())
+}
+
+/// Analyzes the chosen snapshots (all without `selector`) again at `depth`
+pub fn analyze(selector: Option<&str>, depth: u16) -> Result<()> {
+    let snapshots = load()?;
+    let chosen = select(&snapshots, selector)?;
+    anyhow::ensure!(!chosen.is_empty(), "No snapshots yet (type `snapshot <name>` or `b` while analyzing)");
+    for (number, snapshot) in chosen {
+        println!("{:>3}. {}", number, snapshot.label());
+        println!("     Then: {} ({}) at depth {}", snapshot.best_move, snapshot.evaluation, snapshot.depth);
+        match crate::engine::analyze_multipv(&snapshot.fen, depth, CANDIDATES) {
+            Ok(analysis) => {
+                println!("     Now:  {} ({}) at depth {}", analysis.best_move, analysis.eval, depth);
+                for (rank, (mv, eval)) in analysis.candidates.iter().enumerate() {
+                    println!("       {}. {} ({})", rank + 1, mv, eval);
+                }
+            }
+            Err(e) => println!("     ⚠ {:#}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Snapshots with their 1-based numbers: the one numbered or named `selector`, or all
+fn select<'a>(snapshots: &'a [Snapshot], selector: Option<&str>) -> Result<Vec<(usize, &'a Snapshot)>> {
+    let numbered = snapshots.iter().enumerate().map(|(i, snapshot)| (i + 1, snapshot));
+    let Some(selector) = selector else {
+        return Ok(numbered.collect());
+    };
+    let found: Vec<_> = match selector.parse::<usize>() {
+        Ok(number) => numbered.filter(|(n, _)| *n == number).collect(),
+        Err(_) => numbered.filter(|(_, snapshot)| snapshot.name.eq_ignore_ascii_case(selector.trim())).collect(),
+    };
+    anyhow::ensure!(!found.is_empty(), "No snapshot '{}' (see `snapshots`)", selector);
+    Ok(found)
+}
+
+fn manifest_path() -> String {
+    format!("{}/snapshots.jsonl", SNAPSHOTS_DIR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(name: &str) -> Snapshot {
+        Snapshot {
+            timestamp_ms: 42,
+            name: name.to_string(),
+            fen: "8/8/8/8/8/8/8/K6k w - - 0 1".to_string(),
+            best_move: "a1b2".to_string(),
+            evaluation: "+0.00".to_string(),
+            candidates: vec![("a1b2".to_string(), "+0.00".to_string())],
+            depth: 6,
+            image: "42.png".to_string(),
+            cycle: None,
+        }
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let json = serde_json::to_string(&snapshot("critical moment")).unwrap();
+        assert!(!json.contains("cycle"));
+        let parsed: Snapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, snapshot("critical moment"));
+        assert_eq!(snapshot("").label(), "(unnamed)");
+    }
+
+    #[test]
+    fn test_select_by_number_or_name() {
+        let snapshots = vec![snapshot("opening trap"), snapshot(""), snapshot("Critical moment")];
+        assert_eq!(select(&snapshots, None).unwrap().len(), 3);
+        assert_eq!(select(&snapshots, Some("2")).unwrap()[0].0, 2);
+        assert_eq!(select(&snapshots, Some("critical moment")).unwrap()[0].0, 3);
+        assert!(select(&snapshots, Some("4")).is_err());
+        assert!(select(&snapshots, Some("endgame")).is_err());
+    }
+}
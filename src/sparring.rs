@@ -0,0 +1,237 @@
+//! Sparring opponent and post-game review for `play`
+//!
+//! At full strength the built-in engine is no fun to practice against. With
+//! `play --rating <elo>` the engine picks its reply from the scored move list instead:
+//! the lower the rating, the more often it settles for an inaccuracy, and now and then it
+//! deliberately hands over a tactical opportunity (a "gift") for the user to find.
+//!
+//! Every user move is recorded with its centipawn loss, so the game ends with an accuracy
+//! report: per-move accuracy from win-probability drop (the same curve lichess uses),
+//! inaccuracy/mistake/blunder counts, and how many gifts were punished.
+
+/// Centipawn loss thresholds for move classification
+const INACCURACY_CP: i32 = 50;
+const MISTAKE_CP: i32 = 100;
+const BLUNDER_CP: i32 = 300;
+
+/// A gift costs the engine at least this much (and at most `GIFT_MAX_CP`)
+const GIFT_MIN_CP: i32 = 150;
+const GIFT_MAX_CP: i32 = 600;
+
+/// Rating range the strength model is calibrated for
+pub const MIN_RATING: u16 = 600;
+pub const MAX_RATING: u16 = 2600;
+
+/// Rating-limited engine opponent
+pub struct Sparring {
+    rating: u16,
+    rng: u64,
+}
+
+impl Sparring {
+    /// Creates an opponent at `rating` (clamped to MIN_RATING..=MAX_RATING)
+    pub fn new(rating: u16) -> Self {
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0x9E37_79B9_7F4A_7C15);
+        Self::with_seed(rating, seed)
+    }
+
+    fn with_seed(rating: u16, seed: u64) -> Self {
+        Sparring {
+            rating: rating.clamp(MIN_RATING, MAX_RATING),
+            rng: seed | 1,
+        }
+    }
+
+    /// Chance of not playing the best move (5% at 2600, 80% at 600)
+    fn error_rate(&self) -> f64 {
+        let weakness = (MAX_RATING - self.rating) as f64 / (MAX_RATING - MIN_RATING) as f64;
+        0.05 + 0.75 * weakness
+    }
+
+    /// Largest centipawn loss accepted for an ordinary inaccuracy (30 at 2600, 230 at 600)
+    fn max_inaccuracy_cp(&self) -> i32 {
+        30 + (MAX_RATING - self.rating) as i32 / 10
+    }
+
+    /// Picks a reply from moves scored best-first. Returns the move and whether it is a gift.
+    pub fn choose(&mut self, scored: &[(String, i32)]) -> Option<(String, bool)> {
+        let (best_move, best_score) = scored.first()?;
+        let error_rate = self.error_rate();
+
+        // Occasionally offer a tactic: a move that drops material the user can win
+        if self.next_f64() < error_rate / 4.0 {
+            let gifts: Vec<&(String, i32)> = scored
+                .iter()
+                .filter(|(_, s)| (GIFT_MIN_CP..=GIFT_MAX_CP).contains(&(best_score - s)))
+                .collect();
+            if !gifts.is_empty() {
+                let pick = gifts[self.next_index(gifts.len())];
+                return Some((pick.0.clone(), true));
+            }
+        }
+
+        if self.next_f64() < error_rate {
+            let max_loss = self.max_inaccuracy_cp();
+            let inaccuracies: Vec<&(String, i32)> =
+                scored.iter().skip(1).filter(|(_, s)| best_score - s <= max_loss).collect();
+            if !inaccuracies.is_empty() {
+                let pick = inaccuracies[self.next_index(inaccuracies.len())];
+                return Some((pick.0.clone(), false));
+            }
+        }
+
+        Some((best_move.clone(), false))
+    }
+
+    /// xorshift64* step
+    fn next_u64(&mut self) -> u64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        self.rng.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn next_index(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+}
+
+/// One user move as seen by the review
+#[derive(Debug, Clone)]
+struct ReviewedMove {
+    played: String,
+    best: String,
+    loss_cp: i32,
+    accuracy: f64,
+    /// The engine's previous move was a gift
+    after_gift: bool,
+}
+
+/// Collects the user's moves for the post-game accuracy report
+#[derive(Debug, Default)]
+pub struct Review {
+    moves: Vec<ReviewedMove>,
+}
+
+impl Review {
+    /// Records a user move given the best move's score and the played move's score
+    /// (both centipawns from the user's perspective, same search)
+    pub fn record(&mut self, played: &str, played_cp: i32, best: &str, best_cp: i32, after_gift: bool) {
+        let loss_cp = (best_cp - played_cp).max(0);
+        let accuracy = move_accuracy(win_percent(best_cp), win_percent(played_cp));
+        self.moves.push(ReviewedMove {
+            played: played.to_string(),
+            best: best.to_string(),
+            loss_cp,
+            accuracy,
+            after_gift,
+        });
+    }
+
+    /// Average move accuracy in percent (None before any move)
+    pub fn accuracy(&self) -> Option<f64> {
+        if self.moves.is_empty() {
+            return None;
+        }
+        Some(self.moves.iter().map(|m| m.accuracy).sum::<f64>() / self.moves.len() as f64)
+    }
+
+    /// Formats the post-game report
+    pub fn report(&self) -> String {
+        let Some(accuracy) = self.accuracy() else {
+            return "No moves to review.".to_string();
+        };
+
+        let count = |min: i32, max: i32| self.moves.iter().filter(|m| m.loss_cp >= min && m.loss_cp < max).count();
+        let avg_loss = self.moves.iter().map(|m| m.loss_cp).sum::<i32>() / self.moves.len() as i32;
+
+        let mut lines = vec![
+            "── Game review ──────────────────────────────────────────────".to_string(),
+            format!("Accuracy:      {:.1}% over {} moves (avg loss {} cp)", accuracy, self.moves.len(), avg_loss),
+            format!(
+                "Inaccuracies:  {}   Mistakes: {}   Blunders: {}",
+                count(INACCURACY_CP, MISTAKE_CP),
+                count(MISTAKE_CP, BLUNDER_CP),
+                count(BLUNDER_CP, i32::MAX)
+            ),
+        ];
+
+        let gifts: Vec<&ReviewedMove> = self.moves.iter().filter(|m| m.after_gift).collect();
+        if !gifts.is_empty() {
+            let punished = gifts.iter().filter(|m| m.loss_cp < INACCURACY_CP).count();
+            lines.push(format!("Tactics found: {}/{} opportunities", punished, gifts.len()));
+        }
+
+        for (n, m) in self.moves.iter().enumerate().filter(|(_, m)| m.loss_cp >= MISTAKE_CP) {
+            lines.push(format!("  move {}: {} (-{} cp), best was {}", n + 1, m.played, m.loss_cp, m.best));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Win probability (0-100) for a centipawn score
+pub fn win_percent(cp: i32) -> f64 {
+    let cp = cp.clamp(-1000, 1000) as f64;
+    50.0 + 50.0 * (2.0 / (1.0 + (-0.003_682_08 * cp).exp()) - 1.0)
+}
+
+/// Accuracy (0-100) of a move from the win-probability drop it caused
+fn move_accuracy(win_before: f64, win_after: f64) -> f64 {
+    let drop = (win_before - win_after).max(0.0);
+    (103.1668 * (-0.04354 * drop).exp() - 3.1669).clamp(0.0, 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scored() -> Vec<(String, i32)> {
+        vec![
+            ("E2 to E4".to_string(), 40),
+            ("D2 to D4".to_string(), 35),
+            ("G1 to F3".to_string(), 20),
+            ("F2 to F3".to_string(), -200),
+        ]
+    }
+
+    #[test]
+    fn test_full_strength_mostly_plays_best() {
+        let mut sparring = Sparring::with_seed(MAX_RATING, 42);
+        let best = (0..200).filter(|_| sparring.choose(&scored()).unwrap().0 == "E2 to E4").count();
+        assert!(best > 150, "best move chosen only {} times", best);
+    }
+
+    #[test]
+    fn test_weak_opponent_errs_and_gifts() {
+        let mut sparring = Sparring::with_seed(MIN_RATING, 7);
+        let picks: Vec<(String, bool)> = (0..200).filter_map(|_| sparring.choose(&scored())).collect();
+        assert!(picks.iter().filter(|(mv, _)| mv != "E2 to E4").count() > 60);
+        assert!(picks.iter().any(|(mv, gift)| *gift && mv == "F2 to F3"));
+    }
+
+    #[test]
+    fn test_move_accuracy_curve() {
+        assert!((move_accuracy(50.0, 50.0) - 100.0).abs() < 0.01);
+        assert!(move_accuracy(win_percent(0), win_percent(-300)) < 50.0);
+    }
+
+    #[test]
+    fn test_review_report() {
+        let mut review = Review::default();
+        assert!(review.accuracy().is_none());
+        review.record("E2 to E4", 40, "E2 to E4", 40, false);
+        review.record("F2 to F3", -200, "D1 to H5", 300, true);
+        let report = review.report();
+        assert!(report.contains("over 2 moves"));
+        assert!(report.contains("Blunders: 1"));
+        assert!(report.contains("Tactics found: 0/1"));
+        assert!(report.contains("best was D1 to H5"));
+    }
+}
@@ -0,0 +1,331 @@
+//! Spoken output (`--output tts`)
+//!
+//! The best move is read out as a player would say it - "Knight takes ee 5, check" -
+//! rather than as coordinates: the piece, capture, castling, promotion, and check come from
+//! the position, and file letters are spelled so the voice says the letter instead of a
+//! word ("a" as "ay"). Phrasing follows `--tts-language` (en, de, fr, es); `--tts-voice`
+//! picks the platform voice (`say -v`, `espeak -v`, or a Windows SAPI voice). espeak uses
+//! the language's own voice when none is given.
+//!
+//! Results without a position (direct mode) fall back to the headline with signs spelled out.
+
+use crate::output::CycleOutput;
+use crate::pgn;
+use shakmaty::uci::UciMove;
+use shakmaty::{CastlingMode, CastlingSide, Move, Position, Role, Square};
+use std::sync::OnceLock;
+
+/// Language of spoken phrases (`--tts-language`, set once at startup)
+static LANGUAGE: OnceLock<Language> = OnceLock::new();
+
+/// Platform voice (`--tts-voice`, set once at startup)
+static VOICE: OnceLock<String> = OnceLock::new();
+
+/// Languages with chess phrasing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+}
+
+impl Language {
+    /// Codes accepted by `--tts-language` (also espeak's voice names)
+    pub const CODES: [&str; 4] = ["en", "de", "fr", "es"];
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "en" => Some(Language::English),
+            "de" => Some(Language::German),
+            "fr" => Some(Language::French),
+            "es" => Some(Language::Spanish),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+            Language::French => "fr",
+            Language::Spanish => "es",
+        }
+    }
+
+    fn phrases(self) -> &'static Phrases {
+        match self {
+            Language::English => &ENGLISH,
+            Language::German => &GERMAN,
+            Language::French => &FRENCH,
+            Language::Spanish => &SPANISH,
+        }
+    }
+}
+
+/// Sets the spoken language (first call wins)
+pub fn set_language(language: Language) {
+    let _ = LANGUAGE.set(language);
+}
+
+/// Sets the platform voice (first call wins)
+pub fn set_voice(voice: &str) {
+    let _ = VOICE.set(voice.to_string());
+}
+
+/// Words for one language
+struct Phrases {
+    /// King, queen, rook, bishop, knight, pawn
+    pieces: [&'static str; 6],
+    /// Files a-h as the letter is pronounced
+    files: [&'static str; 8],
+    takes: &'static str,
+    castles: [&'static str; 2],
+    promotes: &'static str,
+    check: &'static str,
+    checkmate: &'static str,
+    plus: &'static str,
+    minus: &'static str,
+    decimal: char,
+    /// "{n}" is replaced by the number of moves
+    mate_in: &'static str,
+    mated_in: &'static str,
+}
+
+const ENGLISH: Phrases = Phrases {
+    pieces: ["King", "Queen", "Rook", "Bishop", "Knight", "Pawn"],
+    files: ["ay", "bee", "see", "dee", "ee", "eff", "gee", "aitch"],
+    takes: "takes",
+    castles: ["Castles kingside", "Castles queenside"],
+    promotes: "promotes to",
+    check: "check",
+    checkmate: "checkmate",
+    plus: "plus",
+    minus: "minus",
+    decimal: '.',
+    mate_in: "mate in {n}",
+    mated_in: "mated in {n}",
+};
+
+const GERMAN: Phrases = Phrases {
+    pieces: ["König", "Dame", "Turm", "Läufer", "Springer", "Bauer"],
+    files: ["a", "be", "ce", "de", "e", "ef", "ge", "ha"],
+    takes: "schlägt",
+    castles: ["Kurze Rochade", "Lange Rochade"],
+    promotes: "wandelt um in",
+    check: "Schach",
+    checkmate: "Schachmatt",
+    plus: "plus",
+    minus: "minus",
+    decimal: ',',
+    mate_in: "Matt in {n}",
+    mated_in: "wird in {n} mattgesetzt",
+};
+
+const FRENCH: Phrases = Phrases {
+    pieces: ["Roi", "Dame", "Tour", "Fou", "Cavalier", "Pion"],
+    files: ["a", "bé", "cé", "dé", "e", "effe", "gé", "ache"],
+    takes: "prend",
+    castles: ["Petit roque", "Grand roque"],
+    promotes: "promu en",
+    check: "échec",
+    checkmate: "échec et mat",
+    plus: "plus",
+    minus: "moins",
+    decimal: ',',
+    mate_in: "mat en {n}",
+    mated_in: "maté en {n}",
+};
+
+const SPANISH: Phrases = Phrases {
+    pieces: ["Rey", "Dama", "Torre", "Alfil", "Caballo", "Peón"],
+    files: ["a", "be", "ce", "de", "e", "efe", "ge", "hache"],
+    takes: "captura",
+    castles: ["Enroque corto", "Enroque largo"],
+    promotes: "corona",
+    check: "jaque",
+    checkmate: "jaque mate",
+    plus: "más",
+    minus: "menos",
+    decimal: ',',
+    mate_in: "mate en {n}",
+    mated_in: "recibe mate en {n}",
+};
+
+/// What to say for a result in the configured language
+pub fn spoken(output: &CycleOutput) -> String {
+    sentence(output, LANGUAGE.get().copied().unwrap_or_default())
+}
+
+/// Speech tool and arguments for the platform, with the configured voice
+pub fn command(text: &str) -> (&'static str, Vec<String>) {
+    let language = LANGUAGE.get().copied().unwrap_or_default();
+    let voice = VOICE.get().cloned();
+    let text = text.to_string();
+    if cfg!(target_os = "macos") {
+        match voice {
+            Some(voice) => ("say", vec!["-v".to_string(), voice, text]),
+            None => ("say", vec![text]),
+        }
+    } else if cfg!(windows) {
+        let select = voice.map(|v| format!("$s.SelectVoice('{}'); ", v.replace('\'', "''"))).unwrap_or_default();
+        let script = format!(
+            "Add-Type -AssemblyName System.Speech; $s = New-Object System.Speech.Synthesis.SpeechSynthesizer; {}$s.Speak('{}')",
+            select,
+            text.replace('\'', "''")
+        );
+        ("powershell", vec!["-NoProfile".to_string(), "-Command".to_string(), script])
+    } else {
+        let voice = voice.unwrap_or_else(|| language.code().to_string());
+        ("espeak", vec!["-v".to_string(), voice, text])
+    }
+}
+
+fn sentence(output: &CycleOutput, language: Language) -> String {
+    let phrases = language.phrases();
+    let value = &output.value;
+    let fen = value["fen"].as_str();
+    let best = value["best_move"].as_str().or(value["engine"]["move"].as_str());
+    let eval = value["evaluation"].as_str().or(value["engine"]["evaluation"].as_str());
+    let Some(said) = fen.zip(best).and_then(|(fen, best)| spoken_move(fen, best, phrases)) else {
+        return speakable(&output.headline, phrases);
+    };
+    match eval.and_then(|eval| spoken_eval(eval, phrases)) {
+        Some(eval) => format!("{}. {}.", said, eval),
+        None => format!("{}.", said),
+    }
+}
+
+/// A readable engine move ("G1 to F3", "E7 to E8 (=Q)") as spoken in `fen`
+fn spoken_move(fen: &str, readable: &str, phrases: &Phrases) -> Option<String> {
+    let position = pgn::parse_position(fen)?;
+    let mut words = readable.split_whitespace();
+    let from: Square = words.next()?.to_lowercase().parse().ok()?;
+    let to: Square = words.nth(1)?.to_lowercase().parse().ok()?;
+    let promotion = readable.split_once("(=").and_then(|(_, rest)| rest.chars().next()).and_then(|c| Role::from_char(c.to_ascii_lowercase()));
+    let legal = position.legal_moves();
+    let m = legal.iter().find(|m| match m.to_uci(CastlingMode::Standard) {
+        UciMove::Normal { from: f, to: t, promotion: p } => f == from && t == to && (promotion.is_none() || p == promotion),
+        _ => false,
+    })?;
+
+    let mut said = describe(m, phrases);
+    let after = position.play(*m).ok()?;
+    if after.is_checkmate() {
+        said = format!("{}, {}", said, phrases.checkmate);
+    } else if after.is_check() {
+        said = format!("{}, {}", said, phrases.check);
+    }
+    Some(said)
+}
+
+fn describe(m: &Move, phrases: &Phrases) -> String {
+    if let Some(side) = m.castling_side() {
+        return phrases.castles[if side == CastlingSide::KingSide { 0 } else { 1 }].to_string();
+    }
+    let square = |sq: Square| format!("{} {}", phrases.files[usize::from(sq.file())], sq.rank().char());
+    let target = square(m.to());
+    let mut said = match (m.role(), m.is_capture()) {
+        (Role::Pawn, false) => target,
+        (Role::Pawn, true) => {
+            let file = m.from().map(|from| phrases.files[usize::from(from.file())]).unwrap_or_default();
+            format!("{} {} {}", file, phrases.takes, target)
+        }
+        (role, false) => format!("{} {}", piece(role, phrases), target),
+        (role, true) => format!("{} {} {}", piece(role, phrases), phrases.takes, target),
+    };
+    if let Some(role) = m.promotion() {
+        said = format!("{}, {} {}", said, phrases.promotes, piece(role, phrases).to_lowercase());
+    }
+    // Sentence case: pawn moves start with a file letter
+    let mut chars = said.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+fn piece(role: Role, phrases: &Phrases) -> &'static str {
+    let index = match role {
+        Role::King => 0,
+        Role::Queen => 1,
+        Role::Rook => 2,
+        Role::Bishop => 3,
+        Role::Knight => 4,
+        Role::Pawn => 5,
+    };
+    phrases.pieces[index]
+}
+
+/// An engine eval ("+0.35", "#3", "#-2") in words; None for anything else
+fn spoken_eval(eval: &str, phrases: &Phrases) -> Option<String> {
+    if let Some(mate) = eval.strip_prefix('#') {
+        let moves: i32 = mate.parse().ok()?;
+        let phrase = if moves > 0 { phrases.mate_in } else { phrases.mated_in };
+        return Some(capitalized(&phrase.replace("{n}", &moves.abs().to_string())));
+    }
+    let pawns: f64 = eval.parse().ok()?;
+    let sign = if pawns < 0.0 { phrases.minus } else { phrases.plus };
+    let number = format!("{:.2}", pawns.abs()).replace('.', &phrases.decimal.to_string());
+    Some(capitalized(&format!("{} {}", sign, number)))
+}
+
+fn capitalized(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// Headline as it should be spoken: signs and symbols spelled out
+fn speakable(text: &str, phrases: &Phrases) -> String {
+    text.replace(['(', ')', '✓', '⚠'], "")
+        .replace('+', &format!("{} ", phrases.plus))
+        .replace('-', &format!("{} ", phrases.minus))
+        .replace("  ", " ")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine_output(fen: &str, best_move: &str, eval: &str) -> CycleOutput {
+        CycleOutput {
+            value: serde_json::json!({ "mode": "engine", "fen": fen, "best_move": best_move, "evaluation": eval }),
+            lines: Vec::new(),
+            headline: format!("Best: {} ({})", best_move, eval),
+            timings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_speakable() {
+        assert_eq!(speakable("Best: E2 to E4 (+0.35)", &ENGLISH), "Best: E2 to E4 plus 0.35");
+        assert_eq!(speakable("Best: G8 to F6 (-1.20)", &ENGLISH), "Best: G8 to F6 minus 1.20");
+    }
+
+    #[test]
+    fn test_spoken_moves() {
+        // 1. e4 e5 2. Nf3 Nc6 3. Bb5 a6: White to move
+        let fen = "r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4";
+        let said = |mv, eval, language| sentence(&engine_output(fen, mv, eval), language);
+        assert_eq!(said("B5 to C6", "+0.10", Language::English), "Bishop takes see 6. Plus 0.10.");
+        assert_eq!(said("E1 to G1", "+0.35", Language::English), "Castles kingside. Plus 0.35.");
+        assert_eq!(said("D2 to D4", "-0.20", Language::English), "Dee 4. Minus 0.20.");
+        assert_eq!(said("F3 to E5", "-0.50", Language::German), "Springer schlägt e 5. Minus 0,50.");
+        assert_eq!(said("B5 to C6", "#3", Language::French), "Fou prend cé 6. Mat en 3.");
+
+        let fen = "7k/P7/8/8/8/8/8/K7 w - - 0 1";
+        let promotion = sentence(&engine_output(fen, "A7 to A8 (=Q)", "+9.00"), Language::English);
+        assert_eq!(promotion, "Ay 8, promotes to queen, check. Plus 9.00.");
+    }
+
+    #[test]
+    fn test_no_position_falls_back_to_headline() {
+        let output = CycleOutput {
+            value: serde_json::json!({ "mode": "direct" }),
+            lines: Vec::new(),
+            headline: "Move: E2 to E4 (+0.35)".to_string(),
+            timings: Vec::new(),
+        };
+        assert_eq!(sentence(&output, Language::Spanish), "Move: E2 to E4 más 0.35");
+    }
+}
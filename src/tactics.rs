@@ -0,0 +1,175 @@
+//! Tactics extraction from recorded sessions
+//!
+//! `zugzwang-rs tactics` replays the analyzed positions of one or all sessions through the
+//! engine and keeps those where a single forcing move (check, capture, or promotion) was
+//! far better than anything else - the best move beats the second best by at least
+//! `--min-gain` centipawns. Each one becomes a puzzle: the position plus the engine's
+//! solution line, written as a PGN game with a FEN header (one chapter per puzzle when
+//! imported into a Lichess study).
+
+use anyhow::{Context, Result};
+use crate::engine;
+use crate::game::GameTree;
+use crate::pgn::{self, Headers};
+use crate::session;
+use shakmaty::uci::UciMove;
+use shakmaty::{Chess, Move, Position};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Default minimum advantage of the best move over the second best, in centipawns
+pub const DEFAULT_MIN_GAIN: i32 = 200;
+
+/// Plies in a solution line (the tactic, the best defence, the follow-up)
+const SOLUTION_PLIES: usize = 3;
+
+/// A position with one clearly winning forcing move
+#[derive(Debug)]
+pub struct Puzzle {
+    /// Session file the position came from
+    pub session: String,
+    pub fen: String,
+    /// Advantage of the solution's first move over the second-best move, in centipawns
+    pub gain_cp: i32,
+    /// Solution moves, readable ("G5 to F7"), starting with the tactic
+    pub solution: Vec<String>,
+}
+
+impl Puzzle {
+    /// PGN game for the puzzle: FEN header, the solution line, and the gain as a comment
+    pub fn to_pgn(&self, number: usize) -> Result<String> {
+        let mut tree = GameTree::from_fen(&self.fen)?;
+        tree.add_comment(&format!("Find the best move (wins {:.1} pawns over the alternatives)", self.gain_cp as f64 / 100.0));
+        for mv in &self.solution {
+            tree.play_text(mv)?;
+        }
+        let mut headers = Headers::new("Zugzwang puzzles");
+        headers.set("Site", &self.session);
+        headers.set("Round", &number.to_string());
+        Ok(tree.to_pgn(&headers))
+    }
+}
+
+/// Session logs to mine: "all" for every session, otherwise as `session::resolve`
+pub fn sessions(arg: &str) -> Result<Vec<PathBuf>> {
+    if arg == "all" {
+        session::list()
+    } else {
+        Ok(vec![session::resolve(arg)?])
+    }
+}
+
+/// Finds the puzzles in the given sessions; each position is searched once even if it was
+/// analyzed several times
+pub fn extract(paths: &[PathBuf], depth: u16, min_gain: i32) -> Result<Vec<Puzzle>> {
+    let mut seen = HashSet::new();
+    let mut puzzles = Vec::new();
+    for path in paths {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        for entry in session::load(path)? {
+            // Placement and side to move identify the position (counters differ between reads)
+            let key: String = entry.fen.split_whitespace().take(2).collect::<Vec<_>>().join(" ");
+            if !seen.insert(key) {
+                continue;
+            }
+            let Some(position) = pgn::parse_position(&entry.fen) else {
+                continue;
+            };
+            if let Some(puzzle) = find_puzzle(&position, depth, min_gain)? {
+                puzzles.push(Puzzle { session: name.clone(), ..puzzle });
+            }
+        }
+    }
+    Ok(puzzles)
+}
+
+/// The puzzle in this position, if its best move is a forcing move clearly ahead of the rest
+fn find_puzzle(position: &Chess, depth: u16, min_gain: i32) -> Result<Option<Puzzle>> {
+    let fen = shakmaty::fen::Fen::from_position(position, shakmaty::EnPassantMode::Legal).to_string();
+    let top = engine::top_scored_moves(&fen, depth, 2)?;
+    let [(best, best_cp), (_, second_cp)] = top.as_slice() else {
+        return Ok(None); // a single legal move is no puzzle
+    };
+    let gain_cp = best_cp - second_cp;
+    if gain_cp < min_gain || !to_move(position, best).is_some_and(|m| is_forcing(position, m)) {
+        return Ok(None);
+    }
+
+    let mut solution = vec![best.clone()];
+    let mut line_fen = engine::apply_move(&fen, best)?;
+    while solution.len() < SOLUTION_PLIES {
+        let Some((reply, _)) = engine::best_scored_move(&line_fen, depth)? else {
+            break; // mate or stalemate ends the line
+        };
+        line_fen = engine::apply_move(&line_fen, &reply)?;
+        solution.push(reply);
+    }
+    Ok(Some(Puzzle { session: String::new(), fen, gain_cp, solution }))
+}
+
+/// Checks, captures, and promotions
+fn is_forcing(position: &Chess, m: Move) -> bool {
+    if m.is_capture() || m.is_promotion() {
+        return true;
+    }
+    let mut after = position.clone();
+    after.play_unchecked(m);
+    after.is_check()
+}
+
+/// Legal move for a readable engine move ("E7 to E8 (=Q)")
+fn to_move(position: &Chess, readable: &str) -> Option<Move> {
+    let uci: String = readable
+        .to_lowercase()
+        .replace(" to ", "")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+    UciMove::from_ascii(uci.as_bytes()).ok()?.to_move(position).ok()
+}
+
+/// Writes all puzzles as one PGN text (one game per puzzle)
+pub fn to_pgn(puzzles: &[Puzzle]) -> Result<String> {
+    let games = puzzles
+        .iter()
+        .enumerate()
+        .map(|(n, puzzle)| puzzle.to_pgn(n + 1))
+        .collect::<Result<Vec<_>>>()
+        .context("Failed to write puzzle PGN")?;
+    Ok(games.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_free_queen_is_a_puzzle() {
+        // White wins the undefended queen on d5
+        let position = pgn::parse_position("4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let puzzle = find_puzzle(&position, 2, DEFAULT_MIN_GAIN).unwrap().unwrap();
+        assert_eq!(puzzle.solution[0], "D1 to D5");
+        assert!(puzzle.gain_cp >= DEFAULT_MIN_GAIN);
+        assert!(puzzle.solution.len() <= SOLUTION_PLIES);
+
+        let pgn = puzzle.to_pgn(1).unwrap();
+        assert!(pgn.contains("[FEN \"4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1\"]"));
+        assert!(pgn.contains("1. Qxd5"));
+    }
+
+    #[test]
+    fn test_quiet_position_is_not_a_puzzle() {
+        let position = Chess::default();
+        assert!(find_puzzle(&position, 2, DEFAULT_MIN_GAIN).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_forcing_moves() {
+        let position = pgn::parse_position("4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert!(is_forcing(&position, to_move(&position, "D1 to D5").unwrap()));
+        assert!(!is_forcing(&position, to_move(&position, "D1 to C2").unwrap()));
+        // Check without capturing
+        assert!(is_forcing(&position, to_move(&position, "D1 to A4").unwrap()));
+        assert!(to_move(&position, "D1 to D8").is_none());
+    }
+}
@@ -0,0 +1,89 @@
+//! Session token budget for LLM calls (`--token-budget`)
+//!
+//! Every response reports the tokens it used; they are added up for the session. When the
+//! total nears the budget (`DOWNGRADE_AT`), requests switch to the cheap settings: gpt-4o-mini
+//! on OpenAI and low image detail everywhere. Analysis carries on past the budget on those
+//! settings rather than stopping mid-game; the user is warned at each step.
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+/// Share of the budget after which requests are downgraded
+const DOWNGRADE_AT: f64 = 0.8;
+
+/// Budget in tokens from `--token-budget` (unset: unlimited)
+static BUDGET: OnceLock<u64> = OnceLock::new();
+
+/// Tokens used this session
+static USED: AtomicU64 = AtomicU64::new(0);
+
+/// Highest `Pressure` reached (as its discriminant), so each warning is shown once
+static REACHED: AtomicU8 = AtomicU8::new(Pressure::Normal as u8);
+
+/// How close the session is to its budget
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Pressure {
+    Normal = 0,
+    /// Past `DOWNGRADE_AT`: cheap model and low detail
+    Downgraded = 1,
+    /// Budget spent: still on the cheap settings
+    Exceeded = 2,
+}
+
+/// Sets the session budget (first call wins)
+pub fn set_budget(tokens: u64) {
+    let _ = BUDGET.set(tokens);
+}
+
+/// True once requests should use the cheap model and low detail
+pub fn downgraded() -> bool {
+    REACHED.load(Ordering::Relaxed) >= Pressure::Downgraded as u8
+}
+
+/// Adds a response's token usage, warning when the session crosses into a new pressure level
+pub fn record(tokens: u64) {
+    let used = USED.fetch_add(tokens, Ordering::Relaxed) + tokens;
+    let Some(&budget) = BUDGET.get() else {
+        return;
+    };
+    let pressure = pressure(used, budget);
+    let previous = REACHED.fetch_max(pressure as u8, Ordering::Relaxed);
+    if pressure as u8 <= previous {
+        return;
+    }
+    match pressure {
+        Pressure::Downgraded => eprintln!(
+            "⚠ {} of {} LLM tokens used - switching to gpt-4o-mini and low image detail for the rest of the session",
+            used, budget
+        ),
+        Pressure::Exceeded => eprintln!(
+            "⚠ LLM token budget of {} spent ({} used) - continuing on the cheap model; raise --token-budget for more",
+            budget, used
+        ),
+        Pressure::Normal => {}
+    }
+}
+
+fn pressure(used: u64, budget: u64) -> Pressure {
+    if used >= budget {
+        Pressure::Exceeded
+    } else if used as f64 >= budget as f64 * DOWNGRADE_AT {
+        Pressure::Downgraded
+    } else {
+        Pressure::Normal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pressure_levels() {
+        assert_eq!(pressure(0, 100_000), Pressure::Normal);
+        assert_eq!(pressure(79_999, 100_000), Pressure::Normal);
+        assert_eq!(pressure(80_000, 100_000), Pressure::Downgraded);
+        assert_eq!(pressure(100_000, 100_000), Pressure::Exceeded);
+        assert_eq!(pressure(1, 0), Pressure::Exceeded);
+    }
+}
@@ -0,0 +1,547 @@
+//! Terminal dashboard (`--tui`)
+//!
+//! An output sink that redraws one screen per analyzed position instead of scrolling a log:
+//! the board as a Unicode diagram (seen from the side to move, with the best move's squares
+//! highlighted), the best move and candidates, an evaluation bar from White's point of
+//! view (the smoothed eval, see `eval_smoothing`), the moves played so far, and a sparkline of cycle latency with the last cycle's
+//! per-stage times.
+//!
+//! Moves are reconstructed between consecutive positions (`pgn::connect`); a position that
+//! doesn't follow from the previous one starts the list over. The screen is fully redrawn
+//! each cycle, so log lines printed in between are painted over. The terminal is restored
+//! on exit, on Ctrl-C, and on a panic.
+//!
+//! The dashboard reads the keyboard and mouse itself (raw mode) and sends the loop the same
+//! commands as typed lines (`controls`): the single keys (`d`, `m`, `o`, `s`, ...) and Enter
+//! for a capture. A misread square is fixed on the board: the arrow keys or a click select
+//! it, then a piece key (`KQRBNP` / `kqrbnp`) puts that piece there, `.` or Delete clears
+//! it, and Enter cycles through the pieces; Esc drops the selection.
+
+use anyhow::{Context, Result};
+use crate::controls::ControlCommand;
+use crate::eval_units::EvalUnits;
+use crate::ocr::BoxFuture;
+use crate::output::{CycleOutput, OutputSink};
+use crate::notation;
+use crate::pgn;
+use ratatui::Terminal;
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton,
+    MouseEventKind,
+};
+use ratatui::crossterm::{cursor, execute, terminal};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Gauge, Paragraph, Sparkline, Wrap};
+use shakmaty::san::SanPlus;
+use shakmaty::{Chess, Color as Side, File, Position, Rank, Square};
+use std::io::Stdout;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Cycles shown in the latency sparkline
+const LATENCY_HISTORY: usize = 60;
+
+/// Board colors (lichess' brown theme) and the highlight for the best move
+const LIGHT_SQUARE: Color = Color::Rgb(240, 217, 181);
+const DARK_SQUARE: Color = Color::Rgb(181, 136, 99);
+const MOVE_SQUARE: Color = Color::Rgb(205, 210, 106);
+/// The square selected for a correction
+const SELECTED_SQUARE: Color = Color::Rgb(106, 155, 210);
+
+/// How often the key thread checks that the dashboard is still open
+const KEY_POLL: Duration = Duration::from_millis(200);
+
+/// The loop's command channel, for the keys (first call wins)
+static COMMANDS: OnceLock<UnboundedSender<ControlCommand>> = OnceLock::new();
+
+/// Lets the dashboard's keys send commands to the loop (first call wins)
+pub fn set_commands(commands: UnboundedSender<ControlCommand>) {
+    let _ = COMMANDS.set(commands);
+}
+
+/// The dashboard, shared with the thread reading keys
+pub struct TuiSink {
+    dashboard: Arc<Mutex<Dashboard>>,
+}
+
+/// Dashboard state and the terminal it draws on
+struct Dashboard {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    /// Last position, for reconstructing the moves to the next one
+    position: Option<Chess>,
+    /// Move numbers and SAN since the game line started, e.g. "1. e4", "e5"
+    moves: Vec<String>,
+    /// Total cycle time in milliseconds, oldest first
+    latencies: Vec<u64>,
+    last: Option<CycleOutput>,
+    /// Units for the eval label and the result lines
+    units: EvalUnits,
+    /// Square selected for a correction
+    selected: Option<Square>,
+    /// Where the board was last drawn, for clicks
+    board_area: Rect,
+}
+
+/// What a key or click does
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Action {
+    /// Select a square (None: drop the selection)
+    Select(Option<Square>),
+    Command(ControlCommand),
+    Interrupt,
+    Redraw,
+    Nothing,
+}
+
+impl TuiSink {
+    /// Switches to the alternate screen; the banner stays visible until the first result
+    pub fn start(units: EvalUnits) -> Result<Self> {
+        anyhow::ensure!(terminal::size().is_ok(), "--tui needs an interactive terminal");
+        execute!(std::io::stdout(), terminal::EnterAlternateScreen, EnableMouseCapture, cursor::Hide)
+            .context("Failed to switch to the alternate screen")?;
+        terminal::enable_raw_mode().context("Failed to switch the terminal to raw mode")?;
+
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore();
+            default_hook(info);
+        }));
+        // A first Ctrl+C stops the loop, dropping the sink; a second one quits at once
+        crate::shutdown::on_forced_exit(restore);
+
+        let terminal = Terminal::new(CrosstermBackend::new(std::io::stdout())).context("Failed to open the terminal")?;
+        let dashboard = Arc::new(Mutex::new(Dashboard {
+            terminal,
+            position: None,
+            moves: Vec::new(),
+            latencies: Vec::new(),
+            last: None,
+            units,
+            selected: None,
+            board_area: Rect::default(),
+        }));
+        let keys = Arc::downgrade(&dashboard);
+        std::thread::spawn(move || read_keys(keys));
+        Ok(TuiSink { dashboard })
+    }
+}
+
+/// Reads keys and clicks until the dashboard closes, acting on them
+fn read_keys(dashboard: Weak<Mutex<Dashboard>>) {
+    loop {
+        match event::poll(KEY_POLL) {
+            Ok(true) => {}
+            Ok(false) if dashboard.strong_count() > 0 => continue,
+            _ => return,
+        }
+        let Ok(event) = event::read() else { return };
+        let Some(dashboard) = dashboard.upgrade() else { return };
+        let Ok(mut dashboard) = dashboard.lock() else { return };
+        let flipped = dashboard.flipped();
+        let action = match event {
+            Event::Key(key) if key.kind != KeyEventKind::Release => key_action(dashboard.selected, flipped, key),
+            Event::Mouse(mouse) if mouse.kind == MouseEventKind::Down(MouseButton::Left) => {
+                Action::Select(square_at(dashboard.board_area, flipped, mouse.column, mouse.row))
+            }
+            Event::Resize(..) => Action::Redraw,
+            _ => Action::Nothing,
+        };
+        match action {
+            Action::Select(square) => {
+                dashboard.selected = square;
+                let _ = dashboard.draw();
+            }
+            Action::Command(command) => {
+                if let Some(commands) = COMMANDS.get() {
+                    let _ = commands.send(command);
+                }
+            }
+            Action::Interrupt => {
+                drop(dashboard);
+                crate::shutdown::interrupt();
+            }
+            Action::Redraw => {
+                let _ = dashboard.draw();
+            }
+            Action::Nothing => {}
+        }
+    }
+}
+
+/// Maps a key to its action, given the selected square and the board's orientation
+fn key_action(selected: Option<Square>, flipped: bool, key: KeyEvent) -> Action {
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        return if key.code == KeyCode::Char('c') { Action::Interrupt } else { Action::Nothing };
+    }
+    let correct = |square: Square, piece: Option<char>| {
+        Action::Command(ControlCommand::Correct { file: square.file().to_u32() as u8, rank: square.rank().to_u32() as u8, piece })
+    };
+    // Arrows move on the board as drawn (from Black's side when flipped)
+    let arrow = match key.code {
+        KeyCode::Left => Some((-1, 0)),
+        KeyCode::Right => Some((1, 0)),
+        KeyCode::Up => Some((0, 1)),
+        KeyCode::Down => Some((0, -1)),
+        _ => None,
+    };
+    if let Some((right, up)) = arrow {
+        let (right, up) = if flipped { (-right, -up) } else { (right, up) };
+        let square = match selected {
+            Some(square) => {
+                let file = (square.file().to_u32() as i32 + right).clamp(0, 7) as u32;
+                let rank = (square.rank().to_u32() as i32 + up).clamp(0, 7) as u32;
+                Square::from_coords(File::new(file), Rank::new(rank))
+            }
+            // Start in the bottom-left corner
+            None if flipped => Square::H8,
+            None => Square::A1,
+        };
+        return Action::Select(Some(square));
+    }
+    match (selected, key.code) {
+        (Some(_), KeyCode::Esc) => Action::Select(None),
+        (Some(square), KeyCode::Char(piece)) if "KQRBNPkqrbnp".contains(piece) => correct(square, Some(piece)),
+        (Some(square), KeyCode::Char('.') | KeyCode::Delete | KeyCode::Backspace) => correct(square, Some('1')),
+        (Some(square), KeyCode::Enter) => correct(square, None),
+        (None, KeyCode::Enter) => Action::Command(ControlCommand::Capture),
+        (None, KeyCode::Char(key)) => ControlCommand::parse(&key.to_string()).map_or(Action::Nothing, Action::Command),
+        _ => Action::Nothing,
+    }
+}
+
+/// The square drawn at a terminal cell, given where the board block is
+fn square_at(area: Rect, flipped: bool, column: u16, row: u16) -> Option<Square> {
+    // Inside the border: a rank label and a space, then three cells per square
+    let x = column.checked_sub(area.x + 1 + 2)?;
+    let y = row.checked_sub(area.y + 1)?;
+    let (col, line) = (u32::from(x / 3), u32::from(y));
+    if col >= 8 || line >= 8 {
+        return None;
+    }
+    let (file, rank) = if flipped { (7 - col, line) } else { (col, 7 - line) };
+    Some(Square::from_coords(File::new(file), Rank::new(rank)))
+}
+
+impl Dashboard {
+    /// Whether the board is drawn from Black's side (Black to move)
+    fn flipped(&self) -> bool {
+        self.last
+            .as_ref()
+            .and_then(|output| output.value["fen"].as_str())
+            .is_some_and(|fen| fen.split_whitespace().nth(1) == Some("b"))
+    }
+
+    /// Extends the move list with the moves that lead to `fen`
+    fn follow(&mut self, fen: &str) {
+        let Some(next) = pgn::parse_position(fen) else {
+            return;
+        };
+        let played = self.position.as_ref().and_then(|last| Some((last.clone(), pgn::connect(last, next.board())?)));
+        match played {
+            Some((mut position, moves)) => {
+                for m in moves {
+                    let san = SanPlus::from_move(position.clone(), m).to_string();
+                    self.moves.push(match position.turn() {
+                        Side::White => format!("{}. {}", position.fullmoves(), san),
+                        Side::Black if self.moves.is_empty() => format!("{}... {}", position.fullmoves(), san),
+                        Side::Black => san,
+                    });
+                    position.play_unchecked(m);
+                }
+                self.position = Some(position);
+            }
+            None => {
+                self.moves.clear();
+                self.position = Some(next);
+            }
+        }
+    }
+
+    fn draw(&mut self) -> Result<()> {
+        let Some(output) = &self.last else {
+            return Ok(());
+        };
+        let fen = output.value["fen"].as_str();
+        let best_move = output.value["best_move"]
+            .as_str()
+            .or(output.value["engine"]["move"].as_str())
+            .or(output.value["recommendation"]["best_move"].as_str());
+        let eval = output.value["smoothed_evaluation"]
+            .as_str()
+            .or(output.value["evaluation"].as_str())
+            .or(output.value["engine"]["evaluation"].as_str())
+            .or(output.value["recommendation"]["evaluation"].as_str());
+        let moves = self.moves.join(" ");
+        let latencies = &self.latencies;
+        let units = self.units;
+        let selected = self.selected;
+        let shown_board_area = &mut self.board_area;
+
+        self.terminal.clear()?;
+        self.terminal.draw(|frame| {
+            let [board_area, side_area] =
+                Layout::horizontal([Constraint::Length(30), Constraint::Min(30)]).areas(frame.area());
+            let [best_area, eval_area, moves_area, latency_area] = Layout::vertical([
+                Constraint::Min(6),
+                Constraint::Length(3),
+                Constraint::Length(6),
+                Constraint::Length(5),
+            ])
+            .areas(side_area);
+
+            let board = match fen {
+                Some(fen) => board_lines(fen, best_move.and_then(move_squares), selected),
+                None => vec![Line::from("No board (direct mode)")],
+            };
+            let board_title = match selected {
+                Some(square) => format!(" Fix {}: piece key, . clear, Enter cycle, Esc ", square),
+                None => " Board ".to_string(),
+            };
+            frame.render_widget(
+                Paragraph::new(board).block(Block::default().borders(Borders::ALL).title(board_title)),
+                board_area,
+            );
+            *shown_board_area = board_area;
+
+            let best: Vec<Line> = output.with_units(units).lines.into_iter().map(Line::from).collect();
+            let best_title = match best_move {
+                Some(mv) => format!(" Best: {} ", fen.map(|fen| notation::display(fen, mv)).unwrap_or_else(|| mv.to_string()).replacen(" to ", " → ", 1)),
+                None => " Best move ".to_string(),
+            };
+            frame.render_widget(
+                Paragraph::new(best).wrap(Wrap { trim: false }).block(Block::default().borders(Borders::ALL).title(best_title)),
+                best_area,
+            );
+
+            let white = fen.zip(eval).and_then(|(fen, eval)| white_eval(fen, eval));
+            let label = match white {
+                Some(pawns) if pawns.is_infinite() => format!("White {}", if pawns > 0.0 { "mates" } else { "is mated" }),
+                Some(pawns) => {
+                    // Accuracy needs a move to compare; the bar shows winning chances instead
+                    let units = if units == EvalUnits::Accuracy { EvalUnits::WinPercent } else { units };
+                    let eval = format!("{:+.2}", pawns);
+                    format!("White {}", units.format(&eval, None).unwrap_or(eval))
+                }
+                None => "-".to_string(),
+            };
+            frame.render_widget(
+                Gauge::default()
+                    .block(Block::default().borders(Borders::ALL).title(" Eval "))
+                    .gauge_style(Style::default().fg(Color::White).bg(Color::DarkGray))
+                    .ratio(white.map(eval_ratio).unwrap_or(0.5))
+                    .label(label),
+                eval_area,
+            );
+
+            frame.render_widget(
+                Paragraph::new(moves).wrap(Wrap { trim: true }).block(Block::default().borders(Borders::ALL).title(" Moves ")),
+                moves_area,
+            );
+
+            let stages: Vec<String> = output
+                .timings
+                .iter()
+                .map(|(stage, time)| format!("{} {:.0}ms", stage, time.as_secs_f64() * 1000.0))
+                .collect();
+            frame.render_widget(
+                Sparkline::default()
+                    .block(Block::default().borders(Borders::ALL).title(format!(" Latency: {} ", stages.join(" · "))))
+                    .data(latencies)
+                    .style(Style::default().fg(Color::Cyan)),
+                latency_area,
+            );
+        })?;
+        Ok(())
+    }
+}
+
+impl OutputSink for TuiSink {
+    fn name(&self) -> String {
+        "tui".to_string()
+    }
+
+    fn emit<'a>(&'a mut self, output: &'a CycleOutput) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut dashboard = self.dashboard.lock().map_err(|_| anyhow::anyhow!("TUI state poisoned"))?;
+            if let Some(fen) = output.value["fen"].as_str() {
+                dashboard.follow(fen);
+            }
+            let total: std::time::Duration = output.timings.iter().map(|(_, time)| *time).sum();
+            dashboard.latencies.push(total.as_millis() as u64);
+            if dashboard.latencies.len() > LATENCY_HISTORY {
+                dashboard.latencies.remove(0);
+            }
+            dashboard.last = Some(output.clone());
+            dashboard.draw()
+        })
+    }
+}
+
+impl Drop for TuiSink {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Leaves raw mode and the alternate screen and shows the cursor again
+fn restore() {
+    let _ = terminal::disable_raw_mode();
+    let _ = execute!(std::io::stdout(), DisableMouseCapture, terminal::LeaveAlternateScreen, cursor::Show);
+}
+
+/// Board diagram from the side to move's point of view, with rank and file labels; the
+/// best move's squares and the square selected for a correction are highlighted
+fn board_lines(fen: &str, highlight: Option<(Square, Square)>, selected: Option<Square>) -> Vec<Line<'static>> {
+    let Some(position) = pgn::parse_position(fen) else {
+        return vec![Line::from("Unreadable position")];
+    };
+    let flipped = position.turn() == Side::Black;
+    let ranks: Vec<Rank> = if flipped { Rank::ALL.to_vec() } else { Rank::ALL.into_iter().rev().collect() };
+    let files: Vec<File> = if flipped { File::ALL.into_iter().rev().collect() } else { File::ALL.to_vec() };
+
+    let mut lines: Vec<Line> = ranks
+        .iter()
+        .map(|&rank| {
+            let mut spans = vec![Span::raw(format!("{} ", rank.char()))];
+            for &file in &files {
+                let square = Square::from_coords(file, rank);
+                let background = if selected == Some(square) {
+                    SELECTED_SQUARE
+                } else if highlight.is_some_and(|(from, to)| square == from || square == to) {
+                    MOVE_SQUARE
+                } else if square.is_light() {
+                    LIGHT_SQUARE
+                } else {
+                    DARK_SQUARE
+                };
+                let (glyph, color) = match position.board().piece_at(square) {
+                    Some(piece) => {
+                        let color = if piece.color == Side::White { Color::White } else { Color::Black };
+                        (piece_glyph(piece.role), color)
+                    }
+                    None => (' ', Color::Reset),
+                };
+                let style = Style::default().bg(background).fg(color).add_modifier(Modifier::BOLD);
+                spans.push(Span::styled(format!(" {} ", glyph), style));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    let labels: String = files.iter().map(|file| format!(" {} ", file.char())).collect();
+    lines.push(Line::from(format!("  {}", labels)));
+    lines
+}
+
+/// Solid chess glyph for a piece type (colored by the caller)
+fn piece_glyph(role: shakmaty::Role) -> char {
+    match role {
+        shakmaty::Role::King => '♚',
+        shakmaty::Role::Queen => '♛',
+        shakmaty::Role::Rook => '♜',
+        shakmaty::Role::Bishop => '♝',
+        shakmaty::Role::Knight => '♞',
+        shakmaty::Role::Pawn => '♟',
+    }
+}
+
+/// From and to squares of a readable move ("E2 to E4", "E7 to E8 (=Q)")
+pub fn move_squares(readable: &str) -> Option<(Square, Square)> {
+    let mut words = readable.split_whitespace();
+    let from = words.next()?.to_lowercase().parse().ok()?;
+    let to = words.nth(1)?.to_lowercase().parse().ok()?;
+    Some((from, to))
+}
+
+/// Eval in pawns from White's point of view (infinite for a forced mate). Evals are given
+/// from the side to move's point of view.
+pub fn white_eval(fen: &str, eval: &str) -> Option<f64> {
+    let pawns = match eval.strip_prefix('#') {
+        Some(mate) => mate.parse::<i32>().ok().map(|n| if n > 0 { f64::INFINITY } else { f64::NEG_INFINITY })?,
+        None => eval.parse::<f64>().ok()?,
+    };
+    let black_to_move = fen.split_whitespace().nth(1) == Some("b");
+    Some(if black_to_move { -pawns } else { pawns })
+}
+
+/// Share of the eval bar that is White's: half at equality, nearly full at +5
+fn eval_ratio(white_pawns: f64) -> f64 {
+    1.0 / (1.0 + (-0.8 * white_pawns).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_white_eval_follows_side_to_move() {
+        assert_eq!(white_eval("8/8/8/8/8/8/8/K6k w - - 0 1", "+0.50"), Some(0.5));
+        assert_eq!(white_eval("8/8/8/8/8/8/8/K6k b - - 0 1", "+0.50"), Some(-0.5));
+        assert_eq!(white_eval("8/8/8/8/8/8/8/K6k b - - 0 1", "#2"), Some(f64::NEG_INFINITY));
+        assert_eq!(white_eval("8/8/8/8/8/8/8/K6k w - - 0 1", "--"), None);
+        assert_eq!(eval_ratio(0.0), 0.5);
+        assert!(eval_ratio(5.0) > 0.95 && eval_ratio(f64::NEG_INFINITY) == 0.0);
+    }
+
+    #[test]
+    fn test_move_squares() {
+        assert_eq!(move_squares("E2 to E4"), Some((Square::E2, Square::E4)));
+        assert_eq!(move_squares("E7 to E8 (=Q)"), Some((Square::E7, Square::E8)));
+        assert_eq!(move_squares("castle"), None);
+    }
+
+    #[test]
+    fn test_keys_select_and_correct_squares() {
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        // Without a selection keys are the usual commands
+        assert_eq!(key_action(None, false, key(KeyCode::Char('d'))), Action::Command(ControlCommand::DepthDown));
+        assert_eq!(key_action(None, false, key(KeyCode::Enter)), Action::Command(ControlCommand::Capture));
+        assert_eq!(key_action(None, false, key(KeyCode::Up)), Action::Select(Some(Square::A1)));
+        assert_eq!(key_action(Some(Square::E4), false, key(KeyCode::Up)), Action::Select(Some(Square::E5)));
+        // From Black's side up is down the ranks
+        assert_eq!(key_action(Some(Square::E4), true, key(KeyCode::Right)), Action::Select(Some(Square::D4)));
+        assert_eq!(key_action(Some(Square::H4), false, key(KeyCode::Right)), Action::Select(Some(Square::H4)));
+        // With a selection piece keys fix the square
+        assert_eq!(
+            key_action(Some(Square::E4), false, key(KeyCode::Char('n'))),
+            Action::Command(ControlCommand::Correct { file: 4, rank: 3, piece: Some('n') })
+        );
+        assert_eq!(
+            key_action(Some(Square::A8), false, key(KeyCode::Char('.'))),
+            Action::Command(ControlCommand::Correct { file: 0, rank: 7, piece: Some('1') })
+        );
+        assert_eq!(
+            key_action(Some(Square::A8), false, key(KeyCode::Enter)),
+            Action::Command(ControlCommand::Correct { file: 0, rank: 7, piece: None })
+        );
+        assert_eq!(key_action(Some(Square::A8), false, key(KeyCode::Esc)), Action::Select(None));
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(key_action(Some(Square::A8), false, ctrl_c), Action::Interrupt);
+    }
+
+    #[test]
+    fn test_clicks_find_squares() {
+        let area = Rect::new(0, 0, 30, 11);
+        // Top-left square after the border and the rank label
+        assert_eq!(square_at(area, false, 3, 1), Some(Square::A8));
+        assert_eq!(square_at(area, false, 25, 8), Some(Square::H1));
+        assert_eq!(square_at(area, true, 3, 1), Some(Square::H1));
+        assert_eq!(square_at(area, false, 1, 1), None);
+        assert_eq!(square_at(area, false, 3, 9), None);
+    }
+
+    #[test]
+    fn test_board_lines_orientation() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
+        let text = |lines: Vec<Line>| lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        let white = text(board_lines(&format!("{} w KQkq - 0 1", start), None, None));
+        assert!(white[0].starts_with("8  ♜"));
+        assert_eq!(white[8].trim(), "a  b  c  d  e  f  g  h");
+        let black = text(board_lines(&format!("{} b KQkq - 0 1", start), None, Some(Square::E4)));
+        assert!(black[0].starts_with("1  ♜"));
+        assert_eq!(black[8].trim(), "h  g  f  e  d  c  b  a");
+    }
+}
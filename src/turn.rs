@@ -0,0 +1,233 @@
+//! Side to move from the screen
+//!
+//! OCR used to assume it is always the player's turn, so a board captured while the opponent
+//! was thinking got the player's best move for a position that can't arise. Native OCR now
+//! reads two cues from the screen:
+//!
+//! 1. The last-move highlight: the two tinted squares of the last move. The one that holds a
+//!    piece shows who just moved, so the other side is to move.
+//! 2. The running clock: sites draw the active clock brighter than the idle one, above or
+//!    below the board on chess.com and to its right on lichess.
+//!
+//! The LLM reads the same cues from its prompt. Without a clear cue the player's side is
+//! kept. `--assume-turn` turns detection off.
+
+use crate::PlayerSide;
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use shakmaty::{Board, Color, File, Rank, Square};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Detection is on unless `--assume-turn` was given
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Channel difference (summed over RGB) from a square's usual color that counts as a tint
+const TINT_DISTANCE: u32 = 45;
+
+/// Corner patch size sampled per square, in pixels of the 512×512 board
+const PATCH: u32 = 6;
+
+/// Relative brightness difference between the clocks that counts as one running
+const CLOCK_CONTRAST: f32 = 0.25;
+
+/// Turns detection on or off (`--assume-turn`)
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// True unless `--assume-turn` was given
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Side to move for a recognized board: `board` is the 512×512 crop as displayed, `screen`
+/// and `bounds` locate it on screen for the clocks. None when neither cue is clear.
+pub fn detect(
+    board: &DynamicImage,
+    placement: &Board,
+    screen: &DynamicImage,
+    bounds: (u32, u32, u32, u32),
+    site: &str,
+    player_side: PlayerSide,
+) -> Option<Color> {
+    if !enabled() {
+        return None;
+    }
+    from_highlight(&board.to_rgba8(), placement, player_side).or_else(|| from_clocks(screen, bounds, site, player_side))
+}
+
+/// The FEN with its side-to-move field set to `turn`
+pub fn with_turn(fen: &str, turn: Color) -> String {
+    let mut fields: Vec<&str> = fen.split_whitespace().collect();
+    let turn = if turn == Color::White { "w" } else { "b" };
+    match fields.get_mut(1) {
+        Some(field) => *field = turn,
+        None => fields.push(turn),
+    }
+    fields.join(" ")
+}
+
+/// Side to move from the last-move highlight
+fn from_highlight(board: &RgbaImage, placement: &Board, player_side: PlayerSide) -> Option<Color> {
+    let square_size = board.width() / 8;
+    // Usual color of light and dark squares: the median corner color of each
+    let mut usual: [Vec<Rgba<u8>>; 2] = [Vec::new(), Vec::new()];
+    let corners: Vec<Vec<Rgba<u8>>> = (0..64)
+        .map(|i| {
+            let (col, row) = (i % 8, i / 8);
+            let corners = corner_colors(board, col * square_size, row * square_size, square_size);
+            usual[((col + row) % 2) as usize].extend(corners.iter().copied());
+            corners
+        })
+        .collect();
+    let usual = usual.map(|colors| median_color(&colors));
+
+    // A square is tinted when none of its corners shows the usual color (a corner can hold
+    // a coordinate label)
+    let tinted: Vec<Square> = (0..64u32)
+        .filter(|&i| {
+            let reference = usual[((i % 8 + i / 8) % 2) as usize];
+            corners[i as usize].iter().all(|&color| distance(color, reference) > TINT_DISTANCE)
+        })
+        .map(|i| display_square(i % 8, i / 8, player_side))
+        .collect();
+    if tinted.len() != 2 {
+        return None;
+    }
+    let moved: Vec<Color> = tinted.iter().filter_map(|&square| placement.color_at(square)).collect();
+    match moved.as_slice() {
+        [mover] => Some(!*mover),
+        _ => None,
+    }
+}
+
+/// Side to move from which clock is lit: the bottom clock is the player's
+fn from_clocks(screen: &DynamicImage, bounds: (u32, u32, u32, u32), site: &str, player_side: PlayerSide) -> Option<Color> {
+    let (x, y, width, height) = bounds;
+    let (screen_w, screen_h) = screen.dimensions();
+    let band = height / 10;
+    let (top, bottom) = if site == "lichess" {
+        // Clocks sit right of the board, above and below its middle
+        let left = x + width + width / 50;
+        let clock_w = width / 4;
+        ((left, y + height / 2 - 2 * band, clock_w, band), (left, y + height / 2 + band, clock_w, band))
+    } else {
+        // Clocks sit at the right end of the name bars above and below the board
+        let left = x + width * 3 / 4;
+        ((left, y.checked_sub(band)?, width / 4, band), (left, y + height, width / 4, band))
+    };
+    let fits = |(rx, ry, rw, rh): (u32, u32, u32, u32)| rw > 0 && rh > 0 && rx + rw <= screen_w && ry + rh <= screen_h;
+    if !fits(top) || !fits(bottom) {
+        return None;
+    }
+    let (top, bottom) = (brightness(screen, top), brightness(screen, bottom));
+    let player = if player_side == PlayerSide::White { Color::White } else { Color::Black };
+    if bottom > top * (1.0 + CLOCK_CONTRAST) {
+        Some(player)
+    } else if top > bottom * (1.0 + CLOCK_CONTRAST) {
+        Some(!player)
+    } else {
+        None
+    }
+}
+
+/// Mean luma of a screen region, 0-255
+fn brightness(screen: &DynamicImage, (x, y, width, height): (u32, u32, u32, u32)) -> f32 {
+    let region = screen.crop_imm(x, y, width, height).to_luma8();
+    region.pixels().map(|p| f32::from(p[0])).sum::<f32>() / (width * height) as f32
+}
+
+/// Mean color of a small patch inside each corner of a square
+fn corner_colors(board: &RgbaImage, x: u32, y: u32, size: u32) -> Vec<Rgba<u8>> {
+    let inset = size / 16;
+    let far = size - inset - PATCH;
+    [(inset, inset), (far, inset), (inset, far), (far, far)]
+        .iter()
+        .map(|&(dx, dy)| {
+            let mut sum = [0u32; 3];
+            for py in 0..PATCH {
+                for px in 0..PATCH {
+                    let pixel = board.get_pixel(x + dx + px, y + dy + py);
+                    (0..3).for_each(|c| sum[c] += u32::from(pixel[c]));
+                }
+            }
+            let n = PATCH * PATCH;
+            Rgba([(sum[0] / n) as u8, (sum[1] / n) as u8, (sum[2] / n) as u8, 255])
+        })
+        .collect()
+}
+
+/// Per-channel median
+fn median_color(colors: &[Rgba<u8>]) -> Rgba<u8> {
+    let mut median = [0u8; 3];
+    for (c, value) in median.iter_mut().enumerate() {
+        let mut channel: Vec<u8> = colors.iter().map(|color| color[c]).collect();
+        channel.sort_unstable();
+        *value = channel.get(channel.len() / 2).copied().unwrap_or_default();
+    }
+    Rgba([median[0], median[1], median[2], 255])
+}
+
+fn distance(a: Rgba<u8>, b: Rgba<u8>) -> u32 {
+    (0..3).map(|c| u32::from(a[c].abs_diff(b[c]))).sum()
+}
+
+/// Board square shown at a display column/row (row 0 at the top)
+fn display_square(col: u32, row: u32, player_side: PlayerSide) -> Square {
+    let (file, rank) = if player_side.needs_board_flip() { (7 - col, row) } else { (col, 7 - row) };
+    Square::from_coords(File::new(file), Rank::new(rank))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shakmaty::fen::Fen;
+
+    const LIGHT: Rgba<u8> = Rgba([240, 217, 181, 255]);
+    const DARK: Rgba<u8> = Rgba([181, 136, 99, 255]);
+    const TINT: Rgba<u8> = Rgba([205, 210, 106, 255]);
+
+    /// A plain 512×512 board with `tinted` squares highlighted (White at the bottom)
+    fn board_image(tinted: &[Square]) -> RgbaImage {
+        RgbaImage::from_fn(512, 512, |x, y| {
+            let (col, row) = (x / 64, y / 64);
+            if tinted.contains(&display_square(col, row, PlayerSide::White)) {
+                TINT
+            } else if (col + row) % 2 == 0 {
+                LIGHT
+            } else {
+                DARK
+            }
+        })
+    }
+
+    fn placement(fen: &str) -> Board {
+        Fen::from_ascii(fen.as_bytes()).unwrap().into_setup().board
+    }
+
+    #[test]
+    fn test_highlight_shows_who_moved() {
+        // 1. e4: the pawn on e4 is White's, so Black is to move
+        let after_e4 = placement("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+        let board = board_image(&[Square::E2, Square::E4]);
+        assert_eq!(from_highlight(&board, &after_e4, PlayerSide::White), Some(Color::Black));
+        // No highlight (game start): no cue
+        assert_eq!(from_highlight(&board_image(&[]), &after_e4, PlayerSide::White), None);
+    }
+
+    #[test]
+    fn test_clocks() {
+        // Board at (100, 100) 400×400; the top clock is lit
+        let screen = DynamicImage::ImageRgba8(RgbaImage::from_fn(800, 700, |x, y| {
+            if (400..500).contains(&x) && (60..100).contains(&y) { Rgba([250, 250, 250, 255]) } else { Rgba([40, 40, 40, 255]) }
+        }));
+        let bounds = (100, 100, 400, 400);
+        assert_eq!(from_clocks(&screen, bounds, "chesscom", PlayerSide::White), Some(Color::Black));
+        assert_eq!(from_clocks(&screen, bounds, "chesscom", PlayerSide::Black), Some(Color::White));
+    }
+
+    #[test]
+    fn test_with_turn() {
+        assert_eq!(with_turn("8/8/8/8/8/8/8/K6k w KQkq - 0 1", Color::Black), "8/8/8/8/8/8/8/K6k b KQkq - 0 1");
+        assert_eq!(with_turn("8/8/8/8/8/8/8/K6k", Color::White), "8/8/8/8/8/8/8/K6k w");
+    }
+}
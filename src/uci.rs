@@ -0,0 +1,217 @@
+//! Minimal UCI client for external engines (e.g. Stockfish)
+//!
+//! Spawns the engine binary and speaks the UCI text protocol over its stdin/stdout.
+//! Only what the analysis workflows need is implemented: options, positions by FEN,
+//! `go`/`stop`, and parsing of `info` lines (depth, MultiPV index, score, principal
+//! variation).
+//!
+//! Starting engine processes needs the desktop feature; the `info` line parsing doesn't.
+
+#[cfg(feature = "desktop")]
+use anyhow::{Context, Result};
+#[cfg(feature = "desktop")]
+use std::process::Stdio;
+#[cfg(feature = "desktop")]
+use std::time::Duration;
+#[cfg(feature = "desktop")]
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+#[cfg(feature = "desktop")]
+use tokio::process::{Child, ChildStdin, ChildStdout};
+
+/// How long the engine may take to answer the `uci` / `isready` handshakes
+#[cfg(feature = "desktop")]
+const HANDSHAKE_TIMEOUT_SECS: u64 = 10;
+
+/// Engine score for a line, from the side to move's perspective
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Score {
+    /// Centipawns
+    Cp(i32),
+    /// Mate in N moves (negative: getting mated)
+    Mate(i32),
+}
+
+impl std::fmt::Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Score::Cp(cp) => write!(f, "{:+.2}", *cp as f64 / 100.0),
+            Score::Mate(n) => write!(f, "#{}", n),
+        }
+    }
+}
+
+/// One `info` line that carries a scored principal variation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InfoLine {
+    pub depth: u32,
+    /// 1-based MultiPV index (1 when MultiPV is off)
+    pub multipv: usize,
+    pub score: Score,
+    /// Principal variation in UCI notation
+    pub pv: Vec<String>,
+}
+
+/// Parses an `info` line; returns None for info without a score and PV (currmove, strings, ...)
+pub fn parse_info(line: &str) -> Option<InfoLine> {
+    let mut tokens = line.split_whitespace();
+    if tokens.next()? != "info" {
+        return None;
+    }
+
+    let mut depth = None;
+    let mut multipv = 1;
+    let mut score = None;
+    let mut pv = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            "depth" => depth = tokens.next()?.parse().ok(),
+            "multipv" => multipv = tokens.next()?.parse().ok()?,
+            "score" => {
+                score = match (tokens.next()?, tokens.next()?.parse().ok()?) {
+                    ("cp", v) => Some(Score::Cp(v)),
+                    ("mate", v) => Some(Score::Mate(v)),
+                    _ => None,
+                }
+            }
+            // The PV runs to the end of the line
+            "pv" => pv = tokens.by_ref().map(String::from).collect(),
+            _ => {}
+        }
+    }
+
+    if pv.is_empty() {
+        return None;
+    }
+    Some(InfoLine { depth: depth?, multipv, score: score?, pv })
+}
+
+/// A running UCI engine process
+#[cfg(feature = "desktop")]
+pub struct UciEngine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Lines<BufReader<ChildStdout>>,
+    /// Engine name reported by `id name`
+    pub name: String,
+}
+
+#[cfg(feature = "desktop")]
+impl UciEngine {
+    /// Starts the engine and completes the `uci` handshake
+    pub async fn spawn(path: &str) -> Result<Self> {
+        let mut child = tokio::process::Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start engine: {}", path))?;
+
+        let stdin = child.stdin.take().context("Engine stdin unavailable")?;
+        let stdout = child.stdout.take().context("Engine stdout unavailable")?;
+        let mut engine = UciEngine {
+            child,
+            stdin,
+            lines: BufReader::new(stdout).lines(),
+            name: path.to_string(),
+        };
+
+        engine.send("uci").await?;
+        let handshake = async {
+            loop {
+                let line = engine.read_line().await?.context("Engine exited during handshake")?;
+                if let Some(name) = line.strip_prefix("id name ") {
+                    engine.name = name.trim().to_string();
+                }
+                if line.trim() == "uciok" {
+                    return Ok::<_, anyhow::Error>(());
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(HANDSHAKE_TIMEOUT_SECS), handshake)
+            .await
+            .context("Engine did not answer 'uci' (is this a UCI engine?)")??;
+
+        Ok(engine)
+    }
+
+    /// Sends one command line
+    pub async fn send(&mut self, command: &str) -> Result<()> {
+        self.stdin.write_all(command.as_bytes()).await?;
+        self.stdin.write_all(b"\n").await?;
+        self.stdin.flush().await.context("Failed to write to engine")
+    }
+
+    /// Reads the next output line (None when the engine exited)
+    pub async fn read_line(&mut self) -> Result<Option<String>> {
+        self.lines.next_line().await.context("Failed to read from engine")
+    }
+
+    /// Sets a UCI option (e.g. "MultiPV", "Threads", "Hash")
+    pub async fn set_option(&mut self, name: &str, value: &str) -> Result<()> {
+        self.send(&format!("setoption name {} value {}", name, value)).await
+    }
+
+    /// Waits until the engine has processed all previous commands
+    pub async fn ready(&mut self) -> Result<()> {
+        self.send("isready").await?;
+        let wait = async {
+            while let Some(line) = self.read_line().await? {
+                if line.trim() == "readyok" {
+                    return Ok(());
+                }
+            }
+            anyhow::bail!("Engine exited before readyok")
+        };
+        tokio::time::timeout(Duration::from_secs(HANDSHAKE_TIMEOUT_SECS), wait)
+            .await
+            .context("Engine did not answer 'isready'")?
+    }
+
+    /// Sets up a position from FEN
+    pub async fn position(&mut self, fen: &str) -> Result<()> {
+        self.send(&format!("position fen {}", fen)).await
+    }
+
+    /// Asks the engine to quit and waits briefly for it to exit
+    pub async fn quit(mut self) {
+        let _ = self.send("quit").await;
+        let _ = tokio::time::timeout(Duration::from_secs(2), self.child.wait()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_info_with_multipv() {
+        let line = "info depth 24 seldepth 30 multipv 2 score cp -35 nodes 123 nps 1000 pv e7e5 g1f3 b8c6";
+        let info = parse_info(line).unwrap();
+        assert_eq!(info.depth, 24);
+        assert_eq!(info.multipv, 2);
+        assert_eq!(info.score, Score::Cp(-35));
+        assert_eq!(info.pv, vec!["e7e5", "g1f3", "b8c6"]);
+    }
+
+    #[test]
+    fn test_parse_info_mate_and_bound() {
+        let info = parse_info("info depth 12 score mate -3 upperbound pv h7h8").unwrap();
+        assert_eq!(info.score, Score::Mate(-3));
+        assert_eq!(info.multipv, 1);
+    }
+
+    #[test]
+    fn test_parse_info_ignores_non_pv_lines() {
+        assert!(parse_info("info depth 5 currmove e2e4 currmovenumber 1").is_none());
+        assert!(parse_info("info string NNUE enabled").is_none());
+        assert!(parse_info("bestmove e2e4").is_none());
+    }
+
+    #[test]
+    fn test_score_display() {
+        assert_eq!(Score::Cp(145).to_string(), "+1.45");
+        assert_eq!(Score::Cp(-30).to_string(), "-0.30");
+        assert_eq!(Score::Mate(4).to_string(), "#4");
+    }
+}
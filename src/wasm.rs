@@ -0,0 +1,34 @@
+//! Browser bindings (`wasm` feature)
+//!
+//! Exposes FEN validation and tanton's search to JavaScript through `wasm-bindgen`. Searches
+//! run synchronously on the calling thread, so a page should call them from a web worker.
+
+use crate::{engine, fen};
+use wasm_bindgen::prelude::*;
+
+/// Best move and evaluation of a position
+#[wasm_bindgen(getter_with_clone)]
+pub struct BestMove {
+    /// Readable move ("E2 to E4")
+    pub best_move: String,
+    /// From the side to move's perspective ("+0.35", "#4")
+    pub evaluation: String,
+}
+
+/// The FEN with castling rights fixed, or an error naming what makes it impossible
+#[wasm_bindgen(js_name = validateFen)]
+pub fn validate_fen(fen: &str) -> Result<String, JsError> {
+    fen::validate_fen(fen).map_err(js_error)
+}
+
+/// Validates `fen` and searches it to `depth`
+#[wasm_bindgen(js_name = analyzePosition)]
+pub fn analyze_position(fen: &str, depth: u16) -> Result<BestMove, JsError> {
+    let fen = fen::validate_fen(fen).map_err(js_error)?;
+    let (best_move, evaluation) = engine::analyze_position(&fen, depth).map_err(js_error)?;
+    Ok(BestMove { best_move, evaluation })
+}
+
+fn js_error(e: anyhow::Error) -> JsError {
+    JsError::new(&format!("{:#}", e))
+}